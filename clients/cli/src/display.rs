@@ -0,0 +1,139 @@
+// Formats offers for the terminal
+// Amounts are shown in base units and times as unix timestamps, as the program stores them
+
+use std::fmt::Write;
+
+use escrow_client::Offer;
+use solana_pubkey::Pubkey;
+
+// One line per offer, under a header
+pub fn format_offer_table(offers: &[(Pubkey, Offer)]) -> String {
+    if offers.is_empty() {
+        return "No offers found\n".to_string();
+    }
+
+    let mut table = format!(
+        "{:<44}  {:>20}  {:<8}  {:>20}  {:<44}  {:>20}  {:<44}\n",
+        "OFFER", "ID", "STATUS", "TOKEN A OFFERED", "TOKEN MINT A", "TOKEN B WANTED", "TOKEN MINT B"
+    );
+    for (address, offer) in offers {
+        // Writing to a String can't fail
+        writeln!(
+            table,
+            "{:<44}  {:>20}  {:<8}  {:>20}  {:<44}  {:>20}  {:<44}",
+            address.to_string(),
+            offer.id,
+            format!("{:?}", offer.status()),
+            offer.token_a_offered_amount,
+            offer.token_mint_a.to_string(),
+            offer.token_b_wanted_amount,
+            offer.token_mint_b.to_string(),
+        )
+        .unwrap();
+    }
+    table
+}
+
+// Every detail of one offer, as label: value lines, leaving out options the offer doesn't use
+pub fn format_offer(address: &Pubkey, offer: &Offer) -> String {
+    let mut lines = vec![
+        ("Offer", address.to_string()),
+        ("Id", offer.id.to_string()),
+        ("Status", format!("{:?}", offer.status())),
+        ("Maker", offer.maker.to_string()),
+        ("Token mint A", offer.token_mint_a.to_string()),
+        ("Token A offered", offer.token_a_offered_amount.to_string()),
+        ("Token mint B", offer.token_mint_b.to_string()),
+        ("Token B wanted", offer.token_b_wanted_amount.to_string()),
+        (
+            "Price",
+            format!(
+                "{} token B for every {} token A",
+                offer.price_numerator, offer.price_denominator
+            ),
+        ),
+        ("Created at", offer.created_at.to_string()),
+        ("Rent payer", offer.rent_payer.to_string()),
+    ];
+
+    if offer.maker_authority != offer.maker {
+        lines.push(("Maker authority", offer.maker_authority.to_string()));
+    }
+    if offer.min_fill_amount > 0 {
+        lines.push(("Minimum fill", offer.min_fill_amount.to_string()));
+    }
+    if let Some(expires_at) = offer.expires_at() {
+        lines.push(("Expires at", expires_at.to_string()));
+    }
+    if let Some(dutch_auction) = offer.dutch_auction() {
+        lines.push((
+            "Dutch auction",
+            format!(
+                "{} falling to {} token B, from {} to {}",
+                dutch_auction.start_price,
+                dutch_auction.end_price,
+                dutch_auction.start_timestamp,
+                dutch_auction.end_timestamp
+            ),
+        ));
+    }
+    if let Some(english_auction) = offer.english_auction() {
+        lines.push((
+            "English auction",
+            format!(
+                "ends at {}, bids rise by at least {}, highest bid {}",
+                english_auction.end_timestamp, english_auction.min_bid_increment, offer.highest_bid
+            ),
+        ));
+    }
+    if let Some(vesting_duration) = offer.vesting_duration() {
+        lines.push(("Vests over", format!("{vesting_duration} seconds")));
+    }
+    if let Some(installment_terms) = offer.installment_terms() {
+        lines.push((
+            "Installments",
+            format!(
+                "up to {}, each due {} seconds after the last",
+                installment_terms.max_installments, installment_terms.interval
+            ),
+        ));
+    }
+    for additional_token_mint_a in offer.additional_token_mints_a() {
+        lines.push(("Also offers", additional_token_mint_a.to_string()));
+    }
+    if let Some(nft_collection) = offer.nft_collection() {
+        lines.push(("NFT collection", nft_collection.to_string()));
+    }
+    if let Some(attestor) = offer.attestor() {
+        lines.push(("Attestor", attestor.to_string()));
+    }
+    if let Some(eth_taker) = offer.eth_taker() {
+        let eth_taker: String = eth_taker.iter().map(|byte| format!("{byte:02x}")).collect();
+        lines.push(("Ethereum taker", format!("0x{eth_taker}")));
+    }
+    if let Some(refund_authority) = offer.refund_authority() {
+        lines.push(("Refund authority", refund_authority.to_string()));
+    }
+    if let Some(arbiter) = offer.arbiter() {
+        lines.push(("Arbiter", arbiter.to_string()));
+    }
+    if offer.hashlock().is_some() {
+        lines.push(("Hashlocked", "yes, taking it needs the preimage".to_string()));
+    }
+    if offer.is_delegated() {
+        lines.push(("Delegated", "yes, the tokens are still in the maker's account".to_string()));
+    }
+    if offer.allowlist_enabled() {
+        lines.push(("Allowlist", "only allowed takers can take it".to_string()));
+    }
+    if let Some(memo) = offer.memo() {
+        lines.push(("Memo", memo.to_string()));
+    }
+
+    // Line the values up after the longest label and its colon
+    let label_width = lines.iter().map(|(label, _)| label.len() + 1).max().unwrap_or(0);
+    lines
+        .into_iter()
+        .map(|(label, value)| format!("{:<label_width$}  {value}\n", format!("{label}:")))
+        .collect()
+}
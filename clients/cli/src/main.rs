@@ -0,0 +1,257 @@
+// A command line tool for the escrow program, built on the escrow-client crate
+// Makes, takes and refunds offers signed with a keypair file, and lists and shows offers fetched over RPC
+// Amounts are in each token's base units, the same as the program stores them
+
+mod display;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use escrow_client::{
+    instructions::{
+        make_offer_with_assigned_id, refund_offer, take_offer, MakeOfferAccounts, MakeOfferArgs,
+        TakeOfferAccounts, TakeOfferArgs,
+    },
+    pda::find_offer_address,
+    EscrowClient, Offer, OfferFilter, OfferStatus,
+};
+use solana_instruction::Instruction;
+use solana_keypair::{read_keypair_file, Keypair};
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+#[derive(Debug, Parser)]
+#[command(name = "escrow-cli", version, about = "Make, take, refund and inspect escrow offers")]
+struct Cli {
+    /// The RPC endpoint to use
+    #[arg(long, short = 'u', global = true, default_value = "http://localhost:8899")]
+    url: String,
+
+    /// The keypair file that signs and pays, defaults to the Solana CLI's ~/.config/solana/id.json
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Offer token a for token b, with the program assigning the offer's id
+    Make {
+        #[arg(long)]
+        token_mint_a: Pubkey,
+        #[arg(long)]
+        token_mint_b: Pubkey,
+        /// The amount of token a to offer
+        #[arg(long)]
+        offered: u64,
+        /// The amount of token b wanted in return
+        #[arg(long)]
+        wanted: u64,
+        /// Keep the offer, with its final status, once it's taken or refunded
+        #[arg(long)]
+        keep_history: bool,
+        /// Pass both mints' allowlist entries, needed while the config's mint allowlist is enabled
+        #[arg(long)]
+        mint_allowlist: bool,
+    },
+    /// Take an offer in full, paying the maker in token b
+    Take {
+        offer: Pubkey,
+        /// The most token b to pay, defaults to what the offer wants, or a Dutch auction's start price
+        #[arg(long)]
+        max_token_b: Option<u64>,
+        /// A note like an invoice number, recorded with the settlement
+        #[arg(long)]
+        memo: Option<String>,
+        /// Who receives token a, defaults to the taker, who pays token b either way
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+    },
+    /// Refund an offer, returning the offered tokens to the maker
+    Refund { offer: Pubkey },
+    /// List open offers, narrowed down by maker or mints
+    List {
+        #[arg(long)]
+        maker: Option<Pubkey>,
+        #[arg(long)]
+        token_mint_a: Option<Pubkey>,
+        #[arg(long)]
+        token_mint_b: Option<Pubkey>,
+    },
+    /// Show every detail of a single offer
+    Show { offer: Pubkey },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let client = EscrowClient::new_with_url(cli.url.clone());
+
+    match cli.command {
+        Command::Make {
+            token_mint_a,
+            token_mint_b,
+            offered,
+            wanted,
+            keep_history,
+            mint_allowlist,
+        } => {
+            let maker = read_keypair(cli.keypair)?;
+            let token_program = get_token_program(&client, &token_mint_a).await?;
+            let token_program_b = get_token_program(&client, &token_mint_b).await?;
+            let accounts = MakeOfferAccounts {
+                maker_authority: maker.pubkey(),
+                maker: maker.pubkey(),
+                payer: maker.pubkey(),
+                token_mint_a,
+                token_mint_b,
+                token_program,
+                token_program_b: (token_program_b != token_program).then_some(token_program_b),
+                mint_allowlist_enabled: mint_allowlist,
+            };
+            let args = MakeOfferArgs {
+                token_a_offered_amount: offered,
+                token_b_wanted_amount: wanted,
+                keep_history,
+                ..MakeOfferArgs::default()
+            };
+
+            // The offer's address comes from the id, so the make fails if another offer takes it first
+            let offer_id = client.get_next_offer_id(&maker.pubkey()).await?;
+            let instruction = make_offer_with_assigned_id(&accounts, &args, offer_id);
+            send_instruction(&client, &maker, instruction).await?;
+            println!(
+                "Made offer {} with id {offer_id}",
+                find_offer_address(&maker.pubkey(), offer_id).0
+            );
+        }
+        Command::Take {
+            offer: offer_address,
+            max_token_b,
+            memo,
+            recipient,
+        } => {
+            let taker = read_keypair(cli.keypair)?;
+            let offer = client.get_offer(&offer_address).await?;
+            require_single_mint_offer(&offer)?;
+            let token_program = get_token_program(&client, &offer.token_mint_a).await?;
+            let token_program_b = get_token_program(&client, &offer.token_mint_b).await?;
+            let accounts = TakeOfferAccounts {
+                taker: taker.pubkey(),
+                offer: offer_address,
+                token_program,
+                token_program_b: (token_program_b != token_program).then_some(token_program_b),
+                referrer_token_account: None,
+                token_mint_a_metadata: None,
+                membership_token_account: None,
+                recipient,
+            };
+            // Dutch auction prices only fall, so the start price covers whatever the offer wants by the time it lands
+            let expected_token_b_amount = max_token_b.unwrap_or_else(|| {
+                offer
+                    .dutch_auction()
+                    .map_or(offer.token_b_wanted_amount, |dutch_auction| dutch_auction.start_price)
+            });
+            let args = TakeOfferArgs {
+                expected_token_a_amount: offer.token_a_offered_amount,
+                expected_token_b_amount,
+                preimage: None,
+                memo,
+            };
+
+            send_instruction(&client, &taker, take_offer(&accounts, &offer, &args)).await?;
+            println!("Took offer {offer_address}");
+        }
+        Command::Refund {
+            offer: offer_address,
+        } => {
+            let maker_authority = read_keypair(cli.keypair)?;
+            let offer = client.get_offer(&offer_address).await?;
+            require_single_mint_offer(&offer)?;
+            let token_program = get_token_program(&client, &offer.token_mint_a).await?;
+
+            let instruction =
+                refund_offer(&maker_authority.pubkey(), &offer_address, &offer, &token_program);
+            send_instruction(&client, &maker_authority, instruction).await?;
+            println!("Refunded offer {offer_address}");
+        }
+        Command::List {
+            maker,
+            token_mint_a,
+            token_mint_b,
+        } => {
+            let mut offers = client
+                .get_offers(&OfferFilter {
+                    maker,
+                    token_mint_a,
+                    token_mint_b,
+                    // Offers that keep their history stay around once filled or refunded
+                    status: Some(OfferStatus::Open),
+                    tags: None,
+                })
+                .await?;
+            offers.sort_by_key(|(_, offer)| (offer.maker, offer.id));
+            print!("{}", display::format_offer_table(&offers));
+        }
+        Command::Show {
+            offer: offer_address,
+        } => {
+            let offer = client.get_offer(&offer_address).await?;
+            print!("{}", display::format_offer(&offer_address, &offer));
+        }
+    }
+
+    Ok(())
+}
+
+// Read the keypair file, or the Solana CLI's default keypair if none was given
+fn read_keypair(path: Option<PathBuf>) -> Result<Keypair> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let home = std::env::var("HOME").context("HOME isn't set, pass --keypair")?;
+            PathBuf::from(home).join(".config/solana/id.json")
+        }
+    };
+    read_keypair_file(&path)
+        .map_err(|error| anyhow!("Couldn't read keypair {}: {error}", path.display()))
+}
+
+// The classic token program or the token extensions program, whichever owns the mint
+async fn get_token_program(client: &EscrowClient, mint: &Pubkey) -> Result<Pubkey> {
+    let mint_account = client
+        .rpc_client()
+        .get_account(mint)
+        .await
+        .with_context(|| format!("Couldn't fetch mint {mint}"))?;
+    Ok(mint_account.owner)
+}
+
+// Bundles need an extra vault and token account for each additional mint, which the CLI doesn't pass yet
+fn require_single_mint_offer(offer: &Offer) -> Result<()> {
+    if !offer.additional_token_mints_a().is_empty() {
+        bail!("Offers with more than one offered mint can't be taken or refunded with the CLI yet");
+    }
+    Ok(())
+}
+
+async fn send_instruction(client: &EscrowClient, signer: &Keypair, instruction: Instruction) -> Result<()> {
+    let rpc_client = client.rpc_client();
+    let blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer.pubkey()),
+        &[signer],
+        blockhash,
+    );
+    let signature = rpc_client.send_and_confirm_transaction(&transaction).await?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
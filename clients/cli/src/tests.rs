@@ -0,0 +1,107 @@
+use bytemuck::Zeroable;
+use clap::{CommandFactory, Parser};
+use escrow_client::Offer;
+use solana_pubkey::Pubkey;
+
+use crate::{
+    display::{format_offer, format_offer_table},
+    Cli, Command,
+};
+
+fn offer() -> Offer {
+    let mut offer = Offer::zeroed();
+    offer.id = 7;
+    offer.maker = Pubkey::new_unique();
+    offer.maker_authority = offer.maker;
+    offer.rent_payer = offer.maker;
+    offer.token_mint_a = Pubkey::new_unique();
+    offer.token_mint_b = Pubkey::new_unique();
+    offer.token_a_offered_amount = 3_000;
+    offer.token_b_wanted_amount = 2_000;
+    offer.price_numerator = 2_000;
+    offer.price_denominator = 3_000;
+    offer
+}
+
+#[test]
+fn test_cli_arguments_are_well_formed() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_take_reads_the_offer_and_options() {
+    let offer_address = Pubkey::new_unique();
+    let cli = Cli::try_parse_from([
+        "escrow-cli",
+        "take",
+        &offer_address.to_string(),
+        "--max-token-b",
+        "2500",
+        "--keypair",
+        "taker.json",
+    ])
+    .unwrap();
+
+    assert_eq!(cli.keypair.unwrap().to_str(), Some("taker.json"));
+    match cli.command {
+        Command::Take {
+            offer,
+            max_token_b,
+            memo,
+            recipient,
+        } => {
+            assert_eq!(offer, offer_address);
+            assert_eq!(max_token_b, Some(2_500));
+            assert_eq!(memo, None);
+            assert_eq!(recipient, None);
+        }
+        command => panic!("Expected take, got {command:?}"),
+    }
+}
+
+#[test]
+fn test_make_refuses_a_mint_that_isnt_a_pubkey() {
+    let result = Cli::try_parse_from([
+        "escrow-cli",
+        "make",
+        "--token-mint-a",
+        "not-a-mint",
+        "--token-mint-b",
+        &Pubkey::new_unique().to_string(),
+        "--offered",
+        "3000",
+        "--wanted",
+        "2000",
+    ]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_show_lists_only_the_options_an_offer_uses() {
+    let offer_address = Pubkey::new_unique();
+    let offer = offer();
+    let shown = format_offer(&offer_address, &offer);
+
+    assert!(shown.contains(&format!("Offer:            {offer_address}\n")));
+    assert!(shown.contains("Status:           Open\n"));
+    assert!(shown.contains("Token A offered:  3000\n"));
+    assert!(shown.contains("Price:            2000 token B for every 3000 token A\n"));
+    assert!(!shown.contains("Expires at"));
+    assert!(!shown.contains("Dutch auction"));
+    assert!(!shown.contains("Maker authority"));
+}
+
+#[test]
+fn test_list_prints_a_row_for_each_offer() {
+    assert_eq!(format_offer_table(&[]), "No offers found\n");
+
+    let offers = [(Pubkey::new_unique(), offer()), (Pubkey::new_unique(), offer())];
+    let table = format_offer_table(&offers);
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].starts_with("OFFER"));
+    for ((address, _), row) in offers.iter().zip(&rows[1..]) {
+        assert!(row.starts_with(&address.to_string()));
+        assert!(row.contains("Open"));
+    }
+}
@@ -0,0 +1,321 @@
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_message::{AddressLookupTableAccount, Message};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+    filter::{Memcmp, RpcFilterType},
+};
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+use solana_transaction_status_client_types::{
+    UiInnerInstructions, UiInstruction, UiParsedInstruction,
+};
+
+use crate::{
+    account_discriminator,
+    error::{Error, Result},
+    events::EscrowEvent,
+    instructions::{
+        make_offer, take_offer, MakeOfferAccounts, MakeOfferArgs, TakeOfferAccounts, TakeOfferArgs,
+    },
+    logs::{parse_log_events, parse_program_error},
+    lookup_table::decode_lookup_table,
+    offer::{offsets, Offer, OfferStatus, OFFER_TAGS_LENGTH},
+    pda::find_maker_counter_address,
+    program_error::ProgramError,
+    PROGRAM_ID,
+};
+
+// Where next_id sits in a MakerCounter account, after the discriminator and the maker
+const MAKER_COUNTER_NEXT_ID_OFFSET: usize = 8 + 32;
+
+// Narrows get_offers down to the offers a client cares about, every field that is set has to match
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OfferFilter {
+    pub maker: Option<Pubkey>,
+    pub token_mint_a: Option<Pubkey>,
+    pub token_mint_b: Option<Pubkey>,
+    // Offers only stay around once filled or refunded if they keep their history, so this is mostly useful for Open
+    pub status: Option<OfferStatus>,
+    // Every non-zero tag has to match the offer's tag in the same position, zero tags match anything
+    pub tags: Option<[u8; OFFER_TAGS_LENGTH]>,
+}
+
+impl OfferFilter {
+    // Every open offer the maker has for token a in exchange for token b
+    pub fn open_offers_by_maker_for_pair(
+        maker: Pubkey,
+        token_mint_a: Pubkey,
+        token_mint_b: Pubkey,
+    ) -> Self {
+        Self {
+            maker: Some(maker),
+            token_mint_a: Some(token_mint_a),
+            token_mint_b: Some(token_mint_b),
+            status: Some(OfferStatus::Open),
+            tags: None,
+        }
+    }
+
+    // The getProgramAccounts filters that match offers passing this filter, the discriminator first,
+    // then a memcmp for every field that is set
+    pub fn rpc_filters(&self) -> Vec<RpcFilterType> {
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            &Offer::discriminator(),
+        ))];
+        let field_filters = [
+            (offsets::MAKER, self.maker),
+            (offsets::TOKEN_MINT_A, self.token_mint_a),
+            (offsets::TOKEN_MINT_B, self.token_mint_b),
+        ];
+        for (offset, value) in field_filters {
+            if let Some(value) = value {
+                filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    offset,
+                    value.as_ref(),
+                )));
+            }
+        }
+        if let Some(status) = self.status {
+            filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                offsets::STATUS,
+                &[status as u8],
+            )));
+        }
+        if let Some(tags) = self.tags {
+            for (index, tag) in tags.iter().enumerate() {
+                if *tag != 0 {
+                    filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        offsets::TAGS + index,
+                        &[*tag],
+                    )));
+                }
+            }
+        }
+        filters
+    }
+}
+
+// What a transaction would do if it was sent now, from EscrowClient::simulate
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    // Why the transaction would fail, None if it would succeed
+    pub error: Option<TransactionError>,
+    // The escrow error it would fail with, None if it would succeed or fail somewhere other than the escrow program
+    pub program_error: Option<ProgramError>,
+    // The events the escrow program would emit, like the OfferTaken a settlement preview shows
+    pub events: Vec<EscrowEvent>,
+    // The compute units the instructions used, not counting any ComputeBudget instructions sent alongside them
+    pub compute_units: u64,
+    pub logs: Vec<String>,
+}
+
+// The events in the simulated transaction's inner instructions to the escrow program, where emit_cpi! stores them
+// account_keys are the transaction's accounts, which compiled inner instructions refer to by index
+pub(crate) fn inner_instruction_events(
+    inner_instructions: &[UiInnerInstructions],
+    account_keys: &[Pubkey],
+) -> Vec<EscrowEvent> {
+    inner_instructions
+        .iter()
+        .flat_map(|inner_instructions| &inner_instructions.instructions)
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Compiled(instruction) => Some((
+                account_keys
+                    .get(usize::from(instruction.program_id_index))
+                    .copied(),
+                &instruction.data,
+            )),
+            // Nodes decode instructions to programs they don't know this far, with the program id spelled out
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(instruction)) => {
+                Some((instruction.program_id.parse().ok(), &instruction.data))
+            }
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => None,
+        })
+        .filter(|(program_id, _)| *program_id == Some(PROGRAM_ID))
+        .filter_map(|(_, data)| bs58::decode(data).into_vec().ok())
+        .filter_map(|data| EscrowEvent::from_cpi_instruction_data(&data))
+        .collect()
+}
+
+// Fetches offers from an RPC node
+pub struct EscrowClient {
+    rpc_client: RpcClient,
+}
+
+impl EscrowClient {
+    pub fn new(rpc_client: RpcClient) -> Self {
+        Self { rpc_client }
+    }
+
+    pub fn new_with_url(url: String) -> Self {
+        Self::new(RpcClient::new(url))
+    }
+
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
+    // Fetch and read a single offer
+    pub async fn get_offer(&self, offer_address: &Pubkey) -> Result<Offer> {
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(offer_address, self.rpc_client.commitment())
+            .await?
+            .value
+            .ok_or(Error::AccountNotFound(*offer_address))?;
+        if account.owner != PROGRAM_ID {
+            return Err(Error::NotAnOffer);
+        }
+        Offer::from_account_data(&account.data)
+    }
+
+    // Fetch the id make_offer will assign to the maker's next offer, for make_offer_with_assigned_id
+    // Makers who haven't made an offer yet don't have a counter, and start from 0
+    pub async fn get_next_offer_id(&self, maker: &Pubkey) -> Result<u64> {
+        let maker_counter_address = find_maker_counter_address(maker).0;
+        let Some(account) = self
+            .rpc_client
+            .get_account_with_commitment(&maker_counter_address, self.rpc_client.commitment())
+            .await?
+            .value
+        else {
+            return Ok(0);
+        };
+        if account.owner != PROGRAM_ID
+            || !account.data.starts_with(&account_discriminator("MakerCounter"))
+        {
+            return Err(Error::NotAMakerCounter);
+        }
+        let next_id_bytes = account
+            .data
+            .get(MAKER_COUNTER_NEXT_ID_OFFSET..MAKER_COUNTER_NEXT_ID_OFFSET + 8)
+            .ok_or(Error::NotAMakerCounter)?;
+        Ok(u64::from_le_bytes(next_id_bytes.try_into().unwrap()))
+    }
+
+    // Fetch a lookup table, like one made with create_escrow_lookup_table, for build_v0_message
+    pub async fn get_lookup_table(&self, lookup_table: &Pubkey) -> Result<AddressLookupTableAccount> {
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(lookup_table, self.rpc_client.commitment())
+            .await?
+            .value
+            .ok_or(Error::AccountNotFound(*lookup_table))?;
+        decode_lookup_table(lookup_table, &account.data)
+    }
+
+    // Simulate instructions paid for by payer, without signing them, to preview their events and estimate their compute units
+    // Add a margin to compute_units, plus the ComputeBudget instructions' own, before using it as a compute unit limit
+    pub async fn simulate(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+    ) -> Result<Simulation> {
+        let message = Message::new_with_blockhash(instructions, Some(payer), &Hash::default());
+        let account_keys = message.account_keys.clone();
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.rpc_client.commitment()),
+            inner_instructions: true,
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let result = self
+            .rpc_client
+            .simulate_transaction_with_config(&Transaction::new_unsigned(message), config)
+            .await?
+            .value;
+
+        let logs = result.logs.unwrap_or_default();
+        // Nodes that don't return inner instructions leave only the logs, which have events if the program logs them
+        let events = match result.inner_instructions {
+            Some(inner_instructions) => {
+                inner_instruction_events(&inner_instructions, &account_keys)
+            }
+            None => parse_log_events(&logs),
+        };
+        Ok(Simulation {
+            program_error: result.err.as_ref().and_then(|_| parse_program_error(&logs)),
+            error: result.err,
+            events,
+            compute_units: result.units_consumed.unwrap_or_default(),
+            logs,
+        })
+    }
+
+    // Simulate taking an offer the caller has already fetched, see take_offer
+    pub async fn simulate_take(
+        &self,
+        accounts: &TakeOfferAccounts,
+        offer: &Offer,
+        args: &TakeOfferArgs,
+    ) -> Result<Simulation> {
+        self.simulate(&[take_offer(accounts, offer, args)], &accounts.taker)
+            .await
+    }
+
+    // Simulate making an offer, see make_offer
+    pub async fn simulate_make(
+        &self,
+        accounts: &MakeOfferAccounts,
+        args: &MakeOfferArgs,
+    ) -> Result<Simulation> {
+        self.simulate(&[make_offer(accounts, args)], &accounts.payer)
+            .await
+    }
+
+    // Fetch every offer matching the filter, filtering on the RPC node with memcmp so only matching offers are sent
+    // Offers that still need migrate_offer can't be read, so they are left out
+    pub async fn get_offers(&self, filter: &OfferFilter) -> Result<Vec<(Pubkey, Offer)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filter.rpc_filters()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.rpc_client.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(&PROGRAM_ID, config)
+            .await?;
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| {
+                Offer::from_account_data(&account.data)
+                    .ok()
+                    .map(|offer| (address, offer))
+            })
+            .collect())
+    }
+
+    // Fetch every open offer made by a maker
+    pub async fn get_offers_by_maker(&self, maker: &Pubkey) -> Result<Vec<(Pubkey, Offer)>> {
+        self.get_offers(&OfferFilter {
+            maker: Some(*maker),
+            ..OfferFilter::default()
+        })
+        .await
+    }
+
+    // Fetch every open offer for a pair, offering token_mint_a for token_mint_b
+    pub async fn get_offers_for_pair(
+        &self,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Offer)>> {
+        self.get_offers(&OfferFilter {
+            token_mint_a: Some(*token_mint_a),
+            token_mint_b: Some(*token_mint_b),
+            ..OfferFilter::default()
+        })
+        .await
+    }
+}
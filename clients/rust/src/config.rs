@@ -0,0 +1,82 @@
+use borsh::BorshDeserialize;
+use solana_pubkey::Pubkey;
+
+use crate::{
+    account_discriminator,
+    error::{Error, Result},
+};
+
+// The protocol-wide settings, laid out exactly like the program's Config
+// Unlike offers, the config is borsh serialized, so the account data is the 8 byte discriminator then the borsh bytes,
+// followed by unused space the account keeps for fee tiers it doesn't have yet
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub authority: Pubkey,
+    // In basis points of the token a a taker receives
+    pub fee_bps: u16,
+    pub fee_authority: Pubkey,
+    // In basis points of the protocol fee
+    pub referral_share_bps: u16,
+    pub paused: bool,
+    pub mint_policy: MintPolicy,
+    pub royalties_required: bool,
+    // In token a's base units
+    pub min_offer_amount: u64,
+    pub max_offer_amount: u64,
+    pub mint_allowlist_enabled: bool,
+    pub denied_mint_count: u32,
+    // Sorted by the volume needed
+    pub fee_tiers: Vec<FeeTier>,
+    pub membership_discount: Option<MembershipDiscount>,
+    pub pauser: Pubkey,
+    pub fee_manager: Pubkey,
+    pub allowlist_manager: Pubkey,
+    // In basis points of a match's price improvement
+    pub matcher_share_bps: u16,
+    // How many slots an offer must have existed for before it can be taken
+    pub min_offer_age_slots: u64,
+    // The most open offers a single maker can have at once
+    pub max_open_offers_per_maker: u16,
+    // The mint fee tier volumes are measured in
+    pub fee_tier_mint: Pubkey,
+    pub bump: u8,
+}
+
+// A protocol fee for makers whose settled token b volume has reached min_volume
+#[derive(BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_volume: u128,
+    pub fee_bps: u16,
+}
+
+// Takers holding at least min_balance of mint pay discount_bps less of the protocol fee
+#[derive(BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MembershipDiscount {
+    pub mint: Pubkey,
+    pub min_balance: u64,
+    pub discount_bps: u16,
+}
+
+// Which mint configurations offers may use
+#[derive(BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MintPolicy {
+    pub allow_freeze_authority: bool,
+    pub allow_mint_authority: bool,
+    pub allow_non_transferable: bool,
+    pub allow_permanent_delegate: bool,
+}
+
+impl Config {
+    // The discriminator at the start of the config account
+    pub fn discriminator() -> [u8; 8] {
+        account_discriminator("Config")
+    }
+
+    // Read the config from its account data, ignoring the unused space after it like Anchor does
+    pub fn from_account_data(data: &[u8]) -> Result<Config> {
+        let Some(mut config_data) = data.strip_prefix(&Config::discriminator()) else {
+            return Err(Error::NotAConfig);
+        };
+        Config::deserialize(&mut config_data).map_err(|_| Error::NotAConfig)
+    }
+}
@@ -0,0 +1,37 @@
+// Reads escrow accounts from their raw data, for indexers, WASM frontends and embedded services
+// Only needs the account bytes, so it works with the rpc feature turned off
+
+use crate::{
+    config::Config,
+    error::{Error, Result},
+    offer::Offer,
+};
+
+// An escrow account this client can read, told apart by its discriminator
+// Both are boxed since they're several hundred bytes each
+#[derive(Clone, Debug)]
+pub enum EscrowAccount {
+    Offer(Box<Offer>),
+    Config(Box<Config>),
+}
+
+// Read an offer from its account data, see Offer::from_account_data
+pub fn decode_offer(data: &[u8]) -> Result<Offer> {
+    Offer::from_account_data(data)
+}
+
+// Read the config from its account data, see Config::from_account_data
+pub fn decode_config(data: &[u8]) -> Result<Config> {
+    Config::from_account_data(data)
+}
+
+// Read any escrow account this client knows about, for indexers that see every account the program owns
+pub fn decode_account(data: &[u8]) -> Result<EscrowAccount> {
+    if data.starts_with(&Offer::discriminator()) {
+        decode_offer(data).map(|offer| EscrowAccount::Offer(Box::new(offer)))
+    } else if data.starts_with(&Config::discriminator()) {
+        decode_config(data).map(|config| EscrowAccount::Config(Box::new(config)))
+    } else {
+        Err(Error::UnknownAccount)
+    }
+}
@@ -0,0 +1,40 @@
+use solana_message::CompileError;
+use solana_pubkey::Pubkey;
+#[cfg(feature = "rpc")]
+use solana_rpc_client_api::client_error::Error as ClientError;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[cfg(feature = "rpc")]
+    #[error("RPC request failed: {0}")]
+    // Boxed because RPC errors are much larger than every other variant
+    Rpc(Box<ClientError>),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account data is not an offer")]
+    NotAnOffer,
+    #[error("Offer was written with layout version {0}, migrate it with migrate_offer to read it")]
+    OutdatedOffer(u8),
+    #[error("Offer account has an unknown layout")]
+    UnknownOfferVersion,
+    #[error("Account data is not a maker counter")]
+    NotAMakerCounter,
+    #[error("Account data is not a config")]
+    NotAConfig,
+    #[error("Account data is not an escrow account this client can read")]
+    UnknownAccount,
+    #[error("Account data is not an address lookup table")]
+    NotALookupTable,
+    #[error("Couldn't compile the transaction message: {0}")]
+    CompileMessage(#[from] CompileError),
+}
+
+#[cfg(feature = "rpc")]
+impl From<ClientError> for Error {
+    fn from(error: ClientError) -> Self {
+        Error::Rpc(Box::new(error))
+    }
+}
@@ -0,0 +1,85 @@
+use borsh::BorshDeserialize;
+use solana_pubkey::Pubkey;
+
+use crate::{event_discriminator, offer::OFFER_TAGS_LENGTH};
+
+// Anchor's emit_cpi! prefixes the self-CPI's instruction data with this tag, then the event's discriminator and data
+pub const EVENT_IX_TAG_LE: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+// The events the program emits over an offer's lifecycle, laid out exactly like the program's
+// See the program's events.rs for what each field means
+
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OfferMade {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub created_at: i64,
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+}
+
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OfferTaken {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub maker_token_account_b_created: bool,
+    pub created_at: i64,
+    pub filled_at: i64,
+    pub recipient: Pubkey,
+}
+
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OfferRefunded {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    pub created_at: i64,
+    pub refunded_at: i64,
+}
+
+// Any event the program emits, told apart by its discriminator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EscrowEvent {
+    OfferMade(OfferMade),
+    OfferTaken(OfferTaken),
+    OfferRefunded(OfferRefunded),
+}
+
+impl EscrowEvent {
+    // Read an event from its data, the discriminator followed by the borsh serialized event
+    // Returns None for data that isn't one of the program's events
+    pub fn from_event_data(data: &[u8]) -> Option<EscrowEvent> {
+        let (discriminator, mut event_data) = data.split_first_chunk::<8>()?;
+        if *discriminator == event_discriminator("OfferMade") {
+            OfferMade::deserialize(&mut event_data)
+                .ok()
+                .map(EscrowEvent::OfferMade)
+        } else if *discriminator == event_discriminator("OfferTaken") {
+            OfferTaken::deserialize(&mut event_data)
+                .ok()
+                .map(EscrowEvent::OfferTaken)
+        } else if *discriminator == event_discriminator("OfferRefunded") {
+            OfferRefunded::deserialize(&mut event_data)
+                .ok()
+                .map(EscrowEvent::OfferRefunded)
+        } else {
+            None
+        }
+    }
+
+    // Read an event from the data of one of the program's inner instructions, where emit_cpi! stores it
+    // Pass only inner instructions to the escrow program, anything else with the event tag could be forged
+    pub fn from_cpi_instruction_data(instruction_data: &[u8]) -> Option<EscrowEvent> {
+        EscrowEvent::from_event_data(instruction_data.strip_prefix(&EVENT_IX_TAG_LE)?)
+    }
+}
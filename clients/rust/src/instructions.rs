@@ -0,0 +1,1183 @@
+use borsh::BorshSerialize;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::{ed25519_program, secp256k1_program, system_program, sysvar};
+use spl_associated_token_account_client::{
+    address::get_associated_token_address_with_program_id,
+    program::ID as ASSOCIATED_TOKEN_PROGRAM_ID,
+};
+
+use crate::{
+    instruction_discriminator,
+    offer::{
+        DutchAuction, EnglishAuction, Offer, ETH_ADDRESS_LENGTH, HASH_BYTES, OFFER_TAGS_LENGTH,
+    },
+    pda::{
+        find_allowlist_address, find_config_address, find_event_authority_address,
+        find_fee_vault_address, find_maker_counter_address, find_maker_index_address,
+        find_maker_stats_address, find_mint_allowlist_entry_address, find_mint_deny_entry_address,
+        find_offer_address, find_registry_address, find_route_authority_address,
+        find_route_token_account_address, find_taker_authority_address, find_vault_address,
+        find_vault_authority_address,
+    },
+    MEMO_PROGRAM_ID, PROGRAM_ID,
+};
+
+// Passed as the id to make_offer to have the program assign the maker's next offer id, see make_offer_with_assigned_id
+pub const AUTO_ASSIGN_OFFER_ID: u64 = u64::MAX;
+
+// The arguments to make_offer, in the order the program reads them
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct MakeOfferArgs {
+    pub id: u64,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub dutch_auction: Option<DutchAuction>,
+    pub english_auction: Option<EnglishAuction>,
+    pub additional_token_a_offered_amounts: Vec<u64>,
+    pub memo: Option<String>,
+    pub min_fill_amount: u64,
+    pub hashlock: Option<[u8; HASH_BYTES]>,
+    pub vesting_duration: Option<i64>,
+    pub arbiter: Option<Pubkey>,
+    // Keep the offer, with its final status, once it's taken or refunded, until the maker calls claim_rent
+    pub keep_history: bool,
+    // Categories for frontends to filter listings on, see OfferFilter::tags, all zero to leave the offer untagged
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+}
+
+// The arguments to make_offer_delegated, in the order the program reads them
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct MakeOfferDelegatedArgs {
+    pub id: u64,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    // See MakeOfferArgs::tags
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+}
+
+// The arguments to make_offer_with_existing_vault, in the order the program reads them
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct MakeOfferWithExistingVaultArgs {
+    pub id: u64,
+    // The least the vault must already hold, the offer is for everything it holds
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    // See MakeOfferArgs::tags
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+}
+
+// The arguments to replace_offer, the new offer's terms in the order the program reads them
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct ReplaceOfferArgs {
+    pub new_id: u64,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub expires_at: Option<i64>,
+}
+
+// The arguments to take_offer, the expected amounts are the terms the taker agreed to
+// The take fails if the offer has changed since the taker saw it
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct TakeOfferArgs {
+    pub expected_token_a_amount: u64,
+    pub expected_token_b_amount: u64,
+    pub preimage: Option<Vec<u8>>,
+    // A note like an invoice number, which the memo program records with the settlement
+    pub memo: Option<String>,
+}
+
+// The arguments to take_offer_with_token_accounts, take_offer's expected amounts without a preimage or memo
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct TakeOfferWithTokenAccountsArgs {
+    pub expected_token_a_amount: u64,
+    pub expected_token_b_amount: u64,
+}
+
+// The arguments to take_offer_with_authorization, the terms the taker signed
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct TakeOfferWithAuthorizationArgs {
+    pub expected_token_a_amount: u64,
+    pub expected_token_b_amount: u64,
+    // Unix timestamp after which a relayer can no longer submit the authorization
+    pub authorization_expires_at: i64,
+}
+
+// The accounts that change from one offer to the next, every other account is derived from these
+pub struct MakeOfferAccounts {
+    // Signs for the maker: either the maker's own wallet, or a delegate the maker approved on their token a account
+    pub maker_authority: Pubkey,
+    pub maker: Pubkey,
+    // Pays the rent for the offer, and gets it back when the offer is closed
+    pub payer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer instruction, the offer's address comes from the maker and the id in args
+// Bundle offers also need [mint, maker token account, vault] appended for each additional mint
+pub fn make_offer(accounts: &MakeOfferAccounts, args: &MakeOfferArgs) -> Instruction {
+    build_make_offer(accounts, args, args.id)
+}
+
+// Build a make_offer instruction that has the program assign the maker's next offer id from their counter
+// Get next_offer_id from EscrowClient::get_next_offer_id, the offer's address comes from it,
+// so the transaction fails if another offer takes that id first
+pub fn make_offer_with_assigned_id(
+    accounts: &MakeOfferAccounts,
+    args: &MakeOfferArgs,
+    next_offer_id: u64,
+) -> Instruction {
+    let args = MakeOfferArgs {
+        id: AUTO_ASSIGN_OFFER_ID,
+        ..args.clone()
+    };
+    build_make_offer(accounts, &args, next_offer_id)
+}
+
+fn build_make_offer(accounts: &MakeOfferAccounts, args: &MakeOfferArgs, offer_id: u64) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, offer_id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+    let maker_token_account_a = get_associated_token_address_with_program_id(
+        &accounts.maker,
+        &accounts.token_mint_a,
+        &accounts.token_program,
+    );
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(accounts.maker_authority, true),
+        AccountMeta::new_readonly(accounts.maker, false),
+        AccountMeta::new(accounts.payer, true),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(maker_token_account_a, false),
+        AccountMeta::new(find_maker_counter_address(&accounts.maker).0, false),
+        AccountMeta::new(offer, false),
+        AccountMeta::new_readonly(find_vault_authority_address(&offer).0, false),
+        AccountMeta::new(
+            find_vault_address(&offer, &accounts.token_mint_a, &accounts.token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&accounts.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&accounts.maker).0, false),
+        // No token a metadata, which is only needed for NFT offers
+        optional_account_meta(None, false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_a), false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_b), false),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("make_offer", args, account_metas)
+}
+
+// The accounts for a delegated offer, which the maker signs and pays for themselves
+pub struct MakeOfferDelegatedAccounts {
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer_delegated instruction, which approves the offer's vault authority to spend from the maker's token a account
+// rather than moving the tokens to a vault
+pub fn make_offer_delegated(
+    accounts: &MakeOfferDelegatedAccounts,
+    args: &MakeOfferDelegatedArgs,
+) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, args.id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.maker, true),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &accounts.maker,
+                &accounts.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(find_maker_counter_address(&accounts.maker).0, false),
+        AccountMeta::new(offer, false),
+        AccountMeta::new_readonly(find_vault_authority_address(&offer).0, false),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&accounts.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&accounts.maker).0, false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_a), false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_b), false),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("make_offer_delegated", args, account_metas)
+}
+
+// The accounts for an offer whose vault was funded before the offer was made, which the maker signs and pays for
+pub struct MakeOfferWithExistingVaultAccounts {
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer_with_existing_vault instruction, for an offer whose vault already holds the offered tokens
+// Fund find_vault_address(&find_offer_address(&maker, id).0, ...) first, in an earlier instruction or CPI
+pub fn make_offer_with_existing_vault(
+    accounts: &MakeOfferWithExistingVaultAccounts,
+    args: &MakeOfferWithExistingVaultArgs,
+) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, args.id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.maker, true),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(find_maker_counter_address(&accounts.maker).0, false),
+        AccountMeta::new(offer, false),
+        AccountMeta::new_readonly(find_vault_authority_address(&offer).0, false),
+        AccountMeta::new_readonly(
+            find_vault_address(&offer, &accounts.token_mint_a, &accounts.token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&accounts.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&accounts.maker).0, false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_a), false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_b), false),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("make_offer_with_existing_vault", args, account_metas)
+}
+
+// The accounts that change from one take to the next, the rest are read from the offer or derived
+pub struct TakeOfferAccounts {
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // The token a account of whoever referred the taker, if anyone, who gets a share of the protocol fee
+    pub referrer_token_account: Option<Pubkey>,
+    // The NFT's Metaplex metadata, to pay its creators' royalties when taking an NFT offer
+    pub token_mint_a_metadata: Option<Pubkey>,
+    // The taker's account for the config's membership token, if they hold it, for the membership fee discount
+    pub membership_token_account: Option<Pubkey>,
+    // Who receives token a, if the taker is taking the offer on behalf of someone else
+    // The recipient needs an associated token account for token a already, the taker still pays token b
+    // The taker's own token a account isn't passed, so they aren't charged rent to create it
+    pub recipient: Option<Pubkey>,
+}
+
+// Build a take_offer instruction for an offer the caller has already fetched
+// The memo program is only passed when args has a memo
+// Bundle offers also need [mint, vault, taker token account] appended for each additional mint
+// Paying royalties also needs each creator with a share's token b account appended after those, in metadata order
+// Attested offers also need verify_attestation earlier in the same transaction
+// Offers with an Ethereum taker also need verify_eth_taker earlier in the same transaction
+pub fn take_offer(accounts: &TakeOfferAccounts, offer: &Offer, args: &TakeOfferArgs) -> Instruction {
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+    let token_account = |owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey| {
+        get_associated_token_address_with_program_id(owner, mint, token_program)
+    };
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(&accounts.offer).0);
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(offer.maker, false),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new_readonly(offer.token_mint_b, false),
+        // Token a goes to the recipient's account instead when there is one
+        optional_account_meta(
+            accounts
+                .recipient
+                .is_none()
+                .then(|| token_account(&accounts.taker, &offer.token_mint_a, &accounts.token_program)),
+            true,
+        ),
+        AccountMeta::new(token_account(&accounts.taker, &offer.token_mint_b, &token_program_b), false),
+        AccountMeta::new(token_account(&offer.maker, &offer.token_mint_b, &token_program_b), false),
+        AccountMeta::new(accounts.offer, false),
+        AccountMeta::new_readonly(vault_authority, false),
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        AccountMeta::new(
+            token_account(
+                &offer.token_a_holder(&vault_authority),
+                &offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        AccountMeta::new(find_fee_vault_address(&offer.token_mint_a).0, false),
+        optional_account_meta(allowlist, true),
+        optional_account_meta(accounts.referrer_token_account, true),
+        optional_account_meta(args.memo.is_some().then_some(MEMO_PROGRAM_ID), false),
+        optional_account_meta(accounts.token_mint_a_metadata, false),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_b).0, false),
+        optional_account_meta(accounts.membership_token_account, false),
+        optional_account_meta(accounts.token_program_b, false),
+        // take_offer reads the attestor's or Ethereum taker's signature from the transaction's earlier instructions
+        optional_account_meta(
+            (offer.attestor().is_some() || offer.eth_taker().is_some()).then_some(sysvar::instructions::ID),
+            false,
+        ),
+        optional_account_meta(accounts.recipient, false),
+        optional_account_meta(
+            accounts.recipient.map(|recipient| {
+                token_account(&recipient, &offer.token_mint_a, &accounts.token_program)
+            }),
+            true,
+        ),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("take_offer", args, account_metas)
+}
+
+// The ed25519 program's instruction data is a signature count and a padding byte, then the offsets of the signature,
+// public key and message, each with the index of the instruction holding it, u16::MAX for this one
+const ED25519_OFFSETS_START: u16 = 2;
+const ED25519_OFFSETS_SIZE: u16 = 14;
+
+// The message an attestor signs once whatever the offer depends on has happened, vouching for this taker:
+// the offer's address and the slot it was made in, the taker's address, then the offer's token mints and amounts,
+// the slot and amounts little endian
+// The slot and mints tell the offer apart from a later offer made at the same address, and the amounts stop the
+// attestation carrying over to terms the maker changes afterwards
+pub fn attestation_message(offer_address: &Pubkey, offer: &Offer, taker: &Pubkey) -> [u8; 152] {
+    let mut message = [0; 152];
+    message[..32].copy_from_slice(offer_address.as_ref());
+    message[32..40].copy_from_slice(&offer.created_slot.to_le_bytes());
+    message[40..72].copy_from_slice(taker.as_ref());
+    message[72..104].copy_from_slice(offer.token_mint_a.as_ref());
+    message[104..136].copy_from_slice(offer.token_mint_b.as_ref());
+    message[136..144].copy_from_slice(&offer.token_a_offered_amount.to_le_bytes());
+    message[144..].copy_from_slice(&offer.token_b_wanted_amount.to_le_bytes());
+    message
+}
+
+// Build an ed25519 program instruction verifying the attestor's signature over attestation_message,
+// which must come before take_offer in the same transaction to take an attested offer
+pub fn verify_attestation(
+    attestor: &Pubkey,
+    signature: &[u8; 64],
+    offer_address: &Pubkey,
+    offer: &Offer,
+    taker: &Pubkey,
+) -> Instruction {
+    verify_ed25519_signature(
+        attestor,
+        signature,
+        &attestation_message(offer_address, offer, taker),
+    )
+}
+
+// Build an ed25519 program instruction verifying signer's signature over message,
+// with the signature, public key and message all in the instruction itself
+fn verify_ed25519_signature(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let public_key_offset = ED25519_OFFSETS_START + ED25519_OFFSETS_SIZE;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let this_instruction = u16::MAX;
+
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        this_instruction,
+        public_key_offset,
+        this_instruction,
+        message_offset,
+        message.len() as u16,
+        this_instruction,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+// The message an offer's Ethereum taker signs to let taker take it, the offer's address followed by the taker's
+// The secp256k1 program checks a signature over the keccak-256 hash of these bytes, without the EIP-191 prefix
+// personal_sign adds, so sign the hash directly, eg with eth_sign or a raw signing key
+pub fn eth_taker_message(offer: &Pubkey, taker: &Pubkey) -> [u8; 64] {
+    let mut message = [0; 64];
+    message[..32].copy_from_slice(offer.as_ref());
+    message[32..].copy_from_slice(taker.as_ref());
+    message
+}
+
+// The secp256k1 program's instruction data is a signature count, then the offsets of the signature, Ethereum address
+// and message, each but the message size followed by the index of the instruction holding it
+const SECP256K1_OFFSETS_START: u16 = 1;
+const SECP256K1_OFFSETS_SIZE: u16 = 11;
+
+// Build a secp256k1 program instruction verifying the Ethereum taker's signature over eth_taker_message,
+// which must come before take_offer in the same transaction to take an offer with an Ethereum taker
+// Unlike the ed25519 program, the secp256k1 program only takes absolute instruction indexes,
+// so instruction_index has to be where this instruction will be in the transaction
+pub fn verify_eth_taker(
+    eth_taker: &[u8; ETH_ADDRESS_LENGTH],
+    signature: &[u8; 64],
+    recovery_id: u8,
+    offer: &Pubkey,
+    taker: &Pubkey,
+    instruction_index: u8,
+) -> Instruction {
+    let eth_address_offset = SECP256K1_OFFSETS_START + SECP256K1_OFFSETS_SIZE;
+    let signature_offset = eth_address_offset + ETH_ADDRESS_LENGTH as u16;
+    // The recovery id follows the signature
+    let message_offset = signature_offset + 64 + 1;
+
+    let mut data = vec![1];
+    data.extend_from_slice(&signature_offset.to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(&eth_address_offset.to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(&message_offset.to_le_bytes());
+    data.extend_from_slice(&64u16.to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(eth_taker);
+    data.extend_from_slice(signature);
+    data.push(recovery_id);
+    data.extend_from_slice(&eth_taker_message(offer, taker));
+
+    Instruction {
+        program_id: secp256k1_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+// The accounts for take_offer_with_token_accounts, for takers who keep their tokens outside their associated token accounts
+pub struct TakeOfferWithTokenAccountsAccounts {
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the taker's token a accounts, which receives the offered tokens
+    pub taker_token_account_a: Pubkey,
+    // Any of the taker's token b accounts, which pays the maker
+    pub taker_token_account_b: Pubkey,
+}
+
+// Build a take_offer_with_token_accounts instruction for an offer the caller has already fetched
+// Bundles, hashlocked offers, referrers, royalties and the membership discount need take_offer
+pub fn take_offer_with_token_accounts(
+    accounts: &TakeOfferWithTokenAccountsAccounts,
+    offer: &Offer,
+    args: &TakeOfferWithTokenAccountsArgs,
+) -> Instruction {
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(&accounts.offer).0);
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(offer.maker, false),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new_readonly(offer.token_mint_b, false),
+        AccountMeta::new(accounts.taker_token_account_a, false),
+        AccountMeta::new(accounts.taker_token_account_b, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_b,
+                &accounts.token_program_b.unwrap_or(accounts.token_program),
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.offer, false),
+        AccountMeta::new_readonly(vault_authority, false),
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.token_a_holder(&vault_authority),
+                &offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        AccountMeta::new(find_fee_vault_address(&offer.token_mint_a).0, false),
+        optional_account_meta(allowlist, true),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("take_offer_with_token_accounts", args, account_metas)
+}
+
+// The accounts for take_offer_with_authorization, for relayers submitting a take the taker authorized off-chain
+pub struct TakeOfferWithAuthorizationAccounts {
+    // Signs the transaction, and pays its fee and any rent
+    pub relayer: Pubkey,
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the taker's token b accounts, with the taker's authority approved as its delegate,
+    // see find_taker_authority_address
+    pub taker_token_account_b: Pubkey,
+}
+
+// The message a taker signs to authorize a relayer to take an offer for them on these terms:
+// the offer's address, token mints and the slot it was made in, then the expected amounts and when the
+// authorization expires, each little endian
+// The mints and slot tell the offer apart from a later offer made at the same address
+pub fn taker_authorization_message(
+    offer_address: &Pubkey,
+    offer: &Offer,
+    args: &TakeOfferWithAuthorizationArgs,
+) -> [u8; 128] {
+    let mut message = [0; 128];
+    message[..32].copy_from_slice(offer_address.as_ref());
+    message[32..64].copy_from_slice(offer.token_mint_a.as_ref());
+    message[64..96].copy_from_slice(offer.token_mint_b.as_ref());
+    message[96..104].copy_from_slice(&offer.created_slot.to_le_bytes());
+    message[104..112].copy_from_slice(&args.expected_token_a_amount.to_le_bytes());
+    message[112..120].copy_from_slice(&args.expected_token_b_amount.to_le_bytes());
+    message[120..].copy_from_slice(&args.authorization_expires_at.to_le_bytes());
+    message
+}
+
+// Build an ed25519 program instruction verifying the taker's signature over taker_authorization_message,
+// which must come before take_offer_with_authorization in the same transaction
+pub fn verify_taker_authorization(
+    taker: &Pubkey,
+    signature: &[u8; 64],
+    offer_address: &Pubkey,
+    offer: &Offer,
+    args: &TakeOfferWithAuthorizationArgs,
+) -> Instruction {
+    verify_ed25519_signature(
+        taker,
+        signature,
+        &taker_authorization_message(offer_address, offer, args),
+    )
+}
+
+// Build a take_offer_with_authorization instruction for an offer the caller has already fetched
+// The taker's token a goes to their associated token account, which the relayer creates if needed
+// Needs verify_taker_authorization earlier in the same transaction
+// Bundles, hashlocked offers, referrers, royalties and the membership discount need take_offer
+pub fn take_offer_with_authorization(
+    accounts: &TakeOfferWithAuthorizationAccounts,
+    offer: &Offer,
+    args: &TakeOfferWithAuthorizationArgs,
+) -> Instruction {
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(&accounts.offer).0);
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.relayer, true),
+        AccountMeta::new_readonly(accounts.taker, false),
+        AccountMeta::new(offer.maker, false),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new_readonly(offer.token_mint_b, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &accounts.taker,
+                &offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.taker_token_account_b, false),
+        AccountMeta::new_readonly(find_taker_authority_address(&accounts.taker).0, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_b,
+                &accounts.token_program_b.unwrap_or(accounts.token_program),
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.offer, false),
+        AccountMeta::new_readonly(vault_authority, false),
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.token_a_holder(&vault_authority),
+                &offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        AccountMeta::new(find_fee_vault_address(&offer.token_mint_a).0, false),
+        optional_account_meta(allowlist, true),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("take_offer_with_authorization", args, account_metas)
+}
+
+// The accounts for match_offers that don't come from the two offers
+#[derive(Clone, Debug)]
+pub struct MatchOffersAccounts {
+    // Signs the transaction, and pays for any token accounts the makers need
+    pub matcher: Pubkey,
+    // Offers token a for token b
+    pub first_offer: Pubkey,
+    // Offers token b for token a
+    pub second_offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the matcher's accounts for each token, which get the matcher's share of the price improvement
+    pub matcher_token_account_a: Pubkey,
+    pub matcher_token_account_b: Pubkey,
+}
+
+// Build a match_offers instruction, settling two mirrored offers the caller has already fetched against each other
+// Each offer must offer at least what the other wants, the price improvement left over is split between the matcher
+// and the makers by the config's matcher_share_bps
+// Each maker receives what they want less the protocol fee, the matcher pays the rent for any fee vault not made yet
+// Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or
+// delegation can be matched
+pub fn match_offers(
+    accounts: &MatchOffersAccounts,
+    first_offer: &Offer,
+    second_offer: &Offer,
+) -> Instruction {
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+    let first_vault_authority = find_vault_authority_address(&accounts.first_offer).0;
+    let second_vault_authority = find_vault_authority_address(&accounts.second_offer).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.matcher, true),
+        AccountMeta::new(first_offer.maker, false),
+        AccountMeta::new(first_offer.rent_payer, false),
+        AccountMeta::new(second_offer.maker, false),
+        AccountMeta::new(second_offer.rent_payer, false),
+        AccountMeta::new_readonly(first_offer.token_mint_a, false),
+        AccountMeta::new_readonly(first_offer.token_mint_b, false),
+        AccountMeta::new(accounts.first_offer, false),
+        AccountMeta::new_readonly(first_vault_authority, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &first_vault_authority,
+                &first_offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.second_offer, false),
+        AccountMeta::new_readonly(second_vault_authority, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &second_vault_authority,
+                &first_offer.token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &first_offer.maker,
+                &first_offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &first_offer.maker,
+                &first_offer.token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &second_offer.maker,
+                &first_offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &second_offer.maker,
+                &first_offer.token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.matcher_token_account_a, false),
+        AccountMeta::new(accounts.matcher_token_account_b, false),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&first_offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&first_offer.maker).0, false),
+        AccountMeta::new(find_maker_index_address(&second_offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&second_offer.maker).0, false),
+        // Always passed, so offers still match once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&first_offer.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&first_offer.token_mint_b).0, false),
+        AccountMeta::new_readonly(token_program_b, false),
+        AccountMeta::new(find_fee_vault_address(&first_offer.token_mint_a).0, false),
+        AccountMeta::new(find_fee_vault_address(&first_offer.token_mint_b).0, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("match_offers", &(), account_metas)
+}
+
+// The arguments to take_offer_route
+#[derive(BorshSerialize, Clone, Debug, Default)]
+pub struct TakeOfferRouteArgs {
+    // What the first offer must still offer, in token a
+    pub expected_token_a_amount: u64,
+    // The most token c the taker will pay the second offer's maker
+    pub maximum_token_c_amount: u64,
+}
+
+// The accounts for take_offer_route that don't come from the two offers
+pub struct TakeOfferRouteAccounts {
+    // Signs the transaction, pays with token c and receives token a
+    pub taker: Pubkey,
+    // Offers token a for token b
+    pub first_offer: Pubkey,
+    // Offers token b for token c
+    pub second_offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token programs that own token b and token c, if they aren't token_program
+    pub token_program_b: Option<Pubkey>,
+    pub token_program_c: Option<Pubkey>,
+}
+
+// Build a take_offer_route instruction, settling two offers the caller has already fetched for a taker holding
+// only token c: the second offer's token b pays for the first offer, and the taker gets the first offer's token a
+// Whatever token b the second offer holds beyond what the first wants goes to the taker's token b account
+// Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or
+// delegation can be routed
+pub fn take_offer_route(
+    accounts: &TakeOfferRouteAccounts,
+    first_offer: &Offer,
+    second_offer: &Offer,
+    args: &TakeOfferRouteArgs,
+) -> Instruction {
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+    let token_program_c = accounts.token_program_c.unwrap_or(accounts.token_program);
+    let token_mint_a = first_offer.token_mint_a;
+    let token_mint_b = first_offer.token_mint_b;
+    let token_mint_c = second_offer.token_mint_b;
+    let first_vault_authority = find_vault_authority_address(&accounts.first_offer).0;
+    let second_vault_authority = find_vault_authority_address(&accounts.second_offer).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(token_program_b, false),
+        AccountMeta::new_readonly(token_program_c, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(first_offer.maker, false),
+        AccountMeta::new(first_offer.rent_payer, false),
+        AccountMeta::new(second_offer.maker, false),
+        AccountMeta::new(second_offer.rent_payer, false),
+        AccountMeta::new_readonly(token_mint_a, false),
+        AccountMeta::new_readonly(token_mint_b, false),
+        AccountMeta::new_readonly(token_mint_c, false),
+        AccountMeta::new(accounts.first_offer, false),
+        AccountMeta::new_readonly(first_vault_authority, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &first_vault_authority,
+                &token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(accounts.second_offer, false),
+        AccountMeta::new_readonly(second_vault_authority, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &second_vault_authority,
+                &token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(find_route_authority_address(&accounts.taker).0, false),
+        AccountMeta::new(
+            find_route_token_account_address(&accounts.taker, &token_mint_b).0,
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &accounts.taker,
+                &token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &accounts.taker,
+                &token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &accounts.taker,
+                &token_mint_c,
+                &token_program_c,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &first_offer.maker,
+                &token_mint_b,
+                &token_program_b,
+            ),
+            false,
+        ),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &second_offer.maker,
+                &token_mint_c,
+                &token_program_c,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&first_offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&first_offer.maker).0, false),
+        AccountMeta::new(find_maker_index_address(&second_offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&second_offer.maker).0, false),
+        AccountMeta::new(find_fee_vault_address(&token_mint_a).0, false),
+        AccountMeta::new(find_fee_vault_address(&token_mint_b).0, false),
+        // Always passed, so routes still settle once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&token_mint_b).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&token_mint_c).0, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("take_offer_route", args, account_metas)
+}
+
+// Build a refund_offer instruction, signed by the maker, the authority that made the offer, or its refund authority
+// Bundle offers also need [mint, vault, maker token account] appended for each additional mint
+pub fn refund_offer(
+    maker_authority: &Pubkey,
+    offer_address: &Pubkey,
+    offer: &Offer,
+    token_program: &Pubkey,
+) -> Instruction {
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(offer_address).0);
+    let vault_authority = find_vault_authority_address(offer_address).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(*maker_authority, true),
+        AccountMeta::new(offer.maker, false),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_a,
+                token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(*offer_address, false),
+        AccountMeta::new_readonly(vault_authority, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.token_a_holder(&vault_authority),
+                &offer.token_mint_a,
+                token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        optional_account_meta(allowlist, true),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("refund_offer", &(), account_metas)
+}
+
+// Build a refund_legacy_offer instruction, signed by the maker, for an offer made before vault authorities, whose vault
+// the offer owns itself, including offers at ["offer", id] from before offer addresses were derived from their maker
+// Bundle offers also need [mint, vault, maker token account] appended for each additional mint
+pub fn refund_legacy_offer(offer_address: &Pubkey, offer: &Offer, token_program: &Pubkey) -> Instruction {
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(offer_address).0);
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(offer.maker, true),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_a,
+                token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(*offer_address, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.token_a_holder(offer_address),
+                &offer.token_mint_a,
+                token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        optional_account_meta(allowlist, true),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("refund_legacy_offer", &(), account_metas)
+}
+
+// The accounts for replacing an offer, besides what's read from the old offer
+pub struct ReplaceOfferAccounts {
+    // What the new offer wants, which can differ from what the old offer wanted
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a replace_offer instruction, which refunds an offer and makes a new one for the same token a, signed by the maker
+pub fn replace_offer(
+    accounts: &ReplaceOfferAccounts,
+    offer_address: &Pubkey,
+    offer: &Offer,
+    args: &ReplaceOfferArgs,
+) -> Instruction {
+    let allowlist = offer
+        .allowlist_enabled()
+        .then(|| find_allowlist_address(offer_address).0);
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+    let new_offer = find_offer_address(&offer.maker, args.new_id).0;
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(offer.maker, true),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new_readonly(offer.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(
+            get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_a,
+                &accounts.token_program,
+            ),
+            false,
+        ),
+        AccountMeta::new(*offer_address, false),
+        AccountMeta::new_readonly(find_vault_authority_address(offer_address).0, false),
+        AccountMeta::new(
+            find_vault_address(offer_address, &offer.token_mint_a, &accounts.token_program),
+            false,
+        ),
+        AccountMeta::new(find_maker_counter_address(&offer.maker).0, false),
+        AccountMeta::new(new_offer, false),
+        AccountMeta::new_readonly(find_vault_authority_address(&new_offer).0, false),
+        AccountMeta::new(
+            find_vault_address(&new_offer, &offer.token_mint_a, &accounts.token_program),
+            false,
+        ),
+        AccountMeta::new_readonly(find_config_address().0, false),
+        AccountMeta::new(find_registry_address().0, false),
+        AccountMeta::new(find_maker_index_address(&offer.maker).0, false),
+        AccountMeta::new(find_maker_stats_address(&offer.maker).0, false),
+        optional_account_meta(allowlist, true),
+        optional_account_meta(mint_allowlist_entry(&offer.token_mint_a), false),
+        optional_account_meta(mint_allowlist_entry(&accounts.token_mint_b), false),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&offer.token_mint_a).0, false),
+        AccountMeta::new_readonly(find_mint_deny_entry_address(&accounts.token_mint_b).0, false),
+        optional_account_meta(accounts.token_program_b, false),
+        AccountMeta::new_readonly(find_event_authority_address().0, false),
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+    ];
+
+    build_instruction("replace_offer", args, account_metas)
+}
+
+// Build a claim_rent instruction, which closes an offer that kept its history once it's been taken or refunded
+// Signed by the maker, or the authority that made the offer, the rent goes back to whoever paid it
+pub fn claim_rent(maker_authority: &Pubkey, offer_address: &Pubkey, offer: &Offer) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new_readonly(*maker_authority, true),
+        AccountMeta::new(offer.rent_payer, false),
+        AccountMeta::new(*offer_address, false),
+    ];
+
+    build_instruction("claim_rent", &(), account_metas)
+}
+
+// Anchor reads an optional account that was left out as the program's own address
+fn optional_account_meta(account: Option<Pubkey>, is_writable: bool) -> AccountMeta {
+    match account {
+        Some(account) if is_writable => AccountMeta::new(account, false),
+        Some(account) => AccountMeta::new_readonly(account, false),
+        None => AccountMeta::new_readonly(PROGRAM_ID, false),
+    }
+}
+
+fn build_instruction(
+    instruction_name: &str,
+    args: &impl BorshSerialize,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut data = instruction_discriminator(instruction_name).to_vec();
+    // Writing to a Vec can't fail
+    args.serialize(&mut data).unwrap();
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
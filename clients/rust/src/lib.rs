@@ -0,0 +1,61 @@
+// A client for the escrow program that doesn't need the Anchor runtime
+// Reads offers straight from their account data, builds instructions by hand, and derives the program's PDAs
+// EscrowClient wraps an RPC client for fetching and filtering offers, behind the default rpc feature
+// Without it the crate only decodes accounts and builds instructions, see decode
+
+#[cfg(feature = "rpc")]
+pub mod client;
+pub mod config;
+pub mod decode;
+pub mod error;
+pub mod events;
+pub mod instructions;
+pub mod logs;
+pub mod lookup_table;
+pub mod offer;
+pub mod pda;
+pub mod program_error;
+
+#[cfg(feature = "rpc")]
+pub use client::{EscrowClient, OfferFilter, Simulation};
+pub use config::{Config, FeeTier, MembershipDiscount, MintPolicy};
+pub use error::{Error, Result};
+pub use events::EscrowEvent;
+pub use offer::{DutchAuction, EnglishAuction, InstallmentTerms, Offer, OfferStatus};
+pub use program_error::{EscrowErrorCode, ProgramError};
+
+use solana_pubkey::{pubkey, Pubkey};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("8jR5GeNzeweq35Uo84kGP3v1NcBaZWH5u62k7PxN4T2y");
+
+// Records a taker's memo with the settlement
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+// The classic token program and the token extensions program, either can own an offer's mints
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EHFLC1NkWc3KPDEeCBs1");
+
+// Anchor prefixes instruction data with the first 8 bytes of sha256("global:<instruction name>")
+pub(crate) fn instruction_discriminator(instruction_name: &str) -> [u8; 8] {
+    hash_discriminator("global", instruction_name)
+}
+
+// And account data with the first 8 bytes of sha256("account:<account struct name>")
+pub(crate) fn account_discriminator(account_name: &str) -> [u8; 8] {
+    hash_discriminator("account", account_name)
+}
+
+// And events with the first 8 bytes of sha256("event:<event struct name>")
+pub(crate) fn event_discriminator(event_name: &str) -> [u8; 8] {
+    hash_discriminator("event", event_name)
+}
+
+fn hash_discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = solana_sha256_hasher::hashv(&[namespace.as_bytes(), b":", name.as_bytes()]);
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+    discriminator
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+// Reads events and errors out of a transaction's logs, so services don't each have to match on log strings
+// The runtime logs when each program starts and finishes, so only lines the escrow program itself wrote are read,
+// not ones from programs it calls or programs that call it
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_pubkey::Pubkey;
+
+use crate::{
+    events::EscrowEvent,
+    program_error::{EscrowErrorCode, ProgramError},
+    PROGRAM_ID,
+};
+
+// The lines the escrow program logged, with the prefix the runtime adds, like "Program log: "
+fn escrow_program_logs(logs: &[String]) -> Vec<&str> {
+    let program_id = PROGRAM_ID.to_string();
+    // The programs running at each point, the innermost last
+    let mut invoked_programs: Vec<&str> = Vec::new();
+    let mut escrow_program_logs = Vec::new();
+
+    for log in logs {
+        // "Program <program id> invoke [<depth>]", "Program <program id> success" or "Program <program id> failed: ..."
+        // Checking for a program id keeps lines a program logged, like "Program log: invoke", from counting
+        let mut words = log.split(' ');
+        if let (Some("Program"), Some(program), Some(action)) =
+            (words.next(), words.next(), words.next())
+        {
+            if program.parse::<Pubkey>().is_ok() {
+                match action {
+                    "invoke" => {
+                        invoked_programs.push(program);
+                        continue;
+                    }
+                    "success" | "failed:" => {
+                        invoked_programs.pop();
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if invoked_programs.last() == Some(&program_id.as_str()) {
+            escrow_program_logs.push(log.as_str());
+        }
+    }
+    escrow_program_logs
+}
+
+// Every event the escrow program wrote to the logs, in order
+// emit! and the program's log-events feature write events as "Program data: " lines, but the runtime can truncate logs,
+// so events from emit_cpi! are more reliably read with EscrowEvent::from_cpi_instruction_data
+pub fn parse_log_events(logs: &[String]) -> Vec<EscrowEvent> {
+    escrow_program_logs(logs)
+        .into_iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data.split(' ').next()?).ok())
+        .filter_map(|data| EscrowEvent::from_event_data(&data))
+        .collect()
+}
+
+// The error the escrow program failed with, if the transaction failed in it with a custom program error
+// Failures that aren't custom program errors, like running out of compute units, return None
+pub fn parse_program_error(logs: &[String]) -> Option<ProgramError> {
+    // The last failure is the outermost one, which is what the transaction failed with
+    let failed_prefix = format!("Program {PROGRAM_ID} failed: custom program error: 0x");
+    let number = logs
+        .iter()
+        .rev()
+        .find_map(|log| log.strip_prefix(&failed_prefix))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())?;
+
+    // Anchor logs "AnchorError ... Error Code: <name>. Error Number: <number>. Error Message: <message>."
+    let anchor_error = escrow_program_logs(logs)
+        .into_iter()
+        .rev()
+        .filter(|log| log.starts_with("Program log: AnchorError"))
+        .find_map(parse_anchor_error)
+        .filter(|(_, anchor_error_number, _)| *anchor_error_number == number);
+
+    Some(match anchor_error {
+        Some((name, _, message)) => ProgramError {
+            number,
+            code: EscrowErrorCode::from_number(number),
+            name: Some(name.to_string()),
+            message: Some(message.to_string()),
+        },
+        // The error came from a program the escrow program called
+        None => ProgramError {
+            number,
+            code: None,
+            name: None,
+            message: None,
+        },
+    })
+}
+
+// The name, number and message in an AnchorError log line
+fn parse_anchor_error(log: &str) -> Option<(&str, u32, &str)> {
+    let (_, rest) = log.split_once("Error Code: ")?;
+    let (name, rest) = rest.split_once(". Error Number: ")?;
+    let (number, message) = rest.split_once(". Error Message: ")?;
+    let message = message.strip_suffix('.').unwrap_or(message);
+    Some((name, number.parse().ok()?, message))
+}
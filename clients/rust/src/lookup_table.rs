@@ -0,0 +1,112 @@
+// Address lookup tables for the escrow program's common accounts, and v0 transactions that use them
+// A legacy transaction fits around 35 accounts, so batches of makes and takes need a lookup table
+// to list the accounts every instruction shares by a one byte index rather than the whole address
+
+use solana_address_lookup_table_interface::{
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::AddressLookupTable,
+};
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_message::{v0, AddressLookupTableAccount};
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::{system_program, sysvar};
+use spl_associated_token_account_client::program::ID as ASSOCIATED_TOKEN_PROGRAM_ID;
+
+use crate::{
+    error::{Error, Result},
+    pda::{
+        find_config_address, find_event_authority_address, find_fee_vault_address,
+        find_mint_deny_entry_address, find_registry_address,
+    },
+    MEMO_PROGRAM_ID, PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+
+// How many addresses one extend_lookup_table instruction adds, so it fits in a transaction with its signers
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 30;
+
+// The accounts shared by every make and take: the programs, the config, the registry and the event authority,
+// then each mint with its fee vault and deny entry
+pub fn escrow_lookup_table_addresses(token_mints: &[Pubkey]) -> Vec<Pubkey> {
+    let mut addresses = vec![
+        PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        system_program::ID,
+        MEMO_PROGRAM_ID,
+        sysvar::instructions::ID,
+        find_config_address().0,
+        find_registry_address().0,
+        find_event_authority_address().0,
+    ];
+    for token_mint in token_mints {
+        addresses.extend([
+            *token_mint,
+            find_fee_vault_address(token_mint).0,
+            find_mint_deny_entry_address(token_mint).0,
+        ]);
+    }
+    addresses
+}
+
+// Build the instructions that create a lookup table holding the escrow program's common accounts, see
+// escrow_lookup_table_addresses, and the table's address
+// recent_slot must be a recent finalized slot, it's part of the table's address
+// A table can't be used in the slot it was extended in, so wait a slot before sending transactions that use it
+pub fn create_escrow_lookup_table(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: u64,
+    token_mints: &[Pubkey],
+) -> (Vec<Instruction>, Pubkey) {
+    let (create_instruction, lookup_table) = create_lookup_table(*authority, *payer, recent_slot);
+    let mut instructions = vec![create_instruction];
+    instructions.extend(extend_escrow_lookup_table(
+        &lookup_table,
+        authority,
+        payer,
+        &escrow_lookup_table_addresses(token_mints),
+    ));
+    (instructions, lookup_table)
+}
+
+// Build the instructions that add addresses to a lookup table, like the accounts for newly listed mints
+// Send each in its own transaction if they don't fit in one, every instruction adds up to MAX_ADDRESSES_PER_EXTEND
+pub fn extend_escrow_lookup_table(
+    lookup_table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    addresses: &[Pubkey],
+) -> Vec<Instruction> {
+    addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| extend_lookup_table(*lookup_table, *authority, Some(*payer), chunk.to_vec()))
+        .collect()
+}
+
+// Read a lookup table from its account data, for building v0 transactions with it
+pub fn decode_lookup_table(address: &Pubkey, data: &[u8]) -> Result<AddressLookupTableAccount> {
+    let lookup_table = AddressLookupTable::deserialize(data).map_err(|_| Error::NotALookupTable)?;
+    Ok(AddressLookupTableAccount {
+        key: *address,
+        addresses: lookup_table.addresses.to_vec(),
+    })
+}
+
+// Compile instructions, like a batch of makes or takes, into a v0 message that reads any account it can
+// from the lookup tables, for the payer and any other signers to sign
+// Signers and programs called directly are always listed in full, only the other accounts can come from a table
+pub fn build_v0_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<v0::Message> {
+    Ok(v0::Message::try_compile(
+        payer,
+        instructions,
+        lookup_tables,
+        recent_blockhash,
+    )?)
+}
@@ -0,0 +1,355 @@
+use borsh::BorshSerialize;
+use bytemuck::{Pod, Zeroable};
+use solana_pubkey::Pubkey;
+use std::mem::{offset_of, size_of};
+
+use crate::{
+    account_discriminator,
+    error::{Error, Result},
+};
+
+// The same limits the program uses, the offer layout depends on them
+pub const MAX_ADDITIONAL_OFFERED_MINTS: usize = 3;
+pub const MAX_MEMO_LENGTH: usize = 64;
+pub const HASH_BYTES: usize = 32;
+pub const OFFER_RESERVED_SPACE: usize = 128;
+pub const ETH_ADDRESS_LENGTH: usize = 20;
+pub const OFFER_TAGS_LENGTH: usize = 8;
+
+// The offer layout version this client reads, older offers have to be migrated first
+pub const OFFER_VERSION: u8 = 5;
+
+// An offer to swap token a for token b, laid out exactly like the program's zero copy Offer
+// The account data is the 8 byte discriminator, then this struct, then the metadata URI if one is attached
+// Optional values are stored as a value plus a has_ flag, read them with the accessor methods below
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Offer {
+    // Identifier of the offer
+    pub id: u64,
+    // Who made the offer
+    pub maker: Pubkey,
+    // The token mint of the token being offered
+    pub token_mint_a: Pubkey,
+    // The token mint of the token wanted
+    pub token_mint_b: Pubkey,
+    // The amount of token b being wanted
+    pub token_b_wanted_amount: u64,
+    // The amount of token a the vault actually received, after any Token-2022 transfer fee
+    pub token_a_offered_amount: u64,
+    // The highest bid placed so far in an English auction, zero if there are no bids
+    pub highest_bid: u64,
+    // The smallest amount of token a a partial fill can take, or leave behind, zero for no minimum
+    pub min_fill_amount: u64,
+    expires_at: i64,
+    dutch_auction_start_price: u64,
+    dutch_auction_end_price: u64,
+    dutch_auction_start_timestamp: i64,
+    dutch_auction_end_timestamp: i64,
+    english_auction_end_timestamp: i64,
+    english_auction_min_bid_increment: u64,
+    vesting_duration: i64,
+    // Who signed for the maker when the offer was made, the maker themselves or a delegate
+    pub maker_authority: Pubkey,
+    nft_collection: Pubkey,
+    refund_authority: Pubkey,
+    arbiter: Pubkey,
+    hashlock: [u8; HASH_BYTES],
+    additional_token_mints_a: [Pubkey; MAX_ADDITIONAL_OFFERED_MINTS],
+    memo: [u8; MAX_MEMO_LENGTH],
+    pub bump: u8,
+    additional_token_mints_a_count: u8,
+    memo_length: u8,
+    allowlist_enabled: u8,
+    is_nft: u8,
+    has_expires_at: u8,
+    has_dutch_auction: u8,
+    has_english_auction: u8,
+    has_vesting_duration: u8,
+    has_nft_collection: u8,
+    has_refund_authority: u8,
+    has_hashlock: u8,
+    has_arbiter: u8,
+    arbitration_pending: u8,
+    metadata_uri_length: u16,
+    // Which version of the layout the offer was written with
+    pub version: u8,
+    is_delegated: u8,
+    // The bump of the vault authority PDA, which owns and signs for the offer's vaults
+    pub vault_authority_bump: u8,
+    _padding: [u8; 5],
+    // Who paid the rent for the offer and its vaults, and gets it back when the offer is closed
+    pub rent_payer: Pubkey,
+    // The maker's price, as price_numerator token b for every price_denominator token a
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    // When the offer was made, as a unix timestamp, 0 for offers made before it was recorded
+    pub created_at: i64,
+    keep_history: u8,
+    status: u8,
+    max_installments: u8,
+    _installment_padding: [u8; 5],
+    installment_interval: i64,
+    attestor: Pubkey,
+    has_attestor: u8,
+    eth_taker: [u8; ETH_ADDRESS_LENGTH],
+    has_eth_taker: u8,
+    _created_slot_padding: [u8; 2],
+    // The slot the offer was made in, 0 for offers made before it was recorded
+    pub created_slot: u64,
+    // Categories the maker tagged the offer with, all zero if untagged, frontends agree on what each byte means
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+    // Set aside by the program for future fields
+    _reserved: [u8; OFFER_RESERVED_SPACE
+        - 2 * size_of::<i64>()
+        - 8
+        - size_of::<Pubkey>()
+        - 1
+        - ETH_ADDRESS_LENGTH
+        - 1
+        - 2
+        - size_of::<u64>()
+        - OFFER_TAGS_LENGTH],
+}
+
+// Where the fields getProgramAccounts memcmp filters usually match on start in the account data,
+// including the discriminator, the same as the program's state::offer::offsets
+pub mod offsets {
+    use super::*;
+
+    pub const MAKER: usize = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, maker);
+    pub const TOKEN_MINT_A: usize = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, token_mint_a);
+    pub const TOKEN_MINT_B: usize = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, token_mint_b);
+    // A single byte, OfferStatus as u8
+    pub const STATUS: usize = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, status);
+    // OFFER_TAGS_LENGTH bytes, one per tag
+    pub const TAGS: usize = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, tags);
+}
+
+// Where an offer is in its lifecycle, offers are only left open once settled or refunded if they keep their history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OfferStatus {
+    Open,
+    Filled,
+    Refunded,
+    // Refunded after its expiry had passed
+    Expired,
+}
+
+// A linear price schedule for the total amount of token b wanted
+#[derive(BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DutchAuction {
+    pub start_price: u64,
+    pub end_price: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+// The payment schedule a maker accepts, takers pay in up to max_installments, each within interval seconds of the last
+#[derive(BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InstallmentTerms {
+    pub max_installments: u8,
+    pub interval: i64,
+}
+
+// Bidding rules for an English auction, token_b_wanted_amount is the reserve price
+#[derive(BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnglishAuction {
+    pub end_timestamp: i64,
+    pub min_bid_increment: u64,
+}
+
+impl Offer {
+    pub const DISCRIMINATOR_LENGTH: usize = 8;
+
+    // Where the fields clients usually filter on start in the account data, see offsets
+    pub const MAKER_OFFSET: usize = offsets::MAKER;
+    pub const TOKEN_MINT_A_OFFSET: usize = offsets::TOKEN_MINT_A;
+    pub const TOKEN_MINT_B_OFFSET: usize = offsets::TOKEN_MINT_B;
+
+    // The size of a new offer account, before any metadata URI is attached
+    pub const SPACE: usize = Offer::DISCRIMINATOR_LENGTH + size_of::<Offer>();
+
+    // The metadata URI's bytes follow the offer in the account data
+    pub const METADATA_URI_OFFSET: usize = Offer::SPACE;
+
+    // The size of each version of the layout, indexed by version
+    pub const LAYOUT_SIZES: [usize; OFFER_VERSION as usize + 1] = [536, 544, 576, 592, 656, size_of::<Offer>()];
+
+    // The discriminator at the start of every offer account
+    pub fn discriminator() -> [u8; 8] {
+        account_discriminator("Offer")
+    }
+
+    // Read an offer from its account data
+    // Offers written with an older layout are refused rather than guessed at, since migrate_offer fills in fields they lack
+    pub fn from_account_data(data: &[u8]) -> Result<Offer> {
+        if !data.starts_with(&Offer::discriminator()) {
+            return Err(Error::NotAnOffer);
+        }
+        let version = Offer::layout_version(data)?;
+        if version != OFFER_VERSION {
+            return Err(Error::OutdatedOffer(version));
+        }
+        Ok(bytemuck::pod_read_unaligned(
+            &data[Offer::DISCRIMINATOR_LENGTH..Offer::METADATA_URI_OFFSET],
+        ))
+    }
+
+    // Works out which version of the layout an offer account was written with, the same way the program does
+    // metadata_uri_length is at the same offset in every version, so the account size less the URI is the layout size
+    pub fn layout_version(data: &[u8]) -> Result<u8> {
+        let length_offset = Offer::DISCRIMINATOR_LENGTH + offset_of!(Offer, metadata_uri_length);
+        let length_bytes = data
+            .get(length_offset..length_offset + size_of::<u16>())
+            .ok_or(Error::UnknownOfferVersion)?;
+        let metadata_uri_length = u16::from_le_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let layout_size = data
+            .len()
+            .checked_sub(Offer::DISCRIMINATOR_LENGTH + metadata_uri_length)
+            .ok_or(Error::UnknownOfferVersion)?;
+        let version = Offer::LAYOUT_SIZES
+            .iter()
+            .position(|size| *size == layout_size)
+            .ok_or(Error::UnknownOfferVersion)?;
+        Ok(version as u8)
+    }
+
+    // The metadata URI stored after the offer, if one was attached
+    pub fn metadata_uri<'a>(&self, data: &'a [u8]) -> Option<&'a str> {
+        if self.metadata_uri_length == 0 {
+            return None;
+        }
+        let metadata_uri_end = Offer::METADATA_URI_OFFSET + self.metadata_uri_length as usize;
+        data.get(Offer::METADATA_URI_OFFSET..metadata_uri_end)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    // The amount of token b owed for token_a_amount at the offer's price, rounded up like the program does
+    // None if the offer has no price or the amount doesn't fit in a u64
+    pub fn token_b_amount_for(&self, token_a_amount: u64) -> Option<u64> {
+        if self.price_denominator == 0 {
+            return None;
+        }
+        let numerator = (token_a_amount as u128).checked_mul(self.price_numerator as u128)?;
+        u64::try_from(numerator.div_ceil(self.price_denominator as u128)).ok()
+    }
+
+    pub fn expires_at(&self) -> Option<i64> {
+        get_optional(self.has_expires_at, self.expires_at)
+    }
+
+    pub fn dutch_auction(&self) -> Option<DutchAuction> {
+        get_optional(
+            self.has_dutch_auction,
+            DutchAuction {
+                start_price: self.dutch_auction_start_price,
+                end_price: self.dutch_auction_end_price,
+                start_timestamp: self.dutch_auction_start_timestamp,
+                end_timestamp: self.dutch_auction_end_timestamp,
+            },
+        )
+    }
+
+    pub fn english_auction(&self) -> Option<EnglishAuction> {
+        get_optional(
+            self.has_english_auction,
+            EnglishAuction {
+                end_timestamp: self.english_auction_end_timestamp,
+                min_bid_increment: self.english_auction_min_bid_increment,
+            },
+        )
+    }
+
+    pub fn vesting_duration(&self) -> Option<i64> {
+        get_optional(self.has_vesting_duration, self.vesting_duration)
+    }
+
+    pub fn nft_collection(&self) -> Option<Pubkey> {
+        get_optional(self.has_nft_collection, self.nft_collection)
+    }
+
+    pub fn refund_authority(&self) -> Option<Pubkey> {
+        get_optional(self.has_refund_authority, self.refund_authority)
+    }
+
+    pub fn hashlock(&self) -> Option<[u8; HASH_BYTES]> {
+        get_optional(self.has_hashlock, self.hashlock)
+    }
+
+    pub fn arbiter(&self) -> Option<Pubkey> {
+        get_optional(self.has_arbiter, self.arbiter)
+    }
+
+    pub fn arbitration_pending(&self) -> bool {
+        self.arbitration_pending != 0
+    }
+
+    pub fn allowlist_enabled(&self) -> bool {
+        self.allowlist_enabled != 0
+    }
+
+    pub fn is_nft(&self) -> bool {
+        self.is_nft != 0
+    }
+
+    // Delegated offers leave token a in the maker's token account, with the vault authority approved as its delegate
+    pub fn is_delegated(&self) -> bool {
+        self.is_delegated != 0
+    }
+
+    // Who owns the token account holding the offer's token a: the vault authority for its vault, or the maker if delegated
+    pub fn token_a_holder(&self, vault_authority: &Pubkey) -> Pubkey {
+        if self.is_delegated() {
+            self.maker
+        } else {
+            *vault_authority
+        }
+    }
+
+    // Offers that keep their history stay open once taken or refunded, until the maker closes them with claim_rent
+    pub fn keep_history(&self) -> bool {
+        self.keep_history != 0
+    }
+
+    pub fn status(&self) -> OfferStatus {
+        match self.status {
+            1 => OfferStatus::Filled,
+            2 => OfferStatus::Refunded,
+            3 => OfferStatus::Expired,
+            _ => OfferStatus::Open,
+        }
+    }
+
+    pub fn installment_terms(&self) -> Option<InstallmentTerms> {
+        (self.max_installments != 0).then_some(InstallmentTerms {
+            max_installments: self.max_installments,
+            interval: self.installment_interval,
+        })
+    }
+
+    // The key that must sign the offer and its taker before take_offer will settle it, see instructions::verify_attestation
+    pub fn attestor(&self) -> Option<Pubkey> {
+        get_optional(self.has_attestor, self.attestor)
+    }
+
+    // The Ethereum address that has to sign for whoever takes the offer, see instructions::verify_eth_taker
+    pub fn eth_taker(&self) -> Option<[u8; ETH_ADDRESS_LENGTH]> {
+        get_optional(self.has_eth_taker, self.eth_taker)
+    }
+
+    pub fn additional_token_mints_a(&self) -> &[Pubkey] {
+        &self.additional_token_mints_a[..self.additional_token_mints_a_count as usize]
+    }
+
+    pub fn memo(&self) -> Option<&str> {
+        if self.memo_length == 0 {
+            return None;
+        }
+        std::str::from_utf8(&self.memo[..self.memo_length as usize]).ok()
+    }
+}
+
+fn get_optional<T>(flag: u8, value: T) -> Option<T> {
+    (flag != 0).then_some(value)
+}
@@ -0,0 +1,107 @@
+use solana_pubkey::Pubkey;
+use spl_associated_token_account_client::address::get_associated_token_address_with_program_id;
+
+use crate::PROGRAM_ID;
+
+// The offer account for a maker's offer id, ids only need to be unique per maker
+pub fn find_offer_address(maker: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer", maker.as_ref(), id.to_le_bytes().as_ref()], &PROGRAM_ID)
+}
+
+// Owns and signs for an offer's vaults, so the offer account itself only holds data
+pub fn find_vault_authority_address(offer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault_authority", offer.as_ref()], &PROGRAM_ID)
+}
+
+// The vault holding an offer's token a, which is the vault authority's associated token account
+pub fn find_vault_address(offer: &Pubkey, token_mint_a: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(
+        &find_vault_authority_address(offer).0,
+        token_mint_a,
+        token_program,
+    )
+}
+
+// The protocol-wide settings, there is only one
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &PROGRAM_ID)
+}
+
+// The protocol-wide offer statistics, there is only one
+pub fn find_registry_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry"], &PROGRAM_ID)
+}
+
+// Hands out a maker's offer ids when make_offer is asked to assign one
+pub fn find_maker_counter_address(maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"maker_counter", maker.as_ref()], &PROGRAM_ID)
+}
+
+// The list of a maker's open offers
+pub fn find_maker_index_address(maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"maker_index", maker.as_ref()], &PROGRAM_ID)
+}
+
+// A maker's lifetime offer statistics
+pub fn find_maker_stats_address(maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"maker_stats", maker.as_ref()], &PROGRAM_ID)
+}
+
+// Where the protocol fee for a mint is collected
+pub fn find_fee_vault_address(token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"fee_vault", token_mint.as_ref()], &PROGRAM_ID)
+}
+
+// The takers allowed to take an offer, if the maker enabled an allowlist
+pub fn find_allowlist_address(offer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"allowlist", offer.as_ref()], &PROGRAM_ID)
+}
+
+// A mint's entry on the protocol's mint allowlist, if the allowlist manager has allowed it
+pub fn find_mint_allowlist_entry_address(token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint_allowlist_entry", token_mint.as_ref()], &PROGRAM_ID)
+}
+
+// A mint's deny entry, which only has an account while the allowlist manager denies the mint
+pub fn find_mint_deny_entry_address(token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint_deny_entry", token_mint.as_ref()], &PROGRAM_ID)
+}
+
+// Approved by a taker as the delegate on their token b account, so relayers can settle offers the taker authorized
+// with take_offer_with_authorization
+pub fn find_taker_authority_address(taker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"taker_authority", taker.as_ref()], &PROGRAM_ID)
+}
+
+// A prospective taker's request for quotes, ids only need to be unique per requester
+pub fn find_quote_request_address(requester: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"quote_request", requester.as_ref(), id.to_le_bytes().as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+// A maker's quote for a quote request, which also owns the vault holding the quoted token a
+pub fn find_quote_address(quote_request: &Pubkey, maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"quote", quote_request.as_ref(), maker.as_ref()], &PROGRAM_ID)
+}
+
+// Owns a taker's transient account for the intermediate token while take_offer_route settles their route
+pub fn find_route_authority_address(taker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"route_authority", taker.as_ref()], &PROGRAM_ID)
+}
+
+// The transient account take_offer_route creates and closes within the instruction
+pub fn find_route_token_account_address(taker: &Pubkey, token_mint_b: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"route", taker.as_ref(), token_mint_b.as_ref()], &PROGRAM_ID)
+}
+
+// A maker's stake offer, which holds the stake account's staker and withdrawer authorities while it's open
+pub fn find_stake_offer_address(stake_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake_offer", stake_account.as_ref()], &PROGRAM_ID)
+}
+
+// Signs the self-CPI that emits the program's events, every instruction that emits events needs it
+pub fn find_event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID)
+}
@@ -0,0 +1,464 @@
+// Anchor numbers a program's own errors from 6000, in the order the program declares them
+pub const ERROR_CODE_OFFSET: u32 = 6000;
+
+// The escrow program's error codes, in the same order as the program's ErrorCode
+// The program only ever appends new codes, so add them to the end here and to ALL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowErrorCode {
+    InsufficientMakerBalance,
+    InsufficientTakerBalance,
+    InvalidTokenMint,
+    InvalidAmount,
+    FailedVaultWithdrawal,
+    FailedVaultClosure,
+    FailedRefundTransfer,
+    FailedRefundClosure,
+    OfferExpired,
+    InvalidExpiry,
+    SelfTradeNotAllowed,
+    InvalidAuctionSchedule,
+    MathOverflow,
+    ConflictingAuctionModes,
+    OfferIsAuction,
+    NotAnAuction,
+    AuctionEnded,
+    AuctionNotEnded,
+    BidTooLow,
+    AuctionHasBids,
+    AuctionHasNoBids,
+    BidderMismatch,
+    TakerNotAllowed,
+    AllowlistRequired,
+    AllowlistFull,
+    TooManyOfferedMints,
+    InvalidRemainingAccounts,
+    InvalidVault,
+    InvalidTokenAccount,
+    InvalidTokenProgram,
+    BundleNotSupported,
+    NotAnNft,
+    InvalidMetadata,
+    CollectionMismatch,
+    TransferFeeExceedsAmount,
+    TransferFeeShortfall,
+    TransferHookNotSupported,
+    InvalidFee,
+    NoFeesToWithdraw,
+    SelfReferral,
+    ProgramPaused,
+    MemoTooLong,
+    MetadataUriTooLong,
+    BatchOfferMismatch,
+    InsufficientOfferBalance,
+    PartialFillNotSupported,
+    OfferTermsChanged,
+    FillTooSmall,
+    InvalidPreimage,
+    OfferIsHashlocked,
+    OfferIsVested,
+    NotVested,
+    InvalidVestingSchedule,
+    NothingToClaim,
+    DepositNotSupported,
+    NotSwapParty,
+    InvalidMakerAuthority,
+    MakerIndexFull,
+    OfferIsArbitrated,
+    NotArbitrated,
+    ArbiterNotSupported,
+    InvalidArbiter,
+    ArbitrationPending,
+    UnknownOfferVersion,
+    OfferAlreadyMigrated,
+    InvalidRentPayer,
+    MintNotAllowed,
+    InvalidMaker,
+    WrongTokenMint,
+    WrongOffer,
+    InvalidTaker,
+    ArbiterMismatch,
+    InvalidAuthority,
+    InvalidFeeAuthority,
+    InvalidBeneficiary,
+    InvalidProposer,
+    InvalidTokenAccountOwner,
+    TokenAccountFrozen,
+    InvalidMinFillAmount,
+    InvalidNftAmount,
+    InvalidPrice,
+    InvalidOfferedAmount,
+    InvalidWantedAmount,
+    DivisionByZero,
+    MemoProgramRequired,
+    RoyaltyMetadataRequired,
+    RoyaltiesRequired,
+    DelegationRevoked,
+    OfferStillExists,
+    OfferNotOpen,
+    OfferStillOpen,
+    InvalidOfferLimits,
+    OfferTooSmall,
+    OfferTooLarge,
+    MintNotAllowlisted,
+    MintDenied,
+    MintDenyEntryRequired,
+    InvalidFeeTiers,
+    InvalidMembershipDiscount,
+    NotAMember,
+    InvalidPauser,
+    InvalidFeeManager,
+    InvalidAllowlistManager,
+    SettledAmountMismatch,
+    TemplateOfferStillOpen,
+    InvalidInstallmentTerms,
+    InstallmentsNotSupported,
+    InstallmentsNotAllowed,
+    InvalidInstallmentCount,
+    InstallmentNotOverdue,
+    AttestorNotSupported,
+    OfferIsAttested,
+    AttestationRequired,
+    InvalidAttestation,
+    EthTakerNotSupported,
+    OfferHasEthTaker,
+    EthSignatureRequired,
+    InvalidEthSignature,
+    AuthorizationExpired,
+    InvalidTakerAuthorization,
+    TakerDelegationMissing,
+    InvalidMatcherShare,
+    MatchNotSupported,
+    OffersDontMatch,
+    QuoteRequestExpired,
+    QuoteStillFirm,
+    WrongQuoteRequest,
+    InvalidRequester,
+    VaultUnderfunded,
+    ReplaceNotSupported,
+    RouteNotSupported,
+    RouteLegsDontMatch,
+    InvalidStakeAuthority,
+    StakeLockupInForce,
+    StakeNotDeactivated,
+    RecipientTokenAccountRequired,
+    OfferTooNew,
+    TooManyOpenOffers,
+    InvalidMaxOpenOffers,
+    NotALegacyOffer,
+}
+
+impl EscrowErrorCode {
+    // Every code, indexed by its number less ERROR_CODE_OFFSET
+    pub const ALL: [EscrowErrorCode; 140] = [
+        EscrowErrorCode::InsufficientMakerBalance,
+        EscrowErrorCode::InsufficientTakerBalance,
+        EscrowErrorCode::InvalidTokenMint,
+        EscrowErrorCode::InvalidAmount,
+        EscrowErrorCode::FailedVaultWithdrawal,
+        EscrowErrorCode::FailedVaultClosure,
+        EscrowErrorCode::FailedRefundTransfer,
+        EscrowErrorCode::FailedRefundClosure,
+        EscrowErrorCode::OfferExpired,
+        EscrowErrorCode::InvalidExpiry,
+        EscrowErrorCode::SelfTradeNotAllowed,
+        EscrowErrorCode::InvalidAuctionSchedule,
+        EscrowErrorCode::MathOverflow,
+        EscrowErrorCode::ConflictingAuctionModes,
+        EscrowErrorCode::OfferIsAuction,
+        EscrowErrorCode::NotAnAuction,
+        EscrowErrorCode::AuctionEnded,
+        EscrowErrorCode::AuctionNotEnded,
+        EscrowErrorCode::BidTooLow,
+        EscrowErrorCode::AuctionHasBids,
+        EscrowErrorCode::AuctionHasNoBids,
+        EscrowErrorCode::BidderMismatch,
+        EscrowErrorCode::TakerNotAllowed,
+        EscrowErrorCode::AllowlistRequired,
+        EscrowErrorCode::AllowlistFull,
+        EscrowErrorCode::TooManyOfferedMints,
+        EscrowErrorCode::InvalidRemainingAccounts,
+        EscrowErrorCode::InvalidVault,
+        EscrowErrorCode::InvalidTokenAccount,
+        EscrowErrorCode::InvalidTokenProgram,
+        EscrowErrorCode::BundleNotSupported,
+        EscrowErrorCode::NotAnNft,
+        EscrowErrorCode::InvalidMetadata,
+        EscrowErrorCode::CollectionMismatch,
+        EscrowErrorCode::TransferFeeExceedsAmount,
+        EscrowErrorCode::TransferFeeShortfall,
+        EscrowErrorCode::TransferHookNotSupported,
+        EscrowErrorCode::InvalidFee,
+        EscrowErrorCode::NoFeesToWithdraw,
+        EscrowErrorCode::SelfReferral,
+        EscrowErrorCode::ProgramPaused,
+        EscrowErrorCode::MemoTooLong,
+        EscrowErrorCode::MetadataUriTooLong,
+        EscrowErrorCode::BatchOfferMismatch,
+        EscrowErrorCode::InsufficientOfferBalance,
+        EscrowErrorCode::PartialFillNotSupported,
+        EscrowErrorCode::OfferTermsChanged,
+        EscrowErrorCode::FillTooSmall,
+        EscrowErrorCode::InvalidPreimage,
+        EscrowErrorCode::OfferIsHashlocked,
+        EscrowErrorCode::OfferIsVested,
+        EscrowErrorCode::NotVested,
+        EscrowErrorCode::InvalidVestingSchedule,
+        EscrowErrorCode::NothingToClaim,
+        EscrowErrorCode::DepositNotSupported,
+        EscrowErrorCode::NotSwapParty,
+        EscrowErrorCode::InvalidMakerAuthority,
+        EscrowErrorCode::MakerIndexFull,
+        EscrowErrorCode::OfferIsArbitrated,
+        EscrowErrorCode::NotArbitrated,
+        EscrowErrorCode::ArbiterNotSupported,
+        EscrowErrorCode::InvalidArbiter,
+        EscrowErrorCode::ArbitrationPending,
+        EscrowErrorCode::UnknownOfferVersion,
+        EscrowErrorCode::OfferAlreadyMigrated,
+        EscrowErrorCode::InvalidRentPayer,
+        EscrowErrorCode::MintNotAllowed,
+        EscrowErrorCode::InvalidMaker,
+        EscrowErrorCode::WrongTokenMint,
+        EscrowErrorCode::WrongOffer,
+        EscrowErrorCode::InvalidTaker,
+        EscrowErrorCode::ArbiterMismatch,
+        EscrowErrorCode::InvalidAuthority,
+        EscrowErrorCode::InvalidFeeAuthority,
+        EscrowErrorCode::InvalidBeneficiary,
+        EscrowErrorCode::InvalidProposer,
+        EscrowErrorCode::InvalidTokenAccountOwner,
+        EscrowErrorCode::TokenAccountFrozen,
+        EscrowErrorCode::InvalidMinFillAmount,
+        EscrowErrorCode::InvalidNftAmount,
+        EscrowErrorCode::InvalidPrice,
+        EscrowErrorCode::InvalidOfferedAmount,
+        EscrowErrorCode::InvalidWantedAmount,
+        EscrowErrorCode::DivisionByZero,
+        EscrowErrorCode::MemoProgramRequired,
+        EscrowErrorCode::RoyaltyMetadataRequired,
+        EscrowErrorCode::RoyaltiesRequired,
+        EscrowErrorCode::DelegationRevoked,
+        EscrowErrorCode::OfferStillExists,
+        EscrowErrorCode::OfferNotOpen,
+        EscrowErrorCode::OfferStillOpen,
+        EscrowErrorCode::InvalidOfferLimits,
+        EscrowErrorCode::OfferTooSmall,
+        EscrowErrorCode::OfferTooLarge,
+        EscrowErrorCode::MintNotAllowlisted,
+        EscrowErrorCode::MintDenied,
+        EscrowErrorCode::MintDenyEntryRequired,
+        EscrowErrorCode::InvalidFeeTiers,
+        EscrowErrorCode::InvalidMembershipDiscount,
+        EscrowErrorCode::NotAMember,
+        EscrowErrorCode::InvalidPauser,
+        EscrowErrorCode::InvalidFeeManager,
+        EscrowErrorCode::InvalidAllowlistManager,
+        EscrowErrorCode::SettledAmountMismatch,
+        EscrowErrorCode::TemplateOfferStillOpen,
+        EscrowErrorCode::InvalidInstallmentTerms,
+        EscrowErrorCode::InstallmentsNotSupported,
+        EscrowErrorCode::InstallmentsNotAllowed,
+        EscrowErrorCode::InvalidInstallmentCount,
+        EscrowErrorCode::InstallmentNotOverdue,
+        EscrowErrorCode::AttestorNotSupported,
+        EscrowErrorCode::OfferIsAttested,
+        EscrowErrorCode::AttestationRequired,
+        EscrowErrorCode::InvalidAttestation,
+        EscrowErrorCode::EthTakerNotSupported,
+        EscrowErrorCode::OfferHasEthTaker,
+        EscrowErrorCode::EthSignatureRequired,
+        EscrowErrorCode::InvalidEthSignature,
+        EscrowErrorCode::AuthorizationExpired,
+        EscrowErrorCode::InvalidTakerAuthorization,
+        EscrowErrorCode::TakerDelegationMissing,
+        EscrowErrorCode::InvalidMatcherShare,
+        EscrowErrorCode::MatchNotSupported,
+        EscrowErrorCode::OffersDontMatch,
+        EscrowErrorCode::QuoteRequestExpired,
+        EscrowErrorCode::QuoteStillFirm,
+        EscrowErrorCode::WrongQuoteRequest,
+        EscrowErrorCode::InvalidRequester,
+        EscrowErrorCode::VaultUnderfunded,
+        EscrowErrorCode::ReplaceNotSupported,
+        EscrowErrorCode::RouteNotSupported,
+        EscrowErrorCode::RouteLegsDontMatch,
+        EscrowErrorCode::InvalidStakeAuthority,
+        EscrowErrorCode::StakeLockupInForce,
+        EscrowErrorCode::StakeNotDeactivated,
+        EscrowErrorCode::RecipientTokenAccountRequired,
+        EscrowErrorCode::OfferTooNew,
+        EscrowErrorCode::TooManyOpenOffers,
+        EscrowErrorCode::InvalidMaxOpenOffers,
+        EscrowErrorCode::NotALegacyOffer,
+    ];
+
+    // The code for a custom program error number, if it's one of the escrow program's
+    pub fn from_number(number: u32) -> Option<EscrowErrorCode> {
+        let index = number.checked_sub(ERROR_CODE_OFFSET)?;
+        EscrowErrorCode::ALL.get(index as usize).copied()
+    }
+
+    // The number the program reports this code as, in a custom instruction error
+    pub fn number(&self) -> u32 {
+        ERROR_CODE_OFFSET + *self as u32
+    }
+
+    // The program's message for this code
+    pub fn message(&self) -> &'static str {
+        match self {
+            EscrowErrorCode::InsufficientMakerBalance => "Insufficient token balance in maker's account",
+            EscrowErrorCode::InsufficientTakerBalance => "Insufficient token balance in taker's account",
+            EscrowErrorCode::InvalidTokenMint => "Invalid token mint - must be different from offered token",
+            EscrowErrorCode::InvalidAmount => "Amount must be greater than zero",
+            EscrowErrorCode::FailedVaultWithdrawal => "Failed to withdraw tokens from vault",
+            EscrowErrorCode::FailedVaultClosure => "Failed to close vault account",
+            EscrowErrorCode::FailedRefundTransfer => "Failed to refund tokens from vault",
+            EscrowErrorCode::FailedRefundClosure => "Failed to close vault during refund",
+            EscrowErrorCode::OfferExpired => "Offer has expired",
+            EscrowErrorCode::InvalidExpiry => "Expiry must be in the future",
+            EscrowErrorCode::SelfTradeNotAllowed => "The maker cannot trade with their own offer",
+            EscrowErrorCode::InvalidAuctionSchedule => "Dutch auction must end after it starts, and its price can only decrease",
+            EscrowErrorCode::MathOverflow => "Arithmetic overflow",
+            EscrowErrorCode::ConflictingAuctionModes => "An offer can't be both a Dutch and an English auction",
+            EscrowErrorCode::OfferIsAuction => "English auction offers can only be settled to the highest bid",
+            EscrowErrorCode::NotAnAuction => "Offer is not an English auction",
+            EscrowErrorCode::AuctionEnded => "Auction has ended",
+            EscrowErrorCode::AuctionNotEnded => "Auction has not ended yet",
+            EscrowErrorCode::BidTooLow => "Bid is below the reserve price or minimum increment",
+            EscrowErrorCode::AuctionHasBids => "Auction already has bids",
+            EscrowErrorCode::AuctionHasNoBids => "Auction has no bids to settle",
+            EscrowErrorCode::BidderMismatch => "Account does not belong to the current highest bidder",
+            EscrowErrorCode::TakerNotAllowed => "Taker is not on the offer's allowlist",
+            EscrowErrorCode::AllowlistRequired => "Offer has an allowlist, so the allowlist account must be provided",
+            EscrowErrorCode::AllowlistFull => "Offer allowlist is full",
+            EscrowErrorCode::TooManyOfferedMints => "Too many mints offered in one bundle",
+            EscrowErrorCode::InvalidRemainingAccounts => "Wrong number of remaining accounts",
+            EscrowErrorCode::InvalidVault => "Vault must be the offer vault authority's associated token account for the mint",
+            EscrowErrorCode::InvalidTokenAccount => "Token account has the wrong mint or owner",
+            EscrowErrorCode::InvalidTokenProgram => "Mint is not owned by the token program",
+            EscrowErrorCode::BundleNotSupported => "This operation is not supported for bundle offers",
+            EscrowErrorCode::NotAnNft => "Token is not an NFT",
+            EscrowErrorCode::InvalidMetadata => "Metadata account is not the Metaplex metadata for this mint",
+            EscrowErrorCode::CollectionMismatch => "NFT is not part of the expected collection",
+            EscrowErrorCode::TransferFeeExceedsAmount => "Transfer fee would consume the entire offered amount",
+            EscrowErrorCode::TransferFeeShortfall => "Recipient received less than the amount owed after transfer fees",
+            EscrowErrorCode::TransferHookNotSupported => "Mints with a Token-2022 transfer hook are not supported",
+            EscrowErrorCode::InvalidFee => "Fee is higher than the maximum allowed",
+            EscrowErrorCode::NoFeesToWithdraw => "There are no fees to withdraw",
+            EscrowErrorCode::SelfReferral => "Takers can't refer themselves",
+            EscrowErrorCode::ProgramPaused => "The program is paused",
+            EscrowErrorCode::MemoTooLong => "Memo is too long",
+            EscrowErrorCode::MetadataUriTooLong => "Metadata URI is too long",
+            EscrowErrorCode::BatchOfferMismatch => "Every offer in a batch must be from the same maker, for the same pair of mints",
+            EscrowErrorCode::InsufficientOfferBalance => "Offer doesn't hold enough token a",
+            EscrowErrorCode::PartialFillNotSupported => "This offer can only be taken in full",
+            EscrowErrorCode::OfferTermsChanged => "Offer terms changed since the taker saw them",
+            EscrowErrorCode::FillTooSmall => "Partial fill is below the offer's minimum fill amount",
+            EscrowErrorCode::InvalidPreimage => "Preimage doesn't match the offer's hashlock",
+            EscrowErrorCode::OfferIsHashlocked => "Hashlocked offers can only be taken with take_offer",
+            EscrowErrorCode::OfferIsVested => "Vested offers can only be taken with take_offer_vested",
+            EscrowErrorCode::NotVested => "This offer doesn't vest",
+            EscrowErrorCode::InvalidVestingSchedule => "Vesting duration must be positive",
+            EscrowErrorCode::NothingToClaim => "Nothing has vested since the last claim",
+            EscrowErrorCode::DepositNotSupported => "Only fixed price offers without a bundle, hashlock or vesting can be deposited for",
+            EscrowErrorCode::NotSwapParty => "Only the maker, the maker's authority or the depositing taker can confirm the swap",
+            EscrowErrorCode::InvalidMakerAuthority => "Signer is neither the maker nor an authority acting for the maker",
+            EscrowErrorCode::MakerIndexFull => "Maker already has the most open offers their index can list",
+            EscrowErrorCode::OfferIsArbitrated => "Arbitrated offers can only be taken with take_offer_arbitrated",
+            EscrowErrorCode::NotArbitrated => "This offer has no arbiter",
+            EscrowErrorCode::ArbiterNotSupported => "Arbitrated offers can't be auctions, bundles, hashlocked or vested",
+            EscrowErrorCode::InvalidArbiter => "The arbiter can't be the maker or the taker of the offer",
+            EscrowErrorCode::ArbitrationPending => "Offer has been taken and is waiting for its arbiter",
+            EscrowErrorCode::UnknownOfferVersion => "Offer account doesn't match any known version of the offer layout",
+            EscrowErrorCode::OfferAlreadyMigrated => "Offer already uses the current layout",
+            EscrowErrorCode::InvalidRentPayer => "Rent payer doesn't match the account that paid for the offer",
+            EscrowErrorCode::MintNotAllowed => "Mint has a configuration the protocol's mint policy doesn't allow",
+            EscrowErrorCode::InvalidMaker => "Account is not the offer's maker",
+            EscrowErrorCode::WrongTokenMint => "Mint doesn't match the mint the account was made for",
+            EscrowErrorCode::WrongOffer => "Account belongs to a different offer",
+            EscrowErrorCode::InvalidTaker => "Account is not the taker recorded for this swap",
+            EscrowErrorCode::ArbiterMismatch => "Signer is not the offer's arbiter",
+            EscrowErrorCode::InvalidAuthority => "Signer is not the config authority",
+            EscrowErrorCode::InvalidFeeAuthority => "Signer is not the config's fee authority",
+            EscrowErrorCode::InvalidBeneficiary => "Account is not the vesting beneficiary",
+            EscrowErrorCode::InvalidProposer => "Account is not the counter offer's proposer",
+            EscrowErrorCode::InvalidTokenAccountOwner => "Token account is owned by the wrong wallet",
+            EscrowErrorCode::TokenAccountFrozen => "Token account is frozen",
+            EscrowErrorCode::InvalidMinFillAmount => "Minimum fill amount can't be more than the amount offered",
+            EscrowErrorCode::InvalidNftAmount => "NFT offers must offer exactly one token",
+            EscrowErrorCode::InvalidPrice => "Offer has no price",
+            EscrowErrorCode::InvalidOfferedAmount => "Offered amount must be greater than zero",
+            EscrowErrorCode::InvalidWantedAmount => "Wanted amount must be greater than zero",
+            EscrowErrorCode::DivisionByZero => "Division by zero",
+            EscrowErrorCode::MemoProgramRequired => "The memo program is required to attach a memo",
+            EscrowErrorCode::RoyaltyMetadataRequired => "Royalties are required, pass the NFT's metadata and its creators' token accounts",
+            EscrowErrorCode::RoyaltiesRequired => "Royalties are required, and only take_offer pays them on NFT offers",
+            EscrowErrorCode::DelegationRevoked => "The maker has revoked the offer's approval or spent the tokens it covered",
+            EscrowErrorCode::OfferStillExists => "The vault's offer still exists, close the offer instead",
+            EscrowErrorCode::OfferNotOpen => "The offer has already been settled or refunded",
+            EscrowErrorCode::OfferStillOpen => "The offer is still open, only settled or refunded offers' rent can be claimed",
+            EscrowErrorCode::InvalidOfferLimits => "The minimum offer amount can't be more than the maximum",
+            EscrowErrorCode::OfferTooSmall => "The offered amount is below the protocol's minimum",
+            EscrowErrorCode::OfferTooLarge => "The offered amount is above the protocol's maximum",
+            EscrowErrorCode::MintNotAllowlisted => "The mint isn't on the protocol's mint allowlist",
+            EscrowErrorCode::MintDenied => "The mint is on the protocol's mint denylist",
+            EscrowErrorCode::MintDenyEntryRequired => "Pass each mint's deny entry address while the protocol denies any mints",
+            EscrowErrorCode::InvalidFeeTiers => "Fee tiers must be sorted by volume, with at most MAX_FEE_TIERS of them",
+            EscrowErrorCode::InvalidMembershipDiscount => "A membership discount needs a minimum balance, and can't be more than the whole fee",
+            EscrowErrorCode::NotAMember => "The membership token account doesn't hold enough of the membership token for the discount",
+            EscrowErrorCode::InvalidPauser => "Only the config's pauser can do this",
+            EscrowErrorCode::InvalidFeeManager => "Only the config's fee manager can do this",
+            EscrowErrorCode::InvalidAllowlistManager => "Only the config's allowlist manager can do this",
+            EscrowErrorCode::SettledAmountMismatch => "Token account received a different amount than the transfer should have delivered",
+            EscrowErrorCode::TemplateOfferStillOpen => "The template's last offer is still open",
+            EscrowErrorCode::InvalidInstallmentTerms => "Installment terms need at least two installments and a positive interval",
+            EscrowErrorCode::InstallmentsNotSupported => "Only offers without an English auction, bundle, hashlock, attestor, Ethereum taker, vesting or arbiter, and not delegated, can be paid in installments",
+            EscrowErrorCode::InstallmentsNotAllowed => "The maker hasn't agreed to be paid in installments for this offer",
+            EscrowErrorCode::InvalidInstallmentCount => "Pay in at least two installments, and no more than the maker allows",
+            EscrowErrorCode::InstallmentNotOverdue => "The taker isn't behind on their installments",
+            EscrowErrorCode::AttestorNotSupported => "Only offers take_offer settles, without installments, can have an attestor",
+            EscrowErrorCode::OfferIsAttested => "Attested offers can only be taken with take_offer",
+            EscrowErrorCode::AttestationRequired => "Attested offers need the instructions sysvar to check the attestor's signature",
+            EscrowErrorCode::InvalidAttestation => "The transaction doesn't verify the attestor's ed25519 signature over the offer and taker",
+            EscrowErrorCode::EthTakerNotSupported => "Only offers take_offer settles, without installments, can be limited to an Ethereum taker",
+            EscrowErrorCode::OfferHasEthTaker => "Offers limited to an Ethereum taker can only be taken with take_offer",
+            EscrowErrorCode::EthSignatureRequired => "Offers limited to an Ethereum taker need the instructions sysvar to check the taker's signature",
+            EscrowErrorCode::InvalidEthSignature => "The transaction doesn't verify a secp256k1 signature from the offer's Ethereum taker over the offer and taker addresses",
+            EscrowErrorCode::AuthorizationExpired => "The taker's authorization has expired",
+            EscrowErrorCode::InvalidTakerAuthorization => "The transaction doesn't verify the taker's ed25519 signature over the offer and the terms they authorized",
+            EscrowErrorCode::TakerDelegationMissing => "The taker hasn't approved their taker authority to spend enough token b",
+            EscrowErrorCode::InvalidMatcherShare => "Matcher share is more than the whole price improvement",
+            EscrowErrorCode::MatchNotSupported => "Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or delegation can be matched",
+            EscrowErrorCode::OffersDontMatch => "Each offer must offer at least what the other wants",
+            EscrowErrorCode::QuoteRequestExpired => "The quote request has expired",
+            EscrowErrorCode::QuoteStillFirm => "Quotes can only be withdrawn once their request expires or is closed",
+            EscrowErrorCode::WrongQuoteRequest => "Quote belongs to a different quote request",
+            EscrowErrorCode::InvalidRequester => "Signer is not the quote request's requester",
+            EscrowErrorCode::VaultUnderfunded => "The vault holds less token a than the offer is for",
+            EscrowErrorCode::ReplaceNotSupported => "Bundle and delegated offers can't be replaced, refund them and make a new offer instead",
+            EscrowErrorCode::RouteNotSupported => "Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or delegation can be routed",
+            EscrowErrorCode::RouteLegsDontMatch => "The second offer must offer at least the intermediate token the first offer wants",
+            EscrowErrorCode::InvalidStakeAuthority => "The maker must be the stake account's staker and withdrawer",
+            EscrowErrorCode::StakeLockupInForce => "Stake accounts with a lockup in force can't be offered",
+            EscrowErrorCode::StakeNotDeactivated => "Only deactivated stake accounts can be offered",
+            EscrowErrorCode::RecipientTokenAccountRequired => "Pass a recipient with their token account, or the taker's token account without a recipient",
+            EscrowErrorCode::OfferTooNew => "This offer was made too recently to be taken yet",
+            EscrowErrorCode::TooManyOpenOffers => "Maker already has the most open offers the config allows",
+            EscrowErrorCode::InvalidMaxOpenOffers => "The open offer cap must be at least one and at most what a maker index can list",
+            EscrowErrorCode::NotALegacyOffer => "Only offers made before offers had a vault authority can be refunded this way",
+        }
+    }
+}
+
+// An error the escrow program failed with, read from a failed transaction's logs, see logs::parse_program_error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramError {
+    // The custom program error number the transaction failed with
+    pub number: u32,
+    // Set when the escrow program raised the error itself, rather than Anchor or a program it called
+    pub code: Option<EscrowErrorCode>,
+    // Anchor's name and message for the error, when the escrow program logged them
+    // Anchor's own errors, like a failed seeds constraint, only have these
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
@@ -0,0 +1,1146 @@
+use anchor_lang::{AccountSerialize, Discriminator, Event, InstructionData, Space};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytemuck::Zeroable;
+use escrow::state::Offer as ProgramOffer;
+use solana_pubkey::Pubkey;
+#[cfg(feature = "rpc")]
+use solana_rpc_client_api::filter::RpcFilterType;
+use spl_associated_token_account_client::address::get_associated_token_address_with_program_id;
+use std::mem::size_of;
+
+use crate::{
+    config::{FeeTier, MembershipDiscount},
+    decode::{decode_account, decode_config, EscrowAccount},
+    error::Error,
+    events::{EscrowEvent, OfferMade, OfferRefunded, OfferTaken, EVENT_IX_TAG_LE},
+    instruction_discriminator,
+    instructions::{
+        attestation_message, make_offer, make_offer_delegated, make_offer_with_assigned_id, make_offer_with_existing_vault, match_offers, replace_offer, take_offer,
+        take_offer_with_authorization, take_offer_with_token_accounts, taker_authorization_message,
+        MakeOfferAccounts, MakeOfferArgs, MakeOfferDelegatedAccounts, MakeOfferDelegatedArgs, MakeOfferWithExistingVaultAccounts,
+        MakeOfferWithExistingVaultArgs, MatchOffersAccounts, ReplaceOfferAccounts, ReplaceOfferArgs, take_offer_route, TakeOfferRouteAccounts, TakeOfferRouteArgs,
+        TakeOfferAccounts, TakeOfferArgs, TakeOfferWithAuthorizationAccounts, TakeOfferWithAuthorizationArgs,
+        TakeOfferWithTokenAccountsAccounts, TakeOfferWithTokenAccountsArgs, AUTO_ASSIGN_OFFER_ID,
+    },
+    pda::{
+        find_config_address, find_fee_vault_address, find_maker_counter_address, find_mint_deny_entry_address, find_offer_address,
+        find_route_token_account_address, find_taker_authority_address, find_vault_address, find_vault_authority_address,
+    },
+    logs::{parse_log_events, parse_program_error},
+    lookup_table::{build_v0_message, create_escrow_lookup_table, escrow_lookup_table_addresses},
+    offer::{offsets, DutchAuction, Offer, OfferStatus, OFFER_VERSION},
+    program_error::{EscrowErrorCode, ProgramError},
+    MEMO_PROGRAM_ID, PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+#[cfg(feature = "rpc")]
+use crate::client::{inner_instruction_events, OfferFilter};
+#[cfg(feature = "rpc")]
+use solana_transaction_status_client_types::{
+    UiCompiledInstruction, UiInnerInstructions, UiInstruction, UiParsedInstruction, UiPartiallyDecodedInstruction,
+};
+
+/// Builds offer account data the way the program writes it, with the metadata URI after the offer
+fn program_offer_account_data(offer: &ProgramOffer, metadata_uri: &str) -> Vec<u8> {
+    let mut data = ProgramOffer::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(offer));
+    data.extend_from_slice(metadata_uri.as_bytes());
+    data
+}
+
+fn program_offer() -> ProgramOffer {
+    let mut offer = ProgramOffer::zeroed();
+    offer.id = 42;
+    offer.maker = Pubkey::new_unique();
+    offer.token_mint_a = Pubkey::new_unique();
+    offer.token_mint_b = Pubkey::new_unique();
+    offer.token_a_offered_amount = 10;
+    offer.token_b_wanted_amount = 3;
+    offer.maker_authority = offer.maker;
+    offer.rent_payer = Pubkey::new_unique();
+    offer.bump = 254;
+    offer.vault_authority_bump = 253;
+    offer.created_at = 1_500;
+    offer.created_slot = 77;
+    offer.tags = [3, 7, 0, 0, 0, 0, 0, 0];
+    offer.version = escrow::constants::OFFER_VERSION;
+    offer.set_price(3, 10);
+    offer
+}
+
+#[test]
+fn test_offer_layout_matches_program() {
+    assert_eq!(PROGRAM_ID, escrow::ID);
+    assert_eq!(OFFER_VERSION, escrow::constants::OFFER_VERSION);
+    assert_eq!(size_of::<Offer>(), ProgramOffer::INIT_SPACE);
+    assert_eq!(Offer::discriminator(), ProgramOffer::DISCRIMINATOR);
+    assert_eq!(Offer::MAKER_OFFSET, ProgramOffer::MAKER_OFFSET);
+    assert_eq!(Offer::TOKEN_MINT_A_OFFSET, ProgramOffer::TOKEN_MINT_A_OFFSET);
+    assert_eq!(Offer::TOKEN_MINT_B_OFFSET, ProgramOffer::TOKEN_MINT_B_OFFSET);
+    assert_eq!(offsets::MAKER, escrow::state::offsets::MAKER);
+    assert_eq!(offsets::TOKEN_MINT_A, escrow::state::offsets::TOKEN_MINT_A);
+    assert_eq!(offsets::TOKEN_MINT_B, escrow::state::offsets::TOKEN_MINT_B);
+    assert_eq!(offsets::STATUS, escrow::state::offsets::STATUS);
+    assert_eq!(offsets::TAGS, escrow::state::offsets::TAGS);
+    assert_eq!(Offer::SPACE, ProgramOffer::SPACE);
+    assert_eq!(Offer::METADATA_URI_OFFSET, ProgramOffer::METADATA_URI_OFFSET);
+    assert_eq!(Offer::LAYOUT_SIZES, ProgramOffer::LAYOUT_SIZES);
+}
+
+#[test]
+fn test_offer_reads_program_offer_data() {
+    let arbiter = Pubkey::new_unique();
+    let dutch_auction = escrow::state::DutchAuction {
+        start_price: 5,
+        end_price: 3,
+        start_timestamp: 1_000,
+        end_timestamp: 2_000,
+    };
+    let metadata_uri = "https://example.com/offer.json";
+
+    let mut program_offer = program_offer();
+    program_offer.set_expires_at(Some(3_000));
+    program_offer.set_dutch_auction(Some(dutch_auction));
+    program_offer.set_arbiter(Some(arbiter));
+    program_offer.set_memo(Some("OTC deal")).unwrap();
+    program_offer.set_allowlist_enabled(true);
+    program_offer.set_keep_history(true);
+    program_offer.set_status(escrow::state::OfferStatus::Expired);
+    program_offer.set_metadata_uri_length(metadata_uri.len());
+    let data = program_offer_account_data(&program_offer, metadata_uri);
+
+    let offer = Offer::from_account_data(&data).unwrap();
+
+    assert_eq!(offer.id, 42);
+    assert_eq!(offer.maker, program_offer.maker);
+    assert_eq!(offer.token_mint_a, program_offer.token_mint_a);
+    assert_eq!(offer.token_mint_b, program_offer.token_mint_b);
+    assert_eq!(offer.token_a_offered_amount, 10);
+    assert_eq!(offer.token_b_wanted_amount, 3);
+    assert_eq!(offer.rent_payer, program_offer.rent_payer);
+    assert_eq!(offer.bump, 254);
+    assert_eq!(offer.vault_authority_bump, 253);
+    assert_eq!(offer.created_at, 1_500);
+    assert_eq!(offer.created_slot, 77);
+    assert_eq!(offer.tags, [3, 7, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(offer.expires_at(), Some(3_000));
+    assert_eq!(
+        offer.dutch_auction(),
+        Some(DutchAuction {
+            start_price: 5,
+            end_price: 3,
+            start_timestamp: 1_000,
+            end_timestamp: 2_000,
+        })
+    );
+    assert_eq!(offer.english_auction(), None);
+    assert_eq!(offer.arbiter(), Some(arbiter));
+    assert_eq!(offer.refund_authority(), None);
+    assert_eq!(offer.memo(), Some("OTC deal"));
+    assert!(offer.allowlist_enabled());
+    assert!(offer.keep_history());
+    assert_eq!(offer.status(), OfferStatus::Expired);
+    assert_eq!(offer.metadata_uri(&data), Some(metadata_uri));
+    // 3 token b for every 10 token a, rounded up like the program
+    assert_eq!(offer.token_b_amount_for(5), program_offer.token_b_amount_for(5).ok());
+    assert_eq!(offer.token_b_amount_for(5), Some(2));
+}
+
+#[test]
+fn test_offer_refuses_outdated_and_foreign_accounts() {
+    let data = program_offer_account_data(&program_offer(), "");
+
+    // A version 2 offer is the current layout without the price fields
+    let version_2_length = 8 + ProgramOffer::LAYOUT_SIZES[2];
+    let result = Offer::from_account_data(&data[..version_2_length]);
+    assert!(matches!(result, Err(Error::OutdatedOffer(2))));
+
+    let mut foreign_data = data.clone();
+    foreign_data[0] ^= 1;
+    let result = Offer::from_account_data(&foreign_data);
+    assert!(matches!(result, Err(Error::NotAnOffer)));
+}
+
+#[test]
+fn test_config_reads_program_config_data() {
+    let membership_mint = Pubkey::new_unique();
+    let program_config = escrow::state::Config {
+        authority: Pubkey::new_unique(),
+        fee_bps: 30,
+        fee_authority: Pubkey::new_unique(),
+        referral_share_bps: 2_000,
+        paused: true,
+        mint_policy: escrow::state::MintPolicy {
+            allow_freeze_authority: false,
+            ..escrow::state::MintPolicy::default()
+        },
+        royalties_required: true,
+        min_offer_amount: 100,
+        max_offer_amount: 1_000_000,
+        mint_allowlist_enabled: true,
+        denied_mint_count: 2,
+        fee_tiers: vec![escrow::state::FeeTier {
+            min_volume: 1_000_000,
+            fee_bps: 20,
+        }],
+        membership_discount: Some(escrow::state::MembershipDiscount {
+            mint: membership_mint,
+            min_balance: 1,
+            discount_bps: 5_000,
+        }),
+        pauser: Pubkey::new_unique(),
+        fee_manager: Pubkey::new_unique(),
+        allowlist_manager: Pubkey::new_unique(),
+        matcher_share_bps: 2_500,
+        min_offer_age_slots: 10,
+        max_open_offers_per_maker: 16,
+        fee_tier_mint: Pubkey::new_unique(),
+        bump: 255,
+    };
+    // The account is sized for the most fee tiers, so the borsh bytes are followed by zeroes
+    let mut data = Vec::new();
+    program_config.try_serialize(&mut data).unwrap();
+    data.resize(8 + escrow::state::Config::INIT_SPACE, 0);
+
+    let config = decode_config(&data).unwrap();
+
+    assert_eq!(config.authority, program_config.authority);
+    assert_eq!(config.fee_bps, 30);
+    assert_eq!(config.fee_authority, program_config.fee_authority);
+    assert_eq!(config.referral_share_bps, 2_000);
+    assert!(config.paused);
+    assert!(!config.mint_policy.allow_freeze_authority);
+    assert!(config.mint_policy.allow_permanent_delegate);
+    assert!(config.royalties_required);
+    assert_eq!(config.min_offer_amount, 100);
+    assert_eq!(config.max_offer_amount, 1_000_000);
+    assert!(config.mint_allowlist_enabled);
+    assert_eq!(config.denied_mint_count, 2);
+    assert_eq!(
+        config.fee_tiers,
+        vec![FeeTier {
+            min_volume: 1_000_000,
+            fee_bps: 20,
+        }]
+    );
+    assert_eq!(
+        config.membership_discount,
+        Some(MembershipDiscount {
+            mint: membership_mint,
+            min_balance: 1,
+            discount_bps: 5_000,
+        })
+    );
+    assert_eq!(config.pauser, program_config.pauser);
+    assert_eq!(config.fee_manager, program_config.fee_manager);
+    assert_eq!(config.allowlist_manager, program_config.allowlist_manager);
+    assert_eq!(config.matcher_share_bps, 2_500);
+    assert_eq!(config.min_offer_age_slots, 10);
+    assert_eq!(config.max_open_offers_per_maker, 16);
+    assert_eq!(config.fee_tier_mint, program_config.fee_tier_mint);
+    assert_eq!(config.bump, 255);
+    assert!(matches!(decode_account(&data), Ok(EscrowAccount::Config(_))));
+
+    // Cut short, the config can't be read
+    let result = decode_config(&data[..40]);
+    assert!(matches!(result, Err(Error::NotAConfig)));
+}
+
+#[test]
+fn test_decode_account_tells_accounts_apart() {
+    let program_offer = program_offer();
+    let data = program_offer_account_data(&program_offer, "");
+    match decode_account(&data) {
+        Ok(EscrowAccount::Offer(offer)) => assert_eq!(offer.maker, program_offer.maker),
+        result => panic!("Expected an offer, got {result:?}"),
+    }
+
+    let result = decode_config(&data);
+    assert!(matches!(result, Err(Error::NotAConfig)));
+
+    let maker_counter_data = escrow::state::MakerCounter::DISCRIMINATOR.to_vec();
+    let result = decode_account(&maker_counter_data);
+    assert!(matches!(result, Err(Error::UnknownAccount)));
+}
+
+// Whether the RPC node would return the account data for these filters
+#[cfg(feature = "rpc")]
+fn filters_match(filters: &[RpcFilterType], data: &[u8]) -> bool {
+    filters.iter().all(|filter| match filter {
+        RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(data),
+        filter => panic!("Expected only memcmp filters, got {filter:?}"),
+    })
+}
+
+#[cfg(feature = "rpc")]
+#[test]
+fn test_offer_filter_matches_program_offer_data() {
+    let program_offer = program_offer();
+    let data = program_offer_account_data(&program_offer, "");
+    let filter = OfferFilter::open_offers_by_maker_for_pair(
+        program_offer.maker,
+        program_offer.token_mint_a,
+        program_offer.token_mint_b,
+    );
+    assert_eq!(filter.rpc_filters().len(), 5);
+    assert!(filters_match(&filter.rpc_filters(), &data));
+    assert!(filters_match(&OfferFilter::default().rpc_filters(), &data));
+
+    // The pair the other way round is a different market
+    let reversed_filter = OfferFilter {
+        token_mint_a: Some(program_offer.token_mint_b),
+        token_mint_b: Some(program_offer.token_mint_a),
+        ..filter
+    };
+    assert!(!filters_match(&reversed_filter.rpc_filters(), &data));
+
+    let other_maker_filter = OfferFilter {
+        maker: Some(Pubkey::new_unique()),
+        ..filter
+    };
+    assert!(!filters_match(&other_maker_filter.rpc_filters(), &data));
+
+    // An offer kept around after being filled isn't open any more
+    let mut filled_offer = program_offer;
+    filled_offer.set_keep_history(true);
+    filled_offer.set_status(escrow::state::OfferStatus::Filled);
+    let filled_data = program_offer_account_data(&filled_offer, "");
+    assert!(!filters_match(&filter.rpc_filters(), &filled_data));
+    let filled_filter = OfferFilter {
+        status: Some(OfferStatus::Filled),
+        ..filter
+    };
+    assert!(filters_match(&filled_filter.rpc_filters(), &filled_data));
+
+    // Tags left at zero match anything, the rest have to match the offer's tag in the same position
+    let tag_filter = OfferFilter {
+        tags: Some([0, 7, 0, 0, 0, 0, 0, 0]),
+        ..filter
+    };
+    assert_eq!(tag_filter.rpc_filters().len(), 6);
+    assert!(filters_match(&tag_filter.rpc_filters(), &data));
+    let other_tag_filter = OfferFilter {
+        tags: Some([3, 8, 0, 0, 0, 0, 0, 0]),
+        ..filter
+    };
+    assert!(!filters_match(&other_tag_filter.rpc_filters(), &data));
+}
+
+#[test]
+fn test_instruction_data_matches_program() {
+    assert_eq!(
+        instruction_discriminator("make_offer"),
+        escrow::instruction::MakeOffer::DISCRIMINATOR
+    );
+
+    let maker = Pubkey::new_unique();
+    let arbiter = Pubkey::new_unique();
+    let make_offer_instruction = make_offer(
+        &MakeOfferAccounts {
+            maker_authority: maker,
+            maker,
+            payer: maker,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            token_program_b: None,
+            mint_allowlist_enabled: false,
+        },
+        &MakeOfferArgs {
+            id: 7,
+            token_a_offered_amount: 10,
+            token_b_wanted_amount: 3,
+            dutch_auction: Some(DutchAuction {
+                start_price: 5,
+                end_price: 3,
+                start_timestamp: 1_000,
+                end_timestamp: 2_000,
+            }),
+            memo: Some("OTC deal".to_string()),
+            min_fill_amount: 1,
+            vesting_duration: Some(60),
+            arbiter: Some(arbiter),
+            keep_history: true,
+            tags: [3, 7, 0, 0, 0, 0, 0, 0],
+            ..MakeOfferArgs::default()
+        },
+    );
+    let program_data = escrow::instruction::MakeOffer {
+        id: 7,
+        token_a_offered_amount: 10,
+        token_b_wanted_amount: 3,
+        dutch_auction: Some(escrow::state::DutchAuction {
+            start_price: 5,
+            end_price: 3,
+            start_timestamp: 1_000,
+            end_timestamp: 2_000,
+        }),
+        english_auction: None,
+        additional_token_a_offered_amounts: vec![],
+        memo: Some("OTC deal".to_string()),
+        min_fill_amount: 1,
+        hashlock: None,
+        vesting_duration: Some(60),
+        arbiter: Some(arbiter),
+        keep_history: true,
+        tags: [3, 7, 0, 0, 0, 0, 0, 0],
+    }
+    .data();
+    assert_eq!(make_offer_instruction.data, program_data);
+    assert_eq!(make_offer_instruction.program_id, PROGRAM_ID);
+
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let take_offer_instruction = take_offer(
+        &TakeOfferAccounts {
+            taker: Pubkey::new_unique(),
+            offer: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            token_program_b: None,
+            referrer_token_account: None,
+            token_mint_a_metadata: None,
+            membership_token_account: None,
+            recipient: None,
+        },
+        &offer,
+        &TakeOfferArgs {
+            expected_token_a_amount: 10,
+            expected_token_b_amount: 3,
+            preimage: Some(vec![1, 2, 3]),
+            memo: Some("Invoice 2024-117".to_string()),
+        },
+    );
+    let program_data = escrow::instruction::TakeOffer {
+        expected_token_a_amount: 10,
+        expected_token_b_amount: 3,
+        preimage: Some(vec![1, 2, 3]),
+        memo: Some("Invoice 2024-117".to_string()),
+    }
+    .data();
+    assert_eq!(take_offer_instruction.data, program_data);
+    // The offer's rent goes back to whoever paid it
+    assert_eq!(take_offer_instruction.accounts[5].pubkey, program_offer.rent_payer);
+    // The memo program follows the referrer, since there's a memo
+    assert_eq!(take_offer_instruction.accounts[21].pubkey, MEMO_PROGRAM_ID);
+}
+
+#[test]
+fn test_take_offer_passes_the_recipient_and_their_token_account() {
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let recipient = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+    let take_offer_instruction = take_offer(
+        &TakeOfferAccounts {
+            taker: Pubkey::new_unique(),
+            offer: Pubkey::new_unique(),
+            token_program,
+            token_program_b: None,
+            referrer_token_account: None,
+            token_mint_a_metadata: None,
+            membership_token_account: None,
+            recipient: Some(recipient),
+        },
+        &offer,
+        &TakeOfferArgs::default(),
+    );
+
+    // The recipient and their token a account come just before the event authority and the program
+    let recipient_accounts = &take_offer_instruction.accounts[take_offer_instruction.accounts.len() - 4..][..2];
+    assert_eq!(recipient_accounts[0].pubkey, recipient);
+    assert_eq!(
+        recipient_accounts[1].pubkey,
+        get_associated_token_address_with_program_id(&recipient, &program_offer.token_mint_a, &token_program)
+    );
+    assert!(recipient_accounts[1].is_writable);
+    // The taker's own token a account is left out, so they don't pay to create it
+    assert_eq!(take_offer_instruction.accounts[8].pubkey, PROGRAM_ID);
+}
+
+#[test]
+fn test_make_offer_with_assigned_id_matches_program() {
+    assert_eq!(AUTO_ASSIGN_OFFER_ID, escrow::constants::AUTO_ASSIGN_OFFER_ID);
+
+    let maker = Pubkey::new_unique();
+    let make_offer_instruction = make_offer_with_assigned_id(
+        &MakeOfferAccounts {
+            maker_authority: maker,
+            maker,
+            payer: maker,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            token_program_b: None,
+            mint_allowlist_enabled: false,
+        },
+        &MakeOfferArgs {
+            id: 7,
+            token_a_offered_amount: 10,
+            token_b_wanted_amount: 3,
+            ..MakeOfferArgs::default()
+        },
+        4,
+    );
+
+    // The program is asked to assign the id, and the offer's address uses the id it will assign
+    let program_data = escrow::instruction::MakeOffer {
+        id: AUTO_ASSIGN_OFFER_ID,
+        token_a_offered_amount: 10,
+        token_b_wanted_amount: 3,
+        dutch_auction: None,
+        english_auction: None,
+        additional_token_a_offered_amounts: vec![],
+        memo: None,
+        min_fill_amount: 0,
+        hashlock: None,
+        vesting_duration: None,
+        arbiter: None,
+        keep_history: false,
+        tags: [0; 8],
+    }
+    .data();
+    assert_eq!(make_offer_instruction.data, program_data);
+    assert_eq!(make_offer_instruction.accounts[9].pubkey, find_maker_counter_address(&maker).0);
+    assert_eq!(make_offer_instruction.accounts[10].pubkey, find_offer_address(&maker, 4).0);
+}
+
+#[test]
+fn test_delegated_offers_use_the_makers_token_account() {
+    let maker = Pubkey::new_unique();
+    let token_mint_a = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+    let maker_token_account_a =
+        get_associated_token_address_with_program_id(&maker, &token_mint_a, &token_program);
+
+    let make_offer_instruction = make_offer_delegated(
+        &MakeOfferDelegatedAccounts {
+            maker,
+            token_mint_a,
+            token_mint_b: Pubkey::new_unique(),
+            token_program,
+            token_program_b: None,
+            mint_allowlist_enabled: false,
+        },
+        &MakeOfferDelegatedArgs {
+            id: 7,
+            token_a_offered_amount: 10,
+            token_b_wanted_amount: 3,
+            tags: [3, 7, 0, 0, 0, 0, 0, 0],
+        },
+    );
+    let program_data = escrow::instruction::MakeOfferDelegated {
+        id: 7,
+        token_a_offered_amount: 10,
+        token_b_wanted_amount: 3,
+        tags: [3, 7, 0, 0, 0, 0, 0, 0],
+    }
+    .data();
+    assert_eq!(make_offer_instruction.data, program_data);
+    assert_eq!(make_offer_instruction.accounts[5].pubkey, maker_token_account_a);
+    assert_eq!(make_offer_instruction.accounts[7].pubkey, find_offer_address(&maker, 7).0);
+    // The vault authority is approved as the delegate, not the offer
+    assert_eq!(
+        make_offer_instruction.accounts[8].pubkey,
+        find_vault_authority_address(&find_offer_address(&maker, 7).0).0
+    );
+    // The mints' deny entries are always passed, whether or not any mints are denied
+    assert_eq!(make_offer_instruction.accounts[15].pubkey, find_mint_deny_entry_address(&token_mint_a).0);
+
+    // Taking a delegated offer pulls from the maker's token account rather than a vault
+    let mut program_offer = program_offer();
+    program_offer.maker = maker;
+    program_offer.token_mint_a = token_mint_a;
+    program_offer.set_delegated(true);
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    assert!(offer.is_delegated());
+    let take_offer_instruction = take_offer(
+        &TakeOfferAccounts {
+            taker: Pubkey::new_unique(),
+            offer: find_offer_address(&maker, 7).0,
+            token_program,
+            token_program_b: None,
+            referrer_token_account: None,
+            token_mint_a_metadata: None,
+            membership_token_account: None,
+            recipient: None,
+        },
+        &offer,
+        &TakeOfferArgs {
+            expected_token_a_amount: 10,
+            expected_token_b_amount: 3,
+            ..TakeOfferArgs::default()
+        },
+    );
+    assert_eq!(take_offer_instruction.accounts[13].pubkey, maker_token_account_a);
+}
+
+#[test]
+fn test_make_offer_with_existing_vault_passes_the_offers_vault() {
+    let maker = Pubkey::new_unique();
+    let token_mint_a = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+
+    let make_offer_instruction = make_offer_with_existing_vault(
+        &MakeOfferWithExistingVaultAccounts {
+            maker,
+            token_mint_a,
+            token_mint_b: Pubkey::new_unique(),
+            token_program,
+            token_program_b: None,
+            mint_allowlist_enabled: false,
+        },
+        &MakeOfferWithExistingVaultArgs {
+            id: 7,
+            token_a_offered_amount: 10,
+            token_b_wanted_amount: 3,
+            tags: [3, 7, 0, 0, 0, 0, 0, 0],
+        },
+    );
+    let program_data = escrow::instruction::MakeOfferWithExistingVault {
+        id: 7,
+        token_a_offered_amount: 10,
+        token_b_wanted_amount: 3,
+        tags: [3, 7, 0, 0, 0, 0, 0, 0],
+    }
+    .data();
+    assert_eq!(make_offer_instruction.data, program_data);
+    // The maker signs, but no maker token account is passed, the vault is already funded
+    assert!(make_offer_instruction.accounts[2].is_signer);
+    assert_eq!(make_offer_instruction.accounts[6].pubkey, find_offer_address(&maker, 7).0);
+    assert_eq!(
+        make_offer_instruction.accounts[8].pubkey,
+        find_vault_address(&find_offer_address(&maker, 7).0, &token_mint_a, &token_program)
+    );
+    assert!(!make_offer_instruction.accounts[8].is_writable);
+}
+
+#[test]
+fn test_take_offer_with_token_accounts_uses_the_given_token_accounts() {
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let taker_token_account_a = Pubkey::new_unique();
+    let taker_token_account_b = Pubkey::new_unique();
+
+    let take_offer_instruction = take_offer_with_token_accounts(
+        &TakeOfferWithTokenAccountsAccounts {
+            taker: Pubkey::new_unique(),
+            offer: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            token_program_b: None,
+            taker_token_account_a,
+            taker_token_account_b,
+        },
+        &offer,
+        &TakeOfferWithTokenAccountsArgs {
+            expected_token_a_amount: 10,
+            expected_token_b_amount: 3,
+        },
+    );
+    let program_data = escrow::instruction::TakeOfferWithTokenAccounts {
+        expected_token_a_amount: 10,
+        expected_token_b_amount: 3,
+    }
+    .data();
+    assert_eq!(take_offer_instruction.data, program_data);
+    assert_eq!(take_offer_instruction.accounts[8].pubkey, taker_token_account_a);
+    assert_eq!(take_offer_instruction.accounts[9].pubkey, taker_token_account_b);
+    assert_eq!(take_offer_instruction.accounts.len(), 25);
+}
+
+#[test]
+fn test_take_offer_with_authorization_is_signed_by_the_relayer_not_the_taker() {
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let relayer = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let offer_address = Pubkey::new_unique();
+    let args = TakeOfferWithAuthorizationArgs {
+        expected_token_a_amount: 10,
+        expected_token_b_amount: 3,
+        authorization_expires_at: 2_000,
+    };
+
+    let take_offer_instruction = take_offer_with_authorization(
+        &TakeOfferWithAuthorizationAccounts {
+            relayer,
+            taker,
+            offer: offer_address,
+            token_program: Pubkey::new_unique(),
+            token_program_b: None,
+            taker_token_account_b: Pubkey::new_unique(),
+        },
+        &offer,
+        &args,
+    );
+    let program_data = escrow::instruction::TakeOfferWithAuthorization {
+        expected_token_a_amount: 10,
+        expected_token_b_amount: 3,
+        authorization_expires_at: 2_000,
+    }
+    .data();
+    assert_eq!(take_offer_instruction.data, program_data);
+    let signers: Vec<Pubkey> = take_offer_instruction
+        .accounts
+        .iter()
+        .filter(|account| account.is_signer)
+        .map(|account| account.pubkey)
+        .collect();
+    assert_eq!(signers, vec![relayer]);
+    assert_eq!(take_offer_instruction.accounts[4].pubkey, taker);
+    assert_eq!(take_offer_instruction.accounts[11].pubkey, find_taker_authority_address(&taker).0);
+    assert_eq!(take_offer_instruction.accounts.len(), 28);
+
+    // The taker signs the offer's address, mints and created slot, then the amounts and expiry, each little endian
+    let message = taker_authorization_message(&offer_address, &offer, &args);
+    assert_eq!(&message[..32], offer_address.as_ref());
+    assert_eq!(&message[32..64], program_offer.token_mint_a.as_ref());
+    assert_eq!(&message[64..96], program_offer.token_mint_b.as_ref());
+    assert_eq!(&message[96..104], &program_offer.created_slot.to_le_bytes());
+    assert_eq!(&message[104..112], &10u64.to_le_bytes());
+    assert_eq!(&message[120..], &2_000i64.to_le_bytes());
+}
+
+#[test]
+fn test_attestation_message_covers_the_offer_and_taker() {
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let offer_address = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+
+    // The attestor signs the offer's address and created slot, the taker, then the offer's mints and amounts
+    let message = attestation_message(&offer_address, &offer, &taker);
+    assert_eq!(&message[..32], offer_address.as_ref());
+    assert_eq!(&message[32..40], &program_offer.created_slot.to_le_bytes());
+    assert_eq!(&message[40..72], taker.as_ref());
+    assert_eq!(&message[72..104], program_offer.token_mint_a.as_ref());
+    assert_eq!(&message[104..136], program_offer.token_mint_b.as_ref());
+    assert_eq!(&message[136..144], &program_offer.token_a_offered_amount.to_le_bytes());
+    assert_eq!(&message[144..], &program_offer.token_b_wanted_amount.to_le_bytes());
+}
+
+#[test]
+fn test_match_offers_pays_each_maker_from_the_other_offers_vault() {
+    let first_program_offer = program_offer();
+    let mut second_program_offer = program_offer();
+    second_program_offer.maker = Pubkey::new_unique();
+    second_program_offer.rent_payer = second_program_offer.maker;
+    second_program_offer.token_mint_a = first_program_offer.token_mint_b;
+    second_program_offer.token_mint_b = first_program_offer.token_mint_a;
+    let first_offer = Offer::from_account_data(&program_offer_account_data(&first_program_offer, "")).unwrap();
+    let second_offer = Offer::from_account_data(&program_offer_account_data(&second_program_offer, "")).unwrap();
+    let matcher = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+    let second_offer_address = Pubkey::new_unique();
+
+    let match_instruction = match_offers(
+        &MatchOffersAccounts {
+            matcher,
+            first_offer: Pubkey::new_unique(),
+            second_offer: second_offer_address,
+            token_program,
+            token_program_b: None,
+            matcher_token_account_a: Pubkey::new_unique(),
+            matcher_token_account_b: Pubkey::new_unique(),
+        },
+        &first_offer,
+        &second_offer,
+    );
+    assert_eq!(match_instruction.data, escrow::instruction::MatchOffers {}.data());
+    assert!(match_instruction.accounts[3].is_signer);
+    assert_eq!(match_instruction.accounts[3].pubkey, matcher);
+    // The second offer's vault holds token b
+    assert_eq!(
+        match_instruction.accounts[15].pubkey,
+        get_associated_token_address_with_program_id(
+            &find_vault_authority_address(&second_offer_address).0,
+            &first_offer.token_mint_b,
+            &token_program,
+        )
+    );
+    // The second maker gets token a
+    assert_eq!(
+        match_instruction.accounts[18].pubkey,
+        get_associated_token_address_with_program_id(&second_offer.maker, &first_offer.token_mint_a, &token_program)
+    );
+    // Token b's program is always passed, since token b's fee vault belongs to it
+    assert_eq!(match_instruction.accounts[30].pubkey, token_program);
+    assert_eq!(match_instruction.accounts[32].pubkey, find_fee_vault_address(&first_offer.token_mint_b).0);
+    assert_eq!(match_instruction.accounts.len(), 35);
+}
+
+#[test]
+fn test_take_offer_route_passes_the_taker_through_both_offers() {
+    let first_program_offer = program_offer();
+    let mut second_program_offer = program_offer();
+    second_program_offer.maker = Pubkey::new_unique();
+    second_program_offer.rent_payer = second_program_offer.maker;
+    second_program_offer.token_mint_a = first_program_offer.token_mint_b;
+    second_program_offer.token_mint_b = Pubkey::new_unique();
+    let first_offer = Offer::from_account_data(&program_offer_account_data(&first_program_offer, "")).unwrap();
+    let second_offer = Offer::from_account_data(&program_offer_account_data(&second_program_offer, "")).unwrap();
+    let taker = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+
+    let route_instruction = take_offer_route(
+        &TakeOfferRouteAccounts {
+            taker,
+            first_offer: Pubkey::new_unique(),
+            second_offer: Pubkey::new_unique(),
+            token_program,
+            token_program_b: None,
+            token_program_c: None,
+        },
+        &first_offer,
+        &second_offer,
+        &TakeOfferRouteArgs {
+            expected_token_a_amount: 10,
+            maximum_token_c_amount: 7,
+        },
+    );
+    assert_eq!(
+        route_instruction.data,
+        escrow::instruction::TakeOfferRoute {
+            expected_token_a_amount: 10,
+            maximum_token_c_amount: 7,
+        }
+        .data()
+    );
+    assert!(route_instruction.accounts[5].is_signer);
+    assert_eq!(route_instruction.accounts[5].pubkey, taker);
+    // The transient account holds token b between the two offers
+    assert_eq!(
+        route_instruction.accounts[20].pubkey,
+        find_route_token_account_address(&taker, &first_offer.token_mint_b).0
+    );
+    // The taker pays with token c, which the second offer wants
+    assert_eq!(
+        route_instruction.accounts[23].pubkey,
+        get_associated_token_address_with_program_id(&taker, &second_offer.token_mint_b, &token_program)
+    );
+    // The token b left over between the offers pays its protocol fee into token b's fee vault
+    assert_eq!(route_instruction.accounts[33].pubkey, find_fee_vault_address(&first_offer.token_mint_b).0);
+    assert_eq!(route_instruction.accounts.len(), 39);
+}
+
+#[test]
+fn test_replace_offer_refunds_the_old_vault_into_the_new_offers_vault() {
+    let program_offer = program_offer();
+    let offer = Offer::from_account_data(&program_offer_account_data(&program_offer, "")).unwrap();
+    let offer_address = find_offer_address(&offer.maker, offer.id).0;
+    let token_program = Pubkey::new_unique();
+    let args = ReplaceOfferArgs {
+        new_id: 43,
+        token_a_offered_amount: 12,
+        token_b_wanted_amount: 4,
+        expires_at: Some(2_000),
+    };
+
+    let replace_instruction = replace_offer(
+        &ReplaceOfferAccounts {
+            token_mint_b: offer.token_mint_b,
+            token_program,
+            token_program_b: None,
+            mint_allowlist_enabled: false,
+        },
+        &offer_address,
+        &offer,
+        &args,
+    );
+    let program_data = escrow::instruction::ReplaceOffer {
+        new_id: 43,
+        token_a_offered_amount: 12,
+        token_b_wanted_amount: 4,
+        expires_at: Some(2_000),
+    }
+    .data();
+    assert_eq!(replace_instruction.data, program_data);
+    assert!(replace_instruction.accounts[3].is_signer);
+    assert_eq!(replace_instruction.accounts[3].pubkey, offer.maker);
+    // The old offer's rent goes back to whoever paid it
+    assert_eq!(replace_instruction.accounts[4].pubkey, offer.rent_payer);
+    assert_eq!(
+        replace_instruction.accounts[10].pubkey,
+        find_vault_address(&offer_address, &offer.token_mint_a, &token_program)
+    );
+    let new_offer_address = find_offer_address(&offer.maker, 43).0;
+    assert_eq!(replace_instruction.accounts[12].pubkey, new_offer_address);
+    assert_eq!(
+        replace_instruction.accounts[14].pubkey,
+        find_vault_address(&new_offer_address, &offer.token_mint_a, &token_program)
+    );
+    assert_eq!(replace_instruction.accounts.len(), 27);
+}
+
+#[test]
+fn test_v0_messages_read_the_shared_accounts_from_the_lookup_table() {
+    let maker = Pubkey::new_unique();
+    let token_mint_a = Pubkey::new_unique();
+    let token_mint_b = Pubkey::new_unique();
+    let lookup_table_addresses = escrow_lookup_table_addresses(&[token_mint_a, token_mint_b]);
+    assert_eq!(lookup_table_addresses.len(), 10 + 2 * 3);
+
+    // Every address fits in a single extend, after the create
+    let (instructions, lookup_table) = create_escrow_lookup_table(&maker, &maker, 100, &[token_mint_a, token_mint_b]);
+    assert_eq!(instructions.len(), 2);
+
+    let make_offer_instructions: Vec<_> = (0..3)
+        .map(|id| {
+            make_offer(
+                &MakeOfferAccounts {
+                    maker_authority: maker,
+                    maker,
+                    payer: maker,
+                    token_mint_a,
+                    token_mint_b,
+                    token_program: TOKEN_PROGRAM_ID,
+                    token_program_b: None,
+                    mint_allowlist_enabled: false,
+                },
+                &MakeOfferArgs {
+                    id,
+                    token_a_offered_amount: 10,
+                    token_b_wanted_amount: 3,
+                    ..MakeOfferArgs::default()
+                },
+            )
+        })
+        .collect();
+    let message = build_v0_message(
+        &maker,
+        &make_offer_instructions,
+        &[solana_message::AddressLookupTableAccount {
+            key: lookup_table,
+            addresses: lookup_table_addresses,
+        }],
+        solana_hash::Hash::default(),
+    )
+    .unwrap();
+
+    // The config is only read, so it comes from the table, the escrow program is called so it's listed in full
+    assert_eq!(message.address_table_lookups.len(), 1);
+    assert!(!message.account_keys.contains(&find_config_address().0));
+    assert!(message.account_keys.contains(&PROGRAM_ID));
+}
+
+fn program_offer_taken() -> escrow::events::OfferTaken {
+    escrow::events::OfferTaken {
+        offer_id: 42,
+        maker: Pubkey::new_unique(),
+        taker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_amount: 10,
+        token_b_amount: 3,
+        maker_token_account_b_created: true,
+        created_at: 1_500,
+        filled_at: 2_500,
+        recipient: Pubkey::new_unique(),
+    }
+}
+
+#[test]
+fn test_events_read_program_event_data() {
+    assert_eq!(EVENT_IX_TAG_LE, anchor_lang::event::EVENT_IX_TAG_LE);
+
+    let program_event = program_offer_taken();
+    let expected_event = EscrowEvent::OfferTaken(OfferTaken {
+        offer_id: 42,
+        maker: program_event.maker,
+        taker: program_event.taker,
+        token_mint_a: program_event.token_mint_a,
+        token_mint_b: program_event.token_mint_b,
+        token_a_amount: 10,
+        token_b_amount: 3,
+        maker_token_account_b_created: true,
+        created_at: 1_500,
+        filled_at: 2_500,
+        recipient: program_event.recipient,
+    });
+    assert_eq!(
+        EscrowEvent::from_event_data(&program_event.data()),
+        Some(expected_event.clone())
+    );
+
+    // emit_cpi! puts the event tag in front of the event data
+    let cpi_instruction_data = [EVENT_IX_TAG_LE.as_slice(), &program_event.data()].concat();
+    assert_eq!(
+        EscrowEvent::from_cpi_instruction_data(&cpi_instruction_data),
+        Some(expected_event)
+    );
+    assert_eq!(EscrowEvent::from_cpi_instruction_data(&program_event.data()), None);
+
+    let program_refunded_event = escrow::events::OfferRefunded {
+        offer_id: 7,
+        maker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_amount: 10,
+        created_at: 1_500,
+        refunded_at: 3_000,
+    };
+    assert!(matches!(
+        EscrowEvent::from_event_data(&program_refunded_event.data()),
+        Some(EscrowEvent::OfferRefunded(OfferRefunded { offer_id: 7, .. }))
+    ));
+
+    let program_made_event = escrow::events::OfferMade {
+        offer_id: 9,
+        maker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_offered_amount: 10,
+        token_b_wanted_amount: 3,
+        created_at: 1_500,
+        tags: [3, 7, 0, 0, 0, 0, 0, 0],
+    };
+    assert!(matches!(
+        EscrowEvent::from_event_data(&program_made_event.data()),
+        Some(EscrowEvent::OfferMade(OfferMade { offer_id: 9, tags: [3, 7, 0, 0, 0, 0, 0, 0], .. }))
+    ));
+}
+
+#[test]
+fn test_parse_log_events_reads_only_the_escrow_programs_events() {
+    let program_event = program_offer_taken();
+    let other_program = Pubkey::new_unique();
+    let event_log = format!("Program data: {}", STANDARD.encode(program_event.data()));
+    let logs = vec![
+        format!("Program {other_program} invoke [1]"),
+        // The same event data written by another program doesn't count
+        event_log.clone(),
+        format!("Program {PROGRAM_ID} invoke [2]"),
+        "Program log: Instruction: TakeOffer".to_string(),
+        format!("Program {other_program} invoke [3]"),
+        event_log.clone(),
+        format!("Program {other_program} success"),
+        event_log,
+        format!("Program {PROGRAM_ID} consumed 50000 of 200000 compute units"),
+        format!("Program {PROGRAM_ID} success"),
+        format!("Program {other_program} success"),
+    ];
+
+    let events = parse_log_events(&logs);
+    assert_eq!(events.len(), 1);
+    assert!(matches!(&events[0], EscrowEvent::OfferTaken(event) if event.taker == program_event.taker));
+}
+
+#[cfg(feature = "rpc")]
+#[test]
+fn test_simulations_read_events_from_the_escrow_programs_inner_instructions() {
+    let program_event = program_offer_taken();
+    let cpi_instruction_data =
+        bs58::encode([EVENT_IX_TAG_LE.as_slice(), &program_event.data()].concat()).into_string();
+    let other_program = Pubkey::new_unique();
+    let account_keys = [Pubkey::new_unique(), PROGRAM_ID, other_program];
+    let compiled = |program_id_index: u8| {
+        UiInstruction::Compiled(UiCompiledInstruction {
+            program_id_index,
+            accounts: vec![],
+            data: cpi_instruction_data.clone(),
+            stack_height: Some(2),
+        })
+    };
+    let inner_instructions = [UiInnerInstructions {
+        index: 0,
+        instructions: vec![
+            compiled(1),
+            // The same data sent to another program isn't an event
+            compiled(2),
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+                program_id: PROGRAM_ID.to_string(),
+                accounts: vec![],
+                data: cpi_instruction_data.clone(),
+                stack_height: Some(2),
+            })),
+        ],
+    }];
+
+    let events = inner_instruction_events(&inner_instructions, &account_keys);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|event| matches!(event, EscrowEvent::OfferTaken(event) if event.taker == program_event.taker)));
+}
+
+#[test]
+fn test_error_codes_match_program() {
+    use escrow::error::ErrorCode as ProgramErrorCode;
+
+    for (index, code) in EscrowErrorCode::ALL.iter().enumerate() {
+        assert_eq!(code.number(), 6000 + index as u32);
+        assert_eq!(EscrowErrorCode::from_number(code.number()), Some(*code));
+    }
+    assert_eq!(EscrowErrorCode::from_number(2006), None);
+    assert_eq!(EscrowErrorCode::from_number(6000 + EscrowErrorCode::ALL.len() as u32), None);
+
+    let program_codes = [
+        (EscrowErrorCode::InsufficientMakerBalance, ProgramErrorCode::InsufficientMakerBalance),
+        (EscrowErrorCode::OfferExpired, ProgramErrorCode::OfferExpired),
+        (EscrowErrorCode::InvalidVault, ProgramErrorCode::InvalidVault),
+        (EscrowErrorCode::SettledAmountMismatch, ProgramErrorCode::SettledAmountMismatch),
+        (EscrowErrorCode::TemplateOfferStillOpen, ProgramErrorCode::TemplateOfferStillOpen),
+        (EscrowErrorCode::InstallmentNotOverdue, ProgramErrorCode::InstallmentNotOverdue),
+        (EscrowErrorCode::InvalidAttestation, ProgramErrorCode::InvalidAttestation),
+        (EscrowErrorCode::InvalidEthSignature, ProgramErrorCode::InvalidEthSignature),
+        (EscrowErrorCode::InvalidTakerAuthorization, ProgramErrorCode::InvalidTakerAuthorization),
+        (EscrowErrorCode::OffersDontMatch, ProgramErrorCode::OffersDontMatch),
+        (EscrowErrorCode::QuoteStillFirm, ProgramErrorCode::QuoteStillFirm),
+        (EscrowErrorCode::RouteLegsDontMatch, ProgramErrorCode::RouteLegsDontMatch),
+    ];
+    for (code, program_code) in program_codes {
+        assert_eq!(code.number(), u32::from(program_code));
+        assert_eq!(format!("{code:?}"), format!("{program_code:?}"));
+        assert_eq!(code.message(), program_code.to_string());
+    }
+}
+
+#[test]
+fn test_parse_program_error_reads_anchor_errors() {
+    let logs = vec![
+        format!("Program {PROGRAM_ID} invoke [1]"),
+        "Program log: Instruction: TakeOffer".to_string(),
+        "Program log: AnchorError thrown in programs/escrow/src/handlers/take_offer.rs:120. Error Code: OfferExpired. Error Number: 6008. Error Message: Offer has expired.".to_string(),
+        format!("Program {PROGRAM_ID} consumed 20000 of 200000 compute units"),
+        format!("Program {PROGRAM_ID} failed: custom program error: 0x1778"),
+    ];
+    assert_eq!(
+        parse_program_error(&logs),
+        Some(ProgramError {
+            number: 6008,
+            code: Some(EscrowErrorCode::OfferExpired),
+            name: Some("OfferExpired".to_string()),
+            message: Some("Offer has expired".to_string()),
+        })
+    );
+
+    // Anchor's own errors have a name and message, but aren't escrow error codes
+    let logs = vec![
+        format!("Program {PROGRAM_ID} invoke [1]"),
+        "Program log: AnchorError caused by account: offer. Error Code: ConstraintSeeds. Error Number: 2006. Error Message: A seeds constraint was violated.".to_string(),
+        format!("Program {PROGRAM_ID} failed: custom program error: 0x7d6"),
+    ];
+    let program_error = parse_program_error(&logs).unwrap();
+    assert_eq!(program_error.number, 2006);
+    assert_eq!(program_error.code, None);
+    assert_eq!(program_error.name.as_deref(), Some("ConstraintSeeds"));
+
+    // An error from a program the escrow program called isn't the escrow program's, even with the same number
+    let other_program = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {PROGRAM_ID} invoke [1]"),
+        format!("Program {other_program} invoke [2]"),
+        "Program log: AnchorError occurred. Error Code: Other. Error Number: 6008. Error Message: Something else.".to_string(),
+        format!("Program {other_program} failed: custom program error: 0x1778"),
+        format!("Program {PROGRAM_ID} failed: custom program error: 0x1778"),
+    ];
+    let program_error = parse_program_error(&logs).unwrap();
+    assert_eq!(program_error.number, 6008);
+    assert_eq!(program_error.code, None);
+    assert_eq!(program_error.name, None);
+
+    // Transactions that succeeded, or failed some other way, have no program error
+    let logs = vec![
+        format!("Program {PROGRAM_ID} invoke [1]"),
+        format!("Program {PROGRAM_ID} failed: exceeded CUs meter at BPF instruction"),
+    ];
+    assert_eq!(parse_program_error(&logs), None);
+}
@@ -0,0 +1,701 @@
+use anchor_lang::{
+    prelude::*, solana_program::instruction::Instruction, system_program, InstructionData,
+    ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, ID as ASSOCIATED_TOKEN_PROGRAM_ID},
+    memo::ID as MEMO_PROGRAM_ID,
+};
+
+use crate::pda::{
+    find_allowlist_address, find_config_address, find_event_authority_address,
+    find_fee_vault_address, find_maker_counter_address, find_maker_index_address,
+    find_maker_stats_address, find_mint_allowlist_entry_address, find_mint_deny_entry_address,
+    find_offer_address, find_registry_address, find_route_authority_address,
+    find_route_token_account_address, find_taker_authority_address, find_vault_address,
+    find_vault_authority_address,
+};
+use escrow::{accounts, constants::AUTO_ASSIGN_OFFER_ID, instruction, state::Offer, ID};
+
+// The accounts that change from one offer to the next, every other account is derived from these
+pub struct MakeOfferAccounts {
+    // Signs for the maker: either the maker's own wallet, or a delegate the maker approved on their token a account
+    pub maker_authority: Pubkey,
+    pub maker: Pubkey,
+    // Pays the rent for the offer, and gets it back when the offer is closed
+    pub payer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer instruction, the offer's address comes from the maker and the id in args
+// Bundle offers also need [mint, maker token account, vault] appended for each additional mint
+pub fn make_offer(accounts: &MakeOfferAccounts, args: instruction::MakeOffer) -> Instruction {
+    let id = args.id;
+    build_make_offer(accounts, args, id)
+}
+
+// Build a make_offer instruction that has the program assign the maker's next offer id from their counter
+// The offer's address comes from next_offer_id, the counter's next_id, so it fails if another offer takes that id first
+pub fn make_offer_with_assigned_id(
+    accounts: &MakeOfferAccounts,
+    args: instruction::MakeOffer,
+    next_offer_id: u64,
+) -> Instruction {
+    let args = instruction::MakeOffer {
+        id: AUTO_ASSIGN_OFFER_ID,
+        ..args
+    };
+    build_make_offer(accounts, args, next_offer_id)
+}
+
+fn build_make_offer(
+    accounts: &MakeOfferAccounts,
+    args: instruction::MakeOffer,
+    offer_id: u64,
+) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, offer_id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+
+    let account_metas = accounts::MakeOffer {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        maker_authority: accounts.maker_authority,
+        maker: accounts.maker,
+        payer: accounts.payer,
+        token_mint_a: accounts.token_mint_a,
+        token_mint_b: accounts.token_mint_b,
+        maker_token_account_a: get_associated_token_address_with_program_id(
+            &accounts.maker,
+            &accounts.token_mint_a,
+            &accounts.token_program,
+        ),
+        maker_counter: find_maker_counter_address(&accounts.maker).0,
+        offer,
+        vault_authority: find_vault_authority_address(&offer).0,
+        vault: find_vault_address(&offer, &accounts.token_mint_a, &accounts.token_program),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&accounts.maker).0,
+        maker_stats: find_maker_stats_address(&accounts.maker).0,
+        token_mint_a_metadata: None,
+        token_mint_a_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_a),
+        token_mint_b_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_b),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_b).0),
+        token_program_b: accounts.token_program_b,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts for a delegated offer, which the maker signs and pays for themselves
+pub struct MakeOfferDelegatedAccounts {
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer_delegated instruction, which approves the offer's vault authority to spend from the maker's token a account
+// rather than moving the tokens to a vault
+pub fn make_offer_delegated(
+    accounts: &MakeOfferDelegatedAccounts,
+    args: instruction::MakeOfferDelegated,
+) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, args.id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+
+    let account_metas = accounts::MakeOfferDelegated {
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        maker: accounts.maker,
+        token_mint_a: accounts.token_mint_a,
+        token_mint_b: accounts.token_mint_b,
+        maker_token_account_a: get_associated_token_address_with_program_id(
+            &accounts.maker,
+            &accounts.token_mint_a,
+            &accounts.token_program,
+        ),
+        maker_counter: find_maker_counter_address(&accounts.maker).0,
+        offer,
+        vault_authority: find_vault_authority_address(&offer).0,
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&accounts.maker).0,
+        maker_stats: find_maker_stats_address(&accounts.maker).0,
+        token_mint_a_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_a),
+        token_mint_b_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_b),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_b).0),
+        token_program_b: accounts.token_program_b,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts for an offer whose vault was funded before the offer was made, which the maker signs and pays for
+// Programs fund find_vault_address(&find_offer_address(&maker, id).0, ...) with their own CPI first
+pub struct MakeOfferWithExistingVaultAccounts {
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Whether the config's mint allowlist is enabled, so both mints' allowlist entries are passed
+    pub mint_allowlist_enabled: bool,
+}
+
+// Build a make_offer_with_existing_vault instruction, for an offer whose vault already holds the offered tokens
+pub fn make_offer_with_existing_vault(
+    accounts: &MakeOfferWithExistingVaultAccounts,
+    args: instruction::MakeOfferWithExistingVault,
+) -> Instruction {
+    let offer = find_offer_address(&accounts.maker, args.id).0;
+    let mint_allowlist_entry = |mint: &Pubkey| {
+        accounts
+            .mint_allowlist_enabled
+            .then(|| find_mint_allowlist_entry_address(mint).0)
+    };
+
+    let account_metas = accounts::MakeOfferWithExistingVault {
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        maker: accounts.maker,
+        token_mint_a: accounts.token_mint_a,
+        token_mint_b: accounts.token_mint_b,
+        maker_counter: find_maker_counter_address(&accounts.maker).0,
+        offer,
+        vault_authority: find_vault_authority_address(&offer).0,
+        vault: find_vault_address(&offer, &accounts.token_mint_a, &accounts.token_program),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&accounts.maker).0,
+        maker_stats: find_maker_stats_address(&accounts.maker).0,
+        token_mint_a_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_a),
+        token_mint_b_allowlist_entry: mint_allowlist_entry(&accounts.token_mint_b),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&accounts.token_mint_b).0),
+        token_program_b: accounts.token_program_b,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts that change from one take to the next, the rest are read from the offer or derived
+pub struct TakeOfferAccounts {
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // The token a account of whoever referred the taker, if anyone, who gets a share of the protocol fee
+    pub referrer_token_account: Option<Pubkey>,
+    // The NFT's Metaplex metadata, to pay its creators' royalties when taking an NFT offer
+    pub token_mint_a_metadata: Option<Pubkey>,
+    // The taker's account for the config's membership token, if they hold it, for the membership fee discount
+    pub membership_token_account: Option<Pubkey>,
+    // Who receives token a, if the taker is taking the offer on behalf of someone else
+    // The recipient needs an associated token account for token a already, the taker still pays token b
+    // The taker's own token a account isn't passed, so they aren't charged rent to create it
+    pub recipient: Option<Pubkey>,
+}
+
+// Build a take_offer instruction for an offer the caller has already fetched
+// The memo program is only passed when args has a memo
+// Bundle offers also need [mint, vault, taker token account] appended for each additional mint
+// Paying royalties also needs each creator with a share's token b account appended after those, in metadata order
+// Attested offers also need an ed25519 program instruction verifying the attestor's signature over the offer's address
+// and created slot, the taker's address, then the offer's mints and amounts, earlier in the same transaction
+// Offers with an Ethereum taker also need a secp256k1 program instruction verifying its signature over the offer's
+// and taker's addresses earlier in the same transaction
+pub fn take_offer(
+    accounts: &TakeOfferAccounts,
+    offer: &Offer,
+    args: instruction::TakeOffer,
+) -> Instruction {
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+
+    let account_metas = accounts::TakeOffer {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        taker: accounts.taker,
+        maker: offer.maker,
+        rent_payer: offer.rent_payer,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        // Token a goes to the recipient's account instead when there is one
+        taker_token_account_a: accounts.recipient.is_none().then(|| {
+            get_associated_token_address_with_program_id(
+                &accounts.taker,
+                &offer.token_mint_a,
+                &accounts.token_program,
+            )
+        }),
+        taker_token_account_b: get_associated_token_address_with_program_id(
+            &accounts.taker,
+            &offer.token_mint_b,
+            &token_program_b,
+        ),
+        maker_token_account_b: get_associated_token_address_with_program_id(
+            &offer.maker,
+            &offer.token_mint_b,
+            &token_program_b,
+        ),
+        offer: accounts.offer,
+        vault_authority,
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        vault: get_associated_token_address_with_program_id(
+            &offer.token_a_holder(&vault_authority),
+            &offer.token_mint_a,
+            &accounts.token_program,
+        ),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&offer.maker).0,
+        maker_stats: find_maker_stats_address(&offer.maker).0,
+        fee_vault: find_fee_vault_address(&offer.token_mint_a).0,
+        allowlist: offer
+            .allowlist_enabled()
+            .then(|| find_allowlist_address(&accounts.offer).0),
+        referrer_token_account: accounts.referrer_token_account,
+        memo_program: args.memo.is_some().then_some(MEMO_PROGRAM_ID),
+        token_mint_a_metadata: accounts.token_mint_a_metadata,
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_b).0),
+        membership_token_account: accounts.membership_token_account,
+        token_program_b: accounts.token_program_b,
+        // take_offer reads the attestor's or Ethereum taker's signature from the transaction's earlier instructions
+        instructions_sysvar: (offer.attestor().is_some() || offer.eth_taker().is_some())
+            .then_some(anchor_lang::solana_program::sysvar::instructions::ID),
+        recipient: accounts.recipient,
+        recipient_token_account_a: accounts.recipient.map(|recipient| {
+            get_associated_token_address_with_program_id(
+                &recipient,
+                &offer.token_mint_a,
+                &accounts.token_program,
+            )
+        }),
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts for take_offer_with_token_accounts, for takers who keep their tokens outside their associated token accounts
+pub struct TakeOfferWithTokenAccountsAccounts {
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the taker's token a accounts, which receives the offered tokens
+    pub taker_token_account_a: Pubkey,
+    // Any of the taker's token b accounts, which pays the maker
+    pub taker_token_account_b: Pubkey,
+}
+
+// Build a take_offer_with_token_accounts instruction for an offer the caller has already fetched
+// Bundles, hashlocked offers, referrers, royalties and the membership discount need take_offer
+pub fn take_offer_with_token_accounts(
+    accounts: &TakeOfferWithTokenAccountsAccounts,
+    offer: &Offer,
+    args: instruction::TakeOfferWithTokenAccounts,
+) -> Instruction {
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+
+    let account_metas = accounts::TakeOfferWithTokenAccounts {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        taker: accounts.taker,
+        maker: offer.maker,
+        rent_payer: offer.rent_payer,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        taker_token_account_a: accounts.taker_token_account_a,
+        taker_token_account_b: accounts.taker_token_account_b,
+        maker_token_account_b: get_associated_token_address_with_program_id(
+            &offer.maker,
+            &offer.token_mint_b,
+            &token_program_b,
+        ),
+        offer: accounts.offer,
+        vault_authority,
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        vault: get_associated_token_address_with_program_id(
+            &offer.token_a_holder(&vault_authority),
+            &offer.token_mint_a,
+            &accounts.token_program,
+        ),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&offer.maker).0,
+        maker_stats: find_maker_stats_address(&offer.maker).0,
+        fee_vault: find_fee_vault_address(&offer.token_mint_a).0,
+        allowlist: offer
+            .allowlist_enabled()
+            .then(|| find_allowlist_address(&accounts.offer).0),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_b).0),
+        token_program_b: accounts.token_program_b,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts for take_offer_with_authorization, for relayers submitting a take the taker authorized off-chain
+pub struct TakeOfferWithAuthorizationAccounts {
+    // Signs the transaction, and pays its fee and any rent
+    pub relayer: Pubkey,
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the taker's token b accounts, with the taker's authority approved as its delegate
+    pub taker_token_account_b: Pubkey,
+}
+
+// Build a take_offer_with_authorization instruction for an offer the caller has already fetched
+// Needs an ed25519 program instruction verifying the taker's signature over the offer's address, token mints and
+// created slot, then the expected amounts and the authorization's expiry, each little endian, earlier in the same
+// transaction
+pub fn take_offer_with_authorization(
+    accounts: &TakeOfferWithAuthorizationAccounts,
+    offer: &Offer,
+    args: instruction::TakeOfferWithAuthorization,
+) -> Instruction {
+    let vault_authority = find_vault_authority_address(&accounts.offer).0;
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+
+    let account_metas = accounts::TakeOfferWithAuthorization {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        relayer: accounts.relayer,
+        taker: accounts.taker,
+        maker: offer.maker,
+        rent_payer: offer.rent_payer,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        taker_token_account_a: get_associated_token_address_with_program_id(
+            &accounts.taker,
+            &offer.token_mint_a,
+            &accounts.token_program,
+        ),
+        taker_token_account_b: accounts.taker_token_account_b,
+        taker_authority: find_taker_authority_address(&accounts.taker).0,
+        maker_token_account_b: get_associated_token_address_with_program_id(
+            &offer.maker,
+            &offer.token_mint_b,
+            &token_program_b,
+        ),
+        offer: accounts.offer,
+        vault_authority,
+        // Delegated offers have no vault, the tokens come straight from the maker's account
+        vault: get_associated_token_address_with_program_id(
+            &offer.token_a_holder(&vault_authority),
+            &offer.token_mint_a,
+            &accounts.token_program,
+        ),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        maker_index: find_maker_index_address(&offer.maker).0,
+        maker_stats: find_maker_stats_address(&offer.maker).0,
+        fee_vault: find_fee_vault_address(&offer.token_mint_a).0,
+        allowlist: offer
+            .allowlist_enabled()
+            .then(|| find_allowlist_address(&accounts.offer).0),
+        // Always passed, so offers still work once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&offer.token_mint_b).0),
+        token_program_b: accounts.token_program_b,
+        instructions_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
+
+// The accounts for match_offers that don't come from the two offers
+pub struct MatchOffersAccounts {
+    // Signs the transaction, and pays for any token accounts the makers need
+    pub matcher: Pubkey,
+    // Offers token a for token b
+    pub first_offer: Pubkey,
+    // Offers token b for token a
+    pub second_offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token program that owns token b, if it isn't token_program
+    pub token_program_b: Option<Pubkey>,
+    // Any of the matcher's accounts for each token, which get the matcher's share of the price improvement
+    pub matcher_token_account_a: Pubkey,
+    pub matcher_token_account_b: Pubkey,
+}
+
+// Build a match_offers instruction, settling two mirrored offers the caller has already fetched against each other
+pub fn match_offers(
+    accounts: &MatchOffersAccounts,
+    first_offer: &Offer,
+    second_offer: &Offer,
+) -> Instruction {
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+    let first_vault_authority = find_vault_authority_address(&accounts.first_offer).0;
+    let second_vault_authority = find_vault_authority_address(&accounts.second_offer).0;
+    let token_mint_a = first_offer.token_mint_a;
+    let token_mint_b = first_offer.token_mint_b;
+
+    let account_metas = accounts::MatchOffers {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        system_program: system_program::ID,
+        matcher: accounts.matcher,
+        first_maker: first_offer.maker,
+        first_rent_payer: first_offer.rent_payer,
+        second_maker: second_offer.maker,
+        second_rent_payer: second_offer.rent_payer,
+        token_mint_a,
+        token_mint_b,
+        first_offer: accounts.first_offer,
+        first_vault_authority,
+        first_vault: get_associated_token_address_with_program_id(
+            &first_vault_authority,
+            &token_mint_a,
+            &accounts.token_program,
+        ),
+        second_offer: accounts.second_offer,
+        second_vault_authority,
+        second_vault: get_associated_token_address_with_program_id(
+            &second_vault_authority,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        first_maker_token_account_a: get_associated_token_address_with_program_id(
+            &first_offer.maker,
+            &token_mint_a,
+            &accounts.token_program,
+        ),
+        first_maker_token_account_b: get_associated_token_address_with_program_id(
+            &first_offer.maker,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        second_maker_token_account_a: get_associated_token_address_with_program_id(
+            &second_offer.maker,
+            &token_mint_a,
+            &accounts.token_program,
+        ),
+        second_maker_token_account_b: get_associated_token_address_with_program_id(
+            &second_offer.maker,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        matcher_token_account_a: accounts.matcher_token_account_a,
+        matcher_token_account_b: accounts.matcher_token_account_b,
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        first_maker_index: find_maker_index_address(&first_offer.maker).0,
+        first_maker_stats: find_maker_stats_address(&first_offer.maker).0,
+        second_maker_index: find_maker_index_address(&second_offer.maker).0,
+        second_maker_stats: find_maker_stats_address(&second_offer.maker).0,
+        // Always passed, so offers still match once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&token_mint_b).0),
+        token_program_b,
+        fee_vault_a: find_fee_vault_address(&token_mint_a).0,
+        fee_vault_b: find_fee_vault_address(&token_mint_b).0,
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: instruction::MatchOffers {}.data(),
+    }
+}
+
+// The accounts for take_offer_route that don't come from the two offers
+pub struct TakeOfferRouteAccounts {
+    // Signs the transaction, pays with token c and receives token a
+    pub taker: Pubkey,
+    // Offers token a for token b
+    pub first_offer: Pubkey,
+    // Offers token b for token c
+    pub second_offer: Pubkey,
+    // The classic token program or the token extensions program, whichever owns token a
+    pub token_program: Pubkey,
+    // The token programs that own token b and token c, if they aren't token_program
+    pub token_program_b: Option<Pubkey>,
+    pub token_program_c: Option<Pubkey>,
+}
+
+// Build a take_offer_route instruction, settling two offers the caller has already fetched for a taker holding only token c
+pub fn take_offer_route(
+    accounts: &TakeOfferRouteAccounts,
+    first_offer: &Offer,
+    second_offer: &Offer,
+    args: instruction::TakeOfferRoute,
+) -> Instruction {
+    let token_program_b = accounts.token_program_b.unwrap_or(accounts.token_program);
+    let token_program_c = accounts.token_program_c.unwrap_or(accounts.token_program);
+    let token_mint_a = first_offer.token_mint_a;
+    let token_mint_b = first_offer.token_mint_b;
+    let token_mint_c = second_offer.token_mint_b;
+    let first_vault_authority = find_vault_authority_address(&accounts.first_offer).0;
+    let second_vault_authority = find_vault_authority_address(&accounts.second_offer).0;
+
+    let account_metas = accounts::TakeOfferRoute {
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        token_program: accounts.token_program,
+        token_program_b,
+        token_program_c,
+        system_program: system_program::ID,
+        taker: accounts.taker,
+        first_maker: first_offer.maker,
+        first_rent_payer: first_offer.rent_payer,
+        second_maker: second_offer.maker,
+        second_rent_payer: second_offer.rent_payer,
+        token_mint_a,
+        token_mint_b,
+        token_mint_c,
+        first_offer: accounts.first_offer,
+        first_vault_authority,
+        first_vault: get_associated_token_address_with_program_id(
+            &first_vault_authority,
+            &token_mint_a,
+            &accounts.token_program,
+        ),
+        second_offer: accounts.second_offer,
+        second_vault_authority,
+        second_vault: get_associated_token_address_with_program_id(
+            &second_vault_authority,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        route_authority: find_route_authority_address(&accounts.taker).0,
+        route_token_account: find_route_token_account_address(&accounts.taker, &token_mint_b).0,
+        taker_token_account_a: get_associated_token_address_with_program_id(
+            &accounts.taker,
+            &token_mint_a,
+            &accounts.token_program,
+        ),
+        taker_token_account_b: get_associated_token_address_with_program_id(
+            &accounts.taker,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        taker_token_account_c: get_associated_token_address_with_program_id(
+            &accounts.taker,
+            &token_mint_c,
+            &token_program_c,
+        ),
+        first_maker_token_account_b: get_associated_token_address_with_program_id(
+            &first_offer.maker,
+            &token_mint_b,
+            &token_program_b,
+        ),
+        second_maker_token_account_c: get_associated_token_address_with_program_id(
+            &second_offer.maker,
+            &token_mint_c,
+            &token_program_c,
+        ),
+        config: find_config_address().0,
+        registry: find_registry_address().0,
+        first_maker_index: find_maker_index_address(&first_offer.maker).0,
+        first_maker_stats: find_maker_stats_address(&first_offer.maker).0,
+        second_maker_index: find_maker_index_address(&second_offer.maker).0,
+        second_maker_stats: find_maker_stats_address(&second_offer.maker).0,
+        fee_vault: find_fee_vault_address(&token_mint_a).0,
+        fee_vault_b: find_fee_vault_address(&token_mint_b).0,
+        // Always passed, so routes still settle once the allowlist manager denies any mint
+        token_mint_a_deny_entry: Some(find_mint_deny_entry_address(&token_mint_a).0),
+        token_mint_b_deny_entry: Some(find_mint_deny_entry_address(&token_mint_b).0),
+        token_mint_c_deny_entry: Some(find_mint_deny_entry_address(&token_mint_c).0),
+        event_authority: find_event_authority_address().0,
+        program: ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: ID,
+        accounts: account_metas,
+        data: args.data(),
+    }
+}
@@ -0,0 +1,15 @@
+// Everything another program needs to call the escrow program, without copying its account layouts or seeds
+// Programs CPI into the escrow with the Anchor generated cpi module, eg cpi::make_offer(CpiContext, ...)
+// Off-chain code, and programs that build instructions by hand, can use the builders in instructions instead
+
+pub mod instructions;
+pub mod pda;
+
+pub use escrow::{
+    accounts, constants, cpi, error::ErrorCode, events, instruction,
+    state::{
+        DutchAuction, EnglishAuction, InstallmentPlan, InstallmentTerms, MakerCounter, MakerIndex,
+        MakerStats, Offer, OfferTemplate, Registry,
+    },
+    ID,
+};
@@ -0,0 +1,319 @@
+// Compute unit benchmarks for each instruction, run with the other tests using Mollusk
+//
+// Mollusk runs a single instruction at a time against an account store, without
+// transactions or signatures, so the compute units it reports are the instruction's own.
+// Each scenario has a budget, and the benchmark fails if the instruction goes over it.
+// When an optimization lands, lower the budget so the saving can't be lost silently.
+use std::collections::HashMap;
+
+use anchor_spl::token_2022;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use mollusk_svm_programs_token::{associated_token, token, token2022};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::{
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+use crate::escrow_test_helpers::{
+    build_initialize_config_instruction, build_make_offer_instruction, build_refund_offer_instruction,
+    build_take_offer_instruction, get_offer_address, get_program_id, get_vault_authority_address,
+    MakeOfferAccounts, RefundOfferAccounts, TakeOfferAccounts, REFERRAL_SHARE_BPS, TOKEN_A, TOKEN_B,
+};
+
+const MAKE_OFFER_BUDGET: u64 = 120_000;
+const TAKE_OFFER_BUDGET: u64 = 100_000;
+// Creating the taker's token a account and the maker's token b account costs two extra CPIs each
+const TAKE_OFFER_CREATING_TOKEN_ACCOUNTS_BUDGET: u64 = 150_000;
+const REFUND_OFFER_BUDGET: u64 = 80_000;
+
+const WALLET_LAMPORTS: u64 = 10_000_000_000;
+
+/// The accounts every benchmark scenario starts with: a maker and taker with funded
+/// token accounts, two mints owned by the token program being benchmarked, and an
+/// initialized config
+struct BenchmarkEnvironment {
+    mollusk: Mollusk,
+    accounts: HashMap<Pubkey, Account>,
+    token_program: Pubkey,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+    maker: Pubkey,
+    taker: Pubkey,
+}
+
+impl BenchmarkEnvironment {
+    fn new(token_program: Pubkey) -> Self {
+        let program_id = get_program_id();
+        let mut mollusk = Mollusk::default();
+        let elf = std::fs::read("../../target/deploy/escrow.so")
+            .expect("Build the program with anchor build before running the benchmarks");
+        mollusk.add_program_with_elf_and_loader(&program_id, &elf, &LOADER_V3);
+        token::add_program(&mut mollusk);
+        token2022::add_program(&mut mollusk);
+        associated_token::add_program(&mut mollusk);
+
+        let mut accounts = HashMap::new();
+        for (address, account) in [
+            keyed_account_for_system_program(),
+            token::keyed_account(),
+            token2022::keyed_account(),
+            associated_token::keyed_account(),
+            (program_id, create_program_account_loader_v3(&program_id)),
+        ] {
+            accounts.insert(address, account);
+        }
+
+        let mut environment = Self {
+            mollusk,
+            accounts,
+            token_program,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            maker: Pubkey::new_unique(),
+            taker: Pubkey::new_unique(),
+        };
+
+        let admin = Pubkey::new_unique();
+        for wallet in [environment.maker, environment.taker, admin] {
+            environment.set_account(wallet, Account::new(WALLET_LAMPORTS, 0, &Pubkey::default()));
+        }
+        for mint in [environment.token_mint_a, environment.token_mint_b] {
+            environment.create_mint(mint);
+        }
+
+        environment.process(&build_initialize_config_instruction(0, admin, REFERRAL_SHARE_BPS, admin));
+
+        environment
+    }
+
+    fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    fn create_mint(&mut self, address: Pubkey) {
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: Some(Pubkey::new_unique()).into(),
+                supply: 100 * TOKEN_A,
+                decimals: 9,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.set_token_program_account(address, data);
+    }
+
+    /// Creates the owner's associated token account for a mint, holding `amount`
+    fn create_token_account(&mut self, owner: Pubkey, mint: Pubkey, amount: u64) -> Pubkey {
+        let address = self.token_account_address(&owner, &mint);
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint,
+                owner,
+                amount,
+                delegate: None.into(),
+                state: AccountState::Initialized,
+                is_native: None.into(),
+                delegated_amount: 0,
+                close_authority: None.into(),
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.set_token_program_account(address, data);
+        address
+    }
+
+    // Token-2022 accepts mints and token accounts without extensions in the classic layout
+    fn set_token_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        let rent = self.mollusk.sysvars.rent.minimum_balance(data.len());
+        self.set_account(
+            address,
+            Account {
+                lamports: rent,
+                data,
+                owner: self.token_program,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    fn token_account_address(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    /// Runs an instruction, which must succeed, keeps the accounts it changed, and returns the compute units it used
+    fn process(&mut self, instruction: &Instruction) -> u64 {
+        // Accounts the instruction creates start out empty and owned by the system program
+        let accounts: Vec<(Pubkey, Account)> = instruction
+            .accounts
+            .iter()
+            .map(|account_meta| {
+                let account = self.accounts.get(&account_meta.pubkey).cloned().unwrap_or_default();
+                (account_meta.pubkey, account)
+            })
+            .collect();
+        let result = self
+            .mollusk
+            .process_and_validate_instruction(instruction, &accounts, &[Check::success()]);
+        self.accounts.extend(result.resulting_accounts);
+        result.compute_units_consumed
+    }
+
+    fn make_offer(&mut self, offer_id: u64) -> u64 {
+        let maker_token_account_a = self.create_token_account(self.maker, self.token_mint_a, 10 * TOKEN_A);
+        let offer_account = get_offer_address(&self.maker, offer_id);
+        let instruction = build_make_offer_instruction(
+            offer_id,
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            MakeOfferAccounts {
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: self.token_program,
+                system_program: anchor_lang::system_program::ID,
+                maker_authority: None,
+                maker: self.maker,
+                payer: None,
+                token_mint_a: self.token_mint_a,
+                token_mint_b: self.token_mint_b,
+                maker_token_account_a,
+                offer_account,
+                vault: self.token_account_address(&get_vault_authority_address(&offer_account), &self.token_mint_a),
+                token_mint_a_metadata: None,
+                token_mint_a_allowlist_entry: None,
+                token_mint_b_allowlist_entry: None,
+                token_program_b: None,
+            },
+        );
+        self.process(&instruction)
+    }
+
+    fn take_offer(&mut self, offer_id: u64) -> u64 {
+        let offer_account = get_offer_address(&self.maker, offer_id);
+        let instruction = build_take_offer_instruction(
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            TakeOfferAccounts {
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: self.token_program,
+                system_program: anchor_lang::system_program::ID,
+                taker: self.taker,
+                maker: self.maker,
+                rent_payer: None,
+                token_mint_a: self.token_mint_a,
+                token_mint_b: self.token_mint_b,
+                taker_token_account_a: self.token_account_address(&self.taker, &self.token_mint_a),
+                taker_token_account_b: self.token_account_address(&self.taker, &self.token_mint_b),
+                maker_token_account_b: self.token_account_address(&self.maker, &self.token_mint_b),
+                offer_account,
+                vault: self.token_account_address(&get_vault_authority_address(&offer_account), &self.token_mint_a),
+                allowlist: None,
+                referrer_token_account: None,
+                token_program_b: None,
+            },
+        );
+        self.process(&instruction)
+    }
+
+    fn refund_offer(&mut self, offer_id: u64) -> u64 {
+        let offer_account = get_offer_address(&self.maker, offer_id);
+        let instruction = build_refund_offer_instruction(RefundOfferAccounts {
+            token_program: self.token_program,
+            system_program: anchor_lang::system_program::ID,
+            maker_authority: None,
+            maker: self.maker,
+            rent_payer: None,
+            token_mint_a: self.token_mint_a,
+            maker_token_account_a: self.token_account_address(&self.maker, &self.token_mint_a),
+            offer_account,
+            vault: self.token_account_address(&get_vault_authority_address(&offer_account), &self.token_mint_a),
+            allowlist: None,
+        });
+        self.process(&instruction)
+    }
+}
+
+fn token_program_name(token_program: &Pubkey) -> &'static str {
+    if *token_program == token_2022::ID {
+        "Token-2022"
+    } else {
+        "SPL Token"
+    }
+}
+
+fn assert_within_budget(scenario: &str, token_program: &Pubkey, compute_units: u64, budget: u64) {
+    println!(
+        "{scenario} ({}): {compute_units} compute units, budget {budget}",
+        token_program_name(token_program)
+    );
+    assert!(
+        compute_units <= budget,
+        "{scenario} ({}) used {compute_units} compute units, over its budget of {budget}",
+        token_program_name(token_program)
+    );
+}
+
+#[test]
+fn benchmark_make_offer() {
+    for token_program in [spl_token::ID, token_2022::ID] {
+        let mut environment = BenchmarkEnvironment::new(token_program);
+        let compute_units = environment.make_offer(1);
+        assert_within_budget("make_offer", &token_program, compute_units, MAKE_OFFER_BUDGET);
+    }
+}
+
+#[test]
+fn benchmark_take_offer() {
+    for token_program in [spl_token::ID, token_2022::ID] {
+        let mut environment = BenchmarkEnvironment::new(token_program);
+        environment.make_offer(1);
+        environment.create_token_account(environment.maker, environment.token_mint_b, 0);
+        environment.create_token_account(environment.taker, environment.token_mint_a, 0);
+        environment.create_token_account(environment.taker, environment.token_mint_b, 5 * TOKEN_B);
+
+        let compute_units = environment.take_offer(1);
+        assert_within_budget("take_offer", &token_program, compute_units, TAKE_OFFER_BUDGET);
+    }
+}
+
+#[test]
+fn benchmark_take_offer_creating_token_accounts() {
+    for token_program in [spl_token::ID, token_2022::ID] {
+        let mut environment = BenchmarkEnvironment::new(token_program);
+        environment.make_offer(1);
+        // Only the taker's token b account exists, the program creates the other two
+        environment.create_token_account(environment.taker, environment.token_mint_b, 5 * TOKEN_B);
+
+        let compute_units = environment.take_offer(1);
+        assert_within_budget(
+            "take_offer creating token accounts",
+            &token_program,
+            compute_units,
+            TAKE_OFFER_CREATING_TOKEN_ACCOUNTS_BUDGET,
+        );
+    }
+}
+
+#[test]
+fn benchmark_refund_offer() {
+    for token_program in [spl_token::ID, token_2022::ID] {
+        let mut environment = BenchmarkEnvironment::new(token_program);
+        environment.make_offer(1);
+
+        let compute_units = environment.refund_offer(1);
+        assert_within_budget("refund_offer", &token_program, compute_units, REFUND_OFFER_BUDGET);
+    }
+}
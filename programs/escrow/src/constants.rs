@@ -1 +1,47 @@
-// We don't actually have any constants in this program
+// The most takers a maker can add to a single offer's allowlist
+pub const MAX_ALLOWED_TAKERS: usize = 16;
+
+// A bundle offer escrows token a plus up to this many other mints, four mints in total
+pub const MAX_ADDITIONAL_OFFERED_MINTS: usize = 3;
+
+// An NFT is a mint with no decimals and a supply of exactly one token
+pub const NFT_DECIMALS: u8 = 0;
+pub const NFT_SUPPLY: u64 = 1;
+
+// Metaplex creator shares of an NFT's royalties are percentages
+pub const CREATOR_SHARE_DENOMINATOR: u64 = 100;
+
+// Fees are expressed in basis points, hundredths of a percent
+pub const BASIS_POINTS_DENOMINATOR: u64 = 10_000;
+
+// The protocol fee can never be more than 10%
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+// The most volume tiers the config can lower the protocol fee with
+pub const MAX_FEE_TIERS: usize = 4;
+
+// Makers can label offers with a short memo, up to this many bytes
+pub const MAX_MEMO_LENGTH: usize = 64;
+
+// The longest metadata URI that can be attached to an offer
+pub const MAX_METADATA_URI_LENGTH: usize = 200;
+
+// The most open offers a maker's index can list, makers need to close one to make another
+pub const MAX_MAKER_OPEN_OFFERS: usize = 32;
+
+// Passed as the id to make_offer to have the program assign the maker's next offer id from their counter
+pub const AUTO_ASSIGN_OFFER_ID: u64 = u64::MAX;
+
+// The current version of the Offer layout, increased whenever a field is added to the end of it
+pub const OFFER_VERSION: u8 = 5;
+
+// Bytes at the end of the Offer layout kept free for future fields
+// Version 5 doubled it from 64 bytes, once an Ethereum address no longer fit in what was left
+pub const OFFER_RESERVED_SPACE: usize = 128;
+
+// Offers carry this many tag bytes, each a category the maker picks, like the pair's class or OTC vs retail
+// The program doesn't give the bytes any meaning, frontends agree on what each one holds
+pub const OFFER_TAGS_LENGTH: usize = 8;
+
+// Ethereum addresses are the last 20 bytes of the keccak-256 hash of a secp256k1 public key
+pub const ETH_ADDRESS_LENGTH: usize = 20;
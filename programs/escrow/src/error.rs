@@ -25,4 +25,400 @@ pub enum ErrorCode {
 
     #[msg("Failed to close vault during refund")]
     FailedRefundClosure,
+
+    #[msg("Offer has expired")]
+    OfferExpired,
+
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+
+    #[msg("The maker cannot trade with their own offer")]
+    SelfTradeNotAllowed,
+
+    #[msg("Dutch auction must end after it starts, and its price can only decrease")]
+    InvalidAuctionSchedule,
+
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+
+    #[msg("An offer can't be both a Dutch and an English auction")]
+    ConflictingAuctionModes,
+
+    #[msg("English auction offers can only be settled to the highest bid")]
+    OfferIsAuction,
+
+    #[msg("Offer is not an English auction")]
+    NotAnAuction,
+
+    #[msg("Auction has ended")]
+    AuctionEnded,
+
+    #[msg("Auction has not ended yet")]
+    AuctionNotEnded,
+
+    #[msg("Bid is below the reserve price or minimum increment")]
+    BidTooLow,
+
+    #[msg("Auction already has bids")]
+    AuctionHasBids,
+
+    #[msg("Auction has no bids to settle")]
+    AuctionHasNoBids,
+
+    #[msg("Account does not belong to the current highest bidder")]
+    BidderMismatch,
+
+    #[msg("Taker is not on the offer's allowlist")]
+    TakerNotAllowed,
+
+    #[msg("Offer has an allowlist, so the allowlist account must be provided")]
+    AllowlistRequired,
+
+    #[msg("Offer allowlist is full")]
+    AllowlistFull,
+
+    #[msg("Too many mints offered in one bundle")]
+    TooManyOfferedMints,
+
+    #[msg("Wrong number of remaining accounts")]
+    InvalidRemainingAccounts,
+
+    #[msg("Vault must be the offer vault authority's associated token account for the mint")]
+    InvalidVault,
+
+    #[msg("Token account has the wrong mint or owner")]
+    InvalidTokenAccount,
+
+    #[msg("Mint is not owned by the token program")]
+    InvalidTokenProgram,
+
+    #[msg("This operation is not supported for bundle offers")]
+    BundleNotSupported,
+
+    #[msg("Token is not an NFT")]
+    NotAnNft,
+
+    #[msg("Metadata account is not the Metaplex metadata for this mint")]
+    InvalidMetadata,
+
+    #[msg("NFT is not part of the expected collection")]
+    CollectionMismatch,
+
+    #[msg("Transfer fee would consume the entire offered amount")]
+    TransferFeeExceedsAmount,
+
+    #[msg("Recipient received less than the amount owed after transfer fees")]
+    TransferFeeShortfall,
+
+    #[msg("Mints with a Token-2022 transfer hook are not supported")]
+    TransferHookNotSupported,
+
+    #[msg("Fee is higher than the maximum allowed")]
+    InvalidFee,
+
+    #[msg("There are no fees to withdraw")]
+    NoFeesToWithdraw,
+
+    #[msg("Takers can't refer themselves")]
+    SelfReferral,
+
+    #[msg("The program is paused")]
+    ProgramPaused,
+
+    #[msg("Memo is too long")]
+    MemoTooLong,
+
+    #[msg("Metadata URI is too long")]
+    MetadataUriTooLong,
+
+    #[msg("Every offer in a batch must be from the same maker, for the same pair of mints")]
+    BatchOfferMismatch,
+
+    #[msg("Offer doesn't hold enough token a")]
+    InsufficientOfferBalance,
+
+    #[msg("This offer can only be taken in full")]
+    PartialFillNotSupported,
+
+    #[msg("Offer terms changed since the taker saw them")]
+    OfferTermsChanged,
+
+    #[msg("Partial fill is below the offer's minimum fill amount")]
+    FillTooSmall,
+
+    #[msg("Preimage doesn't match the offer's hashlock")]
+    InvalidPreimage,
+
+    #[msg("Hashlocked offers can only be taken with take_offer")]
+    OfferIsHashlocked,
+
+    #[msg("Vested offers can only be taken with take_offer_vested")]
+    OfferIsVested,
+
+    #[msg("This offer doesn't vest")]
+    NotVested,
+
+    #[msg("Vesting duration must be positive")]
+    InvalidVestingSchedule,
+
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+
+    #[msg("Only fixed price offers without a bundle, hashlock or vesting can be deposited for")]
+    DepositNotSupported,
+
+    #[msg("Only the maker, the maker's authority or the depositing taker can confirm the swap")]
+    NotSwapParty,
+
+    #[msg("Signer is neither the maker nor an authority acting for the maker")]
+    InvalidMakerAuthority,
+
+    #[msg("Maker already has the most open offers their index can list")]
+    MakerIndexFull,
+
+    #[msg("Arbitrated offers can only be taken with take_offer_arbitrated")]
+    OfferIsArbitrated,
+
+    #[msg("This offer has no arbiter")]
+    NotArbitrated,
+
+    #[msg("Arbitrated offers can't be auctions, bundles, hashlocked or vested")]
+    ArbiterNotSupported,
+
+    #[msg("The arbiter can't be the maker or the taker of the offer")]
+    InvalidArbiter,
+
+    #[msg("Offer has been taken and is waiting for its arbiter")]
+    ArbitrationPending,
+
+    #[msg("Offer account doesn't match any known version of the offer layout")]
+    UnknownOfferVersion,
+
+    #[msg("Offer already uses the current layout")]
+    OfferAlreadyMigrated,
+
+    #[msg("Rent payer doesn't match the account that paid for the offer")]
+    InvalidRentPayer,
+
+    #[msg("Mint has a configuration the protocol's mint policy doesn't allow")]
+    MintNotAllowed,
+
+    #[msg("Account is not the offer's maker")]
+    InvalidMaker,
+
+    #[msg("Mint doesn't match the mint the account was made for")]
+    WrongTokenMint,
+
+    #[msg("Account belongs to a different offer")]
+    WrongOffer,
+
+    #[msg("Account is not the taker recorded for this swap")]
+    InvalidTaker,
+
+    #[msg("Signer is not the offer's arbiter")]
+    ArbiterMismatch,
+
+    #[msg("Signer is not the config authority")]
+    InvalidAuthority,
+
+    #[msg("Signer is not the config's fee authority")]
+    InvalidFeeAuthority,
+
+    #[msg("Account is not the vesting beneficiary")]
+    InvalidBeneficiary,
+
+    #[msg("Account is not the counter offer's proposer")]
+    InvalidProposer,
+
+    #[msg("Token account is owned by the wrong wallet")]
+    InvalidTokenAccountOwner,
+
+    #[msg("Token account is frozen")]
+    TokenAccountFrozen,
+
+    #[msg("Minimum fill amount can't be more than the amount offered")]
+    InvalidMinFillAmount,
+
+    #[msg("NFT offers must offer exactly one token")]
+    InvalidNftAmount,
+
+    #[msg("Offer has no price")]
+    InvalidPrice,
+
+    #[msg("Offered amount must be greater than zero")]
+    InvalidOfferedAmount,
+
+    #[msg("Wanted amount must be greater than zero")]
+    InvalidWantedAmount,
+
+    #[msg("Division by zero")]
+    DivisionByZero,
+
+    #[msg("The memo program is required to attach a memo")]
+    MemoProgramRequired,
+
+    #[msg("Royalties are required, pass the NFT's metadata and its creators' token accounts")]
+    RoyaltyMetadataRequired,
+
+    #[msg("Royalties are required, and only take_offer pays them on NFT offers")]
+    RoyaltiesRequired,
+
+    #[msg("The maker has revoked the offer's approval or spent the tokens it covered")]
+    DelegationRevoked,
+
+    #[msg("The vault's offer still exists, close the offer instead")]
+    OfferStillExists,
+
+    #[msg("The offer has already been settled or refunded")]
+    OfferNotOpen,
+
+    #[msg("The offer is still open, only settled or refunded offers' rent can be claimed")]
+    OfferStillOpen,
+
+    #[msg("The minimum offer amount can't be more than the maximum")]
+    InvalidOfferLimits,
+
+    #[msg("The offered amount is below the protocol's minimum")]
+    OfferTooSmall,
+
+    #[msg("The offered amount is above the protocol's maximum")]
+    OfferTooLarge,
+
+    #[msg("The mint isn't on the protocol's mint allowlist")]
+    MintNotAllowlisted,
+
+    #[msg("The mint is on the protocol's mint denylist")]
+    MintDenied,
+
+    #[msg("Pass each mint's deny entry address while the protocol denies any mints")]
+    MintDenyEntryRequired,
+
+    #[msg("Fee tiers must be sorted by volume, with at most MAX_FEE_TIERS of them")]
+    InvalidFeeTiers,
+
+    #[msg("A membership discount needs a minimum balance, and can't be more than the whole fee")]
+    InvalidMembershipDiscount,
+
+    #[msg("The membership token account doesn't hold enough of the membership token for the discount")]
+    NotAMember,
+
+    #[msg("Only the config's pauser can do this")]
+    InvalidPauser,
+
+    #[msg("Only the config's fee manager can do this")]
+    InvalidFeeManager,
+
+    #[msg("Only the config's allowlist manager can do this")]
+    InvalidAllowlistManager,
+
+    #[msg("Token account received a different amount than the transfer should have delivered")]
+    SettledAmountMismatch,
+
+    #[msg("The template's last offer is still open")]
+    TemplateOfferStillOpen,
+
+    #[msg("Installment terms need at least two installments and a positive interval")]
+    InvalidInstallmentTerms,
+
+    #[msg("Only offers without an English auction, bundle, hashlock, attestor, Ethereum taker, vesting or arbiter, and not delegated, can be paid in installments")]
+    InstallmentsNotSupported,
+
+    #[msg("The maker hasn't agreed to be paid in installments for this offer")]
+    InstallmentsNotAllowed,
+
+    #[msg("Pay in at least two installments, and no more than the maker allows")]
+    InvalidInstallmentCount,
+
+    #[msg("The taker isn't behind on their installments")]
+    InstallmentNotOverdue,
+
+    #[msg("Only offers take_offer settles, without installments, can have an attestor")]
+    AttestorNotSupported,
+
+    #[msg("Attested offers can only be taken with take_offer")]
+    OfferIsAttested,
+
+    #[msg("Attested offers need the instructions sysvar to check the attestor's signature")]
+    AttestationRequired,
+
+    #[msg("The transaction doesn't verify the attestor's ed25519 signature over the offer and taker")]
+    InvalidAttestation,
+
+    #[msg("Only offers take_offer settles, without installments, can be limited to an Ethereum taker")]
+    EthTakerNotSupported,
+
+    #[msg("Offers limited to an Ethereum taker can only be taken with take_offer")]
+    OfferHasEthTaker,
+
+    #[msg("Offers limited to an Ethereum taker need the instructions sysvar to check the taker's signature")]
+    EthSignatureRequired,
+
+    #[msg("The transaction doesn't verify a secp256k1 signature from the offer's Ethereum taker over the offer and taker addresses")]
+    InvalidEthSignature,
+
+    #[msg("The taker's authorization has expired")]
+    AuthorizationExpired,
+
+    #[msg("The transaction doesn't verify the taker's ed25519 signature over the offer and the terms they authorized")]
+    InvalidTakerAuthorization,
+
+    #[msg("The taker hasn't approved their taker authority to spend enough token b")]
+    TakerDelegationMissing,
+
+    #[msg("Matcher share is more than the whole price improvement")]
+    InvalidMatcherShare,
+
+    #[msg("Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or delegation can be matched")]
+    MatchNotSupported,
+
+    #[msg("Each offer must offer at least what the other wants")]
+    OffersDontMatch,
+
+    #[msg("The quote request has expired")]
+    QuoteRequestExpired,
+
+    #[msg("Quotes can only be withdrawn once their request expires or is closed")]
+    QuoteStillFirm,
+
+    #[msg("Quote belongs to a different quote request")]
+    WrongQuoteRequest,
+
+    #[msg("Signer is not the quote request's requester")]
+    InvalidRequester,
+
+    #[msg("The vault holds less token a than the offer is for")]
+    VaultUnderfunded,
+
+    #[msg("Bundle and delegated offers can't be replaced, refund them and make a new offer instead")]
+    ReplaceNotSupported,
+
+    #[msg("Only fixed price offers without a bundle, allowlist, hashlock, attestor, Ethereum taker, vesting, arbiter or delegation can be routed")]
+    RouteNotSupported,
+
+    #[msg("The second offer must offer at least the intermediate token the first offer wants")]
+    RouteLegsDontMatch,
+
+    #[msg("The maker must be the stake account's staker and withdrawer")]
+    InvalidStakeAuthority,
+
+    #[msg("Stake accounts with a lockup in force can't be offered")]
+    StakeLockupInForce,
+
+    #[msg("Only deactivated stake accounts can be offered")]
+    StakeNotDeactivated,
+
+    #[msg("Pass a recipient with their token account, or the taker's token account without a recipient")]
+    RecipientTokenAccountRequired,
+
+    #[msg("This offer was made too recently to be taken yet")]
+    OfferTooNew,
+
+    #[msg("Maker already has the most open offers the config allows")]
+    TooManyOpenOffers,
+
+    #[msg("The open offer cap must be at least one and at most what a maker index can list")]
+    InvalidMaxOpenOffers,
+
+    #[msg("Only offers made before offers had a vault authority can be refunded this way")]
+    NotALegacyOffer,
 }
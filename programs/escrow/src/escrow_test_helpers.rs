@@ -2,13 +2,26 @@ use solana_kite::{
     create_associated_token_account, create_token_mint, deploy_program, mint_tokens_to_account,
     send_transaction_from_instructions, get_pda_and_bump, SolanaKiteError,
 };
-use litesvm::LiteSVM;
+use crate::constants::ETH_ADDRESS_LENGTH;
+use crate::error::ErrorCode;
+use crate::state::{DutchAuction, EnglishAuction, FeeTier, InstallmentPlan, InstallmentTerms, MakerCounter, MakerIndex, MakerStats, MembershipDiscount, MintPolicy, Offer, OfferTemplate, Registry, Role, Vesting};
+use anchor_lang::{prelude::Clock, AccountDeserialize, AnchorSerialize, Discriminator};
+use anchor_spl::metadata::mpl_token_metadata::{
+    self,
+    accounts::Metadata,
+    types::{Key, TokenStandard},
+};
+use litesvm::{types::TransactionResult, LiteSVM};
+use solana_account::Account;
 use std::cell::Cell;
 use solana_instruction::AccountMeta;
 use solana_instruction::Instruction;
 use solana_keypair::Keypair;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_message::{v0, Message, VersionedMessage};
 use solana_pubkey::Pubkey;
 use solana_signer::Signer;
+use solana_transaction::{versioned::VersionedTransaction, Transaction};
 use std::str::FromStr;
 
 pub const PROGRAM_ID: &str = "8jR5GeNzeweq35Uo84kGP3v1NcBaZWH5u62k7PxN4T2y";
@@ -19,6 +32,9 @@ pub const TOKEN_A: u64 = 1_000_000_000;
 /// Standard token unit for token B (1 token = 1_000_000_000 lamports for 9 decimals)
 pub const TOKEN_B: u64 = 1_000_000_000;
 
+/// The share of the protocol fee paid to referrers in tests, 20%
+pub const REFERRAL_SHARE_BPS: u16 = 2_000;
+
 /// Complete escrow test environment containing all necessary components for testing
 ///
 /// This struct holds all the accounts, keypairs, and state needed for comprehensive
@@ -29,6 +45,8 @@ pub struct EscrowTestEnvironment {
     pub litesvm: LiteSVM,
     /// The escrow program ID
     pub program_id: Pubkey,
+    /// The token program that owns both mints, the classic token program unless set up with Token-2022
+    pub token_program: Pubkey,
     /// The mint authority that can create and mint tokens
     pub _mint_authority: Keypair,
     /// Token mint A (the first token in escrow trades)
@@ -47,6 +65,38 @@ pub struct EscrowTestEnvironment {
     pub bob_token_account_a: Pubkey,
     /// Bob's token account for token B
     pub bob_token_account_b: Pubkey,
+    /// The config authority, who can pause the program
+    pub config_authority: Keypair,
+    /// The config's fee authority, who can withdraw protocol fees
+    pub fee_authority: Keypair,
+}
+
+// Solana targets 400ms slots, used to move the slot along with the clock
+const MILLISECONDS_PER_SLOT: i64 = 400;
+
+impl EscrowTestEnvironment {
+    /// The unix timestamp of the LiteSVM clock
+    pub fn now(&self) -> i64 {
+        self.litesvm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    /// Sets the LiteSVM clock to a unix timestamp, for testing expiry, auctions and vesting
+    ///
+    /// The slot moves with the clock, and the blockhash is expired so a transaction that
+    /// was sent before the warp can be sent again after it.
+    pub fn warp_to_timestamp(&mut self, unix_timestamp: i64) {
+        let mut clock = self.litesvm.get_sysvar::<Clock>();
+        let elapsed_slots = (unix_timestamp - clock.unix_timestamp) * 1_000 / MILLISECONDS_PER_SLOT;
+        clock.slot = clock.slot.saturating_add_signed(elapsed_slots);
+        clock.unix_timestamp = unix_timestamp;
+        self.litesvm.set_sysvar(&clock);
+        self.litesvm.expire_blockhash();
+    }
+
+    /// Moves the LiteSVM clock forward by a number of seconds
+    pub fn warp_forward(&mut self, seconds: i64) {
+        self.warp_to_timestamp(self.now() + seconds);
+    }
 }
 
 /// Sets up a complete escrow test environment with all necessary components
@@ -61,6 +111,7 @@ pub struct EscrowTestEnvironment {
 /// 7. Mints initial token balances:
 ///    - Alice: 10 token A, 0 token B
 ///    - Bob: 0 token A, 5 token B
+/// 8. Initializes the protocol config with no protocol fee
 ///
 /// # Returns
 ///
@@ -75,7 +126,7 @@ pub struct EscrowTestEnvironment {
 /// // Create an offer using Alice
 /// let offer_id = 12345u64;
 /// let (offer_account, _) = Pubkey::find_program_address(
-///     &[b"offer", &offer_id.to_le_bytes()],
+///     &[b"offer", env.alice.pubkey().as_ref(), &offer_id.to_le_bytes()],
 ///     &env.program_id
 /// );
 ///
@@ -84,15 +135,44 @@ pub struct EscrowTestEnvironment {
 ///     associated_token_program: spl_associated_token_account::ID,
 ///     token_program: spl_token::ID,
 ///     system_program: anchor_lang::system_program::ID,
+///     maker_authority: None,
 ///     maker: env.alice.pubkey(),
+///     payer: None,
 ///     token_mint_a: env.token_mint_a.pubkey(),
 ///     token_mint_b: env.token_mint_b.pubkey(),
 ///     maker_token_account_a: env.alice_token_account_a,
 ///     offer_account,
-///     vault: spl_associated_token_account::get_associated_token_address(&offer_account, &env.token_mint_a.pubkey()),
+///     vault: spl_associated_token_account::get_associated_token_address(
+///         &get_vault_authority_address(&offer_account),
+///         &env.token_mint_a.pubkey(),
+///     ),
+///     token_mint_a_metadata: None,
+///     token_mint_a_allowlist_entry: None,
+///     token_mint_b_allowlist_entry: None,
+///     token_program_b: None,
 /// };
 /// ```
 pub fn setup_escrow_test() -> EscrowTestEnvironment {
+    setup_escrow_test_with_fee_bps(0)
+}
+
+/// Sets up the escrow test environment with a protocol fee
+///
+/// `setup_escrow_test()` uses no fee, so tests of unrelated features can assert exact
+/// balances. Tests of the protocol fee itself use this instead.
+pub fn setup_escrow_test_with_fee_bps(fee_bps: u16) -> EscrowTestEnvironment {
+    setup_escrow_test_with_decimals(fee_bps, 9, 9)
+}
+
+/// Sets up the escrow test environment with a protocol fee and mints of any precision
+///
+/// Alice and Bob get the same number of base units as in `setup_escrow_test()`,
+/// whatever the decimals, so `TOKEN_A` and `TOKEN_B` are only whole tokens at 9 decimals.
+pub fn setup_escrow_test_with_decimals(
+    fee_bps: u16,
+    token_a_decimals: u8,
+    token_b_decimals: u8,
+) -> EscrowTestEnvironment {
     let mut litesvm = LiteSVM::new();
     let program_id = get_program_id();
 
@@ -106,8 +186,8 @@ pub fn setup_escrow_test() -> EscrowTestEnvironment {
         .unwrap();
 
     // Create token mints
-    let token_mint_a = create_token_mint(&mut litesvm, &mint_authority, 9).unwrap();
-    let token_mint_b = create_token_mint(&mut litesvm, &mint_authority, 9).unwrap();
+    let token_mint_a = create_token_mint(&mut litesvm, &mint_authority, token_a_decimals).unwrap();
+    let token_mint_b = create_token_mint(&mut litesvm, &mint_authority, token_b_decimals).unwrap();
 
     // Create and fund Alice and Bob
     let alice = Keypair::new();
@@ -159,9 +239,12 @@ pub fn setup_escrow_test() -> EscrowTestEnvironment {
         &mint_authority,
     ).unwrap();
 
+    let (config_authority, fee_authority) = initialize_test_config(&mut litesvm, fee_bps);
+
     EscrowTestEnvironment {
         litesvm,
         program_id,
+        token_program: spl_token::ID,
         _mint_authority: mint_authority,
         token_mint_a,
         token_mint_b,
@@ -171,290 +254,5453 @@ pub fn setup_escrow_test() -> EscrowTestEnvironment {
         alice_token_account_b,
         bob_token_account_a,
         bob_token_account_b,
+        config_authority,
+        fee_authority,
     }
 }
 
-pub fn get_program_id() -> Pubkey {
-    Pubkey::from_str(PROGRAM_ID).unwrap()
+/// Sets up the protocol config, returning the config authority and the fee authority
+fn initialize_test_config(litesvm: &mut LiteSVM, fee_bps: u16) -> (Keypair, Keypair) {
+    let config_authority = Keypair::new();
+    let fee_authority = Keypair::new();
+    litesvm.airdrop(&config_authority.pubkey(), 1_000_000_000).unwrap();
+    litesvm.airdrop(&fee_authority.pubkey(), 1_000_000_000).unwrap();
+    let initialize_config_instruction = build_initialize_config_instruction(
+        fee_bps,
+        fee_authority.pubkey(),
+        REFERRAL_SHARE_BPS,
+        config_authority.pubkey(),
+    );
+    send_transaction_from_instructions(
+        litesvm,
+        vec![initialize_config_instruction],
+        &[&config_authority],
+        &config_authority.pubkey(),
+    ).unwrap();
+
+    (config_authority, fee_authority)
 }
 
-thread_local! {
-    static OFFER_ID_COUNTER: Cell<u64> = Cell::new(1);
+/// The Token-2022 extensions to create a test mint with
+///
+/// The default is a Token-2022 mint with no extensions.
+#[derive(Default, Clone)]
+pub struct Token2022Extensions {
+    /// Charge a transfer fee of this many basis points, up to a maximum fee, on every transfer
+    pub transfer_fee: Option<(u16, u64)>,
+    /// Call this program on every transfer
+    pub transfer_hook_program: Option<Pubkey>,
+    /// Create new token accounts frozen, so they have to be thawed by the mint authority before use
+    pub default_account_state_frozen: bool,
 }
 
-/// Generates a unique offer ID for testing
+/// Creates a Token-2022 mint with the given extensions
 ///
-/// This function returns incrementing offer IDs starting from 1, ensuring
-/// each test gets unique IDs to avoid conflicts between test cases.
-pub fn generate_offer_id() -> u64 {
-    OFFER_ID_COUNTER.with(|counter| {
-        let id = counter.get();
-        counter.set(id + 1);
-        id
-    })
+/// The mint authority is also the freeze authority and the authority for every extension.
+pub fn create_token_2022_mint(
+    litesvm: &mut LiteSVM,
+    mint_authority: &Keypair,
+    decimals: u8,
+    extensions: &Token2022Extensions,
+) -> Keypair {
+    use spl_token_2022::extension::{
+        default_account_state, transfer_fee, transfer_hook, ExtensionType,
+    };
+
+    let mint = Keypair::new();
+    let mint_authority_address = mint_authority.pubkey();
+
+    // Extensions are initialized before the mint itself
+    let mut extension_types = Vec::new();
+    let mut extension_instructions = Vec::new();
+    if let Some((transfer_fee_basis_points, maximum_fee)) = extensions.transfer_fee {
+        extension_types.push(ExtensionType::TransferFeeConfig);
+        extension_instructions.push(
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                Some(&mint_authority_address),
+                Some(&mint_authority_address),
+                transfer_fee_basis_points,
+                maximum_fee,
+            )
+            .unwrap(),
+        );
+    }
+    if let Some(transfer_hook_program) = extensions.transfer_hook_program {
+        extension_types.push(ExtensionType::TransferHook);
+        extension_instructions.push(
+            transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                Some(mint_authority_address),
+                Some(transfer_hook_program),
+            )
+            .unwrap(),
+        );
+    }
+    if extensions.default_account_state_frozen {
+        extension_types.push(ExtensionType::DefaultAccountState);
+        extension_instructions.push(
+            default_account_state::instruction::initialize_default_account_state(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                &spl_token_2022::state::AccountState::Frozen,
+            )
+            .unwrap(),
+        );
+    }
+
+    let mint_length =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extension_types).unwrap();
+    let mut instructions = vec![solana_system_interface::instruction::create_account(
+        &mint_authority_address,
+        &mint.pubkey(),
+        litesvm.minimum_balance_for_rent_exemption(mint_length),
+        mint_length as u64,
+        &spl_token_2022::ID,
+    )];
+    instructions.extend(extension_instructions);
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::ID,
+            &mint.pubkey(),
+            &mint_authority_address,
+            Some(&mint_authority_address),
+            decimals,
+        )
+        .unwrap(),
+    );
+
+    send_transaction_from_instructions(
+        litesvm,
+        instructions,
+        &[mint_authority, &mint],
+        &mint_authority_address,
+    )
+    .unwrap();
+
+    mint
 }
 
-pub fn get_make_offer_discriminator() -> Vec<u8> {
-    let discriminator_input = b"global:make_offer";
-    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+/// Creates the owner's Token-2022 associated token account for a mint
+///
+/// For mints with a frozen default account state, the account starts frozen, see `thaw_token_2022_account`.
+pub fn create_token_2022_associated_token_account(
+    litesvm: &mut LiteSVM,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    payer: &Keypair,
+) -> Pubkey {
+    let create_instruction = spl_associated_token_account::instruction::create_associated_token_account(
+        &payer.pubkey(),
+        owner,
+        mint,
+        &spl_token_2022::ID,
+    );
+    send_transaction_from_instructions(litesvm, vec![create_instruction], &[payer], &payer.pubkey()).unwrap();
+
+    spl_associated_token_account::get_associated_token_address_with_program_id(owner, mint, &spl_token_2022::ID)
 }
 
-pub fn get_take_offer_discriminator() -> Vec<u8> {
-    let discriminator_input = b"global:take_offer";
-    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+/// Creates a token account for a mint at a new address, rather than the owner's associated token account
+pub fn create_token_account_at_new_address(
+    test_environment: &mut EscrowTestEnvironment,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    payer: &Keypair,
+) -> Pubkey {
+    use solana_program::program_pack::Pack;
+
+    let token_account = Keypair::new();
+    let token_program = test_environment.token_program;
+    let instructions = vec![
+        solana_system_interface::instruction::create_account(
+            &payer.pubkey(),
+            &token_account.pubkey(),
+            test_environment
+                .litesvm
+                .minimum_balance_for_rent_exemption(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &token_program,
+        ),
+        spl_token_2022::instruction::initialize_account3(&token_program, &token_account.pubkey(), mint, owner)
+            .unwrap(),
+    ];
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        instructions,
+        &[payer, &token_account],
+        &payer.pubkey(),
+    )
+    .unwrap();
+
+    token_account.pubkey()
 }
 
-pub fn get_refund_offer_discriminator() -> Vec<u8> {
-    let discriminator_input = b"global:refund_offer";
-    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+/// Hands a token account to a new owner, which classic token accounts allow even for associated token accounts
+pub fn set_token_account_owner(
+    test_environment: &mut EscrowTestEnvironment,
+    token_account: &Pubkey,
+    owner: &Keypair,
+    new_owner: &Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let instruction = spl_token_2022::instruction::set_authority(
+        &test_environment.token_program,
+        token_account,
+        Some(new_owner),
+        spl_token_2022::instruction::AuthorityType::AccountOwner,
+        &owner.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![instruction],
+        &[owner],
+        &owner.pubkey(),
+    )
 }
 
-pub struct MakeOfferAccounts {
-    pub associated_token_program: Pubkey,
-    pub token_program: Pubkey,
-    pub system_program: Pubkey,
-    pub maker: Pubkey,
-    pub token_mint_a: Pubkey,
-    pub token_mint_b: Pubkey,
-    pub maker_token_account_a: Pubkey,
-    pub offer_account: Pubkey,
-    pub vault: Pubkey,
+/// Mints Token-2022 tokens to a token account
+pub fn mint_token_2022_to_account(
+    litesvm: &mut LiteSVM,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+    mint_authority: &Keypair,
+) {
+    let mint_to_instruction = spl_token_2022::instruction::mint_to(
+        &spl_token_2022::ID,
+        mint,
+        token_account,
+        &mint_authority.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    send_transaction_from_instructions(
+        litesvm,
+        vec![mint_to_instruction],
+        &[mint_authority],
+        &mint_authority.pubkey(),
+    )
+    .unwrap();
 }
 
-/// Helper function to create MakeOfferAccounts with standard program IDs
+/// Thaws a Token-2022 account, such as one created frozen by a mint's default account state
+pub fn thaw_token_2022_account(
+    litesvm: &mut LiteSVM,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    freeze_authority: &Keypair,
+) {
+    let thaw_instruction = spl_token_2022::instruction::thaw_account(
+        &spl_token_2022::ID,
+        token_account,
+        mint,
+        &freeze_authority.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send_transaction_from_instructions(
+        litesvm,
+        vec![thaw_instruction],
+        &[freeze_authority],
+        &freeze_authority.pubkey(),
+    )
+    .unwrap();
+}
+
+/// Sets up the escrow test environment with Token-2022 mints
 ///
-/// This function eliminates the repetitive initialization of the three standard
-/// program IDs (associated_token_program, token_program, system_program) that
-/// are always the same constants across all tests. Instead of copy-pasting
-/// these three lines in every test, this helper focuses on the variable fields.
-pub fn build_make_offer_accounts(
-    maker: Pubkey,
-    token_mint_a: Pubkey,
-    token_mint_b: Pubkey,
-    maker_token_account_a: Pubkey,
-    offer_account: Pubkey,
-    vault: Pubkey,
-) -> MakeOfferAccounts {
-    MakeOfferAccounts {
-        associated_token_program: spl_associated_token_account::ID,
-        token_program: spl_token::ID,
-        system_program: anchor_lang::system_program::ID,
-        maker,
+/// Alice and Bob get the same balances as in `setup_escrow_test()`, and their token
+/// accounts are thawed if the mint creates them frozen. Any transfer fee isn't charged
+/// on minting, so the balances are exact.
+pub fn setup_escrow_test_with_token_2022(
+    fee_bps: u16,
+    token_a_extensions: &Token2022Extensions,
+    token_b_extensions: &Token2022Extensions,
+) -> EscrowTestEnvironment {
+    let mut litesvm = LiteSVM::new();
+    let program_id = get_program_id();
+
+    deploy_program(&mut litesvm, &program_id, "../../target/deploy/escrow.so").unwrap();
+
+    let mint_authority = Keypair::new();
+    litesvm
+        .airdrop(&mint_authority.pubkey(), 1_000_000_000)
+        .unwrap();
+
+    let token_mint_a = create_token_2022_mint(&mut litesvm, &mint_authority, 9, token_a_extensions);
+    let token_mint_b = create_token_2022_mint(&mut litesvm, &mint_authority, 9, token_b_extensions);
+
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    litesvm.airdrop(&alice.pubkey(), 1_000_000_000).unwrap();
+    litesvm.airdrop(&bob.pubkey(), 1_000_000_000).unwrap();
+
+    let mut create_user_token_account = |owner: &Keypair, mint: &Keypair, extensions: &Token2022Extensions| {
+        let token_account = create_token_2022_associated_token_account(
+            &mut litesvm,
+            &owner.pubkey(),
+            &mint.pubkey(),
+            &mint_authority,
+        );
+        if extensions.default_account_state_frozen {
+            thaw_token_2022_account(&mut litesvm, &mint.pubkey(), &token_account, &mint_authority);
+        }
+        token_account
+    };
+    let alice_token_account_a = create_user_token_account(&alice, &token_mint_a, token_a_extensions);
+    let alice_token_account_b = create_user_token_account(&alice, &token_mint_b, token_b_extensions);
+    let bob_token_account_a = create_user_token_account(&bob, &token_mint_a, token_a_extensions);
+    let bob_token_account_b = create_user_token_account(&bob, &token_mint_b, token_b_extensions);
+
+    mint_token_2022_to_account(
+        &mut litesvm,
+        &token_mint_a.pubkey(),
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        &mint_authority,
+    );
+    mint_token_2022_to_account(
+        &mut litesvm,
+        &token_mint_b.pubkey(),
+        &bob_token_account_b,
+        5 * TOKEN_B,
+        &mint_authority,
+    );
+
+    let (config_authority, fee_authority) = initialize_test_config(&mut litesvm, fee_bps);
+
+    EscrowTestEnvironment {
+        litesvm,
+        program_id,
+        token_program: spl_token_2022::ID,
+        _mint_authority: mint_authority,
         token_mint_a,
         token_mint_b,
-        maker_token_account_a,
-        offer_account,
-        vault,
+        alice,
+        bob,
+        alice_token_account_a,
+        alice_token_account_b,
+        bob_token_account_a,
+        bob_token_account_b,
+        config_authority,
+        fee_authority,
     }
 }
 
-pub fn build_make_offer_instruction(
-    offer_id: u64,
-    token_a_offered_amount: u64,
-    token_b_wanted_amount: u64,
-    accounts: MakeOfferAccounts,
-) -> Instruction {
-    let mut instruction_data = get_make_offer_discriminator();
-    instruction_data.extend_from_slice(&offer_id.to_le_bytes());
-    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
-    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+/// Creates and funds an extra user with token accounts for both mints
+///
+/// Returns the user's keypair and their token A and token B accounts. Useful for
+/// scenarios that need more participants than Alice and Bob, like competing bidders.
+pub fn create_user(
+    test_env: &mut EscrowTestEnvironment,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> (Keypair, Pubkey, Pubkey) {
+    let user = Keypair::new();
+    test_env.litesvm.airdrop(&user.pubkey(), 1_000_000_000).unwrap();
 
-    let account_metas = vec![
-        AccountMeta::new_readonly(accounts.associated_token_program, false),
-        AccountMeta::new_readonly(accounts.token_program, false),
-        AccountMeta::new_readonly(accounts.system_program, false),
-        AccountMeta::new(accounts.maker, true),
-        AccountMeta::new_readonly(accounts.token_mint_a, false),
-        AccountMeta::new_readonly(accounts.token_mint_b, false),
-        AccountMeta::new(accounts.maker_token_account_a, false),
-        AccountMeta::new(accounts.offer_account, false),
-        AccountMeta::new(accounts.vault, false),
-    ];
+    let user_token_account_a = create_associated_token_account(
+        &mut test_env.litesvm,
+        &user,
+        &test_env.token_mint_a.pubkey(),
+        &test_env._mint_authority,
+    )
+    .unwrap();
+    let user_token_account_b = create_associated_token_account(
+        &mut test_env.litesvm,
+        &user,
+        &test_env.token_mint_b.pubkey(),
+        &test_env._mint_authority,
+    )
+    .unwrap();
 
-    Instruction {
-        program_id: get_program_id(),
-        accounts: account_metas,
-        data: instruction_data,
+    if token_a_amount > 0 {
+        mint_tokens_to_account(
+            &mut test_env.litesvm,
+            &test_env.token_mint_a.pubkey(),
+            &user_token_account_a,
+            token_a_amount,
+            &test_env._mint_authority,
+        )
+        .unwrap();
+    }
+    if token_b_amount > 0 {
+        mint_tokens_to_account(
+            &mut test_env.litesvm,
+            &test_env.token_mint_b.pubkey(),
+            &user_token_account_b,
+            token_b_amount,
+            &test_env._mint_authority,
+        )
+        .unwrap();
     }
+
+    (user, user_token_account_a, user_token_account_b)
 }
 
-pub struct TakeOfferAccounts {
-    pub associated_token_program: Pubkey,
-    pub token_program: Pubkey,
-    pub system_program: Pubkey,
-    pub taker: Pubkey,
-    pub maker: Pubkey,
-    pub token_mint_a: Pubkey,
-    pub token_mint_b: Pubkey,
-    pub taker_token_account_a: Pubkey,
-    pub taker_token_account_b: Pubkey,
-    pub maker_token_account_b: Pubkey,
-    pub offer_account: Pubkey,
-    pub vault: Pubkey,
+/// Builds the account meta for an optional Anchor account
+///
+/// Anchor treats the program ID in an optional account's position as `None`.
+pub fn optional_account_meta(account: Option<Pubkey>, is_writable: bool) -> AccountMeta {
+    match account {
+        Some(account) if is_writable => AccountMeta::new(account, false),
+        Some(account) => AccountMeta::new_readonly(account, false),
+        None => AccountMeta::new_readonly(get_program_id(), false),
+    }
 }
 
-pub fn build_take_offer_instruction(accounts: TakeOfferAccounts) -> Instruction {
-    let instruction_data = get_take_offer_discriminator();
+/// Asserts a transaction failed with a specific escrow error, rather than just failing
+///
+/// Anchor reports program errors as custom instruction errors numbered from 6000.
+pub fn assert_escrow_error<T: std::fmt::Debug>(result: Result<T, SolanaKiteError>, error_code: ErrorCode) {
+    let error = result.expect_err("Transaction should have failed");
+    let custom_error = format!("Custom({})", u32::from(error_code));
+    assert!(
+        format!("{error:?}").contains(&custom_error),
+        "Expected {error_code:?} ({custom_error}), got {error:?}"
+    );
+}
 
-    let account_metas = vec![
-        AccountMeta::new_readonly(accounts.associated_token_program, false),
-        AccountMeta::new_readonly(accounts.token_program, false),
-        AccountMeta::new_readonly(accounts.system_program, false),
-        AccountMeta::new(accounts.taker, true),
-        AccountMeta::new(accounts.maker, false),
-        AccountMeta::new_readonly(accounts.token_mint_a, false),
-        AccountMeta::new_readonly(accounts.token_mint_b, false),
-        AccountMeta::new(accounts.taker_token_account_a, false),
-        AccountMeta::new(accounts.taker_token_account_b, false),
-        AccountMeta::new(accounts.maker_token_account_b, false),
-        AccountMeta::new(accounts.offer_account, false),
-        AccountMeta::new(accounts.vault, false),
-    ];
+/// Asserts a transaction failed with a specific Anchor framework error, like a failed seeds constraint
+pub fn assert_anchor_error<T: std::fmt::Debug>(
+    result: Result<T, SolanaKiteError>,
+    error_code: anchor_lang::error::ErrorCode,
+) {
+    let error = result.expect_err("Transaction should have failed");
+    let custom_error = format!("Custom({})", u32::from(error_code));
+    assert!(
+        format!("{error:?}").contains(&custom_error),
+        "Expected {error_code:?} ({custom_error}), got {error:?}"
+    );
+}
 
-    Instruction {
-        program_id: get_program_id(),
-        accounts: account_metas,
-        data: instruction_data,
+/// Reads a token account's balance, counting closed accounts as empty
+///
+/// Works for Token-2022 accounts too, since extensions come after the classic layout.
+pub fn get_token_balance(test_env: &EscrowTestEnvironment, token_account: &Pubkey) -> u64 {
+    use solana_program::program_pack::Pack;
+
+    match test_env.litesvm.get_account(token_account) {
+        Some(account) if !account.data.is_empty() => {
+            spl_token::state::Account::unpack(&account.data[..spl_token::state::Account::LEN])
+                .unwrap()
+                .amount
+        }
+        _ => 0,
     }
 }
 
-pub struct RefundOfferAccounts {
-    pub token_program: Pubkey,
-    pub system_program: Pubkey,
-    pub maker: Pubkey,
-    pub token_mint_a: Pubkey,
-    pub maker_token_account_a: Pubkey,
-    pub offer_account: Pubkey,
-    pub vault: Pubkey,
+/// Reads a classic token account, to check its delegate and how much the delegate may still spend
+pub fn get_token_account(test_env: &EscrowTestEnvironment, token_account: &Pubkey) -> spl_token::state::Account {
+    use solana_program::program_pack::Pack;
+
+    let account = test_env.litesvm.get_account(token_account).unwrap();
+    spl_token::state::Account::unpack(&account.data[..spl_token::state::Account::LEN]).unwrap()
 }
 
-pub fn build_refund_offer_instruction(accounts: RefundOfferAccounts) -> Instruction {
-    let instruction_data = get_refund_offer_discriminator();
+/// The transaction fee LiteSVM charges for each signature, paid by the fee payer
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
 
-    let account_metas = vec![
-        AccountMeta::new_readonly(accounts.token_program, false),
-        AccountMeta::new_readonly(accounts.system_program, false),
-        AccountMeta::new(accounts.maker, true),
-        AccountMeta::new_readonly(accounts.token_mint_a, false),
-        AccountMeta::new(accounts.maker_token_account_a, false),
-        AccountMeta::new(accounts.offer_account, false),
-        AccountMeta::new(accounts.vault, false),
-    ];
+/// SOL balances recorded before an instruction, to check who paid and received rent
+pub struct LamportBalances(Vec<(Pubkey, u64)>);
 
-    Instruction {
-        program_id: get_program_id(),
-        accounts: account_metas,
-        data: instruction_data,
-    }
+/// Records the SOL balances of accounts, counting accounts that don't exist yet as empty
+pub fn record_lamport_balances(test_env: &EscrowTestEnvironment, accounts: &[Pubkey]) -> LamportBalances {
+    LamportBalances(
+        accounts
+            .iter()
+            .map(|account| (*account, test_env.litesvm.get_balance(account).unwrap_or(0)))
+            .collect(),
+    )
 }
 
-/// Executes a complete make_offer flow: creates PDAs, builds accounts, and executes instruction
+/// Asserts each recorded account's SOL balance changed by exactly the expected amount
 ///
-/// This helper eliminates the repetitive pattern of creating offer_account and vault PDAs,
+/// Every recorded account needs an expected change, even if it's zero, so a payment
+/// to or from an account nobody expected fails the test rather than going unnoticed.
+pub fn assert_lamport_changes(
+    test_env: &EscrowTestEnvironment,
+    before: &LamportBalances,
+    expected_changes: &[(Pubkey, i64)],
+) {
+    assert_eq!(
+        before.0.len(),
+        expected_changes.len(),
+        "Every recorded account needs an expected change"
+    );
+    for (account, balance_before) in &before.0 {
+        let (_, expected_change) = expected_changes
+            .iter()
+            .find(|(expected_account, _)| expected_account == account)
+            .unwrap_or_else(|| panic!("No expected change for {account}"));
+        let balance_after = test_env.litesvm.get_balance(account).unwrap_or(0);
+        let change = balance_after as i64 - *balance_before as i64;
+        assert_eq!(
+            change, *expected_change,
+            "{account} changed by {change} lamports, expected {expected_change}"
+        );
+    }
+}
+
+/// The rent exempt minimum for an account of this size, signed so it can be used as a lamport change
+pub fn get_rent(test_env: &EscrowTestEnvironment, data_length: usize) -> i64 {
+    test_env.litesvm.minimum_balance_for_rent_exemption(data_length) as i64
+}
+
+/// Accounts recorded before an instruction, to check which ones it changed
+pub struct AccountsSnapshot(Vec<(Pubkey, Option<Account>)>);
+
+/// Records the full state of accounts, counting closed accounts and accounts that don't exist yet as missing
+pub fn snapshot_accounts(test_env: &EscrowTestEnvironment, accounts: &[Pubkey]) -> AccountsSnapshot {
+    AccountsSnapshot(
+        accounts
+            .iter()
+            .map(|address| (*address, get_open_account(test_env, address)))
+            .collect(),
+    )
+}
+
+fn get_open_account(test_env: &EscrowTestEnvironment, address: &Pubkey) -> Option<Account> {
+    test_env
+        .litesvm
+        .get_account(address)
+        .filter(|account| account.lamports > 0)
+}
+
+/// Describes how an account changed, or None if it didn't
+fn describe_account_change(before: &Option<Account>, after: &Option<Account>) -> Option<String> {
+    match (before, after) {
+        (None, None) => None,
+        (None, Some(after)) => Some(format!(
+            "created with {} bytes and {} lamports, owned by {}",
+            after.data.len(),
+            after.lamports,
+            after.owner
+        )),
+        (Some(before), None) => Some(format!("closed, it held {} lamports", before.lamports)),
+        (Some(before), Some(after)) => {
+            let mut changes = Vec::new();
+            if before.lamports != after.lamports {
+                changes.push(format!("lamports {} -> {}", before.lamports, after.lamports));
+            }
+            if before.owner != after.owner {
+                changes.push(format!("owner {} -> {}", before.owner, after.owner));
+            }
+            if before.data.len() != after.data.len() {
+                changes.push(format!("data length {} -> {}", before.data.len(), after.data.len()));
+            }
+            // Group the changed bytes into ranges, so a changed u64 reads as one change rather than eight
+            let mut changed_ranges: Vec<std::ops::Range<usize>> = Vec::new();
+            for (offset, (old, new)) in before.data.iter().zip(&after.data).enumerate() {
+                if old == new {
+                    continue;
+                }
+                match changed_ranges.last_mut() {
+                    Some(range) if range.end == offset => range.end += 1,
+                    _ => changed_ranges.push(offset..offset + 1),
+                }
+            }
+            if !changed_ranges.is_empty() {
+                let ranges: Vec<String> = changed_ranges
+                    .iter()
+                    .map(|range| format!("{}..{}", range.start, range.end))
+                    .collect();
+                changes.push(format!("data changed at bytes {}", ranges.join(", ")));
+            }
+            (!changes.is_empty()).then(|| changes.join(", "))
+        }
+    }
+}
+
+/// Asserts an instruction changed exactly the expected accounts out of those in the snapshot
+///
+/// Fails with a readable diff of every account that changed when it shouldn't have,
+/// or didn't change when it should have.
+pub fn assert_state_diff(
+    test_env: &EscrowTestEnvironment,
+    before: &AccountsSnapshot,
+    expected_changed_accounts: &[Pubkey],
+) {
+    let mut problems = Vec::new();
+    for (address, account_before) in &before.0 {
+        let account_after = get_open_account(test_env, address);
+        let change = describe_account_change(account_before, &account_after);
+        let expected_to_change = expected_changed_accounts.contains(address);
+        match (change, expected_to_change) {
+            (Some(change), false) => problems.push(format!("{address} changed unexpectedly: {change}")),
+            (None, true) => problems.push(format!("{address} was expected to change but didn't")),
+            _ => {}
+        }
+    }
+    assert!(problems.is_empty(), "Unexpected state diff:\n{}", problems.join("\n"));
+}
+
+/// Reads an offer account
+///
+/// Offers are zero copy, so this copies the fixed size offer out of the account data,
+/// leaving behind any metadata URI stored after it.
+pub fn get_offer(test_env: &EscrowTestEnvironment, offer_account: &Pubkey) -> Offer {
+    let account = test_env.litesvm.get_account(offer_account).unwrap();
+    Offer::try_deserialize(&mut &account.data[..Offer::METADATA_URI_OFFSET]).unwrap()
+}
+
+/// Reads the metadata URI stored after an offer in its account data
+pub fn get_offer_metadata_uri(test_env: &EscrowTestEnvironment, offer_account: &Pubkey) -> String {
+    let offer = get_offer(test_env, offer_account);
+    let account = test_env.litesvm.get_account(offer_account).unwrap();
+    let metadata_uri_end = Offer::METADATA_URI_OFFSET + offer.metadata_uri_length();
+    String::from_utf8(account.data[Offer::METADATA_URI_OFFSET..metadata_uri_end].to_vec()).unwrap()
+}
+
+/// Instructions that emit CPI events end with the event authority PDA and the program itself
+pub fn event_authority_account_meta() -> AccountMeta {
+    let (event_authority, _event_authority_bump) =
+        get_pda_and_bump(&[b"__event_authority".as_ref().into()], &get_program_id());
+    AccountMeta::new_readonly(event_authority, false)
+}
+
+pub fn program_account_meta() -> AccountMeta {
+    AccountMeta::new_readonly(get_program_id(), false)
+}
+
+pub fn get_program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID).unwrap()
+}
+
+thread_local! {
+    static OFFER_ID_COUNTER: Cell<u64> = const { Cell::new(1) };
+}
+
+/// Generates a unique offer ID for testing
+///
+/// This function returns incrementing offer IDs starting from 1, ensuring
+/// each test gets unique IDs to avoid conflicts between test cases.
+pub fn generate_offer_id() -> u64 {
+    OFFER_ID_COUNTER.with(|counter| {
+        let id = counter.get();
+        counter.set(id + 1);
+        id
+    })
+}
+
+pub fn get_make_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:make_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_make_offer_delegated_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:make_offer_delegated";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_replace_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:replace_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_make_offer_with_existing_vault_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:make_offer_with_existing_vault";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_create_offer_template_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:create_offer_template";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_spawn_offer_from_template_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:spawn_offer_from_template";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_close_offer_template_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:close_offer_template";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_refund_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:refund_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_refund_legacy_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:refund_legacy_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_claim_rent_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:claim_rent";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_nft_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_nft";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_initialize_config_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:initialize_config";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_withdraw_fees_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:withdraw_fees";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_pause_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:pause";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_unpause_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:unpause";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_mint_policy_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_mint_policy";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_royalties_required_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_royalties_required";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_limits_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_limits";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_mint_allowlist_enabled_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_mint_allowlist_enabled";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_allow_mint_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:allow_mint";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_revoke_mint_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:revoke_mint";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_deny_mint_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:deny_mint";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_undeny_mint_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:undeny_mint";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_fee_tiers_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_fee_tiers";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_membership_discount_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_membership_discount";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_matcher_share_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_matcher_share";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_match_offers_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:match_offers";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_route_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_route";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_request_quote_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:request_quote";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_submit_quote_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:submit_quote";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_accept_quote_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:accept_quote";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_withdraw_quote_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:withdraw_quote";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_close_quote_request_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:close_quote_request";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_make_stake_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:make_stake_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_stake_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_stake_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_cancel_stake_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:cancel_stake_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_min_offer_age_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_min_offer_age";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_max_open_offers_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_max_open_offers";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_role_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_role";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_admin_force_refund_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:admin_force_refund";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_close_orphaned_vault_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:close_orphaned_vault";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_attach_metadata_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:attach_metadata";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offers_batch_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offers_batch";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_exact_out_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_exact_out";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_with_token_accounts_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_with_token_accounts";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_with_authorization_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_with_authorization";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_extend_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:extend_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_top_up_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:top_up_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_reduce_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:reduce_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_refund_authority_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_refund_authority";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_vested_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_vested";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_claim_vested_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:claim_vested";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_installment_terms_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_installment_terms";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_in_installments_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_in_installments";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_pay_installment_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:pay_installment";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_cancel_default_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:cancel_default";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_attestor_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_attestor";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_set_eth_taker_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:set_eth_taker";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_deposit_for_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:deposit_for_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_withdraw_deposit_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:withdraw_deposit";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_confirm_swap_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:confirm_swap";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_update_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:update_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_make_counter_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:make_counter_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_accept_counter_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:accept_counter_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_reject_counter_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:reject_counter_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_place_bid_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:place_bid";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_outbid_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:outbid";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_settle_auction_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:settle_auction";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_add_allowed_taker_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:add_allowed_taker";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_remove_allowed_taker_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:remove_allowed_taker";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_migrate_offer_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:migrate_offer";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_take_offer_arbitrated_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:take_offer_arbitrated";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_release_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:release";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub fn get_revert_discriminator() -> Vec<u8> {
+    let discriminator_input = b"global:revert";
+    anchor_lang::solana_program::hash::hash(discriminator_input).to_bytes()[..8].to_vec()
+}
+
+pub struct MakeOfferAccounts {
+    pub associated_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    /// Signs for the maker, if it isn't the maker itself, such as a delegate of a multisig vault
+    pub maker_authority: Option<Pubkey>,
+    pub maker: Pubkey,
+    /// Pays the rent, if it isn't whoever signs for the maker, such as a frontend sponsoring the offer
+    pub payer: Option<Pubkey>,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub maker_token_account_a: Pubkey,
+    pub offer_account: Pubkey,
+    pub vault: Pubkey,
+    pub token_mint_a_metadata: Option<Pubkey>,
+    /// Only needed while the config's mint allowlist is enabled, see `get_mint_allowlist_entry_address`
+    pub token_mint_a_allowlist_entry: Option<Pubkey>,
+    pub token_mint_b_allowlist_entry: Option<Pubkey>,
+    /// Only needed when token b belongs to the other token program from token a
+    pub token_program_b: Option<Pubkey>,
+}
+
+/// Helper function to create MakeOfferAccounts with standard program IDs
+///
+/// This function eliminates the repetitive initialization of the three standard
+/// program IDs (associated_token_program, token_program, system_program) that
+/// are always the same constants across all tests. Instead of copy-pasting
+/// these three lines in every test, this helper focuses on the variable fields.
+pub fn build_make_offer_accounts(
+    maker: Pubkey,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> MakeOfferAccounts {
+    MakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
+        maker,
+        payer: None,
+        token_mint_a,
+        token_mint_b,
+        maker_token_account_a,
+        offer_account,
+        vault,
+        token_mint_a_metadata: None,
+        token_mint_a_allowlist_entry: None,
+        token_mint_b_allowlist_entry: None,
+        token_program_b: None,
+    }
+}
+
+/// The optional make_offer arguments, so tests for plain offers don't have to spell them out
+#[derive(Default)]
+pub struct MakeOfferOptions {
+    pub dutch_auction: Option<DutchAuction>,
+    pub english_auction: Option<EnglishAuction>,
+    pub additional_token_a_offered_amounts: Vec<u64>,
+    pub memo: Option<String>,
+    pub min_fill_amount: u64,
+    pub hashlock: Option<[u8; 32]>,
+    pub vesting_duration: Option<i64>,
+    pub arbiter: Option<Pubkey>,
+    pub keep_history: bool,
+    pub tags: [u8; 8],
+}
+
+pub fn build_make_offer_instruction(
+    offer_id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    accounts: MakeOfferAccounts,
+) -> Instruction {
+    build_make_offer_instruction_with_options(
+        offer_id,
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        MakeOfferOptions::default(),
+        accounts,
+    )
+}
+
+pub fn build_make_offer_instruction_with_options(
+    offer_id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    options: MakeOfferOptions,
+    accounts: MakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_make_offer_discriminator();
+    instruction_data.extend_from_slice(&offer_id.to_le_bytes());
+    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    options
+        .dutch_auction
+        .serialize(&mut instruction_data)
+        .unwrap();
+    options
+        .english_auction
+        .serialize(&mut instruction_data)
+        .unwrap();
+    options
+        .additional_token_a_offered_amounts
+        .serialize(&mut instruction_data)
+        .unwrap();
+    options.memo.serialize(&mut instruction_data).unwrap();
+    instruction_data.extend_from_slice(&options.min_fill_amount.to_le_bytes());
+    options.hashlock.serialize(&mut instruction_data).unwrap();
+    options.vesting_duration.serialize(&mut instruction_data).unwrap();
+    options.arbiter.serialize(&mut instruction_data).unwrap();
+    options.keep_history.serialize(&mut instruction_data).unwrap();
+    instruction_data.extend_from_slice(&options.tags);
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.maker_authority.unwrap_or(accounts.maker), true),
+        AccountMeta::new_readonly(accounts.maker, accounts.maker_authority.is_none()),
+        AccountMeta::new(
+            accounts.payer.or(accounts.maker_authority).unwrap_or(accounts.maker),
+            true,
+        ),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.maker_token_account_a, false),
+        AccountMeta::new(get_maker_counter_address(&accounts.maker), false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        optional_account_meta(accounts.token_mint_a_metadata, false),
+        optional_account_meta(accounts.token_mint_a_allowlist_entry, false),
+        optional_account_meta(accounts.token_mint_b_allowlist_entry, false),
+        AccountMeta::new_readonly(get_mint_deny_entry_address(&accounts.token_mint_a), false),
+        AccountMeta::new_readonly(get_mint_deny_entry_address(&accounts.token_mint_b), false),
+        optional_account_meta(accounts.token_program_b, false),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct TakeOfferAccounts {
+    pub associated_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    /// Gets the offer's rent back, if a sponsor paid it rather than the maker
+    pub rent_payer: Option<Pubkey>,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub taker_token_account_a: Pubkey,
+    pub taker_token_account_b: Pubkey,
+    pub maker_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub vault: Pubkey,
+    pub allowlist: Option<Pubkey>,
+    pub referrer_token_account: Option<Pubkey>,
+    /// Only needed when token b belongs to the other token program from token a
+    pub token_program_b: Option<Pubkey>,
+}
+
+/// Builds a take_offer instruction
+///
+/// The expected amounts are the offer terms the taker agreed to, the take fails if the offer has changed since.
+pub fn build_take_offer_instruction(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_instruction_with_preimage(
+        expected_token_a_amount,
+        expected_token_b_amount,
+        None,
+        accounts,
+    )
+}
+
+/// Builds a take_offer instruction revealing a preimage, for hashlocked offers
+pub fn build_take_offer_instruction_with_preimage(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    preimage: Option<Vec<u8>>,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_instruction_with_preimage_and_membership(
+        expected_token_a_amount,
+        expected_token_b_amount,
+        preimage,
+        None,
+        None,
+        accounts,
+    )
+}
+
+/// Builds a take_offer instruction passing the taker's membership token account, for the config's fee discount
+pub fn build_take_offer_instruction_with_membership(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    membership_token_account: Pubkey,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_instruction_with_preimage_and_membership(
+        expected_token_a_amount,
+        expected_token_b_amount,
+        None,
+        Some(membership_token_account),
+        None,
+        accounts,
+    )
+}
+
+/// Builds a take_offer instruction passing the instructions sysvar, for attested offers and offers with an Ethereum taker
+///
+/// The transaction needs the attestor's signature over build_attestation_message verified first, see build_ed25519_instruction,
+/// or the Ethereum taker's over the offer's and taker's addresses, see build_secp256k1_instruction.
+pub fn build_take_offer_instruction_with_attestation(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_instruction_with_preimage_and_membership(
+        expected_token_a_amount,
+        expected_token_b_amount,
+        None,
+        None,
+        Some(anchor_lang::solana_program::sysvar::instructions::ID),
+        accounts,
+    )
+}
+
+/// Builds a take_offer instruction where the taker pays token b and `recipient` receives token a
+///
+/// The taker's token a account is left out, take_offer only takes it without a recipient.
+pub fn build_take_offer_instruction_with_recipient(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    recipient: Pubkey,
+    recipient_token_account_a: Pubkey,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction = build_take_offer_instruction(expected_token_a_amount, expected_token_b_amount, accounts);
+    instruction.accounts[8] = optional_account_meta(None, false);
+    // The recipient and their token a account come just before the event authority and the program
+    let recipient_index = instruction.accounts.len() - 4;
+    instruction.accounts[recipient_index] = AccountMeta::new_readonly(recipient, false);
+    instruction.accounts[recipient_index + 1] = AccountMeta::new(recipient_token_account_a, false);
+    instruction
+}
+
+/// Works out the Ethereum address of a secp256k1 key, the last 20 bytes of the keccak-256 hash of its public key
+pub fn get_eth_address(secret_key: &libsecp256k1::SecretKey) -> [u8; ETH_ADDRESS_LENGTH] {
+    // Skip the 0x04 prefix marking an uncompressed public key
+    let public_key = libsecp256k1::PublicKey::from_secret_key(secret_key).serialize();
+    let hash = solana_program::keccak::hash(&public_key[1..]).to_bytes();
+    hash[32 - ETH_ADDRESS_LENGTH..].try_into().unwrap()
+}
+
+/// Builds a secp256k1 program instruction verifying `secret_key`'s signature over `message`
+///
+/// The secp256k1 program only takes absolute instruction indexes, so `instruction_index` must be the instruction's
+/// position in the transaction. The Ethereum address, signature and message all follow the offsets in its own data.
+pub fn build_secp256k1_instruction(
+    secret_key: &libsecp256k1::SecretKey,
+    message: &[u8],
+    instruction_index: u8,
+) -> Instruction {
+    const OFFSETS_START: u16 = 1;
+    const OFFSETS_SIZE: u16 = 11;
+    let eth_address_offset = OFFSETS_START + OFFSETS_SIZE;
+    let signature_offset = eth_address_offset + ETH_ADDRESS_LENGTH as u16;
+    // The recovery id follows the signature
+    let message_offset = signature_offset + 64 + 1;
+
+    let message_hash = solana_program::keccak::hash(message).to_bytes();
+    let (signature, recovery_id) =
+        libsecp256k1::sign(&libsecp256k1::Message::parse(&message_hash), secret_key);
+
+    let mut instruction_data = vec![1];
+    instruction_data.extend_from_slice(&signature_offset.to_le_bytes());
+    instruction_data.push(instruction_index);
+    instruction_data.extend_from_slice(&eth_address_offset.to_le_bytes());
+    instruction_data.push(instruction_index);
+    instruction_data.extend_from_slice(&message_offset.to_le_bytes());
+    instruction_data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+    instruction_data.push(instruction_index);
+    instruction_data.extend_from_slice(&get_eth_address(secret_key));
+    instruction_data.extend_from_slice(&signature.serialize());
+    instruction_data.push(recovery_id.serialize());
+    instruction_data.extend_from_slice(message);
+
+    Instruction {
+        program_id: solana_program::secp256k1_program::ID,
+        accounts: vec![],
+        data: instruction_data,
+    }
+}
+
+/// The message an attestor signs to let `taker` take an attested offer: the offer's address and created slot, the
+/// taker's address, then the offer's token mints and amounts, the slot and amounts little endian
+pub fn build_attestation_message(offer_account: &Pubkey, offer: &Offer, taker: &Pubkey) -> Vec<u8> {
+    [
+        offer_account.as_ref(),
+        &offer.created_slot.to_le_bytes(),
+        taker.as_ref(),
+        offer.token_mint_a.as_ref(),
+        offer.token_mint_b.as_ref(),
+        &offer.token_a_offered_amount.to_le_bytes(),
+        &offer.token_b_wanted_amount.to_le_bytes(),
+    ]
+    .concat()
+}
+
+/// Builds an ed25519 program instruction verifying `signer`'s signature over `message`
+///
+/// The public key, signature and message all follow the offsets in the instruction's own data.
+pub fn build_ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    const OFFSETS_START: u16 = 2;
+    const OFFSETS_SIZE: u16 = 14;
+    let public_key_offset = OFFSETS_START + OFFSETS_SIZE;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let this_instruction = u16::MAX;
+
+    let mut instruction_data = vec![1, 0];
+    for value in [
+        signature_offset,
+        this_instruction,
+        public_key_offset,
+        this_instruction,
+        message_offset,
+        message.len() as u16,
+        this_instruction,
+    ] {
+        instruction_data.extend_from_slice(&value.to_le_bytes());
+    }
+    instruction_data.extend_from_slice(signer.pubkey().as_ref());
+    instruction_data.extend_from_slice(signer.sign_message(message).as_ref());
+    instruction_data.extend_from_slice(message);
+
+    Instruction {
+        program_id: solana_program::ed25519_program::ID,
+        accounts: vec![],
+        data: instruction_data,
+    }
+}
+
+fn build_take_offer_instruction_with_preimage_and_membership(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    preimage: Option<Vec<u8>>,
+    membership_token_account: Option<Pubkey>,
+    instructions_sysvar: Option<Pubkey>,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_take_offer_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+    preimage.serialize(&mut instruction_data).unwrap();
+    None::<String>.serialize(&mut instruction_data).unwrap();
+
+    let mint_deny_entries = build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b);
+    let token_program_b = accounts.token_program_b;
+    let mut account_metas = build_take_offer_account_metas(accounts);
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.extend(mint_deny_entries);
+    account_metas.push(optional_account_meta(membership_token_account, false));
+    account_metas.push(optional_account_meta(token_program_b, false));
+    account_metas.push(optional_account_meta(instructions_sysvar, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Builds a take_offer instruction with a memo, which the memo program records with the settlement
+///
+/// `memo_program` is normally the SPL memo program, tests of a missing memo program pass None.
+pub fn build_take_offer_instruction_with_memo(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    memo: String,
+    memo_program: Option<Pubkey>,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_take_offer_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+    None::<Vec<u8>>.serialize(&mut instruction_data).unwrap();
+    Some(memo).serialize(&mut instruction_data).unwrap();
+
+    let mint_deny_entries = build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b);
+    let token_program_b = accounts.token_program_b;
+    let mut account_metas = build_take_offer_account_metas(accounts);
+    account_metas.push(optional_account_meta(memo_program, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.extend(mint_deny_entries);
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(token_program_b, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Builds a take_offer_nft instruction, which uses the same accounts as take_offer
+pub fn build_take_offer_nft_instruction(
+    expected_collection: Option<Pubkey>,
+    expected_token_b_amount: u64,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_nft_instruction_with_metadata(
+        expected_collection,
+        expected_token_b_amount,
+        None,
+        &[],
+        accounts,
+    )
+}
+
+/// Builds a take_offer_nft instruction that pays the NFT's creators their royalty
+///
+/// `creator_token_accounts` are the creators' token b accounts, in the order the metadata lists them.
+pub fn build_take_offer_nft_instruction_with_royalties(
+    expected_collection: Option<Pubkey>,
+    expected_token_b_amount: u64,
+    token_mint_a_metadata: Pubkey,
+    creator_token_accounts: &[Pubkey],
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    build_take_offer_nft_instruction_with_metadata(
+        expected_collection,
+        expected_token_b_amount,
+        Some(token_mint_a_metadata),
+        creator_token_accounts,
+        accounts,
+    )
+}
+
+fn build_take_offer_nft_instruction_with_metadata(
+    expected_collection: Option<Pubkey>,
+    expected_token_b_amount: u64,
+    token_mint_a_metadata: Option<Pubkey>,
+    creator_token_accounts: &[Pubkey],
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_take_offer_nft_discriminator();
+    expected_collection
+        .serialize(&mut instruction_data)
+        .unwrap();
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+    // NFT offers in these tests are never hashlocked
+    None::<Vec<u8>>.serialize(&mut instruction_data).unwrap();
+
+    // take_offer_nft shares take_offer's accounts, but has no memo
+    let mint_deny_entries = build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b);
+    let token_program_b = accounts.token_program_b;
+    let mut account_metas = build_take_offer_account_metas(accounts);
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(token_mint_a_metadata, false));
+    account_metas.extend(mint_deny_entries);
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(token_program_b, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.push(optional_account_meta(None, false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+    account_metas.extend(
+        creator_token_accounts
+            .iter()
+            .map(|creator_token_account| AccountMeta::new(*creator_token_account, false)),
+    );
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Builds a take_offer_exact_out instruction
+///
+/// take_offer_exact_out has no referrer, so `referrer_token_account` is ignored.
+pub fn build_take_offer_exact_out_instruction(token_a_amount: u64, accounts: TakeOfferAccounts) -> Instruction {
+    let mut instruction_data = get_take_offer_exact_out_discriminator();
+    instruction_data.extend_from_slice(&token_a_amount.to_le_bytes());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new(accounts.rent_payer.unwrap_or(accounts.maker), false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.taker_token_account_a, false),
+        AccountMeta::new(accounts.taker_token_account_b, false),
+        AccountMeta::new(accounts.maker_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_a), false),
+        optional_account_meta(accounts.allowlist, true),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Builds a take_offer_with_token_accounts instruction, sending to and from whichever of the taker's token accounts
+/// `accounts` names rather than their associated token accounts
+///
+/// take_offer_with_token_accounts has no referrer, so `referrer_token_account` is ignored.
+pub fn build_take_offer_with_token_accounts_instruction(
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_take_offer_with_token_accounts_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+
+    let mint_deny_entries = build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b);
+    let token_program_b = accounts.token_program_b;
+    let mut account_metas = build_take_offer_account_metas(accounts);
+    // Drop the referrer, take_offer_with_token_accounts ends its accounts at the allowlist
+    account_metas.pop();
+    account_metas.extend(mint_deny_entries);
+    account_metas.push(optional_account_meta(token_program_b, false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Builds a take_offer_with_authorization instruction, submitted and paid for by `relayer` rather than the taker
+///
+/// The transaction needs the taker's signature over the authorization verified first, see
+/// build_taker_authorization_message and build_ed25519_instruction.
+pub fn build_take_offer_with_authorization_instruction(
+    relayer: Pubkey,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    authorization_expires_at: i64,
+    accounts: TakeOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_take_offer_with_authorization_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&authorization_expires_at.to_le_bytes());
+
+    let taker = accounts.taker;
+    let mint_deny_entries = build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b);
+    let token_program_b = accounts.token_program_b;
+    let mut account_metas = build_take_offer_account_metas(accounts);
+    // The relayer signs in the taker's place, and the taker authority follows the taker's token b account
+    account_metas[3] = AccountMeta::new(relayer, true);
+    account_metas.insert(4, AccountMeta::new_readonly(taker, false));
+    account_metas.insert(11, AccountMeta::new_readonly(get_taker_authority_address(&taker), false));
+    // Drop the referrer, take_offer_with_authorization ends its accounts at the allowlist
+    account_metas.pop();
+    account_metas.extend(mint_deny_entries);
+    account_metas.push(optional_account_meta(token_program_b, false));
+    account_metas.push(AccountMeta::new_readonly(anchor_lang::solana_program::sysvar::instructions::ID, false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// The message a taker signs to authorize take_offer_with_authorization: the offer's address, token mints and created
+/// slot, then the expected amounts and the authorization's expiry, each little endian
+pub fn build_taker_authorization_message(
+    offer_account: &Pubkey,
+    offer: &Offer,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    authorization_expires_at: i64,
+) -> Vec<u8> {
+    [
+        offer_account.as_ref(),
+        offer.token_mint_a.as_ref(),
+        offer.token_mint_b.as_ref(),
+        &offer.created_slot.to_le_bytes(),
+        &expected_token_a_amount.to_le_bytes(),
+        &expected_token_b_amount.to_le_bytes(),
+        &authorization_expires_at.to_le_bytes(),
+    ]
+    .concat()
+}
+
+/// The take_offer accounts up to the referrer, callers add the memo program, token a's metadata, the mint deny entries,
+/// the membership token account, token b's token program, the instructions sysvar and the event accounts
+fn build_take_offer_account_metas(accounts: TakeOfferAccounts) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new(accounts.rent_payer.unwrap_or(accounts.maker), false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.taker_token_account_a, false),
+        AccountMeta::new(accounts.taker_token_account_b, false),
+        AccountMeta::new(accounts.maker_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_a), false),
+        optional_account_meta(accounts.allowlist, true),
+        optional_account_meta(accounts.referrer_token_account, true),
+    ]
+}
+
+/// Both mints' deny entry addresses, which make_offer and take_offer check while the protocol denies any mints
+///
+/// Like the SDK, the tests always pass them, they only have an account once the mint is denied.
+/// The accounts for match_offers, for two offers made with the classic token program whose makers paid their rent
+pub struct MatchOffersAccounts {
+    pub matcher: Pubkey,
+    /// Offers token a for token b
+    pub first_maker: Pubkey,
+    pub first_offer: Pubkey,
+    /// Offers token b for token a
+    pub second_maker: Pubkey,
+    pub second_offer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub matcher_token_account_a: Pubkey,
+    pub matcher_token_account_b: Pubkey,
+}
+
+/// Builds a match_offers instruction, signed by the matcher
+pub fn build_match_offers_instruction(accounts: MatchOffersAccounts) -> Instruction {
+    let associated_token_address = |owner: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(owner, mint, &spl_token::ID)
+    };
+    let first_vault_authority = get_vault_authority_address(&accounts.first_offer);
+    let second_vault_authority = get_vault_authority_address(&accounts.second_offer);
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(accounts.matcher, true),
+        AccountMeta::new(accounts.first_maker, false),
+        AccountMeta::new(accounts.first_maker, false),
+        AccountMeta::new(accounts.second_maker, false),
+        AccountMeta::new(accounts.second_maker, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.first_offer, false),
+        AccountMeta::new_readonly(first_vault_authority, false),
+        AccountMeta::new(associated_token_address(&first_vault_authority, &accounts.token_mint_a), false),
+        AccountMeta::new(accounts.second_offer, false),
+        AccountMeta::new_readonly(second_vault_authority, false),
+        AccountMeta::new(associated_token_address(&second_vault_authority, &accounts.token_mint_b), false),
+        AccountMeta::new(associated_token_address(&accounts.first_maker, &accounts.token_mint_a), false),
+        AccountMeta::new(associated_token_address(&accounts.first_maker, &accounts.token_mint_b), false),
+        AccountMeta::new(associated_token_address(&accounts.second_maker, &accounts.token_mint_a), false),
+        AccountMeta::new(associated_token_address(&accounts.second_maker, &accounts.token_mint_b), false),
+        AccountMeta::new(accounts.matcher_token_account_a, false),
+        AccountMeta::new(accounts.matcher_token_account_b, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.first_maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.first_maker), false),
+        AccountMeta::new(get_maker_index_address(&accounts.second_maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.second_maker), false),
+    ];
+    account_metas.extend(build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b));
+    account_metas.extend([
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_a), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_b), false),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ]);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: get_match_offers_discriminator(),
+    }
+}
+
+/// The accounts for take_offer_route, for two offers made with the classic token program whose makers paid their rent
+pub struct TakeOfferRouteAccounts {
+    pub taker: Pubkey,
+    /// Offers token a for token b
+    pub first_maker: Pubkey,
+    pub first_offer: Pubkey,
+    /// Offers token b for token c
+    pub second_maker: Pubkey,
+    pub second_offer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_mint_c: Pubkey,
+}
+
+/// Builds a take_offer_route instruction, signed by the taker, who pays with their token c account
+pub fn build_take_offer_route_instruction(
+    accounts: TakeOfferRouteAccounts,
+    expected_token_a_amount: u64,
+    maximum_token_c_amount: u64,
+) -> Instruction {
+    let associated_token_address = |owner: &Pubkey, mint: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(owner, mint, &spl_token::ID)
+    };
+    let first_vault_authority = get_vault_authority_address(&accounts.first_offer);
+    let second_vault_authority = get_vault_authority_address(&accounts.second_offer);
+    let (route_authority, _route_authority_bump) = get_pda_and_bump(
+        &[b"route_authority".as_ref().into(), accounts.taker.as_ref().into()],
+        &get_program_id(),
+    );
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(accounts.first_maker, false),
+        AccountMeta::new(accounts.first_maker, false),
+        AccountMeta::new(accounts.second_maker, false),
+        AccountMeta::new(accounts.second_maker, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new_readonly(accounts.token_mint_c, false),
+        AccountMeta::new(accounts.first_offer, false),
+        AccountMeta::new_readonly(first_vault_authority, false),
+        AccountMeta::new(associated_token_address(&first_vault_authority, &accounts.token_mint_a), false),
+        AccountMeta::new(accounts.second_offer, false),
+        AccountMeta::new_readonly(second_vault_authority, false),
+        AccountMeta::new(associated_token_address(&second_vault_authority, &accounts.token_mint_b), false),
+        AccountMeta::new_readonly(route_authority, false),
+        AccountMeta::new(get_route_token_account_address(&accounts.taker, &accounts.token_mint_b), false),
+        AccountMeta::new(associated_token_address(&accounts.taker, &accounts.token_mint_a), false),
+        AccountMeta::new(associated_token_address(&accounts.taker, &accounts.token_mint_b), false),
+        AccountMeta::new(associated_token_address(&accounts.taker, &accounts.token_mint_c), false),
+        AccountMeta::new(associated_token_address(&accounts.first_maker, &accounts.token_mint_b), false),
+        AccountMeta::new(associated_token_address(&accounts.second_maker, &accounts.token_mint_c), false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.first_maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.first_maker), false),
+        AccountMeta::new(get_maker_index_address(&accounts.second_maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.second_maker), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_a), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_b), false),
+    ];
+    account_metas.extend(build_mint_deny_entry_account_metas(&accounts.token_mint_a, &accounts.token_mint_b));
+    account_metas.push(AccountMeta::new_readonly(get_mint_deny_entry_address(&accounts.token_mint_c), false));
+    account_metas.extend([event_authority_account_meta(), program_account_meta()]);
+
+    let mut instruction_data = get_take_offer_route_discriminator();
+    expected_token_a_amount.serialize(&mut instruction_data).unwrap();
+    maximum_token_c_amount.serialize(&mut instruction_data).unwrap();
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+fn build_mint_deny_entry_account_metas(token_mint_a: &Pubkey, token_mint_b: &Pubkey) -> [AccountMeta; 2] {
+    [
+        AccountMeta::new_readonly(get_mint_deny_entry_address(token_mint_a), false),
+        AccountMeta::new_readonly(get_mint_deny_entry_address(token_mint_b), false),
+    ]
+}
+
+pub struct RefundOfferAccounts {
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    /// Signs for the maker, if it isn't the maker itself
+    pub maker_authority: Option<Pubkey>,
+    pub maker: Pubkey,
+    /// Gets the offer's rent back, if a sponsor paid it rather than the maker
+    pub rent_payer: Option<Pubkey>,
+    pub token_mint_a: Pubkey,
+    pub maker_token_account_a: Pubkey,
+    pub offer_account: Pubkey,
+    pub vault: Pubkey,
+    pub allowlist: Option<Pubkey>,
+}
+
+pub fn build_refund_offer_instruction(accounts: RefundOfferAccounts) -> Instruction {
+    let instruction_data = get_refund_offer_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new_readonly(accounts.maker_authority.unwrap_or(accounts.maker), true),
+        AccountMeta::new(accounts.maker, accounts.maker_authority.is_none()),
+        AccountMeta::new(accounts.rent_payer.unwrap_or(accounts.maker), false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new(accounts.maker_token_account_a, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        optional_account_meta(accounts.allowlist, true),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes a complete make_offer flow: creates PDAs, builds accounts, and executes instruction
+///
+/// This helper eliminates the repetitive pattern of creating offer_account and vault PDAs,
 /// building MakeOfferAccounts, and executing the make_offer instruction that appears in
 /// multiple tests.
 pub fn execute_make_offer(
     test_env: &mut EscrowTestEnvironment,
-    offer_id: u64,
+    offer_id: u64,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+) -> Result<(Pubkey, Pubkey), SolanaKiteError> {
+    execute_make_offer_with_options(
+        test_env,
+        offer_id,
+        maker,
+        maker_token_account_a,
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        MakeOfferOptions::default(),
+    )
+}
+
+/// Executes a complete make_offer flow with the optional make_offer arguments
+pub fn execute_make_offer_with_options(
+    test_env: &mut EscrowTestEnvironment,
+    offer_id: u64,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    options: MakeOfferOptions,
+) -> Result<(Pubkey, Pubkey), SolanaKiteError> {
+    // Create PDAs
+    let offer_account = get_offer_address(&maker.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &get_vault_authority_address(&offer_account),
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    // Build accounts
+    let make_offer_accounts = MakeOfferAccounts {
+        token_program: test_env.token_program,
+        ..build_make_offer_accounts(
+            maker.pubkey(),
+            test_env.token_mint_a.pubkey(),
+            test_env.token_mint_b.pubkey(),
+            maker_token_account_a,
+            offer_account,
+            vault,
+        )
+    };
+
+    // Build and execute instruction
+    let make_offer_instruction = build_make_offer_instruction_with_options(
+        offer_id,
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        options,
+        make_offer_accounts,
+    );
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![make_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )?;
+
+    Ok((offer_account, vault))
+}
+
+/// Executes make_offer_delegated, which approves the offer to spend from the maker's token A account
+///
+/// Returns the offer. Delegated offers have no vault, so pass the maker's token A account
+/// wherever a vault is needed.
+pub fn execute_make_offer_delegated(
+    test_env: &mut EscrowTestEnvironment,
+    offer_id: u64,
+    maker: &Keypair,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    tags: [u8; 8],
+) -> Result<Pubkey, SolanaKiteError> {
+    let offer_account = get_offer_address(&maker.pubkey(), offer_id);
+    let maker_token_account_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker.pubkey(),
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    let mut instruction_data = get_make_offer_delegated_discriminator();
+    instruction_data.extend_from_slice(&offer_id.to_le_bytes());
+    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&tags);
+
+    let make_offer_delegated_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(get_maker_counter_address(&maker.pubkey()), false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&maker.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&maker.pubkey()), false),
+            optional_account_meta(None, false),
+            optional_account_meta(None, false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_a.pubkey()), false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_b.pubkey()), false),
+            optional_account_meta(None, false),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![make_offer_delegated_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )?;
+
+    Ok(offer_account)
+}
+
+/// Builds a make_offer_with_existing_vault instruction for one of the maker's offers of token A for token B
+///
+/// The offer's vault, see `get_vault_authority_address`, must already hold the offered token A when it runs.
+pub fn build_make_offer_with_existing_vault_instruction(
+    test_env: &EscrowTestEnvironment,
+    offer_id: u64,
+    maker: &Pubkey,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    tags: [u8; 8],
+) -> Instruction {
+    let offer_account = get_offer_address(maker, offer_id);
+    let vault_authority = get_vault_authority_address(&offer_account);
+    let vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &vault_authority,
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    let mut instruction_data = get_make_offer_with_existing_vault_discriminator();
+    instruction_data.extend_from_slice(&offer_id.to_le_bytes());
+    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&tags);
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(*maker, true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(get_maker_counter_address(maker), false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(vault_authority, false),
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(maker), false),
+            AccountMeta::new(get_maker_stats_address(maker), false),
+            optional_account_meta(None, false),
+            optional_account_meta(None, false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_a.pubkey()), false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_b.pubkey()), false),
+            optional_account_meta(None, false),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: instruction_data,
+    }
+}
+
+/// Executes replace_offer, refunding one of the maker's offers of token A for token B and making `new_offer_id`
+/// in its place, returning the new offer and its vault
+pub fn execute_replace_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    new_offer_id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+) -> Result<(Pubkey, Pubkey), SolanaKiteError> {
+    let token_mint_a = test_env.token_mint_a.pubkey();
+    let token_mint_b = test_env.token_mint_b.pubkey();
+    let associated_token_address = |owner: &Pubkey| {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            owner,
+            &token_mint_a,
+            &test_env.token_program,
+        )
+    };
+    let offer = get_offer(test_env, &offer_account);
+    let vault_authority = get_vault_authority_address(&offer_account);
+    let new_offer_account = get_offer_address(&maker.pubkey(), new_offer_id);
+    let new_vault_authority = get_vault_authority_address(&new_offer_account);
+    let new_vault = associated_token_address(&new_vault_authority);
+
+    let mut instruction_data = get_replace_offer_discriminator();
+    instruction_data.extend_from_slice(&new_offer_id.to_le_bytes());
+    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    None::<i64>.serialize(&mut instruction_data).unwrap();
+
+    let replace_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new(offer.rent_payer, false),
+            AccountMeta::new_readonly(token_mint_a, false),
+            AccountMeta::new_readonly(token_mint_b, false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(vault_authority, false),
+            AccountMeta::new(associated_token_address(&offer.token_a_holder(&vault_authority)), false),
+            AccountMeta::new(get_maker_counter_address(&maker.pubkey()), false),
+            AccountMeta::new(new_offer_account, false),
+            AccountMeta::new_readonly(new_vault_authority, false),
+            AccountMeta::new(new_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&maker.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&maker.pubkey()), false),
+            optional_account_meta(None, true),
+            optional_account_meta(None, false),
+            optional_account_meta(None, false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&token_mint_a), false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&token_mint_b), false),
+            optional_account_meta(None, false),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![replace_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )?;
+
+    Ok((new_offer_account, new_vault))
+}
+
+/// Creates a template for the maker's offers of token A for token B, approving it to spend total_token_a_amount
+pub fn execute_create_offer_template(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    total_token_a_amount: u64,
+    tags: [u8; 8],
+) -> Result<Pubkey, SolanaKiteError> {
+    let offer_template = get_offer_template_address(
+        &maker.pubkey(),
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_mint_b.pubkey(),
+    );
+    let maker_token_account_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker.pubkey(),
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    let mut instruction_data = get_create_offer_template_discriminator();
+    instruction_data.extend_from_slice(&token_a_offered_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&total_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&tags);
+
+    let create_offer_template_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(offer_template, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            optional_account_meta(None, false),
+            optional_account_meta(None, false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_a.pubkey()), false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_b.pubkey()), false),
+            optional_account_meta(None, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![create_offer_template_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )?;
+
+    Ok(offer_template)
+}
+
+/// Makes the template's next offer, paid for by the payer, like a keeper would
+///
+/// Passes the template's current offer as the previous offer, and returns the new offer's address.
+pub fn execute_spawn_offer_from_template(
+    test_env: &mut EscrowTestEnvironment,
+    payer: &Keypair,
+    maker: &Pubkey,
+) -> Result<Pubkey, SolanaKiteError> {
+    let offer_template_address = get_offer_template_address(
+        maker,
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_mint_b.pubkey(),
+    );
+    let previous_offer = test_env
+        .litesvm
+        .get_account(&offer_template_address)
+        .and_then(|account| OfferTemplate::try_deserialize(&mut account.data.as_slice()).ok())
+        .and_then(|offer_template| offer_template.current_offer);
+    // Makers who haven't made an offer yet don't have a counter, and start from 0
+    let offer_id = test_env
+        .litesvm
+        .get_account(&get_maker_counter_address(maker))
+        .map_or(0, |account| {
+            MakerCounter::try_deserialize(&mut account.data.as_slice()).unwrap().next_id
+        });
+    let offer_account = get_offer_address(maker, offer_id);
+    let vault_authority = get_vault_authority_address(&offer_account);
+    let vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &vault_authority,
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+    let maker_token_account_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        maker,
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    let spawn_offer_from_template_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(*maker, false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(offer_template_address, false),
+            optional_account_meta(previous_offer, false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(get_maker_counter_address(maker), false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(vault_authority, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(maker), false),
+            AccountMeta::new(get_maker_stats_address(maker), false),
+            optional_account_meta(None, false),
+            optional_account_meta(None, false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_a.pubkey()), false),
+            AccountMeta::new_readonly(get_mint_deny_entry_address(&test_env.token_mint_b.pubkey()), false),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: get_spawn_offer_from_template_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![spawn_offer_from_template_instruction],
+        &[payer],
+        &payer.pubkey(),
+    )?;
+
+    Ok(offer_account)
+}
+
+pub fn execute_close_offer_template(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let maker_token_account_a = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker.pubkey(),
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_program,
+    );
+
+    let close_offer_template_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(
+                get_offer_template_address(
+                    &maker.pubkey(),
+                    &test_env.token_mint_a.pubkey(),
+                    &test_env.token_mint_b.pubkey(),
+                ),
+                false,
+            ),
+            AccountMeta::new(maker_token_account_a, false),
+        ],
+        data: get_close_offer_template_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![close_offer_template_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes a complete take_offer flow: builds accounts and executes instruction
+///
+/// Like a real client, this reads the offer first and expects the terms it currently has.
+pub fn execute_take_offer(
+    test_env: &mut EscrowTestEnvironment,
+    taker: &Keypair,
+    maker: &Keypair,
+    taker_token_account_a: Pubkey,
+    taker_token_account_b: Pubkey,
+    maker_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let offer = get_offer(test_env, &offer_account);
+    let take_offer_accounts = TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: test_env.token_program,
+        system_program: anchor_lang::system_program::ID,
+        taker: taker.pubkey(),
+        maker: maker.pubkey(),
+        rent_payer: Some(offer.rent_payer),
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        taker_token_account_a,
+        taker_token_account_b,
+        maker_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    };
+
+    let take_offer_instruction = build_take_offer_instruction(
+        offer.token_a_offered_amount,
+        offer.token_b_wanted_amount,
+        take_offer_accounts,
+    );
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offer_instruction],
+        &[taker],
+        &taker.pubkey(),
+    )
+}
+
+/// Races two takers for the same offer, like two take_offer transactions landing in the same block
+///
+/// Bob and a second taker both read the offer and build take_offer with the terms they
+/// saw, then Bob's lands first. Checks Bob's take closed the offer and the second take
+/// moved none of the second taker's tokens, then returns the second taker and their result.
+pub fn execute_competing_takes(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    maker_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> (Keypair, Result<(), SolanaKiteError>) {
+    let offer = get_offer(test_env, &offer_account);
+    let (second_taker, second_taker_token_account_a, second_taker_token_account_b) =
+        create_user(test_env, 0, offer.token_b_wanted_amount);
+
+    let takers = [
+        (test_env.bob.insecure_clone(), test_env.bob_token_account_a, test_env.bob_token_account_b),
+        (second_taker.insecure_clone(), second_taker_token_account_a, second_taker_token_account_b),
+    ];
+    let [first_take_instruction, second_take_instruction] = takers.each_ref().map(
+        |(taker, taker_token_account_a, taker_token_account_b)| {
+            build_take_offer_instruction(
+                offer.token_a_offered_amount,
+                offer.token_b_wanted_amount,
+                TakeOfferAccounts {
+                    associated_token_program: spl_associated_token_account::ID,
+                    token_program: test_env.token_program,
+                    system_program: anchor_lang::system_program::ID,
+                    taker: taker.pubkey(),
+                    maker: maker.pubkey(),
+                    rent_payer: Some(offer.rent_payer),
+                    token_mint_a: test_env.token_mint_a.pubkey(),
+                    token_mint_b: test_env.token_mint_b.pubkey(),
+                    taker_token_account_a: *taker_token_account_a,
+                    taker_token_account_b: *taker_token_account_b,
+                    maker_token_account_b,
+                    offer_account,
+                    vault,
+                    allowlist: None,
+                    referrer_token_account: None,
+                    token_program_b: None,
+                },
+            )
+        },
+    );
+
+    let bob = test_env.bob.insecure_clone();
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![first_take_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .expect("The first take should succeed");
+    assert!(
+        get_open_account(test_env, &offer_account).is_none(),
+        "The first take should close the offer"
+    );
+
+    let second_take_result = send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![second_take_instruction],
+        &[&second_taker],
+        &second_taker.pubkey(),
+    );
+    assert_eq!(
+        get_token_balance(test_env, &second_taker_token_account_b),
+        offer.token_b_wanted_amount,
+        "The second taker's token B should not have moved"
+    );
+    assert_eq!(
+        get_token_balance(test_env, &second_taker_token_account_a),
+        0,
+        "The second taker should not receive any token A"
+    );
+
+    (second_taker, second_take_result)
+}
+
+/// Sends a transaction and returns the compute units it used, for benchmarks
+///
+/// send_transaction_from_instructions doesn't return the transaction metadata, so this sends it directly.
+pub fn send_transaction_and_get_compute_units(
+    litesvm: &mut LiteSVM,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+    payer: &Pubkey,
+) -> u64 {
+    let message = Message::new(&instructions, Some(payer));
+    let transaction = Transaction::new(signers, message, litesvm.latest_blockhash());
+    litesvm
+        .send_transaction(transaction)
+        .unwrap_or_else(|failed| panic!("Transaction failed: {:?}", failed.err))
+        .compute_units_consumed
+}
+
+/// Sends a transaction and returns its logs, for checking what other programs it called recorded
+pub fn send_transaction_and_get_logs(
+    litesvm: &mut LiteSVM,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+    payer: &Pubkey,
+) -> Vec<String> {
+    let message = Message::new(&instructions, Some(payer));
+    let transaction = Transaction::new(signers, message, litesvm.latest_blockhash());
+    litesvm
+        .send_transaction(transaction)
+        .unwrap_or_else(|failed| panic!("Transaction failed: {:?}", failed.err))
+        .logs
+}
+
+/// Sends a transaction and returns the data its last instruction returned, like the id make_offer assigned
+pub fn send_transaction_and_get_return_data(
+    litesvm: &mut LiteSVM,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+    payer: &Pubkey,
+) -> Vec<u8> {
+    let message = Message::new(&instructions, Some(payer));
+    let transaction = Transaction::new(signers, message, litesvm.latest_blockhash());
+    litesvm
+        .send_transaction(transaction)
+        .unwrap_or_else(|failed| panic!("Transaction failed: {:?}", failed.err))
+        .return_data
+        .data
+}
+
+/// The ComputeBudget instructions production clients start their transactions with
+///
+/// compute_unit_price is in micro-lamports per compute unit, the priority fee.
+pub fn build_compute_budget_instructions(compute_unit_limit: u32, compute_unit_price: u64) -> Vec<Instruction> {
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+    ]
+}
+
+/// Sends instructions the way production clients do, as a v0 transaction after ComputeBudget instructions
+///
+/// The limit covers the whole transaction, including the ComputeBudget instructions themselves, so
+/// an instruction that grows past what clients reserve for it fails here instead of on mainnet.
+/// Returns the metadata or the failure rather than panicking, so tests can check either.
+pub fn send_versioned_transaction_with_compute_budget(
+    litesvm: &mut LiteSVM,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+    payer: &Pubkey,
+    compute_unit_limit: u32,
+    compute_unit_price: u64,
+) -> TransactionResult {
+    let mut all_instructions = build_compute_budget_instructions(compute_unit_limit, compute_unit_price);
+    all_instructions.extend(instructions);
+    let message = v0::Message::try_compile(payer, &all_instructions, &[], litesvm.latest_blockhash())
+        .expect("Failed to compile v0 message");
+    let transaction = VersionedTransaction::try_new(VersionedMessage::V0(message), signers)
+        .expect("Failed to sign versioned transaction");
+    litesvm.send_transaction(transaction)
+}
+
+/// Executes a complete refund_offer flow: builds accounts and executes instruction
+pub fn execute_refund_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let refund_offer_accounts = RefundOfferAccounts {
+        token_program: test_env.token_program,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
+        maker: maker.pubkey(),
+        rent_payer: Some(get_offer(test_env, &offer_account).rent_payer),
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        maker_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    };
+
+    let refund_instruction = build_refund_offer_instruction(refund_offer_accounts);
+    
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![refund_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+pub struct UpdateOfferAccounts {
+    pub maker: Pubkey,
+    pub offer_account: Pubkey,
+}
+
+pub fn build_update_offer_instruction(
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+    accounts: UpdateOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_update_offer_discriminator();
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    match expires_at {
+        Some(expires_at) => {
+            instruction_data.push(1);
+            instruction_data.extend_from_slice(&expires_at.to_le_bytes());
+        }
+        None => instruction_data.push(0),
+    }
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.maker, true),
+        AccountMeta::new(accounts.offer_account, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes a complete update_offer flow: builds accounts and executes instruction
+pub fn execute_update_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+) -> Result<(), SolanaKiteError> {
+    let update_offer_accounts = UpdateOfferAccounts {
+        maker: maker.pubkey(),
+        offer_account,
+    };
+
+    let update_offer_instruction =
+        build_update_offer_instruction(token_b_wanted_amount, expires_at, update_offer_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![update_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes extend_offer, which uses the same accounts as update_offer
+pub fn execute_extend_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    expires_at: i64,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_extend_offer_discriminator();
+    instruction_data.extend_from_slice(&expires_at.to_le_bytes());
+
+    let extend_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![extend_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes set_refund_authority, which uses the same accounts as update_offer
+pub fn execute_set_refund_authority(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    refund_authority: Option<Pubkey>,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_refund_authority_discriminator();
+    refund_authority.serialize(&mut instruction_data).unwrap();
+
+    let set_refund_authority_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_refund_authority_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes set_attestor, which uses the same accounts as update_offer
+pub fn execute_set_attestor(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    attestor: Option<Pubkey>,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_attestor_discriminator();
+    attestor.serialize(&mut instruction_data).unwrap();
+
+    let set_attestor_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_attestor_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes set_eth_taker, which uses the same accounts as update_offer
+pub fn execute_set_eth_taker(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    eth_taker: Option<[u8; ETH_ADDRESS_LENGTH]>,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_eth_taker_discriminator();
+    eth_taker.serialize(&mut instruction_data).unwrap();
+
+    let set_eth_taker_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_eth_taker_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Derives the counter offer PDA for a proposer's counter offer on an offer
+pub fn get_counter_offer_address(offer_account: &Pubkey, proposer: &Pubkey) -> Pubkey {
+    let (counter_offer, _counter_offer_bump) = get_pda_and_bump(
+        &[
+            b"counter_offer".as_ref().into(),
+            offer_account.as_ref().into(),
+            proposer.as_ref().into(),
+        ],
+        &get_program_id(),
+    );
+    counter_offer
+}
+
+pub struct MakeCounterOfferAccounts {
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    pub proposer: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub proposer_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub counter_offer: Pubkey,
+}
+
+pub fn build_make_counter_offer_instruction(
+    token_b_offered_amount: u64,
+    accounts: MakeCounterOfferAccounts,
+) -> Instruction {
+    let mut instruction_data = get_make_counter_offer_discriminator();
+    instruction_data.extend_from_slice(&token_b_offered_amount.to_le_bytes());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.proposer, true),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.proposer_token_account_b, false),
+        AccountMeta::new_readonly(accounts.offer_account, false),
+        AccountMeta::new(accounts.counter_offer, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct AcceptCounterOfferAccounts {
+    pub associated_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    pub maker: Pubkey,
+    pub proposer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub proposer_token_account_a: Pubkey,
+    pub proposer_token_account_b: Pubkey,
+    pub maker_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub counter_offer: Pubkey,
+    pub vault: Pubkey,
+}
+
+pub fn build_accept_counter_offer_instruction(accounts: AcceptCounterOfferAccounts) -> Instruction {
+    let instruction_data = get_accept_counter_offer_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.maker, true),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new(accounts.proposer, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.proposer_token_account_a, false),
+        AccountMeta::new(accounts.proposer_token_account_b, false),
+        AccountMeta::new(accounts.maker_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.counter_offer, false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new_readonly(get_config_address(), false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct RejectCounterOfferAccounts {
+    pub maker: Pubkey,
+    pub proposer: Pubkey,
+    pub offer_account: Pubkey,
+    pub counter_offer: Pubkey,
+}
+
+pub fn build_reject_counter_offer_instruction(accounts: RejectCounterOfferAccounts) -> Instruction {
+    let instruction_data = get_reject_counter_offer_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.maker, true),
+        AccountMeta::new(accounts.proposer, false),
+        AccountMeta::new_readonly(accounts.offer_account, false),
+        AccountMeta::new(accounts.counter_offer, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes a complete make_counter_offer flow: derives the counter offer PDA, builds accounts and executes instruction
+pub fn execute_make_counter_offer(
+    test_env: &mut EscrowTestEnvironment,
+    proposer: &Keypair,
+    proposer_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    token_b_offered_amount: u64,
+) -> Result<Pubkey, SolanaKiteError> {
+    let counter_offer = get_counter_offer_address(&offer_account, &proposer.pubkey());
+
+    let make_counter_offer_accounts = MakeCounterOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        proposer: proposer.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        proposer_token_account_b,
+        offer_account,
+        counter_offer,
+    };
+
+    let make_counter_offer_instruction =
+        build_make_counter_offer_instruction(token_b_offered_amount, make_counter_offer_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![make_counter_offer_instruction],
+        &[proposer],
+        &proposer.pubkey(),
+    )?;
+
+    Ok(counter_offer)
+}
+
+/// Executes a complete accept_counter_offer flow: builds accounts and executes instruction
+pub fn execute_accept_counter_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    proposer: &Keypair,
+    proposer_token_account_a: Pubkey,
+    proposer_token_account_b: Pubkey,
+    maker_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    counter_offer: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let accept_counter_offer_accounts = AcceptCounterOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker: maker.pubkey(),
+        proposer: proposer.pubkey(),
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        proposer_token_account_a,
+        proposer_token_account_b,
+        maker_token_account_b,
+        offer_account,
+        counter_offer,
+        vault,
+    };
+
+    let accept_counter_offer_instruction =
+        build_accept_counter_offer_instruction(accept_counter_offer_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![accept_counter_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes a complete reject_counter_offer flow: builds accounts and executes instruction
+pub fn execute_reject_counter_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    proposer: Pubkey,
+    offer_account: Pubkey,
+    counter_offer: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let reject_counter_offer_accounts = RejectCounterOfferAccounts {
+        maker: maker.pubkey(),
+        proposer,
+        offer_account,
+        counter_offer,
+    };
+
+    let reject_counter_offer_instruction =
+        build_reject_counter_offer_instruction(reject_counter_offer_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![reject_counter_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Derives the bid PDA and its bid vault for an English auction offer
+pub fn get_bid_and_bid_vault_addresses(
+    test_env: &EscrowTestEnvironment,
+    offer_account: &Pubkey,
+) -> (Pubkey, Pubkey) {
+    let (bid, _bid_bump) = get_pda_and_bump(
+        &[b"bid".as_ref().into(), offer_account.as_ref().into()],
+        &test_env.program_id,
+    );
+    let bid_vault = spl_associated_token_account::get_associated_token_address(
+        &bid,
+        &test_env.token_mint_b.pubkey(),
+    );
+    (bid, bid_vault)
+}
+
+pub struct PlaceBidAccounts {
+    pub associated_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    pub bidder: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub bidder_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub bid: Pubkey,
+    pub bid_vault: Pubkey,
+}
+
+pub fn build_place_bid_instruction(amount: u64, accounts: PlaceBidAccounts) -> Instruction {
+    let mut instruction_data = get_place_bid_discriminator();
+    instruction_data.extend_from_slice(&amount.to_le_bytes());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.bidder, true),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.bidder_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new(accounts.bid, false),
+        AccountMeta::new(accounts.bid_vault, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct OutbidAccounts {
+    pub token_program: Pubkey,
+    pub bidder: Pubkey,
+    pub previous_bidder: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub bidder_token_account_b: Pubkey,
+    pub previous_bidder_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub bid: Pubkey,
+    pub bid_vault: Pubkey,
+}
+
+pub fn build_outbid_instruction(amount: u64, accounts: OutbidAccounts) -> Instruction {
+    let mut instruction_data = get_outbid_discriminator();
+    instruction_data.extend_from_slice(&amount.to_le_bytes());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new(accounts.bidder, true),
+        AccountMeta::new_readonly(accounts.previous_bidder, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.bidder_token_account_b, false),
+        AccountMeta::new(accounts.previous_bidder_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new(accounts.bid, false),
+        AccountMeta::new(accounts.bid_vault, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct SettleAuctionAccounts {
+    pub associated_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub system_program: Pubkey,
+    pub settler: Pubkey,
+    pub maker: Pubkey,
+    pub bidder: Pubkey,
+    pub rent_payer: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub bidder_token_account_a: Pubkey,
+    pub maker_token_account_b: Pubkey,
+    pub offer_account: Pubkey,
+    pub bid: Pubkey,
+    pub vault: Pubkey,
+    pub bid_vault: Pubkey,
+}
+
+pub fn build_settle_auction_instruction(accounts: SettleAuctionAccounts) -> Instruction {
+    let instruction_data = get_settle_auction_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.associated_token_program, false),
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.settler, true),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new_readonly(accounts.bidder, false),
+        AccountMeta::new(accounts.rent_payer, false),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.bidder_token_account_a, false),
+        AccountMeta::new(accounts.maker_token_account_b, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.bid, false),
+        AccountMeta::new(accounts.vault, false),
+        AccountMeta::new(accounts.bid_vault, false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new_readonly(get_config_address(), false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes a complete place_bid flow: derives the bid PDAs, builds accounts and executes instruction
+pub fn execute_place_bid(
+    test_env: &mut EscrowTestEnvironment,
+    bidder: &Keypair,
+    bidder_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    amount: u64,
+) -> Result<(), SolanaKiteError> {
+    let (bid, bid_vault) = get_bid_and_bid_vault_addresses(test_env, &offer_account);
+
+    let place_bid_accounts = PlaceBidAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        bidder: bidder.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        bidder_token_account_b,
+        offer_account,
+        bid,
+        bid_vault,
+    };
+
+    let place_bid_instruction = build_place_bid_instruction(amount, place_bid_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![place_bid_instruction],
+        &[bidder],
+        &bidder.pubkey(),
+    )
+}
+
+/// Executes a complete outbid flow: derives the bid PDAs, builds accounts and executes instruction
+pub fn execute_outbid(
+    test_env: &mut EscrowTestEnvironment,
+    bidder: &Keypair,
+    bidder_token_account_b: Pubkey,
+    previous_bidder: Pubkey,
+    previous_bidder_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    amount: u64,
+) -> Result<(), SolanaKiteError> {
+    let (bid, bid_vault) = get_bid_and_bid_vault_addresses(test_env, &offer_account);
+
+    let outbid_accounts = OutbidAccounts {
+        token_program: spl_token::ID,
+        bidder: bidder.pubkey(),
+        previous_bidder,
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        bidder_token_account_b,
+        previous_bidder_token_account_b,
+        offer_account,
+        bid,
+        bid_vault,
+    };
+
+    let outbid_instruction = build_outbid_instruction(amount, outbid_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![outbid_instruction],
+        &[bidder],
+        &bidder.pubkey(),
+    )
+}
+
+/// Executes a complete settle_auction flow: derives the bid PDAs, builds accounts and executes instruction
+pub fn execute_settle_auction(
+    test_env: &mut EscrowTestEnvironment,
+    settler: &Keypair,
+    maker: Pubkey,
+    bidder: Pubkey,
+    rent_payer: Pubkey,
+    bidder_token_account_a: Pubkey,
+    maker_token_account_b: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (bid, bid_vault) = get_bid_and_bid_vault_addresses(test_env, &offer_account);
+
+    let settle_auction_accounts = SettleAuctionAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        settler: settler.pubkey(),
+        maker,
+        bidder,
+        rent_payer,
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        bidder_token_account_a,
+        maker_token_account_b,
+        offer_account,
+        bid,
+        vault,
+        bid_vault,
+    };
+
+    let settle_auction_instruction = build_settle_auction_instruction(settle_auction_accounts);
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![settle_auction_instruction],
+        &[settler],
+        &settler.pubkey(),
+    )
+}
+
+/// Derives the allowlist PDA for an offer
+pub fn get_allowlist_address(offer_account: &Pubkey) -> Pubkey {
+    let (allowlist, _allowlist_bump) = get_pda_and_bump(
+        &[b"allowlist".as_ref().into(), offer_account.as_ref().into()],
+        &get_program_id(),
+    );
+    allowlist
+}
+
+pub struct AllowedTakerAccounts {
+    pub system_program: Pubkey,
+    pub maker: Pubkey,
+    pub offer_account: Pubkey,
+    pub allowlist: Pubkey,
+}
+
+pub fn build_add_allowed_taker_instruction(taker: Pubkey, accounts: AllowedTakerAccounts) -> Instruction {
+    let mut instruction_data = get_add_allowed_taker_discriminator();
+    instruction_data.extend_from_slice(taker.as_ref());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.system_program, false),
+        AccountMeta::new(accounts.maker, true),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new(accounts.allowlist, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub fn build_remove_allowed_taker_instruction(taker: Pubkey, accounts: AllowedTakerAccounts) -> Instruction {
+    let mut instruction_data = get_remove_allowed_taker_discriminator();
+    instruction_data.extend_from_slice(taker.as_ref());
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.maker, true),
+        AccountMeta::new_readonly(accounts.offer_account, false),
+        AccountMeta::new(accounts.allowlist, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes a complete add_allowed_taker flow: derives the allowlist PDA, builds accounts and executes instruction
+pub fn execute_add_allowed_taker(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    taker: Pubkey,
+) -> Result<Pubkey, SolanaKiteError> {
+    let allowlist = get_allowlist_address(&offer_account);
+
+    let add_allowed_taker_instruction = build_add_allowed_taker_instruction(
+        taker,
+        AllowedTakerAccounts {
+            system_program: anchor_lang::system_program::ID,
+            maker: maker.pubkey(),
+            offer_account,
+            allowlist,
+        },
+    );
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![add_allowed_taker_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )?;
+
+    Ok(allowlist)
+}
+
+/// Executes a complete remove_allowed_taker flow: derives the allowlist PDA, builds accounts and executes instruction
+pub fn execute_remove_allowed_taker(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    taker: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let remove_allowed_taker_instruction = build_remove_allowed_taker_instruction(
+        taker,
+        AllowedTakerAccounts {
+            system_program: anchor_lang::system_program::ID,
+            maker: maker.pubkey(),
+            offer_account,
+            allowlist: get_allowlist_address(&offer_account),
+        },
+    );
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![remove_allowed_taker_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// An extra mint escrowed in a bundle offer, alongside token A
+pub struct BundleMint {
+    pub mint: Pubkey,
+    /// The maker's token account for make_offer, or the recipient's for take_offer and refund_offer
+    pub token_account: Pubkey,
+    /// The offer's vault for this mint
+    pub vault: Pubkey,
+}
+
+impl BundleMint {
+    pub fn new(offer_account: &Pubkey, mint: Pubkey, token_account: Pubkey) -> Self {
+        let vault =
+            spl_associated_token_account::get_associated_token_address(&get_vault_authority_address(offer_account), &mint);
+        BundleMint {
+            mint,
+            token_account,
+            vault,
+        }
+    }
+}
+
+/// Builds the remaining accounts make_offer expects for each bundle mint: [mint, maker token account, vault]
+pub fn build_make_offer_bundle_accounts(bundle_mints: &[BundleMint]) -> Vec<AccountMeta> {
+    bundle_mints
+        .iter()
+        .flat_map(|bundle_mint| {
+            [
+                AccountMeta::new_readonly(bundle_mint.mint, false),
+                AccountMeta::new(bundle_mint.token_account, false),
+                AccountMeta::new(bundle_mint.vault, false),
+            ]
+        })
+        .collect()
+}
+
+/// Builds the remaining accounts take_offer and refund_offer expect for each bundle mint: [mint, vault, recipient token account]
+pub fn build_settle_bundle_accounts(bundle_mints: &[BundleMint]) -> Vec<AccountMeta> {
+    bundle_mints
+        .iter()
+        .flat_map(|bundle_mint| {
+            [
+                AccountMeta::new_readonly(bundle_mint.mint, false),
+                AccountMeta::new(bundle_mint.vault, false),
+                AccountMeta::new(bundle_mint.token_account, false),
+            ]
+        })
+        .collect()
+}
+
+/// Creates an NFT owned by `owner`, with Metaplex metadata optionally in a verified collection
+///
+/// The Metaplex program isn't deployed in LiteSVM, so the metadata account is written
+/// directly, Borsh-encoded the same way the Metaplex program lays it out.
+///
+/// Returns the NFT's mint, the owner's token account, and the metadata account.
+pub fn create_nft(
+    test_env: &mut EscrowTestEnvironment,
+    owner: &Keypair,
+    collection: Option<Pubkey>,
+) -> (Pubkey, Pubkey, Pubkey) {
+    let nft_mint = create_token_mint(&mut test_env.litesvm, &test_env._mint_authority, 0)
+        .unwrap()
+        .pubkey();
+    let owner_token_account = create_associated_token_account(
+        &mut test_env.litesvm,
+        owner,
+        &nft_mint,
+        &test_env._mint_authority,
+    )
+    .unwrap();
+    mint_tokens_to_account(
+        &mut test_env.litesvm,
+        &nft_mint,
+        &owner_token_account,
+        1,
+        &test_env._mint_authority,
+    )
+    .unwrap();
+
+    let metadata = create_nft_metadata(test_env, &nft_mint, collection);
+
+    (nft_mint, owner_token_account, metadata)
+}
+
+/// Writes a Metaplex metadata account for `mint`, returning its address
+pub fn create_nft_metadata(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    collection: Option<Pubkey>,
+) -> Pubkey {
+    create_nft_metadata_with_royalties(test_env, mint, collection, 0, &[])
+}
+
+/// Writes a Metaplex metadata account for `mint` with a royalty, split between `creators` by share
+pub fn create_nft_metadata_with_royalties(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    collection: Option<Pubkey>,
+    seller_fee_basis_points: u16,
+    creators: &[(Pubkey, u8)],
+) -> Pubkey {
+    let (metadata, _metadata_bump) = Metadata::find_pda(mint);
+
+    let mut data = vec![Key::MetadataV1 as u8];
+    test_env._mint_authority.pubkey().serialize(&mut data).unwrap(); // update authority
+    mint.serialize(&mut data).unwrap();
+    "Test NFT".to_string().serialize(&mut data).unwrap(); // name
+    "TEST".to_string().serialize(&mut data).unwrap(); // symbol
+    "https://example.com/nft.json".to_string().serialize(&mut data).unwrap(); // uri
+    seller_fee_basis_points.serialize(&mut data).unwrap();
+    if creators.is_empty() {
+        data.push(0);
+    } else {
+        data.push(1);
+        (creators.len() as u32).serialize(&mut data).unwrap();
+        for (address, share) in creators {
+            address.serialize(&mut data).unwrap();
+            false.serialize(&mut data).unwrap(); // verified
+            share.serialize(&mut data).unwrap();
+        }
+    }
+    false.serialize(&mut data).unwrap(); // primary sale happened
+    true.serialize(&mut data).unwrap(); // is mutable
+    data.push(0); // no edition nonce
+    data.extend_from_slice(&[1, TokenStandard::NonFungible as u8]);
+    match collection {
+        Some(collection) => {
+            data.push(1);
+            true.serialize(&mut data).unwrap(); // verified
+            collection.serialize(&mut data).unwrap();
+        }
+        None => data.push(0),
+    }
+    data.extend_from_slice(&[0, 0, 0]); // no uses, collection details, or programmable config
+
+    let lamports = test_env.litesvm.minimum_balance_for_rent_exemption(data.len());
+    test_env
+        .litesvm
+        .set_account(
+            metadata,
+            solana_account::Account {
+                lamports,
+                data,
+                owner: mpl_token_metadata::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+        .unwrap();
+
+    metadata
+}
+
+pub fn get_offer_address(maker: &Pubkey, offer_id: u64) -> Pubkey {
+    let (offer, _offer_bump) = get_pda_and_bump(
+        &[b"offer".as_ref().into(), maker.as_ref().into(), offer_id.to_le_bytes().as_ref().into()],
+        &get_program_id(),
+    );
+    offer
+}
+
+pub fn get_vault_authority_address(offer_account: &Pubkey) -> Pubkey {
+    let (vault_authority, _vault_authority_bump) =
+        get_pda_and_bump(&[b"vault_authority".as_ref().into(), offer_account.as_ref().into()], &get_program_id());
+    vault_authority
+}
+
+pub fn get_taker_authority_address(taker: &Pubkey) -> Pubkey {
+    let (taker_authority, _taker_authority_bump) =
+        get_pda_and_bump(&[b"taker_authority".as_ref().into(), taker.as_ref().into()], &get_program_id());
+    taker_authority
+}
+
+pub fn get_config_address() -> Pubkey {
+    let (config, _config_bump) = get_pda_and_bump(&[b"config".as_ref().into()], &get_program_id());
+    config
+}
+
+pub fn get_registry_address() -> Pubkey {
+    let (registry, _registry_bump) = get_pda_and_bump(&[b"registry".as_ref().into()], &get_program_id());
+    registry
+}
+
+pub fn get_maker_index_address(maker: &Pubkey) -> Pubkey {
+    let (maker_index, _maker_index_bump) = get_pda_and_bump(
+        &[b"maker_index".as_ref().into(), maker.as_ref().into()],
+        &get_program_id(),
+    );
+    maker_index
+}
+
+pub fn get_maker_index(test_env: &EscrowTestEnvironment, maker: &Pubkey) -> MakerIndex {
+    let account = test_env.litesvm.get_account(&get_maker_index_address(maker)).unwrap();
+    MakerIndex::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub fn get_maker_stats_address(maker: &Pubkey) -> Pubkey {
+    let (maker_stats, _maker_stats_bump) = get_pda_and_bump(
+        &[b"maker_stats".as_ref().into(), maker.as_ref().into()],
+        &get_program_id(),
+    );
+    maker_stats
+}
+
+pub fn get_maker_counter_address(maker: &Pubkey) -> Pubkey {
+    let (maker_counter, _maker_counter_bump) = get_pda_and_bump(
+        &[b"maker_counter".as_ref().into(), maker.as_ref().into()],
+        &get_program_id(),
+    );
+    maker_counter
+}
+
+pub fn get_maker_counter(test_env: &EscrowTestEnvironment, maker: &Pubkey) -> MakerCounter {
+    let account = test_env.litesvm.get_account(&get_maker_counter_address(maker)).unwrap();
+    MakerCounter::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub fn get_offer_template_address(maker: &Pubkey, token_mint_a: &Pubkey, token_mint_b: &Pubkey) -> Pubkey {
+    let (offer_template, _offer_template_bump) = get_pda_and_bump(
+        &[
+            b"offer_template".as_ref().into(),
+            maker.as_ref().into(),
+            token_mint_a.as_ref().into(),
+            token_mint_b.as_ref().into(),
+        ],
+        &get_program_id(),
+    );
+    offer_template
+}
+
+pub fn get_offer_template(test_env: &EscrowTestEnvironment, offer_template: &Pubkey) -> OfferTemplate {
+    let account = test_env.litesvm.get_account(offer_template).unwrap();
+    OfferTemplate::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub fn get_maker_stats(test_env: &EscrowTestEnvironment, maker: &Pubkey) -> MakerStats {
+    let account = test_env.litesvm.get_account(&get_maker_stats_address(maker)).unwrap();
+    MakerStats::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub fn get_registry(test_env: &EscrowTestEnvironment) -> Registry {
+    let account = test_env.litesvm.get_account(&get_registry_address()).unwrap();
+    Registry::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Derives the vesting PDA created when a vested offer is taken
+pub fn get_vesting_address(offer_account: &Pubkey) -> Pubkey {
+    let (vesting, _vesting_bump) = get_pda_and_bump(
+        &[b"vesting".as_ref().into(), offer_account.as_ref().into()],
+        &get_program_id(),
+    );
+    vesting
+}
+
+pub fn get_vesting(test_env: &EscrowTestEnvironment, vesting: &Pubkey) -> Vesting {
+    let account = test_env.litesvm.get_account(vesting).unwrap();
+    Vesting::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Derives the installment plan for an offer taken with take_offer_in_installments
+pub fn get_installment_plan_address(offer_account: &Pubkey) -> Pubkey {
+    let (installment_plan, _installment_plan_bump) = get_pda_and_bump(
+        &[b"installment_plan".as_ref().into(), offer_account.as_ref().into()],
+        &get_program_id(),
+    );
+    installment_plan
+}
+
+pub fn get_installment_plan(test_env: &EscrowTestEnvironment, installment_plan: &Pubkey) -> InstallmentPlan {
+    let account = test_env.litesvm.get_account(installment_plan).unwrap();
+    InstallmentPlan::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Derives a taker's deposit PDA for an offer, and the deposit vault holding their token B
+pub fn get_taker_deposit_addresses(test_env: &EscrowTestEnvironment, offer_account: &Pubkey, taker: &Pubkey) -> (Pubkey, Pubkey) {
+    let (taker_deposit, _taker_deposit_bump) = get_pda_and_bump(
+        &[
+            b"taker_deposit".as_ref().into(),
+            offer_account.as_ref().into(),
+            taker.as_ref().into(),
+        ],
+        &get_program_id(),
+    );
+    let deposit_vault = spl_associated_token_account::get_associated_token_address(
+        &taker_deposit,
+        &test_env.token_mint_b.pubkey(),
+    );
+    (taker_deposit, deposit_vault)
+}
+
+/// Derives a requester's quote request for one of their request ids
+pub fn get_quote_request_address(requester: &Pubkey, request_id: u64) -> Pubkey {
+    let (quote_request, _quote_request_bump) = get_pda_and_bump(
+        &[b"quote_request".as_ref().into(), requester.as_ref().into(), request_id.to_le_bytes().as_ref().into()],
+        &get_program_id(),
+    );
+    quote_request
+}
+
+/// Derives a maker's quote for a quote request, and the vault holding its token A
+pub fn get_quote_addresses(test_env: &EscrowTestEnvironment, quote_request: &Pubkey, maker: &Pubkey) -> (Pubkey, Pubkey) {
+    let (quote, _quote_bump) = get_pda_and_bump(
+        &[b"quote".as_ref().into(), quote_request.as_ref().into(), maker.as_ref().into()],
+        &get_program_id(),
+    );
+    let quote_vault = spl_associated_token_account::get_associated_token_address(&quote, &test_env.token_mint_a.pubkey());
+    (quote, quote_vault)
+}
+
+/// Derives a mint's entry on the protocol's mint allowlist
+pub fn get_mint_allowlist_entry_address(mint: &Pubkey) -> Pubkey {
+    let (mint_allowlist_entry, _mint_allowlist_entry_bump) = get_pda_and_bump(
+        &[b"mint_allowlist_entry".as_ref().into(), mint.as_ref().into()],
+        &get_program_id(),
+    );
+    mint_allowlist_entry
+}
+
+/// Derives a mint's deny entry, which only has an account while the mint is denied
+pub fn get_mint_deny_entry_address(mint: &Pubkey) -> Pubkey {
+    let (mint_deny_entry, _mint_deny_entry_bump) = get_pda_and_bump(
+        &[b"mint_deny_entry".as_ref().into(), mint.as_ref().into()],
+        &get_program_id(),
+    );
+    mint_deny_entry
+}
+
+/// Derives the transient account take_offer_route holds a taker's token B in between the two offers
+pub fn get_route_token_account_address(taker: &Pubkey, token_mint_b: &Pubkey) -> Pubkey {
+    let (route_token_account, _route_token_account_bump) = get_pda_and_bump(
+        &[b"route".as_ref().into(), taker.as_ref().into(), token_mint_b.as_ref().into()],
+        &get_program_id(),
+    );
+    route_token_account
+}
+
+/// Derives the stake offer selling a stake account
+pub fn get_stake_offer_address(stake_account: &Pubkey) -> Pubkey {
+    let (stake_offer, _stake_offer_bump) = get_pda_and_bump(
+        &[b"stake_offer".as_ref().into(), stake_account.as_ref().into()],
+        &get_program_id(),
+    );
+    stake_offer
+}
+
+/// take_offer derives the fee vault for token A, so tests don't have to pass it in
+pub fn get_fee_vault_address(mint: &Pubkey) -> Pubkey {
+    let (fee_vault, _fee_vault_bump) = get_pda_and_bump(
+        &[b"fee_vault".as_ref().into(), mint.as_ref().into()],
+        &get_program_id(),
+    );
+    fee_vault
+}
+
+pub fn build_initialize_config_instruction(
+    fee_bps: u16,
+    fee_authority: Pubkey,
+    referral_share_bps: u16,
+    admin: Pubkey,
+) -> Instruction {
+    let mut instruction_data = get_initialize_config_discriminator();
+    instruction_data.extend_from_slice(&fee_bps.to_le_bytes());
+    instruction_data.extend_from_slice(fee_authority.as_ref());
+    instruction_data.extend_from_slice(&referral_share_bps.to_le_bytes());
+
+    let account_metas = vec![
+        AccountMeta::new(admin, true),
+        AccountMeta::new(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+pub struct WithdrawFeesAccounts {
+    pub fee_authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub destination_token_account: Pubkey,
+}
+
+pub fn build_withdraw_fees_instruction(accounts: WithdrawFeesAccounts) -> Instruction {
+    let instruction_data = get_withdraw_fees_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(accounts.fee_authority, true),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new_readonly(accounts.token_mint, false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint), false),
+        AccountMeta::new(accounts.destination_token_account, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes withdraw_fees, signed by `fee_authority`, for token A
+pub fn execute_withdraw_fees(
+    test_env: &mut EscrowTestEnvironment,
+    fee_authority: &Keypair,
+    destination_token_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let withdraw_fees_instruction = build_withdraw_fees_instruction(WithdrawFeesAccounts {
+        fee_authority: fee_authority.pubkey(),
+        token_mint: test_env.token_mint_a.pubkey(),
+        destination_token_account,
+    });
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![withdraw_fees_instruction],
+        &[fee_authority],
+        &fee_authority.pubkey(),
+    )
+}
+
+/// Builds a pause or unpause instruction, which take the same accounts
+pub fn build_set_paused_instruction(paused: bool, authority: Pubkey) -> Instruction {
+    let instruction_data = if paused {
+        get_pause_discriminator()
+    } else {
+        get_unpause_discriminator()
+    };
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new(get_config_address(), false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Pauses or unpauses the program, signed by `authority`
+pub fn execute_set_paused(
+    test_env: &mut EscrowTestEnvironment,
+    paused: bool,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![build_set_paused_instruction(paused, authority.pubkey())],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets which kinds of mint offers may use, signed by `authority`
+pub fn execute_set_mint_policy(
+    test_env: &mut EscrowTestEnvironment,
+    mint_policy: MintPolicy,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_mint_policy_discriminator();
+    mint_policy.serialize(&mut instruction_data).unwrap();
+
+    let set_mint_policy_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_mint_policy_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets the smallest and largest amount of token a an offer can be made for, signed by `authority`
+pub fn execute_set_limits(
+    test_env: &mut EscrowTestEnvironment,
+    min_offer_amount: u64,
+    max_offer_amount: u64,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_limits_discriminator();
+    min_offer_amount.serialize(&mut instruction_data).unwrap();
+    max_offer_amount.serialize(&mut instruction_data).unwrap();
+
+    let set_limits_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_limits_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets how many slots an offer must have existed for before it can be taken, signed by `authority`
+pub fn execute_set_min_offer_age(
+    test_env: &mut EscrowTestEnvironment,
+    min_offer_age_slots: u64,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_min_offer_age_discriminator();
+    min_offer_age_slots.serialize(&mut instruction_data).unwrap();
+
+    let set_min_offer_age_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_min_offer_age_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets the most open offers a single maker can have at once, signed by `authority`
+pub fn execute_set_max_open_offers(
+    test_env: &mut EscrowTestEnvironment,
+    max_open_offers_per_maker: u16,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_max_open_offers_discriminator();
+    max_open_offers_per_maker.serialize(&mut instruction_data).unwrap();
+
+    let set_max_open_offers_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_max_open_offers_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets whether offers can only be made between allowlisted mints, signed by `authority`
+pub fn execute_set_mint_allowlist_enabled(
+    test_env: &mut EscrowTestEnvironment,
+    mint_allowlist_enabled: bool,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_mint_allowlist_enabled_discriminator();
+    mint_allowlist_enabled.serialize(&mut instruction_data).unwrap();
+
+    let set_mint_allowlist_enabled_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_mint_allowlist_enabled_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Adds a mint to the protocol's mint allowlist, signed and paid for by `authority`
+pub fn execute_allow_mint(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let allow_mint_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(get_mint_allowlist_entry_address(mint), false),
+        ],
+        data: get_allow_mint_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![allow_mint_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Removes a mint from the protocol's mint allowlist, signed by `authority`, who gets the entry's rent back
+pub fn execute_revoke_mint(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let revoke_mint_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_mint_allowlist_entry_address(mint), false),
+        ],
+        data: get_revoke_mint_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![revoke_mint_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Adds a mint to the protocol's mint denylist, signed and paid for by `authority`
+pub fn execute_deny_mint(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let deny_mint_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(get_mint_deny_entry_address(mint), false),
+        ],
+        data: get_deny_mint_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![deny_mint_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Removes a mint from the protocol's mint denylist, signed by `authority`, who gets the entry's rent back
+pub fn execute_undeny_mint(
+    test_env: &mut EscrowTestEnvironment,
+    mint: &Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let undeny_mint_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+            AccountMeta::new(get_mint_deny_entry_address(mint), false),
+        ],
+        data: get_undeny_mint_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![undeny_mint_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Replaces the config's volume fee tiers, measured in `fee_tier_mint`, signed by `authority`
+pub fn execute_set_fee_tiers(
+    test_env: &mut EscrowTestEnvironment,
+    fee_tiers: Vec<FeeTier>,
+    fee_tier_mint: Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_fee_tiers_discriminator();
+    fee_tiers.serialize(&mut instruction_data).unwrap();
+    fee_tier_mint.serialize(&mut instruction_data).unwrap();
+
+    let set_fee_tiers_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_fee_tiers_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets or removes the config's membership fee discount, signed by `authority`
+pub fn execute_set_membership_discount(
+    test_env: &mut EscrowTestEnvironment,
+    membership_discount: Option<MembershipDiscount>,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_membership_discount_discriminator();
+    membership_discount.serialize(&mut instruction_data).unwrap();
+
+    let set_membership_discount_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_membership_discount_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets the config's share of each match's price improvement for the matcher, signed by `authority`
+pub fn execute_set_matcher_share(
+    test_env: &mut EscrowTestEnvironment,
+    matcher_share_bps: u16,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_matcher_share_discriminator();
+    instruction_data.extend_from_slice(&matcher_share_bps.to_le_bytes());
+
+    let set_matcher_share_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_matcher_share_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Gives an admin role to `holder`, signed by `authority`
+pub fn execute_set_role(
+    test_env: &mut EscrowTestEnvironment,
+    role: Role,
+    holder: Pubkey,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_role_discriminator();
+    role.serialize(&mut instruction_data).unwrap();
+    holder.serialize(&mut instruction_data).unwrap();
+
+    let set_role_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_role_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Sets whether NFT settlements must pay creator royalties, signed by `authority`
+pub fn execute_set_royalties_required(
+    test_env: &mut EscrowTestEnvironment,
+    royalties_required: bool,
+    authority: &Keypair,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_royalties_required_discriminator();
+    royalties_required.serialize(&mut instruction_data).unwrap();
+
+    let set_royalties_required_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(get_config_address(), false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_royalties_required_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Gives a classic token mint a freeze authority, which the test mints are created without
+pub fn set_mint_freeze_authority(test_env: &mut EscrowTestEnvironment, mint: &Pubkey, freeze_authority: Pubkey) {
+    use solana_program::program_pack::Pack;
+
+    let mut account = test_env.litesvm.get_account(mint).unwrap();
+    let mut mint_state = spl_token::state::Mint::unpack(&account.data).unwrap();
+    mint_state.freeze_authority = solana_program::program_option::COption::Some(freeze_authority);
+    spl_token::state::Mint::pack(mint_state, &mut account.data).unwrap();
+    test_env.litesvm.set_account(*mint, account).unwrap();
+}
+
+pub struct AdminForceRefundAccounts {
+    pub authority: Pubkey,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub maker_token_account_a: Pubkey,
+    pub offer_account: Pubkey,
+    pub vault: Pubkey,
+    pub allowlist: Option<Pubkey>,
+}
+
+pub fn build_admin_force_refund_instruction(accounts: AdminForceRefundAccounts) -> Instruction {
+    let instruction_data = get_admin_force_refund_discriminator();
+
+    let account_metas = vec![
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(accounts.authority, true),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new(accounts.maker_token_account_a, false),
+        AccountMeta::new(accounts.offer_account, false),
+        AccountMeta::new_readonly(get_vault_authority_address(&accounts.offer_account), false),
+        AccountMeta::new(accounts.vault, false),
+        optional_account_meta(accounts.allowlist, true),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes admin_force_refund for one of Alice's token A offers, signed by `authority`
+pub fn execute_admin_force_refund(
+    test_env: &mut EscrowTestEnvironment,
+    authority: &Keypair,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let admin_force_refund_instruction = build_admin_force_refund_instruction(AdminForceRefundAccounts {
+        authority: authority.pubkey(),
+        maker: test_env.alice.pubkey(),
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        maker_token_account_a: test_env.alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![admin_force_refund_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Closes one of the vaults of a maker's offer that no longer exists, the vault for `token_mint`
+/// Anything left in the vault goes back to the maker, and the rent to the fee authority
+pub fn execute_close_orphaned_vault(
+    test_env: &mut EscrowTestEnvironment,
+    authority: &Keypair,
+    maker: Pubkey,
+    offer_id: u64,
+    token_mint: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_close_orphaned_vault_discriminator();
+    maker.serialize(&mut instruction_data).unwrap();
+    offer_id.serialize(&mut instruction_data).unwrap();
+
+    let offer_account = get_offer_address(&maker, offer_id);
+    let vault_authority = get_vault_authority_address(&offer_account);
+    let vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &vault_authority,
+        &token_mint,
+        &test_env.token_program,
+    );
+    let maker_token_account = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &maker,
+        &token_mint,
+        &test_env.token_program,
+    );
+
+    let close_orphaned_vault_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(test_env.fee_authority.pubkey(), false),
+            AccountMeta::new_readonly(offer_account, false),
+            AccountMeta::new_readonly(vault_authority, false),
+            AccountMeta::new_readonly(maker, false),
+            AccountMeta::new_readonly(token_mint, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(maker_token_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![close_orphaned_vault_instruction],
+        &[authority],
+        &authority.pubkey(),
+    )
+}
+
+/// Executes claim_rent, closing an offer that kept its history and returning the rent to its rent payer
+pub fn execute_claim_rent(
+    test_env: &mut EscrowTestEnvironment,
+    maker_authority: &Keypair,
+    offer_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let claim_rent_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker_authority.pubkey(), true),
+            AccountMeta::new(get_offer(test_env, &offer_account).rent_payer, false),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: get_claim_rent_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![claim_rent_instruction],
+        &[maker_authority],
+        &maker_authority.pubkey(),
+    )
+}
+
+pub fn build_attach_metadata_instruction(metadata_uri: &str, maker: Pubkey, offer_account: Pubkey) -> Instruction {
+    let mut instruction_data = get_attach_metadata_discriminator();
+    metadata_uri.to_string().serialize(&mut instruction_data).unwrap();
+
+    let account_metas = vec![
+        AccountMeta::new(maker, true),
+        AccountMeta::new(offer_account, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+    ];
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes attach_metadata, signed by the offer's maker
+pub fn execute_attach_metadata(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    metadata_uri: &str,
+) -> Result<(), SolanaKiteError> {
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![build_attach_metadata_instruction(metadata_uri, maker.pubkey(), offer_account)],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Rewrites an offer account in the layout used before offers were versioned, as if it was made back then
+/// Version 0 is the current layout without the version field and its padding, with any metadata URI straight after it
+/// Removes an offer account without closing its vaults, like an incident that strands them
+pub fn remove_offer_account(test_env: &mut EscrowTestEnvironment, offer_account: &Pubkey) {
+    let mut account = test_env.litesvm.get_account(offer_account).unwrap();
+    account.lamports = 0;
+    account.data = vec![];
+    account.owner = anchor_lang::system_program::ID;
+    test_env.litesvm.set_account(*offer_account, account).unwrap();
+}
+
+/// Overwrites a byte of an offer's account data, to forge a stored bump like an attacker who could write to it
+pub fn forge_offer_byte(test_env: &mut EscrowTestEnvironment, offer_account: &Pubkey, offset: usize, value: u8) {
+    let mut account = test_env.litesvm.get_account(offer_account).unwrap();
+    account.data[Offer::DISCRIMINATOR.len() + offset] = value;
+    test_env.litesvm.set_account(*offer_account, account).unwrap();
+}
+
+pub fn rewrite_offer_as_version_zero(test_env: &mut EscrowTestEnvironment, offer_account: &Pubkey) {
+    let mut account = test_env.litesvm.get_account(offer_account).unwrap();
+    let version_zero_end = Offer::DISCRIMINATOR.len() + Offer::LAYOUT_SIZES[0];
+    let mut data = account.data[..version_zero_end].to_vec();
+    data.extend_from_slice(&account.data[Offer::METADATA_URI_OFFSET..]);
+    account.data = data;
+    test_env.litesvm.set_account(*offer_account, account).unwrap();
+}
+
+/// Executes migrate_offer, upgrading an offer to the current layout
+pub fn execute_migrate_offer(
+    test_env: &mut EscrowTestEnvironment,
+    payer: &Keypair,
+    offer_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let migrate_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        ],
+        data: get_migrate_offer_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![migrate_offer_instruction],
+        &[payer],
+        &payer.pubkey(),
+    )
+}
+
+/// Moves an offer to the address offers had before they were derived from their maker, ["offer", id], with a vault
+/// owned by the offer itself, as every offer had then
+/// Returns the legacy offer and vault addresses
+pub fn rewrite_offer_as_legacy(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: &Pubkey,
+    vault: &Pubkey,
+) -> (Pubkey, Pubkey) {
+    let offer = get_offer(test_env, offer_account);
+    let (legacy_offer_account, legacy_bump) =
+        Pubkey::find_program_address(&[b"offer", offer.id.to_le_bytes().as_ref()], &get_program_id());
+
+    let mut offer_account_data = test_env.litesvm.get_account(offer_account).unwrap();
+    offer_account_data.data[Offer::DISCRIMINATOR.len() + std::mem::offset_of!(Offer, bump)] = legacy_bump;
+    // Vault authorities came later, so legacy offers never recorded a bump for one
+    offer_account_data.data[Offer::DISCRIMINATOR.len() + std::mem::offset_of!(Offer, vault_authority_bump)] = 0;
+
+    // A token account's owner is stored right after its mint
+    let mut vault_account = test_env.litesvm.get_account(vault).unwrap();
+    vault_account.data[32..64].copy_from_slice(legacy_offer_account.as_ref());
+    let legacy_vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &legacy_offer_account,
+        &offer.token_mint_a,
+        &vault_account.owner,
+    );
+
+    test_env.litesvm.set_account(legacy_offer_account, offer_account_data).unwrap();
+    test_env.litesvm.set_account(legacy_vault, vault_account).unwrap();
+    test_env.litesvm.set_account(*offer_account, Account::default()).unwrap();
+    test_env.litesvm.set_account(*vault, Account::default()).unwrap();
+
+    (legacy_offer_account, legacy_vault)
+}
+
+/// Rewrites an offer as it was before vault authorities, when the offer itself owned its vault, or for delegated offers
+/// was the maker's approved delegate
+/// Returns the offer-owned vault address, or the maker's token a account for delegated offers
+pub fn rewrite_offer_as_offer_owned(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: &Pubkey,
+    vault: &Pubkey,
+) -> Pubkey {
+    let offer = get_offer(test_env, offer_account);
+
+    let mut offer_account_data = test_env.litesvm.get_account(offer_account).unwrap();
+    offer_account_data.data[Offer::DISCRIMINATOR.len() + std::mem::offset_of!(Offer, vault_authority_bump)] = 0;
+    test_env.litesvm.set_account(*offer_account, offer_account_data).unwrap();
+
+    // A token account's owner is stored right after its mint, and its delegate after its amount and the delegate's tag
+    let mut vault_account = test_env.litesvm.get_account(vault).unwrap();
+    if offer.is_delegated() {
+        vault_account.data[76..108].copy_from_slice(offer_account.as_ref());
+        test_env.litesvm.set_account(*vault, vault_account).unwrap();
+        return *vault;
+    }
+    vault_account.data[32..64].copy_from_slice(offer_account.as_ref());
+    let offer_owned_vault = spl_associated_token_account::get_associated_token_address_with_program_id(
+        offer_account,
+        &offer.token_mint_a,
+        &vault_account.owner,
+    );
+    test_env.litesvm.set_account(offer_owned_vault, vault_account).unwrap();
+    test_env.litesvm.set_account(*vault, Account::default()).unwrap();
+
+    offer_owned_vault
+}
+
+/// Executes refund_legacy_offer, returning a legacy offer's tokens to its maker
+pub fn execute_refund_legacy_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let refund_legacy_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(test_env.token_program, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new(get_offer(test_env, &offer_account).rent_payer, false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&maker.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&maker.pubkey()), false),
+            optional_account_meta(None, true),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: get_refund_legacy_offer_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![refund_legacy_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+pub struct TakeOffersBatchAccounts {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub taker_token_account_a: Pubkey,
+    pub taker_token_account_b: Pubkey,
+    pub maker_token_account_b: Pubkey,
+    /// The offer account and vault of each offer to take
+    pub offers: Vec<(Pubkey, Pubkey)>,
+}
+
+pub fn build_take_offers_batch_instruction(accounts: TakeOffersBatchAccounts) -> Instruction {
+    let instruction_data = get_take_offers_batch_discriminator();
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(accounts.taker, true),
+        AccountMeta::new(accounts.maker, false),
+        AccountMeta::new_readonly(accounts.token_mint_a, false),
+        AccountMeta::new_readonly(accounts.token_mint_b, false),
+        AccountMeta::new(accounts.taker_token_account_a, false),
+        AccountMeta::new(accounts.taker_token_account_b, false),
+        AccountMeta::new(accounts.maker_token_account_b, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_registry_address(), false),
+        AccountMeta::new(get_maker_index_address(&accounts.maker), false),
+        AccountMeta::new(get_maker_stats_address(&accounts.maker), false),
+        AccountMeta::new(get_fee_vault_address(&accounts.token_mint_a), false),
+        event_authority_account_meta(),
+        program_account_meta(),
+    ];
+    for (offer_account, vault) in accounts.offers {
+        account_metas.push(AccountMeta::new(offer_account, false));
+        account_metas.push(AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false));
+        account_metas.push(AccountMeta::new(vault, false));
+    }
+
+    Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    }
+}
+
+/// Executes take_offers_batch with Bob taking offers `maker` made for token A and token B
+pub fn execute_take_offers_batch(
+    test_env: &mut EscrowTestEnvironment,
+    maker: Pubkey,
+    offers: Vec<(Pubkey, Pubkey)>,
+) -> Result<(), SolanaKiteError> {
+    let take_offers_batch_instruction = build_take_offers_batch_instruction(TakeOffersBatchAccounts {
+        taker: test_env.bob.pubkey(),
+        maker,
+        token_mint_a: test_env.token_mint_a.pubkey(),
+        token_mint_b: test_env.token_mint_b.pubkey(),
+        taker_token_account_a: test_env.bob_token_account_a,
+        taker_token_account_b: test_env.bob_token_account_b,
+        maker_token_account_b: spl_associated_token_account::get_associated_token_address(
+            &maker,
+            &test_env.token_mint_b.pubkey(),
+        ),
+        offers,
+    });
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offers_batch_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes take_offer_exact_out with Bob taking `token_a_amount` of one of Alice's offers
+pub fn execute_take_offer_exact_out(
+    test_env: &mut EscrowTestEnvironment,
+    token_a_amount: u64,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let take_offer_instruction = build_take_offer_exact_out_instruction(
+        token_a_amount,
+        TakeOfferAccounts {
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: test_env.token_program,
+            system_program: anchor_lang::system_program::ID,
+            taker: test_env.bob.pubkey(),
+            maker: test_env.alice.pubkey(),
+            rent_payer: None,
+            token_mint_a: test_env.token_mint_a.pubkey(),
+            token_mint_b: test_env.token_mint_b.pubkey(),
+            taker_token_account_a: test_env.bob_token_account_a,
+            taker_token_account_b: test_env.bob_token_account_b,
+            maker_token_account_b: test_env.alice_token_account_b,
+            offer_account,
+            vault,
+            allowlist: None,
+            referrer_token_account: None,
+            token_program_b: None,
+        },
+    );
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offer_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes take_offer_vested with Bob taking one of Alice's vested offers
+///
+/// Returns the vesting account, which holds Bob's token A until it vests.
+pub fn execute_take_offer_vested(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: Pubkey,
+    vault: Pubkey,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<Pubkey, SolanaKiteError> {
+    let mut instruction_data = get_take_offer_vested_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+
+    let vesting = get_vesting_address(&offer_account);
+    let vesting_vault = spl_associated_token_account::get_associated_token_address(
+        &vesting,
+        &test_env.token_mint_a.pubkey(),
+    );
+
+    let take_offer_vested_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(test_env.alice_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(vesting, false),
+            AccountMeta::new(vesting_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_fee_vault_address(&test_env.token_mint_a.pubkey()), false),
+            optional_account_meta(None, true),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offer_vested_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )?;
+
+    Ok(vesting)
+}
+
+/// Executes claim_vested, sending Bob whatever token A has vested since his last claim
+pub fn execute_claim_vested(
+    test_env: &mut EscrowTestEnvironment,
+    beneficiary: &Keypair,
+    beneficiary_token_account: Pubkey,
+    vesting: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let vesting_vault = spl_associated_token_account::get_associated_token_address(
+        &vesting,
+        &test_env.token_mint_a.pubkey(),
+    );
+
+    let claim_vested_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(beneficiary.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(vesting, false),
+            AccountMeta::new(vesting_vault, false),
+            AccountMeta::new(beneficiary_token_account, false),
+        ],
+        data: get_claim_vested_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![claim_vested_instruction],
+        &[beneficiary],
+        &beneficiary.pubkey(),
+    )
+}
+
+/// Executes set_installment_terms, letting takers pay for one of the maker's offers in installments
+pub fn execute_set_installment_terms(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    offer_account: Pubkey,
+    installment_terms: Option<InstallmentTerms>,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_set_installment_terms_discriminator();
+    installment_terms.serialize(&mut instruction_data).unwrap();
+
+    let set_installment_terms_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new(offer_account, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![set_installment_terms_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes take_offer_in_installments with Bob taking one of Alice's offers and paying the first installment
+pub fn execute_take_offer_in_installments(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: Pubkey,
+    vault: Pubkey,
+    installment_count: u8,
+    installment_interval: i64,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<Pubkey, SolanaKiteError> {
+    let installment_plan = get_installment_plan_address(&offer_account);
+    let installment_vault = spl_associated_token_account::get_associated_token_address(
+        &installment_plan,
+        &test_env.token_mint_a.pubkey(),
+    );
+
+    let mut instruction_data = get_take_offer_in_installments_discriminator();
+    instruction_data.push(installment_count);
+    instruction_data.extend_from_slice(&installment_interval.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+
+    let take_offer_in_installments_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_a, false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(test_env.alice_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(installment_plan, false),
+            AccountMeta::new(installment_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_fee_vault_address(&test_env.token_mint_a.pubkey()), false),
+            optional_account_meta(None, true),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offer_in_installments_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )?;
+
+    Ok(installment_plan)
+}
+
+/// Executes pay_installment, paying Alice the next installment of token B and releasing Bob's share of token A
+pub fn execute_pay_installment(
+    test_env: &mut EscrowTestEnvironment,
+    taker: &Keypair,
+    installment_plan: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let installment_vault = spl_associated_token_account::get_associated_token_address(
+        &installment_plan,
+        &test_env.token_mint_a.pubkey(),
+    );
+    let taker_token_account_a = spl_associated_token_account::get_associated_token_address(
+        &taker.pubkey(),
+        &test_env.token_mint_a.pubkey(),
+    );
+    let taker_token_account_b = spl_associated_token_account::get_associated_token_address(
+        &taker.pubkey(),
+        &test_env.token_mint_b.pubkey(),
+    );
+
+    let pay_installment_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(taker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.alice.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(taker_token_account_a, false),
+            AccountMeta::new(taker_token_account_b, false),
+            AccountMeta::new(test_env.alice_token_account_b, false),
+            AccountMeta::new(installment_plan, false),
+            AccountMeta::new(installment_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_fee_vault_address(&test_env.token_mint_a.pubkey()), false),
+        ],
+        data: get_pay_installment_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![pay_installment_instruction],
+        &[taker],
+        &taker.pubkey(),
+    )
+}
+
+/// Executes cancel_default, returning the token A Bob hasn't paid for yet to Alice
+pub fn execute_cancel_default(
+    test_env: &mut EscrowTestEnvironment,
+    maker_authority: &Keypair,
+    installment_plan: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let installment_vault = spl_associated_token_account::get_associated_token_address(
+        &installment_plan,
+        &test_env.token_mint_a.pubkey(),
+    );
+
+    let cancel_default_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker_authority.pubkey(), true),
+            AccountMeta::new_readonly(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.bob.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(test_env.alice_token_account_a, false),
+            AccountMeta::new(installment_plan, false),
+            AccountMeta::new(installment_vault, false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: get_cancel_default_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![cancel_default_instruction],
+        &[maker_authority],
+        &maker_authority.pubkey(),
+    )
+}
+
+/// Executes deposit_for_offer with Bob depositing the token B one of Alice's offers wants
+pub fn execute_deposit_for_offer(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (taker_deposit, deposit_vault) =
+        get_taker_deposit_addresses(test_env, &offer_account, &test_env.bob.pubkey());
+
+    let deposit_for_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new_readonly(offer_account, false),
+            AccountMeta::new(taker_deposit, false),
+            AccountMeta::new(deposit_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            optional_account_meta(None, false),
+        ],
+        data: get_deposit_for_offer_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![deposit_for_offer_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes withdraw_deposit, returning Bob's deposit for one of Alice's offers
+pub fn execute_withdraw_deposit(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (taker_deposit, deposit_vault) =
+        get_taker_deposit_addresses(test_env, &offer_account, &test_env.bob.pubkey());
+
+    let withdraw_deposit_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(taker_deposit, false),
+            AccountMeta::new(deposit_vault, false),
+        ],
+        data: get_withdraw_deposit_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![withdraw_deposit_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes confirm_swap between Alice's offer and Bob's deposit, signed by either of them
+pub fn execute_confirm_swap(
+    test_env: &mut EscrowTestEnvironment,
+    signer: &Keypair,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (taker_deposit, deposit_vault) =
+        get_taker_deposit_addresses(test_env, &offer_account, &test_env.bob.pubkey());
+
+    let confirm_swap_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(signer.pubkey(), true),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.bob.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_a, false),
+            AccountMeta::new(test_env.alice_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(taker_deposit, false),
+            AccountMeta::new(deposit_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_fee_vault_address(&test_env.token_mint_a.pubkey()), false),
+            optional_account_meta(None, true),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: get_confirm_swap_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![confirm_swap_instruction],
+        &[signer],
+        &signer.pubkey(),
+    )
+}
+
+/// Derives the pending settlement PDA for an arbitrated offer, and the pending vault holding the taker's token B
+pub fn get_pending_settlement_addresses(test_env: &EscrowTestEnvironment, offer_account: &Pubkey) -> (Pubkey, Pubkey) {
+    let (pending_settlement, _pending_settlement_bump) = get_pda_and_bump(
+        &[b"pending_settlement".as_ref().into(), offer_account.as_ref().into()],
+        &get_program_id(),
+    );
+    let pending_vault = spl_associated_token_account::get_associated_token_address(
+        &pending_settlement,
+        &test_env.token_mint_b.pubkey(),
+    );
+    (pending_settlement, pending_vault)
+}
+
+/// Executes take_offer_arbitrated with Bob taking one of Alice's arbitrated offers
+pub fn execute_take_offer_arbitrated(
+    test_env: &mut EscrowTestEnvironment,
+    offer_account: Pubkey,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<(), SolanaKiteError> {
+    let (pending_settlement, pending_vault) =
+        get_pending_settlement_addresses(test_env, &offer_account);
+
+    let mut instruction_data = get_take_offer_arbitrated_discriminator();
+    instruction_data.extend_from_slice(&expected_token_a_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expected_token_b_amount.to_le_bytes());
+
+    let take_offer_arbitrated_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new(pending_settlement, false),
+            AccountMeta::new(pending_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            optional_account_meta(None, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_offer_arbitrated_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes release, settling Bob's take of one of Alice's arbitrated offers
+pub fn execute_release(
+    test_env: &mut EscrowTestEnvironment,
+    arbiter: &Keypair,
+    offer_account: Pubkey,
+    vault: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (pending_settlement, pending_vault) =
+        get_pending_settlement_addresses(test_env, &offer_account);
+
+    let release_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(arbiter.pubkey(), true),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.alice.pubkey(), false),
+            AccountMeta::new(test_env.bob.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_a, false),
+            AccountMeta::new(test_env.alice_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(pending_settlement, false),
+            AccountMeta::new(pending_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_registry_address(), false),
+            AccountMeta::new(get_maker_index_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_maker_stats_address(&test_env.alice.pubkey()), false),
+            AccountMeta::new(get_fee_vault_address(&test_env.token_mint_a.pubkey()), false),
+            optional_account_meta(None, true),
+            event_authority_account_meta(),
+            program_account_meta(),
+        ],
+        data: get_release_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![release_instruction],
+        &[arbiter],
+        &arbiter.pubkey(),
+    )
+}
+
+/// Executes revert, returning Bob's token B and reopening one of Alice's arbitrated offers
+pub fn execute_revert(
+    test_env: &mut EscrowTestEnvironment,
+    arbiter: &Keypair,
+    offer_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (pending_settlement, pending_vault) =
+        get_pending_settlement_addresses(test_env, &offer_account);
+
+    let revert_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(arbiter.pubkey(), true),
+            AccountMeta::new(test_env.bob.pubkey(), false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new(pending_settlement, false),
+            AccountMeta::new(pending_vault, false),
+        ],
+        data: get_revert_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![revert_instruction],
+        &[arbiter],
+        &arbiter.pubkey(),
+    )
+}
+
+pub fn execute_top_up_offer(
+    test_env: &mut EscrowTestEnvironment,
     maker: &Keypair,
     maker_token_account_a: Pubkey,
-    token_a_offered_amount: u64,
-    token_b_wanted_amount: u64,
-) -> Result<(Pubkey, Pubkey), SolanaKiteError> {
-    // Create PDAs
-    let (offer_account, _offer_bump) = get_pda_and_bump(&[b"offer".as_ref().into(), offer_id.to_le_bytes().as_ref().into()], &test_env.program_id);
-    let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
-        &test_env.token_mint_a.pubkey(),
-    );
+    offer_account: Pubkey,
+    vault: Pubkey,
+    amount: u64,
+) -> Result<(), SolanaKiteError> {
+    execute_change_offered_amount(
+        test_env,
+        get_top_up_offer_discriminator(),
+        maker,
+        maker_token_account_a,
+        offer_account,
+        vault,
+        amount,
+    )
+}
 
-    // Build accounts
-    let make_offer_accounts = build_make_offer_accounts(
-        maker.pubkey(),
-        test_env.token_mint_a.pubkey(),
-        test_env.token_mint_b.pubkey(),
+/// Executes reduce_offer, which uses the same accounts as top_up_offer
+pub fn execute_reduce_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+    amount: u64,
+) -> Result<(), SolanaKiteError> {
+    execute_change_offered_amount(
+        test_env,
+        get_reduce_offer_discriminator(),
+        maker,
         maker_token_account_a,
         offer_account,
         vault,
-    );
+        amount,
+    )
+}
 
-    // Build and execute instruction
-    let make_offer_instruction = build_make_offer_instruction(
-        offer_id,
-        token_a_offered_amount,
-        token_b_wanted_amount,
-        make_offer_accounts,
-    );
+fn execute_change_offered_amount(
+    test_env: &mut EscrowTestEnvironment,
+    discriminator: Vec<u8>,
+    maker: &Keypair,
+    maker_token_account_a: Pubkey,
+    offer_account: Pubkey,
+    vault: Pubkey,
+    amount: u64,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = discriminator;
+    instruction_data.extend_from_slice(&amount.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new(offer_account, false),
+            AccountMeta::new_readonly(get_vault_authority_address(&offer_account), false),
+            AccountMeta::new(vault, false),
+        ],
+        data: instruction_data,
+    };
 
     send_transaction_from_instructions(
         &mut test_env.litesvm,
-        vec![make_offer_instruction],
+        vec![instruction],
         &[maker],
         &maker.pubkey(),
-    )?;
+    )
+}
 
-    Ok((offer_account, vault))
+/// Executes request_quote with Bob asking makers to quote for `token_a_wanted_amount` of token A, paid in token B
+pub fn execute_request_quote(
+    test_env: &mut EscrowTestEnvironment,
+    request_id: u64,
+    token_a_wanted_amount: u64,
+    expires_at: i64,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_request_quote_discriminator();
+    instruction_data.extend_from_slice(&request_id.to_le_bytes());
+    instruction_data.extend_from_slice(&token_a_wanted_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&expires_at.to_le_bytes());
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(test_env.bob.pubkey(), true),
+        AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+        AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+        AccountMeta::new(get_quote_request_address(&test_env.bob.pubkey(), request_id), false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        optional_account_meta(None, false),
+        optional_account_meta(None, false),
+    ];
+    account_metas.extend(build_mint_deny_entry_account_metas(
+        &test_env.token_mint_a.pubkey(),
+        &test_env.token_mint_b.pubkey(),
+    ));
+
+    let request_quote_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![request_quote_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
 }
 
-/// Executes a complete take_offer flow: builds accounts and executes instruction
-pub fn execute_take_offer(
+/// Executes submit_quote, with `maker` escrowing the token A one of Bob's quote requests wants
+pub fn execute_submit_quote(
     test_env: &mut EscrowTestEnvironment,
-    taker: &Keypair,
     maker: &Keypair,
-    taker_token_account_a: Pubkey,
-    taker_token_account_b: Pubkey,
-    maker_token_account_b: Pubkey,
-    offer_account: Pubkey,
-    vault: Pubkey,
+    maker_token_account_a: Pubkey,
+    quote_request: Pubkey,
+    token_b_wanted_amount: u64,
 ) -> Result<(), SolanaKiteError> {
-    let take_offer_accounts = TakeOfferAccounts {
-        associated_token_program: spl_associated_token_account::ID,
-        token_program: spl_token::ID,
-        system_program: anchor_lang::system_program::ID,
-        taker: taker.pubkey(),
-        maker: maker.pubkey(),
-        token_mint_a: test_env.token_mint_a.pubkey(),
-        token_mint_b: test_env.token_mint_b.pubkey(),
-        taker_token_account_a,
-        taker_token_account_b,
-        maker_token_account_b,
-        offer_account,
-        vault,
+    let (quote, quote_vault) = get_quote_addresses(test_env, &quote_request, &maker.pubkey());
+    let mut instruction_data = get_submit_quote_discriminator();
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+
+    let submit_quote_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new_readonly(quote_request, false),
+            AccountMeta::new(quote, false),
+            AccountMeta::new(quote_vault, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+        ],
+        data: instruction_data,
     };
 
-    let take_offer_instruction = build_take_offer_instruction(take_offer_accounts);
-    
     send_transaction_from_instructions(
         &mut test_env.litesvm,
-        vec![take_offer_instruction],
-        &[taker],
-        &taker.pubkey(),
+        vec![submit_quote_instruction],
+        &[maker],
+        &maker.pubkey(),
     )
 }
 
-/// Executes a complete refund_offer flow: builds accounts and executes instruction
-pub fn execute_refund_offer(
+/// Executes accept_quote, with Bob paying for `maker`'s quote on one of their quote requests
+pub fn execute_accept_quote(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Pubkey,
+    quote_request: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let (quote, quote_vault) = get_quote_addresses(test_env, &quote_request, maker);
+    let token_mint_a = test_env.token_mint_a.pubkey();
+    let token_mint_b = test_env.token_mint_b.pubkey();
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+        AccountMeta::new(test_env.bob.pubkey(), true),
+        AccountMeta::new(*maker, false),
+        AccountMeta::new_readonly(token_mint_a, false),
+        AccountMeta::new_readonly(token_mint_b, false),
+        AccountMeta::new(test_env.bob_token_account_a, false),
+        AccountMeta::new(test_env.bob_token_account_b, false),
+        AccountMeta::new(spl_associated_token_account::get_associated_token_address(maker, &token_mint_b), false),
+        AccountMeta::new(quote_request, false),
+        AccountMeta::new(quote, false),
+        AccountMeta::new(quote_vault, false),
+        AccountMeta::new_readonly(get_config_address(), false),
+        AccountMeta::new(get_fee_vault_address(&token_mint_a), false),
+    ];
+    account_metas.extend(build_mint_deny_entry_account_metas(&token_mint_a, &token_mint_b));
+    account_metas.push(optional_account_meta(None, false));
+
+    let accept_quote_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: account_metas,
+        data: get_accept_quote_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![accept_quote_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes withdraw_quote, returning `maker`'s quoted token A once the quote is no longer firm
+pub fn execute_withdraw_quote(
     test_env: &mut EscrowTestEnvironment,
     maker: &Keypair,
     maker_token_account_a: Pubkey,
-    offer_account: Pubkey,
-    vault: Pubkey,
+    quote_request: Pubkey,
 ) -> Result<(), SolanaKiteError> {
-    let refund_offer_accounts = RefundOfferAccounts {
-        token_program: spl_token::ID,
-        system_program: anchor_lang::system_program::ID,
-        maker: maker.pubkey(),
-        token_mint_a: test_env.token_mint_a.pubkey(),
-        maker_token_account_a,
-        offer_account,
-        vault,
+    let (quote, quote_vault) = get_quote_addresses(test_env, &quote_request, &maker.pubkey());
+
+    let withdraw_quote_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new_readonly(test_env.token_mint_a.pubkey(), false),
+            AccountMeta::new(maker_token_account_a, false),
+            AccountMeta::new_readonly(quote_request, false),
+            AccountMeta::new(quote, false),
+            AccountMeta::new(quote_vault, false),
+        ],
+        data: get_withdraw_quote_discriminator(),
     };
 
-    let refund_instruction = build_refund_offer_instruction(refund_offer_accounts);
-    
     send_transaction_from_instructions(
         &mut test_env.litesvm,
-        vec![refund_instruction],
+        vec![withdraw_quote_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes close_quote_request, with Bob closing one of their quote requests without accepting a quote
+pub fn execute_close_quote_request(
+    test_env: &mut EscrowTestEnvironment,
+    quote_request: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let close_quote_request_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new(quote_request, false),
+        ],
+        data: get_close_quote_request_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![close_quote_request_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Creates an initialized, never delegated stake account holding `lamports`, with `owner` as its staker and withdrawer
+pub fn create_stake_account(test_env: &mut EscrowTestEnvironment, owner: &Keypair, lamports: u64) -> Pubkey {
+    use solana_program::stake::{
+        instruction::create_account,
+        state::{Authorized, Lockup},
+    };
+
+    let stake_account = Keypair::new();
+    let instructions = create_account(
+        &owner.pubkey(),
+        &stake_account.pubkey(),
+        &Authorized::auto(&owner.pubkey()),
+        &Lockup::default(),
+        lamports,
+    );
+    send_transaction_from_instructions(&mut test_env.litesvm, instructions, &[owner, &stake_account], &owner.pubkey())
+        .unwrap();
+    stake_account.pubkey()
+}
+
+/// Reads a stake account's staker and withdrawer
+pub fn get_stake_authorities(test_env: &EscrowTestEnvironment, stake_account: &Pubkey) -> (Pubkey, Pubkey) {
+    let account = test_env.litesvm.get_account(stake_account).unwrap();
+    let stake_state = anchor_spl::stake::StakeAccount::try_deserialize(&mut account.data.as_slice()).unwrap();
+    let authorized = stake_state.authorized().unwrap();
+    (authorized.staker, authorized.withdrawer)
+}
+
+/// Executes make_stake_offer, with `maker` offering their stake account for `token_b_wanted_amount` of token B
+pub fn execute_make_stake_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    stake_account: Pubkey,
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+) -> Result<(), SolanaKiteError> {
+    let mut instruction_data = get_make_stake_offer_discriminator();
+    instruction_data.extend_from_slice(&token_b_wanted_amount.to_le_bytes());
+    expires_at.serialize(&mut instruction_data).unwrap();
+
+    let make_stake_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(solana_program::stake::program::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new(stake_account, false),
+            AccountMeta::new_readonly(test_env.token_mint_b.pubkey(), false),
+            AccountMeta::new(get_stake_offer_address(&stake_account), false),
+            AccountMeta::new_readonly(solana_program::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            optional_account_meta(None, false),
+        ],
+        data: instruction_data,
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![make_stake_offer_instruction],
+        &[maker],
+        &maker.pubkey(),
+    )
+}
+
+/// Executes take_stake_offer, with Bob paying `maker`'s stake offer in token B and becoming the stake account's staker and withdrawer
+pub fn execute_take_stake_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Pubkey,
+    stake_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let token_mint_b = test_env.token_mint_b.pubkey();
+
+    let take_stake_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(solana_program::stake::program::ID, false),
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new(test_env.bob.pubkey(), true),
+            AccountMeta::new(*maker, false),
+            AccountMeta::new(stake_account, false),
+            AccountMeta::new_readonly(token_mint_b, false),
+            AccountMeta::new(test_env.bob_token_account_b, false),
+            AccountMeta::new(spl_associated_token_account::get_associated_token_address(maker, &token_mint_b), false),
+            AccountMeta::new(get_stake_offer_address(&stake_account), false),
+            AccountMeta::new_readonly(solana_program::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(get_config_address(), false),
+            AccountMeta::new(get_fee_vault_address(&token_mint_b), false),
+            optional_account_meta(None, false),
+        ],
+        data: get_take_stake_offer_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![take_stake_offer_instruction],
+        &[&test_env.bob],
+        &test_env.bob.pubkey(),
+    )
+}
+
+/// Executes cancel_stake_offer, handing the stake account's authorities back to `maker`
+pub fn execute_cancel_stake_offer(
+    test_env: &mut EscrowTestEnvironment,
+    maker: &Keypair,
+    stake_account: Pubkey,
+) -> Result<(), SolanaKiteError> {
+    let cancel_stake_offer_instruction = Instruction {
+        program_id: get_program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(solana_program::stake::program::ID, false),
+            AccountMeta::new(maker.pubkey(), true),
+            AccountMeta::new(stake_account, false),
+            AccountMeta::new(get_stake_offer_address(&stake_account), false),
+            AccountMeta::new_readonly(solana_program::sysvar::clock::ID, false),
+        ],
+        data: get_cancel_stake_offer_discriminator(),
+    };
+
+    send_transaction_from_instructions(
+        &mut test_env.litesvm,
+        vec![cancel_stake_offer_instruction],
         &[maker],
         &maker.pubkey(),
     )
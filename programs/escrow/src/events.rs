@@ -0,0 +1,85 @@
+use anchor_lang::{
+    event::EVENT_IX_TAG_LE,
+    prelude::*,
+    solana_program::{instruction::Instruction, program::invoke_signed},
+    Event,
+};
+
+use crate::constants::OFFER_TAGS_LENGTH;
+
+// Events emitted over an offer's lifecycle, so indexers and frontends don't have to
+// re-derive what happened from account changes
+
+#[event]
+pub struct OfferMade {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The amount of token a the vault received, after any transfer fee
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub created_at: i64,
+    // The categories the maker tagged the offer with, all zero if untagged
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+}
+
+#[event]
+pub struct OfferTaken {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The amount of token a the taker received, after the protocol fee and any Token-2022 transfer fee
+    pub token_a_amount: u64,
+    // The amount of token b the maker received
+    pub token_b_amount: u64,
+    // Whether the maker didn't have a token b account yet, so one was created for them
+    pub maker_token_account_b_created: bool,
+    // When the offer was made, so analytics can work out how long it took to fill, 0 for offers made before it was recorded
+    pub created_at: i64,
+    pub filled_at: i64,
+    // Who received token a: the taker, unless they took the offer on behalf of a recipient
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct OfferRefunded {
+    pub offer_id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The amount of token a returned to the maker
+    pub token_a_amount: u64,
+    // When the offer was made, 0 for offers made before it was recorded
+    pub created_at: i64,
+    pub refunded_at: i64,
+}
+
+// Emit an event through a self-CPI, which is what Anchor's emit_cpi! does
+// CPI events are stored in the transaction's inner instructions, so unlike logs they can't be truncated
+// With the log-events feature, the event is also written to the program logs like emit!
+pub fn emit_event<'info, T: Event>(
+    event: T,
+    event_authority: &AccountInfo<'info>,
+    event_authority_bump: u8,
+) -> Result<()> {
+    let event_data = event.data();
+
+    #[cfg(feature = "log-events")]
+    anchor_lang::solana_program::log::sol_log_data(&[&event_data]);
+
+    let instruction = Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new_readonly(*event_authority.key, true)],
+        data: EVENT_IX_TAG_LE.iter().copied().chain(event_data).collect(),
+    };
+    invoke_signed(
+        &instruction,
+        std::slice::from_ref(event_authority),
+        &[&[b"__event_authority", &[event_authority_bump]]],
+    )?;
+
+    Ok(())
+}
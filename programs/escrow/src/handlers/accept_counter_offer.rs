@@ -0,0 +1,214 @@
+use super::shared::{
+    close_token_account, record_maker_offer_filled, record_maker_volume, record_offers_closed,
+    record_volume_settled, remove_from_maker_index, require_offer_not_expired, transfer_tokens,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, CounterOffer, MakerIndex, MakerStats, Offer, Registry},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct AcceptCounterOffer<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = proposer,
+        associated_token::token_program = token_program,
+    )]
+    pub proposer_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = proposer,
+        associated_token::token_program = token_program,
+    )]
+    pub proposer_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = proposer,
+        has_one = offer @ ErrorCode::WrongOffer,
+        has_one = proposer @ ErrorCode::InvalidProposer,
+        seeds = [b"counter_offer", offer.key().as_ref(), proposer.key().as_ref()],
+        bump = counter_offer.bump
+    )]
+    pub counter_offer: Account<'info, CounterOffer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Read for the mint the maker's fee tier volume is counted in
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the accept counter offer instruction by:
+// 1. Withdrawing the offered tokens from the vault to the proposer and closing the vault
+// 2. Sending the proposed tokens from the proposer to the maker, using the counter offer's delegation
+// 3. Recording the settlement in the registry, the maker's index and the maker's statistics
+pub fn accept_counter_offer(context: Context<AcceptCounterOffer>) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+    require!(offer.arbiter().is_none(), ErrorCode::OfferIsArbitrated);
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let vault_authority_signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Withdraw the offered tokens from the vault to the proposer
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.proposer_token_account_a,
+        &context.accounts.vault.amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // The counter offer PDA was approved as a delegate on the proposer's token b account
+    let proposer_key = context.accounts.proposer.key();
+    let counter_offer_account_seeds = &[
+        b"counter_offer",
+        offer_key.as_ref(),
+        proposer_key.as_ref(),
+        &[context.accounts.counter_offer.bump],
+    ];
+
+    // Send the proposed tokens from the proposer to the maker
+    transfer_tokens(
+        &context.accounts.proposer_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &context.accounts.counter_offer.token_b_offered_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.counter_offer.to_account_info(),
+        &context.accounts.token_program,
+        Some(&counter_offer_account_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::InsufficientTakerBalance)?;
+
+    let token_b_offered_amount = context.accounts.counter_offer.token_b_offered_amount;
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        context.accounts.vault.amount,
+        token_b_offered_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_offered_amount)?;
+
+    Ok(())
+}
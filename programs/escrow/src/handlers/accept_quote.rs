@@ -0,0 +1,236 @@
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_token_program_b, require_mint_not_denied, transfer_tokens, transfer_tokens_and_verify,
+    transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, Quote, QuoteRequest},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct AcceptQuote<'info> {
+    // Used to create the requester's token a account and the maker's token b account if they don't have them yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program, whichever owns token a
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    /// CHECK: Checked against the quote's maker with has_one, who gets the rent for the quote and its vault back
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = requester,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = requester,
+        associated_token::token_program = token_program,
+    )]
+    pub requester_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Any of the requester's token b accounts
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = requester
+    )]
+    pub requester_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    /// Derived with token b's own token program, which can differ from token a's
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    // Closed once a quote is accepted, the makers of the other quotes can then withdraw them
+    #[account(
+        mut,
+        close = requester,
+        has_one = requester @ ErrorCode::InvalidRequester,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"quote_request", quote_request.requester.as_ref(), quote_request.id.to_le_bytes().as_ref()],
+        bump = quote_request.bump
+    )]
+    pub quote_request: Box<Account<'info, QuoteRequest>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = quote_request @ ErrorCode::WrongQuoteRequest,
+        seeds = [b"quote", quote_request.key().as_ref(), maker.key().as_ref()],
+        bump = quote.bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = quote,
+        associated_token::token_program = token_program
+    )]
+    pub quote_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = requester,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+}
+
+// Handle the accept quote instruction by:
+// 1. Checking the quote request hasn't expired
+// 2. Withdrawing the quoted tokens from the quote vault to the requester, less the config's protocol fee,
+//    and closing the vault
+// 3. Sending the token b the maker wants from the requester to the maker
+//    If token b charges a transfer fee, the requester pays it on top, so the maker gets the full amount
+// 4. Closing the quote and the quote request, returning their rent to whoever paid for them
+// Quotes aren't offers, so they don't count towards the maker's statistics or fee tier
+pub fn accept_quote(context: Context<AcceptQuote>) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < context.accounts.quote_request.expires_at,
+        ErrorCode::QuoteRequestExpired
+    );
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    let token_program_b = get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    let quote_request_key = context.accounts.quote_request.key();
+    let maker_key = context.accounts.maker.key();
+    let quote_seeds = &[
+        b"quote",
+        quote_request_key.as_ref(),
+        maker_key.as_ref(),
+        &[context.accounts.quote.bump],
+    ];
+    let signers_seeds = Some(&quote_seeds[..]);
+    let quote_info = context.accounts.quote.to_account_info();
+
+    let token_a_amount = context.accounts.quote_vault.amount;
+    let protocol_fee = calculate_fee(token_a_amount, context.accounts.config.fee_bps)?;
+
+    // Send the protocol fee from the quote vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.quote_vault,
+            &*context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &quote_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Withdraw the rest of the quoted tokens to the requester, checking what actually arrived
+    transfer_tokens_and_verify(
+        &context.accounts.quote_vault,
+        &*context.accounts.requester_token_account_a,
+        &(token_a_amount - protocol_fee),
+        &context.accounts.token_mint_a,
+        &quote_info,
+        &context.accounts.token_program,
+        signers_seeds,
+        ErrorCode::FailedVaultWithdrawal,
+    )?;
+
+    // Close the quote vault and return the rent to the maker, who paid for it
+    close_token_account(
+        &context.accounts.quote_vault,
+        &context.accounts.maker.to_account_info(),
+        &quote_info,
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // Makers don't need a token b account to quote, so create one for them if they don't have one yet
+    create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.requester.to_account_info(),
+        token_program_b,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Send the wanted tokens from the requester to the maker
+    transfer_tokens_including_fee(
+        &context.accounts.requester_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &context.accounts.quote.token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.requester.to_account_info(),
+        token_program_b,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    Ok(())
+}
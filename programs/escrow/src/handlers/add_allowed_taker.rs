@@ -0,0 +1,60 @@
+use crate::{
+    constants::MAX_ALLOWED_TAKERS,
+    error::ErrorCode,
+    state::{Offer, OfferAllowlist},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct AddAllowedTaker<'info> {
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = OfferAllowlist::DISCRIMINATOR.len() + OfferAllowlist::INIT_SPACE,
+        seeds = [b"allowlist", offer.key().as_ref()],
+        bump
+    )]
+    pub allowlist: Account<'info, OfferAllowlist>,
+}
+
+// Handle the add allowed taker instruction by:
+// 1. Starting a fresh allowlist, if the offer doesn't have one enabled yet
+// 2. Adding the taker to the allowlist
+pub fn add_allowed_taker(context: Context<AddAllowedTaker>, taker: Pubkey) -> Result<()> {
+    let mut offer = context.accounts.offer.load_mut()?;
+
+    // An allowlist left behind by an earlier offer with the same id must not carry over
+    if !offer.allowlist_enabled() {
+        context.accounts.allowlist.set_inner(OfferAllowlist {
+            offer: context.accounts.offer.key(),
+            takers: Vec::new(),
+            bump: context.bumps.allowlist,
+        });
+        offer.set_allowlist_enabled(true);
+    }
+
+    let allowlist = &mut context.accounts.allowlist;
+    if allowlist.takers.contains(&taker) {
+        return Ok(());
+    }
+    require!(
+        allowlist.takers.len() < MAX_ALLOWED_TAKERS,
+        ErrorCode::AllowlistFull
+    );
+    allowlist.takers.push(taker);
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferRefunded},
+    handlers::{
+        bundle::withdraw_additional_tokens,
+        shared::{
+            close_token_account, record_maker_offer_refunded, record_offers_closed,
+            remove_from_maker_index, transfer_tokens,
+        },
+    },
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, Registry},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AdminForceRefund<'info> {
+    // Used to create the maker's token account if they have closed it
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the admin force refund instruction by:
+// 1. Returning the tokens from the vault to the maker's account
+// 2. Closing the vault and returning the rent to whoever paid for it
+// 3. For bundle offers, doing the same for each additional vault
+// 4. Recording the refund in the registry, the maker's index and the maker's statistics
+// 5. Emitting an OfferRefunded event
+// This is refund_offer for incident response: the config authority can refund any offer,
+// for example when a mint is compromised, and everything still goes back to the maker
+pub fn admin_force_refund<'info>(
+    context: Context<'_, '_, 'info, 'info, AdminForceRefund<'info>>,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    // Bidders' tokens are locked in the bid vault until the auction is settled
+    require!(offer.highest_bid == 0, ErrorCode::AuctionHasBids);
+    // A taker's tokens are waiting on the arbiter, who has to revert the take first
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+    let token_a_refunded_amount = context.accounts.vault.amount;
+
+    // Return the tokens from the vault to the maker's account
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.maker_token_account_a,
+        &token_a_refunded_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundClosure)?;
+
+    // Return the rest of a bundle
+    withdraw_additional_tokens(
+        context.remaining_accounts,
+        offer.additional_token_mints_a(),
+        &context.accounts.maker.key(),
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        &vault_authority_seeds[..],
+        ErrorCode::FailedRefundTransfer,
+        ErrorCode::FailedRefundClosure,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_refunded(&mut context.accounts.maker_stats)?;
+
+    let clock = Clock::get()?;
+    emit_event(
+        OfferRefunded {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: offer.token_mint_b,
+            token_a_amount: token_a_refunded_amount,
+            created_at: offer.created_at,
+            refunded_at: clock.unix_timestamp,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
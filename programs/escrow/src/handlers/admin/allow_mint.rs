@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintAllowlistEntry},
+};
+
+#[derive(Accounts)]
+pub struct AllowMint<'info> {
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Pays the rent for the allowlist entry, and gets it back when the mint is revoked
+    #[account(mut)]
+    pub allowlist_manager: Signer<'info>,
+
+    #[account(
+        has_one = allowlist_manager @ ErrorCode::InvalidAllowlistManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = allowlist_manager,
+        space = MintAllowlistEntry::DISCRIMINATOR.len() + MintAllowlistEntry::INIT_SPACE,
+        seeds = [b"mint_allowlist_entry", token_mint.key().as_ref()],
+        bump
+    )]
+    pub mint_allowlist_entry: Account<'info, MintAllowlistEntry>,
+}
+
+// Handle the allow mint instruction by:
+// 1. Creating the mint's allowlist entry
+// While the config's mint allowlist is enabled, offers can only be made between mints that have an entry
+pub fn allow_mint(context: Context<AllowMint>) -> Result<()> {
+    context
+        .accounts
+        .mint_allowlist_entry
+        .set_inner(MintAllowlistEntry {
+            mint: context.accounts.token_mint.key(),
+            bump: context.bumps.mint_allowlist_entry,
+        });
+    Ok(())
+}
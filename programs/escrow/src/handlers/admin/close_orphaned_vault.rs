@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    error::ErrorCode,
+    handlers::shared::{close_token_account, transfer_tokens},
+    state::Config,
+};
+
+#[derive(Accounts)]
+// The maker argument is read as maker_key, since maker is also the account the stranded tokens go back to
+#[instruction(maker_key: Pubkey, offer_id: u64)]
+pub struct CloseOrphanedVault<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create the maker's token account if they don't have one yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        has_one = fee_authority @ ErrorCode::InvalidFeeAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: The protocol's treasury, checked against the config's fee authority with has_one, gets the vault's rent
+    #[account(mut)]
+    pub fee_authority: UncheckedAccount<'info>,
+
+    /// CHECK: The address the maker's offer with this id had, the handler checks the offer no longer exists
+    #[account(
+        seeds = [b"offer", maker_key.as_ref(), offer_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub offer: UncheckedAccount<'info>,
+
+    /// CHECK: Holds no data, it owned and still signs for the missing offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    /// CHECK: The maker the missing offer belonged to, who gets back anything left in the vault
+    #[account(address = maker_key @ ErrorCode::InvalidMaker)]
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    // Any of the offer's vaults, token a's or a bundle's, are the vault authority's associated token account for their mint
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Anything left in the vault is still the maker's, so it goes back to them
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token_mint,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the close orphaned vault instruction by:
+// 1. Checking the offer the vault belonged to no longer exists, so a live offer's vault can never be closed
+// 2. Returning anything left in the vault to the maker's token account
+// 3. Closing the vault and sending its rent to the fee authority, the protocol's treasury
+// The tokens always go to the maker, so a vault funded ahead of make_offer_with_existing_vault only goes back to them
+// Every handler that closes an offer also closes its vaults, so this is only for vaults stranded by an incident,
+// like a migration that failed part way
+pub fn close_orphaned_vault(
+    context: Context<CloseOrphanedVault>,
+    _maker: Pubkey,
+    _offer_id: u64,
+) -> Result<()> {
+    // A closed account has no lamports, no data, and is handed back to the system program
+    let offer = &context.accounts.offer;
+    require!(
+        offer.lamports() == 0 && offer.data_is_empty() && offer.owner == &System::id(),
+        ErrorCode::OfferStillExists
+    );
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[context.bumps.vault_authority],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    let stranded_amount = context.accounts.vault.amount;
+    if stranded_amount > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.maker_token_account,
+            &stranded_amount,
+            &context.accounts.token_mint,
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.fee_authority.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintDenyEntry},
+};
+
+#[derive(Accounts)]
+pub struct DenyMint<'info> {
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Pays the rent for the deny entry, and gets it back when the mint is undenied
+    #[account(mut)]
+    pub allowlist_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = allowlist_manager @ ErrorCode::InvalidAllowlistManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = allowlist_manager,
+        space = MintDenyEntry::DISCRIMINATOR.len() + MintDenyEntry::INIT_SPACE,
+        seeds = [b"mint_deny_entry", token_mint.key().as_ref()],
+        bump
+    )]
+    pub mint_deny_entry: Account<'info, MintDenyEntry>,
+}
+
+// Handle the deny mint instruction by:
+// 1. Creating the mint's deny entry
+// 2. Counting it in the config, so make_offer and take_offer start checking deny entries
+// Offers for the mint can no longer be made or taken with take_offer, but makers can still refund them
+pub fn deny_mint(context: Context<DenyMint>) -> Result<()> {
+    context.accounts.mint_deny_entry.set_inner(MintDenyEntry {
+        mint: context.accounts.token_mint.key(),
+        bump: context.bumps.mint_deny_entry,
+    });
+
+    let config = &mut context.accounts.config;
+    config.denied_mint_count = config
+        .denied_mint_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::{BASIS_POINTS_DENOMINATOR, MAX_FEE_BPS, MAX_MAKER_OPEN_OFFERS},
+    error::ErrorCode,
+    state::{Config, MintPolicy, Registry},
+};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // There is only one config, so the seeds are constant and it can only be initialized once
+    #[account(
+        init,
+        payer = admin,
+        space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The protocol-wide offer statistics, created alongside the config since there is also only one
+    #[account(
+        init,
+        payer = admin,
+        space = Registry::DISCRIMINATOR.len() + Registry::INIT_SPACE,
+        seeds = [b"registry"],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Handle the initialize config instruction by:
+// 1. Checking the protocol fee and referral share are within the allowed range
+// 2. Saving the fee, referral share and fee authority to the config account, allowing every kind of mint
+//    Royalties start out optional, and offers of any size can be made between any mints
+//    Makers keep all of a match's price improvement until the fee manager gives matchers a share
+// 3. Starting the registry's offer statistics at zero
+// Whoever calls this first becomes the config authority, and holds every role until they give them out with set_role,
+// so it should be sent as soon as the program is deployed
+pub fn initialize_config(
+    context: Context<InitializeConfig>,
+    fee_bps: u16,
+    fee_authority: Pubkey,
+    referral_share_bps: u16,
+) -> Result<()> {
+    require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+    // Referrers can be given at most the whole protocol fee
+    require!(
+        referral_share_bps as u64 <= BASIS_POINTS_DENOMINATOR,
+        ErrorCode::InvalidFee
+    );
+
+    context.accounts.config.set_inner(Config {
+        authority: context.accounts.admin.key(),
+        fee_bps,
+        fee_authority,
+        referral_share_bps,
+        paused: false,
+        mint_policy: MintPolicy::default(),
+        royalties_required: false,
+        min_offer_amount: 0,
+        max_offer_amount: u64::MAX,
+        mint_allowlist_enabled: false,
+        denied_mint_count: 0,
+        fee_tiers: Vec::new(),
+        membership_discount: None,
+        pauser: context.accounts.admin.key(),
+        fee_manager: context.accounts.admin.key(),
+        allowlist_manager: context.accounts.admin.key(),
+        matcher_share_bps: 0,
+        min_offer_age_slots: 0,
+        max_open_offers_per_maker: MAX_MAKER_OPEN_OFFERS as u16,
+        fee_tier_mint: Pubkey::default(),
+        bump: context.bumps.config,
+    });
+
+    context.accounts.registry.set_inner(Registry {
+        total_offers_created: 0,
+        total_offers_open: 0,
+        total_volume_settled: 0,
+        bump: context.bumps.registry,
+    });
+
+    Ok(())
+}
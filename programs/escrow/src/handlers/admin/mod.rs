@@ -0,0 +1,59 @@
+pub mod initialize_config;
+pub use initialize_config::*;
+
+pub mod withdraw_fees;
+pub use withdraw_fees::*;
+
+pub mod pause;
+pub use pause::*;
+
+pub mod unpause;
+pub use unpause::*;
+
+pub mod set_mint_policy;
+pub use set_mint_policy::*;
+
+pub mod set_royalties_required;
+pub use set_royalties_required::*;
+
+pub mod set_limits;
+pub use set_limits::*;
+
+pub mod set_mint_allowlist_enabled;
+pub use set_mint_allowlist_enabled::*;
+
+pub mod allow_mint;
+pub use allow_mint::*;
+
+pub mod revoke_mint;
+pub use revoke_mint::*;
+
+pub mod deny_mint;
+pub use deny_mint::*;
+
+pub mod undeny_mint;
+pub use undeny_mint::*;
+
+pub mod set_fee_tiers;
+pub use set_fee_tiers::*;
+
+pub mod set_membership_discount;
+pub use set_membership_discount::*;
+
+pub mod set_matcher_share;
+pub use set_matcher_share::*;
+
+pub mod set_min_offer_age;
+pub use set_min_offer_age::*;
+
+pub mod set_max_open_offers;
+pub use set_max_open_offers::*;
+
+pub mod set_role;
+pub use set_role::*;
+
+pub mod admin_force_refund;
+pub use admin_force_refund::*;
+
+pub mod close_orphaned_vault;
+pub use close_orphaned_vault::*;
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    pub pauser: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = pauser @ ErrorCode::InvalidPauser,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the pause instruction by:
+// 1. Setting the paused flag on the config to true
+// Pausing stops new offers being made or taken, refunds are still allowed so makers can always get their tokens back
+pub fn pause(context: Context<Pause>) -> Result<()> {
+    context.accounts.config.paused = true;
+    Ok(())
+}
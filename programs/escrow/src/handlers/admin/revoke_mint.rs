@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintAllowlistEntry},
+};
+
+#[derive(Accounts)]
+pub struct RevokeMint<'info> {
+    #[account(mut)]
+    pub allowlist_manager: Signer<'info>,
+
+    #[account(
+        has_one = allowlist_manager @ ErrorCode::InvalidAllowlistManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = allowlist_manager,
+        seeds = [b"mint_allowlist_entry", mint_allowlist_entry.mint.as_ref()],
+        bump = mint_allowlist_entry.bump
+    )]
+    pub mint_allowlist_entry: Account<'info, MintAllowlistEntry>,
+}
+
+// Handle the revoke mint instruction by:
+// 1. Closing the mint's allowlist entry, returning its rent to the allowlist manager
+// Only offers made afterwards are checked, existing offers for the mint can still be taken or refunded
+pub fn revoke_mint(_context: Context<RevokeMint>) -> Result<()> {
+    Ok(())
+}
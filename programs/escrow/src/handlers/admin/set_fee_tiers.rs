@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::{MAX_FEE_BPS, MAX_FEE_TIERS},
+    error::ErrorCode,
+    state::{Config, FeeTier},
+};
+
+#[derive(Accounts)]
+pub struct SetFeeTiers<'info> {
+    pub fee_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = fee_manager @ ErrorCode::InvalidFeeManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set fee tiers instruction by:
+// 1. Checking there are at most MAX_FEE_TIERS tiers, sorted by the volume needed to reach them, each with an allowed fee
+// 2. Replacing the config's fee tiers with them, measured in fee_tier_mint
+// Makers below the first tier, or every maker if there are no tiers, pay the config's fee_bps
+// Changing fee_tier_mint starts every maker's fee tier volume again from zero
+pub fn set_fee_tiers(
+    context: Context<SetFeeTiers>,
+    fee_tiers: Vec<FeeTier>,
+    fee_tier_mint: Pubkey,
+) -> Result<()> {
+    require!(fee_tiers.len() <= MAX_FEE_TIERS, ErrorCode::InvalidFeeTiers);
+    require!(
+        fee_tiers.iter().all(|fee_tier| fee_tier.fee_bps <= MAX_FEE_BPS),
+        ErrorCode::InvalidFee
+    );
+    require!(
+        fee_tiers
+            .windows(2)
+            .all(|pair| pair[0].min_volume < pair[1].min_volume),
+        ErrorCode::InvalidFeeTiers
+    );
+
+    context.accounts.config.fee_tiers = fee_tiers;
+    context.accounts.config.fee_tier_mint = fee_tier_mint;
+
+    Ok(())
+}
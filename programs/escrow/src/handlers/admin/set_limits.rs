@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetLimits<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set limits instruction by:
+// 1. Checking the minimum isn't more than the maximum
+// 2. Saving the smallest and largest amount of token a an offer can be made for to the config
+// Only offers made afterwards are checked, existing offers can still be taken or refunded
+pub fn set_limits(
+    context: Context<SetLimits>,
+    min_offer_amount: u64,
+    max_offer_amount: u64,
+) -> Result<()> {
+    require!(
+        min_offer_amount <= max_offer_amount,
+        ErrorCode::InvalidOfferLimits
+    );
+
+    let config = &mut context.accounts.config;
+    config.min_offer_amount = min_offer_amount;
+    config.max_offer_amount = max_offer_amount;
+
+    Ok(())
+}
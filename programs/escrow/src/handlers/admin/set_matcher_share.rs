@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::BASIS_POINTS_DENOMINATOR, error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetMatcherShare<'info> {
+    pub fee_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = fee_manager @ ErrorCode::InvalidFeeManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set matcher share instruction by:
+// 1. Checking the share is at most the whole price improvement
+// 2. Saving the share of each match's price improvement that goes to the matcher to the config
+// A share gives keepers a reason to find and match mirrored offers, the makers get the rest of the improvement
+pub fn set_matcher_share(context: Context<SetMatcherShare>, matcher_share_bps: u16) -> Result<()> {
+    require!(
+        matcher_share_bps as u64 <= BASIS_POINTS_DENOMINATOR,
+        ErrorCode::InvalidMatcherShare
+    );
+
+    context.accounts.config.matcher_share_bps = matcher_share_bps;
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::MAX_MAKER_OPEN_OFFERS, error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetMaxOpenOffers<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set max open offers instruction by:
+// 1. Checking the cap allows at least one offer, and no more than a maker's index can list
+// 2. Saving the most open offers a single maker can have at once to the config
+// Makers already over a lowered cap keep their offers, they just can't make more until enough are closed
+pub fn set_max_open_offers(
+    context: Context<SetMaxOpenOffers>,
+    max_open_offers_per_maker: u16,
+) -> Result<()> {
+    require!(
+        max_open_offers_per_maker > 0
+            && max_open_offers_per_maker as usize <= MAX_MAKER_OPEN_OFFERS,
+        ErrorCode::InvalidMaxOpenOffers
+    );
+
+    context.accounts.config.max_open_offers_per_maker = max_open_offers_per_maker;
+
+    Ok(())
+}
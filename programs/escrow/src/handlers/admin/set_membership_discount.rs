@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::BASIS_POINTS_DENOMINATOR,
+    error::ErrorCode,
+    state::{Config, MembershipDiscount},
+};
+
+#[derive(Accounts)]
+pub struct SetMembershipDiscount<'info> {
+    pub fee_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = fee_manager @ ErrorCode::InvalidFeeManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set membership discount instruction by:
+// 1. Checking the discount is at most the whole fee, and that holders need at least one token to qualify
+// 2. Saving the membership discount to the config, or removing it if there is none
+// To reward holders of an NFT collection, use a mint per NFT with a minimum balance of one
+pub fn set_membership_discount(
+    context: Context<SetMembershipDiscount>,
+    membership_discount: Option<MembershipDiscount>,
+) -> Result<()> {
+    if let Some(membership_discount) = &membership_discount {
+        require!(membership_discount.min_balance > 0, ErrorCode::InvalidMembershipDiscount);
+        require!(
+            membership_discount.discount_bps as u64 <= BASIS_POINTS_DENOMINATOR,
+            ErrorCode::InvalidMembershipDiscount
+        );
+    }
+
+    context.accounts.config.membership_discount = membership_discount;
+
+    Ok(())
+}
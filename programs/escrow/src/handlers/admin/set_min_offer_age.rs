@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetMinOfferAge<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set min offer age instruction by:
+// 1. Saving how many slots an offer must have existed for before it can be taken to the config
+// Applies to every open offer, counting from the slot each was made in, zero lets offers be taken straight away
+pub fn set_min_offer_age(context: Context<SetMinOfferAge>, min_offer_age_slots: u64) -> Result<()> {
+    context.accounts.config.min_offer_age_slots = min_offer_age_slots;
+
+    Ok(())
+}
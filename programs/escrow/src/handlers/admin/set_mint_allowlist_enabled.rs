@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetMintAllowlistEnabled<'info> {
+    pub allowlist_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = allowlist_manager @ ErrorCode::InvalidAllowlistManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set mint allowlist enabled instruction by:
+// 1. Saving whether offers can only be made between mints on the allowlist to the config
+// Add the vetted mints with allow_mint before enabling it, or no offers can be made
+pub fn set_mint_allowlist_enabled(
+    context: Context<SetMintAllowlistEnabled>,
+    mint_allowlist_enabled: bool,
+) -> Result<()> {
+    context.accounts.config.mint_allowlist_enabled = mint_allowlist_enabled;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintPolicy},
+};
+
+#[derive(Accounts)]
+pub struct SetMintPolicy<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set mint policy instruction by:
+// 1. Saving which kinds of mint offers may use to the config
+// Only offers made afterwards are checked, existing offers can still be taken or refunded
+pub fn set_mint_policy(context: Context<SetMintPolicy>, mint_policy: MintPolicy) -> Result<()> {
+    context.accounts.config.mint_policy = mint_policy;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, Role},
+};
+
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set role instruction by:
+// 1. Giving the role to the new holder, replacing whoever held it before
+// Each role can be given to a different key, such as a multisig for fees and a hot key for pausing
+pub fn set_role(context: Context<SetRole>, role: Role, holder: Pubkey) -> Result<()> {
+    let config = &mut context.accounts.config;
+    match role {
+        Role::Pauser => config.pauser = holder,
+        Role::FeeManager => config.fee_manager = holder,
+        Role::AllowlistManager => config.allowlist_manager = holder,
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct SetRoyaltiesRequired<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::InvalidAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the set royalties required instruction by:
+// 1. Saving whether NFT offers must pay their creators' royalties to the config
+// While required, take_offer won't settle an NFT offer without its metadata, and other ways of settling NFT offers are refused
+pub fn set_royalties_required(
+    context: Context<SetRoyaltiesRequired>,
+    royalties_required: bool,
+) -> Result<()> {
+    context.accounts.config.royalties_required = royalties_required;
+    Ok(())
+}
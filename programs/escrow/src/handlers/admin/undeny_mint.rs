@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintDenyEntry},
+};
+
+#[derive(Accounts)]
+pub struct UndenyMint<'info> {
+    #[account(mut)]
+    pub allowlist_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = allowlist_manager @ ErrorCode::InvalidAllowlistManager,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = allowlist_manager,
+        seeds = [b"mint_deny_entry", mint_deny_entry.mint.as_ref()],
+        bump = mint_deny_entry.bump
+    )]
+    pub mint_deny_entry: Account<'info, MintDenyEntry>,
+}
+
+// Handle the undeny mint instruction by:
+// 1. Closing the mint's deny entry, returning its rent to the allowlist manager
+// 2. Removing it from the config's count, once none are left offers skip the deny entry checks
+pub fn undeny_mint(context: Context<UndenyMint>) -> Result<()> {
+    let config = &mut context.accounts.config;
+    config.denied_mint_count = config
+        .denied_mint_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
+}
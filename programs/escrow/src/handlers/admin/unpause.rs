@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, state::Config};
+
+#[derive(Accounts)]
+pub struct Unpause<'info> {
+    pub pauser: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = pauser @ ErrorCode::InvalidPauser,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the unpause instruction by:
+// 1. Setting the paused flag on the config to false
+// Unpausing lets offers be made and taken again
+pub fn unpause(context: Context<Unpause>) -> Result<()> {
+    context.accounts.config.paused = false;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{error::ErrorCode, handlers::shared::transfer_tokens, state::Config};
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    pub fee_authority: Signer<'info>,
+
+    #[account(
+        has_one = fee_authority @ ErrorCode::InvalidFeeAuthority,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", token_mint.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Any token account for the mint, so the fee authority can send fees wherever they like
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::token_program = token_program
+    )]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Handle the withdraw fees instruction by:
+// 1. Moving every token in the mint's fee vault to the destination token account
+// The fee vault stays open, ready to collect more fees
+pub fn withdraw_fees(context: Context<WithdrawFees>) -> Result<()> {
+    let fee_amount = context.accounts.fee_vault.amount;
+    require!(fee_amount > 0, ErrorCode::NoFeesToWithdraw);
+
+    // The config owns every fee vault, so it signs for the withdrawal
+    let config_seeds = &[b"config".as_ref(), &[context.accounts.config.bump]];
+
+    transfer_tokens(
+        &context.accounts.fee_vault,
+        &context.accounts.destination_token_account,
+        &fee_amount,
+        &context.accounts.token_mint,
+        &context.accounts.config.to_account_info(),
+        &context.accounts.token_program,
+        Some(&config_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    Ok(())
+}
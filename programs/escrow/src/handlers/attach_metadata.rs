@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use super::shared::require_offer_open;
+use crate::{constants::MAX_METADATA_URI_LENGTH, error::ErrorCode, state::Offer};
+
+#[derive(Accounts)]
+#[instruction(metadata_uri: String)]
+pub struct AttachMetadata<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // The URI's bytes are stored straight after the offer, so we only need room for them
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump,
+        realloc = Offer::SPACE + metadata_uri.len(),
+        realloc::payer = maker,
+        realloc::zero = false
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    // Used to pay for, or refund, the rent of the extra space
+    pub system_program: Program<'info, System>,
+}
+
+// Handle the attach metadata instruction by:
+// 1. Resizing the offer account to fit the metadata URI, with the maker paying the extra rent
+// 2. Saving the metadata URI to the offer account, after the offer itself
+// Attaching a shorter URI, or an empty one, shrinks the account and refunds the rent to the maker
+pub fn attach_metadata(context: Context<AttachMetadata>, metadata_uri: String) -> Result<()> {
+    require!(
+        metadata_uri.len() <= MAX_METADATA_URI_LENGTH,
+        ErrorCode::MetadataUriTooLong
+    );
+    require_offer_open(&*context.accounts.offer.load()?)?;
+
+    context
+        .accounts
+        .offer
+        .load_mut()?
+        .set_metadata_uri_length(metadata_uri.len());
+
+    let offer_info = context.accounts.offer.to_account_info();
+    let mut data = offer_info.try_borrow_mut_data()?;
+    data[Offer::METADATA_URI_OFFSET..].copy_from_slice(metadata_uri.as_bytes());
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{
+        create, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    close_token_account, require_can_send, require_mint_allowed, require_no_transfer_hook,
+    transfer_tokens,
+};
+use crate::{constants::MAX_ADDITIONAL_OFFERED_MINTS, error::ErrorCode, state::MintPolicy};
+
+// Each additional offered mint in a bundle is passed as three remaining accounts:
+// make_offer: [mint, maker token account, vault]
+// take_offer and refund_offer: [mint, vault, recipient token account]
+pub const ACCOUNTS_PER_ADDITIONAL_MINT: usize = 3;
+
+// Create a vault for each additional offered mint and move the maker's tokens into it
+// The payer pays for the vaults and the maker authority signs the transfers, as in make_offer
+// Returns the additional mints, in the order they were escrowed
+// Takes the accounts every vault's transfers need individually, like transfer_tokens
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_additional_tokens<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    amounts: &[u64],
+    excluded_mints: &[Pubkey],
+    mint_policy: &MintPolicy,
+    maker: &Pubkey,
+    maker_authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    vault_authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+) -> Result<Vec<Pubkey>> {
+    require!(
+        amounts.len() <= MAX_ADDITIONAL_OFFERED_MINTS,
+        ErrorCode::TooManyOfferedMints
+    );
+    require!(
+        remaining_accounts.len() == amounts.len() * ACCOUNTS_PER_ADDITIONAL_MINT,
+        ErrorCode::InvalidRemainingAccounts
+    );
+
+    let mut additional_mints: Vec<Pubkey> = Vec::with_capacity(amounts.len());
+
+    for (amount, accounts) in amounts
+        .iter()
+        .zip(remaining_accounts.chunks(ACCOUNTS_PER_ADDITIONAL_MINT))
+    {
+        require!(*amount > 0, ErrorCode::InvalidAmount);
+
+        let mint_info = &accounts[0];
+        let maker_token_account_info = &accounts[1];
+        let vault_info = &accounts[2];
+
+        // Every mint in the bundle must be different from the others and from the wanted mint
+        require!(
+            *mint_info.owner == token_program.key(),
+            ErrorCode::InvalidTokenProgram
+        );
+        require!(
+            !excluded_mints.contains(mint_info.key) && !additional_mints.contains(mint_info.key),
+            ErrorCode::InvalidTokenMint
+        );
+        require_no_transfer_hook(mint_info)?;
+        let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+        require_mint_allowed(&mint, mint_policy)?;
+
+        let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(maker_token_account_info)?;
+        require_keys_eq!(maker_token_account.mint, mint.key(), ErrorCode::WrongTokenMint);
+        require_keys_eq!(
+            maker_token_account.owner,
+            *maker,
+            ErrorCode::InvalidTokenAccountOwner
+        );
+
+        // The vault is always the vault authority's associated token account for the mint
+        require!(
+            vault_info.key()
+                == get_associated_token_address_with_program_id(
+                    vault_authority.key,
+                    mint_info.key,
+                    &token_program.key()
+                ),
+            ErrorCode::InvalidVault
+        );
+        create(CpiContext::new(
+            associated_token_program.to_account_info(),
+            Create {
+                payer: payer.clone(),
+                associated_token: vault_info.clone(),
+                authority: vault_authority.clone(),
+                mint: mint_info.clone(),
+                system_program: system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))?;
+        let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+
+        require_can_send(&maker_token_account, *amount, ErrorCode::InsufficientMakerBalance)?;
+        transfer_tokens(
+            &maker_token_account,
+            &vault,
+            amount,
+            &mint,
+            maker_authority,
+            token_program,
+            None,
+        )?;
+
+        additional_mints.push(mint.key());
+    }
+
+    Ok(additional_mints)
+}
+
+// Empty and close the vault for each additional offered mint
+// Tokens go to recipient's token accounts, rent goes to rent_destination
+// Takes the accounts every vault's transfers need individually, like transfer_tokens
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_additional_tokens<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    additional_mints: &[Pubkey],
+    recipient: &Pubkey,
+    rent_destination: &AccountInfo<'info>,
+    vault_authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    vault_authority_seeds: &[&[u8]],
+    transfer_error: ErrorCode,
+    close_error: ErrorCode,
+) -> Result<()> {
+    require!(
+        remaining_accounts.len() >= additional_mints.len() * ACCOUNTS_PER_ADDITIONAL_MINT,
+        ErrorCode::InvalidRemainingAccounts
+    );
+
+    for (expected_mint, accounts) in additional_mints
+        .iter()
+        .zip(remaining_accounts.chunks(ACCOUNTS_PER_ADDITIONAL_MINT))
+    {
+        let mint_info = &accounts[0];
+        let vault_info = &accounts[1];
+        let recipient_token_account_info = &accounts[2];
+
+        require_keys_eq!(*mint_info.key, *expected_mint, ErrorCode::WrongTokenMint);
+        let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+
+        require!(
+            vault_info.key()
+                == get_associated_token_address_with_program_id(
+                    vault_authority.key,
+                    mint_info.key,
+                    &token_program.key()
+                ),
+            ErrorCode::InvalidVault
+        );
+        let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        require!(
+            vault.mint == mint.key() && vault.owner == vault_authority.key(),
+            ErrorCode::InvalidVault
+        );
+
+        let recipient_token_account =
+            InterfaceAccount::<TokenAccount>::try_from(recipient_token_account_info)?;
+        require_keys_eq!(
+            recipient_token_account.mint,
+            mint.key(),
+            ErrorCode::WrongTokenMint
+        );
+        require_keys_eq!(
+            recipient_token_account.owner,
+            *recipient,
+            ErrorCode::InvalidTokenAccountOwner
+        );
+
+        transfer_tokens(
+            &vault,
+            &recipient_token_account,
+            &vault.amount,
+            &mint,
+            vault_authority,
+            token_program,
+            Some(vault_authority_seeds),
+        )
+        .map_err(|_| transfer_error)?;
+
+        close_token_account(
+            &vault,
+            rent_destination,
+            vault_authority,
+            token_program,
+            Some(vault_authority_seeds),
+        )
+        .map_err(|_| close_error)?;
+    }
+
+    Ok(())
+}
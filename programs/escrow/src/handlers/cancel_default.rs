@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    close_token_account, get_next_installment_due, record_maker_offer_refunded, transfer_tokens,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferRefunded},
+    state::{InstallmentPlan, MakerStats},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelDefault<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The maker, or the authority that signed for them when the offer was made
+    // Makers that can't sign transactions, like program owned multisig vaults, cancel through that authority
+    #[account(
+        mut,
+        constraint = maker_authority.key() == installment_plan.maker
+            || maker_authority.key() == installment_plan.maker_authority
+            @ ErrorCode::InvalidMakerAuthority
+    )]
+    pub maker_authority: Signer<'info>,
+
+    /// CHECK: Checked against the plan's maker with has_one, the unreleased tokens go back to them
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the plan's taker with has_one, who paid the rent for the plan and its vault and gets it back
+    #[account(mut)]
+    pub taker: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = maker_authority,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = taker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = taker @ ErrorCode::InvalidTaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [b"installment_plan", installment_plan.offer.as_ref()],
+        bump = installment_plan.bump
+    )]
+    pub installment_plan: Account<'info, InstallmentPlan>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = installment_plan,
+        associated_token::token_program = token_program,
+    )]
+    pub installment_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+}
+
+// Handle the cancel default instruction by:
+// 1. Checking the taker's next installment is overdue
+// 2. Returning the token a not yet released from the installment vault to the maker
+//    The taker keeps what they've already paid for, and the maker keeps what they've been paid
+// 3. Closing the installment vault and plan, returning the rent to the taker who paid it
+// 4. Recording the refund in the maker's statistics, the offer was already recorded as closed when it was taken
+// 5. Emitting an OfferRefunded event for the token a returned
+pub fn cancel_default(context: Context<CancelDefault>) -> Result<()> {
+    let clock = Clock::get()?;
+    let installment_due = get_next_installment_due(&context.accounts.installment_plan)?;
+    require!(
+        clock.unix_timestamp > installment_due,
+        ErrorCode::InstallmentNotOverdue
+    );
+
+    // The installment plan owns the installment vault, so it signs for the withdrawal
+    let offer_key = context.accounts.installment_plan.offer;
+    let installment_plan_seeds = &[
+        b"installment_plan",
+        offer_key.as_ref(),
+        &[context.accounts.installment_plan.bump],
+    ];
+    let signers_seeds = Some(&installment_plan_seeds[..]);
+
+    let token_a_refunded_amount = context.accounts.installment_vault.amount;
+    transfer_tokens(
+        &context.accounts.installment_vault,
+        &context.accounts.maker_token_account_a,
+        &token_a_refunded_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.installment_plan.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+    close_token_account(
+        &context.accounts.installment_vault,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.installment_plan.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundClosure)?;
+
+    record_maker_offer_refunded(&mut context.accounts.maker_stats)?;
+
+    let installment_plan = &context.accounts.installment_plan;
+    emit_event(
+        OfferRefunded {
+            offer_id: installment_plan.offer_id,
+            maker: installment_plan.maker,
+            token_mint_a: installment_plan.token_mint_a,
+            token_mint_b: installment_plan.token_mint_b,
+            token_a_amount: token_a_refunded_amount,
+            created_at: installment_plan.created_at,
+            refunded_at: clock.unix_timestamp,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use super::shared::set_stake_authorities;
+use crate::{error::ErrorCode, state::StakeOffer};
+use anchor_lang::prelude::*;
+use anchor_spl::stake::{Stake, StakeAccount};
+
+#[derive(Accounts)]
+pub struct CancelStakeOffer<'info> {
+    // Hands the stake account's authorities back to the maker
+    pub stake_program: Program<'info, Stake>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub stake_account: Box<Account<'info, StakeAccount>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = stake_account,
+        seeds = [b"stake_offer", stake_account.key().as_ref()],
+        bump = stake_offer.bump
+    )]
+    pub stake_offer: Account<'info, StakeOffer>,
+
+    // The stake program reads the clock when changing authorities
+    pub clock: Sysvar<'info, Clock>,
+}
+
+// Handle the cancel stake offer instruction by:
+// 1. Handing the stake account's staker and withdrawer authorities from the stake offer back to the maker
+// 2. Closing the stake offer and returning its rent to the maker
+// Makers can cancel at any time, even while the program is paused, like refunding an offer
+pub fn cancel_stake_offer(context: Context<CancelStakeOffer>) -> Result<()> {
+    let stake_account_key = context.accounts.stake_account.key();
+    let stake_offer_seeds = &[
+        b"stake_offer",
+        stake_account_key.as_ref(),
+        &[context.accounts.stake_offer.bump],
+    ];
+    set_stake_authorities(
+        &context.accounts.stake_account.to_account_info(),
+        &context.accounts.stake_offer.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.clock.to_account_info(),
+        &context.accounts.stake_program,
+        Some(&stake_offer_seeds[..]),
+    )?;
+
+    Ok(())
+}
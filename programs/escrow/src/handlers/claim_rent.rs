@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    state::{Offer, OfferStatus},
+};
+
+#[derive(Accounts)]
+pub struct ClaimRent<'info> {
+    // The maker, or the authority that signed for them when the offer was made
+    #[account(
+        constraint = maker_authority.key() == offer.load()?.maker
+            || maker_authority.key() == offer.load()?.maker_authority
+            @ ErrorCode::InvalidMakerAuthority
+    )]
+    pub maker_authority: Signer<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+}
+
+// Handle the claim rent instruction by:
+// 1. Checking the offer has been settled or refunded, its tokens and vaults are already gone
+// 2. Closing the offer, returning the rent to whoever paid for it
+// Only offers that keep their history are left open once settled or refunded, so the maker decides when their record goes
+pub fn claim_rent(context: Context<ClaimRent>) -> Result<()> {
+    require!(
+        context.accounts.offer.load()?.status() != OfferStatus::Open,
+        ErrorCode::OfferStillOpen
+    );
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{close_token_account, get_vested_amount, transfer_tokens};
+use crate::{error::ErrorCode, state::Vesting};
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = beneficiary @ ErrorCode::InvalidBeneficiary,
+        has_one = token_mint @ ErrorCode::WrongTokenMint,
+        seeds = [b"vesting", vesting.offer.as_ref()],
+        bump = vesting.bump
+    )]
+    pub vesting: Account<'info, Vesting>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = vesting,
+        associated_token::token_program = token_program,
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        associated_token::mint = token_mint,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program,
+    )]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the claim vested instruction by:
+// 1. Working out how much has vested and not been claimed yet
+// 2. Sending that much from the vesting vault to the beneficiary
+// 3. Once everything has been claimed, closing the vesting vault and vesting account, returning the rent to the beneficiary
+pub fn claim_vested(context: Context<ClaimVested>) -> Result<()> {
+    let clock = Clock::get()?;
+    let vested_amount = get_vested_amount(&context.accounts.vesting, clock.unix_timestamp)?;
+    let claimable_amount = vested_amount - context.accounts.vesting.claimed_amount;
+    require!(claimable_amount > 0, ErrorCode::NothingToClaim);
+
+    // The vesting account owns the vesting vault, so it signs for the withdrawal
+    let offer_key = context.accounts.vesting.offer;
+    let vesting_account_seeds = &[
+        b"vesting",
+        offer_key.as_ref(),
+        &[context.accounts.vesting.bump],
+    ];
+    let signers_seeds = Some(&vesting_account_seeds[..]);
+
+    transfer_tokens(
+        &context.accounts.vesting_vault,
+        &context.accounts.beneficiary_token_account,
+        &claimable_amount,
+        &context.accounts.token_mint,
+        &context.accounts.vesting.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    context.accounts.vesting.claimed_amount = vested_amount;
+
+    if vested_amount == context.accounts.vesting.total_amount {
+        close_token_account(
+            &context.accounts.vesting_vault,
+            &context.accounts.beneficiary.to_account_info(),
+            &context.accounts.vesting.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+        context
+            .accounts
+            .vesting
+            .close(context.accounts.beneficiary.to_account_info())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use super::shared::revoke_delegate;
+use crate::{error::ErrorCode, state::OfferTemplate};
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct CloseOfferTemplate<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [
+            b"offer_template",
+            maker.key().as_ref(),
+            token_mint_a.key().as_ref(),
+            offer_template.token_mint_b.as_ref()
+        ],
+        bump = offer_template.bump
+    )]
+    pub offer_template: Account<'info, OfferTemplate>,
+
+    // The template's approval on this account is revoked
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the close offer template instruction by:
+// 1. Revoking the template's approval on the maker's ATA, unless the maker has already approved someone else
+// 2. Closing the template and returning its rent to the maker
+// The template's current offer, if it has one open, stays open until it is taken or the maker refunds it
+pub fn close_offer_template(context: Context<CloseOfferTemplate>) -> Result<()> {
+    if context.accounts.maker_token_account_a.delegate
+        == COption::Some(context.accounts.offer_template.key())
+    {
+        revoke_delegate(
+            &context.accounts.maker_token_account_a,
+            &context.accounts.maker.to_account_info(),
+            &context.accounts.token_program,
+        )?;
+    }
+
+    Ok(())
+}
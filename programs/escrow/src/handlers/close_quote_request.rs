@@ -0,0 +1,24 @@
+use crate::{error::ErrorCode, state::QuoteRequest};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CloseQuoteRequest<'info> {
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    #[account(
+        mut,
+        close = requester,
+        has_one = requester @ ErrorCode::InvalidRequester,
+        seeds = [b"quote_request", requester.key().as_ref(), quote_request.id.to_le_bytes().as_ref()],
+        bump = quote_request.bump
+    )]
+    pub quote_request: Account<'info, QuoteRequest>,
+}
+
+// Handle the close quote request instruction by closing the quote request and returning its rent to the requester
+// Requesters who didn't like any quote close the request rather than wait for it to expire,
+// so the quotes' makers can withdraw them straight away
+pub fn close_quote_request(_context: Context<CloseQuoteRequest>) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use super::shared::{
+    approve_delegate, get_token_program_b, require_mint_allowed, require_mint_allowlisted,
+    require_mint_not_denied, require_no_transfer_hook, require_offer_amount_within_limits,
+};
+use crate::{
+    constants::OFFER_TAGS_LENGTH,
+    error::ErrorCode,
+    state::{Config, MintAllowlistEntry, OfferTemplate},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct CreateOfferTemplate<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The maker signs the approval on their own token account, and pays for the template
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // Funds every offer made from the template, with the template approved as its delegate
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = OfferTemplate::DISCRIMINATOR.len() + OfferTemplate::INIT_SPACE,
+        seeds = [
+            b"offer_template",
+            maker.key().as_ref(),
+            token_mint_a.key().as_ref(),
+            token_mint_b.key().as_ref()
+        ],
+        bump
+    )]
+    pub offer_template: Account<'info, OfferTemplate>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+}
+
+// Handle the create offer template instruction by:
+// 1. Checking the terms and both mints the same way make_offer does
+// 2. Approving the template to move up to total_token_a_amount out of the maker's ATA, the most its offers can sell
+// 3. Saving the terms to the template, which has no offer yet, spawn_offer_from_template makes the first one
+// A token account has a single delegate, so a later approval on the same account, including a delegated offer,
+// replaces this one and no more offers can be made from the template
+pub fn create_offer_template(
+    context: Context<CreateOfferTemplate>,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    total_token_a_amount: u64,
+    tags: [u8; OFFER_TAGS_LENGTH],
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidOfferedAmount);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    require!(
+        total_token_a_amount >= token_a_offered_amount,
+        ErrorCode::InvalidAmount
+    );
+    require_offer_amount_within_limits(token_a_offered_amount, &context.accounts.config)?;
+    require_keys_neq!(
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
+        ErrorCode::InvalidTokenMint
+    );
+
+    // Token b can belong to the other token program, which the taker then passes as token_program_b
+    get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Both sides of the trade must be transferable without extra hook accounts
+    require_no_transfer_hook(&context.accounts.token_mint_a.to_account_info())?;
+    require_no_transfer_hook(&context.accounts.token_mint_b.to_account_info())?;
+    require_mint_allowed(&context.accounts.token_mint_a, &context.accounts.config.mint_policy)?;
+    require_mint_allowed(&context.accounts.token_mint_b, &context.accounts.config.mint_policy)?;
+
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    // Let the template move the tokens for each offer it makes
+    approve_delegate(
+        &context.accounts.maker_token_account_a,
+        &context.accounts.offer_template.to_account_info(),
+        &total_token_a_amount,
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+    )?;
+
+    context.accounts.offer_template.set_inner(OfferTemplate {
+        maker: context.accounts.maker.key(),
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        tags,
+        current_offer: None,
+        offers_spawned: 0,
+        bump: context.bumps.offer_template,
+    });
+
+    Ok(())
+}
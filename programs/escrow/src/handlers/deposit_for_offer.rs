@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{require_offer_not_expired, require_offer_open, transfer_tokens_including_fee};
+use crate::{
+    error::ErrorCode,
+    state::{Config, Offer, OfferAllowlist, TakerDeposit},
+};
+
+#[derive(Accounts)]
+pub struct DepositForOffer<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        init,
+        payer = taker,
+        space = TakerDeposit::DISCRIMINATOR.len() + TakerDeposit::INIT_SPACE,
+        seeds = [b"taker_deposit", offer.key().as_ref(), taker.key().as_ref()],
+        bump
+    )]
+    pub taker_deposit: Account<'info, TakerDeposit>,
+
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker_deposit,
+        associated_token::token_program = token_program,
+    )]
+    pub deposit_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(has_one = offer @ ErrorCode::WrongOffer)]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the deposit for offer instruction by:
+// 1. Checking the offer is a plain fixed price offer the taker could take
+// 2. Moving the token b the offer wants from the taker to the deposit vault
+// 3. Recording the offer's current terms, so confirm_swap fails if the maker changes them
+// Either party can then settle the swap with confirm_swap, and the taker can withdraw_deposit at any time before that
+pub fn deposit_for_offer(context: Context<DepositForOffer>) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+
+    // Only offers that take_offer settles in one go at a fixed price can be deposited for
+    // confirm_swap pays the taker from the vault, which delegated offers don't have
+    require!(
+        offer.dutch_auction().is_none()
+            && offer.english_auction().is_none()
+            && offer.hashlock().is_none()
+            && offer.attestor().is_none()
+            && offer.eth_taker().is_none()
+            && offer.vesting_duration().is_none()
+            && offer.arbiter().is_none()
+            && offer.additional_token_mints_a().is_empty()
+            && !offer.is_delegated(),
+        ErrorCode::DepositNotSupported
+    );
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    // If token b charges a transfer fee, the taker pays it on top, so the deposit holds the full amount
+    let token_b_wanted_amount = offer.token_b_wanted_amount;
+    transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.deposit_vault,
+        &token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    context.accounts.taker_deposit.set_inner(TakerDeposit {
+        offer: context.accounts.offer.key(),
+        maker: offer.maker,
+        taker: context.accounts.taker.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_mint_a: offer.token_mint_a,
+        offer_created_slot: offer.created_slot,
+        token_a_amount: offer.token_a_offered_amount,
+        token_b_amount: token_b_wanted_amount,
+        bump: context.bumps.taker_deposit,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use super::{shared::require_offer_open, update_offer::UpdateOffer};
+use crate::error::ErrorCode;
+
+// Handle the extend offer instruction by:
+// 1. Checking the new expiry is later than both now and the current expiry
+// 2. Saving the new expiry
+// Nothing else about the offer changes, so makers keep their offer, and its vault, instead of refunding and recreating it
+pub fn extend_offer(context: Context<UpdateOffer>, expires_at: i64) -> Result<()> {
+    let mut offer = context.accounts.offer.load_mut()?;
+    require_offer_open(&offer)?;
+
+    // Offers without an expiry never expire, so there's nothing to extend
+    let current_expires_at = offer.expires_at().ok_or(ErrorCode::InvalidExpiry)?;
+    require!(expires_at > current_expires_at, ErrorCode::InvalidExpiry);
+
+    let clock = Clock::get()?;
+    require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpiry);
+
+    offer.set_expires_at(Some(expires_at));
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::shared::{approve_delegate, require_offer_not_expired, require_offer_open};
+use crate::{
+    error::ErrorCode,
+    state::{CounterOffer, Offer},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct MakeCounterOffer<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = proposer,
+        associated_token::token_program = token_program,
+    )]
+    pub proposer_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = CounterOffer::DISCRIMINATOR.len() + CounterOffer::INIT_SPACE,
+        seeds = [b"counter_offer", offer.key().as_ref(), proposer.key().as_ref()],
+        bump
+    )]
+    pub counter_offer: Account<'info, CounterOffer>,
+}
+
+// Handle the make counter offer instruction by:
+// 1. Approving the counter offer PDA to move the proposed token b from the proposer's account
+// 2. Saving the proposed terms to the counter offer account
+pub fn make_counter_offer(
+    context: Context<MakeCounterOffer>,
+    token_b_offered_amount: u64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(token_b_offered_amount > 0, ErrorCode::InvalidAmount);
+    require!(
+        context.accounts.proposer.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    require_offer_open(&offer)?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+    // accept_counter_offer only settles the main vault
+    require!(
+        offer.additional_token_mints_a().is_empty(),
+        ErrorCode::BundleNotSupported
+    );
+
+    // Check up front, rather than letting the maker find out when they accept
+    require!(
+        context.accounts.proposer_token_account_b.amount >= token_b_offered_amount,
+        ErrorCode::InsufficientTakerBalance
+    );
+
+    // Let the counter offer PDA move the proposed token b when the maker accepts
+    approve_delegate(
+        &context.accounts.proposer_token_account_b,
+        &context.accounts.counter_offer.to_account_info(),
+        &token_b_offered_amount,
+        &context.accounts.proposer.to_account_info(),
+        &context.accounts.token_program,
+    )?;
+
+    // Save the proposed terms to the counter offer account
+    context.accounts.counter_offer.set_inner(CounterOffer {
+        offer: context.accounts.offer.key(),
+        proposer: context.accounts.proposer.key(),
+        token_b_offered_amount,
+        bump: context.bumps.counter_offer,
+    });
+    Ok(())
+}
@@ -1,12 +1,33 @@
-use super::shared::transfer_tokens;
-use crate::{error::ErrorCode, state::Offer};
-use anchor_lang::prelude::*;
+use super::{
+    bundle::deposit_additional_tokens,
+    nft::validate_nft_metadata,
+    shared::{
+        add_to_maker_index, get_token_program_b, record_maker_offer_made, record_offer_made,
+        require_can_send, require_mint_allowed, require_mint_allowlisted, require_mint_not_denied,
+        require_no_transfer_hook, require_offer_amount_within_limits, transfer_tokens,
+    },
+};
+use crate::{
+    constants::{AUTO_ASSIGN_OFFER_ID, OFFER_TAGS_LENGTH, OFFER_VERSION},
+    error::ErrorCode,
+    events::{emit_event, OfferMade},
+    state::{
+        Config, DutchAuction, EnglishAuction, MakerCounter, MakerIndex, MakerStats,
+        MintAllowlistEntry, Offer, Registry,
+    },
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash::HASH_BYTES, program::set_return_data, program_option::COption},
+};
 use anchor_spl::{
     associated_token::AssociatedToken,
+    metadata::MetadataAccount,
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 // See https://www.anchor-lang.com/docs/account-constraints#instruction-attribute
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(id: u64)]
 pub struct MakeOffer<'info> {
@@ -21,13 +42,27 @@ pub struct MakeOffer<'info> {
     // Used to create accounts
     pub system_program: Program<'info, System>,
 
+    // Signs for the maker: either the maker's own wallet, or a delegate the maker approved on maker_token_account_a
+    // Makers that can't sign transactions or pay rent, like program owned multisig vaults, make offers through a delegate
+    #[account(
+        constraint = maker_authority.key() == maker.key()
+            || maker_token_account_a.delegate == COption::Some(maker_authority.key())
+            @ ErrorCode::InvalidMakerAuthority
+    )]
+    pub maker_authority: Signer<'info>,
+
+    /// CHECK: Any account that owns maker_token_account_a, which the associated token constraint checks
+    pub maker: UncheckedAccount<'info>,
+
+    // Pays the rent for the offer, its vaults and the maker's index and statistics
+    // Usually the maker authority, but a sponsor like a frontend can pay instead, and gets the offer's rent back when it closes
     #[account(mut)]
-    pub maker: Signer<'info>,
+    pub payer: Signer<'info>,
 
     #[account(mint::token_program = token_program)]
     pub token_mint_a: InterfaceAccount<'info, Mint>,
 
-    #[account(mint::token_program = token_program)]
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
     pub token_mint_b: InterfaceAccount<'info, Mint>,
 
     #[account(
@@ -38,64 +73,386 @@ pub struct MakeOffer<'info> {
     )]
     pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
 
+    // Hands out the maker's offer ids, created with their first offer
+    // Comes before the offer, since the offer's address uses the id it assigns
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerCounter::DISCRIMINATOR.len() + MakerCounter::INIT_SPACE,
+        seeds = [b"maker_counter", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_counter: Account<'info, MakerCounter>,
+
     #[account(
         init,
-        payer = maker,
-        space = Offer::DISCRIMINATOR.len() + Offer::INIT_SPACE,
-        seeds = [b"offer", id.to_le_bytes().as_ref()],
+        payer = payer,
+        space = Offer::SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_counter.resolve_offer_id(id).to_le_bytes().as_ref()
+        ],
         bump
     )]
-    pub offer: Account<'info, Offer>,
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
 
     #[account(
         init,
-        payer = maker,
+        payer = payer,
         associated_token::mint = token_mint_a,
-        associated_token::authority = offer,
+        associated_token::authority = vault_authority,
         associated_token::token_program = token_program
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, created with their first offer, so clients can list them without scanning every offer
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerIndex::DISCRIMINATOR.len() + MakerIndex::INIT_SPACE,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics, also created with their first offer
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerStats::DISCRIMINATOR.len() + MakerStats::INIT_SPACE,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Only needed when offering an NFT: the Metaplex metadata of token a
+    // The address is checked in the handler, so clients offering fungible tokens can leave it out
+    pub token_mint_a_metadata: Option<Account<'info, MetadataAccount>>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
 }
 
 // Handle the make offer instruction by:
-// 1. Moving the tokens from the maker's ATA to the vault
-// 2. Saving the details of the offer to the offer account
-pub fn make_offer(
-    context: Context<MakeOffer>,
+// 1. Moving the tokens from the maker's ATA to the vault, and recording how many arrived
+//    If token a's metadata was provided, it must be an NFT, and we record its collection
+// 2. For bundle offers, creating a vault for each additional mint and moving those tokens too
+// 3. Saving the details of the offer to the offer account, with the maker's next id if passed AUTO_ASSIGN_OFFER_ID
+//    Makers who need an on-chain audit trail can keep the offer's history, so it isn't closed when it's taken or refunded
+//    Makers can tag the offer with categories frontends filter listings on
+// 4. Counting the new offer in the registry and the maker's statistics, and listing it in the maker's index
+// 5. Emitting an OfferMade event, and returning the offer's id
+// Each argument is one of the instruction's, which Anchor passes to the handler separately
+#[allow(clippy::too_many_arguments)]
+pub fn make_offer<'info>(
+    context: Context<'_, '_, 'info, 'info, MakeOffer<'info>>,
     id: u64,
     token_a_offered_amount: u64,
     token_b_wanted_amount: u64,
+    dutch_auction: Option<DutchAuction>,
+    english_auction: Option<EnglishAuction>,
+    additional_token_a_offered_amounts: Vec<u64>,
+    memo: Option<String>,
+    min_fill_amount: u64,
+    hashlock: Option<[u8; HASH_BYTES]>,
+    vesting_duration: Option<i64>,
+    arbiter: Option<Pubkey>,
+    keep_history: bool,
+    tags: [u8; OFFER_TAGS_LENGTH],
 ) -> Result<()> {
-    // Validate amounts
-    require!(token_a_offered_amount > 0, ErrorCode::InvalidAmount);
-    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
 
-    // Validate token mints are different
-    require!(
-        context.accounts.token_mint_a.key() != context.accounts.token_mint_b.key(),
+    // Validate amounts and mints before any tokens move, so bad offers fail with a clear error
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidOfferedAmount);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    require_offer_amount_within_limits(token_a_offered_amount, &context.accounts.config)?;
+    require_keys_neq!(
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
         ErrorCode::InvalidTokenMint
     );
 
+    // Validate the Dutch auction schedule, if there is one
+    if let Some(dutch_auction) = dutch_auction {
+        require!(dutch_auction.end_price > 0, ErrorCode::InvalidAmount);
+        require!(
+            dutch_auction.start_price >= dutch_auction.end_price,
+            ErrorCode::InvalidAuctionSchedule
+        );
+        require!(
+            dutch_auction.start_timestamp < dutch_auction.end_timestamp,
+            ErrorCode::InvalidAuctionSchedule
+        );
+    }
+
+    // Validate the English auction, if there is one
+    if let Some(english_auction) = english_auction {
+        require!(dutch_auction.is_none(), ErrorCode::ConflictingAuctionModes);
+        let clock = Clock::get()?;
+        require!(
+            english_auction.end_timestamp > clock.unix_timestamp,
+            ErrorCode::InvalidExpiry
+        );
+    }
+
+    // Bundles are settled at a fixed price or refunded, auctions would strand the extra vaults
+    require!(
+        english_auction.is_none() || additional_token_a_offered_amounts.is_empty(),
+        ErrorCode::BundleNotSupported
+    );
+
+    // Bids and settlement never reveal a preimage, so auctions can't be hashlocked
+    require!(
+        english_auction.is_none() || hashlock.is_none(),
+        ErrorCode::OfferIsHashlocked
+    );
+
+    // Vested offers are only taken with take_offer_vested, which settles a single mint at a fixed or Dutch auction price
+    if let Some(vesting_duration) = vesting_duration {
+        require!(vesting_duration > 0, ErrorCode::InvalidVestingSchedule);
+        require!(english_auction.is_none(), ErrorCode::OfferIsVested);
+        require!(hashlock.is_none(), ErrorCode::OfferIsHashlocked);
+        require!(
+            additional_token_a_offered_amounts.is_empty(),
+            ErrorCode::BundleNotSupported
+        );
+    }
+
+    // Arbitrated offers are only taken with take_offer_arbitrated, which holds a single mint at a fixed price until
+    // the arbiter releases it, so the arbiter must be someone other than the maker
+    if let Some(arbiter) = arbiter {
+        require!(
+            arbiter != context.accounts.maker.key(),
+            ErrorCode::InvalidArbiter
+        );
+        require!(
+            dutch_auction.is_none()
+                && english_auction.is_none()
+                && hashlock.is_none()
+                && vesting_duration.is_none()
+                && additional_token_a_offered_amounts.is_empty(),
+            ErrorCode::ArbiterNotSupported
+        );
+    }
+
+    // Token b can belong to the other token program, which the taker then passes as token_program_b
+    get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Both sides of the trade must be transferable without extra hook accounts
+    require_no_transfer_hook(&context.accounts.token_mint_a.to_account_info())?;
+    require_no_transfer_hook(&context.accounts.token_mint_b.to_account_info())?;
+    require_mint_allowed(&context.accounts.token_mint_a, &context.accounts.config.mint_policy)?;
+    require_mint_allowed(&context.accounts.token_mint_b, &context.accounts.config.mint_policy)?;
+
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    // Offering an NFT records its verified collection, so takers can check it
+    let nft_collection = match &context.accounts.token_mint_a_metadata {
+        Some(metadata) => validate_nft_metadata(
+            &context.accounts.token_mint_a,
+            metadata,
+            token_a_offered_amount,
+        )?,
+        None => None,
+    };
+
+    // Auctions are settled by settle_auction, which doesn't pay royalties
+    require!(
+        context.accounts.token_mint_a_metadata.is_none()
+            || english_auction.is_none()
+            || !context.accounts.config.royalties_required,
+        ErrorCode::RoyaltiesRequired
+    );
+
     // Move the tokens from the maker's ATA to the vault
+    require_can_send(
+        &context.accounts.maker_token_account_a,
+        token_a_offered_amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
     transfer_tokens(
         &context.accounts.maker_token_account_a,
         &context.accounts.vault,
         &token_a_offered_amount,
         &context.accounts.token_mint_a,
-        &context.accounts.maker.to_account_info(),
+        &context.accounts.maker_authority.to_account_info(),
         &context.accounts.token_program,
         None,
-    )
-    .map_err(|_| ErrorCode::InsufficientMakerBalance)?;
+    )?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so record what the vault actually holds
+    context.accounts.vault.reload()?;
+    let token_a_received_amount = context.accounts.vault.amount;
+    require!(
+        token_a_received_amount > 0,
+        ErrorCode::TransferFeeExceedsAmount
+    );
+
+    // A minimum fill larger than the offer itself could never be met
+    require!(
+        min_fill_amount <= token_a_received_amount,
+        ErrorCode::InvalidMinFillAmount
+    );
+
+    // Move any additional offered tokens into their own vaults
+    let additional_token_mints_a = deposit_additional_tokens(
+        context.remaining_accounts,
+        &additional_token_a_offered_amounts,
+        &[
+            context.accounts.token_mint_a.key(),
+            context.accounts.token_mint_b.key(),
+        ],
+        &context.accounts.config.mint_policy,
+        &context.accounts.maker.key(),
+        &context.accounts.maker_authority.to_account_info(),
+        &context.accounts.payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Take the maker's next id if they asked for one, the offer's address was derived from it
+    let maker_counter = &mut context.accounts.maker_counter;
+    maker_counter.maker = context.accounts.maker.key();
+    maker_counter.bump = context.bumps.maker_counter;
+    let id = if id == AUTO_ASSIGN_OFFER_ID {
+        let assigned_id = maker_counter.next_id;
+        maker_counter.next_id = assigned_id
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        assigned_id
+    } else {
+        id
+    };
+
+    let clock = Clock::get()?;
 
     // Save the details of the offer to the offer account
-    context.accounts.offer.set_inner(Offer {
-        id,
-        maker: context.accounts.maker.key(),
-        token_mint_a: context.accounts.token_mint_a.key(),
-        token_mint_b: context.accounts.token_mint_b.key(),
-        token_b_wanted_amount,
-        bump: context.bumps.offer,
-    });
+    // The account starts zeroed, so every optional value starts out unset
+    let mut offer = context.accounts.offer.load_init()?;
+    offer.id = id;
+    offer.maker = context.accounts.maker.key();
+    offer.token_mint_a = context.accounts.token_mint_a.key();
+    offer.token_mint_b = context.accounts.token_mint_b.key();
+    offer.token_b_wanted_amount = token_b_wanted_amount;
+    offer.bump = context.bumps.offer;
+    offer.vault_authority_bump = context.bumps.vault_authority;
+    offer.version = OFFER_VERSION;
+    offer.created_at = clock.unix_timestamp;
+    offer.created_slot = clock.slot;
+    offer.set_dutch_auction(dutch_auction);
+    offer.set_english_auction(english_auction);
+    offer.set_additional_token_mints_a(&additional_token_mints_a)?;
+    if context.accounts.token_mint_a_metadata.is_some() {
+        offer.set_nft(nft_collection);
+    }
+    offer.token_a_offered_amount = token_a_received_amount;
+    offer.set_price(token_b_wanted_amount, token_a_received_amount);
+    offer.min_fill_amount = min_fill_amount;
+    offer.set_hashlock(hashlock);
+    offer.set_vesting_duration(vesting_duration);
+    offer.set_arbiter(arbiter);
+    offer.set_keep_history(keep_history);
+    offer.tags = tags;
+    offer.maker_authority = context.accounts.maker_authority.key();
+    // Rent the maker authority paid goes back to the maker, a sponsor gets back what they paid
+    offer.rent_payer = if context.accounts.payer.key() == context.accounts.maker_authority.key() {
+        context.accounts.maker.key()
+    } else {
+        context.accounts.payer.key()
+    };
+    offer.set_memo(memo.as_deref())?;
+
+    record_offer_made(&mut context.accounts.registry)?;
+
+    let maker_index = &mut context.accounts.maker_index;
+    maker_index.maker = context.accounts.maker.key();
+    maker_index.bump = context.bumps.maker_index;
+    add_to_maker_index(maker_index, id, &context.accounts.config)?;
+
+    let maker_stats = &mut context.accounts.maker_stats;
+    maker_stats.maker = context.accounts.maker.key();
+    maker_stats.bump = context.bumps.maker_stats;
+    record_maker_offer_made(maker_stats)?;
+
+    emit_event(
+        OfferMade {
+            offer_id: id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_offered_amount: token_a_received_amount,
+            token_b_wanted_amount,
+            created_at: clock.unix_timestamp,
+            tags,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    // Callers who had the id assigned can read it from here rather than from the event
+    // Set last, since the event's self-CPI would clear it
+    set_return_data(&id.to_le_bytes());
+
     Ok(())
 }
@@ -0,0 +1,273 @@
+use super::shared::{
+    add_to_maker_index, approve_delegate, get_token_program_b, record_maker_offer_made,
+    record_offer_made, require_can_send, require_mint_allowed, require_mint_allowlisted,
+    require_mint_not_denied, require_no_transfer_hook, require_offer_amount_within_limits,
+};
+use crate::{
+    constants::{AUTO_ASSIGN_OFFER_ID, OFFER_TAGS_LENGTH, OFFER_VERSION},
+    error::ErrorCode,
+    events::{emit_event, OfferMade},
+    state::{Config, MakerCounter, MakerIndex, MakerStats, MintAllowlistEntry, Offer, Registry},
+};
+use anchor_lang::{prelude::*, solana_program::program::set_return_data};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct MakeOfferDelegated<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The maker signs the approval on their own token account, so there's no separate maker authority or payer
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // Keeps the offered tokens until the offer is taken, with the vault authority approved as its delegate
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Hands out the maker's offer ids, created with their first offer
+    // Comes before the offer, since the offer's address uses the id it assigns
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = MakerCounter::DISCRIMINATOR.len() + MakerCounter::INIT_SPACE,
+        seeds = [b"maker_counter", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_counter: Account<'info, MakerCounter>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Offer::SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_counter.resolve_offer_id(id).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it's approved as the delegate on the maker's token account
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, created with their first offer, so clients can list them without scanning every offer
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = MakerIndex::DISCRIMINATOR.len() + MakerIndex::INIT_SPACE,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics, also created with their first offer
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = MakerStats::DISCRIMINATOR.len() + MakerStats::INIT_SPACE,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+}
+
+// Handle the make offer delegated instruction by:
+// 1. Approving the vault authority PDA to move the offered tokens out of the maker's ATA, rather than moving them to a vault
+// 2. Saving the details of the offer to the offer account, with the maker's next id if passed AUTO_ASSIGN_OFFER_ID
+// 3. Counting the new offer in the registry and the maker's statistics, and listing it in the maker's index
+// 4. Emitting an OfferMade event, and returning the offer's id
+// The maker keeps custody of their tokens, and any yield on them, until the offer is taken
+// A token account has a single delegate, so a later approval on the same account, including another delegated offer,
+// replaces this one and the offer can no longer be taken until it is refunded
+// Delegated offers have a fixed price and are settled in full by take_offer, or refunded with refund_offer
+pub fn make_offer_delegated(
+    context: Context<MakeOfferDelegated>,
+    id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    tags: [u8; OFFER_TAGS_LENGTH],
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidOfferedAmount);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    require_offer_amount_within_limits(token_a_offered_amount, &context.accounts.config)?;
+    require_keys_neq!(
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
+        ErrorCode::InvalidTokenMint
+    );
+
+    // Token b can belong to the other token program, which the taker then passes as token_program_b
+    get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Both sides of the trade must be transferable without extra hook accounts
+    require_no_transfer_hook(&context.accounts.token_mint_a.to_account_info())?;
+    require_no_transfer_hook(&context.accounts.token_mint_b.to_account_info())?;
+    require_mint_allowed(&context.accounts.token_mint_a, &context.accounts.config.mint_policy)?;
+    require_mint_allowed(&context.accounts.token_mint_b, &context.accounts.config.mint_policy)?;
+
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    // The tokens stay with the maker, but they should at least be there when the offer is made
+    require_can_send(
+        &context.accounts.maker_token_account_a,
+        token_a_offered_amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+
+    // Let the vault authority move the offered tokens when the offer is taken
+    approve_delegate(
+        &context.accounts.maker_token_account_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &token_a_offered_amount,
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+    )?;
+
+    // Take the maker's next id if they asked for one, the offer's address was derived from it
+    let maker_counter = &mut context.accounts.maker_counter;
+    maker_counter.maker = context.accounts.maker.key();
+    maker_counter.bump = context.bumps.maker_counter;
+    let id = if id == AUTO_ASSIGN_OFFER_ID {
+        let assigned_id = maker_counter.next_id;
+        maker_counter.next_id = assigned_id
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        assigned_id
+    } else {
+        id
+    };
+
+    let clock = Clock::get()?;
+
+    // Save the details of the offer to the offer account
+    // The account starts zeroed, so every optional value starts out unset
+    let mut offer = context.accounts.offer.load_init()?;
+    offer.id = id;
+    offer.maker = context.accounts.maker.key();
+    offer.token_mint_a = context.accounts.token_mint_a.key();
+    offer.token_mint_b = context.accounts.token_mint_b.key();
+    offer.token_b_wanted_amount = token_b_wanted_amount;
+    offer.token_a_offered_amount = token_a_offered_amount;
+    offer.set_price(token_b_wanted_amount, token_a_offered_amount);
+    offer.bump = context.bumps.offer;
+    offer.vault_authority_bump = context.bumps.vault_authority;
+    offer.version = OFFER_VERSION;
+    offer.created_at = clock.unix_timestamp;
+    offer.created_slot = clock.slot;
+    offer.tags = tags;
+    offer.set_delegated(true);
+    offer.maker_authority = context.accounts.maker.key();
+    offer.rent_payer = context.accounts.maker.key();
+
+    record_offer_made(&mut context.accounts.registry)?;
+
+    let maker_index = &mut context.accounts.maker_index;
+    maker_index.maker = context.accounts.maker.key();
+    maker_index.bump = context.bumps.maker_index;
+    add_to_maker_index(maker_index, id, &context.accounts.config)?;
+
+    let maker_stats = &mut context.accounts.maker_stats;
+    maker_stats.maker = context.accounts.maker.key();
+    maker_stats.bump = context.bumps.maker_stats;
+    record_maker_offer_made(maker_stats)?;
+
+    emit_event(
+        OfferMade {
+            offer_id: id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            created_at: clock.unix_timestamp,
+            tags,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    // Callers who had the id assigned can read it from here rather than from the event
+    // Set last, since the event's self-CPI would clear it
+    set_return_data(&id.to_le_bytes());
+
+    Ok(())
+}
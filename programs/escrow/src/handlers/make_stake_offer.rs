@@ -0,0 +1,130 @@
+use super::shared::{require_mint_not_denied, set_stake_authorities};
+use crate::{
+    error::ErrorCode,
+    state::{Config, StakeOffer},
+};
+use anchor_lang::{prelude::*, solana_program::sysvar::stake_history::StakeHistorySysvar};
+use anchor_spl::{
+    stake::{Stake, StakeAccount},
+    token_interface::Mint,
+};
+
+#[derive(Accounts)]
+pub struct MakeStakeOffer<'info> {
+    // Hands the stake account's authorities to the stake offer
+    pub stake_program: Program<'info, Stake>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The stake account's staker and withdrawer
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub stake_account: Box<Account<'info, StakeAccount>>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = StakeOffer::DISCRIMINATOR.len() + StakeOffer::INIT_SPACE,
+        seeds = [b"stake_offer", stake_account.key().as_ref()],
+        bump
+    )]
+    pub stake_offer: Account<'info, StakeOffer>,
+
+    // The stake program reads the clock when changing authorities
+    pub clock: Sysvar<'info, Clock>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token b's deny entry address
+    /// The handler checks the address, and that it has no deny entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+}
+
+// Handle the make stake offer instruction by:
+// 1. Checking the maker is the stake account's staker and withdrawer, that no lockup is in force,
+//    and that the stake account is deactivated, either never delegated or with none of its stake still effective
+// 2. Handing the stake account's staker and withdrawer authorities to the stake offer
+// 3. Saving the token b the maker wants, and the stake account's lamports, to the stake offer
+// Only deactivated stake can be offered, so the taker can withdraw it without waiting for a cooldown
+pub fn make_stake_offer(
+    context: Context<MakeStakeOffer>,
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    let clock = &context.accounts.clock;
+    if let Some(expires_at) = expires_at {
+        require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpiry);
+    }
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    let stake_account = &context.accounts.stake_account;
+    let maker = context.accounts.maker.key();
+    let authorized = stake_account
+        .authorized()
+        .ok_or(ErrorCode::InvalidStakeAuthority)?;
+    require!(
+        authorized.staker == maker && authorized.withdrawer == maker,
+        ErrorCode::InvalidStakeAuthority
+    );
+    require!(
+        !stake_account
+            .lockup()
+            .is_some_and(|lockup| lockup.is_in_force(clock, None)),
+        ErrorCode::StakeLockupInForce
+    );
+    let deactivated = match stake_account.delegation() {
+        // Deactivating stake cools down over as many epochs as the cluster's rate limit needs,
+        // so check nothing is left effective rather than only that deactivation started
+        // Assuming the slower warmup and cooldown rate from epoch 0 can only overstate what's left
+        Some(delegation) => {
+            delegation.deactivation_epoch < clock.epoch
+                && delegation
+                    .stake_activating_and_deactivating(
+                        clock.epoch,
+                        &StakeHistorySysvar(clock.epoch),
+                        Some(0),
+                    )
+                    .effective
+                    == 0
+        }
+        // Stake accounts that were only initialized were never delegated
+        None => true,
+    };
+    require!(deactivated, ErrorCode::StakeNotDeactivated);
+
+    set_stake_authorities(
+        &context.accounts.stake_account.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.stake_offer.to_account_info(),
+        &context.accounts.clock.to_account_info(),
+        &context.accounts.stake_program,
+        None,
+    )?;
+
+    context.accounts.stake_offer.set_inner(StakeOffer {
+        maker,
+        stake_account: context.accounts.stake_account.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_b_wanted_amount,
+        stake_lamports: context.accounts.stake_account.to_account_info().lamports(),
+        expires_at,
+        created_at: clock.unix_timestamp,
+        bump: context.bumps.stake_offer,
+    });
+    Ok(())
+}
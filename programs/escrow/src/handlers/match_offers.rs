@@ -0,0 +1,585 @@
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_token_program_b, maker_fee_bps, record_maker_offer_filled, record_maker_volume,
+    record_offers_closed, record_volume_settled, remove_from_maker_index, require_mint_not_denied,
+    require_offer_not_expired, require_offer_old_enough, require_offer_open,
+    require_royalties_paid_by_take_offer, transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferStatus, Registry},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MatchOffers<'info> {
+    // Used to create the makers' accounts for the tokens they wanted if they don't have them yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Token a's token program, token b can belong to the other one
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Finds the mirrored offers and submits the match, paying for any token accounts the makers need
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+
+    /// CHECK: Checked against the first offer's maker by the first offer's constraints
+    #[account(mut)]
+    pub first_maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the first offer's rent payer, who gets the rent for the offer and its vault back
+    #[account(mut)]
+    pub first_rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the second offer's maker by the second offer's constraints
+    #[account(mut)]
+    pub second_maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the second offer's rent payer, who gets the rent for the offer and its vault back
+    #[account(mut)]
+    pub second_rent_payer: UncheckedAccount<'info>,
+
+    // What the first offer offers and the second offer wants
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    // What the first offer wants and the second offer offers
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    // Offers token a for token b, closed by the handler unless its maker asked to keep the offer's history
+    #[account(
+        mut,
+        constraint = first_offer.load()?.maker == first_maker.key() @ ErrorCode::InvalidMaker,
+        constraint = first_offer.load()?.rent_payer == first_rent_payer.key() @ ErrorCode::InvalidRentPayer,
+        constraint = first_offer.load()?.token_mint_a == token_mint_a.key() @ ErrorCode::WrongTokenMint,
+        constraint = first_offer.load()?.token_mint_b == token_mint_b.key() @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", first_offer.load()?.maker.as_ref(), first_offer.load()?.id.to_le_bytes().as_ref()],
+        bump = first_offer.load()?.bump
+    )]
+    pub first_offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the first offer's vault
+    #[account(
+        seeds = [b"vault_authority", first_offer.key().as_ref()],
+        bump = first_offer.load()?.vault_authority_bump
+    )]
+    pub first_vault_authority: UncheckedAccount<'info>,
+
+    // The first offer's vault, delegated offers can't be matched
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = first_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub first_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Offers token b for token a, closed by the handler unless its maker asked to keep the offer's history
+    #[account(
+        mut,
+        constraint = second_offer.load()?.maker == second_maker.key() @ ErrorCode::InvalidMaker,
+        constraint = second_offer.load()?.rent_payer == second_rent_payer.key() @ ErrorCode::InvalidRentPayer,
+        constraint = second_offer.load()?.token_mint_a == token_mint_b.key() @ ErrorCode::WrongTokenMint,
+        constraint = second_offer.load()?.token_mint_b == token_mint_a.key() @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", second_offer.load()?.maker.as_ref(), second_offer.load()?.id.to_le_bytes().as_ref()],
+        bump = second_offer.load()?.bump
+    )]
+    pub second_offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the second offer's vault
+    #[account(
+        seeds = [b"vault_authority", second_offer.key().as_ref()],
+        bump = second_offer.load()?.vault_authority_bump
+    )]
+    pub second_vault_authority: UncheckedAccount<'info>,
+
+    // The second offer's vault, derived with token b's own token program, which can differ from token a's
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = second_vault_authority,
+        address = get_associated_token_address_with_program_id(
+            &second_vault_authority.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidVault
+    )]
+    pub second_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Gets back whatever the first offer holds beyond what the second maker wants, less the matcher's share
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = first_maker,
+        associated_token::token_program = token_program
+    )]
+    pub first_maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Checked to be the first maker's associated token account for token b, the handler creates it if the maker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &first_maker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub first_maker_token_account_b: UncheckedAccount<'info>,
+
+    /// CHECK: Checked to be the second maker's associated token account for token a, the handler creates it if the maker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &second_maker.key(),
+            &token_mint_a.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub second_maker_token_account_a: UncheckedAccount<'info>,
+
+    // Gets back whatever the second offer holds beyond what the first maker wants, less the matcher's share
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = second_maker,
+        address = get_associated_token_address_with_program_id(
+            &second_maker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub second_maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The matcher's share of the price improvement in each token goes to any of their accounts for it
+    #[account(
+        mut,
+        token::mint = token_mint_a,
+        token::authority = matcher
+    )]
+    pub matcher_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = matcher
+    )]
+    pub matcher_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_index", first_maker.key().as_ref()],
+        bump = first_maker_index.bump
+    )]
+    pub first_maker_index: Box<Account<'info, MakerIndex>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_stats", first_maker.key().as_ref()],
+        bump = first_maker_stats.bump
+    )]
+    pub first_maker_stats: Box<Account<'info, MakerStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_index", second_maker.key().as_ref()],
+        bump = second_maker_index.bump
+    )]
+    pub second_maker_index: Box<Account<'info, MakerIndex>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_stats", second_maker.key().as_ref()],
+        bump = second_maker_stats.bump
+    )]
+    pub second_maker_stats: Box<Account<'info, MakerStats>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Token b's token program, the same as token_program if both tokens belong to one
+    // The handler checks it owns token b's mint
+    pub token_program_b: Interface<'info, TokenInterface>,
+
+    // Collects the protocol fee on the token a the second maker receives, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collects the protocol fee on the token b the first maker receives
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        seeds = [b"fee_vault", token_mint_b.key().as_ref()],
+        bump,
+        token::mint = token_mint_b,
+        token::authority = config,
+        token::token_program = token_program_b
+    )]
+    pub fee_vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+// Handle the match offers instruction by:
+// 1. Checking both offers are open, fixed price offers for the same pair of mints in opposite directions,
+//    from different makers, and that each offers at least what the other wants
+// 2. Paying each maker what they want straight out of the other offer's vault, less the protocol fee
+//    Each maker is the other offer's taker, so like take_offer the fee is charged at the rate of the offer they take
+//    If the wanted token charges a transfer fee, the vault pays it on top, so the maker gets the full amount
+// 3. Splitting whatever each vault has left over, the price improvement, between the matcher and that vault's maker
+//    The matcher gets the config's matcher_share_bps, the maker gets the rest back, then the vault is closed
+// 4. Recording both settlements in the registry and each maker's index and statistics
+//    Each offer is closed, or marked filled if its maker asked to keep its history
+// 5. Emitting an OfferTaken event for each offer, with the other offer's maker as the taker
+// Keepers can match offers without holding either token, the makers' own deposits settle both sides
+pub fn match_offers(context: Context<MatchOffers>) -> Result<()> {
+    let first_offer = *context.accounts.first_offer.load()?;
+    let second_offer = *context.accounts.second_offer.load()?;
+    let clock = Clock::get()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    for offer in [&first_offer, &second_offer] {
+        require_offer_open(offer)?;
+        require_offer_not_expired(offer, clock.unix_timestamp)?;
+        require_royalties_paid_by_take_offer(offer, &context.accounts.config)?;
+        require_offer_old_enough(offer, &context.accounts.config, clock.slot)?;
+        // A match settles both offers in full at the prices their makers set
+        require!(
+            offer.dutch_auction().is_none()
+                && offer.english_auction().is_none()
+                && offer.hashlock().is_none()
+                && offer.attestor().is_none()
+                && offer.eth_taker().is_none()
+                && offer.vesting_duration().is_none()
+                && offer.arbiter().is_none()
+                && !offer.is_delegated()
+                && !offer.allowlist_enabled()
+                && offer.additional_token_mints_a().is_empty(),
+            ErrorCode::MatchNotSupported
+        );
+    }
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require!(
+        first_offer.maker != second_offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    let token_program_b = get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        Some(&context.accounts.token_program_b),
+    )?;
+
+    // We match the vaults' real balances, so any Token-2022 transfer fee on the deposits isn't paid for twice
+    require!(
+        context.accounts.first_vault.amount >= second_offer.token_b_wanted_amount
+            && context.accounts.second_vault.amount >= first_offer.token_b_wanted_amount,
+        ErrorCode::OffersDontMatch
+    );
+
+    // Makers don't need an account for the token they want to make an offer, so create them if they don't have one yet
+    let first_maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context
+            .accounts
+            .first_maker_token_account_b
+            .to_account_info(),
+        &context.accounts.first_maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.matcher.to_account_info(),
+        token_program_b,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+    let second_maker_token_account_a_created = create_associated_token_account_if_needed(
+        &context
+            .accounts
+            .second_maker_token_account_a
+            .to_account_info(),
+        &context.accounts.second_maker.to_account_info(),
+        &context.accounts.token_mint_a.to_account_info(),
+        &context.accounts.matcher.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    let matcher_share_bps = context.accounts.config.matcher_share_bps;
+    let (token_a_spent_amount, token_a_received_amount) = settle_match_side(
+        &first_offer,
+        &context.accounts.first_offer.key(),
+        &mut context.accounts.first_vault,
+        &context.accounts.first_vault_authority.to_account_info(),
+        &context.accounts.token_mint_a,
+        &context.accounts.token_program,
+        &context
+            .accounts
+            .second_maker_token_account_a
+            .to_account_info(),
+        second_offer.token_b_wanted_amount,
+        &context.accounts.matcher_token_account_a,
+        &context.accounts.first_maker_token_account_a,
+        matcher_share_bps,
+        &context.accounts.fee_vault_a,
+        maker_fee_bps(
+            &context.accounts.config,
+            &context.accounts.first_maker_stats,
+        ),
+        &context.accounts.first_rent_payer.to_account_info(),
+    )?;
+    let (token_b_spent_amount, token_b_received_amount) = settle_match_side(
+        &second_offer,
+        &context.accounts.second_offer.key(),
+        &mut context.accounts.second_vault,
+        &context.accounts.second_vault_authority.to_account_info(),
+        &context.accounts.token_mint_b,
+        token_program_b,
+        &context
+            .accounts
+            .first_maker_token_account_b
+            .to_account_info(),
+        first_offer.token_b_wanted_amount,
+        &context.accounts.matcher_token_account_b,
+        &context.accounts.second_maker_token_account_b,
+        matcher_share_bps,
+        &context.accounts.fee_vault_b,
+        maker_fee_bps(
+            &context.accounts.config,
+            &context.accounts.second_maker_stats,
+        ),
+        &context.accounts.second_rent_payer.to_account_info(),
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 2)?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+    record_volume_settled(&mut context.accounts.registry, token_a_received_amount)?;
+    remove_from_maker_index(&mut context.accounts.first_maker_index, first_offer.id);
+    record_maker_offer_filled(&mut context.accounts.first_maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.first_maker_stats,
+        token_a_spent_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    remove_from_maker_index(&mut context.accounts.second_maker_index, second_offer.id);
+    record_maker_offer_filled(&mut context.accounts.second_maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.second_maker_stats,
+        token_b_spent_amount,
+        token_a_received_amount,
+        &context.accounts.token_mint_a.key(),
+        &context.accounts.config,
+    )?;
+
+    // Close the offers and return the rent to whoever paid for them
+    // Offers that keep their history stay open as a record of the fill, until claim_rent closes them
+    if first_offer.keep_history() {
+        context
+            .accounts
+            .first_offer
+            .load_mut()?
+            .set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .first_offer
+            .close(context.accounts.first_rent_payer.to_account_info())?;
+    }
+    if second_offer.keep_history() {
+        context
+            .accounts
+            .second_offer
+            .load_mut()?
+            .set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .second_offer
+            .close(context.accounts.second_rent_payer.to_account_info())?;
+    }
+
+    emit_event(
+        OfferTaken {
+            offer_id: first_offer.id,
+            maker: first_offer.maker,
+            taker: second_offer.maker,
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_received_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created: first_maker_token_account_b_created,
+            created_at: first_offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: second_offer.maker,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+    emit_event(
+        OfferTaken {
+            offer_id: second_offer.id,
+            maker: second_offer.maker,
+            taker: first_offer.maker,
+            token_mint_a: context.accounts.token_mint_b.key(),
+            token_mint_b: context.accounts.token_mint_a.key(),
+            token_a_amount: token_b_received_amount,
+            token_b_amount: token_a_received_amount,
+            maker_token_account_b_created: second_maker_token_account_a_created,
+            created_at: second_offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: first_offer.maker,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
+
+// Settle one offer's side of a match: pay the other maker what they want out of the offer's vault, less the protocol
+// fee, then split what's left over between the matcher and the offer's maker, and close the vault
+// Returns how much left the vault for the other maker, including the protocol fee and any transfer fee, and how much
+// they received
+// Both makers' accounts are passed individually, since each side of the match settles from different ones
+#[allow(clippy::too_many_arguments)]
+fn settle_match_side<'info>(
+    offer: &Offer,
+    offer_key: &Pubkey,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    vault_authority: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    other_maker_token_account: &AccountInfo<'info>,
+    other_maker_wanted_amount: u64,
+    matcher_token_account: &InterfaceAccount<'info, TokenAccount>,
+    maker_token_account: &InterfaceAccount<'info, TokenAccount>,
+    matcher_share_bps: u16,
+    fee_vault: &InterfaceAccount<'info, TokenAccount>,
+    fee_bps: u16,
+    rent_payer: &AccountInfo<'info>,
+) -> Result<(u64, u64)> {
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    let offered_amount = vault.amount;
+
+    // Send the protocol fee from the vault to the fee vault
+    let protocol_fee = calculate_fee(other_maker_wanted_amount, fee_bps)?;
+    if protocol_fee > 0 {
+        transfer_tokens(
+            vault,
+            fee_vault,
+            &protocol_fee,
+            mint,
+            vault_authority,
+            token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    let received_amount = transfer_tokens_including_fee(
+        vault,
+        other_maker_token_account,
+        &(other_maker_wanted_amount - protocol_fee),
+        mint,
+        vault_authority,
+        token_program,
+        signers_seeds,
+        ErrorCode::FailedVaultWithdrawal,
+    )?;
+    vault.reload()?;
+    let spent_amount = offered_amount
+        .checked_sub(vault.amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // A transfer fee on top of what the other maker wants can use up more than the offer holds
+    let price_improvement = offered_amount
+        .checked_sub(spent_amount)
+        .ok_or(ErrorCode::OffersDontMatch)?;
+
+    let matcher_amount = calculate_fee(price_improvement, matcher_share_bps)?;
+    if matcher_amount > 0 {
+        transfer_tokens(
+            vault,
+            matcher_token_account,
+            &matcher_amount,
+            mint,
+            vault_authority,
+            token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    let maker_amount = price_improvement - matcher_amount;
+    if maker_amount > 0 {
+        transfer_tokens(
+            vault,
+            maker_token_account,
+            &maker_amount,
+            mint,
+            vault_authority,
+            token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        vault,
+        rent_payer,
+        vault_authority,
+        token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    Ok((spent_amount, received_amount))
+}
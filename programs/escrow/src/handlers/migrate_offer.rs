@@ -0,0 +1,106 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::pubkey::PUBKEY_BYTES,
+    system_program::{transfer, Transfer},
+};
+use std::mem::{offset_of, size_of};
+
+use crate::{constants::OFFER_VERSION, error::ErrorCode, state::Offer};
+
+#[derive(Accounts)]
+pub struct MigrateOffer<'info> {
+    // Anyone can migrate an offer, since it only adds space, and they pay the extra rent
+    // The rent goes to the maker when the offer is closed, like the rest of the offer's rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Older offers are too small to load as the current Offer, so the handler checks the discriminator itself
+    #[account(
+        mut,
+        owner = crate::ID
+    )]
+    pub offer: UncheckedAccount<'info>,
+
+    // Used to pay for the rent of the extra space
+    pub system_program: Program<'info, System>,
+}
+
+// Handle the migrate offer instruction by:
+// 1. Working out which version of the layout the offer was written with
+// 2. Growing the account to the current layout, with the payer paying the extra rent
+// 3. Moving any metadata URI to after the current layout, and zeroing the new fields in between
+// 4. Filling in fields older offers didn't record, then recording the current version on the offer
+// Most new fields start zeroed, which is the same as an offer made without them, ie every optional value unset
+pub fn migrate_offer(context: Context<MigrateOffer>) -> Result<()> {
+    let offer_info = context.accounts.offer.to_account_info();
+
+    let (version, old_length) = {
+        let data = offer_info.try_borrow_data()?;
+        require!(
+            data.starts_with(Offer::DISCRIMINATOR),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        (Offer::layout_version(&data)?, data.len())
+    };
+    require!(version < OFFER_VERSION, ErrorCode::OfferAlreadyMigrated);
+
+    let old_offer_end = Offer::DISCRIMINATOR.len() + Offer::LAYOUT_SIZES[version as usize];
+    let metadata_uri_length = old_length - old_offer_end;
+    let new_length = Offer::METADATA_URI_OFFSET + metadata_uri_length;
+
+    // Top the account up to the rent exempt minimum for its new size
+    let rent_needed = Rent::get()?
+        .minimum_balance(new_length)
+        .saturating_sub(offer_info.lamports());
+    if rent_needed > 0 {
+        transfer(
+            CpiContext::new(
+                context.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: context.accounts.payer.to_account_info(),
+                    to: offer_info.clone(),
+                },
+            ),
+            rent_needed,
+        )?;
+    }
+
+    offer_info.realloc(new_length, false)?;
+
+    let mut data = offer_info.try_borrow_mut_data()?;
+    data.copy_within(
+        old_offer_end..old_offer_end + metadata_uri_length,
+        Offer::METADATA_URI_OFFSET,
+    );
+    data[old_offer_end..Offer::METADATA_URI_OFFSET].fill(0);
+
+    // Offers from before the rent payer was recorded were always paid for by their maker
+    if version < 2 {
+        let rent_payer_offset = Offer::DISCRIMINATOR.len() + offset_of!(Offer, rent_payer);
+        data.copy_within(
+            Offer::MAKER_OFFSET..Offer::MAKER_OFFSET + PUBKEY_BYTES,
+            rent_payer_offset,
+        );
+    }
+    // Offers from before the price was recorded were priced by their totals
+    if version < 3 {
+        let token_b_wanted_offset =
+            Offer::DISCRIMINATOR.len() + offset_of!(Offer, token_b_wanted_amount);
+        let token_a_offered_offset =
+            Offer::DISCRIMINATOR.len() + offset_of!(Offer, token_a_offered_amount);
+        let price_numerator_offset = Offer::DISCRIMINATOR.len() + offset_of!(Offer, price_numerator);
+        let price_denominator_offset =
+            Offer::DISCRIMINATOR.len() + offset_of!(Offer, price_denominator);
+        data.copy_within(
+            token_b_wanted_offset..token_b_wanted_offset + size_of::<u64>(),
+            price_numerator_offset,
+        );
+        data.copy_within(
+            token_a_offered_offset..token_a_offered_offset + size_of::<u64>(),
+            price_denominator_offset,
+        );
+    }
+    data[Offer::DISCRIMINATOR.len() + offset_of!(Offer, version)] = OFFER_VERSION;
+
+    Ok(())
+}
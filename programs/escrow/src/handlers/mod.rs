@@ -1,11 +1,175 @@
 pub mod make_offer;
 pub use make_offer::*;
 
+pub mod make_offer_delegated;
+pub use make_offer_delegated::*;
+
+pub mod make_offer_with_existing_vault;
+pub use make_offer_with_existing_vault::*;
+
+pub mod create_offer_template;
+pub use create_offer_template::*;
+
+pub mod spawn_offer_from_template;
+pub use spawn_offer_from_template::*;
+
+pub mod close_offer_template;
+pub use close_offer_template::*;
+
 pub mod take_offer;
 pub use take_offer::*;
 
+pub mod take_offer_exact_out;
+pub use take_offer_exact_out::*;
+
+pub mod take_offer_with_token_accounts;
+pub use take_offer_with_token_accounts::*;
+
+pub mod take_offer_with_authorization;
+pub use take_offer_with_authorization::*;
+
+pub mod match_offers;
+pub use match_offers::*;
+
+pub mod take_offer_route;
+pub use take_offer_route::*;
+
+pub mod request_quote;
+pub use request_quote::*;
+
+pub mod submit_quote;
+pub use submit_quote::*;
+
+pub mod accept_quote;
+pub use accept_quote::*;
+
+pub mod withdraw_quote;
+pub use withdraw_quote::*;
+
+pub mod close_quote_request;
+pub use close_quote_request::*;
+
+pub mod make_stake_offer;
+pub use make_stake_offer::*;
+
+pub mod take_stake_offer;
+pub use take_stake_offer::*;
+
+pub mod cancel_stake_offer;
+pub use cancel_stake_offer::*;
+
+pub mod take_offer_vested;
+pub use take_offer_vested::*;
+
+pub mod claim_vested;
+pub use claim_vested::*;
+
+pub mod take_offer_in_installments;
+pub use take_offer_in_installments::*;
+
+pub mod pay_installment;
+pub use pay_installment::*;
+
+pub mod cancel_default;
+pub use cancel_default::*;
+
+pub mod deposit_for_offer;
+pub use deposit_for_offer::*;
+
+pub mod withdraw_deposit;
+pub use withdraw_deposit::*;
+
+pub mod confirm_swap;
+pub use confirm_swap::*;
+
+pub mod take_offers_batch;
+pub use take_offers_batch::*;
+
+pub mod take_offer_arbitrated;
+pub use take_offer_arbitrated::*;
+
+pub mod release;
+pub use release::*;
+
+pub mod revert;
+pub use revert::*;
+
 pub mod refund_offer;
 pub use refund_offer::*;
 
+pub mod refund_legacy_offer;
+pub use refund_legacy_offer::*;
+
+pub mod claim_rent;
+pub use claim_rent::*;
+
+pub mod update_offer;
+pub use update_offer::*;
+
+pub mod replace_offer;
+pub use replace_offer::*;
+
+pub mod extend_offer;
+pub use extend_offer::*;
+
+pub mod top_up_offer;
+pub use top_up_offer::*;
+
+pub mod reduce_offer;
+pub use reduce_offer::*;
+
+pub mod set_refund_authority;
+pub use set_refund_authority::*;
+
+pub mod set_installment_terms;
+pub use set_installment_terms::*;
+
+pub mod set_attestor;
+pub use set_attestor::*;
+pub mod set_eth_taker;
+pub use set_eth_taker::*;
+
+pub mod make_counter_offer;
+pub use make_counter_offer::*;
+
+pub mod accept_counter_offer;
+pub use accept_counter_offer::*;
+
+pub mod reject_counter_offer;
+pub use reject_counter_offer::*;
+
+pub mod place_bid;
+pub use place_bid::*;
+
+pub mod outbid;
+pub use outbid::*;
+
+pub mod settle_auction;
+pub use settle_auction::*;
+
+pub mod add_allowed_taker;
+pub use add_allowed_taker::*;
+
+pub mod remove_allowed_taker;
+pub use remove_allowed_taker::*;
+
+pub mod attach_metadata;
+pub use attach_metadata::*;
+
+pub mod migrate_offer;
+pub use migrate_offer::*;
+
+pub mod take_offer_nft;
+pub use take_offer_nft::*;
+
+pub mod admin;
+pub use admin::*;
+
+pub mod bundle;
+pub use bundle::*;
+
+pub mod nft;
+pub use nft::*;
+
 pub mod shared;
 pub use shared::*;
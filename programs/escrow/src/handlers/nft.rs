@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    metadata::{mpl_token_metadata::accounts::Metadata, MetadataAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::transfer_tokens_including_fee;
+use crate::{
+    constants::{BASIS_POINTS_DENOMINATOR, CREATOR_SHARE_DENOMINATOR, NFT_DECIMALS, NFT_SUPPLY},
+    error::ErrorCode,
+    math::{mul_div, Rounding},
+};
+
+// Check that the offered mint is an NFT and that the metadata account is its Metaplex metadata
+// Returns the NFT's collection, but only if the collection's update authority has verified it,
+// since anyone can claim an unverified collection
+pub fn validate_nft_metadata(
+    mint: &InterfaceAccount<Mint>,
+    metadata: &Account<MetadataAccount>,
+    offered_amount: u64,
+) -> Result<Option<Pubkey>> {
+    require!(
+        mint.decimals == NFT_DECIMALS && mint.supply == NFT_SUPPLY,
+        ErrorCode::NotAnNft
+    );
+    require!(offered_amount == NFT_SUPPLY, ErrorCode::InvalidNftAmount);
+
+    // The Account wrapper has already checked the metadata is owned by the Metaplex program
+    let (metadata_address, _metadata_bump) = Metadata::find_pda(&mint.key());
+    require_keys_eq!(metadata.key(), metadata_address, ErrorCode::InvalidMetadata);
+    require_keys_eq!(metadata.mint, mint.key(), ErrorCode::InvalidMetadata);
+
+    Ok(metadata
+        .collection
+        .as_ref()
+        .filter(|collection| collection.verified)
+        .map(|collection| collection.key))
+}
+
+// Pay an NFT's creators their royalty on what the taker pays for it, out of the maker's proceeds
+// Each creator with a share is passed as a remaining account, their token b associated token account,
+// in the order the metadata lists them, and creators with no share are skipped
+// Returns the royalty the creators were paid in total, so the maker can be sent the rest
+// Takes the token accounts and programs a royalty transfer needs individually, like transfer_tokens
+#[allow(clippy::too_many_arguments)]
+pub fn pay_royalties<'info>(
+    metadata: &Account<'info, MetadataAccount>,
+    token_mint_a: &Pubkey,
+    token_b_amount: u64,
+    creator_token_accounts: &'info [AccountInfo<'info>],
+    taker_token_account_b: &InterfaceAccount<'info, TokenAccount>,
+    token_mint_b: &InterfaceAccount<'info, Mint>,
+    taker: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<u64> {
+    let (metadata_address, _metadata_bump) = Metadata::find_pda(token_mint_a);
+    require_keys_eq!(metadata.key(), metadata_address, ErrorCode::InvalidMetadata);
+
+    let royalty_amount = mul_div(
+        token_b_amount,
+        metadata.seller_fee_basis_points as u64,
+        BASIS_POINTS_DENOMINATOR,
+        Rounding::Down,
+    )?;
+
+    let creators: Vec<_> = metadata
+        .creators
+        .iter()
+        .flatten()
+        .filter(|creator| creator.share > 0)
+        .collect();
+    require!(
+        creator_token_accounts.len() == creators.len(),
+        ErrorCode::InvalidRemainingAccounts
+    );
+
+    // Each creator's cut rounds down, so any dust stays with the maker
+    let mut royalty_paid_amount: u64 = 0;
+    for (creator, creator_token_account_info) in creators.iter().zip(creator_token_accounts) {
+        require_keys_eq!(
+            creator_token_account_info.key(),
+            get_associated_token_address_with_program_id(
+                &creator.address,
+                &token_mint_b.key(),
+                &token_program.key()
+            ),
+            ErrorCode::InvalidTokenAccount
+        );
+        let creator_token_account =
+            InterfaceAccount::<TokenAccount>::try_from(creator_token_account_info)?;
+
+        let creator_amount = mul_div(
+            royalty_amount,
+            creator.share as u64,
+            CREATOR_SHARE_DENOMINATOR,
+            Rounding::Down,
+        )?;
+        if creator_amount == 0 {
+            continue;
+        }
+
+        // Like the maker, creators get their full cut, the taker pays any transfer fee on top
+        transfer_tokens_including_fee(
+            taker_token_account_b,
+            &creator_token_account,
+            &creator_amount,
+            token_mint_b,
+            taker,
+            token_program,
+            None,
+            ErrorCode::InsufficientTakerBalance,
+        )?;
+        royalty_paid_amount += creator_amount;
+    }
+
+    Ok(royalty_paid_amount)
+}
@@ -0,0 +1,124 @@
+use super::shared::transfer_tokens;
+use crate::{
+    error::ErrorCode,
+    state::{Bid, Offer},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct Outbid<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    pub previous_bidder: SystemAccount<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = bidder,
+        associated_token::token_program = token_program,
+    )]
+    pub bidder_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = previous_bidder,
+        associated_token::token_program = token_program,
+    )]
+    pub previous_bidder_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = offer @ ErrorCode::WrongOffer,
+        constraint = bid.bidder == previous_bidder.key() @ ErrorCode::BidderMismatch,
+        seeds = [b"bid", offer.key().as_ref()],
+        bump = bid.bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = bid,
+        associated_token::token_program = token_program,
+    )]
+    pub bid_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+// Handle the outbid instruction by:
+// 1. Refunding the previous highest bid from the bid vault
+// 2. Moving the new bid from the bidder's account to the bid vault
+// 3. Replacing the previous bid details in the bid account
+pub fn outbid(context: Context<Outbid>, amount: u64) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    let english_auction = offer.english_auction().ok_or(ErrorCode::NotAnAuction)?;
+
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < english_auction.end_timestamp,
+        ErrorCode::AuctionEnded
+    );
+    require!(
+        context.accounts.bidder.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    let minimum_bid = context
+        .accounts
+        .bid
+        .amount
+        .checked_add(english_auction.min_bid_increment)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(amount >= minimum_bid, ErrorCode::BidTooLow);
+
+    // The bid account owns the bid vault, so it signs to refund the previous bidder
+    let offer_key = context.accounts.offer.key();
+    let bid_account_seeds = &[b"bid", offer_key.as_ref(), &[context.accounts.bid.bump]];
+
+    // Refund the previous highest bid
+    transfer_tokens(
+        &context.accounts.bid_vault,
+        &*context.accounts.previous_bidder_token_account_b,
+        &context.accounts.bid.amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.bid.to_account_info(),
+        &context.accounts.token_program,
+        Some(&bid_account_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+    // Move the new bid from the bidder's account to the bid vault
+    transfer_tokens(
+        &context.accounts.bidder_token_account_b,
+        &*context.accounts.bid_vault,
+        &amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.bidder.to_account_info(),
+        &context.accounts.token_program,
+        None,
+    )
+    .map_err(|_| ErrorCode::InsufficientTakerBalance)?;
+
+    // Replace the previous bid details
+    context.accounts.bid.bidder = context.accounts.bidder.key();
+    context.accounts.bid.amount = amount;
+    context.accounts.offer.load_mut()?.highest_bid = amount;
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_next_installment, record_maker_offer_filled, record_maker_volume, record_volume_settled,
+    transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, InstallmentPlan, MakerStats, Registry},
+};
+
+#[derive(Accounts)]
+pub struct PayInstallment<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the plan's maker with has_one, makers can be program owned accounts like multisig vaults
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker closed it since, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = taker @ ErrorCode::InvalidTaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"installment_plan", installment_plan.offer.as_ref()],
+        bump = installment_plan.bump
+    )]
+    pub installment_plan: Account<'info, InstallmentPlan>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = installment_plan,
+        associated_token::token_program = token_program,
+    )]
+    pub installment_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated as each installment settles
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the pay installment instruction by:
+// 1. Sending the next installment of token b from the taker to the maker
+// 2. Releasing the same share of token a from the installment vault to the taker, less the protocol fee
+// 3. Recording the volume settled in the registry and the maker's statistics
+// 4. Once the last installment is paid, recording the offer as filled,
+//    and closing the installment vault and plan, returning the rent to the taker
+// Late installments are still accepted, until the maker cancels the plan with cancel_default
+// Pausing the program doesn't stop installments, so a pause can't push a taker into default
+pub fn pay_installment(context: Context<PayInstallment>) -> Result<()> {
+    let (token_a_installment_amount, token_b_installment_amount) =
+        get_next_installment(&context.accounts.installment_plan)?;
+
+    // Makers can close their token b account while the plan runs, so create it again if they have
+    create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_installment_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    // The installment plan owns the installment vault, so it signs for the release
+    let offer_key = context.accounts.installment_plan.offer;
+    let installment_plan_seeds = &[
+        b"installment_plan",
+        offer_key.as_ref(),
+        &[context.accounts.installment_plan.bump],
+    ];
+    let signers_seeds = Some(&installment_plan_seeds[..]);
+
+    let protocol_fee = calculate_fee(
+        token_a_installment_amount,
+        context.accounts.installment_plan.fee_bps,
+    )?;
+
+    // Send the protocol fee from the installment vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.installment_vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &context.accounts.installment_plan.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Release this installment of the offered tokens to the taker
+    transfer_tokens(
+        &context.accounts.installment_vault,
+        &context.accounts.taker_token_account_a,
+        &(token_a_installment_amount - protocol_fee),
+        &context.accounts.token_mint_a,
+        &context.accounts.installment_plan.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    let installment_plan = &mut context.accounts.installment_plan;
+    installment_plan.installments_paid += 1;
+    installment_plan.token_a_released += token_a_installment_amount;
+    installment_plan.token_b_paid += token_b_installment_amount;
+    let plan_complete = installment_plan.installments_paid == installment_plan.installment_count;
+
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        token_a_installment_amount,
+        token_b_received_amount,
+
+        &context.accounts.token_mint_b.key(),
+
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    if plan_complete {
+        record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+
+        close_token_account(
+            &context.accounts.installment_vault,
+            &context.accounts.taker.to_account_info(),
+            &context.accounts.installment_plan.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+        context
+            .accounts
+            .installment_plan
+            .close(context.accounts.taker.to_account_info())?;
+    }
+
+    Ok(())
+}
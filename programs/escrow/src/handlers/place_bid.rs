@@ -0,0 +1,110 @@
+use super::shared::{require_offer_open, transfer_tokens};
+use crate::{
+    error::ErrorCode,
+    state::{Bid, Offer},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = bidder,
+        associated_token::token_program = token_program,
+    )]
+    pub bidder_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        init,
+        payer = bidder,
+        space = Bid::DISCRIMINATOR.len() + Bid::INIT_SPACE,
+        seeds = [b"bid", offer.key().as_ref()],
+        bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    #[account(
+        init,
+        payer = bidder,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = bid,
+        associated_token::token_program = token_program
+    )]
+    pub bid_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the place bid instruction, for the first bid in an English auction, by:
+// 1. Moving the bid from the bidder's account to the bid vault
+// 2. Saving the bid details to the bid account
+// Later bids use outbid, which refunds the previous bidder
+pub fn place_bid(context: Context<PlaceBid>, amount: u64) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require_offer_open(&offer)?;
+    let english_auction = offer.english_auction().ok_or(ErrorCode::NotAnAuction)?;
+
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < english_auction.end_timestamp,
+        ErrorCode::AuctionEnded
+    );
+    require!(
+        context.accounts.bidder.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    // The first bid must meet the reserve price
+    require!(amount >= offer.token_b_wanted_amount, ErrorCode::BidTooLow);
+
+    // Move the bid from the bidder's account to the bid vault
+    transfer_tokens(
+        &context.accounts.bidder_token_account_b,
+        &context.accounts.bid_vault,
+        &amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.bidder.to_account_info(),
+        &context.accounts.token_program,
+        None,
+    )
+    .map_err(|_| ErrorCode::InsufficientTakerBalance)?;
+
+    // Save the bid details to the bid account
+    context.accounts.bid.set_inner(Bid {
+        offer: context.accounts.offer.key(),
+        bidder: context.accounts.bidder.key(),
+        amount,
+        rent_payer: context.accounts.bidder.key(),
+        bump: context.bumps.bid,
+    });
+    context.accounts.offer.load_mut()?.highest_bid = amount;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use super::{shared::transfer_tokens, top_up_offer::TopUpOffer};
+use crate::error::ErrorCode;
+
+// Handle the reduce offer instruction by:
+// 1. Checking what's left in the vault can still be filled
+// 2. Withdrawing part of the vault back to the maker's ATA
+// 3. Reducing the amount of token b wanted to what the rest is worth at the offer's price
+// Emptying the vault isn't a reduction, makers who want everything back use refund_offer
+pub fn reduce_offer(context: Context<TopUpOffer>, amount: u64) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+
+    let offer = *context.accounts.offer.load()?;
+    // A taker waiting on the arbiter has already paid for the current terms
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+    // Like partial fills, this needs the offer to have a single fixed price
+    require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+    require!(
+        offer.dutch_auction().is_none() && offer.additional_token_mints_a().is_empty(),
+        ErrorCode::PartialFillNotSupported
+    );
+
+    let vault_amount = context.accounts.vault.amount;
+    require!(amount < vault_amount, ErrorCode::InsufficientOfferBalance);
+    let token_a_remaining_amount = vault_amount - amount;
+    require!(
+        token_a_remaining_amount >= offer.min_fill_amount,
+        ErrorCode::FillTooSmall
+    );
+
+    let token_b_remaining_amount = offer.token_b_amount_for(token_a_remaining_amount)?;
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.maker_token_account_a,
+        &amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    let mut offer = context.accounts.offer.load_mut()?;
+    offer.token_a_offered_amount = token_a_remaining_amount;
+    offer.token_b_wanted_amount = token_b_remaining_amount;
+
+    Ok(())
+}
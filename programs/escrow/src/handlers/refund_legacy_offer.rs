@@ -0,0 +1,209 @@
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use super::{
+    bundle::withdraw_additional_tokens,
+    shared::{
+        close_token_account, record_maker_offer_refunded, record_offers_closed,
+        remove_from_maker_index, require_offer_open, revoke_delegate, transfer_tokens,
+    },
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferRefunded},
+    state::{MakerIndex, MakerStats, Offer, OfferAllowlist, Registry},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefundLegacyOffer<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Only the maker could refund offers when legacy offers were made
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Legacy offers made before offers were derived from their maker aren't at the address the other instructions
+    // derive, so the handler checks the seeds itself
+    // Offers written with an older layout are upgraded with migrate_offer first
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    // Legacy vaults are owned by the offer itself, rather than a vault authority
+    // For delegated offers this is the maker's token a account again
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer.load()?.token_a_holder(&offer.key()),
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the refund legacy offer instruction by:
+// 1. Checking the offer was made before offers had a vault authority, so its vaults are owned by the offer itself,
+//    and that it's at either its current address or the address offers had before they were derived from their
+//    maker, ["offer", id]
+// 2. Returning the tokens from the vault, and each additional vault of a bundle, to the maker, signed by the offer
+//    Delegated offers have no vault, so the maker's approval of the offer is revoked instead
+// 3. Closing the vaults and the offer, and returning the rent to whoever paid for them
+// 4. Recording the refund in the registry, the maker's index and the maker's statistics
+// 5. Emitting an OfferRefunded event
+// Every other instruction signs with the offer's vault authority, so legacy offers can only be refunded
+// Legacy offers are always closed, even if the maker asked to keep their history
+pub fn refund_legacy_offer<'info>(
+    context: Context<'_, '_, 'info, 'info, RefundLegacyOffer<'info>>,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require_offer_open(&offer)?;
+    // Bidders' tokens are locked in the bid vault until the auction is settled
+    require!(offer.highest_bid == 0, ErrorCode::AuctionHasBids);
+    // A taker's tokens are waiting on the arbiter, who has to revert the take first
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+
+    // Offers made since have a vault authority, and its bump is never zero in practice
+    require!(offer.vault_authority_bump == 0, ErrorCode::NotALegacyOffer);
+
+    let offer_key = context.accounts.offer.key();
+    let id_bytes = offer.id.to_le_bytes();
+    let bump = [offer.bump];
+    let current_offer_seeds: &[&[u8]] = &[b"offer", offer.maker.as_ref(), id_bytes.as_ref(), &bump];
+    let old_offer_seeds: &[&[u8]] = &[b"offer", id_bytes.as_ref(), &bump];
+    let offer_seeds = [current_offer_seeds, old_offer_seeds]
+        .into_iter()
+        .find(|seeds| Pubkey::create_program_address(seeds, &crate::ID) == Ok(offer_key))
+        .ok_or(ErrorCode::NotALegacyOffer)?;
+    let signers_seeds = Some(offer_seeds);
+
+    // Delegated offers' tokens never left the maker's account, so there's nothing to return
+    let token_a_refunded_amount = if offer.is_delegated() {
+        // Leave alone any approval the maker has given since
+        if context.accounts.maker_token_account_a.delegate == COption::Some(offer_key) {
+            revoke_delegate(
+                &context.accounts.maker_token_account_a,
+                &context.accounts.maker.to_account_info(),
+                &context.accounts.token_program,
+            )?;
+        }
+        0
+    } else {
+        let token_a_refunded_amount = context.accounts.vault.amount;
+
+        // Return the tokens from the vault to the maker's account
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.maker_token_account_a,
+            &token_a_refunded_amount,
+            &context.accounts.token_mint_a,
+            &context.accounts.offer.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+        // Close the vault and return the rent to whoever paid for it
+        close_token_account(
+            &context.accounts.vault,
+            &context.accounts.rent_payer.to_account_info(),
+            &context.accounts.offer.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedRefundClosure)?;
+
+        token_a_refunded_amount
+    };
+
+    // Return the rest of a bundle
+    withdraw_additional_tokens(
+        context.remaining_accounts,
+        offer.additional_token_mints_a(),
+        &context.accounts.maker.key(),
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.offer.to_account_info(),
+        &context.accounts.token_program,
+        offer_seeds,
+        ErrorCode::FailedRefundTransfer,
+        ErrorCode::FailedRefundClosure,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_refunded(&mut context.accounts.maker_stats)?;
+
+    // Close the offer and return the rent to whoever paid for it
+    context
+        .accounts
+        .offer
+        .close(context.accounts.rent_payer.to_account_info())?;
+
+    emit_event(
+        OfferRefunded {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: offer.token_mint_b,
+            token_a_amount: token_a_refunded_amount,
+            created_at: offer.created_at,
+            refunded_at: Clock::get()?.unix_timestamp,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
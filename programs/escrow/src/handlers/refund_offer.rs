@@ -1,9 +1,20 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use super::shared::{close_token_account, transfer_tokens};
-use crate::{error::ErrorCode, state::Offer};
+use super::{
+    bundle::withdraw_additional_tokens,
+    shared::{
+        close_token_account, record_maker_offer_refunded, record_offers_closed,
+        remove_from_maker_index, require_offer_open, revoke_delegate, transfer_tokens,
+    },
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferRefunded},
+    state::{MakerIndex, MakerStats, Offer, OfferAllowlist, OfferStatus, Registry},
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct RefundOffer<'info> {
     // Work with either the classic token program or
@@ -13,8 +24,23 @@ pub struct RefundOffer<'info> {
     // Used to create accounts
     pub system_program: Program<'info, System>,
 
+    // The maker, the authority that signed for them when the offer was made, or the offer's refund authority
+    // Makers that can't sign transactions, like program owned multisig vaults, refund through that authority
+    #[account(
+        constraint = maker_authority.key() == offer.load()?.maker
+            || maker_authority.key() == offer.load()?.maker_authority
+            || offer.load()?.refund_authority() == Some(maker_authority.key())
+            @ ErrorCode::InvalidMakerAuthority
+    )]
+    pub maker_authority: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, the refunded tokens go back to them
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
     #[account(mut)]
-    pub maker: Signer<'info>,
+    pub rent_payer: UncheckedAccount<'info>,
 
     pub token_mint_a: InterfaceAccount<'info, Mint>,
 
@@ -26,56 +52,186 @@ pub struct RefundOffer<'info> {
     )]
     pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
 
+    // Closed by the handler, unless the maker asked to keep the offer's history
     #[account(
         mut,
-        close = maker,
-        has_one = maker,
-        seeds = [b"offer", offer.id.to_le_bytes().as_ref()],
-        bump = offer.bump
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
     )]
-    pub offer: Account<'info, Offer>,
+    pub offer: AccountLoader<'info, Offer>,
 
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // The offer's vault, or for delegated offers the maker's token a account again
     #[account(
         mut,
         associated_token::mint = token_mint_a,
-        associated_token::authority = offer,
-        associated_token::token_program = token_program,
+        associated_token::authority = offer.load()?.token_a_holder(&vault_authority.key()),
+        associated_token::token_program = token_program
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
 }
 
 // Handle the refund offer instruction by:
 // 1. Returning the tokens from the vault to the maker's account
-// 2. Closing the vault and returning the rent to the maker
-pub fn refund_offer(context: Context<RefundOffer>) -> Result<()> {
-    let offer_account_seeds = &[
-        b"offer",
-        &context.accounts.offer.id.to_le_bytes()[..],
-        &[context.accounts.offer.bump],
+// 2. Closing the vault and returning the rent to whoever paid for it
+// 3. For bundle offers, doing the same for each additional vault
+//    Delegated offers have no vault, their tokens never left the maker, so the maker's approval is revoked instead
+//    The approval can only be revoked when the maker signs, otherwise it's left for the maker to revoke
+// 4. Recording the refund in the registry, the maker's index and the maker's statistics
+//    The offer is closed, or marked refunded, or expired if its expiry had passed, if the maker asked to keep its history
+// 5. Emitting an OfferRefunded event
+pub fn refund_offer<'info>(
+    context: Context<'_, '_, 'info, 'info, RefundOffer<'info>>,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require_offer_open(&offer)?;
+    // Bidders' tokens are locked in the bid vault until the auction is settled
+    require!(offer.highest_bid == 0, ErrorCode::AuctionHasBids);
+    // A taker's tokens are waiting on the arbiter, who has to revert the take first
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
     ];
-    let signers_seeds = Some(&offer_account_seeds[..]);
-
-    // Return the tokens from the vault to the maker's account
-    transfer_tokens(
-        &context.accounts.vault,
-        &context.accounts.maker_token_account_a,
-        &context.accounts.vault.amount,
-        &context.accounts.token_mint_a,
-        &context.accounts.offer.to_account_info(),
-        &context.accounts.token_program,
-        signers_seeds,
-    )
-    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
-
-    // Close the vault and return the rent to the maker
-    close_token_account(
-        &context.accounts.vault,
-        &context.accounts.maker.to_account_info(),
-        &context.accounts.offer.to_account_info(),
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Delegated offers' tokens never left the maker's account, so there's nothing to return
+    let token_a_refunded_amount = if offer.is_delegated() {
+        // Leave alone any approval the maker has given since
+        if context.accounts.maker_authority.key() == offer.maker
+            && context.accounts.maker_token_account_a.delegate
+                == COption::Some(context.accounts.vault_authority.key())
+        {
+            revoke_delegate(
+                &context.accounts.maker_token_account_a,
+                &context.accounts.maker_authority.to_account_info(),
+                &context.accounts.token_program,
+            )?;
+        }
+        0
+    } else {
+        let token_a_refunded_amount = context.accounts.vault.amount;
+
+        // Return the tokens from the vault to the maker's account
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.maker_token_account_a,
+            &token_a_refunded_amount,
+            &context.accounts.token_mint_a,
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+        // Close the vault and return the rent to whoever paid for it
+        close_token_account(
+            &context.accounts.vault,
+            &context.accounts.rent_payer.to_account_info(),
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedRefundClosure)?;
+
+        token_a_refunded_amount
+    };
+
+    // Return the rest of a bundle
+    withdraw_additional_tokens(
+        context.remaining_accounts,
+        offer.additional_token_mints_a(),
+        &context.accounts.maker.key(),
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
         &context.accounts.token_program,
-        signers_seeds,
-    )
-    .map_err(|_| ErrorCode::FailedRefundClosure)?;
+        &vault_authority_seeds[..],
+        ErrorCode::FailedRefundTransfer,
+        ErrorCode::FailedRefundClosure,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_refunded(&mut context.accounts.maker_stats)?;
+
+    // Close the offer and return the rent to whoever paid for it
+    // Offers that keep their history stay open as a record of the refund, until claim_rent closes them
+    let clock = Clock::get()?;
+    if offer.keep_history() {
+        let refunded_after_expiry = offer
+            .expires_at()
+            .is_some_and(|expires_at| clock.unix_timestamp >= expires_at);
+        let status = if refunded_after_expiry {
+            OfferStatus::Expired
+        } else {
+            OfferStatus::Refunded
+        };
+        context.accounts.offer.load_mut()?.set_status(status);
+    } else {
+        context
+            .accounts
+            .offer
+            .close(context.accounts.rent_payer.to_account_info())?;
+    }
+
+    emit_event(
+        OfferRefunded {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: offer.token_mint_b,
+            token_a_amount: token_a_refunded_amount,
+            created_at: offer.created_at,
+            refunded_at: clock.unix_timestamp,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,36 @@
+use crate::{
+    error::ErrorCode,
+    state::{CounterOffer, Offer},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RejectCounterOffer<'info> {
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+
+    #[account(
+        has_one = maker @ ErrorCode::InvalidMaker,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        mut,
+        close = proposer,
+        has_one = offer @ ErrorCode::WrongOffer,
+        has_one = proposer @ ErrorCode::InvalidProposer,
+        seeds = [b"counter_offer", offer.key().as_ref(), proposer.key().as_ref()],
+        bump = counter_offer.bump
+    )]
+    pub counter_offer: Account<'info, CounterOffer>,
+}
+
+// Handle the reject counter offer instruction by closing the counter offer
+// and returning the rent to the proposer. The original offer stays open.
+pub fn reject_counter_offer(_context: Context<RejectCounterOffer>) -> Result<()> {
+    Ok(())
+}
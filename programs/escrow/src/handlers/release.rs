@@ -0,0 +1,302 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed, maker_fee_bps,
+    record_maker_offer_filled, record_maker_volume, record_offers_closed, record_volume_settled,
+    remove_from_maker_index, transfer_tokens,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, PendingSettlement, Registry},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Release<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The offer's arbiter, checked against the pending settlement with has_one
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = arbiter,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = taker,
+        has_one = offer @ ErrorCode::WrongOffer,
+        has_one = taker @ ErrorCode::InvalidTaker,
+        has_one = arbiter @ ErrorCode::ArbiterMismatch,
+        seeds = [b"pending_settlement", offer.key().as_ref()],
+        bump = pending_settlement.bump
+    )]
+    pub pending_settlement: Account<'info, PendingSettlement>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = pending_settlement,
+        associated_token::token_program = token_program,
+    )]
+    pub pending_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = arbiter,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // The taker was checked against it when they took the offer
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the release instruction by:
+// 1. Withdrawing the offered tokens from the vault to the taker, less the protocol fee, and closing the vault
+// 2. Sending the taker's token b from the pending vault to the maker, and closing the pending vault
+// 3. Recording the settlement in the registry, the maker's index and the maker's statistics
+// 4. Emitting an OfferTaken event
+// The vault rent goes back to the offer's rent payer and the pending settlement rent back to the taker, since each paid for their own
+pub fn release(context: Context<Release>) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let vault_authority_signers_seeds = Some(&vault_authority_seeds[..]);
+
+    let protocol_fee = calculate_fee(
+        context.accounts.vault.amount,
+        maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+    )?;
+    let token_a_taker_amount = context.accounts.vault.amount - protocol_fee;
+
+    // Send the protocol fee from the vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            vault_authority_signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Withdraw the rest of the offered tokens from the vault to the taker
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.taker_token_account_a,
+        &token_a_taker_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.arbiter.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // The pending settlement owns the pending vault, so it signs for the transfer to the maker
+    let pending_settlement_seeds = &[
+        b"pending_settlement",
+        context.accounts.pending_settlement.offer.as_ref(),
+        &[context.accounts.pending_settlement.bump],
+    ];
+    let pending_signers_seeds = Some(&pending_settlement_seeds[..]);
+
+    // Send everything in the pending vault, any Token-2022 transfer fee the taker paid on the way in is already covered
+    let token_b_amount = context.accounts.pending_vault.amount;
+    transfer_tokens(
+        &context.accounts.pending_vault,
+        &context.accounts.maker_token_account_b,
+        &token_b_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.pending_settlement.to_account_info(),
+        &context.accounts.token_program,
+        pending_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    close_token_account(
+        &context.accounts.pending_vault,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.pending_settlement.to_account_info(),
+        &context.accounts.token_program,
+        pending_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        context.accounts.vault.amount,
+        token_b_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_amount)?;
+
+    let clock = Clock::get()?;
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_taker_amount,
+            token_b_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
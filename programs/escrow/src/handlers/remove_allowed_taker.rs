@@ -0,0 +1,38 @@
+use crate::{
+    error::ErrorCode,
+    state::{Offer, OfferAllowlist},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RemoveAllowedTaker<'info> {
+    pub maker: Signer<'info>,
+
+    #[account(
+        has_one = maker @ ErrorCode::InvalidMaker,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = offer @ ErrorCode::WrongOffer,
+        seeds = [b"allowlist", offer.key().as_ref()],
+        bump = allowlist.bump
+    )]
+    pub allowlist: Account<'info, OfferAllowlist>,
+}
+
+// Handle the remove allowed taker instruction by removing the taker from the allowlist
+// The allowlist stays enabled, so removing the last taker means nobody can take the offer
+pub fn remove_allowed_taker(context: Context<RemoveAllowedTaker>, taker: Pubkey) -> Result<()> {
+    let allowlist = &mut context.accounts.allowlist;
+    let position = allowlist
+        .takers
+        .iter()
+        .position(|allowed_taker| *allowed_taker == taker)
+        .ok_or(ErrorCode::TakerNotAllowed)?;
+    allowlist.takers.swap_remove(position);
+    Ok(())
+}
@@ -0,0 +1,423 @@
+use super::shared::{
+    add_to_maker_index, close_token_account, get_token_program_b, record_maker_offer_made,
+    record_maker_offer_refunded, record_offer_made, record_offers_closed, remove_from_maker_index,
+    require_can_send, require_mint_allowed, require_mint_allowlisted, require_mint_not_denied,
+    require_no_transfer_hook, require_offer_amount_within_limits, require_offer_open,
+    transfer_tokens,
+};
+use crate::{
+    constants::{AUTO_ASSIGN_OFFER_ID, OFFER_VERSION},
+    error::ErrorCode,
+    events::{emit_event, OfferMade, OfferRefunded},
+    state::{
+        Config, MakerCounter, MakerIndex, MakerStats, MintAllowlistEntry, Offer, OfferAllowlist,
+        OfferStatus, Registry,
+    },
+};
+use anchor_lang::{prelude::*, solana_program::program::set_return_data};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(new_id: u64)]
+pub struct ReplaceOffer<'info> {
+    // Used to create the new offer's vault
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program, whichever owns token a
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The maker signs for the tokens moving from the old offer to the new one, and pays for the new offer
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: Checked against the old offer's rent payer with has_one, who gets the rent for the old offer and its vault back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Both offers are for the same token a, so the refunded tokens can be offered again
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    // What the new offer wants, which can differ from what the old offer wanted
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Refunded, and closed unless the maker asked to keep its history
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the old offer's vault
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // The old offer's vault, or for delegated offers the maker's token a account again, which the handler rejects
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer.load()?.token_a_holder(&vault_authority.key()),
+        associated_token::token_program = token_program
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Hands out the maker's offer ids, created with their first offer if they made it before the counter existed
+    // Comes before the new offer, since the new offer's address uses the id it assigns
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = MakerCounter::DISCRIMINATOR.len() + MakerCounter::INIT_SPACE,
+        seeds = [b"maker_counter", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_counter: Box<Account<'info, MakerCounter>>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Offer::SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_counter.resolve_offer_id(new_id).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub new_offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the new offer's vault
+    #[account(
+        seeds = [b"vault_authority", new_offer.key().as_ref()],
+        bump
+    )]
+    pub new_vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = new_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub new_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Box<Account<'info, MakerIndex>>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Box<Account<'info, MakerStats>>,
+
+    // Only needed if the maker has enabled an allowlist for the old offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+}
+
+// Handle the replace offer instruction by:
+// 1. Refunding the old offer like refund_offer: returning its tokens to the maker's ATA and closing its vault,
+//    recording the refund, and closing the offer, or marking it refunded if the maker asked to keep its history
+// 2. Making the new offer like make_offer: moving the offered tokens from the maker's ATA to the new vault,
+//    saving its terms with the maker's next id if passed AUTO_ASSIGN_OFFER_ID, and recording it
+// 3. Emitting an OfferRefunded event for the old offer and an OfferMade event for the new one, and returning its id
+// Both happen in one instruction, so the maker is never left without a live offer between two transactions
+// The new offer can offer more or less than the old one's vault held, the maker's ATA makes up the difference
+// The new offer has a fixed price, makers who want auctions or other options refund and make the offer separately
+pub fn replace_offer(
+    context: Context<ReplaceOffer>,
+    new_id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+
+    let offer = *context.accounts.offer.load()?;
+    require_offer_open(&offer)?;
+    // Bidders' tokens are locked in the bid vault until the auction is settled
+    require!(offer.highest_bid == 0, ErrorCode::AuctionHasBids);
+    // A taker's tokens are waiting on the arbiter, who has to revert the take first
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+    // Bundles have more vaults than the one refunded here, and delegated offers have no vault at all
+    require!(
+        offer.additional_token_mints_a().is_empty() && !offer.is_delegated(),
+        ErrorCode::ReplaceNotSupported
+    );
+
+    // Validate the new offer before any tokens move, so bad offers fail with a clear error
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidOfferedAmount);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    require_offer_amount_within_limits(token_a_offered_amount, &context.accounts.config)?;
+    require_keys_neq!(
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
+        ErrorCode::InvalidTokenMint
+    );
+    let clock = Clock::get()?;
+    if let Some(expires_at) = expires_at {
+        require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpiry);
+    }
+
+    // Token b can belong to the other token program, which the taker then passes as token_program_b
+    get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Both sides of the trade must be transferable without extra hook accounts
+    require_no_transfer_hook(&context.accounts.token_mint_a.to_account_info())?;
+    require_no_transfer_hook(&context.accounts.token_mint_b.to_account_info())?;
+    require_mint_allowed(
+        &context.accounts.token_mint_a,
+        &context.accounts.config.mint_policy,
+    )?;
+    require_mint_allowed(
+        &context.accounts.token_mint_b,
+        &context.accounts.config.mint_policy,
+    )?;
+
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Return the tokens from the old offer's vault to the maker's account
+    let token_a_refunded_amount = context.accounts.vault.amount;
+    transfer_tokens(
+        &context.accounts.vault,
+        &*context.accounts.maker_token_account_a,
+        &token_a_refunded_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+    // Close the old vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundClosure)?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_refunded(&mut context.accounts.maker_stats)?;
+
+    // Close the old offer and return the rent to whoever paid for it
+    // Offers that keep their history stay open as a record of the refund, until claim_rent closes them
+    if offer.keep_history() {
+        let refunded_after_expiry = offer
+            .expires_at()
+            .is_some_and(|expires_at| clock.unix_timestamp >= expires_at);
+        let status = if refunded_after_expiry {
+            OfferStatus::Expired
+        } else {
+            OfferStatus::Refunded
+        };
+        context.accounts.offer.load_mut()?.set_status(status);
+    } else {
+        context
+            .accounts
+            .offer
+            .close(context.accounts.rent_payer.to_account_info())?;
+    }
+
+    emit_event(
+        OfferRefunded {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: offer.token_mint_b,
+            token_a_amount: token_a_refunded_amount,
+            created_at: offer.created_at,
+            refunded_at: clock.unix_timestamp,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    // Move the tokens for the new offer from the maker's ATA, which now includes the refund, to the new vault
+    context.accounts.maker_token_account_a.reload()?;
+    require_can_send(
+        &context.accounts.maker_token_account_a,
+        token_a_offered_amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+    transfer_tokens(
+        &context.accounts.maker_token_account_a,
+        &*context.accounts.new_vault,
+        &token_a_offered_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+    )?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so record what the vault actually holds
+    context.accounts.new_vault.reload()?;
+    let token_a_received_amount = context.accounts.new_vault.amount;
+    require!(
+        token_a_received_amount > 0,
+        ErrorCode::TransferFeeExceedsAmount
+    );
+
+    // Take the maker's next id if they asked for one, the new offer's address was derived from it
+    let maker_counter = &mut context.accounts.maker_counter;
+    maker_counter.maker = context.accounts.maker.key();
+    maker_counter.bump = context.bumps.maker_counter;
+    let new_id = if new_id == AUTO_ASSIGN_OFFER_ID {
+        let assigned_id = maker_counter.next_id;
+        maker_counter.next_id = assigned_id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        assigned_id
+    } else {
+        new_id
+    };
+
+    // Save the details of the new offer to the new offer account
+    // The account starts zeroed, so every optional value starts out unset
+    let mut new_offer = context.accounts.new_offer.load_init()?;
+    new_offer.id = new_id;
+    new_offer.maker = context.accounts.maker.key();
+    new_offer.token_mint_a = context.accounts.token_mint_a.key();
+    new_offer.token_mint_b = context.accounts.token_mint_b.key();
+    new_offer.token_b_wanted_amount = token_b_wanted_amount;
+    new_offer.token_a_offered_amount = token_a_received_amount;
+    new_offer.set_price(token_b_wanted_amount, token_a_received_amount);
+    new_offer.bump = context.bumps.new_offer;
+    new_offer.vault_authority_bump = context.bumps.new_vault_authority;
+    new_offer.version = OFFER_VERSION;
+    new_offer.created_at = clock.unix_timestamp;
+    new_offer.created_slot = clock.slot;
+    new_offer.set_expires_at(expires_at);
+    new_offer.set_keep_history(offer.keep_history());
+    new_offer.tags = offer.tags;
+    new_offer.maker_authority = context.accounts.maker.key();
+    new_offer.rent_payer = context.accounts.maker.key();
+
+    record_offer_made(&mut context.accounts.registry)?;
+    add_to_maker_index(
+        &mut context.accounts.maker_index,
+        new_id,
+        &context.accounts.config,
+    )?;
+    record_maker_offer_made(&mut context.accounts.maker_stats)?;
+
+    emit_event(
+        OfferMade {
+            offer_id: new_id,
+            maker: context.accounts.maker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_offered_amount: token_a_received_amount,
+            token_b_wanted_amount,
+            created_at: clock.unix_timestamp,
+            tags: new_offer.tags,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    // Callers who had the id assigned can read it from here rather than from the event
+    // Set last, since the event's self-CPI would clear it
+    set_return_data(&new_id.to_le_bytes());
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use super::shared::{
+    require_mint_allowed, require_mint_allowlisted, require_mint_not_denied,
+    require_no_transfer_hook, require_offer_amount_within_limits,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, MintAllowlistEntry, QuoteRequest},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct RequestQuote<'info> {
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    // What the requester wants to buy
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // What the requester will pay with, which can belong to the other token program from token a
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = requester,
+        space = QuoteRequest::DISCRIMINATOR.len() + QuoteRequest::INIT_SPACE,
+        seeds = [b"quote_request", requester.key().as_ref(), id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub quote_request: Account<'info, QuoteRequest>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+}
+
+// Handle the request quote instruction by:
+// 1. Checking the mints and amount are ones an offer could be made for, and the expiry is in the future
+// 2. Saving the request to the quote request account, for makers to answer with submit_quote
+// Nothing is escrowed until a quote is accepted, the requester only pays with accept_quote
+pub fn request_quote(
+    context: Context<RequestQuote>,
+    id: u64,
+    token_a_wanted_amount: u64,
+    expires_at: i64,
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_a_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    require_offer_amount_within_limits(token_a_wanted_amount, &context.accounts.config)?;
+    require_keys_neq!(
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
+        ErrorCode::InvalidTokenMint
+    );
+    let clock = Clock::get()?;
+    require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpiry);
+
+    // The same mints an offer could be made for, since each quote is settled like one
+    require_no_transfer_hook(&context.accounts.token_mint_a.to_account_info())?;
+    require_no_transfer_hook(&context.accounts.token_mint_b.to_account_info())?;
+    require_mint_allowed(
+        &context.accounts.token_mint_a,
+        &context.accounts.config.mint_policy,
+    )?;
+    require_mint_allowed(
+        &context.accounts.token_mint_b,
+        &context.accounts.config.mint_policy,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    context.accounts.quote_request.set_inner(QuoteRequest {
+        requester: context.accounts.requester.key(),
+        id,
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_a_wanted_amount,
+        expires_at,
+        bump: context.bumps.quote_request,
+    });
+    Ok(())
+}
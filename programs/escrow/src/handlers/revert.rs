@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use super::shared::{close_token_account, transfer_tokens};
+use crate::{
+    error::ErrorCode,
+    state::{Offer, PendingSettlement},
+};
+
+#[derive(Accounts)]
+pub struct Revert<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // The offer's arbiter, checked against the pending settlement with has_one
+    pub arbiter: Signer<'info>,
+
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    #[account(
+        mut,
+        close = taker,
+        has_one = offer @ ErrorCode::WrongOffer,
+        has_one = taker @ ErrorCode::InvalidTaker,
+        has_one = arbiter @ ErrorCode::ArbiterMismatch,
+        seeds = [b"pending_settlement", offer.key().as_ref()],
+        bump = pending_settlement.bump
+    )]
+    pub pending_settlement: Account<'info, PendingSettlement>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = pending_settlement,
+        associated_token::token_program = token_program,
+    )]
+    pub pending_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the revert instruction by:
+// 1. Returning the tokens in the pending vault to the taker
+// 2. Closing the pending vault and pending settlement, returning the rent to the taker
+// 3. Marking the offer as no longer waiting on its arbiter, so it can be taken or refunded again
+pub fn revert(context: Context<Revert>) -> Result<()> {
+    // The pending settlement owns the pending vault, so it signs for the return
+    let pending_settlement_seeds = &[
+        b"pending_settlement",
+        context.accounts.pending_settlement.offer.as_ref(),
+        &[context.accounts.pending_settlement.bump],
+    ];
+    let signers_seeds = Some(&pending_settlement_seeds[..]);
+
+    transfer_tokens(
+        &context.accounts.pending_vault,
+        &context.accounts.taker_token_account_b,
+        &context.accounts.pending_vault.amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.pending_settlement.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    close_token_account(
+        &context.accounts.pending_vault,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.pending_settlement.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    context.accounts.offer.load_mut()?.set_arbitration_pending(false);
+
+    Ok(())
+}
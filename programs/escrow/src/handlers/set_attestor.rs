@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use super::{shared::require_offer_open, update_offer::UpdateOffer};
+use crate::error::ErrorCode;
+
+// Handle the set attestor instruction by:
+// 1. Checking take_offer can settle the offer, since it's the only way to take an attested offer
+// 2. Saving the key that must sign the offer's address before it can be taken, or clearing it if none was provided
+// The attestor is usually an oracle or a neutral party confirming something off-chain, like a delivery,
+// and signs the offer's address once it has, which the taker verifies with the ed25519 program before take_offer
+pub fn set_attestor(context: Context<UpdateOffer>, attestor: Option<Pubkey>) -> Result<()> {
+    let mut offer = context.accounts.offer.load_mut()?;
+    require_offer_open(&offer)?;
+
+    if attestor.is_some() {
+        // Auctions, vesting, arbitration and installments are settled by their own instructions
+        require!(
+            offer.english_auction().is_none()
+                && offer.vesting_duration().is_none()
+                && offer.arbiter().is_none()
+                && offer.installment_terms().is_none(),
+            ErrorCode::AttestorNotSupported
+        );
+    }
+
+    offer.set_attestor(attestor);
+
+    Ok(())
+}
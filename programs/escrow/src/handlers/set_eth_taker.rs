@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use super::{shared::require_offer_open, update_offer::UpdateOffer};
+use crate::{constants::ETH_ADDRESS_LENGTH, error::ErrorCode};
+
+// Handle the set eth taker instruction by:
+// 1. Checking take_offer can settle the offer, since it's the only way to take an offer limited to an Ethereum taker
+// 2. Saving the Ethereum address that has to sign for the taker, or clearing it if none was provided
+// This lets a counterparty who only holds an EVM key, like a cross-chain OTC desk, authorize the Solana settlement:
+// they sign the offer's address followed by the taker's with secp256k1, which the taker verifies with the
+// secp256k1 program before take_offer
+pub fn set_eth_taker(
+    context: Context<UpdateOffer>,
+    eth_taker: Option<[u8; ETH_ADDRESS_LENGTH]>,
+) -> Result<()> {
+    let mut offer = context.accounts.offer.load_mut()?;
+    require_offer_open(&offer)?;
+
+    if eth_taker.is_some() {
+        // Auctions, vesting, arbitration and installments are settled by their own instructions
+        require!(
+            offer.english_auction().is_none()
+                && offer.vesting_duration().is_none()
+                && offer.arbiter().is_none()
+                && offer.installment_terms().is_none(),
+            ErrorCode::EthTakerNotSupported
+        );
+    }
+
+    offer.set_eth_taker(eth_taker);
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use super::{shared::require_offer_open, update_offer::UpdateOffer};
+use crate::{error::ErrorCode, state::InstallmentTerms};
+
+// Handle the set installment terms instruction by:
+// 1. Checking the offer can be settled in installments
+// 2. Saving the payment schedule the maker accepts, or clearing it if none was provided
+// Takers can then pay for the offer over time with take_offer_in_installments, rather than all up front
+pub fn set_installment_terms(
+    context: Context<UpdateOffer>,
+    installment_terms: Option<InstallmentTerms>,
+) -> Result<()> {
+    let mut offer = context.accounts.offer.load_mut()?;
+    require_offer_open(&offer)?;
+
+    if let Some(installment_terms) = installment_terms {
+        require!(
+            installment_terms.max_installments >= 2 && installment_terms.interval > 0,
+            ErrorCode::InvalidInstallmentTerms
+        );
+        // Auctions, vesting and arbitration settle the offer their own way,
+        // hashlocks, attestors and Ethereum takers need take_offer,
+        // and bundles and delegated offers don't have a single vault to release token a from
+        require!(
+            offer.english_auction().is_none()
+                && offer.vesting_duration().is_none()
+                && offer.arbiter().is_none()
+                && offer.hashlock().is_none()
+                && offer.attestor().is_none()
+                && offer.eth_taker().is_none()
+                && offer.additional_token_mints_a().is_empty()
+                && !offer.is_delegated(),
+            ErrorCode::InstallmentsNotSupported
+        );
+    }
+
+    offer.set_installment_terms(installment_terms);
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use super::update_offer::UpdateOffer;
+
+// Handle the set refund authority instruction by:
+// 1. Saving the key allowed to refund the offer, or clearing it if none was provided
+// The refund authority can only send the tokens back to the maker, it can't reprice or extend the offer,
+// so desks can keep their treasury key offline and let a bot key pull offers
+pub fn set_refund_authority(
+    context: Context<UpdateOffer>,
+    refund_authority: Option<Pubkey>,
+) -> Result<()> {
+    context
+        .accounts
+        .offer
+        .load_mut()?
+        .set_refund_authority(refund_authority);
+
+    Ok(())
+}
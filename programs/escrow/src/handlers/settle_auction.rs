@@ -0,0 +1,236 @@
+use super::shared::{
+    close_token_account, record_maker_offer_filled, record_maker_volume, record_offers_closed,
+    record_volume_settled, remove_from_maker_index, transfer_tokens,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Bid, Config, MakerIndex, MakerStats, Offer, Registry},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // The maker can settle at any time, anyone else only after the auction ends
+    #[account(mut)]
+    pub settler: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    pub bidder: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub rent_payer: SystemAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer, who gets the rent for the offer and its vault back
+    /// The bid has its own rent payer, so this can't be checked with has_one
+    #[account(
+        mut,
+        address = offer.load()?.rent_payer @ ErrorCode::InvalidRentPayer
+    )]
+    pub offer_rent_payer: UncheckedAccount<'info>,
+
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = settler,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = bidder,
+        associated_token::token_program = token_program,
+    )]
+    pub bidder_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = settler,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = offer_rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = offer @ ErrorCode::WrongOffer,
+        has_one = bidder @ ErrorCode::BidderMismatch,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        seeds = [b"bid", offer.key().as_ref()],
+        bump = bid.bump
+    )]
+    pub bid: Box<Account<'info, Bid>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = bid,
+        associated_token::token_program = token_program,
+    )]
+    pub bid_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Box<Account<'info, MakerIndex>>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Box<Account<'info, MakerStats>>,
+
+    // Read for the mint the maker's fee tier volume is counted in
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+// Handle the settle auction instruction by:
+// 1. Withdrawing the offered tokens from the vault to the highest bidder and closing the vault
+// 2. Sending the highest bid from the bid vault to the maker and closing the bid vault
+// 3. Recording the settlement in the registry, the maker's index and the maker's statistics
+pub fn settle_auction(context: Context<SettleAuction>) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    let english_auction = offer.english_auction().ok_or(ErrorCode::NotAnAuction)?;
+    require!(offer.highest_bid > 0, ErrorCode::AuctionHasNoBids);
+
+    // Only the maker may accept the highest bid before the auction ends
+    if context.accounts.settler.key() != context.accounts.maker.key() {
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= english_auction.end_timestamp,
+            ErrorCode::AuctionNotEnded
+        );
+    }
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let vault_authority_signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Withdraw the offered tokens from the vault to the highest bidder
+    transfer_tokens(
+        &context.accounts.vault,
+        &*context.accounts.bidder_token_account_a,
+        &context.accounts.vault.amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.offer_rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        vault_authority_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    let bid_account_seeds = &[b"bid", offer_key.as_ref(), &[context.accounts.bid.bump]];
+    let bid_signers_seeds = Some(&bid_account_seeds[..]);
+
+    // Send the highest bid from the bid vault to the maker
+    let token_b_amount = context.accounts.bid_vault.amount;
+    transfer_tokens(
+        &context.accounts.bid_vault,
+        &*context.accounts.maker_token_account_b,
+        &token_b_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.bid.to_account_info(),
+        &context.accounts.token_program,
+        bid_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Close the bid vault and return the rent to whoever placed the first bid
+    close_token_account(
+        &context.accounts.bid_vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.bid.to_account_info(),
+        &context.accounts.token_program,
+        bid_signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        context.accounts.vault.amount,
+        token_b_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_amount)?;
+
+    Ok(())
+}
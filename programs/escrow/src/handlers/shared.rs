@@ -1,21 +1,709 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        ed25519_program,
+        hash::hash,
+        pubkey::PUBKEY_BYTES,
+        secp256k1_program,
+        stake::state::StakeAuthorize,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+};
+
+use anchor_spl::{
+    associated_token::{create, AssociatedToken, Create},
+    stake::{authorize, Authorize, Stake},
+    token_2022::{
+        spl_token_2022::{
+            extension::{
+                non_transferable::NonTransferable, permanent_delegate::PermanentDelegate,
+                transfer_fee::TransferFeeConfig, transfer_hook::TransferHook,
+                BaseStateWithExtensions, StateWithExtensions,
+            },
+            state::Mint as MintState,
+        },
+        Token2022,
+    },
+    token_interface::{
+        approve, close_account, revoke, transfer_checked, Approve, CloseAccount, Mint, Revoke,
+        TokenAccount, TokenInterface, TransferChecked,
+    },
+};
 
-use anchor_spl::token_interface::{
-    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-    TransferChecked,
+use crate::{
+    constants::{BASIS_POINTS_DENOMINATOR, ETH_ADDRESS_LENGTH, MAX_MAKER_OPEN_OFFERS},
+    error::ErrorCode,
+    math::{elapsed_and_duration, mul_div, Rounding},
+    state::{
+        Config, DutchAuction, InstallmentPlan, MakerIndex, MakerStats, MintAllowlistEntry,
+        MintPolicy, Offer, OfferStatus, Registry, Vesting,
+    },
 };
 
+// A token account's balance comes after its mint and owner
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+// Only take_offer pays an NFT's creators their royalty
+// While the config requires royalties, every other way of settling an NFT offer is refused
+pub fn require_royalties_paid_by_take_offer(offer: &Offer, config: &Config) -> Result<()> {
+    require!(
+        !(offer.is_nft() && config.royalties_required),
+        ErrorCode::RoyaltiesRequired
+    );
+    Ok(())
+}
+
+// Offers with an expiry can't be settled once it has passed
+// Handlers read the clock once and pass its timestamp around, rather than each helper fetching the sysvar
+pub fn require_offer_not_expired(offer: &Offer, now: i64) -> Result<()> {
+    if let Some(expires_at) = offer.expires_at() {
+        require!(now < expires_at, ErrorCode::OfferExpired);
+    }
+    Ok(())
+}
+
+// Offers can't be taken until they've existed for the config's min_offer_age_slots, so makers can refund a mispriced offer
+// before bots fill it
+// Offers made before their slot was recorded read as made in slot 0, so they're always old enough
+pub fn require_offer_old_enough(offer: &Offer, config: &Config, slot: u64) -> Result<()> {
+    require!(
+        slot >= offer.created_slot.saturating_add(config.min_offer_age_slots),
+        ErrorCode::OfferTooNew
+    );
+    Ok(())
+}
+
+// Offers that keep their history stay open once settled or refunded, but can't be changed or settled again
+pub fn require_offer_open(offer: &Offer) -> Result<()> {
+    require!(offer.status() == OfferStatus::Open, ErrorCode::OfferNotOpen);
+    Ok(())
+}
+
+// Hashlocked offers can only be taken by revealing a preimage whose SHA-256 is the hashlock
+pub fn require_valid_preimage(offer: &Offer, preimage: Option<&[u8]>) -> Result<()> {
+    if let Some(hashlock) = offer.hashlock() {
+        let preimage = preimage.ok_or(ErrorCode::InvalidPreimage)?;
+        require!(
+            hash(preimage).to_bytes() == hashlock,
+            ErrorCode::InvalidPreimage
+        );
+    }
+    Ok(())
+}
+
+// Attested offers can only be taken by a transaction that has the ed25519 program verify the attestor's signature
+// over the offer and its taker before taking it, which the handler finds by reading the instructions sysvar
+// The offer is its address, the slot it was made in, its token mints and amounts, so an attestation can't be used on
+// a later offer made at the same address, or on this one after the maker changes its terms
+// Signing the taker means the attestation only lets the party the attestor vouched for take the offer
+// The ed25519 program fails the whole transaction if the signature is wrong, so finding the instruction is enough
+pub fn require_attestation(
+    offer: &Offer,
+    offer_key: &Pubkey,
+    taker: &Pubkey,
+    instructions_sysvar: Option<&AccountInfo>,
+) -> Result<()> {
+    let Some(attestor) = offer.attestor() else {
+        return Ok(());
+    };
+    let instructions_sysvar = instructions_sysvar.ok_or(ErrorCode::AttestationRequired)?;
+    let message = [
+        offer_key.as_ref(),
+        &offer.created_slot.to_le_bytes(),
+        taker.as_ref(),
+        offer.token_mint_a.as_ref(),
+        offer.token_mint_b.as_ref(),
+        &offer.token_a_offered_amount.to_le_bytes(),
+        &offer.token_b_wanted_amount.to_le_bytes(),
+    ]
+    .concat();
+    require!(
+        has_ed25519_signature(instructions_sysvar, &attestor, &message)?,
+        ErrorCode::InvalidAttestation
+    );
+    Ok(())
+}
+
+// Gasless takes are authorized by the taker signing the offer and the terms they accept, which a relayer
+// then submits, see take_offer_with_authorization
+// The offer is its address, its token mints and the slot it was made in, so an authorization can't be used on a later
+// offer made at the same address once this one closes
+// The terms are the token a and token b amounts take_offer_with_authorization checks against and when the
+// authorization expires, each little endian, so a relayer can't settle on other terms or hold on to it indefinitely
+pub fn require_taker_authorization(
+    instructions_sysvar: &AccountInfo,
+    taker: &Pubkey,
+    offer_key: &Pubkey,
+    offer: &Offer,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    authorization_expires_at: i64,
+) -> Result<()> {
+    let message = [
+        offer_key.as_ref(),
+        offer.token_mint_a.as_ref(),
+        offer.token_mint_b.as_ref(),
+        &offer.created_slot.to_le_bytes(),
+        &expected_token_a_amount.to_le_bytes(),
+        &expected_token_b_amount.to_le_bytes(),
+        &authorization_expires_at.to_le_bytes(),
+    ]
+    .concat();
+    require!(
+        has_ed25519_signature(instructions_sysvar, taker, &message)?,
+        ErrorCode::InvalidTakerAuthorization
+    );
+    Ok(())
+}
+
+// Whether an instruction before this one has the ed25519 program verify signer's signature over message
+fn has_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<bool> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id == ed25519_program::ID
+            && verifies_ed25519_signature(&instruction.data, signer, message)
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// The ed25519 program's instruction data is a signature count and a padding byte, then for each signature
+// the offsets of its signature, public key and message, each with the index of the instruction holding it
+const ED25519_SIGNATURE_OFFSETS_START: usize = 2;
+const ED25519_SIGNATURE_OFFSETS_SIZE: usize = 14;
+
+// Whether an ed25519 program instruction verifies a signature by signer over message
+fn verifies_ed25519_signature(instruction_data: &[u8], signer: &Pubkey, message: &[u8]) -> bool {
+    let Some(&signature_count) = instruction_data.first() else {
+        return false;
+    };
+    (0..signature_count as usize).any(|index| {
+        let start = ED25519_SIGNATURE_OFFSETS_START + index * ED25519_SIGNATURE_OFFSETS_SIZE;
+        let end = start + ED25519_SIGNATURE_OFFSETS_SIZE;
+        let Some(offsets) = instruction_data.get(start..end) else {
+            return false;
+        };
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+        // The public key and message have to be in the ed25519 instruction itself, marked by u16::MAX,
+        // otherwise they could point at any bytes in the transaction
+        if read_u16(6) != u16::MAX || read_u16(12) != u16::MAX {
+            return false;
+        }
+        let public_key_offset = read_u16(4) as usize;
+        let message_offset = read_u16(8) as usize;
+        let message_size = read_u16(10) as usize;
+        instruction_data.get(public_key_offset..public_key_offset + PUBKEY_BYTES)
+            == Some(signer.as_ref())
+            && instruction_data.get(message_offset..message_offset + message_size) == Some(message)
+    })
+}
+
+// Offers limited to an Ethereum taker can only be taken by a transaction that has the secp256k1 program verify
+// a signature from that Ethereum address over the offer's address followed by the taker's, before taking it
+// Signing the taker's address is what lets the Ethereum key choose who on Solana takes the offer
+// Like require_attestation, the secp256k1 program fails the whole transaction if the signature is wrong
+pub fn require_eth_taker_signature(
+    offer: &Offer,
+    offer_key: &Pubkey,
+    taker: &Pubkey,
+    instructions_sysvar: Option<&AccountInfo>,
+) -> Result<()> {
+    let Some(eth_taker) = offer.eth_taker() else {
+        return Ok(());
+    };
+    let instructions_sysvar = instructions_sysvar.ok_or(ErrorCode::EthSignatureRequired)?;
+
+    let message = [offer_key.as_ref(), taker.as_ref()].concat();
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id == secp256k1_program::ID
+            && verifies_secp256k1_signature(&instruction.data, index, &eth_taker, &message)
+        {
+            return Ok(());
+        }
+    }
+    err!(ErrorCode::InvalidEthSignature)
+}
+
+// The secp256k1 program's instruction data is a signature count, then for each signature the offsets of its
+// signature, Ethereum address and message, each but the message size followed by the index of the instruction holding it
+const SECP256K1_SIGNATURE_OFFSETS_START: usize = 1;
+const SECP256K1_SIGNATURE_OFFSETS_SIZE: usize = 11;
+
+// Whether the secp256k1 program instruction at instruction_index verifies a signature by eth_address over message
+fn verifies_secp256k1_signature(
+    instruction_data: &[u8],
+    instruction_index: u16,
+    eth_address: &[u8; ETH_ADDRESS_LENGTH],
+    message: &[u8],
+) -> bool {
+    let Some(&signature_count) = instruction_data.first() else {
+        return false;
+    };
+    (0..signature_count as usize).any(|index| {
+        let start = SECP256K1_SIGNATURE_OFFSETS_START + index * SECP256K1_SIGNATURE_OFFSETS_SIZE;
+        let end = start + SECP256K1_SIGNATURE_OFFSETS_SIZE;
+        let Some(offsets) = instruction_data.get(start..end) else {
+            return false;
+        };
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+        // Unlike the ed25519 program's, these indexes are always absolute, so the Ethereum address and message
+        // have to point at the secp256k1 instruction itself, otherwise they could point at any bytes in the transaction
+        if offsets[5] as u16 != instruction_index || offsets[10] as u16 != instruction_index {
+            return false;
+        }
+        let eth_address_offset = read_u16(3) as usize;
+        let message_offset = read_u16(6) as usize;
+        let message_size = read_u16(8) as usize;
+        instruction_data.get(eth_address_offset..eth_address_offset + ETH_ADDRESS_LENGTH)
+            == Some(&eth_address[..])
+            && instruction_data.get(message_offset..message_offset + message_size) == Some(message)
+    })
+}
+
+// Linearly release a vesting's tokens between its start and end timestamps
+pub fn get_vested_amount(vesting: &Vesting, now: i64) -> Result<u64> {
+    if now <= vesting.start_timestamp {
+        return Ok(0);
+    }
+    if now >= vesting.end_timestamp {
+        return Ok(vesting.total_amount);
+    }
+
+    let (elapsed, duration) =
+        elapsed_and_duration(vesting.start_timestamp, vesting.end_timestamp, now)?;
+
+    // Round down, so tokens never vest faster than the schedule
+    mul_div(vesting.total_amount, elapsed, duration, Rounding::Down)
+}
+
+// The token a released and token b owed by the next installment of a plan
+// Each installment is an equal share of the totals, token a rounded down and token b rounded up so the taker
+// never gets ahead of what they've paid, and the last installment settles whatever is left
+pub fn get_next_installment(plan: &InstallmentPlan) -> Result<(u64, u64)> {
+    let installments_paid = plan
+        .installments_paid
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+    let installment_count = plan.installment_count as u64;
+
+    let token_a_released = mul_div(
+        plan.token_a_amount,
+        installments_paid,
+        installment_count,
+        Rounding::Down,
+    )?;
+    let token_b_paid = mul_div(
+        plan.token_b_amount,
+        installments_paid,
+        installment_count,
+        Rounding::Up,
+    )?;
+
+    Ok((
+        token_a_released
+            .checked_sub(plan.token_a_released)
+            .ok_or(ErrorCode::MathOverflow)?,
+        token_b_paid
+            .checked_sub(plan.token_b_paid)
+            .ok_or(ErrorCode::MathOverflow)?,
+    ))
+}
+
+// When the next installment of a plan is due, each one is due an interval after the one before
+// The first installment is paid when the offer is taken
+pub fn get_next_installment_due(plan: &InstallmentPlan) -> Result<i64> {
+    let elapsed = plan
+        .installment_interval
+        .checked_mul(plan.installments_paid as i64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    plan.start_timestamp
+        .checked_add(elapsed)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
+// The total amount of token b the taker must pay right now
+// Fixed price offers always want token_b_wanted_amount, Dutch auctions follow their schedule
+pub fn get_token_b_wanted_amount(offer: &Offer, now: i64) -> Result<u64> {
+    match offer.dutch_auction() {
+        Some(dutch_auction) => get_dutch_auction_price(&dutch_auction, now),
+        None => Ok(offer.token_b_wanted_amount),
+    }
+}
+
+// Linearly interpolate between the start and end price of a Dutch auction
+pub fn get_dutch_auction_price(dutch_auction: &DutchAuction, now: i64) -> Result<u64> {
+    if now <= dutch_auction.start_timestamp {
+        return Ok(dutch_auction.start_price);
+    }
+    if now >= dutch_auction.end_timestamp {
+        return Ok(dutch_auction.end_price);
+    }
+
+    // make_offer checks start_price >= end_price and start_timestamp < end_timestamp
+    let price_drop = dutch_auction
+        .start_price
+        .checked_sub(dutch_auction.end_price)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let (elapsed, duration) = elapsed_and_duration(
+        dutch_auction.start_timestamp,
+        dutch_auction.end_timestamp,
+        now,
+    )?;
+
+    // Round the drop down, so the price never falls faster than the maker's schedule
+    let current_drop = mul_div(price_drop, elapsed, duration, Rounding::Down)?;
+
+    // The drop is at most price_drop, since elapsed < duration
+    Ok(dutch_auction.start_price - current_drop)
+}
+
+// The protocol fee on amount, rounded down so takers are never overcharged
+pub fn calculate_fee(amount: u64, fee_bps: u16) -> Result<u64> {
+    mul_div(amount, fee_bps as u64, BASIS_POINTS_DENOMINATOR, Rounding::Down)
+}
+
+// The protocol fee for a maker's offers, which the config's fee tiers lower as the maker's settled volume grows
+// Tiers are sorted by volume, so the last one the maker has reached applies
+// Only volume in the config's current fee tier mint counts
+pub fn maker_fee_bps(config: &Config, maker_stats: &MakerStats) -> u16 {
+    let fee_tier_volume = if maker_stats.fee_tier_mint == config.fee_tier_mint {
+        maker_stats.fee_tier_volume
+    } else {
+        0
+    };
+    config
+        .fee_tiers
+        .iter()
+        .rev()
+        .find(|fee_tier| fee_tier_volume >= fee_tier.min_volume)
+        .map_or(config.fee_bps, |fee_tier| fee_tier.fee_bps)
+}
+
+// Lower a fee for takers who hold enough of the config's membership token
+// Passing a membership token account that doesn't qualify is an error, so takers aren't charged the full fee by surprise
+pub fn apply_membership_discount(
+    fee_bps: u16,
+    membership_token_account: Option<&TokenAccount>,
+    config: &Config,
+) -> Result<u16> {
+    let (Some(membership_discount), Some(membership_token_account)) =
+        (&config.membership_discount, membership_token_account)
+    else {
+        return Ok(fee_bps);
+    };
+    require!(
+        membership_token_account.mint == membership_discount.mint
+            && membership_token_account.amount >= membership_discount.min_balance,
+        ErrorCode::NotAMember
+    );
+    let discount = mul_div(
+        fee_bps as u64,
+        membership_discount.discount_bps as u64,
+        BASIS_POINTS_DENOMINATOR,
+        Rounding::Down,
+    )?;
+    Ok(fee_bps - discount as u16)
+}
+
+// Count a newly made offer in the registry's statistics
+pub fn record_offer_made(registry: &mut Registry) -> Result<()> {
+    registry.total_offers_created = registry
+        .total_offers_created
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    registry.total_offers_open = registry
+        .total_offers_open
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Stop counting offers as open once they are taken, settled or refunded
+pub fn record_offers_closed(registry: &mut Registry, offer_count: u64) -> Result<()> {
+    registry.total_offers_open = registry
+        .total_offers_open
+        .checked_sub(offer_count)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Add the token b a maker received to the registry's settled volume
+pub fn record_volume_settled(registry: &mut Registry, token_b_amount: u64) -> Result<()> {
+    registry.total_volume_settled = registry
+        .total_volume_settled
+        .checked_add(token_b_amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// List a newly made offer in its maker's index, which holds the maker's open offers
+// so its length is how many they have open, checked against the config's cap
+pub fn add_to_maker_index(
+    maker_index: &mut MakerIndex,
+    offer_id: u64,
+    config: &Config,
+) -> Result<()> {
+    require!(
+        maker_index.offer_ids.len() < config.max_open_offers_per_maker as usize,
+        ErrorCode::TooManyOpenOffers
+    );
+    require!(
+        maker_index.offer_ids.len() < MAX_MAKER_OPEN_OFFERS,
+        ErrorCode::MakerIndexFull
+    );
+    maker_index.offer_ids.push(offer_id);
+    Ok(())
+}
+
+// Stop listing an offer in its maker's index once it is taken, settled or refunded
+pub fn remove_from_maker_index(maker_index: &mut MakerIndex, offer_id: u64) {
+    maker_index.offer_ids.retain(|id| *id != offer_id);
+}
+
+// Count a newly made offer in its maker's statistics
+pub fn record_maker_offer_made(maker_stats: &mut MakerStats) -> Result<()> {
+    maker_stats.offers_made = maker_stats
+        .offers_made
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Count one of a maker's offers as taken or settled in full
+pub fn record_maker_offer_filled(maker_stats: &mut MakerStats) -> Result<()> {
+    maker_stats.offers_filled = maker_stats
+        .offers_filled
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Count one of a maker's offers as refunded
+pub fn record_maker_offer_refunded(maker_stats: &mut MakerStats) -> Result<()> {
+    maker_stats.offers_refunded = maker_stats
+        .offers_refunded
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Add what a maker sold and received in a settlement to their lifetime volume
+// Partial fills call this for every fill, but only count the offer as filled once nothing is left
+// Token b received in the config's fee tier mint also counts towards the maker's fee tier
+pub fn record_maker_volume(
+    maker_stats: &mut MakerStats,
+    token_a_sold_amount: u64,
+    token_b_received_amount: u64,
+    token_mint_b: &Pubkey,
+    config: &Config,
+) -> Result<()> {
+    maker_stats.token_a_sold_volume = maker_stats
+        .token_a_sold_volume
+        .checked_add(token_a_sold_amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    maker_stats.token_b_received_volume = maker_stats
+        .token_b_received_volume
+        .checked_add(token_b_received_amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    if *token_mint_b == config.fee_tier_mint {
+        // Volume in a previous fee tier mint doesn't carry over
+        if maker_stats.fee_tier_mint != config.fee_tier_mint {
+            maker_stats.fee_tier_mint = config.fee_tier_mint;
+            maker_stats.fee_tier_volume = 0;
+        }
+        maker_stats.fee_tier_volume = maker_stats
+            .fee_tier_volume
+            .checked_add(token_b_received_amount as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    Ok(())
+}
+
+// The transfer fee config of a Token-2022 mint with the TransferFee extension
+// Classic token mints, and Token-2022 mints without the extension, return None
+fn get_transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFeeConfig>> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != Token2022::id() {
+        return Ok(None);
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    Ok(mint_state.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+// Token b can belong to the other token program from token a, in which case its program is passed as token_program_b
+// Returns token b's program, checking it owns token b's mint
+pub fn get_token_program_b<'a, 'info>(
+    token_mint_b: &InterfaceAccount<'info, Mint>,
+    token_program: &'a Interface<'info, TokenInterface>,
+    token_program_b: Option<&'a Interface<'info, TokenInterface>>,
+) -> Result<&'a Interface<'info, TokenInterface>> {
+    let token_program_b = token_program_b.unwrap_or(token_program);
+    require_keys_eq!(
+        *token_mint_b.to_account_info().owner,
+        token_program_b.key(),
+        ErrorCode::InvalidTokenProgram
+    );
+    Ok(token_program_b)
+}
+
+// Token-2022 transfer hooks need extra accounts on every transfer, which settlement doesn't pass
+// Rather than letting a hooked mint fail opaquely when the offer is taken, we refuse it up front
+pub fn require_no_transfer_hook(mint_info: &AccountInfo) -> Result<()> {
+    if *mint_info.owner != Token2022::id() {
+        return Ok(());
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    let hook_program_id = mint_state
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|transfer_hook| Option::<Pubkey>::from(transfer_hook.program_id));
+    require!(
+        hook_program_id.is_none(),
+        ErrorCode::TransferHookNotSupported
+    );
+    Ok(())
+}
+
+// Refuse offers smaller or larger than the config allows, before any tokens are escrowed
+pub fn require_offer_amount_within_limits(token_a_offered_amount: u64, config: &Config) -> Result<()> {
+    require!(
+        token_a_offered_amount >= config.min_offer_amount,
+        ErrorCode::OfferTooSmall
+    );
+    require!(
+        token_a_offered_amount <= config.max_offer_amount,
+        ErrorCode::OfferTooLarge
+    );
+    Ok(())
+}
+
+// While the config's mint allowlist is enabled, refuse mints without an allowlist entry
+// Entries are only ever created at the mint's allowlist entry PDA, so checking its mint is enough
+pub fn require_mint_allowlisted(
+    mint: &Pubkey,
+    mint_allowlist_entry: Option<&MintAllowlistEntry>,
+    config: &Config,
+) -> Result<()> {
+    if !config.mint_allowlist_enabled {
+        return Ok(());
+    }
+    let mint_allowlist_entry = mint_allowlist_entry.ok_or(ErrorCode::MintNotAllowlisted)?;
+    require_keys_eq!(
+        mint_allowlist_entry.mint,
+        *mint,
+        ErrorCode::MintNotAllowlisted
+    );
+    Ok(())
+}
+
+// While the protocol denies any mints, refuse mints with a deny entry
+// The deny entry address is passed whether or not the mint is denied, a denied mint's has an account
+pub fn require_mint_not_denied(
+    mint: &Pubkey,
+    mint_deny_entry: Option<&AccountInfo>,
+    config: &Config,
+) -> Result<()> {
+    if config.denied_mint_count == 0 {
+        return Ok(());
+    }
+    let mint_deny_entry = mint_deny_entry.ok_or(ErrorCode::MintDenyEntryRequired)?;
+    let (mint_deny_entry_address, _bump) =
+        Pubkey::find_program_address(&[b"mint_deny_entry", mint.as_ref()], &crate::ID);
+    require_keys_eq!(
+        mint_deny_entry.key(),
+        mint_deny_entry_address,
+        ErrorCode::MintDenyEntryRequired
+    );
+    require!(mint_deny_entry.data_is_empty(), ErrorCode::MintDenied);
+    Ok(())
+}
+
+// Refuse mints the protocol's mint policy doesn't allow, before any tokens are escrowed
+pub fn require_mint_allowed(mint: &InterfaceAccount<Mint>, mint_policy: &MintPolicy) -> Result<()> {
+    require!(
+        mint_policy.allow_freeze_authority || mint.freeze_authority.is_none(),
+        ErrorCode::MintNotAllowed
+    );
+    require!(
+        mint_policy.allow_mint_authority || mint.mint_authority.is_none(),
+        ErrorCode::MintNotAllowed
+    );
+
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != Token2022::id() {
+        return Ok(());
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    require!(
+        mint_policy.allow_non_transferable
+            || mint_state.get_extension::<NonTransferable>().is_err(),
+        ErrorCode::MintNotAllowed
+    );
+    let permanent_delegate = mint_state
+        .get_extension::<PermanentDelegate>()
+        .ok()
+        .and_then(|permanent_delegate| Option::<Pubkey>::from(permanent_delegate.delegate));
+    require!(
+        mint_policy.allow_permanent_delegate || permanent_delegate.is_none(),
+        ErrorCode::MintNotAllowed
+    );
+    Ok(())
+}
+
+// Token transfers fail the same way whether the sender is short of tokens or frozen
+// Checking both first lets callers report which it was
+pub fn require_can_send(
+    token_account: &InterfaceAccount<TokenAccount>,
+    amount: u64,
+    insufficient_balance_error: ErrorCode,
+) -> Result<()> {
+    require!(!token_account.is_frozen(), ErrorCode::TokenAccountFrozen);
+    if token_account.amount < amount {
+        return Err(insufficient_balance_error.into());
+    }
+    Ok(())
+}
+
+// The amount to send so that the recipient receives amount after the mint's transfer fee
+fn get_amount_with_transfer_fee(transfer_fee_config: &TransferFeeConfig, amount: u64) -> Result<u64> {
+    let clock = Clock::get()?;
+    let fee = transfer_fee_config
+        .calculate_inverse_epoch_fee(clock.epoch, amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    amount
+        .checked_add(fee)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
 // Transfer tokens from one account to another
 // If transferring from a token account owned by a PDA, owning_pda_seeds must be provided.
+// The destination can be a token account created earlier in the same instruction, which isn't an InterfaceAccount yet
+// Always uses transfer_checked with the mint's own decimals, so the token program refuses a mint that isn't the
+// accounts' mint, rather than moving an amount meant for a mint with different decimals
 pub fn transfer_tokens<'info>(
     from: &InterfaceAccount<'info, TokenAccount>,
-    to: &InterfaceAccount<'info, TokenAccount>,
+    to: &impl ToAccountInfo<'info>,
     amount: &u64,
     mint: &InterfaceAccount<'info, Mint>,
     authority: &AccountInfo<'info>,
     token_program: &Interface<'info, TokenInterface>,
     owning_pda_seeds: Option<&[&[u8]]>,
 ) -> Result<()> {
+    require_keys_eq!(from.mint, mint.key(), ErrorCode::WrongTokenMint);
+
     let transfer_accounts = TransferChecked {
         from: from.to_account_info(),
         mint: mint.to_account_info(),
@@ -43,6 +731,143 @@ pub fn transfer_tokens<'info>(
     )
 }
 
+// Transfer tokens like transfer_tokens, then check the destination received what it should have
+// Only a Token-2022 transfer fee may keep back part of the amount, so an extension or hook taking a cut fails the
+// transfer rather than settling short
+// Returns the amount the destination actually received
+// Takes the same accounts as transfer_tokens, and one more argument
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens_and_verify<'info>(
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &impl ToAccountInfo<'info>,
+    amount: &u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    owning_pda_seeds: Option<&[&[u8]]>,
+    transfer_error: ErrorCode,
+) -> Result<u64> {
+    let transfer_fee = match get_transfer_fee_config(mint)? {
+        Some(transfer_fee_config) => transfer_fee_config
+            .calculate_epoch_fee(Clock::get()?.epoch, *amount)
+            .ok_or(ErrorCode::MathOverflow)?,
+        None => 0,
+    };
+    let expected_amount = amount
+        .checked_sub(transfer_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let balance_before = get_token_account_amount(&to.to_account_info())?;
+    transfer_tokens(from, to, amount, mint, authority, token_program, owning_pda_seeds)
+        .map_err(|_| transfer_error)?;
+
+    let received_amount = get_token_account_amount(&to.to_account_info())?
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::SettledAmountMismatch)?;
+    require!(
+        received_amount == expected_amount,
+        ErrorCode::SettledAmountMismatch
+    );
+    Ok(received_amount)
+}
+
+// Read a token account's balance straight from its data, so it is current after a transfer in this instruction
+// The balance is at the same offset for both token programs, so there's no need to unpack the whole account
+fn get_token_account_amount(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    let amount_bytes = data
+        .get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8)
+        .ok_or(ErrorCode::InvalidTokenAccount)?;
+    Ok(u64::from_le_bytes(amount_bytes.try_into().unwrap()))
+}
+
+// Check a token account that isn't an InterfaceAccount belongs to owner and holds mint
+// Classic token accounts, associated ones included, can be handed to a new owner, so the address alone doesn't prove this
+pub fn require_token_account_owned_by(
+    token_account: &AccountInfo,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(*token_account.owner, *token_program, ErrorCode::InvalidTokenProgram);
+    let data = token_account.try_borrow_data()?;
+    let token_account = TokenAccount::try_deserialize(&mut &data[..])?;
+    require_keys_eq!(token_account.owner, *owner, ErrorCode::InvalidTokenAccountOwner);
+    require_keys_eq!(token_account.mint, *mint, ErrorCode::WrongTokenMint);
+    Ok(())
+}
+
+// Create the associated token account of authority for mint if it doesn't exist yet, with payer paying the rent
+// An account that already exists must still belong to authority and hold mint, so a counterparty is always paid
+// into their own account
+// Returns whether it had to be created, so events can record who paid for it
+pub fn create_associated_token_account_if_needed<'info>(
+    associated_token_account: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+) -> Result<bool> {
+    if !associated_token_account.data_is_empty() {
+        require_token_account_owned_by(
+            associated_token_account,
+            authority.key,
+            mint.key,
+            &token_program.key(),
+        )?;
+        return Ok(false);
+    }
+    create(CpiContext::new(
+        associated_token_program.to_account_info(),
+        Create {
+            payer: payer.clone(),
+            associated_token: associated_token_account.clone(),
+            authority: authority.clone(),
+            mint: mint.clone(),
+            system_program: system_program.to_account_info(),
+            token_program: token_program.to_account_info(),
+        },
+    ))?;
+    Ok(true)
+}
+
+// Transfer tokens so the recipient receives exactly amount, with the sender paying any
+// Token-2022 transfer fee on top
+// If a PDA is the authority, as the owner or an approved delegate, owning_pda_seeds must be provided
+// Returns the amount the recipient actually received
+// Takes the same accounts as transfer_tokens, and one more argument
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens_including_fee<'info>(
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &impl ToAccountInfo<'info>,
+    amount: &u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    owning_pda_seeds: Option<&[&[u8]]>,
+    transfer_error: ErrorCode,
+) -> Result<u64> {
+    let sent_amount = match get_transfer_fee_config(mint)? {
+        Some(transfer_fee_config) => get_amount_with_transfer_fee(&transfer_fee_config, *amount)?,
+        None => *amount,
+    };
+    // Measured even without a transfer fee, so nothing else can take a cut of what the recipient is owed
+    let balance_before = get_token_account_amount(&to.to_account_info())?;
+    transfer_tokens(from, to, &sent_amount, mint, authority, token_program, owning_pda_seeds)
+        .map_err(|_| transfer_error)?;
+
+    let received_amount = get_token_account_amount(&to.to_account_info())?
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        received_amount >= *amount,
+        ErrorCode::TransferFeeShortfall
+    );
+    Ok(received_amount)
+}
+
 // Close a token account and send the rent to the specified destination
 // If the token account is owned by a PDA, owning_pda_seeds must be provided.
 pub fn close_token_account<'info>(
@@ -67,3 +892,78 @@ pub fn close_token_account<'info>(
         CpiContext::new(token_program.to_account_info(), close_accounts)
     })
 }
+
+// Hand both of a stake account's authorities, the staker and the withdrawer, to a new authority
+// The staker goes first, while the current authority is still the withdrawer, since the withdrawer can change either
+pub fn set_stake_authorities<'info>(
+    stake_account: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    new_authority: &AccountInfo<'info>,
+    clock: &AccountInfo<'info>,
+    stake_program: &Program<'info, Stake>,
+    owning_pda_seeds: Option<&[&[u8]]>,
+) -> Result<()> {
+    let signers_seeds = owning_pda_seeds.map(|seeds| [seeds]);
+
+    for stake_authorize in [StakeAuthorize::Staker, StakeAuthorize::Withdrawer] {
+        let authorize_accounts = Authorize {
+            stake: stake_account.clone(),
+            authorized: authority.clone(),
+            new_authorized: new_authority.clone(),
+            clock: clock.clone(),
+        };
+        authorize(
+            if let Some(seeds_arr) = signers_seeds.as_ref() {
+                CpiContext::new_with_signer(
+                    stake_program.to_account_info(),
+                    authorize_accounts,
+                    seeds_arr,
+                )
+            } else {
+                CpiContext::new(stake_program.to_account_info(), authorize_accounts)
+            },
+            stake_authorize,
+            None,
+        )?;
+    }
+    Ok(())
+}
+
+// Allow a delegate to move up to amount tokens out of a token account
+// The owner of the token account must sign
+pub fn approve_delegate<'info>(
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    delegate: &AccountInfo<'info>,
+    amount: &u64,
+    owner: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let approve_accounts = Approve {
+        to: token_account.to_account_info(),
+        delegate: delegate.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+
+    approve(
+        CpiContext::new(token_program.to_account_info(), approve_accounts),
+        *amount,
+    )
+}
+
+// Remove whatever delegate a token account has
+// The owner of the token account must sign
+pub fn revoke_delegate<'info>(
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    owner: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let revoke_accounts = Revoke {
+        source: token_account.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+
+    revoke(CpiContext::new(
+        token_program.to_account_info(),
+        revoke_accounts,
+    ))
+}
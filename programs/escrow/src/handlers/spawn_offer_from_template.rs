@@ -0,0 +1,327 @@
+use super::shared::{
+    add_to_maker_index, record_maker_offer_made, record_offer_made, require_can_send,
+    require_mint_allowlisted, require_mint_not_denied, require_offer_amount_within_limits,
+    transfer_tokens,
+};
+use crate::{
+    constants::OFFER_VERSION,
+    error::ErrorCode,
+    events::{emit_event, OfferMade},
+    state::{
+        Config, MakerCounter, MakerIndex, MakerStats, MintAllowlistEntry, Offer, OfferTemplate,
+        Registry,
+    },
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::set_return_data, program_option::COption},
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SpawnOfferFromTemplate<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Anyone can make the template's next offer, usually a keeper, who pays its rent and gets it back when it closes
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Checked against the template's maker with has_one
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [
+            b"offer_template",
+            maker.key().as_ref(),
+            token_mint_a.key().as_ref(),
+            token_mint_b.key().as_ref()
+        ],
+        bump = offer_template.bump
+    )]
+    pub offer_template: Account<'info, OfferTemplate>,
+
+    /// CHECK: Only needed once the template has made an offer: the last offer it made, checked against the template
+    /// The handler checks it has been closed, by being taken or refunded
+    pub previous_offer: Option<UncheckedAccount<'info>>,
+
+    // The template is approved as this account's delegate, and moves the new offer's tokens out of it
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Hands out the maker's offer ids, the template's offers always take the next one
+    // Comes before the offer, since the offer's address uses the id it assigns
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerCounter::DISCRIMINATOR.len() + MakerCounter::INIT_SPACE,
+        seeds = [b"maker_counter", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_counter: Account<'info, MakerCounter>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Offer::SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_counter.next_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerIndex::DISCRIMINATOR.len() + MakerIndex::INIT_SPACE,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MakerStats::DISCRIMINATOR.len() + MakerStats::INIT_SPACE,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Only needed while the config's mint allowlist is enabled: the allowlist entries for token a and token b
+    // The handler checks each entry is for its mint
+    pub token_mint_a_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    pub token_mint_b_allowlist_entry: Option<Account<'info, MintAllowlistEntry>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+}
+
+// Handle the spawn offer from template instruction by:
+// 1. Checking the template's last offer has been taken or refunded, so the template only ever has one offer open
+// 2. Moving the template's token a amount from the maker's ATA to a new vault, using the template's approval
+// 3. Saving the template's terms to a new offer, with the maker's next id, and recording it as the template's offer
+// 4. Counting the new offer in the registry and the maker's statistics, and listing it in the maker's index
+// 5. Emitting an OfferMade event, and returning the offer's id
+// The offer is an ordinary fixed price offer, taken with take_offer and refunded by the maker with refund_offer
+// Refunding it doesn't stop the template, the maker closes the template with close_offer_template for that
+pub fn spawn_offer_from_template(context: Context<SpawnOfferFromTemplate>) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+
+    // Taking or refunding an offer closes it, and the template's offers never keep their history
+    if let Some(current_offer) = context.accounts.offer_template.current_offer {
+        let previous_offer = context
+            .accounts
+            .previous_offer
+            .as_ref()
+            .ok_or(ErrorCode::WrongOffer)?;
+        require_keys_eq!(previous_offer.key(), current_offer, ErrorCode::WrongOffer);
+        require!(
+            previous_offer.data_is_empty(),
+            ErrorCode::TemplateOfferStillOpen
+        );
+    }
+
+    let token_a_offered_amount = context.accounts.offer_template.token_a_offered_amount;
+    let token_b_wanted_amount = context.accounts.offer_template.token_b_wanted_amount;
+
+    // The config can have changed since the template was made
+    require_offer_amount_within_limits(token_a_offered_amount, &context.accounts.config)?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_allowlisted(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_allowlist_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    // The maker can revoke the approval, or replace it, at any time
+    let maker_token_account_a = &context.accounts.maker_token_account_a;
+    require!(
+        maker_token_account_a.delegate == COption::Some(context.accounts.offer_template.key())
+            && maker_token_account_a.delegated_amount >= token_a_offered_amount,
+        ErrorCode::DelegationRevoked
+    );
+    require_can_send(
+        maker_token_account_a,
+        token_a_offered_amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+
+    // Move the tokens from the maker's ATA to the vault, signing as the template
+    let maker_key = context.accounts.maker.key();
+    let token_mint_a_key = context.accounts.token_mint_a.key();
+    let token_mint_b_key = context.accounts.token_mint_b.key();
+    let offer_template_seeds = &[
+        b"offer_template",
+        maker_key.as_ref(),
+        token_mint_a_key.as_ref(),
+        token_mint_b_key.as_ref(),
+        &[context.accounts.offer_template.bump],
+    ];
+    transfer_tokens(
+        &context.accounts.maker_token_account_a,
+        &context.accounts.vault,
+        &token_a_offered_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.offer_template.to_account_info(),
+        &context.accounts.token_program,
+        Some(&offer_template_seeds[..]),
+    )?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so record what the vault actually holds
+    context.accounts.vault.reload()?;
+    let token_a_received_amount = context.accounts.vault.amount;
+    require!(
+        token_a_received_amount > 0,
+        ErrorCode::TransferFeeExceedsAmount
+    );
+
+    // Take the maker's next id, the offer's address was derived from it
+    let maker_counter = &mut context.accounts.maker_counter;
+    maker_counter.maker = maker_key;
+    maker_counter.bump = context.bumps.maker_counter;
+    let id = maker_counter.next_id;
+    maker_counter.next_id = id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+    let clock = Clock::get()?;
+
+    // Save the template's terms to the offer account
+    // The account starts zeroed, so every optional value starts out unset
+    let mut offer = context.accounts.offer.load_init()?;
+    offer.id = id;
+    offer.maker = maker_key;
+    offer.token_mint_a = token_mint_a_key;
+    offer.token_mint_b = token_mint_b_key;
+    offer.token_a_offered_amount = token_a_received_amount;
+    offer.token_b_wanted_amount = token_b_wanted_amount;
+    offer.set_price(token_b_wanted_amount, token_a_received_amount);
+    offer.bump = context.bumps.offer;
+    offer.vault_authority_bump = context.bumps.vault_authority;
+    offer.version = OFFER_VERSION;
+    offer.created_at = clock.unix_timestamp;
+    offer.created_slot = clock.slot;
+    offer.tags = context.accounts.offer_template.tags;
+    offer.maker_authority = maker_key;
+    // Whoever paid for the offer gets the rent back when it closes
+    offer.rent_payer = context.accounts.payer.key();
+
+    let offer_template = &mut context.accounts.offer_template;
+    offer_template.current_offer = Some(context.accounts.offer.key());
+    offer_template.offers_spawned = offer_template
+        .offers_spawned
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    record_offer_made(&mut context.accounts.registry)?;
+
+    let maker_index = &mut context.accounts.maker_index;
+    maker_index.maker = maker_key;
+    maker_index.bump = context.bumps.maker_index;
+    add_to_maker_index(maker_index, id, &context.accounts.config)?;
+
+    let maker_stats = &mut context.accounts.maker_stats;
+    maker_stats.maker = maker_key;
+    maker_stats.bump = context.bumps.maker_stats;
+    record_maker_offer_made(maker_stats)?;
+
+    emit_event(
+        OfferMade {
+            offer_id: id,
+            maker: maker_key,
+            token_mint_a: token_mint_a_key,
+            token_mint_b: token_mint_b_key,
+            token_a_offered_amount: token_a_received_amount,
+            token_b_wanted_amount,
+            created_at: clock.unix_timestamp,
+            tags: offer.tags,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    // Keepers can read the new offer's id from here rather than from the event
+    // Set last, since the event's self-CPI would clear it
+    set_return_data(&id.to_le_bytes());
+
+    Ok(())
+}
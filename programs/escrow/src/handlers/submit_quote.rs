@@ -0,0 +1,115 @@
+use super::shared::{require_can_send, transfer_tokens_including_fee};
+use crate::{
+    error::ErrorCode,
+    state::{Config, Quote, QuoteRequest},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct SubmitQuote<'info> {
+    // Used to create the quote vault
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program, whichever owns token a
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [b"quote_request", quote_request.requester.as_ref(), quote_request.id.to_le_bytes().as_ref()],
+        bump = quote_request.bump
+    )]
+    pub quote_request: Account<'info, QuoteRequest>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Quote::DISCRIMINATOR.len() + Quote::INIT_SPACE,
+        seeds = [b"quote", quote_request.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub quote: Account<'info, Quote>,
+
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = quote,
+        associated_token::token_program = token_program
+    )]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Handle the submit quote instruction by:
+// 1. Moving the requested token a from the maker's account to the quote vault
+//    If token a charges a transfer fee, the maker pays it on top, so the vault holds the full amount requested
+// 2. Saving the token b the maker wants for it to the quote account
+// The quote is firm until the request expires, so the requester can accept it without the maker changing it first
+// Makers wanting a different price wait for withdraw_quote, each maker has one quote per request
+pub fn submit_quote(context: Context<SubmitQuote>, token_b_wanted_amount: u64) -> Result<()> {
+    let quote_request = &context.accounts.quote_request;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidWantedAmount);
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < quote_request.expires_at,
+        ErrorCode::QuoteRequestExpired
+    );
+    require!(
+        context.accounts.maker.key() != quote_request.requester,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    require_can_send(
+        &context.accounts.maker_token_account_a,
+        quote_request.token_a_wanted_amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+    transfer_tokens_including_fee(
+        &context.accounts.maker_token_account_a,
+        &context.accounts.quote_vault,
+        &quote_request.token_a_wanted_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+
+    context.accounts.quote.set_inner(Quote {
+        quote_request: quote_request.key(),
+        maker: context.accounts.maker.key(),
+        token_b_wanted_amount,
+        expires_at: quote_request.expires_at,
+        bump: context.bumps.quote,
+    });
+    Ok(())
+}
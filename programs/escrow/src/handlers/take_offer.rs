@@ -1,11 +1,32 @@
-use super::shared::{close_token_account, transfer_tokens};
-use crate::{error::ErrorCode, state::Offer};
-use anchor_lang::prelude::*;
+use super::bundle::{withdraw_additional_tokens, ACCOUNTS_PER_ADDITIONAL_MINT};
+use super::nft::pay_royalties;
+use super::shared::{
+    apply_membership_discount, calculate_fee, close_token_account,
+    create_associated_token_account_if_needed, get_token_b_wanted_amount, get_token_program_b,
+    maker_fee_bps, record_maker_offer_filled, record_maker_volume, record_offers_closed,
+    record_volume_settled, remove_from_maker_index, require_attestation, require_can_send,
+    require_eth_taker_signature, require_mint_not_denied, require_offer_not_expired,
+    require_offer_old_enough, require_offer_open, require_valid_preimage, transfer_tokens,
+    transfer_tokens_and_verify, transfer_tokens_including_fee,
+};
+use crate::{
+    constants::MAX_MEMO_LENGTH,
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, OfferStatus, Registry},
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program_option::COption, sysvar},
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    memo::{build_memo, BuildMemo, Memo},
+    metadata::MetadataAccount,
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct TakeOffer<'info> {
     // Used to manage associated token accounts
@@ -22,13 +43,23 @@ pub struct TakeOffer<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
 
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
     #[account(mut)]
-    pub maker: SystemAccount<'info>,
+    pub rent_payer: UncheckedAccount<'info>,
 
+    #[account(mint::token_program = token_program)]
     pub token_mint_a: InterfaceAccount<'info, Mint>,
 
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
     pub token_mint_b: InterfaceAccount<'info, Mint>,
 
+    // The taker's token accounts are their associated token accounts, derived from the taker and each mint
+    // Takers who keep their tokens in other accounts use take_offer_with_token_accounts
+    // Only passed without a recipient, so a taker paying for someone else isn't charged rent for an account they don't use
     #[account(
         init_if_needed,
         payer = taker,
@@ -36,91 +67,526 @@ pub struct TakeOffer<'info> {
         associated_token::authority = taker,
         associated_token::token_program = token_program,
     )]
-    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+    pub taker_token_account_a: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    // Derived with token b's own token program, which can differ from token a's
     #[account(
         mut,
-        associated_token::mint = token_mint_b,
-        associated_token::authority = taker,
-        associated_token::token_program = token_program,
+        token::mint = token_mint_b,
+        token::authority = taker,
+        address = get_associated_token_address_with_program_id(
+            &taker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
     )]
     pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    /// Derived with token b's own token program, which can differ from token a's
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    // Closed by the handler, unless the maker asked to keep the offer's history
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // The offer's vault, or for delegated offers the maker's token a account, which the vault authority is approved to spend from
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer.load()?.token_a_holder(&vault_authority.key()),
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
     #[account(
         init_if_needed,
         payer = taker,
-        associated_token::mint = token_mint_b,
-        associated_token::authority = maker,
-        associated_token::token_program = token_program,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
     )]
-    pub maker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
 
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
     #[account(
         mut,
         close = maker,
-        has_one = maker,
-        has_one = token_mint_b,
-        seeds = [b"offer", offer.id.to_le_bytes().as_ref()],
-        bump = offer.bump
+        has_one = offer @ ErrorCode::WrongOffer
     )]
-    offer: Account<'info, Offer>,
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
 
+    // The token a account of whoever referred the taker, such as a frontend, if anyone
+    // The referrer gets the config's referral share of the protocol fee
     #[account(
         mut,
-        associated_token::mint = token_mint_a,
-        associated_token::authority = offer,
-        associated_token::token_program = token_program,
+        token::mint = token_mint_a,
+        token::token_program = token_program
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed if the taker attaches a memo to the settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    // Only needed to pay royalties on an NFT offer: the Metaplex metadata of token a
+    // The creators' token b accounts follow any bundle accounts in the remaining accounts
+    pub token_mint_a_metadata: Option<Account<'info, MetadataAccount>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed for the config's membership discount: the taker's account for the membership token
+    // The membership token can belong to either token program, whatever the offer's mints use
+    #[account(token::authority = taker)]
+    pub membership_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+
+    /// CHECK: Only needed for attested offers and offers with an Ethereum taker, checked to be the instructions sysvar
+    /// The handler reads the transaction's earlier instructions from it, looking for the attestor's or Ethereum taker's signature
+    #[account(address = sysvar::instructions::ID @ ErrorCode::AttestationRequired)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Only needed to take the offer on behalf of someone else, like a treasury paying for an operations wallet
+    /// The recipient receives token a while the taker pays token b, any wallet can receive
+    pub recipient: Option<UncheckedAccount<'info>>,
+
+    // Only needed with a recipient: the recipient's associated token account for token a, which must already exist
+    // The handler checks it's the recipient's associated token account for token a
+    #[account(mut)]
+    pub recipient_token_account_a: Option<InterfaceAccount<'info, TokenAccount>>,
 }
 
 // Handle the take offer instruction by:
-// 1. Withdrawing the offered tokens from the vault to the taker and closing the vault
-// 2. Sending the wanted tokens from the taker to the maker
-pub fn take_offer(context: Context<TakeOffer>) -> Result<()> {
-    // Since the Offer account owns the Vault, we will say
-    // there is one signer (the offer), with the seeds of the specific offer account
+// 1. Checking the offer still has the terms the taker saw, so a repricing landing first can't change the deal
+//    Dutch auction prices only fall, so the taker just needs to pay no more than they expected
+//    Hashlocked offers also need the preimage of their hashlock
+//    Attested offers also need an earlier ed25519 program instruction verifying the attestor's signature over the offer and taker
+//    Offers with an Ethereum taker also need an earlier secp256k1 program instruction verifying that address's signature
+//    over the offer's address followed by the taker's
+// 2. Withdrawing the offered tokens from the vault to the taker, less the protocol fee, and closing the vault
+//    Part of the protocol fee goes to the referrer, if there is one
+//    The fee is the config's fee_bps, or lower if the maker's settled volume has reached one of the config's fee tiers
+//    Takers holding the config's membership token get its discount on top
+//    Delegated offers pull the tokens from the maker's account instead, using the vault authority's approval, and have no vault to close
+// 3. For bundle offers, doing the same for each additional vault
+// 4. Sending the wanted tokens from the taker to the maker
+//    Token b can belong to the other token program from token a, which the taker then passes as token_program_b
+//    For NFT offers given the NFT's metadata, the creators are paid their royalty first and the maker gets the rest
+//    If the config requires royalties, NFT offers can't be taken without the metadata
+// 5. Recording the settlement in the registry, the maker's index and the maker's statistics
+//    The offer is closed, or marked filled if the maker asked to keep its history
+// 6. Recording the taker's memo with the memo program, if they attached one
+// 7. Emitting an OfferTaken event, with the amounts the taker and maker actually received
+// Takers can take an offer on behalf of a recipient, who then receives token a, including any bundle, in place of the taker
+// The taker still pays token b, and is the one any allowlist, attestation or membership discount applies to
+// Both transfers are measured, so an extension or hook skimming either side fails the take rather than settling short
+pub fn take_offer<'info>(
+    context: Context<'_, '_, 'info, 'info, TakeOffer<'info>>,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    preimage: Option<Vec<u8>>,
+    memo: Option<String>,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    if let Some(memo) = &memo {
+        require!(memo.len() <= MAX_MEMO_LENGTH, ErrorCode::MemoTooLong);
+        require!(
+            context.accounts.memo_program.is_some(),
+            ErrorCode::MemoProgramRequired
+        );
+    }
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    // Offers made before the allowlist manager denied one of their mints can't be taken either
+    require_mint_not_denied(
+        &offer.token_mint_a,
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &offer.token_mint_b,
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    require_valid_preimage(&offer, preimage.as_deref())?;
+    require_attestation(
+        &offer,
+        &context.accounts.offer.key(),
+        &context.accounts.taker.key(),
+        context.accounts.instructions_sysvar.as_deref(),
+    )?;
+    require_eth_taker_signature(
+        &offer,
+        &context.accounts.offer.key(),
+        &context.accounts.taker.key(),
+        context.accounts.instructions_sysvar.as_deref(),
+    )?;
+    require!(
+        offer.vesting_duration().is_none(),
+        ErrorCode::OfferIsVested
+    );
+    require!(
+        offer.english_auction().is_none(),
+        ErrorCode::OfferIsAuction
+    );
+    require!(offer.arbiter().is_none(), ErrorCode::OfferIsArbitrated);
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    // Whoever receives token a, and the token a account it goes to
+    let (recipient, recipient_token_account_a) = match (
+        &context.accounts.recipient,
+        &context.accounts.recipient_token_account_a,
+        &context.accounts.taker_token_account_a,
+    ) {
+        (Some(recipient), Some(recipient_token_account_a), None) => {
+            require!(
+                recipient.key() != offer.maker,
+                ErrorCode::SelfTradeNotAllowed
+            );
+            require_keys_eq!(
+                recipient_token_account_a.mint,
+                offer.token_mint_a,
+                ErrorCode::WrongTokenMint
+            );
+            require_keys_eq!(
+                recipient_token_account_a.key(),
+                get_associated_token_address_with_program_id(
+                    &recipient.key(),
+                    &offer.token_mint_a,
+                    &context.accounts.token_program.key()
+                ),
+                ErrorCode::InvalidTokenAccount
+            );
+            (recipient.key(), recipient_token_account_a)
+        }
+        (None, None, Some(taker_token_account_a)) => {
+            (context.accounts.taker.key(), taker_token_account_a)
+        }
+        _ => return err!(ErrorCode::RecipientTokenAccountRequired),
+    };
+
+    let token_program_b = get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Dutch auctions want a different amount depending on when the offer is taken
+    let token_b_wanted_amount = get_token_b_wanted_amount(&offer, clock.unix_timestamp)?;
+
+    require!(
+        offer.token_a_offered_amount == expected_token_a_amount,
+        ErrorCode::OfferTermsChanged
+    );
+    require!(
+        token_b_wanted_amount <= expected_token_b_amount,
+        ErrorCode::OfferTermsChanged
+    );
+
+    // Since the vault authority owns the Vault, we will say
+    // there is one signer (the vault authority), with the seeds of the specific offer's vault authority
     // We can use these signer seeds to withdraw the token from the vault
-    let offer_account_seeds = &[
-        b"offer",
-        &context.accounts.offer.id.to_le_bytes()[..],
-        &[context.accounts.offer.bump],
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
     ];
-    let signers_seeds = Some(&offer_account_seeds[..]);
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+    let vault_authority_info = context.accounts.vault_authority.to_account_info();
+
+    // We settle the vault's real balance, so any Token-2022 transfer fee on the deposit
+    // isn't paid for twice
+    // Delegated offers settle the amount the maker approved, which is still in their account and may have been spent since
+    let token_a_amount = if offer.is_delegated() {
+        let maker_token_account_a = &context.accounts.vault;
+        require!(
+            maker_token_account_a.delegate == COption::Some(context.accounts.vault_authority.key())
+                && maker_token_account_a.delegated_amount >= offer.token_a_offered_amount,
+            ErrorCode::DelegationRevoked
+        );
+        require_can_send(
+            maker_token_account_a,
+            offer.token_a_offered_amount,
+            ErrorCode::InsufficientMakerBalance,
+        )?;
+        offer.token_a_offered_amount
+    } else {
+        context.accounts.vault.amount
+    };
+    let fee_bps = apply_membership_discount(
+        maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+        context.accounts.membership_token_account.as_deref(),
+        &context.accounts.config,
+    )?;
+    let total_fee = calculate_fee(token_a_amount, fee_bps)?;
+    let token_a_taker_amount = token_a_amount - total_fee;
 
-    // Withdraw the offered tokens from the vault to the taker
-    transfer_tokens(
+    // Pay the referrer their share of the fee, the protocol keeps the rest
+    let referral_fee = match &context.accounts.referrer_token_account {
+        Some(referrer_token_account) => {
+            require!(
+                referrer_token_account.owner != context.accounts.taker.key(),
+                ErrorCode::SelfReferral
+            );
+            let referral_fee =
+                calculate_fee(total_fee, context.accounts.config.referral_share_bps)?;
+            if referral_fee > 0 {
+                transfer_tokens(
+                    &context.accounts.vault,
+                    referrer_token_account,
+                    &referral_fee,
+                    &context.accounts.token_mint_a,
+                    &vault_authority_info,
+                    &context.accounts.token_program,
+                    signers_seeds,
+                )
+                .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+            }
+            referral_fee
+        }
+        None => 0,
+    };
+    let protocol_fee = total_fee - referral_fee;
+
+    // Send the protocol fee from the vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Withdraw the rest of the offered tokens from the vault to the taker, or their recipient, checking what actually arrived
+    let token_a_received_amount = transfer_tokens_and_verify(
         &context.accounts.vault,
-        &context.accounts.taker_token_account_a,
-        &context.accounts.vault.amount,
+        recipient_token_account_a,
+        &token_a_taker_amount,
         &context.accounts.token_mint_a,
-        &context.accounts.offer.to_account_info(),
+        &vault_authority_info,
         &context.accounts.token_program,
         signers_seeds,
-    )
-    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+        ErrorCode::FailedVaultWithdrawal,
+    )?;
 
-    // Close the vault and return the rent to the maker
-    close_token_account(
-        &context.accounts.vault,
-        &context.accounts.taker.to_account_info(),
-        &context.accounts.offer.to_account_info(),
+    // Close the vault and return the rent to whoever paid for it
+    // The maker's own account stays open, the approval was used up by the withdrawal
+    if !offer.is_delegated() {
+        close_token_account(
+            &context.accounts.vault,
+            &context.accounts.rent_payer.to_account_info(),
+            &vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+    }
+
+    // Withdraw the rest of a bundle, returning the vault rent to whoever paid for it
+    withdraw_additional_tokens(
+        context.remaining_accounts,
+        offer.additional_token_mints_a(),
+        &recipient,
+        &context.accounts.rent_payer.to_account_info(),
+        &vault_authority_info,
         &context.accounts.token_program,
-        signers_seeds,
-    )
-    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+        &vault_authority_seeds[..],
+        ErrorCode::FailedVaultWithdrawal,
+        ErrorCode::FailedVaultClosure,
+    )?;
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        token_program_b,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Pay the NFT's creators their royalty out of what the taker pays
+    let royalty_amount = match &context.accounts.token_mint_a_metadata {
+        Some(metadata) => {
+            require!(offer.is_nft(), ErrorCode::NotAnNft);
+            let creator_token_accounts = context
+                .remaining_accounts
+                .get(offer.additional_token_mints_a().len() * ACCOUNTS_PER_ADDITIONAL_MINT..)
+                .ok_or(ErrorCode::InvalidRemainingAccounts)?;
+            pay_royalties(
+                metadata,
+                &context.accounts.token_mint_a.key(),
+                token_b_wanted_amount,
+                creator_token_accounts,
+                &context.accounts.taker_token_account_b,
+                &context.accounts.token_mint_b,
+                &context.accounts.taker.to_account_info(),
+                token_program_b,
+            )?
+        }
+        None => {
+            require!(
+                !(offer.is_nft() && context.accounts.config.royalties_required),
+                ErrorCode::RoyaltyMetadataRequired
+            );
+            0
+        }
+    };
+    let token_b_maker_amount = token_b_wanted_amount - royalty_amount;
 
     // Send the wanted tokens from the taker to the maker
-    transfer_tokens(
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
         &context.accounts.taker_token_account_b,
         &context.accounts.maker_token_account_b,
-        &context.accounts.offer.token_b_wanted_amount,
+        &token_b_maker_amount,
         &context.accounts.token_mint_b,
         &context.accounts.taker.to_account_info(),
-        &context.accounts.token_program,
+        token_program_b,
         None,
-    )
-    .map_err(|_| ErrorCode::InsufficientTakerBalance)?;
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        token_a_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    // Close the offer and return the rent to whoever paid for it
+    // Offers that keep their history stay open as a record of the fill, until claim_rent closes them
+    if offer.keep_history() {
+        context.accounts.offer.load_mut()?.set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .offer
+            .close(context.accounts.rent_payer.to_account_info())?;
+    }
+
+    // Takers can attach a note like an invoice number, which the memo program logs with the settlement
+    if let (Some(memo), Some(memo_program)) = (&memo, &context.accounts.memo_program) {
+        build_memo(
+            CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+            memo.as_bytes(),
+        )?;
+    }
+
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_received_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    require_offer_not_expired, require_offer_old_enough, require_offer_open,
+    require_royalties_paid_by_take_offer, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, Offer, OfferAllowlist, PendingSettlement},
+};
+
+#[derive(Accounts)]
+pub struct TakeOfferArbitrated<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    // Only one taker can be waiting on the arbiter at a time, so this is derived from the offer alone
+    #[account(
+        init,
+        payer = taker,
+        space = PendingSettlement::DISCRIMINATOR.len() + PendingSettlement::INIT_SPACE,
+        seeds = [b"pending_settlement", offer.key().as_ref()],
+        bump
+    )]
+    pub pending_settlement: Account<'info, PendingSettlement>,
+
+    // Holds the taker's token b until the arbiter releases it to the maker or reverts it to the taker
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = pending_settlement,
+        associated_token::token_program = token_program,
+    )]
+    pub pending_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(has_one = offer @ ErrorCode::WrongOffer)]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the take offer arbitrated instruction by:
+// 1. Checking the offer has an arbiter and still has the terms the taker saw
+// 2. Moving the token b the offer wants from the taker to the pending vault
+// 3. Marking the offer as waiting on its arbiter, so it can't be taken again or refunded
+// The arbiter then either releases the swap, or reverts it and the offer is open again
+pub fn take_offer_arbitrated(
+    context: Context<TakeOfferArbitrated>,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+
+    let arbiter = offer.arbiter().ok_or(ErrorCode::NotArbitrated)?;
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+    require!(
+        context.accounts.taker.key() != arbiter,
+        ErrorCode::InvalidArbiter
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    require!(
+        offer.token_a_offered_amount == expected_token_a_amount
+            && offer.token_b_wanted_amount == expected_token_b_amount,
+        ErrorCode::OfferTermsChanged
+    );
+
+    // If token b charges a transfer fee, the taker pays it on top, so the pending vault holds the full amount
+    let token_b_wanted_amount = offer.token_b_wanted_amount;
+    transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.pending_vault,
+        &token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    context.accounts.offer.load_mut()?.set_arbitration_pending(true);
+
+    context
+        .accounts
+        .pending_settlement
+        .set_inner(PendingSettlement {
+            offer: context.accounts.offer.key(),
+            taker: context.accounts.taker.key(),
+            arbiter,
+            token_b_amount: token_b_wanted_amount,
+            bump: context.bumps.pending_settlement,
+        });
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed, maker_fee_bps,
+    record_maker_offer_filled, record_maker_volume, record_offers_closed, record_volume_settled,
+    remove_from_maker_index, require_offer_not_expired, require_offer_old_enough,
+    require_royalties_paid_by_take_offer, transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, Registry},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOfferExactOut<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Offers whose mints belong to different token programs are taken with take_offer
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    // The offer is only closed once the vault is empty, so there's no close constraint here
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the take offer exact out instruction by:
+// 1. Withdrawing exactly token_a_amount from the vault to the taker, plus the protocol fee to the fee vault
+// 2. Sending the taker's pro-rata share of the wanted tokens to the maker, rounded up
+// 3. Reducing the offer by what was taken, or closing it and the vault if nothing is left
+// 4. Recording the settlement in the registry, the maker's index and the maker's statistics
+//    Each fill adds to the volume, but the offer only counts as closed and filled once nothing is left
+// 5. Emitting an OfferTaken event
+// The price is the offer's ratio of token b wanted to token a offered, so Dutch auctions,
+// English auctions and bundles, which don't have a single ratio, must be taken whole
+pub fn take_offer_exact_out(
+    context: Context<TakeOfferExactOut>,
+    token_a_amount: u64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_a_amount > 0, ErrorCode::InvalidAmount);
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+    // Hashlocked offers have to be taken with take_offer, which checks the preimage
+    require!(offer.hashlock().is_none(), ErrorCode::OfferIsHashlocked);
+    require!(offer.attestor().is_none(), ErrorCode::OfferIsAttested);
+    require!(offer.eth_taker().is_none(), ErrorCode::OfferHasEthTaker);
+    require!(offer.vesting_duration().is_none(), ErrorCode::OfferIsVested);
+    require!(offer.arbiter().is_none(), ErrorCode::OfferIsArbitrated);
+    require!(
+        offer.dutch_auction().is_none() && offer.additional_token_mints_a().is_empty(),
+        ErrorCode::PartialFillNotSupported
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    // The protocol fee comes out of the vault on top, so the taker receives exactly token_a_amount
+    let protocol_fee = calculate_fee(
+        token_a_amount,
+        maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+    )?;
+    let token_a_withdrawn_amount = token_a_amount
+        .checked_add(protocol_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let vault_amount = context.accounts.vault.amount;
+    require!(
+        token_a_withdrawn_amount <= vault_amount,
+        ErrorCode::InsufficientOfferBalance
+    );
+
+    // Makers can set a minimum fill, so partial fills don't leave them managing dust
+    // Taking everything that's left is always allowed, however small it is
+    let token_a_remaining_amount = vault_amount - token_a_withdrawn_amount;
+    if token_a_remaining_amount > 0 {
+        let min_fill_amount = offer.min_fill_amount;
+        require!(token_a_amount >= min_fill_amount, ErrorCode::FillTooSmall);
+        require!(
+            token_a_remaining_amount >= min_fill_amount,
+            ErrorCode::FillTooSmall
+        );
+    }
+
+    // The maker is paid for everything that leaves the vault, including the fee
+    let token_b_wanted_amount = offer.token_b_amount_for(token_a_withdrawn_amount)?;
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Send the protocol fee from the vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Withdraw exactly the amount the taker asked for from the vault to the taker
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.taker_token_account_a,
+        &token_a_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Send the wanted tokens from the taker to the maker
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        token_a_withdrawn_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+
+    if token_a_remaining_amount == 0 {
+        // Nothing is left, so close the vault, the offer and any allowlist, returning the rent to whoever paid for each
+        close_token_account(
+            &context.accounts.vault,
+            &context.accounts.rent_payer.to_account_info(),
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+        context
+            .accounts
+            .offer
+            .close(context.accounts.rent_payer.to_account_info())?;
+        if let Some(allowlist) = &context.accounts.allowlist {
+            allowlist.close(context.accounts.maker.to_account_info())?;
+        }
+        record_offers_closed(&mut context.accounts.registry, 1)?;
+        remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+        record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    } else {
+        // What's left is offered at the same price
+        let mut offer = context.accounts.offer.load_mut()?;
+        offer.token_b_wanted_amount = offer.token_b_amount_for(token_a_remaining_amount)?;
+        offer.token_a_offered_amount = token_a_remaining_amount;
+    }
+
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_next_installment, get_token_b_wanted_amount, maker_fee_bps, record_maker_volume,
+    record_offers_closed, record_volume_settled, remove_from_maker_index,
+    require_offer_not_expired, require_offer_old_enough, require_offer_open,
+    require_royalties_paid_by_take_offer, transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, InstallmentPlan, MakerIndex, MakerStats, Offer, OfferAllowlist, Registry},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOfferInInstallments<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Offers whose mints belong to different token programs are taken with take_offer
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Holds the taker's token a until they pay for it, the taker pays the rent and gets it back when the plan ends
+    #[account(
+        init,
+        payer = taker,
+        space = InstallmentPlan::DISCRIMINATOR.len() + InstallmentPlan::INIT_SPACE,
+        seeds = [b"installment_plan", offer.key().as_ref()],
+        bump
+    )]
+    pub installment_plan: Account<'info, InstallmentPlan>,
+
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = installment_plan,
+        associated_token::token_program = token_program,
+    )]
+    pub installment_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the take offer in installments instruction by:
+// 1. Checking the maker accepts installments, and that the terms are the ones the taker expected
+// 2. Moving the offered tokens from the vault to the installment vault, and closing the vault
+// 3. Starting the installment plan, and paying its first installment straight away:
+//    the taker sends their share of token b to the maker, and gets the same share of token a, less the protocol fee
+// 4. Recording the offer as closed in the registry and the maker's index, and the volume settled so far
+// 5. Emitting an OfferTaken event for the whole offer
+// The taker pays the rest with pay_installment, each within installment_interval seconds of the last,
+// and the maker can reclaim the token a not yet released with cancel_default if they fall behind
+pub fn take_offer_in_installments(
+    context: Context<TakeOfferInInstallments>,
+    installment_count: u8,
+    installment_interval: i64,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    // set_installment_terms only accepts offers with a single vault and no other way of settling
+    let installment_terms = offer
+        .installment_terms()
+        .ok_or(ErrorCode::InstallmentsNotAllowed)?;
+    require!(
+        installment_count >= 2 && installment_count <= installment_terms.max_installments,
+        ErrorCode::InvalidInstallmentCount
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    // Dutch auctions want a different amount depending on when the offer is taken, the plan fixes it
+    let token_b_wanted_amount = get_token_b_wanted_amount(&offer, clock.unix_timestamp)?;
+
+    // The maker could shorten the interval or reprice the offer before this transaction lands
+    require!(
+        installment_terms.interval == installment_interval
+            && offer.token_a_offered_amount == expected_token_a_amount
+            && token_b_wanted_amount <= expected_token_b_amount,
+        ErrorCode::OfferTermsChanged
+    );
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+
+    // Move the offered tokens from the vault to the installment vault
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.installment_vault,
+        &context.accounts.vault.amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        Some(&vault_authority_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        Some(&vault_authority_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so pay out what the installment vault actually holds
+    context.accounts.installment_vault.reload()?;
+    let token_a_amount = context.accounts.installment_vault.amount;
+
+    context
+        .accounts
+        .installment_plan
+        .set_inner(InstallmentPlan {
+            offer: offer_key,
+            offer_id: offer.id,
+            maker: offer.maker,
+            maker_authority: offer.maker_authority,
+            taker: context.accounts.taker.key(),
+            token_mint_a: offer.token_mint_a,
+            token_mint_b: offer.token_mint_b,
+            token_a_amount,
+            token_b_amount: token_b_wanted_amount,
+            token_a_released: 0,
+            token_b_paid: 0,
+            fee_bps: maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+            installment_count,
+            installments_paid: 0,
+            start_timestamp: clock.unix_timestamp,
+            installment_interval,
+            created_at: offer.created_at,
+            bump: context.bumps.installment_plan,
+        });
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    let (token_a_installment_amount, token_b_installment_amount) =
+        get_next_installment(&context.accounts.installment_plan)?;
+
+    // Send the first installment of the wanted tokens from the taker to the maker
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_installment_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    // The installment plan owns the installment vault, so it signs for the release
+    let installment_plan_seeds = &[
+        b"installment_plan",
+        offer_key.as_ref(),
+        &[context.bumps.installment_plan],
+    ];
+    let signers_seeds = Some(&installment_plan_seeds[..]);
+
+    let protocol_fee = calculate_fee(
+        token_a_installment_amount,
+        context.accounts.installment_plan.fee_bps,
+    )?;
+
+    // Send the protocol fee from the installment vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.installment_vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &context.accounts.installment_plan.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Release the first installment of the offered tokens to the taker
+    transfer_tokens(
+        &context.accounts.installment_vault,
+        &context.accounts.taker_token_account_a,
+        &(token_a_installment_amount - protocol_fee),
+        &context.accounts.token_mint_a,
+        &context.accounts.installment_plan.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    let installment_plan = &mut context.accounts.installment_plan;
+    installment_plan.installments_paid = 1;
+    installment_plan.token_a_released = token_a_installment_amount;
+    installment_plan.token_b_paid = token_b_installment_amount;
+
+    // The offer is no longer open, it's filled once the last installment is paid, see pay_installment
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        token_a_installment_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount,
+            token_b_amount: token_b_wanted_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use super::take_offer::{take_offer, TakeOffer};
+use crate::{constants::NFT_SUPPLY, error::ErrorCode};
+
+// Handle the take offer NFT instruction by:
+// 1. Checking the offer is for an NFT, and that it's from the collection the taker expects, if they gave one
+// 2. Taking the offer like any other, an NFT offer always holds the whole supply of one
+// The collection was checked against the NFT's metadata when the offer was made
+pub fn take_offer_nft<'info>(
+    context: Context<'_, '_, 'info, 'info, TakeOffer<'info>>,
+    expected_collection: Option<Pubkey>,
+    expected_token_b_amount: u64,
+    preimage: Option<Vec<u8>>,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(offer.is_nft(), ErrorCode::NotAnNft);
+
+    if let Some(expected_collection) = expected_collection {
+        require!(
+            offer.nft_collection() == Some(expected_collection),
+            ErrorCode::CollectionMismatch
+        );
+    }
+
+    take_offer(context, NFT_SUPPLY, expected_token_b_amount, preimage, None)
+}
@@ -0,0 +1,625 @@
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed, maker_fee_bps,
+    record_maker_offer_filled, record_maker_volume, record_offers_closed, record_volume_settled,
+    remove_from_maker_index, require_mint_not_denied, require_offer_not_expired,
+    require_offer_old_enough, require_offer_open, require_royalties_paid_by_take_offer,
+    transfer_tokens, transfer_tokens_and_verify, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferStatus, Registry},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOfferRoute<'info> {
+    // Used to create the taker's and makers' token accounts if they don't have them yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Token a's token program, each of the three tokens can belong to either token program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // The intermediate token's token program, the same as token_program if both tokens belong to one
+    pub token_program_b: Interface<'info, TokenInterface>,
+
+    // The token program of the token the taker pays with
+    pub token_program_c: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Pays with token c and receives token a, paying for the route's transient account while it exists
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the first offer's maker by the first offer's constraints
+    #[account(mut)]
+    pub first_maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the first offer's rent payer, who gets the rent for the offer and its vault back
+    #[account(mut)]
+    pub first_rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the second offer's maker by the second offer's constraints
+    #[account(mut)]
+    pub second_maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the second offer's rent payer, who gets the rent for the offer and its vault back
+    #[account(mut)]
+    pub second_rent_payer: UncheckedAccount<'info>,
+
+    // What the taker ends up with, offered by the first offer
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    // The intermediate token, wanted by the first offer and offered by the second
+    #[account(mint::token_program = token_program_b)]
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    // What the taker pays with, wanted by the second offer
+    #[account(mint::token_program = token_program_c)]
+    pub token_mint_c: Box<InterfaceAccount<'info, Mint>>,
+
+    // Offers token a for token b, closed by the handler unless its maker asked to keep the offer's history
+    #[account(
+        mut,
+        constraint = first_offer.load()?.maker == first_maker.key() @ ErrorCode::InvalidMaker,
+        constraint = first_offer.load()?.rent_payer == first_rent_payer.key() @ ErrorCode::InvalidRentPayer,
+        constraint = first_offer.load()?.token_mint_a == token_mint_a.key() @ ErrorCode::WrongTokenMint,
+        constraint = first_offer.load()?.token_mint_b == token_mint_b.key() @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", first_offer.load()?.maker.as_ref(), first_offer.load()?.id.to_le_bytes().as_ref()],
+        bump = first_offer.load()?.bump
+    )]
+    pub first_offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the first offer's vault
+    #[account(
+        seeds = [b"vault_authority", first_offer.key().as_ref()],
+        bump = first_offer.load()?.vault_authority_bump
+    )]
+    pub first_vault_authority: UncheckedAccount<'info>,
+
+    // The first offer's vault, delegated offers can't be routed
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = first_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub first_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Offers token b for token c, closed by the handler unless its maker asked to keep the offer's history
+    #[account(
+        mut,
+        constraint = second_offer.load()?.maker == second_maker.key() @ ErrorCode::InvalidMaker,
+        constraint = second_offer.load()?.rent_payer == second_rent_payer.key() @ ErrorCode::InvalidRentPayer,
+        constraint = second_offer.load()?.token_mint_a == token_mint_b.key() @ ErrorCode::WrongTokenMint,
+        constraint = second_offer.load()?.token_mint_b == token_mint_c.key() @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", second_offer.load()?.maker.as_ref(), second_offer.load()?.id.to_le_bytes().as_ref()],
+        bump = second_offer.load()?.bump
+    )]
+    pub second_offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the second offer's vault
+    #[account(
+        seeds = [b"vault_authority", second_offer.key().as_ref()],
+        bump = second_offer.load()?.vault_authority_bump
+    )]
+    pub second_vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = second_vault_authority,
+        associated_token::token_program = token_program_b
+    )]
+    pub second_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Holds no data, it only owns and signs for the route's transient account
+    #[account(
+        seeds = [b"route_authority", taker.key().as_ref()],
+        bump
+    )]
+    pub route_authority: UncheckedAccount<'info>,
+
+    // Holds the intermediate token between the two legs, created and closed within the instruction
+    #[account(
+        init,
+        payer = taker,
+        seeds = [b"route", taker.key().as_ref(), token_mint_b.key().as_ref()],
+        bump,
+        token::mint = token_mint_b,
+        token::authority = route_authority,
+        token::token_program = token_program_b
+    )]
+    pub route_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Checked to be the taker's associated token account for token a, the handler creates it if the taker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &taker.key(),
+            &token_mint_a.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub taker_token_account_a: UncheckedAccount<'info>,
+
+    /// CHECK: Checked to be the taker's associated token account for token b
+    /// Only created, by the handler, if the second offer holds more of token b than the first offer wants
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &taker.key(),
+            &token_mint_b.key(),
+            &token_program_b.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub taker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_c,
+        token::authority = taker,
+        token::token_program = token_program_c
+    )]
+    pub taker_token_account_c: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Checked to be the first maker's associated token account for token b, the handler creates it if the maker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &first_maker.key(),
+            &token_mint_b.key(),
+            &token_program_b.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub first_maker_token_account_b: UncheckedAccount<'info>,
+
+    /// CHECK: Checked to be the second maker's associated token account for token c, the handler creates it if the maker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &second_maker.key(),
+            &token_mint_c.key(),
+            &token_program_c.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub second_maker_token_account_c: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_index", first_maker.key().as_ref()],
+        bump = first_maker_index.bump
+    )]
+    pub first_maker_index: Box<Account<'info, MakerIndex>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_stats", first_maker.key().as_ref()],
+        bump = first_maker_stats.bump
+    )]
+    pub first_maker_stats: Box<Account<'info, MakerStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_index", second_maker.key().as_ref()],
+        bump = second_maker_index.bump
+    )]
+    pub second_maker_index: Box<Account<'info, MakerIndex>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_stats", second_maker.key().as_ref()],
+        bump = second_maker_stats.bump
+    )]
+    pub second_maker_stats: Box<Account<'info, MakerStats>>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collects the protocol fee for the token b left over between the two offers, which goes to the taker
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_b.key().as_ref()],
+        bump,
+        token::mint = token_mint_b,
+        token::authority = config,
+        token::token_program = token_program_b
+    )]
+    pub fee_vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: each token's deny entry address
+    /// The handler checks the addresses, and that none has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_c_deny_entry: Option<UncheckedAccount<'info>>,
+}
+
+// Handle the take offer route instruction by:
+// 1. Checking both offers are open, fixed price offers, the first offering token a for token b and the second
+//    offering token b for token c, that the taker made neither, and that the second offers at least what the first wants
+//    The first offer still has to offer what the taker expects, and the second can't want more than they'll pay
+// 2. Paying the second maker the token c they want from the taker
+//    If token c charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+// 3. Moving everything in the second offer's vault into the route's transient account and closing the vault
+// 4. Paying the first maker the token b they want out of the transient account, sending whatever is left
+//    to the taker, less the second maker's protocol fee, and closing the transient account
+// 5. Withdrawing the first offer's vault to the taker, less the first maker's protocol fee, and closing the vault
+//    Like take_offer, the protocol fee is charged on what reaches the taker, from each offer
+// 6. Recording both settlements in the registry and each maker's index and statistics
+//    Each offer is closed, or marked filled if its maker asked to keep its history
+// 7. Emitting an OfferTaken event for each offer, with the taker as the taker of both
+// Takers holding only token c can take an offer wanting token b, the intermediate token never needs an account of theirs
+pub fn take_offer_route(
+    context: Context<TakeOfferRoute>,
+    expected_token_a_amount: u64,
+    maximum_token_c_amount: u64,
+) -> Result<()> {
+    let first_offer = *context.accounts.first_offer.load()?;
+    let second_offer = *context.accounts.second_offer.load()?;
+    let clock = Clock::get()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    for offer in [&first_offer, &second_offer] {
+        require_offer_open(offer)?;
+        require_offer_not_expired(offer, clock.unix_timestamp)?;
+        require_royalties_paid_by_take_offer(offer, &context.accounts.config)?;
+        require_offer_old_enough(offer, &context.accounts.config, clock.slot)?;
+        // A route settles both offers in full at the prices their makers set
+        require!(
+            offer.dutch_auction().is_none()
+                && offer.english_auction().is_none()
+                && offer.hashlock().is_none()
+                && offer.attestor().is_none()
+                && offer.eth_taker().is_none()
+                && offer.vesting_duration().is_none()
+                && offer.arbiter().is_none()
+                && !offer.is_delegated()
+                && !offer.allowlist_enabled()
+                && offer.additional_token_mints_a().is_empty(),
+            ErrorCode::RouteNotSupported
+        );
+        require!(
+            context.accounts.taker.key() != offer.maker,
+            ErrorCode::SelfTradeNotAllowed
+        );
+    }
+    require_mint_not_denied(
+        &context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &context.accounts.token_mint_c.key(),
+        context.accounts.token_mint_c_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    require!(
+        first_offer.token_a_offered_amount == expected_token_a_amount,
+        ErrorCode::OfferTermsChanged
+    );
+    require!(
+        second_offer.token_b_wanted_amount <= maximum_token_c_amount,
+        ErrorCode::OfferTermsChanged
+    );
+    // We route the vault's real balance, so any Token-2022 transfer fee on the deposit isn't paid for twice
+    require!(
+        context.accounts.second_vault.amount >= first_offer.token_b_wanted_amount,
+        ErrorCode::RouteLegsDontMatch
+    );
+
+    // Pay the second maker with the taker's token c
+    let second_maker_token_account_c_created = create_associated_token_account_if_needed(
+        &context
+            .accounts
+            .second_maker_token_account_c
+            .to_account_info(),
+        &context.accounts.second_maker.to_account_info(),
+        &context.accounts.token_mint_c.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program_c,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+    let token_c_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_c,
+        &context.accounts.second_maker_token_account_c,
+        &second_offer.token_b_wanted_amount,
+        &context.accounts.token_mint_c,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program_c,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    // Move the second offer's token b into the route's transient account
+    let second_offer_key = context.accounts.second_offer.key();
+    let second_vault_authority_seeds = &[
+        b"vault_authority",
+        second_offer_key.as_ref(),
+        &[second_offer.vault_authority_bump],
+    ];
+    let second_vault_authority_info = context.accounts.second_vault_authority.to_account_info();
+    let token_b_spent_amount = context.accounts.second_vault.amount;
+    transfer_tokens(
+        &context.accounts.second_vault,
+        &*context.accounts.route_token_account,
+        &token_b_spent_amount,
+        &context.accounts.token_mint_b,
+        &second_vault_authority_info,
+        &context.accounts.token_program_b,
+        Some(&second_vault_authority_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    close_token_account(
+        &context.accounts.second_vault,
+        &context.accounts.second_rent_payer.to_account_info(),
+        &second_vault_authority_info,
+        &context.accounts.token_program_b,
+        Some(&second_vault_authority_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // Net the token b leg: the first maker gets what they want, the taker gets anything left over
+    let taker_key = context.accounts.taker.key();
+    let route_authority_seeds = &[
+        b"route_authority",
+        taker_key.as_ref(),
+        &[context.bumps.route_authority],
+    ];
+    let route_authority_info = context.accounts.route_authority.to_account_info();
+    let first_maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context
+            .accounts
+            .first_maker_token_account_b
+            .to_account_info(),
+        &context.accounts.first_maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program_b,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+    context.accounts.route_token_account.reload()?;
+    // A transfer fee on the way into the transient account, or on top of what the first maker wants,
+    // can leave it short of what the first maker wants
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.route_token_account,
+        &context.accounts.first_maker_token_account_b,
+        &first_offer.token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &route_authority_info,
+        &context.accounts.token_program_b,
+        Some(&route_authority_seeds[..]),
+        ErrorCode::RouteLegsDontMatch,
+    )?;
+    context.accounts.route_token_account.reload()?;
+    let token_b_surplus_amount = context.accounts.route_token_account.amount;
+    let token_b_surplus_fee = calculate_fee(
+        token_b_surplus_amount,
+        maker_fee_bps(
+            &context.accounts.config,
+            &context.accounts.second_maker_stats,
+        ),
+    )?;
+    if token_b_surplus_fee > 0 {
+        transfer_tokens(
+            &context.accounts.route_token_account,
+            &*context.accounts.fee_vault_b,
+            &token_b_surplus_fee,
+            &context.accounts.token_mint_b,
+            &route_authority_info,
+            &context.accounts.token_program_b,
+            Some(&route_authority_seeds[..]),
+        )?;
+    }
+    let token_b_taker_amount = token_b_surplus_amount - token_b_surplus_fee;
+    if token_b_taker_amount > 0 {
+        create_associated_token_account_if_needed(
+            &context.accounts.taker_token_account_b.to_account_info(),
+            &context.accounts.taker.to_account_info(),
+            &context.accounts.token_mint_b.to_account_info(),
+            &context.accounts.taker.to_account_info(),
+            &context.accounts.token_program_b,
+            &context.accounts.associated_token_program,
+            &context.accounts.system_program,
+        )?;
+        transfer_tokens(
+            &context.accounts.route_token_account,
+            &context.accounts.taker_token_account_b,
+            &token_b_taker_amount,
+            &context.accounts.token_mint_b,
+            &route_authority_info,
+            &context.accounts.token_program_b,
+            Some(&route_authority_seeds[..]),
+        )?;
+    }
+    close_token_account(
+        &context.accounts.route_token_account,
+        &context.accounts.taker.to_account_info(),
+        &route_authority_info,
+        &context.accounts.token_program_b,
+        Some(&route_authority_seeds[..]),
+    )?;
+
+    // Withdraw the first offer's token a to the taker, less the protocol fee
+    let first_offer_key = context.accounts.first_offer.key();
+    let first_vault_authority_seeds = &[
+        b"vault_authority",
+        first_offer_key.as_ref(),
+        &[first_offer.vault_authority_bump],
+    ];
+    let first_vault_authority_info = context.accounts.first_vault_authority.to_account_info();
+    let token_a_amount = context.accounts.first_vault.amount;
+    let fee_bps = maker_fee_bps(
+        &context.accounts.config,
+        &context.accounts.first_maker_stats,
+    );
+    let protocol_fee = calculate_fee(token_a_amount, fee_bps)?;
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.first_vault,
+            &*context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &first_vault_authority_info,
+            &context.accounts.token_program,
+            Some(&first_vault_authority_seeds[..]),
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+    create_associated_token_account_if_needed(
+        &context.accounts.taker_token_account_a.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_mint_a.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+    let token_a_received_amount = transfer_tokens_and_verify(
+        &context.accounts.first_vault,
+        &context.accounts.taker_token_account_a,
+        &(token_a_amount - protocol_fee),
+        &context.accounts.token_mint_a,
+        &first_vault_authority_info,
+        &context.accounts.token_program,
+        Some(&first_vault_authority_seeds[..]),
+        ErrorCode::FailedVaultWithdrawal,
+    )?;
+    close_token_account(
+        &context.accounts.first_vault,
+        &context.accounts.first_rent_payer.to_account_info(),
+        &first_vault_authority_info,
+        &context.accounts.token_program,
+        Some(&first_vault_authority_seeds[..]),
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    record_offers_closed(&mut context.accounts.registry, 2)?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+    record_volume_settled(&mut context.accounts.registry, token_c_received_amount)?;
+    remove_from_maker_index(&mut context.accounts.first_maker_index, first_offer.id);
+    record_maker_offer_filled(&mut context.accounts.first_maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.first_maker_stats,
+        token_a_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    remove_from_maker_index(&mut context.accounts.second_maker_index, second_offer.id);
+    record_maker_offer_filled(&mut context.accounts.second_maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.second_maker_stats,
+        token_b_spent_amount,
+        token_c_received_amount,
+        &context.accounts.token_mint_c.key(),
+        &context.accounts.config,
+    )?;
+
+    // Close the offers and return the rent to whoever paid for them
+    // Offers that keep their history stay open as a record of the fill, until claim_rent closes them
+    if first_offer.keep_history() {
+        context
+            .accounts
+            .first_offer
+            .load_mut()?
+            .set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .first_offer
+            .close(context.accounts.first_rent_payer.to_account_info())?;
+    }
+    if second_offer.keep_history() {
+        context
+            .accounts
+            .second_offer
+            .load_mut()?
+            .set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .second_offer
+            .close(context.accounts.second_rent_payer.to_account_info())?;
+    }
+
+    emit_event(
+        OfferTaken {
+            offer_id: first_offer.id,
+            maker: first_offer.maker,
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_received_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created: first_maker_token_account_b_created,
+            created_at: first_offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+    emit_event(
+        OfferTaken {
+            offer_id: second_offer.id,
+            maker: second_offer.maker,
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_b.key(),
+            token_mint_b: context.accounts.token_mint_c.key(),
+            token_a_amount: token_b_spent_amount,
+            token_b_amount: token_c_received_amount,
+            maker_token_account_b_created: second_maker_token_account_c_created,
+            created_at: second_offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_token_b_wanted_amount, maker_fee_bps, record_maker_offer_filled, record_maker_volume,
+    record_offers_closed, record_volume_settled, remove_from_maker_index,
+    require_offer_not_expired, require_offer_old_enough, require_offer_open,
+    require_royalties_paid_by_take_offer, transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, Registry, Vesting},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOfferVested<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Offers whose mints belong to different token programs are taken with take_offer
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Holds the taker's token a until it vests, the taker pays the rent and gets it back on their last claim
+    #[account(
+        init,
+        payer = taker,
+        space = Vesting::DISCRIMINATOR.len() + Vesting::INIT_SPACE,
+        seeds = [b"vesting", offer.key().as_ref()],
+        bump
+    )]
+    pub vesting: Account<'info, Vesting>,
+
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vesting,
+        associated_token::token_program = token_program,
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+}
+
+// Handle the take offer vested instruction by:
+// 1. Moving the offered tokens from the vault to the vesting vault, less the protocol fee, and closing the vault
+// 2. Sending the wanted tokens from the taker to the maker straight away
+// 3. Starting the vesting schedule, so the taker can claim token a with claim_vested as it vests
+// 4. Recording the settlement in the registry, the maker's index and the maker's statistics
+// 5. Emitting an OfferTaken event
+pub fn take_offer_vested(
+    context: Context<TakeOfferVested>,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    let vesting_duration = offer.vesting_duration().ok_or(ErrorCode::NotVested)?;
+    // make_offer doesn't allow vesting with auctions or bundles, hashlocks need take_offer
+    require!(
+        offer.hashlock().is_none(),
+        ErrorCode::OfferIsHashlocked
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    // Dutch auctions want a different amount depending on when the offer is taken
+    let token_b_wanted_amount = get_token_b_wanted_amount(&offer, clock.unix_timestamp)?;
+
+    require!(
+        offer.token_a_offered_amount == expected_token_a_amount,
+        ErrorCode::OfferTermsChanged
+    );
+    require!(
+        token_b_wanted_amount <= expected_token_b_amount,
+        ErrorCode::OfferTermsChanged
+    );
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+
+    // Read before the vault is emptied and closed, the maker's volume records it too
+    let vault_amount = context.accounts.vault.amount;
+    let protocol_fee = calculate_fee(
+        vault_amount,
+        maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+    )?;
+    let token_a_vesting_amount = vault_amount - protocol_fee;
+
+    // Send the protocol fee from the vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &context.accounts.vault_authority.to_account_info(),
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Move the rest of the offered tokens from the vault to the vesting vault
+    transfer_tokens(
+        &context.accounts.vault,
+        &context.accounts.vesting_vault,
+        &token_a_vesting_amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    // Close the vault and return the rent to whoever paid for it
+    close_token_account(
+        &context.accounts.vault,
+        &context.accounts.rent_payer.to_account_info(),
+        &context.accounts.vault_authority.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Send the wanted tokens from the taker to the maker
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        vault_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so vest what the vesting vault actually holds
+    context.accounts.vesting_vault.reload()?;
+    let token_a_received_amount = context.accounts.vesting_vault.amount;
+
+    let end_timestamp = clock
+        .unix_timestamp
+        .checked_add(vesting_duration)
+        .ok_or(ErrorCode::MathOverflow)?;
+    context.accounts.vesting.set_inner(Vesting {
+        offer: context.accounts.offer.key(),
+        beneficiary: context.accounts.taker.key(),
+        token_mint: context.accounts.token_mint_a.key(),
+        total_amount: token_a_received_amount,
+        claimed_amount: 0,
+        start_timestamp: clock.unix_timestamp,
+        end_timestamp,
+        bump: context.bumps.vesting,
+    });
+
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker: context.accounts.taker.key(),
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_received_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,443 @@
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_token_b_wanted_amount, get_token_program_b, maker_fee_bps, record_maker_offer_filled,
+    record_maker_volume, record_offers_closed, record_volume_settled, remove_from_maker_index,
+    require_can_send, require_mint_not_denied, require_offer_not_expired, require_offer_old_enough,
+    require_offer_open, require_royalties_paid_by_take_offer, require_taker_authorization,
+    transfer_tokens, transfer_tokens_and_verify, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, OfferAllowlist, OfferStatus, Registry},
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program_option::COption, sysvar},
+};
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOfferWithAuthorization<'info> {
+    // Used to create the taker's token a account and the maker's token b account if they don't have them yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    // Submits the transaction and pays its fees and any rent, so the taker doesn't need SOL
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    /// CHECK: Doesn't sign the transaction, the handler checks it signed the authorization instead
+    pub taker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's maker with has_one, makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the offer's rent payer with has_one, who gets the rent for the offer and its vaults back
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Checked against token_program_b, if token b belongs to the other token program, by the handler
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Any of the taker's token b accounts, with the taker authority approved as its delegate
+    // Token b can belong to the other token program from token a, the handler checks token_program_b owns its mint
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = taker
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Holds no data, it's approved as the delegate on the taker's token b account
+    /// Each taker has their own, so one taker's approval can never pay for another taker's offer
+    #[account(
+        seeds = [b"taker_authority", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    /// Derived with token b's own token program, which can differ from token a's
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            token_mint_b.to_account_info().owner
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    // Closed by the handler, unless the maker asked to keep the offer's history
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = rent_payer @ ErrorCode::InvalidRentPayer,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    // The offer's vault, or for delegated offers the maker's token a account, which the vault authority is approved to spend from
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer.load()?.token_a_holder(&vault_authority.key()),
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed if the maker has enabled an allowlist for this offer
+    // Allowlists are only ever created at the offer's allowlist PDA, so has_one is enough
+    #[account(
+        mut,
+        close = maker,
+        has_one = offer @ ErrorCode::WrongOffer
+    )]
+    pub allowlist: Option<Account<'info, OfferAllowlist>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token a's and token b's deny entry addresses
+    /// The handler checks the addresses, and that neither has a deny entry
+    pub token_mint_a_deny_entry: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: See token_mint_a_deny_entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+
+    // Only needed when token b belongs to the other token program from token a
+    // The handler checks it owns token b's mint
+    pub token_program_b: Option<Interface<'info, TokenInterface>>,
+
+    /// CHECK: Checked to be the instructions sysvar
+    /// The handler reads the transaction's earlier instructions from it, looking for the taker's signature
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+// Handle the take offer with authorization instruction by:
+// 1. Checking the taker signed an authorization for the offer, with an earlier ed25519 program instruction verifying
+//    their signature over the offer, the amounts they expect and when the authorization expires,
+//    see require_taker_authorization
+// 2. Checking the offer still has the terms the taker authorized, like take_offer
+// 3. Withdrawing the offered tokens from the vault to the taker, less the protocol fee, and closing the vault
+//    Delegated offers pull the tokens from the maker's account instead, and have no vault to close
+// 4. Sending the wanted tokens from the taker's token b account to the maker, signed for by the taker authority
+//    the taker approved as its delegate
+// 5. Recording the settlement in the registry, the maker's index and the maker's statistics
+//    The offer is closed, or marked filled if the maker asked to keep its history
+// 6. Emitting an OfferTaken event
+// The relayer pays the transaction fee and any rent, so wallets can sponsor takes for users without SOL
+// The taker approves their taker authority once, for as much token b as they want relayers to be able to settle,
+// and each take spends from that approval
+// Authorizations expire, so a relayer can't hold on to one in case the offer is never settled
+// Like take_offer_with_token_accounts, bundles, hashlocks, attestors, Ethereum takers, referrers, memos,
+// royalties and the membership discount all need take_offer
+pub fn take_offer_with_authorization(
+    context: Context<TakeOfferWithAuthorization>,
+    expected_token_a_amount: u64,
+    expected_token_b_amount: u64,
+    authorization_expires_at: i64,
+) -> Result<()> {
+    let offer = *context.accounts.offer.load()?;
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp <= authorization_expires_at,
+        ErrorCode::AuthorizationExpired
+    );
+    require_taker_authorization(
+        &context.accounts.instructions_sysvar,
+        &context.accounts.taker.key(),
+        &context.accounts.offer.key(),
+        &offer,
+        expected_token_a_amount,
+        expected_token_b_amount,
+        authorization_expires_at,
+    )?;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require_offer_open(&offer)?;
+    require_mint_not_denied(
+        &offer.token_mint_a,
+        context.accounts.token_mint_a_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_mint_not_denied(
+        &offer.token_mint_b,
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+    require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+    require!(
+        context.accounts.taker.key() != offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    require!(offer.hashlock().is_none(), ErrorCode::OfferIsHashlocked);
+    require!(offer.attestor().is_none(), ErrorCode::OfferIsAttested);
+    require!(offer.eth_taker().is_none(), ErrorCode::OfferHasEthTaker);
+    require!(offer.vesting_duration().is_none(), ErrorCode::OfferIsVested);
+    require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+    require!(offer.arbiter().is_none(), ErrorCode::OfferIsArbitrated);
+    require!(
+        offer.additional_token_mints_a().is_empty(),
+        ErrorCode::BundleNotSupported
+    );
+
+    // Makers can restrict who may take an offer
+    if offer.allowlist_enabled() {
+        let allowlist = context
+            .accounts
+            .allowlist
+            .as_ref()
+            .ok_or(ErrorCode::AllowlistRequired)?;
+        require!(
+            allowlist.takers.contains(&context.accounts.taker.key()),
+            ErrorCode::TakerNotAllowed
+        );
+    }
+
+    let token_program_b = get_token_program_b(
+        &context.accounts.token_mint_b,
+        &context.accounts.token_program,
+        context.accounts.token_program_b.as_ref(),
+    )?;
+
+    // Dutch auctions want a different amount depending on when the offer is taken
+    let token_b_wanted_amount = get_token_b_wanted_amount(&offer, clock.unix_timestamp)?;
+
+    require!(
+        offer.token_a_offered_amount == expected_token_a_amount,
+        ErrorCode::OfferTermsChanged
+    );
+    require!(
+        token_b_wanted_amount <= expected_token_b_amount,
+        ErrorCode::OfferTermsChanged
+    );
+
+    // The taker authority can only spend what the taker approved it for
+    // A transfer fee on token b comes on top, which transfer_tokens_including_fee fails on if the approval doesn't cover it
+    let taker_token_account_b = &context.accounts.taker_token_account_b;
+    require!(
+        taker_token_account_b.delegate == COption::Some(context.accounts.taker_authority.key())
+            && taker_token_account_b.delegated_amount >= token_b_wanted_amount,
+        ErrorCode::TakerDelegationMissing
+    );
+
+    let offer_key = context.accounts.offer.key();
+    let vault_authority_seeds = &[
+        b"vault_authority",
+        offer_key.as_ref(),
+        &[offer.vault_authority_bump],
+    ];
+    let signers_seeds = Some(&vault_authority_seeds[..]);
+    let vault_authority_info = context.accounts.vault_authority.to_account_info();
+
+    // We settle the vault's real balance, or for delegated offers the amount the maker approved
+    let token_a_amount = if offer.is_delegated() {
+        let maker_token_account_a = &context.accounts.vault;
+        require!(
+            maker_token_account_a.delegate == COption::Some(context.accounts.vault_authority.key())
+                && maker_token_account_a.delegated_amount >= offer.token_a_offered_amount,
+            ErrorCode::DelegationRevoked
+        );
+        require_can_send(
+            maker_token_account_a,
+            offer.token_a_offered_amount,
+            ErrorCode::InsufficientMakerBalance,
+        )?;
+        offer.token_a_offered_amount
+    } else {
+        context.accounts.vault.amount
+    };
+    let protocol_fee = calculate_fee(
+        token_a_amount,
+        maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+    )?;
+    let token_a_taker_amount = token_a_amount - protocol_fee;
+
+    // Send the protocol fee from the vault to the fee vault
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.vault,
+            &context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_a,
+            &vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+    }
+
+    // Withdraw the rest of the offered tokens from the vault to the taker, checking what actually arrived
+    let token_a_received_amount = transfer_tokens_and_verify(
+        &context.accounts.vault,
+        &context.accounts.taker_token_account_a,
+        &token_a_taker_amount,
+        &context.accounts.token_mint_a,
+        &vault_authority_info,
+        &context.accounts.token_program,
+        signers_seeds,
+        ErrorCode::FailedVaultWithdrawal,
+    )?;
+
+    if !offer.is_delegated() {
+        close_token_account(
+            &context.accounts.vault,
+            &context.accounts.rent_payer.to_account_info(),
+            &vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+    }
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.relayer.to_account_info(),
+        token_program_b,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    // Send the wanted tokens from the taker to the maker, with the taker authority signing as the delegate
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let taker = context.accounts.taker.key();
+    let taker_authority_seeds = &[
+        b"taker_authority",
+        taker.as_ref(),
+        &[context.bumps.taker_authority],
+    ];
+    let token_b_received_amount = transfer_tokens_including_fee(
+        taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_wanted_amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker_authority.to_account_info(),
+        token_program_b,
+        Some(&taker_authority_seeds[..]),
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, 1)?;
+    remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+    record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+    record_maker_volume(
+        &mut context.accounts.maker_stats,
+        token_a_amount,
+        token_b_received_amount,
+        &context.accounts.token_mint_b.key(),
+        &context.accounts.config,
+    )?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    // Close the offer and return the rent to whoever paid for it
+    // Offers that keep their history stay open as a record of the fill, until claim_rent closes them
+    if offer.keep_history() {
+        context.accounts.offer.load_mut()?.set_status(OfferStatus::Filled);
+    } else {
+        context
+            .accounts
+            .offer
+            .close(context.accounts.rent_payer.to_account_info())?;
+    }
+
+    emit_event(
+        OfferTaken {
+            offer_id: offer.id,
+            maker: context.accounts.maker.key(),
+            taker,
+            token_mint_a: context.accounts.token_mint_a.key(),
+            token_mint_b: context.accounts.token_mint_b.key(),
+            token_a_amount: token_a_received_amount,
+            token_b_amount: token_b_received_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: taker,
+        },
+        &context.accounts.event_authority,
+        context.bumps.event_authority,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,336 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use super::shared::{
+    calculate_fee, close_token_account, create_associated_token_account_if_needed,
+    get_token_b_wanted_amount, maker_fee_bps, record_maker_offer_filled, record_maker_volume,
+    record_offers_closed, record_volume_settled, remove_from_maker_index,
+    require_offer_not_expired, require_offer_old_enough, require_royalties_paid_by_take_offer,
+    transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    events::{emit_event, OfferTaken},
+    state::{Config, MakerIndex, MakerStats, Offer, Registry},
+};
+
+// Each offer in a batch is passed as three remaining accounts: [offer, vault authority, vault]
+pub const ACCOUNTS_PER_BATCH_OFFER: usize = 3;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeOffersBatch<'info> {
+    // Used to manage associated token accounts
+    // ie where a wallet holds a specific type of token
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Every offer in the batch must be from this maker, which the handler checks
+    /// Makers can be program owned accounts like multisig vaults
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    // Offers whose mints belong to different token programs are taken with take_offer
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet, or checks the maker still owns it
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Protocol-wide offer statistics, updated whenever an offer is made or closed
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    // The maker's open offers, so clients can list them without scanning every offer
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // The maker's lifetime offer statistics
+    #[account(
+        mut,
+        seeds = [b"maker_stats", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Collects the protocol fee for token a, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the take offers batch instruction by, for each offer in the remaining accounts:
+// 1. Checking the offer is from the maker, for the same pair of mints, and can be taken like take_offer would
+// 2. Withdrawing the offered tokens from the vault to the taker, less the protocol fee
+// 3. Closing the vault and the offer, returning the rent to the maker
+// 4. Emitting an OfferTaken event
+// Then sending the total wanted tokens from the taker to the maker in a single transfer,
+// and recording every settlement in the registry, the maker's index and the maker's statistics
+// Offers with an allowlist, bundles, English auctions and sponsored rent have to be taken one at a time
+pub fn take_offers_batch<'info>(
+    context: Context<'_, '_, 'info, 'info, TakeOffersBatch<'info>>,
+) -> Result<()> {
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(
+        !context.remaining_accounts.is_empty()
+            && context.remaining_accounts.len().is_multiple_of(ACCOUNTS_PER_BATCH_OFFER),
+        ErrorCode::InvalidRemainingAccounts
+    );
+
+    let clock = Clock::get()?;
+    let mut token_b_wanted_total: u64 = 0;
+    let mut taken_offers: Vec<OfferTaken> = Vec::new();
+
+    // Makers don't need a token b account to make an offer, so create one for them if they don't have one yet
+    let maker_token_account_b_created = create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+
+    for accounts in context
+        .remaining_accounts
+        .chunks(ACCOUNTS_PER_BATCH_OFFER)
+    {
+        let offer_info = &accounts[0];
+        let vault_authority_info = &accounts[1];
+        let vault_info = &accounts[2];
+
+        // AccountLoader::try_from checks the owner and discriminator, the seeds check it's the real offer PDA
+        let offer_loader = AccountLoader::<Offer>::try_from(offer_info)?;
+        let offer = *offer_loader.load()?;
+        let offer_id_bytes = offer.id.to_le_bytes();
+        let offer_account_seeds = &[
+            b"offer",
+            offer.maker.as_ref(),
+            &offer_id_bytes[..],
+            &[offer.bump],
+        ];
+        let offer_address = Pubkey::create_program_address(offer_account_seeds, &crate::ID)
+            .map_err(|_| ErrorCode::BatchOfferMismatch)?;
+        require_keys_eq!(offer_address, offer_info.key(), ErrorCode::BatchOfferMismatch);
+
+        // The vault authority signs for the vault, using the bump saved with the offer
+        let vault_authority_seeds = &[
+            b"vault_authority",
+            offer_info.key.as_ref(),
+            &[offer.vault_authority_bump],
+        ];
+        let vault_authority_address =
+            Pubkey::create_program_address(vault_authority_seeds, &crate::ID)
+                .map_err(|_| ErrorCode::BatchOfferMismatch)?;
+        require_keys_eq!(
+            vault_authority_address,
+            vault_authority_info.key(),
+            ErrorCode::BatchOfferMismatch
+        );
+
+        // The maker gets the rent back, so offers a sponsor paid for have to be taken one at a time
+        require!(
+            offer.maker == context.accounts.maker.key()
+                && offer.rent_payer == offer.maker
+                && offer.token_mint_a == context.accounts.token_mint_a.key()
+                && offer.token_mint_b == context.accounts.token_mint_b.key(),
+            ErrorCode::BatchOfferMismatch
+        );
+        require_offer_not_expired(&offer, clock.unix_timestamp)?;
+        require_royalties_paid_by_take_offer(&offer, &context.accounts.config)?;
+        require_offer_old_enough(&offer, &context.accounts.config, clock.slot)?;
+        require!(
+            context.accounts.taker.key() != offer.maker,
+            ErrorCode::SelfTradeNotAllowed
+        );
+        require!(offer.english_auction().is_none(), ErrorCode::OfferIsAuction);
+        require!(!offer.allowlist_enabled(), ErrorCode::TakerNotAllowed);
+        require!(offer.hashlock().is_none(), ErrorCode::OfferIsHashlocked);
+        require!(offer.attestor().is_none(), ErrorCode::OfferIsAttested);
+        require!(offer.eth_taker().is_none(), ErrorCode::OfferHasEthTaker);
+        require!(offer.vesting_duration().is_none(), ErrorCode::OfferIsVested);
+        require!(offer.arbiter().is_none(), ErrorCode::OfferIsArbitrated);
+        require!(
+            offer.additional_token_mints_a().is_empty(),
+            ErrorCode::BundleNotSupported
+        );
+
+        // The vault is always the vault authority's associated token account for token a
+        require_keys_eq!(
+            vault_info.key(),
+            get_associated_token_address_with_program_id(
+                vault_authority_info.key,
+                &offer.token_mint_a,
+                &context.accounts.token_program.key()
+            ),
+            ErrorCode::InvalidVault
+        );
+        let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        // As take_offer's associated_token constraints do, check it holds token a for the vault authority too
+        require!(
+            vault.mint == offer.token_mint_a && vault.owner == vault_authority_info.key(),
+            ErrorCode::InvalidVault
+        );
+
+        let token_b_wanted_amount = get_token_b_wanted_amount(&offer, clock.unix_timestamp)?;
+        token_b_wanted_total = token_b_wanted_total
+            .checked_add(token_b_wanted_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let signers_seeds = Some(&vault_authority_seeds[..]);
+        // The maker's volume grows with each offer, so a batch pays the same fees as taking them one at a time
+        let protocol_fee = calculate_fee(
+            vault.amount,
+            maker_fee_bps(&context.accounts.config, &context.accounts.maker_stats),
+        )?;
+        let token_a_taker_amount = vault.amount - protocol_fee;
+
+        // Send the protocol fee from the vault to the fee vault
+        if protocol_fee > 0 {
+            transfer_tokens(
+                &vault,
+                &context.accounts.fee_vault,
+                &protocol_fee,
+                &context.accounts.token_mint_a,
+                vault_authority_info,
+                &context.accounts.token_program,
+                signers_seeds,
+            )
+            .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+        }
+
+        // Withdraw the rest of the offered tokens from the vault to the taker
+        transfer_tokens(
+            &vault,
+            &context.accounts.taker_token_account_a,
+            &token_a_taker_amount,
+            &context.accounts.token_mint_a,
+            vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+        // Close the vault and the offer, the maker paid for both
+        close_token_account(
+            &vault,
+            &context.accounts.maker.to_account_info(),
+            vault_authority_info,
+            &context.accounts.token_program,
+            signers_seeds,
+        )
+        .map_err(|_| ErrorCode::FailedVaultClosure)?;
+        offer_loader.close(context.accounts.maker.to_account_info())?;
+        remove_from_maker_index(&mut context.accounts.maker_index, offer.id);
+        record_maker_offer_filled(&mut context.accounts.maker_stats)?;
+        record_maker_volume(
+            &mut context.accounts.maker_stats,
+            vault.amount,
+            token_b_wanted_amount,
+            &offer.token_mint_b,
+            &context.accounts.config,
+        )?;
+
+        taken_offers.push(OfferTaken {
+            offer_id: offer.id,
+            maker: offer.maker,
+            taker: context.accounts.taker.key(),
+            token_mint_a: offer.token_mint_a,
+            token_mint_b: offer.token_mint_b,
+            token_a_amount: token_a_taker_amount,
+            token_b_amount: token_b_wanted_amount,
+            maker_token_account_b_created,
+            created_at: offer.created_at,
+            filled_at: clock.unix_timestamp,
+            recipient: context.accounts.taker.key(),
+        });
+    }
+
+    // Send the wanted tokens for every offer from the taker to the maker
+    // If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+    let token_b_received_amount = transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &token_b_wanted_total,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    record_offers_closed(&mut context.accounts.registry, taken_offers.len() as u64)?;
+    record_volume_settled(&mut context.accounts.registry, token_b_received_amount)?;
+
+    for offer_taken in taken_offers {
+        emit_event(
+            offer_taken,
+            &context.accounts.event_authority,
+            context.bumps.event_authority,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use super::shared::{
+    calculate_fee, create_associated_token_account_if_needed, require_mint_not_denied,
+    set_stake_authorities, transfer_tokens, transfer_tokens_including_fee,
+};
+use crate::{
+    error::ErrorCode,
+    state::{Config, StakeOffer},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    stake::{Stake, StakeAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct TakeStakeOffer<'info> {
+    // Hands the stake account's authorities to the taker
+    pub stake_program: Program<'info, Stake>,
+
+    // Used to create the maker's token b account if they don't have one yet
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    // Work with either the classic token program or
+    // the newer token extensions program, whichever owns token b
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Used to create accounts
+    pub system_program: Program<'info, System>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the stake offer's maker with has_one, gets the stake offer's rent back
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub stake_account: Box<Account<'info, StakeAccount>>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_b,
+        token::authority = taker,
+        token::token_program = token_program
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked to be the maker's associated token account for token b, the handler creates it if the maker doesn't have one yet
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &maker.key(),
+            &token_mint_b.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = stake_account,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"stake_offer", stake_account.key().as_ref()],
+        bump = stake_offer.bump
+    )]
+    pub stake_offer: Account<'info, StakeOffer>,
+
+    // The stake program reads the clock when changing authorities
+    pub clock: Sysvar<'info, Clock>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Collects the protocol fee for token b, one fee vault per mint, owned by the config
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"fee_vault", token_mint_b.key().as_ref()],
+        bump,
+        token::mint = token_mint_b,
+        token::authority = config,
+        token::token_program = token_program
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Only needed while the protocol denies any mints: token b's deny entry address
+    /// The handler checks the address, and that it has no deny entry
+    pub token_mint_b_deny_entry: Option<UncheckedAccount<'info>>,
+}
+
+// Handle the take stake offer instruction by:
+// 1. Checking the stake offer hasn't expired, and that the taker isn't the maker
+// 2. Sending the wanted token b from the taker to the maker, less the protocol fee, which goes to the fee vault
+//    Stake offers have no maker statistics, so the fee is the config's base rate without any volume tier
+//    If token b charges a transfer fee, the taker pays it on top, so the maker gets the full amount
+// 3. Handing the stake account's staker and withdrawer authorities from the stake offer to the taker
+// 4. Closing the stake offer and returning its rent to the maker
+// The stake account itself never moves, the taker owns it from here on through its authorities
+pub fn take_stake_offer(context: Context<TakeStakeOffer>) -> Result<()> {
+    let stake_offer = &context.accounts.stake_offer;
+
+    require!(!context.accounts.config.paused, ErrorCode::ProgramPaused);
+    if let Some(expires_at) = stake_offer.expires_at {
+        require!(
+            context.accounts.clock.unix_timestamp < expires_at,
+            ErrorCode::OfferExpired
+        );
+    }
+    require!(
+        context.accounts.taker.key() != stake_offer.maker,
+        ErrorCode::SelfTradeNotAllowed
+    );
+    require_mint_not_denied(
+        &context.accounts.token_mint_b.key(),
+        context.accounts.token_mint_b_deny_entry.as_deref(),
+        &context.accounts.config,
+    )?;
+
+    create_associated_token_account_if_needed(
+        &context.accounts.maker_token_account_b.to_account_info(),
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_mint_b.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        &context.accounts.associated_token_program,
+        &context.accounts.system_program,
+    )?;
+    let protocol_fee = calculate_fee(
+        stake_offer.token_b_wanted_amount,
+        context.accounts.config.fee_bps,
+    )?;
+    if protocol_fee > 0 {
+        transfer_tokens(
+            &context.accounts.taker_token_account_b,
+            &*context.accounts.fee_vault,
+            &protocol_fee,
+            &context.accounts.token_mint_b,
+            &context.accounts.taker.to_account_info(),
+            &context.accounts.token_program,
+            None,
+        )
+        .map_err(|_| ErrorCode::InsufficientTakerBalance)?;
+    }
+    transfer_tokens_including_fee(
+        &context.accounts.taker_token_account_b,
+        &context.accounts.maker_token_account_b,
+        &(stake_offer.token_b_wanted_amount - protocol_fee),
+        &context.accounts.token_mint_b,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+        ErrorCode::InsufficientTakerBalance,
+    )?;
+
+    let stake_account_key = context.accounts.stake_account.key();
+    let stake_offer_seeds = &[
+        b"stake_offer",
+        stake_account_key.as_ref(),
+        &[stake_offer.bump],
+    ];
+    set_stake_authorities(
+        &context.accounts.stake_account.to_account_info(),
+        &context.accounts.stake_offer.to_account_info(),
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.clock.to_account_info(),
+        &context.accounts.stake_program,
+        Some(&stake_offer_seeds[..]),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use super::shared::{require_can_send, require_offer_not_expired, transfer_tokens};
+use crate::{error::ErrorCode, state::Offer};
+
+#[derive(Accounts)]
+pub struct TopUpOffer<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub maker: Signer<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = token_mint_a @ ErrorCode::WrongTokenMint,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+
+    /// CHECK: Holds no data, it only owns and signs for the offer's vaults
+    #[account(
+        seeds = [b"vault_authority", offer.key().as_ref()],
+        bump = offer.load()?.vault_authority_bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the top up offer instruction by:
+// 1. Moving more of token a from the maker's ATA to the vault
+// 2. Adding what actually arrived to the amount offered, and repricing the offer to match
+// The amount of token b wanted doesn't change, makers who want to keep their price can reprice with update_offer
+pub fn top_up_offer(context: Context<TopUpOffer>, amount: u64) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+
+    let offer = *context.accounts.offer.load()?;
+    let clock = Clock::get()?;
+    require_offer_not_expired(&offer, clock.unix_timestamp)?;
+    // A taker waiting on the arbiter has already paid for the current terms
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+
+    let vault_amount_before = context.accounts.vault.amount;
+
+    require_can_send(
+        &context.accounts.maker_token_account_a,
+        amount,
+        ErrorCode::InsufficientMakerBalance,
+    )?;
+    transfer_tokens(
+        &context.accounts.maker_token_account_a,
+        &context.accounts.vault,
+        &amount,
+        &context.accounts.token_mint_a,
+        &context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+        None,
+    )?;
+
+    // Token-2022 transfer fees are taken from the amount sent, so add what the vault actually received
+    context.accounts.vault.reload()?;
+    let token_a_received_amount = context.accounts.vault.amount - vault_amount_before;
+    require!(
+        token_a_received_amount > 0,
+        ErrorCode::TransferFeeExceedsAmount
+    );
+
+    let mut offer = context.accounts.offer.load_mut()?;
+    offer.token_a_offered_amount = offer
+        .token_a_offered_amount
+        .checked_add(token_a_received_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let (token_b_wanted_amount, token_a_offered_amount) =
+        (offer.token_b_wanted_amount, offer.token_a_offered_amount);
+    offer.set_price(token_b_wanted_amount, token_a_offered_amount);
+
+    Ok(())
+}
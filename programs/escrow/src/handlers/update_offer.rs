@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use super::shared::require_offer_open;
+use crate::{error::ErrorCode, state::Offer};
+
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        seeds = [b"offer", offer.load()?.maker.as_ref(), offer.load()?.id.to_le_bytes().as_ref()],
+        bump = offer.load()?.bump
+    )]
+    pub offer: AccountLoader<'info, Offer>,
+}
+
+// Handle the update offer instruction by:
+// 1. Changing the amount of token b wanted, and the price of any partial fills with it
+// 2. Changing the expiry, if a new one was provided
+// Repricing an offer gives it a fixed price, so any Dutch auction schedule is removed
+// The vault is not touched, so the maker doesn't lose the rent of closing and reopening it
+pub fn update_offer(
+    context: Context<UpdateOffer>,
+    token_b_wanted_amount: u64,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+
+    let mut offer = context.accounts.offer.load_mut()?;
+    require_offer_open(&offer)?;
+
+    // The reserve price of an English auction can't change once bidders have committed to it
+    require!(offer.highest_bid == 0, ErrorCode::AuctionHasBids);
+    // Likewise a taker waiting on the arbiter has already paid for the current terms
+    require!(!offer.arbitration_pending(), ErrorCode::ArbitrationPending);
+
+    if let Some(expires_at) = expires_at {
+        let clock = Clock::get()?;
+        require!(expires_at > clock.unix_timestamp, ErrorCode::InvalidExpiry);
+        offer.set_expires_at(Some(expires_at));
+    }
+
+    offer.token_b_wanted_amount = token_b_wanted_amount;
+    let token_a_offered_amount = offer.token_a_offered_amount;
+    offer.set_price(token_b_wanted_amount, token_a_offered_amount);
+    offer.set_dutch_auction(None);
+
+    Ok(())
+}
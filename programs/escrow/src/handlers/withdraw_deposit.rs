@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use super::shared::{close_token_account, transfer_tokens};
+use crate::{error::ErrorCode, state::TakerDeposit};
+
+#[derive(Accounts)]
+pub struct WithdrawDeposit<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    // The offer may already have been refunded, so the deposit is found from its own seeds
+    #[account(
+        mut,
+        close = taker,
+        has_one = taker @ ErrorCode::InvalidTaker,
+        has_one = token_mint_b @ ErrorCode::WrongTokenMint,
+        seeds = [b"taker_deposit", taker_deposit.offer.as_ref(), taker.key().as_ref()],
+        bump = taker_deposit.bump
+    )]
+    pub taker_deposit: Account<'info, TakerDeposit>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker_deposit,
+        associated_token::token_program = token_program,
+    )]
+    pub deposit_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the withdraw deposit instruction by:
+// 1. Returning the tokens in the deposit vault to the taker
+// 2. Closing the deposit vault and deposit account, returning the rent to the taker
+pub fn withdraw_deposit(context: Context<WithdrawDeposit>) -> Result<()> {
+    // The deposit account owns the deposit vault, so it signs for the withdrawal
+    let taker_deposit_seeds = &[
+        b"taker_deposit",
+        context.accounts.taker_deposit.offer.as_ref(),
+        context.accounts.taker_deposit.taker.as_ref(),
+        &[context.accounts.taker_deposit.bump],
+    ];
+    let signers_seeds = Some(&taker_deposit_seeds[..]);
+
+    transfer_tokens(
+        &context.accounts.deposit_vault,
+        &context.accounts.taker_token_account_b,
+        &context.accounts.deposit_vault.amount,
+        &context.accounts.token_mint_b,
+        &context.accounts.taker_deposit.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultWithdrawal)?;
+
+    close_token_account(
+        &context.accounts.deposit_vault,
+        &context.accounts.taker.to_account_info(),
+        &context.accounts.taker_deposit.to_account_info(),
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    Ok(())
+}
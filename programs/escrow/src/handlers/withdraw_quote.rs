@@ -0,0 +1,92 @@
+use super::shared::{close_token_account, transfer_tokens};
+use crate::{error::ErrorCode, state::Quote};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct WithdrawQuote<'info> {
+    // Work with either the classic token program or
+    // the newer token extensions program, whichever owns token a
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Checked against the quote's request with has_one, the handler only checks whether it has been closed
+    pub quote_request: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker @ ErrorCode::InvalidMaker,
+        has_one = quote_request @ ErrorCode::WrongQuoteRequest,
+        seeds = [b"quote", quote_request.key().as_ref(), maker.key().as_ref()],
+        bump = quote.bump
+    )]
+    pub quote: Account<'info, Quote>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = quote,
+        associated_token::token_program = token_program
+    )]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Handle the withdraw quote instruction by:
+// 1. Checking the quote is no longer firm: its request has expired, or was closed by accepting another quote or
+//    by the requester
+// 2. Returning the quoted tokens from the quote vault to the maker, and closing the vault and the quote
+pub fn withdraw_quote(context: Context<WithdrawQuote>) -> Result<()> {
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp >= context.accounts.quote.expires_at
+            || context.accounts.quote_request.data_is_empty(),
+        ErrorCode::QuoteStillFirm
+    );
+
+    let quote_request_key = context.accounts.quote_request.key();
+    let maker_key = context.accounts.maker.key();
+    let quote_seeds = &[
+        b"quote",
+        quote_request_key.as_ref(),
+        maker_key.as_ref(),
+        &[context.accounts.quote.bump],
+    ];
+    let signers_seeds = Some(&quote_seeds[..]);
+    let quote_info = context.accounts.quote.to_account_info();
+
+    transfer_tokens(
+        &context.accounts.quote_vault,
+        &context.accounts.maker_token_account_a,
+        &context.accounts.quote_vault.amount,
+        &context.accounts.token_mint_a,
+        &quote_info,
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedRefundTransfer)?;
+
+    close_token_account(
+        &context.accounts.quote_vault,
+        &context.accounts.maker.to_account_info(),
+        &quote_info,
+        &context.accounts.token_program,
+        signers_seeds,
+    )
+    .map_err(|_| ErrorCode::FailedVaultClosure)?;
+
+    Ok(())
+}
@@ -1,13 +1,18 @@
 #![allow(unexpected_cfgs)]
 // Stops Rust Analyzer complaining about missing configs
 // See https://solana.stackexchange.com/questions/17777
+// The CPI helpers Anchor generates take every instruction argument as a separate parameter
+#![cfg_attr(feature = "cpi", allow(clippy::too_many_arguments))]
 
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::hash::HASH_BYTES};
+use constants::{ETH_ADDRESS_LENGTH, OFFER_TAGS_LENGTH};
 use handlers::*;
 
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod math;
 pub mod state;
 
 
@@ -17,25 +22,560 @@ declare_id!("8jR5GeNzeweq35Uo84kGP3v1NcBaZWH5u62k7PxN4T2y");
 pub mod escrow {
     use super::*;
 
-    pub fn make_offer(
-        context: Context<MakeOffer>,
+    // Anchor reads every instruction argument as a separate parameter
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, MakeOffer<'info>>,
         id: u64,
         token_a_offered_amount: u64,
         token_b_wanted_amount: u64,
+        dutch_auction: Option<state::DutchAuction>,
+        english_auction: Option<state::EnglishAuction>,
+        additional_token_a_offered_amounts: Vec<u64>,
+        memo: Option<String>,
+        min_fill_amount: u64,
+        hashlock: Option<[u8; HASH_BYTES]>,
+        vesting_duration: Option<i64>,
+        arbiter: Option<Pubkey>,
+        keep_history: bool,
+        tags: [u8; OFFER_TAGS_LENGTH],
     ) -> Result<()> {
-        handlers::make_offer::make_offer(context, id, token_a_offered_amount, token_b_wanted_amount)
+        handlers::make_offer::make_offer(
+            context,
+            id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            dutch_auction,
+            english_auction,
+            additional_token_a_offered_amounts,
+            memo,
+            min_fill_amount,
+            hashlock,
+            vesting_duration,
+            arbiter,
+            keep_history,
+            tags,
+        )
     }
 
-    pub fn take_offer(context: Context<TakeOffer>) -> Result<()> {
-        handlers::take_offer::take_offer(context)
+    pub fn make_offer_delegated(
+        context: Context<MakeOfferDelegated>,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        tags: [u8; OFFER_TAGS_LENGTH],
+    ) -> Result<()> {
+        handlers::make_offer_delegated::make_offer_delegated(
+            context,
+            id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            tags,
+        )
+    }
+
+    pub fn make_offer_with_existing_vault(
+        context: Context<MakeOfferWithExistingVault>,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        tags: [u8; OFFER_TAGS_LENGTH],
+    ) -> Result<()> {
+        handlers::make_offer_with_existing_vault::make_offer_with_existing_vault(
+            context,
+            id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            tags,
+        )
+    }
+
+    pub fn create_offer_template(
+        context: Context<CreateOfferTemplate>,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        total_token_a_amount: u64,
+        tags: [u8; OFFER_TAGS_LENGTH],
+    ) -> Result<()> {
+        handlers::create_offer_template::create_offer_template(
+            context,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            total_token_a_amount,
+            tags,
+        )
+    }
+
+    pub fn spawn_offer_from_template(context: Context<SpawnOfferFromTemplate>) -> Result<()> {
+        handlers::spawn_offer_from_template::spawn_offer_from_template(context)
+    }
+
+    pub fn close_offer_template(context: Context<CloseOfferTemplate>) -> Result<()> {
+        handlers::close_offer_template::close_offer_template(context)
+    }
+
+    pub fn take_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeOffer<'info>>,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+        preimage: Option<Vec<u8>>,
+        memo: Option<String>,
+    ) -> Result<()> {
+        handlers::take_offer::take_offer(
+            context,
+            expected_token_a_amount,
+            expected_token_b_amount,
+            preimage,
+            memo,
+        )
+    }
+
+    pub fn take_offer_nft<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeOffer<'info>>,
+        expected_collection: Option<Pubkey>,
+        expected_token_b_amount: u64,
+        preimage: Option<Vec<u8>>,
+    ) -> Result<()> {
+        handlers::take_offer_nft::take_offer_nft(
+            context,
+            expected_collection,
+            expected_token_b_amount,
+            preimage,
+        )
+    }
+
+    pub fn take_offer_exact_out(
+        context: Context<TakeOfferExactOut>,
+        token_a_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_exact_out::take_offer_exact_out(context, token_a_amount)
+    }
+
+    pub fn take_offer_with_token_accounts(
+        context: Context<TakeOfferWithTokenAccounts>,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_with_token_accounts::take_offer_with_token_accounts(
+            context,
+            expected_token_a_amount,
+            expected_token_b_amount,
+        )
+    }
+
+    pub fn take_offer_with_authorization(
+        context: Context<TakeOfferWithAuthorization>,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+        authorization_expires_at: i64,
+    ) -> Result<()> {
+        handlers::take_offer_with_authorization::take_offer_with_authorization(
+            context,
+            expected_token_a_amount,
+            expected_token_b_amount,
+            authorization_expires_at,
+        )
+    }
+
+    pub fn match_offers(context: Context<MatchOffers>) -> Result<()> {
+        handlers::match_offers::match_offers(context)
+    }
+
+    pub fn take_offer_route(
+        context: Context<TakeOfferRoute>,
+        expected_token_a_amount: u64,
+        maximum_token_c_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_route::take_offer_route(
+            context,
+            expected_token_a_amount,
+            maximum_token_c_amount,
+        )
+    }
+
+    pub fn request_quote(
+        context: Context<RequestQuote>,
+        id: u64,
+        token_a_wanted_amount: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        handlers::request_quote::request_quote(context, id, token_a_wanted_amount, expires_at)
+    }
+
+    pub fn submit_quote(context: Context<SubmitQuote>, token_b_wanted_amount: u64) -> Result<()> {
+        handlers::submit_quote::submit_quote(context, token_b_wanted_amount)
+    }
+
+    pub fn accept_quote(context: Context<AcceptQuote>) -> Result<()> {
+        handlers::accept_quote::accept_quote(context)
+    }
+
+    pub fn withdraw_quote(context: Context<WithdrawQuote>) -> Result<()> {
+        handlers::withdraw_quote::withdraw_quote(context)
     }
 
-    pub fn refund_offer(context: Context<RefundOffer>) -> Result<()> {
+    pub fn close_quote_request(context: Context<CloseQuoteRequest>) -> Result<()> {
+        handlers::close_quote_request::close_quote_request(context)
+    }
+
+    pub fn make_stake_offer(
+        context: Context<MakeStakeOffer>,
+        token_b_wanted_amount: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        handlers::make_stake_offer::make_stake_offer(context, token_b_wanted_amount, expires_at)
+    }
+
+    pub fn take_stake_offer(context: Context<TakeStakeOffer>) -> Result<()> {
+        handlers::take_stake_offer::take_stake_offer(context)
+    }
+
+    pub fn cancel_stake_offer(context: Context<CancelStakeOffer>) -> Result<()> {
+        handlers::cancel_stake_offer::cancel_stake_offer(context)
+    }
+
+    pub fn take_offer_vested(
+        context: Context<TakeOfferVested>,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_vested::take_offer_vested(
+            context,
+            expected_token_a_amount,
+            expected_token_b_amount,
+        )
+    }
+
+    pub fn claim_vested(context: Context<ClaimVested>) -> Result<()> {
+        handlers::claim_vested::claim_vested(context)
+    }
+
+    pub fn take_offer_in_installments(
+        context: Context<TakeOfferInInstallments>,
+        installment_count: u8,
+        installment_interval: i64,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_in_installments::take_offer_in_installments(
+            context,
+            installment_count,
+            installment_interval,
+            expected_token_a_amount,
+            expected_token_b_amount,
+        )
+    }
+
+    pub fn pay_installment(context: Context<PayInstallment>) -> Result<()> {
+        handlers::pay_installment::pay_installment(context)
+    }
+
+    pub fn cancel_default(context: Context<CancelDefault>) -> Result<()> {
+        handlers::cancel_default::cancel_default(context)
+    }
+
+    pub fn deposit_for_offer(context: Context<DepositForOffer>) -> Result<()> {
+        handlers::deposit_for_offer::deposit_for_offer(context)
+    }
+
+    pub fn withdraw_deposit(context: Context<WithdrawDeposit>) -> Result<()> {
+        handlers::withdraw_deposit::withdraw_deposit(context)
+    }
+
+    pub fn confirm_swap(context: Context<ConfirmSwap>) -> Result<()> {
+        handlers::confirm_swap::confirm_swap(context)
+    }
+
+    pub fn take_offers_batch<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeOffersBatch<'info>>,
+    ) -> Result<()> {
+        handlers::take_offers_batch::take_offers_batch(context)
+    }
+
+    pub fn take_offer_arbitrated(
+        context: Context<TakeOfferArbitrated>,
+        expected_token_a_amount: u64,
+        expected_token_b_amount: u64,
+    ) -> Result<()> {
+        handlers::take_offer_arbitrated::take_offer_arbitrated(
+            context,
+            expected_token_a_amount,
+            expected_token_b_amount,
+        )
+    }
+
+    pub fn release(context: Context<Release>) -> Result<()> {
+        handlers::release::release(context)
+    }
+
+    pub fn revert(context: Context<Revert>) -> Result<()> {
+        handlers::revert::revert(context)
+    }
+
+    pub fn refund_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, RefundOffer<'info>>,
+    ) -> Result<()> {
         handlers::refund_offer::refund_offer(context)
     }
+
+    pub fn refund_legacy_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, RefundLegacyOffer<'info>>,
+    ) -> Result<()> {
+        handlers::refund_legacy_offer::refund_legacy_offer(context)
+    }
+
+    pub fn claim_rent(context: Context<ClaimRent>) -> Result<()> {
+        handlers::claim_rent::claim_rent(context)
+    }
+
+    pub fn update_offer(
+        context: Context<UpdateOffer>,
+        token_b_wanted_amount: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        handlers::update_offer::update_offer(context, token_b_wanted_amount, expires_at)
+    }
+
+    pub fn replace_offer(
+        context: Context<ReplaceOffer>,
+        new_id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        handlers::replace_offer::replace_offer(
+            context,
+            new_id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            expires_at,
+        )
+    }
+
+    pub fn extend_offer(context: Context<UpdateOffer>, expires_at: i64) -> Result<()> {
+        handlers::extend_offer::extend_offer(context, expires_at)
+    }
+
+    pub fn top_up_offer(context: Context<TopUpOffer>, amount: u64) -> Result<()> {
+        handlers::top_up_offer::top_up_offer(context, amount)
+    }
+
+    pub fn reduce_offer(context: Context<TopUpOffer>, amount: u64) -> Result<()> {
+        handlers::reduce_offer::reduce_offer(context, amount)
+    }
+
+    pub fn set_refund_authority(
+        context: Context<UpdateOffer>,
+        refund_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        handlers::set_refund_authority::set_refund_authority(context, refund_authority)
+    }
+
+    pub fn set_installment_terms(
+        context: Context<UpdateOffer>,
+        installment_terms: Option<state::InstallmentTerms>,
+    ) -> Result<()> {
+        handlers::set_installment_terms::set_installment_terms(context, installment_terms)
+    }
+
+    pub fn set_attestor(context: Context<UpdateOffer>, attestor: Option<Pubkey>) -> Result<()> {
+        handlers::set_attestor::set_attestor(context, attestor)
+    }
+
+    pub fn set_eth_taker(
+        context: Context<UpdateOffer>,
+        eth_taker: Option<[u8; ETH_ADDRESS_LENGTH]>,
+    ) -> Result<()> {
+        handlers::set_eth_taker::set_eth_taker(context, eth_taker)
+    }
+
+    pub fn attach_metadata(context: Context<AttachMetadata>, metadata_uri: String) -> Result<()> {
+        handlers::attach_metadata::attach_metadata(context, metadata_uri)
+    }
+
+    pub fn migrate_offer(context: Context<MigrateOffer>) -> Result<()> {
+        handlers::migrate_offer::migrate_offer(context)
+    }
+
+    pub fn make_counter_offer(
+        context: Context<MakeCounterOffer>,
+        token_b_offered_amount: u64,
+    ) -> Result<()> {
+        handlers::make_counter_offer::make_counter_offer(context, token_b_offered_amount)
+    }
+
+    pub fn accept_counter_offer(context: Context<AcceptCounterOffer>) -> Result<()> {
+        handlers::accept_counter_offer::accept_counter_offer(context)
+    }
+
+    pub fn reject_counter_offer(context: Context<RejectCounterOffer>) -> Result<()> {
+        handlers::reject_counter_offer::reject_counter_offer(context)
+    }
+
+    pub fn place_bid(context: Context<PlaceBid>, amount: u64) -> Result<()> {
+        handlers::place_bid::place_bid(context, amount)
+    }
+
+    pub fn outbid(context: Context<Outbid>, amount: u64) -> Result<()> {
+        handlers::outbid::outbid(context, amount)
+    }
+
+    pub fn settle_auction(context: Context<SettleAuction>) -> Result<()> {
+        handlers::settle_auction::settle_auction(context)
+    }
+
+    pub fn add_allowed_taker(context: Context<AddAllowedTaker>, taker: Pubkey) -> Result<()> {
+        handlers::add_allowed_taker::add_allowed_taker(context, taker)
+    }
+
+    pub fn remove_allowed_taker(context: Context<RemoveAllowedTaker>, taker: Pubkey) -> Result<()> {
+        handlers::remove_allowed_taker::remove_allowed_taker(context, taker)
+    }
+
+    pub fn initialize_config(
+        context: Context<InitializeConfig>,
+        fee_bps: u16,
+        fee_authority: Pubkey,
+        referral_share_bps: u16,
+    ) -> Result<()> {
+        handlers::admin::initialize_config::initialize_config(
+            context,
+            fee_bps,
+            fee_authority,
+            referral_share_bps,
+        )
+    }
+
+    pub fn withdraw_fees(context: Context<WithdrawFees>) -> Result<()> {
+        handlers::admin::withdraw_fees::withdraw_fees(context)
+    }
+
+    pub fn pause(context: Context<Pause>) -> Result<()> {
+        handlers::admin::pause::pause(context)
+    }
+
+    pub fn unpause(context: Context<Unpause>) -> Result<()> {
+        handlers::admin::unpause::unpause(context)
+    }
+
+    pub fn set_mint_policy(
+        context: Context<SetMintPolicy>,
+        mint_policy: state::MintPolicy,
+    ) -> Result<()> {
+        handlers::admin::set_mint_policy::set_mint_policy(context, mint_policy)
+    }
+
+    pub fn set_royalties_required(
+        context: Context<SetRoyaltiesRequired>,
+        royalties_required: bool,
+    ) -> Result<()> {
+        handlers::admin::set_royalties_required::set_royalties_required(
+            context,
+            royalties_required,
+        )
+    }
+
+    pub fn set_limits(
+        context: Context<SetLimits>,
+        min_offer_amount: u64,
+        max_offer_amount: u64,
+    ) -> Result<()> {
+        handlers::admin::set_limits::set_limits(context, min_offer_amount, max_offer_amount)
+    }
+
+    pub fn set_mint_allowlist_enabled(
+        context: Context<SetMintAllowlistEnabled>,
+        mint_allowlist_enabled: bool,
+    ) -> Result<()> {
+        handlers::admin::set_mint_allowlist_enabled::set_mint_allowlist_enabled(
+            context,
+            mint_allowlist_enabled,
+        )
+    }
+
+    pub fn allow_mint(context: Context<AllowMint>) -> Result<()> {
+        handlers::admin::allow_mint::allow_mint(context)
+    }
+
+    pub fn revoke_mint(context: Context<RevokeMint>) -> Result<()> {
+        handlers::admin::revoke_mint::revoke_mint(context)
+    }
+
+    pub fn deny_mint(context: Context<DenyMint>) -> Result<()> {
+        handlers::admin::deny_mint::deny_mint(context)
+    }
+
+    pub fn undeny_mint(context: Context<UndenyMint>) -> Result<()> {
+        handlers::admin::undeny_mint::undeny_mint(context)
+    }
+
+    pub fn set_fee_tiers(
+        context: Context<SetFeeTiers>,
+        fee_tiers: Vec<state::FeeTier>,
+        fee_tier_mint: Pubkey,
+    ) -> Result<()> {
+        handlers::admin::set_fee_tiers::set_fee_tiers(context, fee_tiers, fee_tier_mint)
+    }
+
+    pub fn set_membership_discount(
+        context: Context<SetMembershipDiscount>,
+        membership_discount: Option<state::MembershipDiscount>,
+    ) -> Result<()> {
+        handlers::admin::set_membership_discount::set_membership_discount(
+            context,
+            membership_discount,
+        )
+    }
+
+    pub fn set_matcher_share(context: Context<SetMatcherShare>, matcher_share_bps: u16) -> Result<()> {
+        handlers::admin::set_matcher_share::set_matcher_share(context, matcher_share_bps)
+    }
+
+    pub fn set_min_offer_age(
+        context: Context<SetMinOfferAge>,
+        min_offer_age_slots: u64,
+    ) -> Result<()> {
+        handlers::admin::set_min_offer_age::set_min_offer_age(context, min_offer_age_slots)
+    }
+
+    pub fn set_max_open_offers(
+        context: Context<SetMaxOpenOffers>,
+        max_open_offers_per_maker: u16,
+    ) -> Result<()> {
+        handlers::admin::set_max_open_offers::set_max_open_offers(
+            context,
+            max_open_offers_per_maker,
+        )
+    }
+
+    pub fn set_role(context: Context<SetRole>, role: state::Role, holder: Pubkey) -> Result<()> {
+        handlers::admin::set_role::set_role(context, role, holder)
+    }
+
+    pub fn admin_force_refund<'info>(
+        context: Context<'_, '_, 'info, 'info, AdminForceRefund<'info>>,
+    ) -> Result<()> {
+        handlers::admin::admin_force_refund::admin_force_refund(context)
+    }
+
+    pub fn close_orphaned_vault(
+        context: Context<CloseOrphanedVault>,
+        maker: Pubkey,
+        offer_id: u64,
+    ) -> Result<()> {
+        handlers::admin::close_orphaned_vault::close_orphaned_vault(context, maker, offer_id)
+    }
 }
 
 #[cfg(test)]
+mod compute_unit_benchmarks;
+#[cfg(test)]
+// Helpers take each instruction's arguments as they are, and return LiteSVM's own transaction results
+#[allow(clippy::too_many_arguments, clippy::result_large_err)]
 mod escrow_test_helpers;
 #[cfg(test)]
+mod property_tests;
+#[cfg(test)]
+// Amounts are written as multiples of a whole token, 1 * TOKEN_A included, so they read alike
+#[allow(clippy::identity_op)]
 mod tests;
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// Settlement math for fees, partial fills, prices and schedules
+// Every multiplication happens in u128, so it can't overflow, and every division says which way it rounds
+// Results that don't fit back in a u64 are errors, never truncated
+
+// Which way to round a division that doesn't come out exact
+// Pick whichever direction favors the party who didn't choose the amount,
+// eg fees round down for the taker, and prices round up for the maker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+// amount * numerator / denominator, rounded in the given direction
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64, rounding: Rounding) -> Result<u64> {
+    require!(denominator > 0, ErrorCode::DivisionByZero);
+    let product = (amount as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let quotient = match rounding {
+        Rounding::Down => product / denominator as u128,
+        Rounding::Up => product.div_ceil(denominator as u128),
+    };
+    u64::try_from(quotient).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+// How far through a period [start, end] now is, as (elapsed, duration) in seconds
+// Only called once now is strictly inside the period, so both are positive
+pub fn elapsed_and_duration(start: i64, end: i64, now: i64) -> Result<(u64, u64)> {
+    let elapsed = now.checked_sub(start).ok_or(ErrorCode::MathOverflow)?;
+    let duration = end.checked_sub(start).ok_or(ErrorCode::MathOverflow)?;
+    let elapsed = u64::try_from(elapsed).map_err(|_| ErrorCode::MathOverflow)?;
+    let duration = u64::try_from(duration).map_err(|_| ErrorCode::MathOverflow)?;
+    Ok((elapsed, duration))
+}
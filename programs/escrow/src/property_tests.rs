@@ -0,0 +1,185 @@
+// Property tests for settlement, run with the other tests using proptest
+//
+// The tests in tests.rs check hand-picked amounts. These generate amounts, decimals,
+// fees and sequences of partial fills, and check what must hold for all of them:
+// no tokens are created or lost, and the maker is never paid less than their price.
+use proptest::prelude::*;
+use solana_signer::Signer;
+
+use crate::constants::{BASIS_POINTS_DENOMINATOR, MAX_FEE_BPS};
+use crate::escrow_test_helpers::{
+    execute_make_offer, execute_refund_offer, execute_take_offer_exact_out, generate_offer_id,
+    get_fee_vault_address, get_token_balance, setup_escrow_test_with_decimals, TOKEN_A, TOKEN_B,
+};
+use crate::handlers::calculate_fee;
+use crate::math::{mul_div, Rounding};
+
+// What setup_escrow_test_with_decimals gives Alice and Bob, in base units
+const ALICE_TOKEN_A_BALANCE: u64 = 10 * TOKEN_A;
+const BOB_TOKEN_B_BALANCE: u64 = 5 * TOKEN_B;
+
+// Each fill pays the maker at most one base unit more than their price, from rounding up,
+// so Bob can always afford an offer wanting this much, however it's split
+const MAX_TOKEN_B_WANTED_AMOUNT: u64 = BOB_TOKEN_B_BALANCE - MAX_FILLS as u64;
+const MAX_FILLS: usize = 6;
+
+proptest! {
+    #[test]
+    fn property_fees_never_exceed_the_amount_and_add_back_up(
+        amount in 0..=u64::MAX,
+        fee_bps in 0..=MAX_FEE_BPS,
+        referral_share_bps in 0..=BASIS_POINTS_DENOMINATOR as u16,
+    ) {
+        let total_fee = calculate_fee(amount, fee_bps).unwrap();
+        let referral_fee = calculate_fee(total_fee, referral_share_bps).unwrap();
+
+        prop_assert!(total_fee <= amount);
+        prop_assert!(referral_fee <= total_fee);
+        // Fees round down, so the taker is never charged more than fee_bps
+        prop_assert!(
+            total_fee as u128 * BASIS_POINTS_DENOMINATOR as u128 <= amount as u128 * fee_bps as u128
+        );
+
+        let token_a_taker_amount = amount - total_fee;
+        let protocol_fee = total_fee - referral_fee;
+        prop_assert_eq!(
+            token_a_taker_amount as u128 + protocol_fee as u128 + referral_fee as u128,
+            amount as u128
+        );
+    }
+
+    #[test]
+    fn property_partial_fills_never_pay_the_maker_less_than_their_price(
+        token_a_offered_amount in 1..=u64::MAX / 2,
+        token_b_wanted_amount in 1..=u64::MAX / 2,
+        fill_fractions in prop::collection::vec(1..=BASIS_POINTS_DENOMINATOR, 1..=MAX_FILLS),
+    ) {
+        let mut token_a_remaining_amount = token_a_offered_amount;
+        let mut token_a_filled_amount: u64 = 0;
+        let mut token_b_paid_amount: u128 = 0;
+
+        for fill_fraction in fill_fractions {
+            let token_a_amount = mul_div(
+                token_a_remaining_amount,
+                fill_fraction,
+                BASIS_POINTS_DENOMINATOR,
+                Rounding::Down,
+            )
+            .unwrap()
+            .max(1);
+            // Like Offer::token_b_amount_for, priced by the maker's original terms
+            let token_b_amount = mul_div(
+                token_a_amount,
+                token_b_wanted_amount,
+                token_a_offered_amount,
+                Rounding::Up,
+            )
+            .unwrap();
+
+            prop_assert!(
+                token_b_amount as u128 * token_a_offered_amount as u128
+                    >= token_a_amount as u128 * token_b_wanted_amount as u128
+            );
+
+            token_a_remaining_amount -= token_a_amount;
+            token_a_filled_amount += token_a_amount;
+            token_b_paid_amount += token_b_amount as u128;
+            if token_a_remaining_amount == 0 {
+                break;
+            }
+        }
+
+        prop_assert_eq!(token_a_filled_amount + token_a_remaining_amount, token_a_offered_amount);
+        // Rounding up each fill adds less than one base unit per fill on top of the price
+        prop_assert!(
+            token_b_paid_amount * token_a_offered_amount as u128
+                >= token_a_filled_amount as u128 * token_b_wanted_amount as u128
+        );
+    }
+}
+
+proptest! {
+    // Every case deploys the program into a fresh LiteSVM, so run fewer of them
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn property_settlement_conserves_tokens(
+        token_a_decimals in 0u8..=9,
+        token_b_decimals in 0u8..=9,
+        fee_bps in 0..=MAX_FEE_BPS,
+        token_a_offered_amount in 1..=ALICE_TOKEN_A_BALANCE,
+        token_b_wanted_amount in 1..=MAX_TOKEN_B_WANTED_AMOUNT,
+        fill_fractions in prop::collection::vec(1..=BASIS_POINTS_DENOMINATOR, 0..=MAX_FILLS),
+    ) {
+        let mut test_environment =
+            setup_escrow_test_with_decimals(fee_bps, token_a_decimals, token_b_decimals);
+
+        let alice = test_environment.alice.insecure_clone();
+        let alice_token_account_a = test_environment.alice_token_account_a;
+        let (offer_account, vault) = execute_make_offer(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+        )
+        .unwrap();
+
+        // Bob takes part of what's left each time, the protocol fee comes out of the vault on top
+        let mut token_a_remaining_amount = token_a_offered_amount;
+        for fill_fraction in fill_fractions {
+            let most_bob_can_take = mul_div(
+                token_a_remaining_amount,
+                BASIS_POINTS_DENOMINATOR,
+                BASIS_POINTS_DENOMINATOR + fee_bps as u64,
+                Rounding::Down,
+            )
+            .unwrap();
+            if most_bob_can_take == 0 {
+                break;
+            }
+            let token_a_amount =
+                mul_div(most_bob_can_take, fill_fraction, BASIS_POINTS_DENOMINATOR, Rounding::Down)
+                    .unwrap()
+                    .max(1);
+
+            execute_take_offer_exact_out(&mut test_environment, token_a_amount, offer_account, vault)
+                .unwrap();
+            // Fills of the same amount would otherwise be the same transaction
+            test_environment.litesvm.expire_blockhash();
+
+            token_a_remaining_amount -= token_a_amount + calculate_fee(token_a_amount, fee_bps).unwrap();
+            if token_a_remaining_amount == 0 {
+                break;
+            }
+        }
+
+        // Whatever the fills left behind goes back to Alice
+        if token_a_remaining_amount > 0 {
+            prop_assert_eq!(get_token_balance(&test_environment, &vault), token_a_remaining_amount);
+            execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault)
+                .unwrap();
+        }
+        prop_assert_eq!(get_token_balance(&test_environment, &vault), 0);
+
+        let alice_token_a = get_token_balance(&test_environment, &alice_token_account_a);
+        let bob_token_a = get_token_balance(&test_environment, &test_environment.bob_token_account_a);
+        let fee_vault_token_a = get_token_balance(
+            &test_environment,
+            &get_fee_vault_address(&test_environment.token_mint_a.pubkey()),
+        );
+        prop_assert_eq!(alice_token_a + bob_token_a + fee_vault_token_a, ALICE_TOKEN_A_BALANCE);
+
+        let alice_token_b = get_token_balance(&test_environment, &test_environment.alice_token_account_b);
+        let bob_token_b = get_token_balance(&test_environment, &test_environment.bob_token_account_b);
+        prop_assert_eq!(alice_token_b + bob_token_b, BOB_TOKEN_B_BALANCE);
+
+        // Alice is paid at her price for everything that left the vault, fees included
+        let token_a_withdrawn_amount = ALICE_TOKEN_A_BALANCE - alice_token_a;
+        prop_assert!(
+            alice_token_b as u128 * token_a_offered_amount as u128
+                >= token_a_withdrawn_amount as u128 * token_b_wanted_amount as u128
+        );
+    }
+}
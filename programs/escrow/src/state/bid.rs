@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+// Stores the current highest bid in an English auction
+// There is one bid account per offer: outbidding replaces the bidder and amount
+// The bid itself is held in the bid vault, an associated token account owned by this account
+#[account]
+#[derive(InitSpace)]
+pub struct Bid {
+    // The offer being bid on
+    pub offer: Pubkey,
+    // Who placed the current highest bid
+    pub bidder: Pubkey,
+    // The amount of token b in the current highest bid
+    pub amount: u64,
+    // Who paid the rent for this account and the bid vault, and gets it back at settlement
+    pub rent_payer: Pubkey,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
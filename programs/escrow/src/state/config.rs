@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_FEE_TIERS;
+
+// Protocol-wide settings, stored in a single PDA
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    // Who can change the protocol settings, and give out the roles below with set_role
+    pub authority: Pubkey,
+    // The protocol fee taken from the token a a taker receives, in basis points
+    pub fee_bps: u16,
+    // Who can withdraw the protocol fees collected in the fee vaults
+    pub fee_authority: Pubkey,
+    // The share of the protocol fee paid to the referrer, if take_offer has one, in basis points
+    pub referral_share_bps: u16,
+    // While paused, no offers can be made or taken, but makers can still refund
+    pub paused: bool,
+    // Which kinds of mint can be offered or wanted
+    pub mint_policy: MintPolicy,
+    // Whether NFT offers must pay their creators' royalties when taken, rather than only when the taker chooses to
+    pub royalties_required: bool,
+    // The smallest and largest amount of token a an offer can be made for, in token a's base units
+    // Stops dust offers spamming the order book and fat-fingered listings, see set_limits
+    pub min_offer_amount: u64,
+    pub max_offer_amount: u64,
+    // While set, offers can only be made between mints the allowlist manager has allowed, see allow_mint
+    pub mint_allowlist_enabled: bool,
+    // How many mints the allowlist manager has denied, see deny_mint
+    // While there are any, offers must pass each mint's deny entry address so it can be checked
+    pub denied_mint_count: u32,
+    // Lower protocol fees for makers with more settled volume, sorted by the volume needed, see set_fee_tiers
+    // The volume is only what makers received in fee_tier_mint
+    #[max_len(MAX_FEE_TIERS)]
+    pub fee_tiers: Vec<FeeTier>,
+    // A cut of the protocol fee for takers holding the membership token, see set_membership_discount
+    // Only take_offer checks it, takers prove they qualify by passing their membership token account
+    pub membership_discount: Option<MembershipDiscount>,
+    // Who can pause and unpause the program
+    pub pauser: Pubkey,
+    // Who can change the fee tiers and the membership discount
+    pub fee_manager: Pubkey,
+    // Who can manage the mint allowlist and denylist
+    pub allowlist_manager: Pubkey,
+    // The share of a match's price improvement paid to whoever matched the offers, in basis points
+    // The makers get the rest back, see match_offers and set_matcher_share
+    pub matcher_share_bps: u16,
+    // How many slots an offer must have existed for before it can be taken, see set_min_offer_age
+    // Gives makers a window to refund a mispriced offer before bots fill it
+    pub min_offer_age_slots: u64,
+    // The most open offers a single maker can have at once, see set_max_open_offers
+    // Limits the state one key can create, and is never more than a maker's index can list
+    pub max_open_offers_per_maker: u16,
+    // The mint fee tier volumes are measured in, see set_fee_tiers
+    // Amounts of different mints can't be added up, so settlements in any other token b don't count towards a tier
+    pub fee_tier_mint: Pubkey,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
+
+// The admin roles the authority can give to other keys, each covering some of the admin instructions
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Pauser,
+    FeeManager,
+    AllowlistManager,
+}
+
+// A protocol fee for makers whose settled volume has reached min_volume, in place of the config's fee_bps
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, InitSpace, PartialEq)]
+pub struct FeeTier {
+    // The amount of the config's fee_tier_mint the maker must have received on settlement, their statistics' fee_tier_volume
+    pub min_volume: u128,
+    pub fee_bps: u16,
+}
+
+// Takers holding at least min_balance of mint pay discount_bps less of the protocol fee
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, InitSpace, PartialEq)]
+pub struct MembershipDiscount {
+    // The token, or NFT, that makes the taker a member
+    pub mint: Pubkey,
+    pub min_balance: u64,
+    // The share of the protocol fee members don't pay, in basis points of the fee
+    pub discount_bps: u16,
+}
+
+// Which mint configurations offers may use, checked for both mints when an offer is made
+// Each of these can leave an offer impossible to settle or refund, so the config authority can refuse them
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct MintPolicy {
+    // A freeze authority can freeze the vault, or the maker's or taker's token account
+    pub allow_freeze_authority: bool,
+    // A mint authority can mint more of the token, changing what the offer is worth
+    pub allow_mint_authority: bool,
+    // Token-2022 non-transferable tokens can never leave the vault
+    pub allow_non_transferable: bool,
+    // A Token-2022 permanent delegate can move tokens out of the vault at any time
+    pub allow_permanent_delegate: bool,
+}
+
+// Everything is allowed until the config authority decides otherwise, since well known tokens like USDC have a freeze authority
+impl Default for MintPolicy {
+    fn default() -> Self {
+        Self {
+            allow_freeze_authority: true,
+            allow_mint_authority: true,
+            allow_non_transferable: true,
+            allow_permanent_delegate: true,
+        }
+    }
+}
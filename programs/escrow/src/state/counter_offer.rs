@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+// Stores a prospective taker's proposed terms for an existing offer
+// The token b is not escrowed: the counter offer PDA is approved as a delegate
+// on the counter-offeror's token account, so the maker can settle directly from it
+#[account]
+#[derive(InitSpace)]
+pub struct CounterOffer {
+    // The offer these terms are proposed for
+    pub offer: Pubkey,
+    // Who proposed the new terms
+    pub proposer: Pubkey,
+    // The amount of token b the proposer will pay for the whole offer
+    pub token_b_offered_amount: u64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+// Stores the token a a taker is paying for in installments, released to them as each installment is paid
+// There is one installment plan per offer, created when the offer is taken with take_offer_in_installments
+// The tokens are held in the installment vault, an associated token account owned by this account
+#[account]
+#[derive(InitSpace)]
+pub struct InstallmentPlan {
+    // The offer that was taken, used to derive this account's address
+    pub offer: Pubkey,
+    // The offer's identifier, for the events emitted when the plan finishes
+    pub offer_id: u64,
+    // Who made the offer, and is paid each installment
+    pub maker: Pubkey,
+    // Who signed for the maker when the offer was made, either can cancel the plan if the taker defaults
+    pub maker_authority: Pubkey,
+    // Who took the offer, pays each installment and receives token a as they do
+    pub taker: Pubkey,
+    // The token mint of the token being bought, token a of the offer
+    pub token_mint_a: Pubkey,
+    // The token mint of the token being paid, token b of the offer
+    pub token_mint_b: Pubkey,
+    // The amount of token a held for the taker when the offer was taken
+    pub token_a_amount: u64,
+    // The amount of token b the taker agreed to pay over every installment
+    pub token_b_amount: u64,
+    // The amount of token a released so far, including the protocol fee taken from it
+    pub token_a_released: u64,
+    // The amount of token b paid so far
+    pub token_b_paid: u64,
+    // The protocol fee on the token a released, fixed when the offer was taken
+    pub fee_bps: u16,
+    // How many installments the taker chose to pay in
+    pub installment_count: u8,
+    // How many installments have been paid, the first is paid when the offer is taken
+    pub installments_paid: u8,
+    // Unix timestamp the offer was taken, when the first installment was paid
+    pub start_timestamp: i64,
+    // How many seconds the taker has to pay each installment after the last
+    pub installment_interval: i64,
+    // When the offer was made, for the events emitted when the plan finishes
+    pub created_at: i64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
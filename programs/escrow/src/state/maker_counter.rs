@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::AUTO_ASSIGN_OFFER_ID;
+
+// Hands out offer ids for a maker, so clients don't have to invent ids that don't collide
+// Created the first time the maker makes an offer, and only advanced when make_offer is asked to assign the id
+#[account]
+#[derive(InitSpace)]
+pub struct MakerCounter {
+    // Whose offer ids these are
+    pub maker: Pubkey,
+    // The id the maker's next offer gets if make_offer is passed AUTO_ASSIGN_OFFER_ID
+    pub next_id: u64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
+
+impl MakerCounter {
+    // The id an offer is made with: the next id from the counter if the maker asked for one, otherwise the id they chose
+    pub fn resolve_offer_id(&self, id: u64) -> u64 {
+        if id == AUTO_ASSIGN_OFFER_ID {
+            self.next_id
+        } else {
+            id
+        }
+    }
+}
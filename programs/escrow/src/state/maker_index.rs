@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_MAKER_OPEN_OFFERS;
+
+// The ids of a maker's open offers, so clients can list them without scanning every offer account
+// Created the first time the maker makes an offer, and kept up to date by every handler that opens or closes one
+#[account]
+#[derive(InitSpace)]
+pub struct MakerIndex {
+    // Whose offers these are
+    pub maker: Pubkey,
+    // The ids of the maker's offers that haven't been taken, settled or refunded yet
+    #[max_len(MAX_MAKER_OPEN_OFFERS)]
+    pub offer_ids: Vec<u64>,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
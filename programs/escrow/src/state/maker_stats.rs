@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+// A maker's lifetime offer statistics, for reputation displays and anything else that wants a maker's history
+// Created the first time the maker makes an offer, and updated whenever one of their offers is settled or refunded
+#[account]
+#[derive(InitSpace)]
+pub struct MakerStats {
+    // Whose statistics these are
+    pub maker: Pubkey,
+    // How many offers the maker has made
+    pub offers_made: u64,
+    // How many of the maker's offers were taken or settled in full
+    pub offers_filled: u64,
+    // How many of the maker's offers were refunded, including by the config authority
+    pub offers_refunded: u64,
+    // The total token a that left the maker's vaults on settlement, including any protocol fee
+    // Like the registry's volume, these add up amounts of different mints, so are a measure of activity
+    pub token_a_sold_volume: u128,
+    // The total token b the maker received on settlement
+    pub token_b_received_volume: u128,
+    // The token b the maker received in fee_tier_mint, which the config's fee tiers are measured against
+    // Counting starts again from zero if the config's fee tier mint changes
+    pub fee_tier_mint: Pubkey,
+    pub fee_tier_volume: u128,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// Marks a mint as vetted by the allowlist manager, one per mint
+// Only enforced while the config's mint_allowlist_enabled flag is set
+#[account]
+#[derive(InitSpace)]
+pub struct MintAllowlistEntry {
+    // The mint offers may use
+    pub mint: Pubkey,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// Marks a mint as blocked by the allowlist manager, such as a known scam token, one per mint
+// Offers check the mint's deny entry address has no account, so the entry existing is what blocks the mint
+#[account]
+#[derive(InitSpace)]
+pub struct MintDenyEntry {
+    // The mint offers may not use
+    pub mint: Pubkey,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -1,3 +1,39 @@
+pub mod bid;
+pub mod config;
+pub mod counter_offer;
+pub mod installment_plan;
+pub mod maker_counter;
+pub mod maker_index;
+pub mod maker_stats;
+pub mod mint_allowlist_entry;
+pub mod mint_deny_entry;
 pub mod offer;
+pub mod offer_allowlist;
+pub mod offer_template;
+pub mod pending_settlement;
+pub mod quote;
+pub mod quote_request;
+pub mod registry;
+pub mod stake_offer;
+pub mod taker_deposit;
+pub mod vesting;
 
+pub use bid::*;
+pub use config::*;
+pub use counter_offer::*;
+pub use installment_plan::*;
+pub use maker_counter::*;
+pub use maker_index::*;
+pub use maker_stats::*;
+pub use mint_allowlist_entry::*;
+pub use mint_deny_entry::*;
 pub use offer::*;
+pub use offer_allowlist::*;
+pub use offer_template::*;
+pub use pending_settlement::*;
+pub use quote::*;
+pub use quote_request::*;
+pub use registry::*;
+pub use stake_offer::*;
+pub use taker_deposit::*;
+pub use vesting::*;
@@ -1,9 +1,25 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::hash::HASH_BYTES};
+use std::mem::{offset_of, size_of};
+
+use crate::{
+    constants::{
+        ETH_ADDRESS_LENGTH, MAX_ADDITIONAL_OFFERED_MINTS, MAX_MEMO_LENGTH, OFFER_RESERVED_SPACE,
+        OFFER_TAGS_LENGTH, OFFER_VERSION,
+    },
+    error::ErrorCode,
+    math::{mul_div, Rounding},
+};
 
 // Stores details of an offer to swap token a for token b
-// InitSpace allows us to calculate the space needed for this data
-#[account]
-#[derive(InitSpace)]
+// Offers are zero copy: the account data is read in place rather than deserialized, which saves compute units,
+// and every field lives at a fixed byte offset, so clients can filter offers with getProgramAccounts memcmp
+// Fields are ordered largest alignment first so there is no hidden padding
+// Optional values are stored as a value plus a has_ flag, read them with the accessor methods below
+// Handlers that sign CPIs as the offer copy it out with *offer.load()?, so its data isn't borrowed during the CPI
+// New fields are only ever added to the end, so every older layout is a prefix of this one
+// and migrate_offer can upgrade older offers in place
+// The layout ends with reserved space, so new fields can take bytes from it rather than growing the account
+#[account(zero_copy)]
 pub struct Offer {
     // Identifier of the offer
     pub id: u64,
@@ -15,6 +31,479 @@ pub struct Offer {
     pub token_mint_b: Pubkey,
     // The amount of token b being wanted
     pub token_b_wanted_amount: u64,
+    // The amount of token a the vault actually received, after any Token-2022 transfer fee
+    pub token_a_offered_amount: u64,
+    // The highest bid placed so far in an English auction, zero if there are no bids
+    pub highest_bid: u64,
+    // The smallest amount of token a a partial fill can take, or leave behind, zero for no minimum
+    pub min_fill_amount: u64,
+    // Unix timestamp after which the offer can no longer be taken, see expires_at()
+    expires_at: i64,
+    // A linear price schedule for the total amount of token b wanted, see dutch_auction()
+    dutch_auction_start_price: u64,
+    dutch_auction_end_price: u64,
+    dutch_auction_start_timestamp: i64,
+    dutch_auction_end_timestamp: i64,
+    // Bidding rules if the offer is sold to the highest bidder, see english_auction()
+    english_auction_end_timestamp: i64,
+    english_auction_min_bid_increment: u64,
+    // How many seconds token a vests to the taker over after the offer is taken, see vesting_duration()
+    vesting_duration: i64,
+    // Who signed for the maker when the offer was made, the maker themselves or a delegate, either can refund it
+    pub maker_authority: Pubkey,
+    // The verified Metaplex collection of the NFT, see nft_collection()
+    nft_collection: Pubkey,
+    // An operations key the maker lets refund the offer, but not change its terms, see refund_authority()
+    refund_authority: Pubkey,
+    // A neutral third party who must release or revert the offer once it's taken, see arbiter()
+    arbiter: Pubkey,
+    // The SHA-256 a taker's preimage must match, so the offer can be one leg of an atomic swap, see hashlock()
+    hashlock: [u8; HASH_BYTES],
+    // Other mints escrowed alongside token a in a bundle offer, see additional_token_mints_a()
+    additional_token_mints_a: [Pubkey; MAX_ADDITIONAL_OFFERED_MINTS],
+    // A human readable label for the offer, like "OTC deal with DAO treasury", see memo()
+    memo: [u8; MAX_MEMO_LENGTH],
     // Used to calculate the address for this account, we save it as a performance optimization
     pub bump: u8,
+    // How many of additional_token_mints_a are in use
+    additional_token_mints_a_count: u8,
+    // How many bytes of memo are in use, zero if the maker didn't give one
+    memo_length: u8,
+    // If set, only takers on the offer's allowlist can take it
+    allowlist_enabled: u8,
+    // Whether token a is an NFT whose Metaplex metadata was checked when the offer was made
+    is_nft: u8,
+    // Whether each optional value above is set
+    has_expires_at: u8,
+    has_dutch_auction: u8,
+    has_english_auction: u8,
+    has_vesting_duration: u8,
+    has_nft_collection: u8,
+    has_refund_authority: u8,
+    has_hashlock: u8,
+    has_arbiter: u8,
+    // Set while an arbitrated offer has been taken and is waiting for its arbiter to release or revert it
+    arbitration_pending: u8,
+    // The length of the metadata URI, which is stored after the offer rather than in it
+    // Offers start with no space for it, attach_metadata reallocs the account to fit the URI
+    metadata_uri_length: u16,
+    // Which version of the layout the offer was written with, see OFFER_VERSION
+    pub version: u8,
+    // Whether token a stays in the maker's own account, with the vault authority approved as its delegate, see is_delegated()
+    // Older layouts had padding here, so their offers read as not delegated
+    is_delegated: u8,
+    // Used to sign for the offer's vaults as the vault authority, we save it as a performance optimization
+    // The vault authority is a PDA of the offer, so the offer account only holds data and never signs
+    // Offers made before vault authorities have zero here, their vaults are owned by the offer, see refund_legacy_offer
+    pub vault_authority_bump: u8,
+    // Keeps the size a multiple of the alignment, so bytemuck can check there is no hidden padding
+    _padding: [u8; 5],
+    // Who paid the rent for the offer and its vaults, and gets it back when the offer is closed
+    // The maker, unless a sponsor like a frontend paid for the offer
+    pub rent_payer: Pubkey,
+    // The maker's price, as price_numerator token b for every price_denominator token a, see token_b_amount_for()
+    // Partial fills are priced from this rather than from what's left, so rounding can't drift the price over many fills
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    // When the offer was made, as a unix timestamp, so offers can be sorted by age
+    // Taken from the front of the reserved space, so offers made before it was recorded read as 0
+    pub created_at: i64,
+    // If set, settling or refunding the offer records its status rather than closing it, see keep_history()
+    keep_history: u8,
+    // Where the offer is in its lifecycle, see status()
+    status: u8,
+    // The most installments the maker lets a taker pay token b over, zero if they must pay up front, see installment_terms()
+    max_installments: u8,
+    // Keeps installment_interval aligned, so bytemuck can check there is no hidden padding
+    _installment_padding: [u8; 5],
+    // How many seconds the taker has to pay each installment after the last, see installment_terms()
+    installment_interval: i64,
+    // A key that must sign the offer's address before it can be taken, eg to confirm an off-chain deliverable, see attestor()
+    attestor: Pubkey,
+    has_attestor: u8,
+    // The Ethereum address that must sign the offer and taker's addresses before it can be taken, see eth_taker()
+    eth_taker: [u8; ETH_ADDRESS_LENGTH],
+    has_eth_taker: u8,
+    // Keeps created_slot aligned, so bytemuck can check there is no hidden padding
+    _created_slot_padding: [u8; 2],
+    // The slot the offer was made in, so take_offer can hold it back for the config's min_offer_age_slots
+    // Taken from the front of the reserved space, so offers made before it was recorded read as 0
+    pub created_slot: u64,
+    // Categories the maker tagged the offer with, so frontends can filter listings with a memcmp, see OFFER_TAGS_LENGTH
+    // Taken from the front of the reserved space, so offers made before they were recorded read as untagged
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+    // Set aside for future fields, which take their bytes from the front of it so the account doesn't need a realloc
+    // It starts zeroed, so fields added here read as unset on offers made before them, like the padding above
+    _reserved: [u8; OFFER_RESERVED_SPACE
+        - 2 * size_of::<i64>()
+        - 8
+        - size_of::<Pubkey>()
+        - 1
+        - ETH_ADDRESS_LENGTH
+        - 1
+        - 2
+        - size_of::<u64>()
+        - OFFER_TAGS_LENGTH],
+}
+
+// Where the fields clients filter offers on with getProgramAccounts memcmp start in the account data,
+// including the discriminator
+// The layout is append-only, so these never move, test_offer_layout_has_fixed_offsets holds them to that
+pub mod offsets {
+    use super::*;
+
+    pub const MAKER: usize = Offer::DISCRIMINATOR.len() + offset_of!(Offer, maker);
+    pub const TOKEN_MINT_A: usize = Offer::DISCRIMINATOR.len() + offset_of!(Offer, token_mint_a);
+    pub const TOKEN_MINT_B: usize = Offer::DISCRIMINATOR.len() + offset_of!(Offer, token_mint_b);
+    // A single byte, OfferStatus as u8
+    // Only offers that keep their history are ever anything but open, since every other offer is closed instead
+    pub const STATUS: usize = Offer::DISCRIMINATOR.len() + offset_of!(Offer, status);
+    // OFFER_TAGS_LENGTH bytes, a memcmp can match them all or start further in to match a single tag
+    pub const TAGS: usize = Offer::DISCRIMINATOR.len() + offset_of!(Offer, tags);
+}
+
+// Where an offer is in its lifecycle
+// Offers are closed when they're settled or refunded, unless the maker asked to keep their history,
+// in which case the offer stays open with its final status until the maker claims the rent back
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OfferStatus {
+    Open,
+    Filled,
+    Refunded,
+    // Refunded after its expiry had passed
+    Expired,
+}
+
+// A linear price schedule for the total amount of token b wanted
+// Before start_timestamp the price is start_price, after end_timestamp it is end_price
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct DutchAuction {
+    pub start_price: u64,
+    pub end_price: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+// The payment schedule a maker accepts for an offer, see take_offer_in_installments
+// The taker picks how many installments to pay in, up to max_installments, and must pay each one within interval
+// seconds of the one before, or the maker can reclaim the token a not yet released with cancel_default
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct InstallmentTerms {
+    pub max_installments: u8,
+    pub interval: i64,
+}
+
+// Bidding rules for an English auction
+// token_b_wanted_amount is the reserve price: the first bid must be at least this much
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct EnglishAuction {
+    // Bids are accepted until this time, after which anyone can settle the auction
+    pub end_timestamp: i64,
+    // Each new bid must beat the previous one by at least this much
+    pub min_bid_increment: u64,
+}
+
+impl Offer {
+    // Zero copy accounts are exactly the size of the struct
+    pub const INIT_SPACE: usize = size_of::<Offer>();
+
+    // The size of a new offer account, including the discriminator, before any metadata URI is attached
+    pub const SPACE: usize = Offer::DISCRIMINATOR.len() + Offer::INIT_SPACE;
+
+    // Where the fields clients usually filter on start in the account data, see offsets
+    pub const MAKER_OFFSET: usize = offsets::MAKER;
+    pub const TOKEN_MINT_A_OFFSET: usize = offsets::TOKEN_MINT_A;
+    pub const TOKEN_MINT_B_OFFSET: usize = offsets::TOKEN_MINT_B;
+
+    // The metadata URI's bytes follow the offer in the account data
+    pub const METADATA_URI_OFFSET: usize = Offer::SPACE;
+
+    // The size of each version of the layout, indexed by version
+    // Offers made before the layout was versioned have no version field, and are version 0
+    pub const LAYOUT_SIZES: [usize; OFFER_VERSION as usize + 1] =
+        [536, 544, 576, 592, 656, Offer::INIT_SPACE];
+
+    // Works out which version of the layout an offer account was written with
+    // metadata_uri_length is at the same offset in every version, so the account size less the URI is the layout size
+    pub fn layout_version(data: &[u8]) -> Result<u8> {
+        let length_offset = Offer::DISCRIMINATOR.len() + offset_of!(Offer, metadata_uri_length);
+        let length_bytes = data
+            .get(length_offset..length_offset + size_of::<u16>())
+            .ok_or(ErrorCode::UnknownOfferVersion)?;
+        let metadata_uri_length = u16::from_le_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let layout_size = data
+            .len()
+            .checked_sub(Offer::DISCRIMINATOR.len() + metadata_uri_length)
+            .ok_or(ErrorCode::UnknownOfferVersion)?;
+        let version = Offer::LAYOUT_SIZES
+            .iter()
+            .position(|size| *size == layout_size)
+            .ok_or(ErrorCode::UnknownOfferVersion)?;
+        Ok(version as u8)
+    }
+
+    // Price the offer at token_b_amount for every token_a_amount
+    pub fn set_price(&mut self, token_b_amount: u64, token_a_amount: u64) {
+        self.price_numerator = token_b_amount;
+        self.price_denominator = token_a_amount;
+    }
+
+    // The amount of token b owed for token_a_amount at the offer's price
+    // Rounded up, so the maker is never paid less than their price
+    pub fn token_b_amount_for(&self, token_a_amount: u64) -> Result<u64> {
+        require!(self.price_denominator > 0, ErrorCode::InvalidPrice);
+        mul_div(
+            token_a_amount,
+            self.price_numerator,
+            self.price_denominator,
+            Rounding::Up,
+        )
+    }
+
+    pub fn expires_at(&self) -> Option<i64> {
+        get_optional(self.has_expires_at, self.expires_at)
+    }
+
+    pub fn set_expires_at(&mut self, expires_at: Option<i64>) {
+        set_optional(&mut self.has_expires_at, &mut self.expires_at, expires_at);
+    }
+
+    pub fn dutch_auction(&self) -> Option<DutchAuction> {
+        get_optional(
+            self.has_dutch_auction,
+            DutchAuction {
+                start_price: self.dutch_auction_start_price,
+                end_price: self.dutch_auction_end_price,
+                start_timestamp: self.dutch_auction_start_timestamp,
+                end_timestamp: self.dutch_auction_end_timestamp,
+            },
+        )
+    }
+
+    pub fn set_dutch_auction(&mut self, dutch_auction: Option<DutchAuction>) {
+        self.has_dutch_auction = dutch_auction.is_some() as u8;
+        let dutch_auction = dutch_auction.unwrap_or_default();
+        self.dutch_auction_start_price = dutch_auction.start_price;
+        self.dutch_auction_end_price = dutch_auction.end_price;
+        self.dutch_auction_start_timestamp = dutch_auction.start_timestamp;
+        self.dutch_auction_end_timestamp = dutch_auction.end_timestamp;
+    }
+
+    pub fn english_auction(&self) -> Option<EnglishAuction> {
+        get_optional(
+            self.has_english_auction,
+            EnglishAuction {
+                end_timestamp: self.english_auction_end_timestamp,
+                min_bid_increment: self.english_auction_min_bid_increment,
+            },
+        )
+    }
+
+    pub fn set_english_auction(&mut self, english_auction: Option<EnglishAuction>) {
+        self.has_english_auction = english_auction.is_some() as u8;
+        let english_auction = english_auction.unwrap_or_default();
+        self.english_auction_end_timestamp = english_auction.end_timestamp;
+        self.english_auction_min_bid_increment = english_auction.min_bid_increment;
+    }
+
+    pub fn vesting_duration(&self) -> Option<i64> {
+        get_optional(self.has_vesting_duration, self.vesting_duration)
+    }
+
+    pub fn set_vesting_duration(&mut self, vesting_duration: Option<i64>) {
+        set_optional(
+            &mut self.has_vesting_duration,
+            &mut self.vesting_duration,
+            vesting_duration,
+        );
+    }
+
+    pub fn nft_collection(&self) -> Option<Pubkey> {
+        get_optional(self.has_nft_collection, self.nft_collection)
+    }
+
+    pub fn refund_authority(&self) -> Option<Pubkey> {
+        get_optional(self.has_refund_authority, self.refund_authority)
+    }
+
+    pub fn set_refund_authority(&mut self, refund_authority: Option<Pubkey>) {
+        set_optional(
+            &mut self.has_refund_authority,
+            &mut self.refund_authority,
+            refund_authority,
+        );
+    }
+
+    pub fn hashlock(&self) -> Option<[u8; HASH_BYTES]> {
+        get_optional(self.has_hashlock, self.hashlock)
+    }
+
+    pub fn set_hashlock(&mut self, hashlock: Option<[u8; HASH_BYTES]>) {
+        set_optional(&mut self.has_hashlock, &mut self.hashlock, hashlock);
+    }
+
+    pub fn arbiter(&self) -> Option<Pubkey> {
+        get_optional(self.has_arbiter, self.arbiter)
+    }
+
+    pub fn set_arbiter(&mut self, arbiter: Option<Pubkey>) {
+        set_optional(&mut self.has_arbiter, &mut self.arbiter, arbiter);
+    }
+
+    pub fn arbitration_pending(&self) -> bool {
+        self.arbitration_pending != 0
+    }
+
+    pub fn set_arbitration_pending(&mut self, arbitration_pending: bool) {
+        self.arbitration_pending = arbitration_pending as u8;
+    }
+
+    pub fn allowlist_enabled(&self) -> bool {
+        self.allowlist_enabled != 0
+    }
+
+    pub fn set_allowlist_enabled(&mut self, allowlist_enabled: bool) {
+        self.allowlist_enabled = allowlist_enabled as u8;
+    }
+
+    pub fn is_nft(&self) -> bool {
+        self.is_nft != 0
+    }
+
+    // Record that token a is an NFT, and its verified collection if it has one
+    pub fn set_nft(&mut self, nft_collection: Option<Pubkey>) {
+        self.is_nft = 1;
+        set_optional(
+            &mut self.has_nft_collection,
+            &mut self.nft_collection,
+            nft_collection,
+        );
+    }
+
+    // Delegated offers leave token a in the maker's token account rather than moving it to a vault
+    // The maker keeps custody until the offer is taken, which pulls the tokens using the offer's approval
+    pub fn is_delegated(&self) -> bool {
+        self.is_delegated != 0
+    }
+
+    pub fn set_delegated(&mut self, is_delegated: bool) {
+        self.is_delegated = is_delegated as u8;
+    }
+
+    // Who owns the token account holding the offer's token a: the vault authority, which owns the offer's vault,
+    // or the maker, for delegated offers
+    pub fn token_a_holder(&self, vault_authority: &Pubkey) -> Pubkey {
+        if self.is_delegated() {
+            self.maker
+        } else {
+            *vault_authority
+        }
+    }
+
+    // Offers that keep their history stay open once settled or refunded, as an on-chain record, until claim_rent closes them
+    // Only take_offer and refund_offer keep it, every other way of settling an offer closes it as usual
+    pub fn keep_history(&self) -> bool {
+        self.keep_history != 0
+    }
+
+    pub fn set_keep_history(&mut self, keep_history: bool) {
+        self.keep_history = keep_history as u8;
+    }
+
+    // Only offers that keep their history are ever anything but open, every other offer is closed instead
+    pub fn status(&self) -> OfferStatus {
+        match self.status {
+            1 => OfferStatus::Filled,
+            2 => OfferStatus::Refunded,
+            3 => OfferStatus::Expired,
+            _ => OfferStatus::Open,
+        }
+    }
+
+    pub fn set_status(&mut self, status: OfferStatus) {
+        self.status = status as u8;
+    }
+
+    // Offers made before installments were added read as zero installments, so must be paid up front
+    pub fn installment_terms(&self) -> Option<InstallmentTerms> {
+        (self.max_installments != 0).then_some(InstallmentTerms {
+            max_installments: self.max_installments,
+            interval: self.installment_interval,
+        })
+    }
+
+    pub fn set_installment_terms(&mut self, installment_terms: Option<InstallmentTerms>) {
+        let installment_terms = installment_terms.unwrap_or_default();
+        self.max_installments = installment_terms.max_installments;
+        self.installment_interval = installment_terms.interval;
+    }
+
+    pub fn attestor(&self) -> Option<Pubkey> {
+        get_optional(self.has_attestor, self.attestor)
+    }
+
+    pub fn set_attestor(&mut self, attestor: Option<Pubkey>) {
+        set_optional(&mut self.has_attestor, &mut self.attestor, attestor);
+    }
+
+    pub fn eth_taker(&self) -> Option<[u8; ETH_ADDRESS_LENGTH]> {
+        get_optional(self.has_eth_taker, self.eth_taker)
+    }
+
+    pub fn set_eth_taker(&mut self, eth_taker: Option<[u8; ETH_ADDRESS_LENGTH]>) {
+        set_optional(&mut self.has_eth_taker, &mut self.eth_taker, eth_taker);
+    }
+
+    pub fn additional_token_mints_a(&self) -> &[Pubkey] {
+        &self.additional_token_mints_a[..self.additional_token_mints_a_count as usize]
+    }
+
+    pub fn set_additional_token_mints_a(
+        &mut self,
+        additional_token_mints_a: &[Pubkey],
+    ) -> Result<()> {
+        require!(
+            additional_token_mints_a.len() <= MAX_ADDITIONAL_OFFERED_MINTS,
+            ErrorCode::TooManyOfferedMints
+        );
+        self.additional_token_mints_a = [Pubkey::default(); MAX_ADDITIONAL_OFFERED_MINTS];
+        self.additional_token_mints_a[..additional_token_mints_a.len()]
+            .copy_from_slice(additional_token_mints_a);
+        self.additional_token_mints_a_count = additional_token_mints_a.len() as u8;
+        Ok(())
+    }
+
+    pub fn memo(&self) -> Option<&str> {
+        if self.memo_length == 0 {
+            return None;
+        }
+        std::str::from_utf8(&self.memo[..self.memo_length as usize]).ok()
+    }
+
+    // The memo has to fit in the space reserved for it in the offer account
+    pub fn set_memo(&mut self, memo: Option<&str>) -> Result<()> {
+        let memo = memo.unwrap_or_default().as_bytes();
+        require!(memo.len() <= MAX_MEMO_LENGTH, ErrorCode::MemoTooLong);
+        self.memo = [0; MAX_MEMO_LENGTH];
+        self.memo[..memo.len()].copy_from_slice(memo);
+        self.memo_length = memo.len() as u8;
+        Ok(())
+    }
+
+    pub fn metadata_uri_length(&self) -> usize {
+        self.metadata_uri_length as usize
+    }
+
+    pub fn set_metadata_uri_length(&mut self, metadata_uri_length: usize) {
+        self.metadata_uri_length = metadata_uri_length as u16;
+    }
+}
+
+fn get_optional<T>(flag: u8, value: T) -> Option<T> {
+    (flag != 0).then_some(value)
+}
+
+fn set_optional<T: Default>(flag: &mut u8, value: &mut T, new_value: Option<T>) {
+    *flag = new_value.is_some() as u8;
+    *value = new_value.unwrap_or_default();
 }
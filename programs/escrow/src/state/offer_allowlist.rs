@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_ALLOWED_TAKERS;
+
+// Stores the takers a maker has allowed to take a specific offer
+// Only enforced while the offer's allowlist_enabled flag is set
+#[account]
+#[derive(InitSpace)]
+pub struct OfferAllowlist {
+    // The offer this allowlist applies to
+    pub offer: Pubkey,
+    // Who may take the offer
+    #[max_len(MAX_ALLOWED_TAKERS)]
+    pub takers: Vec<Pubkey>,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
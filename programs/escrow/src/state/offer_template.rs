@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::OFFER_TAGS_LENGTH;
+
+// A maker's standing terms for an offer, so a new offer can be made on their behalf whenever the last one is filled
+// The maker approves the template as the delegate on their token a account, and anyone, like a keeper, can then call
+// spawn_offer_from_template to move one offer's worth of token a into a fresh offer
+// One template per maker and pair, at ["offer_template", maker, token_mint_a, token_mint_b]
+#[account]
+#[derive(InitSpace)]
+pub struct OfferTemplate {
+    // Whose tokens the template's offers sell
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    // The terms every offer made from the template has
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    // The tags every offer made from the template is given, see Offer::tags
+    pub tags: [u8; OFFER_TAGS_LENGTH],
+    // The last offer made from the template, a new one is only made once it has been taken or refunded
+    pub current_offer: Option<Pubkey>,
+    // How many offers have been made from the template
+    pub offers_spawned: u64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+// Stores the taker's side of an arbitrated offer that has been taken, until the arbiter releases or reverts it
+// There is one pending settlement per offer, created when the offer is taken
+// The taker's token b is held in the pending vault, an associated token account owned by this account
+#[account]
+#[derive(InitSpace)]
+pub struct PendingSettlement {
+    // The offer that was taken, used to derive this account's address
+    pub offer: Pubkey,
+    // Who took the offer, and gets their token b back if the arbiter reverts it
+    pub taker: Pubkey,
+    // Who can release or revert the settlement, copied from the offer
+    pub arbiter: Pubkey,
+    // The amount of token b the taker paid into the pending vault
+    pub token_b_amount: u64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
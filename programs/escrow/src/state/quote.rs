@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+// A maker's answer to a quote request, firm until the request expires or is closed
+// There is one quote per maker for each request
+// The quoted token a is held in the quote vault, an associated token account owned by this account
+#[account]
+#[derive(InitSpace)]
+pub struct Quote {
+    // The request this quote answers
+    pub quote_request: Pubkey,
+    // Who escrowed the token a, and gets paid the token b if the quote is accepted
+    pub maker: Pubkey,
+    // The amount of token b the maker wants for the requested token a
+    pub token_b_wanted_amount: u64,
+    // When the quote request expires, after which the maker can withdraw the quote
+    pub expires_at: i64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+// A prospective taker's request for makers to quote a price, for trades too large to leave to the open offers
+// Makers answer with escrowed quotes, and the requester accepts whichever they like best
+#[account]
+#[derive(InitSpace)]
+pub struct QuoteRequest {
+    // Who wants to buy, and the only one who can accept a quote
+    pub requester: Pubkey,
+    // Chosen by the requester, so they can have several requests open at once
+    pub id: u64,
+    // What the requester wants to buy
+    pub token_mint_a: Pubkey,
+    // What the requester will pay with
+    pub token_mint_b: Pubkey,
+    // How much token a the requester wants, every quote escrows exactly this much
+    pub token_a_wanted_amount: u64,
+    // Quotes can't be submitted or accepted from then on, and their makers can withdraw them
+    pub expires_at: i64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
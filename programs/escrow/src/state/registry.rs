@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+// Protocol-wide offer statistics, stored in a single PDA so clients can read them without indexing events
+// Every handler that opens or closes an offer writes to it, so those transactions can't run in parallel
+#[account]
+#[derive(InitSpace)]
+pub struct Registry {
+    // How many offers have ever been made
+    pub total_offers_created: u64,
+    // How many offers are open right now, ie made but not yet taken, settled or refunded
+    pub total_offers_open: u64,
+    // The total token b makers have received from settled offers, in each mint's smallest units
+    // Offers trade many different mints, so this is a rough measure of activity rather than a value
+    // A u128, so a busy protocol can never overflow it and stop offers being taken
+    pub total_volume_settled: u128,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+// A maker's offer of a deactivated native stake account for token b, for OTC sales of large stake positions
+// While the offer is open this account is the stake account's staker and withdrawer, so the maker can't
+// withdraw or delegate the lamports, and settling hands both authorities to the taker
+#[account]
+#[derive(InitSpace)]
+pub struct StakeOffer {
+    // Who put the stake account up for sale, and gets paid the token b
+    pub maker: Pubkey,
+    // The stake account being sold, there is one offer per stake account
+    pub stake_account: Pubkey,
+    // What the maker will be paid with
+    pub token_mint_b: Pubkey,
+    pub token_b_wanted_amount: u64,
+    // The stake account's lamports when the offer was made
+    // Nobody can withdraw them while the offer is open, so the taker gets at least this much
+    pub stake_lamports: u64,
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+// Stores a taker's deposit of token b against an offer, ahead of either party confirming the swap
+// There is one deposit account per offer and taker
+// The deposit itself is held in the deposit vault, an associated token account owned by this account
+// The terms are recorded so the swap only happens if the offer hasn't changed since the taker deposited,
+// or been closed and made again at the same address
+#[account]
+#[derive(InitSpace)]
+pub struct TakerDeposit {
+    // The offer the deposit is for
+    pub offer: Pubkey,
+    // The maker of the offer, who may confirm the swap
+    pub maker: Pubkey,
+    // Who deposited, and gets the deposit back if they withdraw
+    pub taker: Pubkey,
+    // The token mint of the deposit, token b of the offer
+    pub token_mint_b: Pubkey,
+    // The token mint the offer held when the taker deposited, token a of the offer
+    pub token_mint_a: Pubkey,
+    // The slot the offer was made in, which tells it apart from a later offer made at the same address
+    pub offer_created_slot: u64,
+    // The amount of token a the offer held when the taker deposited
+    pub token_a_amount: u64,
+    // The amount of token b the offer wanted when the taker deposited
+    pub token_b_amount: u64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+// Stores the token a a taker bought from a vested offer, released to them linearly over time
+// There is one vesting account per offer, created when the offer is taken
+// The tokens are held in the vesting vault, an associated token account owned by this account
+#[account]
+#[derive(InitSpace)]
+pub struct Vesting {
+    // The offer that was taken, used to derive this account's address
+    pub offer: Pubkey,
+    // The taker, who can claim the tokens as they vest
+    pub beneficiary: Pubkey,
+    // The token mint of the vesting tokens, token a of the offer
+    pub token_mint: Pubkey,
+    // The amount of token a held for the beneficiary when the offer was taken
+    pub total_amount: u64,
+    // The amount of token a the beneficiary has claimed so far
+    pub claimed_amount: u64,
+    // Unix timestamp the offer was taken, when tokens start vesting
+    pub start_timestamp: i64,
+    // Unix timestamp when every token has vested
+    pub end_timestamp: i64,
+    // Used to calculate the address for this account, we save it as a performance optimization
+    pub bump: u8,
+}
@@ -1,13 +1,68 @@
+use solana_program::program_pack::Pack;
 use solana_signer::Signer;
 
+use crate::constants::{AUTO_ASSIGN_OFFER_ID, OFFER_VERSION};
+use crate::error::ErrorCode;
+use crate::math::{mul_div, Rounding};
+use crate::state::{
+    offsets, DutchAuction, EnglishAuction, FeeTier, InstallmentTerms, MembershipDiscount, MintPolicy, Offer, OfferStatus, Role,
+};
+
 use crate::escrow_test_helpers::{
     build_make_offer_accounts, build_make_offer_instruction, build_refund_offer_instruction, build_take_offer_instruction,
-    execute_make_offer, execute_take_offer, execute_refund_offer, generate_offer_id,
-    setup_escrow_test, RefundOfferAccounts, TakeOfferAccounts,
+    execute_accept_counter_offer, execute_make_counter_offer, execute_make_offer, execute_reject_counter_offer,
+    execute_make_offer_with_options, execute_take_offer, execute_refund_offer, execute_update_offer,
+    generate_offer_id, MakeOfferOptions, create_user, execute_outbid, execute_place_bid,
+    execute_settle_auction, get_bid_and_bid_vault_addresses, execute_add_allowed_taker,
+    execute_remove_allowed_taker, build_make_offer_bundle_accounts, build_make_offer_instruction_with_options,
+    build_settle_bundle_accounts, BundleMint, build_take_offer_nft_instruction, create_nft, create_nft_metadata,
+    get_offer, build_initialize_config_instruction, get_fee_vault_address, setup_escrow_test_with_fee_bps,
+    execute_withdraw_fees, REFERRAL_SHARE_BPS, execute_set_paused, execute_admin_force_refund,
+    execute_attach_metadata, execute_take_offers_batch, execute_take_offer_exact_out, execute_extend_offer,
+    build_take_offer_instruction_with_preimage, execute_take_offer_vested, execute_claim_vested, get_vesting,
+    execute_deposit_for_offer, execute_withdraw_deposit, execute_confirm_swap, get_taker_deposit_addresses,
+    execute_set_refund_authority, get_offer_metadata_uri, get_registry,
+    get_maker_index, get_maker_stats, execute_take_offer_arbitrated, execute_release, execute_revert,
+    get_pending_settlement_addresses, execute_migrate_offer, rewrite_offer_as_version_zero, execute_top_up_offer,
+    rewrite_offer_as_legacy, rewrite_offer_as_offer_owned, execute_refund_legacy_offer,
+    execute_reduce_offer, execute_set_mint_policy, set_mint_freeze_authority,
+    setup_escrow_test, RefundOfferAccounts, TakeOfferAccounts, assert_escrow_error,
+    send_transaction_and_get_compute_units, setup_escrow_test_with_token_2022, Token2022Extensions, get_token_balance,
+    setup_escrow_test_with_decimals, record_lamport_balances, assert_lamport_changes, get_rent,
+    LAMPORTS_PER_SIGNATURE, snapshot_accounts, assert_state_diff, get_config_address, get_registry_address,
+    get_maker_index_address, get_maker_stats_address, execute_competing_takes,
+    build_take_offer_instruction_with_memo, send_transaction_and_get_logs, get_offer_address,
+    get_maker_counter, get_maker_counter_address, send_transaction_and_get_return_data,
+    build_take_offer_nft_instruction_with_royalties, create_nft_metadata_with_royalties,
+    execute_set_royalties_required,
+    execute_make_offer_delegated, get_token_account, get_vault_authority_address,
+    execute_close_orphaned_vault, remove_offer_account,
+    assert_anchor_error, forge_offer_byte, get_program_id,
+    execute_claim_rent, execute_set_limits,
+    execute_set_mint_allowlist_enabled, execute_allow_mint, execute_revoke_mint,
+    get_mint_allowlist_entry_address, execute_deny_mint, execute_undeny_mint, get_mint_deny_entry_address,
+    execute_set_fee_tiers, execute_set_membership_discount, build_take_offer_instruction_with_membership,
+    execute_set_role, build_take_offer_with_token_accounts_instruction, create_token_account_at_new_address,
+    MakeOfferAccounts, create_token_2022_mint, create_token_2022_associated_token_account, mint_token_2022_to_account,
+    set_token_account_owner, execute_create_offer_template, execute_spawn_offer_from_template,
+    execute_close_offer_template, get_offer_template,
+    execute_set_installment_terms, execute_take_offer_in_installments, execute_pay_installment,
+    execute_cancel_default, get_installment_plan, execute_set_attestor, build_take_offer_instruction_with_attestation,
+    build_ed25519_instruction, build_attestation_message, execute_set_eth_taker, get_eth_address, build_secp256k1_instruction,
+    build_take_offer_with_authorization_instruction, build_taker_authorization_message, get_taker_authority_address,
+    execute_set_matcher_share, build_match_offers_instruction, MatchOffersAccounts,
+    execute_request_quote, execute_submit_quote, execute_accept_quote, execute_withdraw_quote,
+    execute_close_quote_request, get_quote_request_address, get_quote_addresses,
+    build_make_offer_with_existing_vault_instruction, execute_replace_offer,
+    send_versioned_transaction_with_compute_budget, build_take_offer_route_instruction, TakeOfferRouteAccounts,
+    get_route_token_account_address, create_stake_account, get_stake_authorities, get_stake_offer_address,
+    execute_make_stake_offer, execute_take_stake_offer, execute_cancel_stake_offer,
+    build_take_offer_instruction_with_recipient, execute_set_min_offer_age, execute_set_max_open_offers,
     TOKEN_A, TOKEN_B,
 };
 use solana_kite::{
-    assert_token_balance, check_account_is_closed, get_pda_and_bump, seeds, send_transaction_from_instructions,
+    assert_token_balance, check_account_is_closed, create_associated_token_account, create_token_mint,
+    mint_tokens_to_account, send_transaction_from_instructions,
 };
 
 #[test]
@@ -15,9 +70,9 @@ fn test_make_offer_succeeds() {
     let mut test_environment = setup_escrow_test();
 
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -51,60 +106,76 @@ fn test_make_offer_succeeds() {
 fn test_duplicate_offer_id_fails() {
     let mut test_environment = setup_escrow_test();
 
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
-    let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
-        &test_environment.token_mint_a.pubkey(),
-    );
-
-    let make_offer_accounts = build_make_offer_accounts(
-        test_environment.alice.pubkey(),
-        test_environment.token_mint_a.pubkey(),
-        test_environment.token_mint_b.pubkey(),
-        test_environment.alice_token_account_a,
-        offer_account,
-        vault,
+    let result = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        1 * TOKEN_A,
+        1 * TOKEN_B,
     );
+    assert!(result.is_ok(), "First offer should succeed");
 
-    let make_offer_instruction = build_make_offer_instruction(
+    // Different amounts, so this isn't rejected as the same transaction
+    let result = execute_make_offer(
+        &mut test_environment,
         offer_id,
-        1 * TOKEN_A,
+        &alice,
+        alice_token_account_a,
+        2 * TOKEN_A,
         1 * TOKEN_B,
-        make_offer_accounts,
     );
+    assert!(result.is_err(), "Second offer from the same maker with the same ID should fail");
+}
 
-    let result = send_transaction_from_instructions(
+#[test]
+fn test_different_makers_can_use_the_same_offer_id() {
+    let mut test_environment = setup_escrow_test();
+
+    mint_tokens_to_account(
         &mut test_environment.litesvm,
-        vec![make_offer_instruction],
-        &[&test_environment.alice],
-        &test_environment.alice.pubkey(),
-    );
-    assert!(result.is_ok(), "First offer should succeed");
+        &test_environment.token_mint_a.pubkey(),
+        &test_environment.bob_token_account_a,
+        1 * TOKEN_A,
+        &test_environment._mint_authority,
+    ).unwrap();
 
-    let make_offer_accounts_with_existing_offer_id = build_make_offer_accounts(
-        test_environment.bob.pubkey(),
-        test_environment.token_mint_a.pubkey(),
-        test_environment.token_mint_b.pubkey(),
-        test_environment.bob_token_account_a,
-        offer_account,
-        vault,
-    );
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
 
-    let make_offer_instruction_with_existing_offer_id = build_make_offer_instruction(
+    // Offer ids only need to be unique per maker, so Bob's offer doesn't collide with Alice's
+    let offer_id = generate_offer_id();
+    let (alice_offer_account, alice_vault) = execute_make_offer(
+        &mut test_environment,
         offer_id,
+        &alice,
+        alice_token_account_a,
         1 * TOKEN_A,
         1 * TOKEN_B,
-        make_offer_accounts_with_existing_offer_id,
-    );
+    )
+    .unwrap();
+    let (bob_offer_account, bob_vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &bob,
+        bob_token_account_a,
+        1 * TOKEN_A,
+        1 * TOKEN_B,
+    )
+    .unwrap();
 
-    let result = send_transaction_from_instructions(
-        &mut test_environment.litesvm,
-        vec![make_offer_instruction_with_existing_offer_id],
-        &[&test_environment.bob],
-        &test_environment.bob.pubkey(),
-    );
-    assert!(result.is_err(), "Second offer with same ID should fail");
+    assert_ne!(alice_offer_account, bob_offer_account);
+    assert_eq!(alice_offer_account, get_offer_address(&alice.pubkey(), offer_id));
+    assert_eq!(bob_offer_account, get_offer_address(&bob.pubkey(), offer_id));
+    assert_eq!(get_offer(&test_environment, &alice_offer_account).maker, alice.pubkey());
+    assert_eq!(get_offer(&test_environment, &bob_offer_account).maker, bob.pubkey());
+    assert_token_balance(&test_environment.litesvm, &alice_vault, 1 * TOKEN_A, "Alice's vault should hold Alice's offer");
+    assert_token_balance(&test_environment.litesvm, &bob_vault, 1 * TOKEN_A, "Bob's vault should hold Bob's offer");
 }
 
 #[test]
@@ -113,9 +184,9 @@ fn test_insufficient_funds_fails() {
 
     // Try to create offer with more tokens than Alice owns
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -141,7 +212,7 @@ fn test_insufficient_funds_fails() {
         &[&test_environment.alice],
         &test_environment.alice.pubkey(),
     );
-    assert!(result.is_err(), "Offer with insufficient funds should fail");
+    assert_escrow_error(result, ErrorCode::InsufficientMakerBalance);
 }
 
 #[test]
@@ -150,9 +221,9 @@ fn test_same_token_mints_fails() {
 
     // Try to create offer with same token mint for both token_a and token_b
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -174,7 +245,7 @@ fn test_same_token_mints_fails() {
         &[&test_environment.alice],
         &test_environment.alice.pubkey(),
     );
-    assert!(result.is_err(), "Offer with same token mints should fail");
+    assert_escrow_error(result, ErrorCode::InvalidTokenMint);
 }
 
 #[test]
@@ -183,9 +254,9 @@ fn test_zero_token_b_wanted_amount_fails() {
 
     // Try to create offer with zero token_b_wanted_amount
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -211,10 +282,7 @@ fn test_zero_token_b_wanted_amount_fails() {
         &[&test_environment.alice],
         &test_environment.alice.pubkey(),
     );
-    assert!(
-        result.is_err(),
-        "Offer with zero token_b_wanted_amount should fail"
-    );
+    assert_escrow_error(result, ErrorCode::InvalidWantedAmount);
 }
 
 #[test]
@@ -223,9 +291,9 @@ fn test_zero_token_a_offered_amount_fails() {
 
     // Try to create offer with zero token_a_offered_amount
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -251,10 +319,7 @@ fn test_zero_token_a_offered_amount_fails() {
         &[&test_environment.alice],
         &test_environment.alice.pubkey(),
     );
-    assert!(
-        result.is_err(),
-        "Offer with zero token_a_offered_amount should fail"
-    );
+    assert_escrow_error(result, ErrorCode::InvalidOfferedAmount);
 }
 
 #[test]
@@ -380,9 +445,9 @@ fn test_non_maker_cannot_refund_offer() {
 
     // Alice creates an offer: 3 token A for 2 token B
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -414,11 +479,14 @@ fn test_non_maker_cannot_refund_offer() {
     let refund_offer_accounts = RefundOfferAccounts {
         token_program: spl_token::ID,
         system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
         maker: test_environment.bob.pubkey(),
+        rent_payer: None,
         token_mint_a: test_environment.token_mint_a.pubkey(),
         maker_token_account_a: test_environment.alice_token_account_a,
         offer_account,
         vault,
+        allowlist: None,
     };
 
     let refund_instruction = build_refund_offer_instruction(refund_offer_accounts);
@@ -456,9 +524,9 @@ fn test_take_offer_insufficient_funds_fails() {
     // Create an offer from Alice for a large amount of token B
     let large_token_b_amount = 1000 * TOKEN_B; // Much larger than Bob's balance (he has 5)
     let offer_id = generate_offer_id();
-    let (offer_account, _offer_bump) = get_pda_and_bump(&seeds!["offer", offer_id], &test_environment.program_id);
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
     let vault = spl_associated_token_account::get_associated_token_address(
-        &offer_account,
+        &get_vault_authority_address(&offer_account),
         &test_environment.token_mint_a.pubkey(),
     );
 
@@ -493,6 +561,7 @@ fn test_take_offer_insufficient_funds_fails() {
         system_program: anchor_lang::system_program::ID,
         taker: test_environment.bob.pubkey(),
         maker: test_environment.alice.pubkey(),
+        rent_payer: None,
         token_mint_a: test_environment.token_mint_a.pubkey(),
         token_mint_b: test_environment.token_mint_b.pubkey(),
         taker_token_account_a: test_environment.bob_token_account_a,
@@ -500,9 +569,12 @@ fn test_take_offer_insufficient_funds_fails() {
         maker_token_account_b: test_environment.alice_token_account_b,
         offer_account,
         vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
     };
 
-    let take_offer_instruction = build_take_offer_instruction(take_offer_accounts);
+    let take_offer_instruction = build_take_offer_instruction(1 * TOKEN_A, large_token_b_amount, take_offer_accounts);
     let result = send_transaction_from_instructions(
         &mut test_environment.litesvm,
         vec![take_offer_instruction],
@@ -514,3 +586,6801 @@ fn test_take_offer_insufficient_funds_fails() {
         "Take offer with insufficient funds should fail"
     );
 }
+
+#[test]
+fn test_update_offer_changes_price_paid_by_taker() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice creates an offer: 3 token A for 2 token B
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Alice reprices the offer to 4 token B
+    execute_update_offer(&mut test_environment, &alice, offer_account, 4 * TOKEN_B, None).unwrap();
+
+    // Bob takes the offer at the new price
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        4 * TOKEN_B,
+        "Alice should have received the updated 4 token B",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        1 * TOKEN_B,
+        "Bob should have 1 token B left",
+    );
+}
+
+#[test]
+fn test_non_maker_cannot_update_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Bob tries to make Alice's offer cheaper for himself
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_update_offer(&mut test_environment, &bob, offer_account, 1, None);
+    assert!(result.is_err(), "Non-maker should not be able to update an offer");
+}
+
+#[test]
+fn test_update_offer_with_past_expiry_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let result = execute_update_offer(&mut test_environment, &alice, offer_account, 2 * TOKEN_B, Some(0));
+    assert!(result.is_err(), "Updating an offer with an expiry in the past should fail");
+}
+
+#[test]
+fn test_accept_counter_offer_settles_at_proposed_price() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice creates an offer: 3 token A for 4 token B
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+    ).unwrap();
+
+    // Bob proposes 2 token B instead
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let counter_offer = execute_make_counter_offer(
+        &mut test_environment,
+        &bob,
+        bob_token_account_b,
+        offer_account,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Alice accepts Bob's terms
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_accept_counter_offer(
+        &mut test_environment,
+        &alice,
+        &bob,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        counter_offer,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should have received the proposed 2 token B",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        3 * TOKEN_A,
+        "Bob should have received 3 token A",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        3 * TOKEN_B,
+        "Bob should have 3 token B left",
+    );
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &offer_account,
+        "Offer account should be closed after the counter offer is accepted"
+    );
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &counter_offer,
+        "Counter offer account should be closed after being accepted"
+    );
+}
+
+#[test]
+fn test_reject_counter_offer_keeps_offer_open() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let counter_offer = execute_make_counter_offer(
+        &mut test_environment,
+        &bob,
+        bob_token_account_b,
+        offer_account,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    execute_reject_counter_offer(
+        &mut test_environment,
+        &alice,
+        bob.pubkey(),
+        offer_account,
+        counter_offer,
+    ).unwrap();
+
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &counter_offer,
+        "Counter offer account should be closed after being rejected"
+    );
+    let offer_account_data = test_environment.litesvm.get_account(&offer_account);
+    assert!(
+        offer_account_data.is_some() && !offer_account_data.unwrap().data.is_empty(),
+        "Offer account should still exist after rejecting a counter offer"
+    );
+}
+
+#[test]
+fn test_non_maker_cannot_accept_counter_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let counter_offer = execute_make_counter_offer(
+        &mut test_environment,
+        &bob,
+        bob_token_account_b,
+        offer_account,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    // Bob tries to accept his own lowball counter offer
+    let result = execute_accept_counter_offer(
+        &mut test_environment,
+        &bob,
+        &bob,
+        bob_token_account_a,
+        bob_token_account_b,
+        bob_token_account_b,
+        offer_account,
+        counter_offer,
+        vault,
+    );
+    assert!(result.is_err(), "Only the maker should be able to accept a counter offer");
+}
+
+#[test]
+fn test_dutch_auction_offer_price_decays_over_time() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice auctions 3 token A, starting at 4 token B and falling to 2 token B
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+        MakeOfferOptions {
+            dutch_auction: Some(DutchAuction {
+                start_price: 4 * TOKEN_B,
+                end_price: 2 * TOKEN_B,
+                start_timestamp: 1_000,
+                end_timestamp: 2_000,
+            }),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    // Halfway through the auction, the price should be 3 token B
+    test_environment.warp_to_timestamp(1_500);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        3 * TOKEN_B,
+        "Alice should have received the halfway auction price of 3 token B",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        2 * TOKEN_B,
+        "Bob should have 2 token B left",
+    );
+}
+
+#[test]
+fn test_dutch_auction_with_rising_price_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let result = execute_make_offer_with_options(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            dutch_auction: Some(DutchAuction {
+                start_price: 2 * TOKEN_B,
+                end_price: 4 * TOKEN_B,
+                start_timestamp: 1_000,
+                end_timestamp: 2_000,
+            }),
+            ..Default::default()
+        },
+    );
+    assert!(result.is_err(), "Dutch auction prices should only decrease");
+}
+
+/// Alice auctions 3 token A with a reserve price of 2 token B, ending 1000 seconds from now
+fn make_english_auction_offer(
+    test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment,
+) -> (solana_pubkey::Pubkey, solana_pubkey::Pubkey, i64) {
+    let end_timestamp = test_environment.now() + 1_000;
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            english_auction: Some(EnglishAuction {
+                end_timestamp,
+                min_bid_increment: TOKEN_B / 2,
+            }),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    (offer_account, vault, end_timestamp)
+}
+
+#[test]
+fn test_maker_settles_auction_to_highest_bid() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, _end_timestamp) = make_english_auction_offer(&mut test_environment);
+
+    // Bob bids the reserve price, then Carol outbids him
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    execute_place_bid(&mut test_environment, &bob, bob_token_account_b, offer_account, 2 * TOKEN_B).unwrap();
+
+    let (carol, carol_token_account_a, carol_token_account_b) = create_user(&mut test_environment, 0, 5 * TOKEN_B);
+    execute_outbid(
+        &mut test_environment,
+        &carol,
+        carol_token_account_b,
+        bob.pubkey(),
+        bob_token_account_b,
+        offer_account,
+        3 * TOKEN_B,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_b,
+        5 * TOKEN_B,
+        "Bob should have been refunded after being outbid",
+    );
+
+    // Alice accepts the highest bid before the auction ends
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_settle_auction(
+        &mut test_environment,
+        &alice,
+        alice.pubkey(),
+        carol.pubkey(),
+        bob.pubkey(),
+        carol_token_account_a,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &carol_token_account_a,
+        3 * TOKEN_A,
+        "Carol should have received the auctioned 3 token A",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        3 * TOKEN_B,
+        "Alice should have received Carol's winning bid of 3 token B",
+    );
+
+    let (bid, bid_vault) = get_bid_and_bid_vault_addresses(&test_environment, &offer_account);
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed after settlement");
+    check_account_is_closed(&test_environment.litesvm, &bid, "Bid account should be closed after settlement");
+    check_account_is_closed(&test_environment.litesvm, &bid_vault, "Bid vault should be closed after settlement");
+}
+
+#[test]
+fn test_non_maker_cannot_settle_auction_before_it_ends() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, end_timestamp) = make_english_auction_offer(&mut test_environment);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_pubkey = test_environment.alice.pubkey();
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_place_bid(&mut test_environment, &bob, bob_token_account_b, offer_account, 2 * TOKEN_B).unwrap();
+
+    let result = execute_settle_auction(
+        &mut test_environment,
+        &bob,
+        alice_pubkey,
+        bob.pubkey(),
+        bob.pubkey(),
+        bob_token_account_a,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(result.is_err(), "Bidders should not be able to settle before the auction ends");
+
+    // Once the auction has ended, anyone can settle it
+    test_environment.warp_to_timestamp(end_timestamp);
+
+    execute_settle_auction(
+        &mut test_environment,
+        &bob,
+        alice_pubkey,
+        bob.pubkey(),
+        bob.pubkey(),
+        bob_token_account_a,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_a,
+        3 * TOKEN_A,
+        "Bob should have received the auctioned 3 token A",
+    );
+}
+
+#[test]
+fn test_bids_below_reserve_or_increment_fail() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, _vault, _end_timestamp) = make_english_auction_offer(&mut test_environment);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let result = execute_place_bid(&mut test_environment, &bob, bob_token_account_b, offer_account, 1 * TOKEN_B);
+    assert!(result.is_err(), "Bids below the reserve price should fail");
+
+    execute_place_bid(&mut test_environment, &bob, bob_token_account_b, offer_account, 2 * TOKEN_B).unwrap();
+
+    // Carol tries to outbid Bob by less than the minimum increment
+    let (carol, _carol_token_account_a, carol_token_account_b) = create_user(&mut test_environment, 0, 5 * TOKEN_B);
+    let result = execute_outbid(
+        &mut test_environment,
+        &carol,
+        carol_token_account_b,
+        bob.pubkey(),
+        bob_token_account_b,
+        offer_account,
+        2 * TOKEN_B + 1,
+    );
+    assert!(result.is_err(), "Outbids smaller than the minimum increment should fail");
+}
+
+#[test]
+fn test_allowlisted_offer_can_only_be_taken_by_allowed_takers() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Alice only wants Carol to take her offer
+    let (carol, carol_token_account_a, carol_token_account_b) = create_user(&mut test_environment, 0, 5 * TOKEN_B);
+    let allowlist = execute_add_allowed_taker(&mut test_environment, &alice, offer_account, carol.pubkey()).unwrap();
+
+    // Bob isn't allowed, with or without passing the allowlist
+    let bob = test_environment.bob.insecure_clone();
+    for allowlist_account in [None, Some(allowlist)] {
+        let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            taker: bob.pubkey(),
+            maker: alice.pubkey(),
+            rent_payer: None,
+            token_mint_a: test_environment.token_mint_a.pubkey(),
+            token_mint_b: test_environment.token_mint_b.pubkey(),
+            taker_token_account_a: test_environment.bob_token_account_a,
+            taker_token_account_b: test_environment.bob_token_account_b,
+            maker_token_account_b: test_environment.alice_token_account_b,
+            offer_account,
+            vault,
+            allowlist: allowlist_account,
+            referrer_token_account: None,
+            token_program_b: None,
+        });
+        let result = send_transaction_from_instructions(
+            &mut test_environment.litesvm,
+            vec![take_offer_instruction],
+            &[&bob],
+            &bob.pubkey(),
+        );
+        assert!(result.is_err(), "Takers not on the allowlist should not be able to take the offer");
+    }
+
+    // Carol is allowed
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: carol.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: carol_token_account_a,
+        taker_token_account_b: carol_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: Some(allowlist),
+        referrer_token_account: None,
+        token_program_b: None,
+    });
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&carol],
+        &carol.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &carol_token_account_a,
+        3 * TOKEN_A,
+        "Carol should have received 3 token A",
+    );
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &allowlist,
+        "Allowlist should be closed along with the offer",
+    );
+}
+
+#[test]
+fn test_removed_taker_cannot_take_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let allowlist = execute_add_allowed_taker(&mut test_environment, &alice, offer_account, bob.pubkey()).unwrap();
+    execute_remove_allowed_taker(&mut test_environment, &alice, offer_account, bob.pubkey()).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: Some(allowlist),
+        referrer_token_account: None,
+        token_program_b: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert!(result.is_err(), "Takers removed from the allowlist should not be able to take the offer");
+}
+
+/// Alice offers 3 token A plus 2 of a third token C for 2 token B
+/// Returns the offer, its main vault, token C's mint, and Alice's and Bob's token C accounts
+fn make_bundle_offer(
+    test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment,
+) -> (solana_pubkey::Pubkey, solana_pubkey::Pubkey, solana_pubkey::Pubkey, solana_pubkey::Pubkey, solana_pubkey::Pubkey) {
+    let token_mint_c = create_token_mint(&mut test_environment.litesvm, &test_environment._mint_authority, 9)
+        .unwrap()
+        .pubkey();
+    let alice_token_account_c = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &test_environment.alice,
+        &token_mint_c,
+        &test_environment._mint_authority,
+    ).unwrap();
+    let bob_token_account_c = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &test_environment.bob,
+        &token_mint_c,
+        &test_environment._mint_authority,
+    ).unwrap();
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &token_mint_c,
+        &alice_token_account_c,
+        10 * TOKEN_A,
+        &test_environment._mint_authority,
+    ).unwrap();
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &test_environment.token_mint_a.pubkey(),
+    );
+
+    let mut make_offer_instruction = build_make_offer_instruction_with_options(
+        offer_id,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            additional_token_a_offered_amounts: vec![2 * TOKEN_A],
+            ..Default::default()
+        },
+        build_make_offer_accounts(
+            test_environment.alice.pubkey(),
+            test_environment.token_mint_a.pubkey(),
+            test_environment.token_mint_b.pubkey(),
+            test_environment.alice_token_account_a,
+            offer_account,
+            vault,
+        ),
+    );
+    make_offer_instruction
+        .accounts
+        .extend(build_make_offer_bundle_accounts(&[BundleMint::new(
+            &offer_account,
+            token_mint_c,
+            alice_token_account_c,
+        )]));
+
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&test_environment.alice],
+        &test_environment.alice.pubkey(),
+    ).unwrap();
+
+    (offer_account, vault, token_mint_c, alice_token_account_c, bob_token_account_c)
+}
+
+#[test]
+fn test_take_bundle_offer_transfers_every_mint() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, token_mint_c, alice_token_account_c, bob_token_account_c) =
+        make_bundle_offer(&mut test_environment);
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_c,
+        8 * TOKEN_A,
+        "Alice should have 8 token C left after making the bundle offer",
+    );
+
+    let bundle_mint_c = BundleMint::new(&offer_account, token_mint_c, bob_token_account_c);
+    let bundle_vault_c = bundle_mint_c.vault;
+    let mut take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    });
+    take_offer_instruction
+        .accounts
+        .extend(build_settle_bundle_accounts(&[bundle_mint_c]));
+
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        3 * TOKEN_A,
+        "Bob should have received 3 token A",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_c,
+        2 * TOKEN_A,
+        "Bob should have received 2 token C",
+    );
+    check_account_is_closed(&test_environment.litesvm, &bundle_vault_c, "Token C vault should be closed");
+}
+
+#[test]
+fn test_take_bundle_offer_without_bundle_vaults_fails() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, _token_mint_c, _alice_token_account_c, _bob_token_account_c) =
+        make_bundle_offer(&mut test_environment);
+
+    // Bob tries to take only the token A part of the bundle
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    );
+    assert!(result.is_err(), "Taking a bundle offer without its other vaults should fail");
+}
+
+#[test]
+fn test_refund_bundle_offer_returns_every_mint() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, token_mint_c, alice_token_account_c, _bob_token_account_c) =
+        make_bundle_offer(&mut test_environment);
+
+    let mut refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        maker_token_account_a: test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+    refund_instruction
+        .accounts
+        .extend(build_settle_bundle_accounts(&[BundleMint::new(
+            &offer_account,
+            token_mint_c,
+            alice_token_account_c,
+        )]));
+
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&test_environment.alice],
+        &test_environment.alice.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should have all 10 token A back after refunding",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_c,
+        10 * TOKEN_A,
+        "Alice should have all 10 token C back after refunding",
+    );
+}
+
+/// Alice offers an NFT for 2 token B, passing its Metaplex metadata
+/// Returns the offer, its vault, and the NFT's mint
+fn make_nft_offer(
+    test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment,
+    collection: Option<solana_pubkey::Pubkey>,
+) -> (solana_pubkey::Pubkey, solana_pubkey::Pubkey, solana_pubkey::Pubkey) {
+    let alice = test_environment.alice.insecure_clone();
+    let (nft_mint, alice_nft_account, nft_metadata) = create_nft(test_environment, &alice, collection);
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(&get_vault_authority_address(&offer_account), &nft_mint);
+
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        nft_mint,
+        test_environment.token_mint_b.pubkey(),
+        alice_nft_account,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.token_mint_a_metadata = Some(nft_metadata);
+
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 1, 2 * TOKEN_B, make_offer_accounts);
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    ).unwrap();
+
+    (offer_account, vault, nft_mint)
+}
+
+fn build_take_nft_offer_accounts(
+    test_environment: &crate::escrow_test_helpers::EscrowTestEnvironment,
+    offer_account: solana_pubkey::Pubkey,
+    vault: solana_pubkey::Pubkey,
+    nft_mint: solana_pubkey::Pubkey,
+) -> TakeOfferAccounts {
+    TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: nft_mint,
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: spl_associated_token_account::get_associated_token_address(
+            &test_environment.bob.pubkey(),
+            &nft_mint,
+        ),
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    }
+}
+
+#[test]
+fn test_take_offer_nft_from_expected_collection_succeeds() {
+    let mut test_environment = setup_escrow_test();
+    let collection = solana_pubkey::Pubkey::new_unique();
+    let (offer_account, vault, nft_mint) = make_nft_offer(&mut test_environment, Some(collection));
+
+    let take_offer_instruction = build_take_offer_nft_instruction(
+        Some(collection),
+        2 * TOKEN_B,
+        build_take_nft_offer_accounts(&test_environment, offer_account, vault, nft_mint),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &spl_associated_token_account::get_associated_token_address(&test_environment.bob.pubkey(), &nft_mint),
+        1,
+        "Bob should have received the NFT",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should have received 2 token B",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_take_offer_nft_from_unexpected_collection_fails() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, nft_mint) =
+        make_nft_offer(&mut test_environment, Some(solana_pubkey::Pubkey::new_unique()));
+
+    // Bob wants an NFT from a different collection
+    let take_offer_instruction = build_take_offer_nft_instruction(
+        Some(solana_pubkey::Pubkey::new_unique()),
+        2 * TOKEN_B,
+        build_take_nft_offer_accounts(&test_environment, offer_account, vault, nft_mint),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    );
+    assert!(result.is_err(), "Taking an NFT from the wrong collection should fail");
+}
+
+#[test]
+fn test_make_offer_with_metadata_for_fungible_token_fails() {
+    let mut test_environment = setup_escrow_test();
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let token_mint_a_metadata = create_nft_metadata(&mut test_environment, &token_mint_a, None);
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(&get_vault_authority_address(&offer_account), &token_mint_a);
+
+    let mut make_offer_accounts = build_make_offer_accounts(
+        test_environment.alice.pubkey(),
+        token_mint_a,
+        test_environment.token_mint_b.pubkey(),
+        test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.token_mint_a_metadata = Some(token_mint_a_metadata);
+
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 1, 2 * TOKEN_B, make_offer_accounts);
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&test_environment.alice],
+        &test_environment.alice.pubkey(),
+    );
+    assert!(result.is_err(), "Offering a fungible token as an NFT should fail");
+}
+
+#[test]
+fn test_make_offer_records_amount_received_by_vault() {
+    let mut test_environment = setup_escrow_test();
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Classic token mints have no transfer fee, so the vault receives everything sent
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+}
+
+#[test]
+fn test_take_offer_charges_protocol_fee() {
+    // A 1% protocol fee
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    take_offer_with_fee(&mut test_environment);
+
+    let protocol_fee = 3 * TOKEN_A / 100;
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        3 * TOKEN_A - protocol_fee,
+        "Bob should receive the offered token A less the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &get_fee_vault_address(&test_environment.token_mint_a.pubkey()),
+        protocol_fee,
+        "The fee vault should hold the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should receive the full amount of token B",
+    );
+}
+
+#[test]
+fn test_initialize_config_twice_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    // setup_escrow_test() has already initialized the config
+    let initialize_config_instruction =
+        build_initialize_config_instruction(0, test_environment.bob.pubkey(), 0, test_environment.bob.pubkey());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![initialize_config_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    );
+    assert!(result.is_err(), "The config should only be initialized once");
+}
+
+/// Alice offers 3 token A for 2 token B and Bob takes it, leaving a protocol fee in the token A fee vault
+fn take_offer_with_fee(test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment) {
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+}
+
+#[test]
+fn test_withdraw_fees_sweeps_fee_vault() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    take_offer_with_fee(&mut test_environment);
+
+    let fee_authority = test_environment.fee_authority.insecure_clone();
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let destination_token_account = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &fee_authority,
+        &token_mint_a,
+        &fee_authority,
+    ).unwrap();
+
+    execute_withdraw_fees(&mut test_environment, &fee_authority, destination_token_account).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &destination_token_account,
+        3 * TOKEN_A / 100,
+        "The fee authority should receive the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &get_fee_vault_address(&token_mint_a),
+        0,
+        "The fee vault should be empty",
+    );
+}
+
+#[test]
+fn test_withdraw_fees_by_non_fee_authority_fails() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    take_offer_with_fee(&mut test_environment);
+
+    // Bob tries to sweep the fees to his own token account
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let result = execute_withdraw_fees(&mut test_environment, &bob, bob_token_account_a);
+    assert!(result.is_err(), "Only the fee authority should be able to withdraw fees");
+}
+
+#[test]
+fn test_take_offer_with_referrer_splits_protocol_fee() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    let (_referrer, referrer_token_account_a, _referrer_token_account_b) = create_user(&mut test_environment, 0, 0);
+
+    let offer_id = generate_offer_id();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: Some(referrer_token_account_a),
+        token_program_b: None,
+    });
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    ).unwrap();
+
+    let total_fee = 3 * TOKEN_A / 100;
+    let referral_fee = total_fee * REFERRAL_SHARE_BPS as u64 / 10_000;
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        3 * TOKEN_A - total_fee,
+        "Bob should receive the offered token A less the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &referrer_token_account_a,
+        referral_fee,
+        "The referrer should receive their share of the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &get_fee_vault_address(&test_environment.token_mint_a.pubkey()),
+        total_fee - referral_fee,
+        "The fee vault should hold the rest of the protocol fee",
+    );
+}
+
+#[test]
+fn test_make_offer_while_paused_fails() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    execute_set_paused(&mut test_environment, true, &authority).unwrap();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    );
+    assert!(result.is_err(), "Offers should not be made while the program is paused");
+
+    // Once unpaused, offers can be made again
+    execute_set_paused(&mut test_environment, false, &authority).unwrap();
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    );
+    assert!(result.is_ok(), "Offers should be made once the program is unpaused");
+}
+
+#[test]
+fn test_take_offer_while_paused_fails_but_refund_succeeds() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let authority = test_environment.config_authority.insecure_clone();
+    execute_set_paused(&mut test_environment, true, &authority).unwrap();
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(result.is_err(), "Offers should not be taken while the program is paused");
+
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_a,
+        10 * TOKEN_A,
+        "Makers should still be able to refund while the program is paused",
+    );
+}
+
+#[test]
+fn test_pause_by_non_authority_fails() {
+    let mut test_environment = setup_escrow_test();
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_set_paused(&mut test_environment, true, &bob);
+    assert!(result.is_err(), "Only the config authority should be able to pause the program");
+}
+
+#[test]
+fn test_admin_force_refund_returns_tokens_to_maker() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let authority = test_environment.config_authority.insecure_clone();
+    execute_admin_force_refund(&mut test_environment, &authority, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should have all her token A back",
+    );
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_admin_force_refund_by_non_authority_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_admin_force_refund(&mut test_environment, &bob, offer_account, vault);
+    assert!(result.is_err(), "Only the config authority should be able to force a refund");
+}
+
+#[test]
+fn test_make_offer_saves_memo() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            memo: Some("OTC deal with DAO treasury".to_string()),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.memo(), Some("OTC deal with DAO treasury"));
+}
+
+#[test]
+fn test_make_offer_with_long_memo_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let result = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            memo: Some("a".repeat(crate::constants::MAX_MEMO_LENGTH + 1)),
+            ..Default::default()
+        },
+    );
+    assert!(result.is_err(), "Memos longer than the maximum should be rejected");
+}
+
+#[test]
+fn test_attach_metadata_grows_offer_account() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let size_before = test_environment.litesvm.get_account(&offer_account).unwrap().data.len();
+
+    let metadata_uri = "https://example.com/offers/otc-deal.json";
+    execute_attach_metadata(&mut test_environment, &alice, offer_account, metadata_uri).unwrap();
+
+    let size_after = test_environment.litesvm.get_account(&offer_account).unwrap().data.len();
+    assert_eq!(size_after, size_before + metadata_uri.len());
+    assert_eq!(get_offer_metadata_uri(&test_environment, &offer_account), metadata_uri);
+}
+
+#[test]
+fn test_offer_layout_has_fixed_offsets() {
+    // Clients filter offers with getProgramAccounts memcmp at these offsets, so they must never move
+    assert_eq!(Offer::MAKER_OFFSET, 16);
+    assert_eq!(Offer::TOKEN_MINT_A_OFFSET, 48);
+    assert_eq!(Offer::TOKEN_MINT_B_OFFSET, 80);
+    assert_eq!(offsets::MAKER, 16);
+    assert_eq!(offsets::TOKEN_MINT_A, 48);
+    assert_eq!(offsets::TOKEN_MINT_B, 80);
+    assert_eq!(offsets::STATUS, 609);
+    assert_eq!(offsets::TAGS, 688);
+
+    assert_eq!(std::mem::offset_of!(Offer, id), 0);
+    assert_eq!(std::mem::offset_of!(Offer, maker), 8);
+    assert_eq!(std::mem::offset_of!(Offer, token_mint_a), 40);
+    assert_eq!(std::mem::offset_of!(Offer, token_mint_b), 72);
+    assert_eq!(std::mem::offset_of!(Offer, token_b_wanted_amount), 104);
+    assert_eq!(std::mem::offset_of!(Offer, token_a_offered_amount), 112);
+    assert_eq!(std::mem::offset_of!(Offer, highest_bid), 120);
+    assert_eq!(std::mem::offset_of!(Offer, min_fill_amount), 128);
+    assert_eq!(std::mem::offset_of!(Offer, maker_authority), 200);
+    assert_eq!(std::mem::offset_of!(Offer, bump), 520);
+    assert_eq!(std::mem::offset_of!(Offer, version), 536);
+    assert_eq!(std::mem::offset_of!(Offer, rent_payer), 544);
+    assert_eq!(std::mem::offset_of!(Offer, price_numerator), 576);
+    assert_eq!(std::mem::offset_of!(Offer, price_denominator), 584);
+    assert_eq!(std::mem::offset_of!(Offer, created_at), 592);
+    assert_eq!(std::mem::offset_of!(Offer, created_slot), 672);
+    assert_eq!(std::mem::offset_of!(Offer, tags), 680);
+
+    assert_eq!(Offer::INIT_SPACE, 720);
+    assert_eq!(Offer::SPACE, 728);
+    assert_eq!(Offer::METADATA_URI_OFFSET, 728);
+}
+
+#[test]
+fn test_offer_account_matches_layout() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let data = test_environment.litesvm.get_account(&offer_account).unwrap().data;
+    assert_eq!(data.len(), Offer::METADATA_URI_OFFSET);
+    assert_eq!(&data[Offer::MAKER_OFFSET..Offer::MAKER_OFFSET + 32], alice.pubkey().as_ref());
+    assert_eq!(
+        &data[Offer::TOKEN_MINT_A_OFFSET..Offer::TOKEN_MINT_A_OFFSET + 32],
+        test_environment.token_mint_a.pubkey().as_ref(),
+    );
+    assert_eq!(
+        &data[Offer::TOKEN_MINT_B_OFFSET..Offer::TOKEN_MINT_B_OFFSET + 32],
+        test_environment.token_mint_b.pubkey().as_ref(),
+    );
+    assert_eq!(data[offsets::STATUS], OfferStatus::Open as u8);
+}
+
+#[test]
+fn test_attach_metadata_by_non_maker_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_attach_metadata(&mut test_environment, &bob, offer_account, "https://example.com/spam.json");
+    assert!(result.is_err(), "Only the maker should be able to attach metadata");
+}
+
+#[test]
+fn test_take_offers_batch_takes_every_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let first_offer = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let second_offer = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        2 * TOKEN_A,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    execute_take_offers_batch(&mut test_environment, alice.pubkey(), vec![first_offer, second_offer]).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        5 * TOKEN_A,
+        "Bob should receive the token A from both offers",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        3 * TOKEN_B,
+        "Alice should receive the token B for both offers",
+    );
+    check_account_is_closed(&test_environment.litesvm, &first_offer.0, "First offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &second_offer.0, "Second offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &first_offer.1, "First vault should be closed");
+    check_account_is_closed(&test_environment.litesvm, &second_offer.1, "Second vault should be closed");
+}
+
+#[test]
+fn test_take_offers_batch_with_offer_from_another_maker_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_offer = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let (carol, carol_token_account_a, _carol_token_account_b) = create_user(&mut test_environment, 5 * TOKEN_A, 0);
+    let carol_offer = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &carol,
+        carol_token_account_a,
+        2 * TOKEN_A,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    let result = execute_take_offers_batch(&mut test_environment, alice.pubkey(), vec![alice_offer, carol_offer]);
+    assert!(result.is_err(), "Every offer in a batch must be from the same maker");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        0,
+        "Bob should not receive anything from a failed batch",
+    );
+}
+
+#[test]
+fn test_take_offer_exact_out_fills_offer_in_parts() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Bob takes a quarter of the offer, so pays a quarter of the token B
+    execute_take_offer_exact_out(&mut test_environment, 1 * TOKEN_A, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        1 * TOKEN_A,
+        "Bob should receive exactly the token A he asked for",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        TOKEN_B / 2,
+        "Alice should receive a quarter of the token B wanted",
+    );
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.token_b_wanted_amount, 3 * TOKEN_B / 2);
+    assert_eq!(
+        (offer.price_numerator, offer.price_denominator),
+        (2 * TOKEN_B, 4 * TOKEN_A),
+        "Partial fills should keep the maker's price"
+    );
+
+    // Taking the rest closes the offer
+    execute_take_offer_exact_out(&mut test_environment, 3 * TOKEN_A, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should receive all the token B wanted",
+    );
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_take_offer_exact_out_more_than_offered_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let result = execute_take_offer_exact_out(&mut test_environment, 5 * TOKEN_A, offer_account, vault);
+    assert!(result.is_err(), "Takers can't take more token A than the offer holds");
+}
+
+#[test]
+fn test_take_offer_after_repricing_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Alice reprices the offer before Bob's take lands
+    execute_update_offer(&mut test_environment, &alice, offer_account, 4 * TOKEN_B, None).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    );
+    assert!(result.is_err(), "Takers should not pay more than the terms they saw");
+}
+
+#[test]
+fn test_take_offer_exact_out_enforces_min_fill_amount() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice offers 4 token A for 2 token B, in fills of at least 2 token A
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            min_fill_amount: 2 * TOKEN_A,
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let result = execute_take_offer_exact_out(&mut test_environment, 1 * TOKEN_A, offer_account, vault);
+    assert!(result.is_err(), "Fills below the minimum should fail");
+
+    let result = execute_take_offer_exact_out(&mut test_environment, 3 * TOKEN_A, offer_account, vault);
+    assert!(result.is_err(), "Fills leaving less than the minimum behind should fail");
+
+    execute_take_offer_exact_out(&mut test_environment, 2 * TOKEN_A, offer_account, vault).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).token_a_offered_amount, 2 * TOKEN_A);
+}
+
+#[test]
+fn test_extend_offer_pushes_expiry_forward() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let now = test_environment.now();
+    execute_update_offer(&mut test_environment, &alice, offer_account, 2 * TOKEN_B, Some(now + 1_000)).unwrap();
+
+    execute_extend_offer(&mut test_environment, &alice, offer_account, now + 2_000).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).expires_at(), Some(now + 2_000));
+
+    let result = execute_extend_offer(&mut test_environment, &alice, offer_account, now + 1_500);
+    assert!(result.is_err(), "Extending an offer can't bring its expiry forward");
+
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_extend_offer(&mut test_environment, &bob, offer_account, now + 3_000);
+    assert!(result.is_err(), "Only the maker can extend an offer");
+
+    // The offer can still be taken after its original expiry
+    test_environment.warp_to_timestamp(now + 1_500);
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+}
+
+#[test]
+fn test_hashlocked_offer_needs_matching_preimage() {
+    let mut test_environment = setup_escrow_test();
+
+    let preimage = b"alice and bob's atomic swap secret".to_vec();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            hashlock: Some(anchor_lang::solana_program::hash::hash(&preimage).to_bytes()),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let take_offer_accounts = || TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    };
+
+    let bob = test_environment.bob.insecure_clone();
+    for wrong_preimage in [None, Some(b"wrong secret".to_vec())] {
+        let take_offer_instruction = build_take_offer_instruction_with_preimage(
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            wrong_preimage,
+            take_offer_accounts(),
+        );
+        let result = send_transaction_from_instructions(
+            &mut test_environment.litesvm,
+            vec![take_offer_instruction],
+            &[&bob],
+            &bob.pubkey(),
+        );
+        assert!(result.is_err(), "Hashlocked offers need the matching preimage");
+    }
+
+    let take_offer_instruction = build_take_offer_instruction_with_preimage(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        Some(preimage),
+        take_offer_accounts(),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should have received 2 token B",
+    );
+}
+
+#[test]
+fn test_vested_offer_releases_token_a_over_time() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice sells 4 token A for 2 token B, vesting to the buyer over 1000 seconds
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            vesting_duration: Some(1_000),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    // Vested offers can't be taken outright
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(result.is_err(), "Vested offers should only be taken with take_offer_vested");
+
+    // The maker repricing the offer first makes the take fail, rather than charging Bob the new price
+    execute_update_offer(&mut test_environment, &alice, offer_account, 3 * TOKEN_B, None).unwrap();
+    let result = execute_take_offer_vested(&mut test_environment, offer_account, vault, 4 * TOKEN_A, 2 * TOKEN_B);
+    assert_escrow_error(result, ErrorCode::OfferTermsChanged);
+    execute_update_offer(&mut test_environment, &alice, offer_account, 2 * TOKEN_B, None).unwrap();
+
+    let vesting = execute_take_offer_vested(&mut test_environment, offer_account, vault, 4 * TOKEN_A, 2 * TOKEN_B).unwrap();
+
+    // Alice is paid straight away, Bob's token A is locked up
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should be paid when the offer is taken",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_a,
+        0,
+        "Bob should not receive any token A until it vests",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+
+    let start_timestamp = get_vesting(&test_environment, &vesting).start_timestamp;
+    test_environment.warp_to_timestamp(start_timestamp + 250);
+
+    // A quarter of the way through, a quarter has vested
+    execute_claim_vested(&mut test_environment, &bob, bob_token_account_a, vesting).unwrap();
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_a,
+        1 * TOKEN_A,
+        "Bob should be able to claim a quarter of the token A",
+    );
+
+    let result = execute_claim_vested(&mut test_environment, &alice, alice_token_account_a, vesting);
+    assert!(result.is_err(), "Only the beneficiary can claim vested tokens");
+
+    // Warping also gives the second claim, the same transaction as the first, a fresh blockhash
+    test_environment.warp_to_timestamp(start_timestamp + 1_000);
+
+    execute_claim_vested(&mut test_environment, &bob, bob_token_account_a, vesting).unwrap();
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_a,
+        4 * TOKEN_A,
+        "Bob should have all the token A once it has fully vested",
+    );
+    check_account_is_closed(&test_environment.litesvm, &vesting, "Vesting account should be closed");
+}
+
+#[test]
+fn test_confirm_swap_exchanges_both_deposits() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    execute_deposit_for_offer(&mut test_environment, offer_account).unwrap();
+    let (taker_deposit, deposit_vault) =
+        get_taker_deposit_addresses(&test_environment, &offer_account, &test_environment.bob.pubkey());
+    assert_token_balance(
+        &test_environment.litesvm,
+        &deposit_vault,
+        2 * TOKEN_B,
+        "Bob's deposit should hold the 2 token B the offer wants",
+    );
+
+    // Someone other than Alice or Bob can't settle the swap
+    let (carol, _carol_token_account_a, _carol_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let result = execute_confirm_swap(&mut test_environment, &carol, offer_account, vault);
+    assert!(result.is_err(), "Only the maker or the taker should be able to confirm the swap");
+
+    execute_confirm_swap(&mut test_environment, &alice, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        3 * TOKEN_A,
+        "Bob should receive the 3 token A offered",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should receive Bob's deposit",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+    check_account_is_closed(&test_environment.litesvm, &taker_deposit, "Deposit account should be closed");
+    check_account_is_closed(&test_environment.litesvm, &deposit_vault, "Deposit vault should be closed");
+}
+
+#[test]
+fn test_confirm_swap_after_repricing_fails_and_taker_can_withdraw() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    execute_deposit_for_offer(&mut test_environment, offer_account).unwrap();
+
+    // Alice changes the terms after Bob deposited
+    execute_update_offer(&mut test_environment, &alice, offer_account, 4 * TOKEN_B, None).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_confirm_swap(&mut test_environment, &bob, offer_account, vault);
+    assert!(result.is_err(), "Swaps should not settle on terms the taker didn't deposit for");
+
+    execute_withdraw_deposit(&mut test_environment, offer_account).unwrap();
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        5 * TOKEN_B,
+        "Bob should get his whole deposit back",
+    );
+}
+
+#[test]
+fn test_confirm_swap_fails_for_a_new_offer_made_at_the_same_address() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let offer_id = generate_offer_id();
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    execute_deposit_for_offer(&mut test_environment, offer_account).unwrap();
+
+    // Alice closes the offer and makes a new one with the same id and amounts
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    test_environment.warp_forward(1);
+    execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let result = execute_confirm_swap(&mut test_environment, &alice, offer_account, vault);
+    assert!(result.is_err(), "A deposit should only settle against the offer it was made for");
+
+    execute_withdraw_deposit(&mut test_environment, offer_account).unwrap();
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        5 * TOKEN_B,
+        "Bob should get his whole deposit back",
+    );
+}
+
+#[test]
+fn test_deposit_for_delegated_offer_fails() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let offer_account = execute_make_offer_delegated(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [0; 8],
+    )
+    .unwrap();
+
+    // Delegated offers have no vault for confirm_swap to pay the taker from
+    let result = execute_deposit_for_offer(&mut test_environment, offer_account);
+    assert!(result.is_err(), "Delegated offers should not accept deposits");
+}
+
+#[test]
+fn test_maker_authority_can_confirm_swap() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice's operator makes the offer for her, with her approval to trade 3 token A
+    let (operator, _operator_token_account_a, _operator_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let alice = test_environment.alice.insecure_clone();
+    let approve_instruction = spl_token::instruction::approve(
+        &spl_token::ID,
+        &test_environment.alice_token_account_a,
+        &operator.pubkey(),
+        &alice.pubkey(),
+        &[],
+        3 * TOKEN_A,
+    ).unwrap();
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![approve_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    ).unwrap();
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        test_environment.token_mint_a.pubkey(),
+        test_environment.token_mint_b.pubkey(),
+        test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.maker_authority = Some(operator.pubkey());
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 3 * TOKEN_A, 2 * TOKEN_B, make_offer_accounts);
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&operator],
+        &operator.pubkey(),
+    ).unwrap();
+
+    execute_deposit_for_offer(&mut test_environment, offer_account).unwrap();
+    execute_confirm_swap(&mut test_environment, &operator, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should receive Bob's deposit",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_delegate_can_make_and_refund_offer_for_maker() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice's treasury can't sign for itself, so it approves an operator to trade 3 token A
+    let (operator, _operator_token_account_a, _operator_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let alice = test_environment.alice.insecure_clone();
+    let approve_instruction = spl_token::instruction::approve(
+        &spl_token::ID,
+        &test_environment.alice_token_account_a,
+        &operator.pubkey(),
+        &alice.pubkey(),
+        &[],
+        3 * TOKEN_A,
+    ).unwrap();
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![approve_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    ).unwrap();
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        test_environment.token_mint_a.pubkey(),
+        test_environment.token_mint_b.pubkey(),
+        test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+    );
+
+    // Someone Alice hasn't approved can't make offers for her
+    let (mallory, _mallory_token_account_a, _mallory_token_account_b) = create_user(&mut test_environment, 0, 0);
+    make_offer_accounts.maker_authority = Some(mallory.pubkey());
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 3 * TOKEN_A, 2 * TOKEN_B, make_offer_accounts);
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&mallory],
+        &mallory.pubkey(),
+    );
+    assert!(result.is_err(), "Only the maker or their delegate should be able to make offers for the maker");
+
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        test_environment.token_mint_a.pubkey(),
+        test_environment.token_mint_b.pubkey(),
+        test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.maker_authority = Some(operator.pubkey());
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 3 * TOKEN_A, 2 * TOKEN_B, make_offer_accounts);
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&operator],
+        &operator.pubkey(),
+    ).unwrap();
+
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.maker, alice.pubkey());
+    assert_eq!(offer.maker_authority, operator.pubkey());
+
+    // The operator can refund the offer, and the tokens go back to Alice
+    let refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: Some(operator.pubkey()),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        maker_token_account_a: test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&operator],
+        &operator.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should have all her token A back",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_refund_authority_can_refund_but_not_update_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).refund_authority(), None);
+
+    // Alice keeps her treasury key offline and lets a bot key pull the offer
+    let (bot, _bot_token_account_a, _bot_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_set_refund_authority(&mut test_environment, &bob, offer_account, Some(bob.pubkey()));
+    assert!(result.is_err(), "Only the maker can set the refund authority");
+
+    execute_set_refund_authority(&mut test_environment, &alice, offer_account, Some(bot.pubkey())).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).refund_authority(), Some(bot.pubkey()));
+
+    let result = execute_update_offer(&mut test_environment, &bot, offer_account, TOKEN_B, None);
+    assert!(result.is_err(), "The refund authority can't change the offer's terms");
+
+    let refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: Some(bot.pubkey()),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        maker_token_account_a: alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&bot],
+        &bot.pubkey(),
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "The refunded tokens should go back to Alice, not the refund authority",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_cleared_refund_authority_can_not_refund_offer() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let (bot, _bot_token_account_a, _bot_token_account_b) = create_user(&mut test_environment, 0, 0);
+    execute_set_refund_authority(&mut test_environment, &alice, offer_account, Some(bot.pubkey())).unwrap();
+    execute_set_refund_authority(&mut test_environment, &alice, offer_account, None).unwrap();
+
+    let refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: Some(bot.pubkey()),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        maker_token_account_a: alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&bot],
+        &bot.pubkey(),
+    );
+    assert!(result.is_err(), "A cleared refund authority should no longer be able to refund");
+}
+
+#[test]
+fn test_registry_tracks_offers_and_settled_volume() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (first_offer_account, first_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let (second_offer_account, second_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        2 * TOKEN_A,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    let registry = get_registry(&test_environment);
+    assert_eq!(registry.total_offers_created, 2);
+    assert_eq!(registry.total_offers_open, 2);
+    assert_eq!(registry.total_volume_settled, 0);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        first_offer_account,
+        first_vault,
+    ).unwrap();
+    execute_refund_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        second_offer_account,
+        second_vault,
+    ).unwrap();
+
+    // Refunds close the offer but don't settle anything
+    let registry = get_registry(&test_environment);
+    assert_eq!(registry.total_offers_created, 2);
+    assert_eq!(registry.total_offers_open, 0);
+    assert_eq!(registry.total_volume_settled, 2 * TOKEN_B as u128);
+}
+
+#[test]
+fn test_maker_index_lists_open_offers() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let first_offer_id = generate_offer_id();
+    let (first_offer_account, first_vault) = execute_make_offer(
+        &mut test_environment,
+        first_offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let second_offer_id = generate_offer_id();
+    let (second_offer_account, second_vault) = execute_make_offer(
+        &mut test_environment,
+        second_offer_id,
+        &alice,
+        alice_token_account_a,
+        2 * TOKEN_A,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    let maker_index = get_maker_index(&test_environment, &alice.pubkey());
+    assert_eq!(maker_index.maker, alice.pubkey());
+    assert_eq!(maker_index.offer_ids, vec![first_offer_id, second_offer_id]);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        first_offer_account,
+        first_vault,
+    ).unwrap();
+
+    let maker_index = get_maker_index(&test_environment, &alice.pubkey());
+    assert_eq!(maker_index.offer_ids, vec![second_offer_id]);
+
+    execute_refund_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        second_offer_account,
+        second_vault,
+    ).unwrap();
+
+    let maker_index = get_maker_index(&test_environment, &alice.pubkey());
+    assert!(maker_index.offer_ids.is_empty(), "Alice should have no open offers left");
+}
+
+#[test]
+fn test_maker_stats_track_filled_and_refunded_offers() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (first_offer_account, first_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let (second_offer_account, second_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        2 * TOKEN_A,
+        1 * TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        first_offer_account,
+        first_vault,
+    ).unwrap();
+    execute_refund_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        second_offer_account,
+        second_vault,
+    ).unwrap();
+
+    let maker_stats = get_maker_stats(&test_environment, &alice.pubkey());
+    assert_eq!(maker_stats.maker, alice.pubkey());
+    assert_eq!(maker_stats.offers_made, 2);
+    assert_eq!(maker_stats.offers_filled, 1);
+    assert_eq!(maker_stats.offers_refunded, 1);
+    assert_eq!(maker_stats.token_a_sold_volume, 3 * TOKEN_A as u128);
+    assert_eq!(maker_stats.token_b_received_volume, 2 * TOKEN_B as u128);
+}
+
+#[test]
+fn test_arbiter_releases_arbitrated_offer() {
+    let mut test_environment = setup_escrow_test();
+    let (arbiter, _arbiter_token_account_a, _arbiter_token_account_b) = create_user(&mut test_environment, 0, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            arbiter: Some(arbiter.pubkey()),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    // Arbitrated offers can't be taken outright
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(result.is_err(), "Arbitrated offers should only be taken with take_offer_arbitrated");
+
+    execute_take_offer_arbitrated(&mut test_environment, offer_account, 3 * TOKEN_A, 2 * TOKEN_B).unwrap();
+    let (pending_settlement, pending_vault) = get_pending_settlement_addresses(&test_environment, &offer_account);
+    assert_token_balance(
+        &test_environment.litesvm,
+        &pending_vault,
+        2 * TOKEN_B,
+        "The pending vault should hold the 2 token B Bob paid",
+    );
+    assert!(get_offer(&test_environment, &offer_account).arbitration_pending());
+
+    // Only the arbiter can release the swap
+    let result = execute_release(&mut test_environment, &alice, offer_account, vault);
+    assert!(result.is_err(), "Only the arbiter should be able to release the swap");
+
+    execute_release(&mut test_environment, &arbiter, offer_account, vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_a,
+        3 * TOKEN_A,
+        "Bob should receive the 3 token A offered",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_b,
+        2 * TOKEN_B,
+        "Alice should receive the token B Bob paid",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+    check_account_is_closed(&test_environment.litesvm, &pending_settlement, "Pending settlement should be closed");
+    check_account_is_closed(&test_environment.litesvm, &pending_vault, "Pending vault should be closed");
+}
+
+#[test]
+fn test_arbiter_revert_returns_token_b_and_reopens_offer() {
+    let mut test_environment = setup_escrow_test();
+    let (arbiter, _arbiter_token_account_a, _arbiter_token_account_b) = create_user(&mut test_environment, 0, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            arbiter: Some(arbiter.pubkey()),
+            ..Default::default()
+        },
+    ).unwrap();
+
+    execute_take_offer_arbitrated(&mut test_environment, offer_account, 3 * TOKEN_A, 2 * TOKEN_B).unwrap();
+
+    // Alice can't pull her tokens back while Bob's payment is waiting on the arbiter
+    let result = execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault);
+    assert!(result.is_err(), "Offers waiting on their arbiter should not be refundable");
+
+    execute_revert(&mut test_environment, &arbiter, offer_account).unwrap();
+
+    let (pending_settlement, pending_vault) = get_pending_settlement_addresses(&test_environment, &offer_account);
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        5 * TOKEN_B,
+        "Bob should get all his token B back",
+    );
+    check_account_is_closed(&test_environment.litesvm, &pending_settlement, "Pending settlement should be closed");
+    check_account_is_closed(&test_environment.litesvm, &pending_vault, "Pending vault should be closed");
+    assert!(!get_offer(&test_environment, &offer_account).arbitration_pending());
+
+    // The offer is open again, so Alice can now refund it
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_migrate_offer_upgrades_version_zero_offers() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).version, OFFER_VERSION);
+
+    // Offers already on the current layout don't need migrating
+    let bob = test_environment.bob.insecure_clone();
+    let result = execute_migrate_offer(&mut test_environment, &bob, offer_account);
+    assert!(result.is_err(), "Current offers should not be migrated again");
+
+    let metadata_uri = "https://example.com/offer.json";
+    execute_attach_metadata(&mut test_environment, &alice, offer_account, metadata_uri).unwrap();
+    rewrite_offer_as_version_zero(&mut test_environment, &offer_account);
+
+    // Anyone can migrate an offer, not just the maker
+    test_environment.litesvm.expire_blockhash();
+    execute_migrate_offer(&mut test_environment, &bob, offer_account).unwrap();
+
+    let data = test_environment.litesvm.get_account(&offer_account).unwrap().data;
+    assert_eq!(data.len(), Offer::METADATA_URI_OFFSET + metadata_uri.len());
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.version, OFFER_VERSION);
+    assert_eq!(offer.maker, alice.pubkey());
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.rent_payer, alice.pubkey());
+    assert_eq!((offer.price_numerator, offer.price_denominator), (2 * TOKEN_B, 3 * TOKEN_A));
+    assert_eq!(get_offer_metadata_uri(&test_environment, &offer_account), metadata_uri);
+
+    // The migrated offer works like any other
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_refund_legacy_offer_returns_the_tokens_of_an_offer_at_the_old_address() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let offer_id = get_offer(&test_environment, &offer_account).id;
+
+    // Legacy offers were written with an older layout too, so they're migrated first
+    let (legacy_offer_account, legacy_vault) = rewrite_offer_as_legacy(&mut test_environment, &offer_account, &vault);
+    rewrite_offer_as_version_zero(&mut test_environment, &legacy_offer_account);
+    execute_migrate_offer(&mut test_environment, &bob, legacy_offer_account).unwrap();
+
+    // refund_offer only looks for offers at their current address
+    let result = execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, legacy_offer_account, legacy_vault);
+    assert!(result.is_err(), "Legacy offers should not be refundable with refund_offer");
+
+    // Only the maker can refund a legacy offer
+    let result = execute_refund_legacy_offer(&mut test_environment, &bob, bob_token_account_a, legacy_offer_account, legacy_vault);
+    assert!(result.is_err(), "Only the maker should be able to refund a legacy offer");
+
+    execute_refund_legacy_offer(&mut test_environment, &alice, alice_token_account_a, legacy_offer_account, legacy_vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should get her 3 token A back",
+    );
+    check_account_is_closed(&test_environment.litesvm, &legacy_offer_account, "Legacy offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &legacy_vault, "Legacy vault should be closed");
+    assert!(!get_maker_index(&test_environment, &alice.pubkey()).offer_ids.contains(&offer_id));
+    assert_eq!(get_maker_stats(&test_environment, &alice.pubkey()).offers_refunded, 1);
+}
+
+#[test]
+fn test_refund_legacy_offer_returns_the_tokens_of_an_offer_with_an_offer_owned_vault() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Offers made before vault authorities are at their current address, but their vault is owned by the offer
+    let offer_owned_vault = rewrite_offer_as_offer_owned(&mut test_environment, &offer_account, &vault);
+
+    // refund_offer only signs with the offer's vault authority
+    let result = execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, offer_owned_vault);
+    assert!(result.is_err(), "Offers with an offer-owned vault should not be refundable with refund_offer");
+
+    execute_refund_legacy_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, offer_owned_vault).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should get her 3 token A back",
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &offer_owned_vault, "Offer-owned vault should be closed");
+}
+
+#[test]
+fn test_refund_legacy_offer_revokes_a_delegated_offers_approval() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let offer_account = execute_make_offer_delegated(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [0; 8],
+    )
+    .unwrap();
+
+    // Delegated offers made before vault authorities approved the offer itself
+    rewrite_offer_as_offer_owned(&mut test_environment, &offer_account, &alice_token_account_a);
+    assert_eq!(
+        get_token_account(&test_environment, &alice_token_account_a).delegate,
+        solana_program::program_option::COption::Some(offer_account)
+    );
+
+    execute_refund_legacy_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, alice_token_account_a).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should still hold all her token A",
+    );
+    assert_eq!(
+        get_token_account(&test_environment, &alice_token_account_a).delegate,
+        solana_program::program_option::COption::None
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed");
+}
+
+#[test]
+fn test_sponsor_pays_and_gets_back_offer_rent() {
+    let mut test_environment = setup_escrow_test();
+
+    // A frontend pays the rent so Alice only has to sign for her tokens
+    let (sponsor, _sponsor_token_account_a, _sponsor_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&test_environment.alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        test_environment.token_mint_a.pubkey(),
+        test_environment.token_mint_b.pubkey(),
+        alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.payer = Some(sponsor.pubkey());
+
+    let alice_lamports_before = test_environment.litesvm.get_balance(&alice.pubkey()).unwrap();
+    let make_offer_instruction = build_make_offer_instruction(offer_id, 3 * TOKEN_A, 2 * TOKEN_B, make_offer_accounts);
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&sponsor, &alice],
+        &sponsor.pubkey(),
+    ).unwrap();
+
+    assert_eq!(
+        test_environment.litesvm.get_balance(&alice.pubkey()).unwrap(),
+        alice_lamports_before,
+        "Alice should not pay any rent or fees"
+    );
+    assert_eq!(get_offer(&test_environment, &offer_account).rent_payer, sponsor.pubkey());
+
+    // The rent can't be sent to the maker instead of the sponsor
+    let refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        maker_token_account_a: alice_token_account_a,
+        offer_account,
+        vault,
+        allowlist: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    );
+    assert!(result.is_err(), "Offer rent should only go back to whoever paid it");
+
+    let offer_rent = test_environment.litesvm.get_balance(&offer_account).unwrap();
+    let vault_rent = test_environment.litesvm.get_balance(&vault).unwrap();
+    let sponsor_lamports_before = test_environment.litesvm.get_balance(&sponsor.pubkey()).unwrap();
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+
+    assert_eq!(
+        test_environment.litesvm.get_balance(&sponsor.pubkey()).unwrap(),
+        sponsor_lamports_before + offer_rent + vault_rent,
+        "The sponsor should get the offer and vault rent back"
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_top_up_offer_adds_to_vault_and_offered_amount() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    execute_top_up_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault, 2 * TOKEN_A)
+        .unwrap();
+
+    assert_token_balance(&test_environment.litesvm, &vault, 5 * TOKEN_A, "Vault should hold the topped up amount");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        5 * TOKEN_A,
+        "Alice should have sent the extra token A",
+    );
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 5 * TOKEN_A);
+    assert_eq!(offer.token_b_wanted_amount, 2 * TOKEN_B);
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let result = execute_top_up_offer(&mut test_environment, &bob, bob_token_account_a, offer_account, vault, TOKEN_A);
+    assert!(result.is_err(), "Only the maker can top up an offer");
+
+    let result = execute_top_up_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault, 0);
+    assert!(result.is_err(), "Topping up nothing should fail");
+
+    // The taker receives everything in the vault
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 5 * TOKEN_A, "Bob should receive the topped up amount");
+}
+
+#[test]
+fn test_reduce_offer_withdraws_part_of_vault_at_same_price() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        4 * TOKEN_B,
+        MakeOfferOptions {
+            min_fill_amount: TOKEN_A,
+            ..Default::default()
+        },
+    ).unwrap();
+
+    execute_reduce_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault, TOKEN_A).unwrap();
+
+    assert_token_balance(&test_environment.litesvm, &vault, 3 * TOKEN_A, "Vault should hold what's left");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        7 * TOKEN_A,
+        "Alice should get the withdrawn token A back",
+    );
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.token_b_wanted_amount, 3 * TOKEN_B, "The price should stay the same");
+
+    let result =
+        execute_reduce_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault, 5 * TOKEN_A / 2);
+    assert!(result.is_err(), "Reducing below the minimum fill should fail");
+
+    let result =
+        execute_reduce_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault, 3 * TOKEN_A);
+    assert!(result.is_err(), "Reducing can't empty the vault");
+
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let result = execute_reduce_offer(&mut test_environment, &bob, bob_token_account_a, offer_account, vault, TOKEN_A);
+    assert!(result.is_err(), "Only the maker can reduce an offer");
+}
+
+#[test]
+fn test_mint_policy_refuses_disallowed_mints() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    // The wanted mint can be frozen, which the default policy allows
+    let freeze_authority = solana_pubkey::Pubkey::new_unique();
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+    set_mint_freeze_authority(&mut test_environment, &token_mint_b, freeze_authority);
+    execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    ).unwrap();
+
+    let bob = test_environment.bob.insecure_clone();
+    let strict_policy = MintPolicy {
+        allow_freeze_authority: false,
+        ..MintPolicy::default()
+    };
+    let result = execute_set_mint_policy(&mut test_environment, strict_policy, &bob);
+    assert!(result.is_err(), "Only the config authority can set the mint policy");
+
+    execute_set_mint_policy(&mut test_environment, strict_policy, &authority).unwrap();
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    );
+    assert!(result.is_err(), "Offers for a freezable mint should be refused");
+
+    // The test mints still have their mint authority
+    let no_mintable_policy = MintPolicy {
+        allow_mint_authority: false,
+        ..MintPolicy::default()
+    };
+    execute_set_mint_policy(&mut test_environment, no_mintable_policy, &authority).unwrap();
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    );
+    assert!(result.is_err(), "Offers for a mint that can still be minted should be refused");
+
+    execute_set_mint_policy(&mut test_environment, MintPolicy::default(), &authority).unwrap();
+    execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    ).unwrap();
+}
+
+#[test]
+fn test_take_offer_creates_maker_token_account_b() {
+    let mut test_environment = setup_escrow_test();
+
+    // Carol only has a token A account, she's never held token B
+    let carol = solana_keypair::Keypair::new();
+    test_environment.litesvm.airdrop(&carol.pubkey(), 1_000_000_000).unwrap();
+    let carol_token_account_a = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &carol,
+        &test_environment.token_mint_a.pubkey(),
+        &test_environment._mint_authority,
+    ).unwrap();
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &test_environment.token_mint_a.pubkey(),
+        &carol_token_account_a,
+        3 * TOKEN_A,
+        &test_environment._mint_authority,
+    ).unwrap();
+    let carol_token_account_b = spl_associated_token_account::get_associated_token_address(
+        &carol.pubkey(),
+        &test_environment.token_mint_b.pubkey(),
+    );
+    assert!(test_environment.litesvm.get_account(&carol_token_account_b).is_none());
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &carol,
+        carol_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Only the maker's real associated token account can be paid
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &carol,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(result.is_err(), "Token B should only go to the maker's associated token account");
+
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &carol,
+        bob_token_account_a,
+        bob_token_account_b,
+        carol_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &carol_token_account_b,
+        2 * TOKEN_B,
+        "Carol's token B account should be created and paid",
+    );
+}
+
+#[test]
+fn test_take_offer_creates_taker_token_account_a() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // Dave has token B but has never held token A
+    let dave = solana_keypair::Keypair::new();
+    test_environment.litesvm.airdrop(&dave.pubkey(), 1_000_000_000).unwrap();
+    let dave_token_account_b = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &dave,
+        &test_environment.token_mint_b.pubkey(),
+        &test_environment._mint_authority,
+    ).unwrap();
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &test_environment.token_mint_b.pubkey(),
+        &dave_token_account_b,
+        2 * TOKEN_B,
+        &test_environment._mint_authority,
+    ).unwrap();
+    let dave_token_account_a = spl_associated_token_account::get_associated_token_address(
+        &dave.pubkey(),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    assert!(test_environment.litesvm.get_account(&dave_token_account_a).is_none());
+
+    // Dave takes the offer in a single transaction, without creating his token A account first
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &dave,
+        &alice,
+        dave_token_account_a,
+        dave_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &dave_token_account_a,
+        3 * TOKEN_A,
+        "Dave's token A account should be created and hold the offered tokens",
+    );
+}
+
+#[test]
+fn test_failures_report_specific_error_codes() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        11 * TOKEN_A,
+        2 * TOKEN_B,
+    );
+    assert_escrow_error(result, ErrorCode::InsufficientMakerBalance);
+
+    let result = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            min_fill_amount: 4 * TOKEN_A,
+            ..MakeOfferOptions::default()
+        },
+    );
+    assert_escrow_error(result, ErrorCode::InvalidMinFillAmount);
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let result = execute_update_offer(&mut test_environment, &bob, offer_account, TOKEN_B, None);
+    assert_escrow_error(result, ErrorCode::InvalidMaker);
+
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        bob_token_account_a,
+    );
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintTokenOwner);
+
+    let result = execute_take_offer(
+        &mut test_environment,
+        &alice,
+        &alice,
+        alice_token_account_a,
+        alice_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert_escrow_error(result, ErrorCode::SelfTradeNotAllowed);
+}
+
+#[test]
+fn test_mul_div_rounds_explicitly_and_refuses_overflow() {
+    // 10 * 1 / 3 doesn't come out exact, so the rounding direction decides the result
+    assert_eq!(mul_div(10, 1, 3, Rounding::Down).unwrap(), 3);
+    assert_eq!(mul_div(10, 1, 3, Rounding::Up).unwrap(), 4);
+    assert_eq!(mul_div(9, 1, 3, Rounding::Up).unwrap(), 3);
+
+    // The product is worked out in u128, so it doesn't overflow on the way
+    assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX, Rounding::Down).unwrap(), u64::MAX);
+
+    // Results that don't fit back in a u64 are errors rather than being truncated
+    assert!(mul_div(u64::MAX, 2, 1, Rounding::Down).is_err());
+    assert!(mul_div(1, 1, 0, Rounding::Up).is_err());
+}
+
+// Integrators compose take_offer with swaps and other instructions in one transaction,
+// so it needs to leave most of the default 200,000 compute units per instruction free
+const TAKE_OFFER_COMPUTE_UNIT_BUDGET: u64 = 100_000;
+
+#[test]
+fn test_take_offer_compute_units_stay_within_budget() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let offer = get_offer(&test_environment, &offer_account);
+    let take_offer_instruction = build_take_offer_instruction(
+        offer.token_a_offered_amount,
+        offer.token_b_wanted_amount,
+        TakeOfferAccounts {
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            taker: bob.pubkey(),
+            maker: alice.pubkey(),
+            rent_payer: None,
+            token_mint_a: test_environment.token_mint_a.pubkey(),
+            token_mint_b: test_environment.token_mint_b.pubkey(),
+            taker_token_account_a: test_environment.bob_token_account_a,
+            taker_token_account_b: test_environment.bob_token_account_b,
+            maker_token_account_b: test_environment.alice_token_account_b,
+            offer_account,
+            vault,
+            allowlist: None,
+            referrer_token_account: None,
+            token_program_b: None,
+        },
+    );
+
+    let compute_units = send_transaction_and_get_compute_units(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert!(
+        compute_units <= TAKE_OFFER_COMPUTE_UNIT_BUDGET,
+        "take_offer used {compute_units} compute units, over its budget of {TAKE_OFFER_COMPUTE_UNIT_BUDGET}"
+    );
+}
+
+#[test]
+fn test_offer_with_token_2022_mints_succeeds() {
+    let mut test_environment = setup_escrow_test_with_token_2022(
+        0,
+        &Token2022Extensions::default(),
+        &Token2022Extensions::default(),
+    );
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_eq!(get_token_balance(&test_environment, &bob_token_account_a), 3 * TOKEN_A);
+    assert_eq!(get_token_balance(&test_environment, &alice_token_account_b), 2 * TOKEN_B);
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+}
+
+#[test]
+fn test_take_offer_settles_what_token_2022_transfer_fees_leave() {
+    // A 1% transfer fee on both sides
+    let transfer_fee = Token2022Extensions {
+        transfer_fee: Some((100, u64::MAX)),
+        ..Default::default()
+    };
+    let mut test_environment = setup_escrow_test_with_token_2022(0, &transfer_fee, &transfer_fee);
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    let bob_token_a_before = get_token_balance(&test_environment, &bob_token_account_a);
+    let alice_token_b_before = get_token_balance(&test_environment, &alice_token_account_b);
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    )
+    .unwrap();
+
+    // The deposit into the vault and the withdrawal to Bob each kept back 1%, and nothing else went missing
+    assert_eq!(
+        get_token_balance(&test_environment, &bob_token_account_a) - bob_token_a_before,
+        3 * TOKEN_A * 99 / 100 * 99 / 100
+    );
+    // Bob paid token b's fee on top, so Alice got everything she asked for
+    assert_eq!(
+        get_token_balance(&test_environment, &alice_token_account_b) - alice_token_b_before,
+        2 * TOKEN_B
+    );
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+}
+
+#[test]
+fn test_offer_between_a_classic_mint_and_a_token_2022_mint() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let mint_authority = test_environment._mint_authority.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    // Alice offers her classic token A for a Token-2022 token B
+    let token_mint_b = create_token_2022_mint(
+        &mut test_environment.litesvm,
+        &mint_authority,
+        9,
+        &Token2022Extensions::default(),
+    )
+    .pubkey();
+    let bob_token_account_b =
+        create_token_2022_associated_token_account(&mut test_environment.litesvm, &bob.pubkey(), &token_mint_b, &bob);
+    mint_token_2022_to_account(
+        &mut test_environment.litesvm,
+        &token_mint_b,
+        &bob_token_account_b,
+        5 * TOKEN_B,
+        &mint_authority,
+    );
+    let alice_token_account_b = spl_associated_token_account::get_associated_token_address_with_program_id(
+        &alice.pubkey(),
+        &token_mint_b,
+        &spl_token_2022::ID,
+    );
+
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &token_mint_a,
+    );
+    let make_offer_accounts = |token_program_b| MakeOfferAccounts {
+        token_program_b,
+        ..build_make_offer_accounts(
+            alice.pubkey(),
+            token_mint_a,
+            token_mint_b,
+            alice_token_account_a,
+            offer_account,
+            vault,
+        )
+    };
+
+    // Token B's program has to be passed, and has to be the one that owns it
+    for token_program_b in [None, Some(spl_token::ID)] {
+        let make_offer_instruction =
+            build_make_offer_instruction(offer_id, 3 * TOKEN_A, 2 * TOKEN_B, make_offer_accounts(token_program_b));
+        let result = send_transaction_from_instructions(
+            &mut test_environment.litesvm,
+            vec![make_offer_instruction],
+            &[&alice],
+            &alice.pubkey(),
+        );
+        assert_escrow_error(result, ErrorCode::InvalidTokenProgram);
+    }
+
+    let make_offer_instruction = build_make_offer_instruction(
+        offer_id,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        make_offer_accounts(Some(spl_token_2022::ID)),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    )
+    .unwrap();
+
+    let take_offer_accounts =
+        |test_environment: &crate::escrow_test_helpers::EscrowTestEnvironment, token_program_b| TakeOfferAccounts {
+            token_mint_b,
+            taker_token_account_b: bob_token_account_b,
+            maker_token_account_b: alice_token_account_b,
+            token_program_b,
+            ..build_take_offer_accounts_for_bob(test_environment, offer_account, vault)
+        };
+    for token_program_b in [None, Some(spl_token::ID)] {
+        let take_offer_instruction = build_take_offer_instruction(
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            take_offer_accounts(&test_environment, token_program_b),
+        );
+        let result = send_transaction_from_instructions(
+            &mut test_environment.litesvm,
+            vec![take_offer_instruction],
+            &[&bob],
+            &bob.pubkey(),
+        );
+        assert_escrow_error(result, ErrorCode::InvalidTokenProgram);
+    }
+
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        take_offer_accounts(&test_environment, Some(spl_token_2022::ID)),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+
+    // Each side moved with its own token program, and Alice's Token-2022 account was created for her
+    assert_eq!(get_token_balance(&test_environment, &test_environment.bob_token_account_a), 3 * TOKEN_A);
+    assert_eq!(get_token_balance(&test_environment, &bob_token_account_b), 3 * TOKEN_B);
+    assert_eq!(get_token_balance(&test_environment, &alice_token_account_b), 2 * TOKEN_B);
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+}
+
+#[test]
+fn test_make_offer_with_frozen_default_account_state_fails() {
+    let frozen_by_default = Token2022Extensions {
+        default_account_state_frozen: true,
+        ..Default::default()
+    };
+    let mut test_environment =
+        setup_escrow_test_with_token_2022(0, &frozen_by_default, &Token2022Extensions::default());
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    // The vault is created frozen, and only the mint's freeze authority can thaw it
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    );
+    assert!(result.is_err(), "Offers can't be made with a mint whose new accounts are frozen");
+}
+
+#[test]
+fn test_expired_offer_cannot_be_taken() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+    let expires_at = test_environment.now() + 1_000;
+    execute_update_offer(&mut test_environment, &alice, offer_account, 2 * TOKEN_B, Some(expires_at)).unwrap();
+
+    test_environment.warp_forward(1_001);
+    assert!(test_environment.now() > expires_at);
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert_escrow_error(result, ErrorCode::OfferExpired);
+}
+
+// Base units in one whole token at 6 decimals, like USDC
+const SIX_DECIMAL_TOKEN: u64 = 1_000_000;
+
+#[test]
+fn test_take_offer_between_6_and_0_decimal_mints() {
+    let mut test_environment = setup_escrow_test_with_decimals(0, 6, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    // 3 whole token A for 2 whole token B, which can't be split
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+    ).unwrap();
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_eq!(get_token_balance(&test_environment, &bob_token_account_a), 3 * SIX_DECIMAL_TOKEN);
+    assert_eq!(get_token_balance(&test_environment, &alice_token_account_b), 2);
+    assert_eq!(get_token_balance(&test_environment, &bob_token_account_b), 5 * TOKEN_B - 2);
+}
+
+#[test]
+fn test_partial_fills_between_6_and_0_decimal_mints_round_up_for_the_maker() {
+    let mut test_environment = setup_escrow_test_with_decimals(0, 6, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+    ).unwrap();
+
+    // A third of the offer is worth two thirds of a token B, which can't be paid, so Bob pays a whole one
+    execute_take_offer_exact_out(&mut test_environment, SIX_DECIMAL_TOKEN, offer_account, vault).unwrap();
+    assert_eq!(get_token_balance(&test_environment, &test_environment.alice_token_account_b), 1);
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 2 * SIX_DECIMAL_TOKEN);
+    // The rest is worth one and a third token B, so it's offered for 2
+    assert_eq!(offer.token_b_wanted_amount, 2);
+    assert_eq!((offer.price_numerator, offer.price_denominator), (2, 3 * SIX_DECIMAL_TOKEN));
+
+    execute_take_offer_exact_out(&mut test_environment, 2 * SIX_DECIMAL_TOKEN, offer_account, vault).unwrap();
+    assert_eq!(get_token_balance(&test_environment, &test_environment.bob_token_account_a), 3 * SIX_DECIMAL_TOKEN);
+    assert_eq!(get_token_balance(&test_environment, &test_environment.alice_token_account_b), 3);
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+}
+
+#[test]
+fn test_partial_fills_between_6_and_9_decimal_mints() {
+    let mut test_environment = setup_escrow_test_with_decimals(0, 6, 9);
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * SIX_DECIMAL_TOKEN,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    // A third of 2 token B is 0.666666666 and two thirds of a base unit, which rounds up
+    execute_take_offer_exact_out(&mut test_environment, SIX_DECIMAL_TOKEN, offer_account, vault).unwrap();
+    assert_eq!(get_token_balance(&test_environment, &test_environment.alice_token_account_b), 666_666_667);
+    assert_eq!(get_offer(&test_environment, &offer_account).token_b_wanted_amount, 1_333_333_334);
+
+    // Taking the smallest amount of token A still pays the maker at least their price
+    execute_take_offer_exact_out(&mut test_environment, 1, offer_account, vault).unwrap();
+    assert_eq!(get_token_balance(&test_environment, &test_environment.alice_token_account_b), 666_666_667 + 667);
+}
+
+#[test]
+fn test_protocol_fee_between_6_and_0_decimal_mints() {
+    let mut test_environment = setup_escrow_test_with_decimals(100, 6, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+    ).unwrap();
+
+    // The 1% fee comes out of the vault on top of what Bob takes, and Alice is paid for both
+    execute_take_offer_exact_out(&mut test_environment, SIX_DECIMAL_TOKEN, offer_account, vault).unwrap();
+    assert_eq!(get_token_balance(&test_environment, &test_environment.bob_token_account_a), SIX_DECIMAL_TOKEN);
+    assert_eq!(
+        get_token_balance(&test_environment, &get_fee_vault_address(&test_environment.token_mint_a.pubkey())),
+        SIX_DECIMAL_TOKEN / 100
+    );
+    assert_eq!(get_token_balance(&test_environment, &test_environment.alice_token_account_b), 1);
+    assert_eq!(
+        get_offer(&test_environment, &offer_account).token_a_offered_amount,
+        3 * SIX_DECIMAL_TOKEN - SIX_DECIMAL_TOKEN - SIX_DECIMAL_TOKEN / 100
+    );
+}
+
+#[test]
+fn test_mints_with_other_decimals_cannot_stand_in_for_the_offers_mints() {
+    let mut test_environment = setup_escrow_test_with_decimals(0, 6, 0);
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    // Same authority, but 9 decimals rather than token A's 6, so the same base units are worth far less
+    let lookalike_mint = create_token_mint(&mut test_environment.litesvm, &test_environment._mint_authority, 9)
+        .unwrap()
+        .pubkey();
+
+    // Alice can't escrow her token A as if it were the lookalike
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let make_offer_instruction = build_make_offer_instruction(
+        offer_id,
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+        build_make_offer_accounts(
+            alice.pubkey(),
+            lookalike_mint,
+            test_environment.token_mint_b.pubkey(),
+            alice_token_account_a,
+            offer_account,
+            spl_associated_token_account::get_associated_token_address(
+                &get_vault_authority_address(&offer_account),
+                &lookalike_mint,
+            ),
+        ),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    );
+    assert!(result.is_err(), "make_offer should refuse a mint that isn't the maker's token account's mint");
+    assert_eq!(get_token_balance(&test_environment, &alice_token_account_a), 10 * TOKEN_A);
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+    )
+    .unwrap();
+
+    // Bob can't take the offer naming the lookalike as token A
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * SIX_DECIMAL_TOKEN,
+        2,
+        TakeOfferAccounts {
+            token_mint_a: lookalike_mint,
+            taker_token_account_a: spl_associated_token_account::get_associated_token_address(
+                &bob.pubkey(),
+                &lookalike_mint,
+            ),
+            ..build_take_offer_accounts_for_bob(&test_environment, offer_account, vault)
+        },
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::WrongTokenMint);
+    assert_eq!(get_token_balance(&test_environment, &vault), 3 * SIX_DECIMAL_TOKEN);
+}
+
+#[test]
+fn test_take_offer_returns_rent_to_the_rent_payer() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let fee_vault = get_fee_vault_address(&test_environment.token_mint_a.pubkey());
+    let offer_rent = get_rent(&test_environment, Offer::METADATA_URI_OFFSET);
+    let token_account_rent = get_rent(&test_environment, spl_token::state::Account::LEN);
+    let before = record_lamport_balances(
+        &test_environment,
+        &[alice.pubkey(), bob.pubkey(), offer_account, vault, fee_vault],
+    );
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    // Alice paid for the offer and its vault, so she gets both back
+    // Bob pays for the transaction, and for the fee vault since he's the first to take an offer of token A
+    assert_lamport_changes(
+        &test_environment,
+        &before,
+        &[
+            (alice.pubkey(), offer_rent + token_account_rent),
+            (bob.pubkey(), -token_account_rent - LAMPORTS_PER_SIGNATURE as i64),
+            (offer_account, -offer_rent),
+            (vault, -token_account_rent),
+            (fee_vault, token_account_rent),
+        ],
+    );
+}
+
+#[test]
+fn test_refund_offer_returns_rent_to_the_maker() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let offer_rent = get_rent(&test_environment, Offer::METADATA_URI_OFFSET);
+    let token_account_rent = get_rent(&test_environment, spl_token::state::Account::LEN);
+    let before = record_lamport_balances(&test_environment, &[alice.pubkey(), offer_account, vault]);
+
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+
+    assert_lamport_changes(
+        &test_environment,
+        &before,
+        &[
+            (alice.pubkey(), offer_rent + token_account_rent - LAMPORTS_PER_SIGNATURE as i64),
+            (offer_account, -offer_rent),
+            (vault, -token_account_rent),
+        ],
+    );
+}
+
+#[test]
+fn test_take_offer_only_changes_the_accounts_it_settles() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let fee_vault = get_fee_vault_address(&token_mint_a);
+    let changed_accounts = [
+        alice.pubkey(),
+        bob.pubkey(),
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+        fee_vault,
+        get_registry_address(),
+        get_maker_index_address(&alice.pubkey()),
+        get_maker_stats_address(&alice.pubkey()),
+    ];
+    let unchanged_accounts = [
+        alice_token_account_a,
+        token_mint_a,
+        test_environment.token_mint_b.pubkey(),
+        get_config_address(),
+        get_maker_counter_address(&alice.pubkey()),
+    ];
+    let before = snapshot_accounts(
+        &test_environment,
+        &[&changed_accounts[..], &unchanged_accounts[..]].concat(),
+    );
+
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    ).unwrap();
+
+    assert_state_diff(&test_environment, &before, &changed_accounts);
+}
+
+#[test]
+fn test_second_taker_of_the_same_offer_fails_cleanly() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let (_second_taker, second_take_result) = execute_competing_takes(
+        &mut test_environment,
+        &alice,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert!(second_take_result.is_err(), "Only one taker can take an offer");
+
+    // Alice was paid once, by the first taker
+    assert_eq!(get_token_balance(&test_environment, &alice_token_account_b), 2 * TOKEN_B);
+    assert_eq!(get_token_balance(&test_environment, &test_environment.bob_token_account_a), 3 * TOKEN_A);
+}
+
+fn build_take_offer_accounts_for_bob(
+    test_environment: &crate::escrow_test_helpers::EscrowTestEnvironment,
+    offer_account: solana_pubkey::Pubkey,
+    vault: solana_pubkey::Pubkey,
+) -> TakeOfferAccounts {
+    TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: test_environment.bob.pubkey(),
+        maker: test_environment.alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    }
+}
+
+#[test]
+fn test_take_offer_with_memo_records_it_with_the_settlement() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction_with_memo(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        "Invoice 2024-117".to_string(),
+        Some(anchor_spl::memo::ID),
+        build_take_offer_accounts_for_bob(&test_environment, offer_account, vault),
+    );
+    let logs = send_transaction_and_get_logs(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+
+    assert!(
+        logs.iter().any(|log| log.contains("Invoice 2024-117")),
+        "The memo program should log the memo, got {logs:#?}"
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_take_offer_with_memo_needs_the_memo_program() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction_with_memo(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        "Invoice 2024-117".to_string(),
+        None,
+        build_take_offer_accounts_for_bob(&test_environment, offer_account, vault),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::MemoProgramRequired);
+
+    let too_long_memo_instruction = build_take_offer_instruction_with_memo(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        "x".repeat(crate::constants::MAX_MEMO_LENGTH + 1),
+        Some(anchor_spl::memo::ID),
+        build_take_offer_accounts_for_bob(&test_environment, offer_account, vault),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![too_long_memo_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::MemoTooLong);
+}
+
+#[test]
+fn test_make_offer_assigns_ids_from_the_maker_counter() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    // An offer made with an id Alice chose doesn't use up the counter
+    execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        1 * TOKEN_A,
+        1 * TOKEN_B,
+    )
+    .unwrap();
+    assert_eq!(get_maker_counter(&test_environment, &alice.pubkey()).next_id, 0);
+
+    for expected_offer_id in 0..2 {
+        // The offer's address comes from the id the program is about to assign
+        let offer_account = get_offer_address(&alice.pubkey(), expected_offer_id);
+        let vault = spl_associated_token_account::get_associated_token_address(
+            &get_vault_authority_address(&offer_account),
+            &test_environment.token_mint_a.pubkey(),
+        );
+        let make_offer_instruction = build_make_offer_instruction(
+            AUTO_ASSIGN_OFFER_ID,
+            1 * TOKEN_A,
+            1 * TOKEN_B,
+            build_make_offer_accounts(
+                alice.pubkey(),
+                test_environment.token_mint_a.pubkey(),
+                test_environment.token_mint_b.pubkey(),
+                alice_token_account_a,
+                offer_account,
+                vault,
+            ),
+        );
+
+        let return_data = send_transaction_and_get_return_data(
+            &mut test_environment.litesvm,
+            vec![make_offer_instruction],
+            &[&alice],
+            &alice.pubkey(),
+        );
+        assert_eq!(return_data, expected_offer_id.to_le_bytes().to_vec());
+        assert_eq!(get_offer(&test_environment, &offer_account).id, expected_offer_id);
+        assert!(get_maker_index(&test_environment, &alice.pubkey()).offer_ids.contains(&expected_offer_id));
+    }
+
+    assert_eq!(get_maker_counter(&test_environment, &alice.pubkey()).next_id, 2);
+}
+
+#[test]
+fn test_take_offer_nft_pays_creator_royalties() {
+    let mut test_environment = setup_escrow_test();
+    let (offer_account, vault, nft_mint) = make_nft_offer(&mut test_environment, None);
+
+    // The NFT pays a 5% royalty, split 70/30 between two creators
+    let first_creator = solana_keypair::Keypair::new();
+    let second_creator = solana_keypair::Keypair::new();
+    let nft_metadata = create_nft_metadata_with_royalties(
+        &mut test_environment,
+        &nft_mint,
+        None,
+        500,
+        &[(first_creator.pubkey(), 70), (second_creator.pubkey(), 30)],
+    );
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+    let mut creator_token_accounts = Vec::new();
+    for creator in [&first_creator, &second_creator] {
+        creator_token_accounts.push(
+            create_associated_token_account(
+                &mut test_environment.litesvm,
+                creator,
+                &token_mint_b,
+                &test_environment._mint_authority,
+            )
+            .unwrap(),
+        );
+    }
+
+    let take_offer_instruction = build_take_offer_nft_instruction_with_royalties(
+        None,
+        2 * TOKEN_B,
+        nft_metadata,
+        &creator_token_accounts,
+        build_take_nft_offer_accounts(&test_environment, offer_account, vault, nft_mint),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&test_environment.bob],
+        &test_environment.bob.pubkey(),
+    )
+    .unwrap();
+
+    let royalty_amount = 2 * TOKEN_B * 500 / 10_000;
+    assert_token_balance(
+        &test_environment.litesvm,
+        &creator_token_accounts[0],
+        royalty_amount * 70 / 100,
+        "The first creator should have received 70% of the royalty",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &creator_token_accounts[1],
+        royalty_amount * 30 / 100,
+        "The second creator should have received 30% of the royalty",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B - royalty_amount,
+        "Alice should have received the price less the royalty",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        3 * TOKEN_B,
+        "Bob should have paid 2 token B in total",
+    );
+}
+
+#[test]
+fn test_required_royalties_refuse_nft_takes_without_metadata() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let (offer_account, vault, nft_mint) = make_nft_offer(&mut test_environment, None);
+
+    let result = execute_set_royalties_required(&mut test_environment, true, &bob);
+    assert!(result.is_err(), "Only the config authority can require royalties");
+    execute_set_royalties_required(&mut test_environment, true, &authority).unwrap();
+
+    let take_offer_instruction = build_take_offer_nft_instruction(
+        None,
+        2 * TOKEN_B,
+        build_take_nft_offer_accounts(&test_environment, offer_account, vault, nft_mint),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::RoyaltyMetadataRequired);
+
+    // Once royalties are optional again, the same take goes through
+    execute_set_royalties_required(&mut test_environment, false, &authority).unwrap();
+    test_environment.litesvm.expire_blockhash();
+    let take_offer_instruction = build_take_offer_nft_instruction(
+        None,
+        2 * TOKEN_B,
+        build_take_nft_offer_accounts(&test_environment, offer_account, vault, nft_mint),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_delegated_offer_leaves_tokens_with_the_maker_until_taken() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let offer_account = execute_make_offer_delegated(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [2, 0, 0, 0, 0, 0, 0, 0],
+    )
+    .unwrap();
+
+    // Alice still holds her tokens, the offer's vault authority is only approved to spend them
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should still hold all her token A",
+    );
+    let alice_token_account = get_token_account(&test_environment, &alice_token_account_a);
+    assert_eq!(
+        alice_token_account.delegate,
+        solana_program::program_option::COption::Some(get_vault_authority_address(&offer_account))
+    );
+    assert_eq!(get_offer(&test_environment, &offer_account).tags, [2, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(alice_token_account.delegated_amount, 3 * TOKEN_A);
+    assert!(get_offer(&test_environment, &offer_account).is_delegated());
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_a_before = get_token_balance(&test_environment, &bob_token_account_a);
+    let alice_token_b_before = get_token_balance(&test_environment, &alice_token_account_b);
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        alice_token_account_a,
+    )
+    .unwrap();
+
+    assert_eq!(
+        get_token_balance(&test_environment, &bob_token_account_a) - bob_token_a_before,
+        3 * TOKEN_A,
+        "Bob should have received the 3 token A straight from Alice's account"
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        7 * TOKEN_A,
+        "Alice should have the rest of her token A",
+    );
+    assert_eq!(
+        get_token_balance(&test_environment, &alice_token_account_b) - alice_token_b_before,
+        2 * TOKEN_B,
+        "Alice should have received 2 token B"
+    );
+    // Using up the approval clears the delegate
+    assert_eq!(
+        get_token_account(&test_environment, &alice_token_account_a).delegate,
+        solana_program::program_option::COption::None
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+}
+
+#[test]
+fn test_delegated_offer_cannot_be_taken_once_revoked() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let offer_account = execute_make_offer_delegated(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [0; 8],
+    )
+    .unwrap();
+
+    // Alice keeps custody, so she can revoke the approval without the program
+    let revoke_instruction =
+        spl_token::instruction::revoke(&spl_token::ID, &alice_token_account_a, &alice.pubkey(), &[]).unwrap();
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![revoke_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    )
+    .unwrap();
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        alice_token_account_a,
+    );
+    assert_escrow_error(result, ErrorCode::DelegationRevoked);
+
+    // Refunding just closes the offer, Alice's tokens never left her account
+    execute_refund_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        offer_account,
+        alice_token_account_a,
+    )
+    .unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should still hold all her token A",
+    );
+}
+
+#[test]
+fn test_offer_template_makes_a_new_offer_once_the_last_is_taken() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let (keeper, _, _) = create_user(&mut test_environment, 0, 0);
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    // Enough for two offers of 3 token A
+    let offer_template =
+        execute_create_offer_template(&mut test_environment, &alice, 3 * TOKEN_A, 2 * TOKEN_B, 6 * TOKEN_A, [4, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(
+        get_token_account(&test_environment, &alice_token_account_a).delegate,
+        solana_program::program_option::COption::Some(offer_template)
+    );
+
+    let first_offer = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey()).unwrap();
+    let offer = get_offer(&test_environment, &first_offer);
+    assert_eq!(offer.maker, alice.pubkey());
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.token_b_wanted_amount, 2 * TOKEN_B);
+    assert_eq!(offer.rent_payer, keeper.pubkey());
+    assert_eq!(offer.tags, [4, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(!offer.is_delegated());
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        7 * TOKEN_A,
+        "The template should have moved 3 token A out of Alice's account",
+    );
+    let template = get_offer_template(&test_environment, &offer_template);
+    assert_eq!(template.current_offer, Some(first_offer));
+    assert_eq!(template.offers_spawned, 1);
+
+    // Only one of the template's offers is open at a time
+    let result = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey());
+    assert_escrow_error(result, ErrorCode::TemplateOfferStillOpen);
+
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let first_vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&first_offer),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        first_offer,
+        first_vault,
+    )
+    .unwrap();
+
+    let second_offer = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey()).unwrap();
+    assert_ne!(second_offer, first_offer);
+    assert_eq!(get_offer(&test_environment, &second_offer).token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(get_offer_template(&test_environment, &offer_template).offers_spawned, 2);
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        4 * TOKEN_A,
+        "The template should have moved another 3 token A out of Alice's account",
+    );
+
+    // The approval only covered two offers
+    let second_vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&second_offer),
+        &test_environment.token_mint_a.pubkey(),
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        second_offer,
+        second_vault,
+    )
+    .unwrap();
+    let result = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey());
+    assert_escrow_error(result, ErrorCode::DelegationRevoked);
+}
+
+#[test]
+fn test_closing_an_offer_template_revokes_its_approval() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let (keeper, _, _) = create_user(&mut test_environment, 0, 0);
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let offer_template =
+        execute_create_offer_template(&mut test_environment, &alice, 3 * TOKEN_A, 2 * TOKEN_B, 6 * TOKEN_A, [0; 8]).unwrap();
+    let offer_account = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey()).unwrap();
+
+    execute_close_offer_template(&mut test_environment, &alice).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_template, "Offer template should be closed");
+    assert_eq!(
+        get_token_account(&test_environment, &alice_token_account_a).delegate,
+        solana_program::program_option::COption::None
+    );
+
+    // The offer the template made stays open until it is taken or refunded
+    assert_eq!(get_offer(&test_environment, &offer_account).token_a_offered_amount, 3 * TOKEN_A);
+    let result = execute_spawn_offer_from_template(&mut test_environment, &keeper, &alice.pubkey());
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::AccountNotInitialized);
+}
+
+#[test]
+fn test_installment_offer_releases_token_a_as_each_installment_is_paid() {
+    let mut test_environment = setup_escrow_test();
+
+    // Alice sells 3 token A for 3 token B
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        3 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Takers can only pay in installments once the maker agrees to it
+    let result = execute_take_offer_in_installments(&mut test_environment, offer_account, vault, 2, 100, 3 * TOKEN_A, 3 * TOKEN_B);
+    assert_escrow_error(result, ErrorCode::InstallmentsNotAllowed);
+
+    let result = execute_set_installment_terms(
+        &mut test_environment,
+        &alice,
+        offer_account,
+        Some(InstallmentTerms { max_installments: 1, interval: 100 }),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidInstallmentTerms);
+
+    execute_set_installment_terms(
+        &mut test_environment,
+        &alice,
+        offer_account,
+        Some(InstallmentTerms { max_installments: 3, interval: 100 }),
+    )
+    .unwrap();
+
+    let result = execute_take_offer_in_installments(&mut test_environment, offer_account, vault, 4, 100, 3 * TOKEN_A, 3 * TOKEN_B);
+    assert_escrow_error(result, ErrorCode::InvalidInstallmentCount);
+
+    // The taker agrees to the interval, so the maker can't shorten it under them
+    let result = execute_take_offer_in_installments(&mut test_environment, offer_account, vault, 3, 200, 3 * TOKEN_A, 3 * TOKEN_B);
+    assert_escrow_error(result, ErrorCode::OfferTermsChanged);
+
+    let installment_plan =
+        execute_take_offer_in_installments(&mut test_environment, offer_account, vault, 3, 100, 3 * TOKEN_A, 3 * TOKEN_B)
+            .unwrap();
+
+    // Taking the offer pays the first installment
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_b, 1 * TOKEN_B, "Alice should be paid the first installment");
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 1 * TOKEN_A, "Bob should get a third of the token A");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+    let plan = get_installment_plan(&test_environment, &installment_plan);
+    assert_eq!(plan.installments_paid, 1);
+    assert_eq!(plan.token_b_amount, 3 * TOKEN_B);
+
+    // Bob is paying on time, so Alice can't take the rest back
+    let result = execute_cancel_default(&mut test_environment, &alice, installment_plan);
+    assert_escrow_error(result, ErrorCode::InstallmentNotOverdue);
+
+    let bob = test_environment.bob.insecure_clone();
+    test_environment.warp_forward(50);
+    execute_pay_installment(&mut test_environment, &bob, installment_plan).unwrap();
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_b, 2 * TOKEN_B, "Alice should be paid the second installment");
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 2 * TOKEN_A, "Bob should get two thirds of the token A");
+
+    // Warping also gives the last payment, the same transaction as the second, a fresh blockhash
+    test_environment.warp_forward(50);
+    execute_pay_installment(&mut test_environment, &bob, installment_plan).unwrap();
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_b, 3 * TOKEN_B, "Alice should be paid in full");
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should have all the token A");
+    check_account_is_closed(&test_environment.litesvm, &installment_plan, "Installment plan should be closed");
+
+    let maker_stats = get_maker_stats(&test_environment, &alice.pubkey());
+    assert_eq!(maker_stats.offers_filled, 1);
+    assert_eq!(maker_stats.token_b_received_volume, 3 * TOKEN_B as u128);
+}
+
+#[test]
+fn test_maker_reclaims_unreleased_token_a_when_taker_defaults() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_a_before = get_token_balance(&test_environment, &alice_token_account_a);
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        4 * TOKEN_A,
+        4 * TOKEN_B,
+    )
+    .unwrap();
+    execute_set_installment_terms(
+        &mut test_environment,
+        &alice,
+        offer_account,
+        Some(InstallmentTerms { max_installments: 4, interval: 100 }),
+    )
+    .unwrap();
+
+    let installment_plan =
+        execute_take_offer_in_installments(&mut test_environment, offer_account, vault, 4, 100, 4 * TOKEN_A, 4 * TOKEN_B)
+            .unwrap();
+
+    // The second installment is due 100 seconds after the first, Bob has until then to pay it
+    let start_timestamp = get_installment_plan(&test_environment, &installment_plan).start_timestamp;
+    test_environment.warp_to_timestamp(start_timestamp + 100);
+    let result = execute_cancel_default(&mut test_environment, &alice, installment_plan);
+    assert_escrow_error(result, ErrorCode::InstallmentNotOverdue);
+
+    // Only the maker can cancel the plan
+    let bob = test_environment.bob.insecure_clone();
+    test_environment.warp_to_timestamp(start_timestamp + 101);
+    let result = execute_cancel_default(&mut test_environment, &bob, installment_plan);
+    assert_escrow_error(result, ErrorCode::InvalidMakerAuthority);
+
+    execute_cancel_default(&mut test_environment, &alice, installment_plan).unwrap();
+
+    // Bob keeps the token A he paid for, Alice keeps his payment and gets the rest of her token A back
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        alice_token_a_before - 1 * TOKEN_A,
+        "Alice should get back the token A Bob didn't pay for",
+    );
+    assert_token_balance(&test_environment.litesvm, &test_environment.alice_token_account_b, 1 * TOKEN_B, "Alice should keep the first installment");
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_a, 1 * TOKEN_A, "Bob should keep the token A he paid for");
+    check_account_is_closed(&test_environment.litesvm, &installment_plan, "Installment plan should be closed");
+    assert_eq!(get_maker_stats(&test_environment, &alice.pubkey()).offers_refunded, 1);
+
+    let result = execute_pay_installment(&mut test_environment, &bob, installment_plan);
+    assert!(result.is_err(), "Bob can't pay into a cancelled plan");
+}
+
+#[test]
+fn test_attested_offer_needs_the_attestors_signature() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // The attestor confirms off-chain that the deliverable arrived by signing the offer and who may take it
+    let attestor = solana_keypair::Keypair::new();
+    execute_set_attestor(&mut test_environment, &alice, offer_account, Some(attestor.pubkey())).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).attestor(), Some(attestor.pubkey()));
+
+    let bob = test_environment.bob.insecure_clone();
+    let (token_mint_a, token_mint_b) = (test_environment.token_mint_a.pubkey(), test_environment.token_mint_b.pubkey());
+    let (bob_token_account_a, bob_token_account_b) =
+        (test_environment.bob_token_account_a, test_environment.bob_token_account_b);
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let take_offer_accounts = || TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a,
+        token_mint_b,
+        taker_token_account_a: bob_token_account_a,
+        taker_token_account_b: bob_token_account_b,
+        maker_token_account_b: alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    };
+
+    // Without the instructions sysvar the program can't see the attestor's signature
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::AttestationRequired);
+
+    // A signature from anyone other than the attestor doesn't count
+    let offer = get_offer(&test_environment, &offer_account);
+    let message = build_attestation_message(&offer_account, &offer, &bob.pubkey());
+    let impostor = solana_keypair::Keypair::new();
+    let take_offer_instruction =
+        build_take_offer_instruction_with_attestation(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![build_ed25519_instruction(&impostor, &message), take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidAttestation);
+
+    // Nor does the attestor vouching for someone else
+    let other_taker = solana_pubkey::Pubkey::new_unique();
+    let take_offer_instruction =
+        build_take_offer_instruction_with_attestation(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![
+            build_ed25519_instruction(&attestor, &build_attestation_message(&offer_account, &offer, &other_taker)),
+            take_offer_instruction,
+        ],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidAttestation);
+
+    // Other ways of taking the offer would skip the check, so they're refused
+    let result = execute_take_offer_exact_out(&mut test_environment, 3 * TOKEN_A, offer_account, vault);
+    assert_escrow_error(result, ErrorCode::OfferIsAttested);
+
+    let take_offer_instruction =
+        build_take_offer_instruction_with_attestation(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![build_ed25519_instruction(&attestor, &message), take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should receive the token A");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed");
+}
+
+#[test]
+fn test_offer_with_eth_taker_needs_its_signature_for_the_taker() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // A desk holding only an EVM key lets Bob settle the offer on its behalf
+    let eth_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+    execute_set_eth_taker(&mut test_environment, &alice, offer_account, Some(get_eth_address(&eth_key))).unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).eth_taker(), Some(get_eth_address(&eth_key)));
+
+    let bob = test_environment.bob.insecure_clone();
+    let (token_mint_a, token_mint_b) = (test_environment.token_mint_a.pubkey(), test_environment.token_mint_b.pubkey());
+    let (bob_token_account_a, bob_token_account_b) =
+        (test_environment.bob_token_account_a, test_environment.bob_token_account_b);
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let take_offer_accounts = || TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a,
+        token_mint_b,
+        taker_token_account_a: bob_token_account_a,
+        taker_token_account_b: bob_token_account_b,
+        maker_token_account_b: alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    };
+
+    // Without the instructions sysvar the program can't see the signature
+    let take_offer_instruction = build_take_offer_instruction(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::EthSignatureRequired);
+
+    // A signature letting someone else take the offer doesn't let Bob
+    let other_taker = solana_pubkey::Pubkey::new_unique();
+    let take_offer_instruction =
+        build_take_offer_instruction_with_attestation(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![
+            build_secp256k1_instruction(&eth_key, &[offer_account.as_ref(), other_taker.as_ref()].concat(), 0),
+            take_offer_instruction,
+        ],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidEthSignature);
+
+    // Other ways of taking the offer would skip the check, so they're refused
+    let result = execute_take_offer_exact_out(&mut test_environment, 3 * TOKEN_A, offer_account, vault);
+    assert_escrow_error(result, ErrorCode::OfferHasEthTaker);
+
+    let take_offer_instruction =
+        build_take_offer_instruction_with_attestation(3 * TOKEN_A, 2 * TOKEN_B, take_offer_accounts());
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![
+            build_secp256k1_instruction(&eth_key, &[offer_account.as_ref(), bob.pubkey().as_ref()].concat(), 0),
+            take_offer_instruction,
+        ],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should receive the token A");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed");
+}
+
+#[test]
+fn test_relayer_takes_offer_with_takers_signed_authorization() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // The relayer submits the take and pays for it, Bob only signs the authorization off-chain
+    let (relayer, _relayer_token_account_a, _relayer_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let bob = test_environment.bob.insecure_clone();
+    let (token_mint_a, token_mint_b) = (test_environment.token_mint_a.pubkey(), test_environment.token_mint_b.pubkey());
+    let (bob_token_account_a, bob_token_account_b) =
+        (test_environment.bob_token_account_a, test_environment.bob_token_account_b);
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let take_offer_instruction = |expires_at: i64| {
+        build_take_offer_with_authorization_instruction(
+            relayer.pubkey(),
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            expires_at,
+            TakeOfferAccounts {
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+                taker: bob.pubkey(),
+                maker: alice.pubkey(),
+                rent_payer: None,
+                token_mint_a,
+                token_mint_b,
+                taker_token_account_a: bob_token_account_a,
+                taker_token_account_b: bob_token_account_b,
+                maker_token_account_b: alice_token_account_b,
+                offer_account,
+                vault,
+                allowlist: None,
+                referrer_token_account: None,
+                token_program_b: None,
+            },
+        )
+    };
+    let offer = get_offer(&test_environment, &offer_account);
+    let authorization = |signer: &solana_keypair::Keypair, expires_at: i64| {
+        build_ed25519_instruction(
+            signer,
+            &build_taker_authorization_message(&offer_account, &offer, 3 * TOKEN_A, 2 * TOKEN_B, expires_at),
+        )
+    };
+    let expires_at = test_environment.now() + 60;
+
+    // Bob hasn't approved the taker authority to spend any token b yet
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![authorization(&bob, expires_at - 1), take_offer_instruction(expires_at - 1)],
+        &[&relayer],
+        &relayer.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::TakerDelegationMissing);
+
+    let approve_instruction = spl_token::instruction::approve(
+        &spl_token::ID,
+        &bob_token_account_b,
+        &get_taker_authority_address(&bob.pubkey()),
+        &bob.pubkey(),
+        &[],
+        2 * TOKEN_B,
+    )
+    .unwrap();
+    send_transaction_from_instructions(&mut test_environment.litesvm, vec![approve_instruction], &[&bob], &bob.pubkey())
+        .unwrap();
+
+    // Only Bob can authorize taking the offer with his tokens
+    let impostor = solana_keypair::Keypair::new();
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![authorization(&impostor, expires_at), take_offer_instruction(expires_at)],
+        &[&relayer],
+        &relayer.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidTakerAuthorization);
+
+    // Relayers can't hold on to an authorization past its expiry
+    let expired_at = test_environment.now() - 1;
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![authorization(&bob, expired_at), take_offer_instruction(expired_at)],
+        &[&relayer],
+        &relayer.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::AuthorizationExpired);
+
+    let bob_lamports_before = test_environment.litesvm.get_balance(&bob.pubkey()).unwrap();
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![authorization(&bob, expires_at), take_offer_instruction(expires_at)],
+        &[&relayer],
+        &relayer.pubkey(),
+    )
+    .unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should receive the token A");
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_b, 2 * TOKEN_B, "Alice should receive the token B");
+    assert_eq!(
+        test_environment.litesvm.get_balance(&bob.pubkey()).unwrap(),
+        bob_lamports_before,
+        "The relayer should pay for the take, not Bob"
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed");
+}
+
+#[test]
+fn test_taker_authorization_cannot_be_used_on_a_new_offer_at_the_same_address() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let offer_id = generate_offer_id();
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Bob authorizes taking Alice's offer, and approves his taker authority for it
+    let bob = test_environment.bob.insecure_clone();
+    let expires_at = test_environment.now() + 60;
+    let authorization = build_ed25519_instruction(
+        &bob,
+        &build_taker_authorization_message(
+            &offer_account,
+            &get_offer(&test_environment, &offer_account),
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            expires_at,
+        ),
+    );
+    let approve_instruction = spl_token::instruction::approve(
+        &spl_token::ID,
+        &test_environment.bob_token_account_b,
+        &get_taker_authority_address(&bob.pubkey()),
+        &bob.pubkey(),
+        &[],
+        2 * TOKEN_B,
+    )
+    .unwrap();
+    send_transaction_from_instructions(&mut test_environment.litesvm, vec![approve_instruction], &[&bob], &bob.pubkey())
+        .unwrap();
+
+    // Alice closes the offer and makes a new one at the same address, on the same terms
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    test_environment.warp_forward(1);
+    execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    let (relayer, _relayer_token_account_a, _relayer_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let take_offer_instruction = build_take_offer_with_authorization_instruction(
+        relayer.pubkey(),
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        expires_at,
+        TakeOfferAccounts {
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            taker: bob.pubkey(),
+            maker: alice.pubkey(),
+            rent_payer: None,
+            token_mint_a: test_environment.token_mint_a.pubkey(),
+            token_mint_b: test_environment.token_mint_b.pubkey(),
+            taker_token_account_a: test_environment.bob_token_account_a,
+            taker_token_account_b: test_environment.bob_token_account_b,
+            maker_token_account_b: test_environment.alice_token_account_b,
+            offer_account,
+            vault,
+            allowlist: None,
+            referrer_token_account: None,
+            token_program_b: None,
+        },
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![authorization, take_offer_instruction],
+        &[&relayer],
+        &relayer.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::InvalidTakerAuthorization);
+}
+
+#[test]
+fn test_match_offers_settles_mirrored_offers_and_splits_the_price_improvement() {
+    // A 1% protocol fee on what each maker receives
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+
+    let config_authority = test_environment.config_authority.insecure_clone();
+    let result = execute_set_matcher_share(&mut test_environment, 10_001, &config_authority);
+    assert_escrow_error(result, ErrorCode::InvalidMatcherShare);
+    execute_set_matcher_share(&mut test_environment, 2_500, &config_authority).unwrap();
+
+    // Alice offers 3 token A for 2 token B, Bob offers 3 token B for 2 token A
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (alice_offer, _alice_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+    let (token_mint_a, token_mint_b) = (test_environment.token_mint_a.pubkey(), test_environment.token_mint_b.pubkey());
+    let bob = test_environment.bob.insecure_clone();
+    let bob_offer_id = generate_offer_id();
+    let bob_offer = get_offer_address(&bob.pubkey(), bob_offer_id);
+    let bob_vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&bob_offer),
+        &token_mint_b,
+    );
+    let make_offer_instruction = build_make_offer_instruction(
+        bob_offer_id,
+        3 * TOKEN_B,
+        2 * TOKEN_A,
+        build_make_offer_accounts(
+            bob.pubkey(),
+            token_mint_b,
+            token_mint_a,
+            test_environment.bob_token_account_b,
+            bob_offer,
+            bob_vault,
+        ),
+    );
+    send_transaction_from_instructions(&mut test_environment.litesvm, vec![make_offer_instruction], &[&bob], &bob.pubkey())
+        .unwrap();
+
+    let (matcher, matcher_token_account_a, matcher_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let match_offers_instruction = |first_offer: solana_pubkey::Pubkey| {
+        build_match_offers_instruction(MatchOffersAccounts {
+            matcher: matcher.pubkey(),
+            first_maker: alice.pubkey(),
+            first_offer,
+            second_maker: bob.pubkey(),
+            second_offer: bob_offer,
+            token_mint_a,
+            token_mint_b,
+            matcher_token_account_a,
+            matcher_token_account_b,
+        })
+    };
+
+    // Bob's offer only holds 3 token B, so it can't settle an offer wanting 4
+    let (greedy_offer, _greedy_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+    )
+    .unwrap();
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![match_offers_instruction(greedy_offer)],
+        &[&matcher],
+        &matcher.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::OffersDontMatch);
+
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![match_offers_instruction(alice_offer)],
+        &[&matcher],
+        &matcher.pubkey(),
+    )
+    .unwrap();
+
+    // Each maker gets what they wanted less the protocol fee, and each offer has 1 token left over
+    // The matcher gets a quarter of it, the maker who offered it gets the rest back
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B - 2 * TOKEN_B / 100,
+        "Alice should receive the token B Alice wanted less the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_a,
+        2 * TOKEN_A - 2 * TOKEN_A / 100,
+        "Bob should receive the token A Bob wanted less the protocol fee",
+    );
+    assert_token_balance(&test_environment.litesvm, &get_fee_vault_address(&token_mint_a), 2 * TOKEN_A / 100, "The protocol should collect the fee on the token A Bob received");
+    assert_token_balance(&test_environment.litesvm, &get_fee_vault_address(&token_mint_b), 2 * TOKEN_B / 100, "The protocol should collect the fee on the token B Alice received");
+    assert_token_balance(&test_environment.litesvm, &matcher_token_account_a, TOKEN_A / 4, "The matcher should get a quarter of the token A left over");
+    assert_token_balance(&test_environment.litesvm, &matcher_token_account_b, TOKEN_B / 4, "The matcher should get a quarter of the token B left over");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        4 * TOKEN_A + 3 * TOKEN_A / 4,
+        "Alice should get back the rest of the token A left over",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.bob_token_account_b,
+        2 * TOKEN_B + 3 * TOKEN_B / 4,
+        "Bob should get back the rest of the token B left over",
+    );
+    check_account_is_closed(&test_environment.litesvm, &alice_offer, "Alice's offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &bob_offer, "Bob's offer should be closed");
+}
+
+#[test]
+fn test_accept_quote_settles_the_chosen_quote_and_frees_the_others() {
+    // A 1% protocol fee on the token A the requester receives
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    let now = test_environment.now();
+    execute_request_quote(&mut test_environment, 1, 2 * TOKEN_A, now + 3_600).unwrap();
+    let bob = test_environment.bob.insecure_clone();
+    let quote_request = get_quote_request_address(&bob.pubkey(), 1);
+
+    // Alice quotes 3 token B for the 2 token A Bob asked for, Carol quotes 2
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    execute_submit_quote(&mut test_environment, &alice, alice_token_account_a, quote_request, 3 * TOKEN_B).unwrap();
+    let (carol, carol_token_account_a, carol_token_account_b) = create_user(&mut test_environment, 10 * TOKEN_A, 0);
+    execute_submit_quote(&mut test_environment, &carol, carol_token_account_a, quote_request, 2 * TOKEN_B).unwrap();
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_a, 8 * TOKEN_A, "Alice's quote should escrow the requested token A");
+
+    // Quotes stay firm while Bob is still choosing
+    let result = execute_withdraw_quote(&mut test_environment, &alice, alice_token_account_a, quote_request);
+    assert_escrow_error(result, ErrorCode::QuoteStillFirm);
+
+    execute_accept_quote(&mut test_environment, &carol.pubkey(), quote_request).unwrap();
+
+    let (carol_quote, carol_quote_vault) = get_quote_addresses(&test_environment, &quote_request, &carol.pubkey());
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_a, 2 * TOKEN_A - 2 * TOKEN_A / 100, "Bob should receive the quoted token A, less the protocol fee");
+    assert_token_balance(&test_environment.litesvm, &get_fee_vault_address(&test_environment.token_mint_a.pubkey()), 2 * TOKEN_A / 100, "The fee vault should collect the protocol fee");
+    assert_token_balance(&test_environment.litesvm, &carol_token_account_b, 2 * TOKEN_B, "Carol should receive the token B Carol quoted");
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_b, 3 * TOKEN_B, "Bob should only pay for the accepted quote");
+    check_account_is_closed(&test_environment.litesvm, &quote_request, "The quote request should be closed");
+    check_account_is_closed(&test_environment.litesvm, &carol_quote, "Carol's quote should be closed");
+    check_account_is_closed(&test_environment.litesvm, &carol_quote_vault, "Carol's quote vault should be closed");
+
+    // With the request closed, Alice can take back the quote Bob passed on
+    execute_withdraw_quote(&mut test_environment, &alice, alice_token_account_a, quote_request).unwrap();
+    let (alice_quote, _alice_quote_vault) = get_quote_addresses(&test_environment, &quote_request, &alice.pubkey());
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_a, 10 * TOKEN_A, "Alice should get the quoted token A back");
+    check_account_is_closed(&test_environment.litesvm, &alice_quote, "Alice's quote should be closed");
+}
+
+#[test]
+fn test_quotes_can_be_withdrawn_once_the_quote_request_expires_or_is_closed() {
+    let mut test_environment = setup_escrow_test();
+    let now = test_environment.now();
+    execute_request_quote(&mut test_environment, 1, 2 * TOKEN_A, now + 3_600).unwrap();
+    execute_request_quote(&mut test_environment, 2, 2 * TOKEN_A, now + 3_600).unwrap();
+    let bob = test_environment.bob.insecure_clone();
+    let (expiring_request, closed_request) =
+        (get_quote_request_address(&bob.pubkey(), 1), get_quote_request_address(&bob.pubkey(), 2));
+
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    execute_submit_quote(&mut test_environment, &alice, alice_token_account_a, expiring_request, 3 * TOKEN_B).unwrap();
+    execute_submit_quote(&mut test_environment, &alice, alice_token_account_a, closed_request, 3 * TOKEN_B).unwrap();
+
+    // Bob closes one request without accepting a quote, so Alice can withdraw straight away
+    execute_close_quote_request(&mut test_environment, closed_request).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &closed_request, "The closed quote request should be closed");
+    execute_withdraw_quote(&mut test_environment, &alice, alice_token_account_a, closed_request).unwrap();
+
+    // The other expires, after which no one can quote on it or accept its quotes
+    test_environment.warp_forward(3_600);
+    let (carol, carol_token_account_a, _carol_token_account_b) = create_user(&mut test_environment, 10 * TOKEN_A, 0);
+    let result = execute_submit_quote(&mut test_environment, &carol, carol_token_account_a, expiring_request, 2 * TOKEN_B);
+    assert_escrow_error(result, ErrorCode::QuoteRequestExpired);
+    let result = execute_accept_quote(&mut test_environment, &alice.pubkey(), expiring_request);
+    assert_escrow_error(result, ErrorCode::QuoteRequestExpired);
+
+    execute_withdraw_quote(&mut test_environment, &alice, alice_token_account_a, expiring_request).unwrap();
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_a, 10 * TOKEN_A, "Alice should get both quotes' token A back");
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_b, 5 * TOKEN_B, "Bob shouldn't have paid for either quote");
+}
+
+#[test]
+fn test_make_offer_with_existing_vault_uses_the_tokens_already_in_the_vault() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+
+    // Like a program that funds the vault with its own CPI, Alice creates and funds the offer's vault before making it
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let vault_authority = get_vault_authority_address(&offer_account);
+    let vault = spl_associated_token_account::get_associated_token_address(&vault_authority, &token_mint_a);
+    let fund_vault_instructions = |amount: u64| {
+        vec![
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &alice.pubkey(),
+                &vault_authority,
+                &token_mint_a,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::transfer(&spl_token::ID, &alice_token_account_a, &vault, &alice.pubkey(), &[], amount)
+                .unwrap(),
+        ]
+    };
+
+    // A vault holding less than the offer is for is rejected
+    let mut instructions = fund_vault_instructions(2 * TOKEN_A);
+    instructions.push(build_make_offer_with_existing_vault_instruction(
+        &test_environment,
+        offer_id,
+        &alice.pubkey(),
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [0; 8],
+    ));
+    let result = send_transaction_from_instructions(&mut test_environment.litesvm, instructions, &[&alice], &alice.pubkey());
+    assert_escrow_error(result, ErrorCode::VaultUnderfunded);
+
+    let mut instructions = fund_vault_instructions(3 * TOKEN_A);
+    instructions.push(build_make_offer_with_existing_vault_instruction(
+        &test_environment,
+        offer_id,
+        &alice.pubkey(),
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        [6, 0, 0, 0, 0, 0, 0, 0],
+    ));
+    send_transaction_from_instructions(&mut test_environment.litesvm, instructions, &[&alice], &alice.pubkey()).unwrap();
+
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.rent_payer, alice.pubkey());
+    assert_eq!(offer.tags, [6, 0, 0, 0, 0, 0, 0, 0]);
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_a, 7 * TOKEN_A, "Alice should have funded the vault with 3 token A");
+
+    // The offer is taken like any other
+    let bob = test_environment.bob.insecure_clone();
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    )
+    .unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should receive the token A from the vault");
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_b, 2 * TOKEN_B, "Alice should receive the token B Alice wanted");
+    check_account_is_closed(&test_environment.litesvm, &vault, "The vault should be closed");
+}
+
+#[test]
+fn test_replace_offer_refunds_the_old_offer_and_makes_the_new_one_at_once() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Alice reprices, offering 4 token A for 3 token B instead
+    let new_offer_id = generate_offer_id();
+    let (new_offer_account, new_vault) = execute_replace_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        offer_account,
+        new_offer_id,
+        4 * TOKEN_A,
+        3 * TOKEN_B,
+    )
+    .unwrap();
+
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "The old offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &vault, "The old vault should be closed");
+    assert_token_balance(&test_environment.litesvm, &new_vault, 4 * TOKEN_A, "The new vault should hold the new offer's token A");
+    assert_token_balance(&test_environment.litesvm, &alice_token_account_a, 6 * TOKEN_A, "Only the extra token A should have left Alice's account");
+    let new_offer = get_offer(&test_environment, &new_offer_account);
+    assert_eq!(new_offer.token_a_offered_amount, 4 * TOKEN_A);
+    assert_eq!(new_offer.token_b_wanted_amount, 3 * TOKEN_B);
+    assert_eq!(get_maker_index(&test_environment, &alice.pubkey()).offer_ids, vec![new_offer_id]);
+
+    // Delegated offers have no vault to move into the new offer
+    let delegated_offer = execute_make_offer_delegated(&mut test_environment, generate_offer_id(), &alice, TOKEN_A, TOKEN_B, [0; 8]).unwrap();
+    let result = execute_replace_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        delegated_offer,
+        generate_offer_id(),
+        TOKEN_A,
+        TOKEN_B,
+    );
+    assert_escrow_error(result, ErrorCode::ReplaceNotSupported);
+}
+
+#[test]
+fn test_take_offer_fits_the_compute_unit_limit_clients_set() {
+    let mut test_environment = setup_escrow_test();
+
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    ).unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        TakeOfferAccounts {
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            taker: bob.pubkey(),
+            maker: alice.pubkey(),
+            rent_payer: None,
+            token_mint_a: test_environment.token_mint_a.pubkey(),
+            token_mint_b: test_environment.token_mint_b.pubkey(),
+            taker_token_account_a: test_environment.bob_token_account_a,
+            taker_token_account_b: test_environment.bob_token_account_b,
+            maker_token_account_b: test_environment.alice_token_account_b,
+            offer_account,
+            vault,
+            allowlist: None,
+            referrer_token_account: None,
+            token_program_b: None,
+        },
+    );
+
+    // A limit far below what take_offer needs fails the whole transaction, leaving the offer open
+    let failed = send_versioned_transaction_with_compute_budget(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction.clone()],
+        &[&bob],
+        &bob.pubkey(),
+        10_000,
+        1_000,
+    )
+    .expect_err("take_offer should have run out of compute units");
+    assert!(
+        format!("{:?}", failed.err).contains("ComputationalBudgetExceeded"),
+        "Expected ComputationalBudgetExceeded, got {:?}",
+        failed.err
+    );
+    assert_eq!(get_token_balance(&test_environment, &vault), 3 * TOKEN_A);
+
+    // With the limit clients reserve for take_offer, it goes through, ComputeBudget instructions included
+    let metadata = send_versioned_transaction_with_compute_budget(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+        TAKE_OFFER_COMPUTE_UNIT_BUDGET as u32,
+        1_000,
+    )
+    .unwrap_or_else(|failed| panic!("take_offer went over its compute unit limit: {:?}", failed.err));
+    assert!(metadata.compute_units_consumed <= TAKE_OFFER_COMPUTE_UNIT_BUDGET);
+    assert_eq!(get_token_balance(&test_environment, &vault), 0);
+}
+
+#[test]
+fn test_take_offer_route_settles_a_chain_of_offers_for_a_taker_paying_with_a_third_token() {
+    // A 1% protocol fee on the token A the taker receives
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    let (token_mint_a, token_mint_b) = (test_environment.token_mint_a.pubkey(), test_environment.token_mint_b.pubkey());
+    let token_mint_c = create_token_mint(&mut test_environment.litesvm, &test_environment._mint_authority, 9)
+        .unwrap()
+        .pubkey();
+
+    // Alice offers 3 token A for 2 token B, Bob offers 3 token B for 5 token C
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (alice_offer, _alice_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+    let bob = test_environment.bob.insecure_clone();
+    let bob_offer_id = generate_offer_id();
+    let bob_offer = get_offer_address(&bob.pubkey(), bob_offer_id);
+    let bob_vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&bob_offer),
+        &token_mint_b,
+    );
+    let make_offer_instruction = build_make_offer_instruction(
+        bob_offer_id,
+        3 * TOKEN_B,
+        5 * TOKEN_B,
+        build_make_offer_accounts(
+            bob.pubkey(),
+            token_mint_b,
+            token_mint_c,
+            test_environment.bob_token_account_b,
+            bob_offer,
+            bob_vault,
+        ),
+    );
+    send_transaction_from_instructions(&mut test_environment.litesvm, vec![make_offer_instruction], &[&bob], &bob.pubkey())
+        .unwrap();
+
+    // Carol only holds token C
+    let carol = solana_keypair::Keypair::new();
+    test_environment.litesvm.airdrop(&carol.pubkey(), 1_000_000_000).unwrap();
+    let carol_token_account_c = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &carol,
+        &token_mint_c,
+        &test_environment._mint_authority,
+    )
+    .unwrap();
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &token_mint_c,
+        &carol_token_account_c,
+        10 * TOKEN_B,
+        &test_environment._mint_authority,
+    )
+    .unwrap();
+    let route_instruction = |first_offer: solana_pubkey::Pubkey, maximum_token_c_amount: u64| {
+        build_take_offer_route_instruction(
+            TakeOfferRouteAccounts {
+                taker: carol.pubkey(),
+                first_maker: alice.pubkey(),
+                first_offer,
+                second_maker: bob.pubkey(),
+                second_offer: bob_offer,
+                token_mint_a,
+                token_mint_b,
+                token_mint_c,
+            },
+            3 * TOKEN_A,
+            maximum_token_c_amount,
+        )
+    };
+
+    // Bob's offer wants 5 token C, more than Carol will pay
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![route_instruction(alice_offer, 4 * TOKEN_B)],
+        &[&carol],
+        &carol.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::OfferTermsChanged);
+
+    // Bob's offer only holds 3 token B, so it can't pay for an offer wanting 4
+    let (greedy_offer, _greedy_vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        4 * TOKEN_B,
+    )
+    .unwrap();
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![route_instruction(greedy_offer, 5 * TOKEN_B)],
+        &[&carol],
+        &carol.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::RouteLegsDontMatch);
+
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![route_instruction(alice_offer, 5 * TOKEN_B)],
+        &[&carol],
+        &carol.pubkey(),
+    )
+    .unwrap();
+
+    // Each maker gets what they wanted, Carol gets Alice's token A less the fee,
+    // and the 1 token B Bob offered beyond what Alice wanted, less the fee
+    let associated_token_address = spl_associated_token_account::get_associated_token_address;
+    assert_token_balance(&test_environment.litesvm, &test_environment.alice_token_account_b, 2 * TOKEN_B, "Alice should receive the token B Alice wanted");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &associated_token_address(&bob.pubkey(), &token_mint_c),
+        5 * TOKEN_B,
+        "Bob should receive the token C Bob wanted",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &associated_token_address(&carol.pubkey(), &token_mint_a),
+        3 * TOKEN_A - 3 * TOKEN_A / 100,
+        "Carol should receive Alice's token A less the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &associated_token_address(&carol.pubkey(), &token_mint_b),
+        TOKEN_B - TOKEN_B / 100,
+        "Carol should receive the token B left over between the two offers less the protocol fee",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &get_fee_vault_address(&token_mint_b),
+        TOKEN_B / 100,
+        "The protocol should collect the fee on the token B left over",
+    );
+    assert_token_balance(&test_environment.litesvm, &carol_token_account_c, 5 * TOKEN_B, "Carol should pay the token C Bob wanted");
+    check_account_is_closed(&test_environment.litesvm, &alice_offer, "Alice's offer should be closed");
+    check_account_is_closed(&test_environment.litesvm, &bob_offer, "Bob's offer should be closed");
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &get_route_token_account_address(&carol.pubkey(), &token_mint_b),
+        "The route's transient account should be closed",
+    );
+}
+
+#[test]
+fn test_stake_offer_hands_the_stake_account_to_the_taker() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let stake_account = create_stake_account(&mut test_environment, &alice, 100_000_000);
+    let stake_offer = get_stake_offer_address(&stake_account);
+
+    // Only the stake account's staker and withdrawer can offer it
+    let result = execute_make_stake_offer(&mut test_environment, &bob, stake_account, 2 * TOKEN_B, None);
+    assert_escrow_error(result, ErrorCode::InvalidStakeAuthority);
+
+    execute_make_stake_offer(&mut test_environment, &alice, stake_account, 2 * TOKEN_B, None).unwrap();
+    assert_eq!(
+        get_stake_authorities(&test_environment, &stake_account),
+        (stake_offer, stake_offer),
+        "The stake offer should hold the stake account's authorities while it's open"
+    );
+
+    execute_take_stake_offer(&mut test_environment, &alice.pubkey(), stake_account).unwrap();
+    assert_eq!(
+        get_stake_authorities(&test_environment, &stake_account),
+        (bob.pubkey(), bob.pubkey()),
+        "Bob should become the stake account's staker and withdrawer"
+    );
+    assert_eq!(test_environment.litesvm.get_balance(&stake_account).unwrap(), 100_000_000, "The stake account's lamports should stay put");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &test_environment.alice_token_account_b,
+        2 * TOKEN_B - 2 * TOKEN_B / 100,
+        "Alice should receive the wanted token B less the protocol fee",
+    );
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_b, 3 * TOKEN_B, "Bob should pay the wanted token B");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &get_fee_vault_address(&test_environment.token_mint_b.pubkey()),
+        2 * TOKEN_B / 100,
+        "The protocol should collect the fee on the wanted token B",
+    );
+    check_account_is_closed(&test_environment.litesvm, &stake_offer, "The stake offer should be closed");
+}
+
+#[test]
+fn test_cancel_stake_offer_hands_the_stake_account_back_to_the_maker() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let stake_account = create_stake_account(&mut test_environment, &alice, 100_000_000);
+    let stake_offer = get_stake_offer_address(&stake_account);
+    execute_make_stake_offer(&mut test_environment, &alice, stake_account, 2 * TOKEN_B, None).unwrap();
+
+    let result = execute_cancel_stake_offer(&mut test_environment, &bob, stake_account);
+    assert_escrow_error(result, ErrorCode::InvalidMaker);
+
+    execute_cancel_stake_offer(&mut test_environment, &alice, stake_account).unwrap();
+    assert_eq!(
+        get_stake_authorities(&test_environment, &stake_account),
+        (alice.pubkey(), alice.pubkey()),
+        "Alice should get the stake account's authorities back"
+    );
+    check_account_is_closed(&test_environment.litesvm, &stake_offer, "The stake offer should be closed");
+    let result = execute_take_stake_offer(&mut test_environment, &alice.pubkey(), stake_account);
+    assert!(result.is_err(), "A cancelled stake offer can't be taken");
+}
+
+#[test]
+fn test_take_offer_on_behalf_of_a_recipient() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) =
+        execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, 3 * TOKEN_A, 2 * TOKEN_B)
+            .unwrap();
+
+    // Bob pays, Carol receives
+    let bob = test_environment.bob.insecure_clone();
+    let (carol, carol_token_account_a, carol_token_account_b) = create_user(&mut test_environment, 0, 0);
+    let take_offer_accounts = || TakeOfferAccounts {
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        taker: bob.pubkey(),
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a: test_environment.token_mint_a.pubkey(),
+        token_mint_b: test_environment.token_mint_b.pubkey(),
+        taker_token_account_a: test_environment.bob_token_account_a,
+        taker_token_account_b: test_environment.bob_token_account_b,
+        maker_token_account_b: test_environment.alice_token_account_b,
+        offer_account,
+        vault,
+        allowlist: None,
+        referrer_token_account: None,
+        token_program_b: None,
+    };
+
+    // The recipient's token account has to hold token a
+    let take_offer_instruction = build_take_offer_instruction_with_recipient(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        carol.pubkey(),
+        carol_token_account_b,
+        take_offer_accounts(),
+    );
+    let result = send_transaction_from_instructions(&mut test_environment.litesvm, vec![take_offer_instruction], &[&bob], &bob.pubkey());
+    assert_escrow_error(result, ErrorCode::WrongTokenMint);
+
+    // Bob's own token a account isn't used, so passing it too is refused rather than charging him rent for it
+    let mut take_offer_instruction = build_take_offer_instruction_with_recipient(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        carol.pubkey(),
+        carol_token_account_a,
+        take_offer_accounts(),
+    );
+    take_offer_instruction.accounts[8] = solana_instruction::AccountMeta::new(test_environment.bob_token_account_a, false);
+    let result = send_transaction_from_instructions(&mut test_environment.litesvm, vec![take_offer_instruction], &[&bob], &bob.pubkey());
+    assert_escrow_error(result, ErrorCode::RecipientTokenAccountRequired);
+
+    let take_offer_instruction = build_take_offer_instruction_with_recipient(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        carol.pubkey(),
+        carol_token_account_a,
+        take_offer_accounts(),
+    );
+    send_transaction_from_instructions(&mut test_environment.litesvm, vec![take_offer_instruction], &[&bob], &bob.pubkey()).unwrap();
+
+    assert_token_balance(&test_environment.litesvm, &carol_token_account_a, 3 * TOKEN_A, "Carol should receive the offered token A");
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_a, 0, "Bob shouldn't receive any token A");
+    assert_token_balance(&test_environment.litesvm, &test_environment.bob_token_account_b, 3 * TOKEN_B, "Bob should pay the wanted token B");
+    assert_token_balance(&test_environment.litesvm, &test_environment.alice_token_account_b, 2 * TOKEN_B, "Alice should receive the wanted token B");
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "The offer should be closed");
+}
+
+#[test]
+fn test_offers_cant_be_taken_until_they_reach_the_min_offer_age() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+
+    let result = execute_set_min_offer_age(&mut test_environment, 10, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+    execute_set_min_offer_age(&mut test_environment, 10, &authority).unwrap();
+
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let (offer_account, vault) =
+        execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, 3 * TOKEN_A, 2 * TOKEN_B)
+            .unwrap();
+    let (bob_token_account_a, bob_token_account_b, alice_token_account_b) = (
+        test_environment.bob_token_account_a,
+        test_environment.bob_token_account_b,
+        test_environment.alice_token_account_b,
+    );
+
+    // Bots watching for new offers can't fill it in the slots right after it's made
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert_escrow_error(result, ErrorCode::OfferTooNew);
+
+    // 4 seconds is 10 slots
+    test_environment.warp_forward(4);
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    )
+    .unwrap();
+    assert_token_balance(&test_environment.litesvm, &bob_token_account_a, 3 * TOKEN_A, "Bob should receive the offered token A");
+}
+
+#[test]
+fn test_makers_cant_open_more_offers_than_the_config_allows() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let result = execute_set_max_open_offers(&mut test_environment, 2, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+    for max_open_offers_per_maker in [0, 33] {
+        let result = execute_set_max_open_offers(&mut test_environment, max_open_offers_per_maker, &authority);
+        assert_escrow_error(result, ErrorCode::InvalidMaxOpenOffers);
+    }
+    execute_set_max_open_offers(&mut test_environment, 2, &authority).unwrap();
+
+    let (first_offer_account, first_vault) =
+        execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, TOKEN_A, TOKEN_B)
+            .unwrap();
+    execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, TOKEN_A, TOKEN_B)
+        .unwrap();
+    let result =
+        execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, TOKEN_A, TOKEN_B);
+    assert_escrow_error(result, ErrorCode::TooManyOpenOffers);
+
+    // Closing an offer frees up room for another
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, first_offer_account, first_vault).unwrap();
+    execute_make_offer(&mut test_environment, generate_offer_id(), &alice, alice_token_account_a, TOKEN_A, TOKEN_B)
+        .unwrap();
+    assert_eq!(get_maker_index(&test_environment, &alice.pubkey()).offer_ids.len(), 2);
+}
+
+#[test]
+fn test_offer_tags_are_saved_at_their_fixed_offset() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let tags = [3, 7, 0, 0, 0, 0, 0, 0];
+
+    let (offer_account, _vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            tags,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).tags, tags);
+
+    // Frontends find offers in a category by comparing the bytes at the tags offset
+    let data = test_environment.litesvm.get_account(&offer_account).unwrap().data;
+    assert_eq!(&data[offsets::TAGS..offsets::TAGS + tags.len()], &tags);
+
+    // Offers made without tags are left uncategorised
+    let (untagged_offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    )
+    .unwrap();
+    assert_eq!(get_offer(&test_environment, &untagged_offer_account).tags, [0; 8]);
+}
+
+#[test]
+fn test_vault_is_owned_by_the_vault_authority_not_the_offer() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // The offer account only holds data, a separate PDA owns and signs for the vault
+    let vault_authority = get_vault_authority_address(&offer_account);
+    assert_ne!(vault_authority, offer_account);
+    assert_eq!(get_token_account(&test_environment, &vault).owner, vault_authority);
+    assert_token_balance(&test_environment.litesvm, &vault, 3 * TOKEN_A, "Vault should hold the offered token A");
+
+    // The vault authority still signs the refund
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed after the refund");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should have all her token A back",
+    );
+}
+
+#[test]
+fn test_close_orphaned_vault_returns_a_stranded_vaults_tokens_to_the_maker() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let config_authority = test_environment.config_authority.insecure_clone();
+    let fee_authority = test_environment.fee_authority.pubkey();
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+
+    let offer_id = generate_offer_id();
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        offer_id,
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Live offers' vaults can't be closed
+    let result = execute_close_orphaned_vault(
+        &mut test_environment,
+        &config_authority,
+        alice.pubkey(),
+        offer_id,
+        token_mint_a,
+    );
+    assert_escrow_error(result, ErrorCode::OfferStillExists);
+
+    remove_offer_account(&mut test_environment, &offer_account);
+
+    // Only the config authority can close stranded vaults
+    let result = execute_close_orphaned_vault(&mut test_environment, &alice, alice.pubkey(), offer_id, token_mint_a);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+
+    let vault_rent = test_environment.litesvm.get_balance(&vault).unwrap();
+    let fee_authority_lamports_before = test_environment.litesvm.get_balance(&fee_authority).unwrap();
+    execute_close_orphaned_vault(
+        &mut test_environment,
+        &config_authority,
+        alice.pubkey(),
+        offer_id,
+        token_mint_a,
+    )
+    .unwrap();
+
+    check_account_is_closed(&test_environment.litesvm, &vault, "Stranded vault should be closed");
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "The stranded tokens should be back with the maker",
+    );
+    assert_eq!(
+        test_environment.litesvm.get_balance(&fee_authority).unwrap(),
+        fee_authority_lamports_before + vault_rent,
+        "The treasury should get the vault's rent"
+    );
+}
+
+#[test]
+fn test_forged_bumps_are_rejected() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // make_offer saves the canonical bumps, which take_offer and refund_offer reuse rather than deriving them again
+    let offer = get_offer(&test_environment, &offer_account);
+    let (expected_offer_account, offer_bump) =
+        solana_pubkey::Pubkey::find_program_address(&[b"offer", alice.pubkey().as_ref(), &offer.id.to_le_bytes()], &get_program_id());
+    let (expected_vault_authority, vault_authority_bump) =
+        solana_pubkey::Pubkey::find_program_address(&[b"vault_authority", offer_account.as_ref()], &get_program_id());
+    assert_eq!(offer_account, expected_offer_account);
+    assert_eq!(offer.bump, offer_bump);
+    assert_eq!(get_vault_authority_address(&offer_account), expected_vault_authority);
+    assert_eq!(offer.vault_authority_bump, vault_authority_bump);
+
+    // Any other bump gives a different address, or none at all, so the seeds constraints reject it
+    for (offset, canonical_bump) in [
+        (std::mem::offset_of!(Offer, bump), offer_bump),
+        (std::mem::offset_of!(Offer, vault_authority_bump), vault_authority_bump),
+    ] {
+        forge_offer_byte(&mut test_environment, &offer_account, offset, canonical_bump.wrapping_sub(1));
+
+        let result = execute_take_offer(
+            &mut test_environment,
+            &bob,
+            &alice,
+            bob_token_account_a,
+            bob_token_account_b,
+            alice_token_account_b,
+            offer_account,
+            vault,
+        );
+        assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintSeeds);
+
+        let result = execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault);
+        assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintSeeds);
+
+        forge_offer_byte(&mut test_environment, &offer_account, offset, canonical_bump);
+    }
+
+    // With the real bumps back the offer works as normal
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, offer_account, vault).unwrap();
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed after the refund");
+}
+
+#[test]
+fn test_make_offer_records_when_the_offer_was_made() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    test_environment.warp_to_timestamp(1_700_000_000);
+    let (offer_account, _vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    assert_eq!(get_offer(&test_environment, &offer_account).created_at, 1_700_000_000);
+}
+
+#[test]
+fn test_offer_keeping_history_is_marked_filled_until_its_rent_is_claimed() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+
+    let (offer_account, vault) = execute_make_offer_with_options(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        MakeOfferOptions {
+            keep_history: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(get_offer(&test_environment, &offer_account).status(), OfferStatus::Open);
+
+    // Claiming the rent would close an offer that can still be taken
+    let result = execute_claim_rent(&mut test_environment, &alice, offer_account);
+    assert_escrow_error(result, ErrorCode::OfferStillOpen);
+
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    )
+    .unwrap();
+
+    // The tokens have moved and the vault is closed, but the offer stays as a record of the fill
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed after the take");
+    let offer = get_offer(&test_environment, &offer_account);
+    assert_eq!(offer.status(), OfferStatus::Filled);
+    assert_eq!(offer.token_a_offered_amount, 3 * TOKEN_A);
+    assert_eq!(offer.token_b_wanted_amount, 2 * TOKEN_B);
+
+    // The record can't be changed
+    let result = execute_update_offer(&mut test_environment, &alice, offer_account, 4 * TOKEN_B, None);
+    assert_escrow_error(result, ErrorCode::OfferNotOpen);
+
+    // Only the maker decides when the record goes
+    let result = execute_claim_rent(&mut test_environment, &bob, offer_account);
+    assert_escrow_error(result, ErrorCode::InvalidMakerAuthority);
+
+    let offer_rent = test_environment.litesvm.get_balance(&offer_account).unwrap();
+    let alice_lamports_before = test_environment.litesvm.get_balance(&alice.pubkey()).unwrap();
+    execute_claim_rent(&mut test_environment, &alice, offer_account).unwrap();
+
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed once its rent is claimed");
+    assert_eq!(
+        test_environment.litesvm.get_balance(&alice.pubkey()).unwrap(),
+        alice_lamports_before + offer_rent - LAMPORTS_PER_SIGNATURE,
+        "The offer's rent payer should get its rent back"
+    );
+}
+
+#[test]
+fn test_offer_keeping_history_is_marked_refunded_or_expired() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    test_environment.warp_to_timestamp(1_000);
+
+    let [(refunded_offer_account, refunded_vault), (expired_offer_account, expired_vault)] = [(); 2].map(|()| {
+        execute_make_offer_with_options(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            MakeOfferOptions {
+                keep_history: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    });
+    execute_update_offer(&mut test_environment, &alice, expired_offer_account, 2 * TOKEN_B, Some(2_000)).unwrap();
+
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, refunded_offer_account, refunded_vault)
+        .unwrap();
+    assert_eq!(get_offer(&test_environment, &refunded_offer_account).status(), OfferStatus::Refunded);
+
+    test_environment.warp_to_timestamp(3_000);
+    execute_refund_offer(&mut test_environment, &alice, alice_token_account_a, expired_offer_account, expired_vault)
+        .unwrap();
+    assert_eq!(get_offer(&test_environment, &expired_offer_account).status(), OfferStatus::Expired);
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &alice_token_account_a,
+        10 * TOKEN_A,
+        "Alice should have all her tokens back",
+    );
+
+    for offer_account in [refunded_offer_account, expired_offer_account] {
+        execute_claim_rent(&mut test_environment, &alice, offer_account).unwrap();
+        check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer should be closed once its rent is claimed");
+    }
+}
+
+#[test]
+fn test_offers_outside_the_config_limits_are_refused() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let result = execute_set_limits(&mut test_environment, TOKEN_A, 5 * TOKEN_A, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+    let result = execute_set_limits(&mut test_environment, 5 * TOKEN_A, TOKEN_A, &authority);
+    assert_escrow_error(result, ErrorCode::InvalidOfferLimits);
+
+    execute_set_limits(&mut test_environment, TOKEN_A, 5 * TOKEN_A, &authority).unwrap();
+
+    for (token_a_offered_amount, error_code) in [
+        (TOKEN_A - 1, ErrorCode::OfferTooSmall),
+        (5 * TOKEN_A + 1, ErrorCode::OfferTooLarge),
+    ] {
+        let result = execute_make_offer(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            token_a_offered_amount,
+            TOKEN_B,
+        );
+        assert_escrow_error(result, error_code);
+    }
+
+    // The limits themselves are allowed
+    for token_a_offered_amount in [TOKEN_A, 5 * TOKEN_A] {
+        execute_make_offer(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            token_a_offered_amount,
+            TOKEN_B,
+        )
+        .unwrap();
+    }
+}
+
+/// Alice makes an offer passing whichever mint allowlist entries are given
+fn make_offer_with_mint_allowlist_entries(
+    test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment,
+    token_mint_a_allowlist_entry: Option<solana_pubkey::Pubkey>,
+    token_mint_b_allowlist_entry: Option<solana_pubkey::Pubkey>,
+) -> Result<(), solana_kite::SolanaKiteError> {
+    let alice = test_environment.alice.insecure_clone();
+    let offer_id = generate_offer_id();
+    let offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &get_vault_authority_address(&offer_account),
+        &test_environment.token_mint_a.pubkey(),
+    );
+
+    let mut make_offer_accounts = build_make_offer_accounts(
+        alice.pubkey(),
+        test_environment.token_mint_a.pubkey(),
+        test_environment.token_mint_b.pubkey(),
+        test_environment.alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    make_offer_accounts.token_mint_a_allowlist_entry = token_mint_a_allowlist_entry;
+    make_offer_accounts.token_mint_b_allowlist_entry = token_mint_b_allowlist_entry;
+
+    let make_offer_instruction = build_make_offer_instruction(offer_id, TOKEN_A, TOKEN_B, make_offer_accounts);
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    )
+}
+
+#[test]
+fn test_mint_allowlist_limits_which_mints_offers_can_use() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+    let token_mint_a_allowlist_entry = get_mint_allowlist_entry_address(&token_mint_a);
+    let token_mint_b_allowlist_entry = get_mint_allowlist_entry_address(&token_mint_b);
+
+    // Only the config authority manages the allowlist
+    let result = execute_allow_mint(&mut test_environment, &token_mint_a, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAllowlistManager);
+    let result = execute_set_mint_allowlist_enabled(&mut test_environment, true, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAllowlistManager);
+
+    // Entries are ignored until the allowlist is enabled
+    make_offer_with_mint_allowlist_entries(&mut test_environment, None, None).unwrap();
+
+    execute_set_mint_allowlist_enabled(&mut test_environment, true, &authority).unwrap();
+    let result = make_offer_with_mint_allowlist_entries(&mut test_environment, None, None);
+    assert_escrow_error(result, ErrorCode::MintNotAllowlisted);
+
+    // Both mints need an entry
+    execute_allow_mint(&mut test_environment, &token_mint_a, &authority).unwrap();
+    let result = make_offer_with_mint_allowlist_entries(&mut test_environment, Some(token_mint_a_allowlist_entry), None);
+    assert_escrow_error(result, ErrorCode::MintNotAllowlisted);
+
+    // An entry for the other mint doesn't count
+    let result = make_offer_with_mint_allowlist_entries(
+        &mut test_environment,
+        Some(token_mint_a_allowlist_entry),
+        Some(token_mint_a_allowlist_entry),
+    );
+    assert_escrow_error(result, ErrorCode::MintNotAllowlisted);
+
+    execute_allow_mint(&mut test_environment, &token_mint_b, &authority).unwrap();
+    make_offer_with_mint_allowlist_entries(
+        &mut test_environment,
+        Some(token_mint_a_allowlist_entry),
+        Some(token_mint_b_allowlist_entry),
+    )
+    .unwrap();
+
+    // Revoking closes the entry, so it can't be passed any more
+    execute_revoke_mint(&mut test_environment, &token_mint_b, &authority).unwrap();
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &token_mint_b_allowlist_entry,
+        "Mint allowlist entry should be closed after being revoked"
+    );
+    let result = make_offer_with_mint_allowlist_entries(&mut test_environment, Some(token_mint_a_allowlist_entry), None);
+    assert_escrow_error(result, ErrorCode::MintNotAllowlisted);
+
+    execute_set_mint_allowlist_enabled(&mut test_environment, false, &authority).unwrap();
+    make_offer_with_mint_allowlist_entries(&mut test_environment, None, None).unwrap();
+}
+
+#[test]
+fn test_denied_mints_cannot_be_offered_or_taken() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+
+    // An offer made before the mint is denied
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    )
+    .unwrap();
+
+    let result = execute_deny_mint(&mut test_environment, &token_mint_b, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAllowlistManager);
+    execute_deny_mint(&mut test_environment, &token_mint_b, &authority).unwrap();
+
+    let result = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        TOKEN_A,
+        TOKEN_B,
+    );
+    assert_escrow_error(result, ErrorCode::MintDenied);
+
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert_escrow_error(result, ErrorCode::MintDenied);
+
+    // Leaving the deny entry out doesn't skip the check
+    let offer_id = generate_offer_id();
+    let new_offer_account = get_offer_address(&alice.pubkey(), offer_id);
+    let mut make_offer_instruction = build_make_offer_instruction(
+        offer_id,
+        TOKEN_A,
+        TOKEN_B,
+        build_make_offer_accounts(
+            alice.pubkey(),
+            test_environment.token_mint_a.pubkey(),
+            token_mint_b,
+            alice_token_account_a,
+            new_offer_account,
+            spl_associated_token_account::get_associated_token_address(
+                &get_vault_authority_address(&new_offer_account),
+                &test_environment.token_mint_a.pubkey(),
+            ),
+        ),
+    );
+    let mint_deny_entry = get_mint_deny_entry_address(&token_mint_b);
+    for account_meta in make_offer_instruction.accounts.iter_mut() {
+        if account_meta.pubkey == mint_deny_entry {
+            account_meta.pubkey = get_program_id();
+        }
+    }
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![make_offer_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    );
+    assert_escrow_error(result, ErrorCode::MintDenyEntryRequired);
+
+    // Once the mint is undenied, the earlier offer can be taken again
+    execute_undeny_mint(&mut test_environment, &token_mint_b, &authority).unwrap();
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &mint_deny_entry,
+        "Mint deny entry should be closed after the mint is undenied"
+    );
+    execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_fee_tiers_lower_the_fee_as_the_makers_volume_grows() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(30);
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let fee_vault = get_fee_vault_address(&test_environment.token_mint_a.pubkey());
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+
+    let fee_tiers = vec![
+        FeeTier { min_volume: TOKEN_B as u128, fee_bps: 20 },
+        FeeTier { min_volume: 2 * TOKEN_B as u128, fee_bps: 10 },
+    ];
+    let result = execute_set_fee_tiers(&mut test_environment, fee_tiers.clone(), token_mint_b, &alice);
+    assert_escrow_error(result, ErrorCode::InvalidFeeManager);
+    let unsorted_fee_tiers = fee_tiers.iter().rev().copied().collect();
+    let result = execute_set_fee_tiers(&mut test_environment, unsorted_fee_tiers, token_mint_b, &authority);
+    assert_escrow_error(result, ErrorCode::InvalidFeeTiers);
+    execute_set_fee_tiers(&mut test_environment, fee_tiers, token_mint_b, &authority).unwrap();
+
+    // Each take adds 1 token B to Alice's volume, reaching the next tier
+    let mut expected_fees = 0;
+    for fee_bps in [30, 20, 10] {
+        let (offer_account, vault) = execute_make_offer(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            3 * TOKEN_A,
+            TOKEN_B,
+        )
+        .unwrap();
+        execute_take_offer(
+            &mut test_environment,
+            &bob,
+            &alice,
+            bob_token_account_a,
+            bob_token_account_b,
+            alice_token_account_b,
+            offer_account,
+            vault,
+        )
+        .unwrap();
+
+        expected_fees += 3 * TOKEN_A * fee_bps / 10_000;
+        assert_eq!(get_token_balance(&test_environment, &fee_vault), expected_fees);
+    }
+}
+
+#[test]
+fn test_fee_tiers_only_count_volume_in_the_fee_tier_mint() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(30);
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+    let fee_vault = get_fee_vault_address(&test_environment.token_mint_a.pubkey());
+
+    // The tiers are measured in a mint Alice is never paid in, so her token B volume doesn't lower her fee
+    let other_mint = create_token_mint(&mut test_environment.litesvm, &test_environment._mint_authority, 9)
+        .unwrap()
+        .pubkey();
+    let fee_tiers = vec![FeeTier { min_volume: 1, fee_bps: 10 }];
+    execute_set_fee_tiers(&mut test_environment, fee_tiers, other_mint, &authority).unwrap();
+
+    let mut expected_fees = 0;
+    for _ in 0..2 {
+        let (offer_account, vault) = execute_make_offer(
+            &mut test_environment,
+            generate_offer_id(),
+            &alice,
+            alice_token_account_a,
+            3 * TOKEN_A,
+            TOKEN_B,
+        )
+        .unwrap();
+        execute_take_offer(
+            &mut test_environment,
+            &bob,
+            &alice,
+            bob_token_account_a,
+            bob_token_account_b,
+            alice_token_account_b,
+            offer_account,
+            vault,
+        )
+        .unwrap();
+
+        expected_fees += 3 * TOKEN_A * 30 / 10_000;
+        assert_eq!(get_token_balance(&test_environment, &fee_vault), expected_fees);
+    }
+    let maker_stats = get_maker_stats(&test_environment, &alice.pubkey());
+    assert_eq!(maker_stats.token_b_received_volume, 2 * TOKEN_B as u128);
+    assert_eq!(maker_stats.fee_tier_volume, 0);
+}
+
+#[test]
+fn test_membership_token_holders_get_a_fee_discount() {
+    let mut test_environment = setup_escrow_test_with_fee_bps(100);
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+
+    let membership_mint = create_token_mint(&mut test_environment.litesvm, &test_environment._mint_authority, 0)
+        .unwrap()
+        .pubkey();
+    let bob_membership_account = create_associated_token_account(
+        &mut test_environment.litesvm,
+        &bob,
+        &membership_mint,
+        &test_environment._mint_authority,
+    ).unwrap();
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &membership_mint,
+        &bob_membership_account,
+        4,
+        &test_environment._mint_authority,
+    ).unwrap();
+
+    // Members holding at least 5 tokens pay half the fee
+    let membership_discount = MembershipDiscount {
+        mint: membership_mint,
+        min_balance: 5,
+        discount_bps: 5_000,
+    };
+    let result = execute_set_membership_discount(&mut test_environment, Some(membership_discount), &alice);
+    assert_escrow_error(result, ErrorCode::InvalidFeeManager);
+    let result = execute_set_membership_discount(
+        &mut test_environment,
+        Some(MembershipDiscount { discount_bps: 10_001, ..membership_discount }),
+        &authority,
+    );
+    assert_escrow_error(result, ErrorCode::InvalidMembershipDiscount);
+    execute_set_membership_discount(&mut test_environment, Some(membership_discount), &authority).unwrap();
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    let take_offer = |test_environment: &mut crate::escrow_test_helpers::EscrowTestEnvironment| {
+        let take_offer_instruction = build_take_offer_instruction_with_membership(
+            3 * TOKEN_A,
+            2 * TOKEN_B,
+            bob_membership_account,
+            build_take_offer_accounts_for_bob(test_environment, offer_account, vault),
+        );
+        send_transaction_from_instructions(
+            &mut test_environment.litesvm,
+            vec![take_offer_instruction],
+            &[&bob],
+            &bob.pubkey(),
+        )
+    };
+
+    // Bob doesn't hold enough to be a member yet
+    let result = take_offer(&mut test_environment);
+    assert_escrow_error(result, ErrorCode::NotAMember);
+
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &membership_mint,
+        &bob_membership_account,
+        1,
+        &test_environment._mint_authority,
+    ).unwrap();
+    take_offer(&mut test_environment).unwrap();
+
+    let protocol_fee = 3 * TOKEN_A * 50 / 10_000;
+    assert_eq!(
+        get_token_balance(&test_environment, &get_fee_vault_address(&test_environment.token_mint_a.pubkey())),
+        protocol_fee
+    );
+    assert_eq!(
+        get_token_balance(&test_environment, &test_environment.bob_token_account_a),
+        3 * TOKEN_A - protocol_fee
+    );
+}
+
+#[test]
+fn test_admin_roles_can_be_given_to_separate_keys() {
+    let mut test_environment = setup_escrow_test();
+    let authority = test_environment.config_authority.insecure_clone();
+    let alice = test_environment.alice.insecure_clone();
+    let (pauser, _, _) = create_user(&mut test_environment, 0, 0);
+    let (fee_manager, _, _) = create_user(&mut test_environment, 0, 0);
+    let (allowlist_manager, _, _) = create_user(&mut test_environment, 0, 0);
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+
+    // Only the authority gives out roles
+    let result = execute_set_role(&mut test_environment, Role::Pauser, alice.pubkey(), &alice);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+
+    for (role, holder) in [
+        (Role::Pauser, &pauser),
+        (Role::FeeManager, &fee_manager),
+        (Role::AllowlistManager, &allowlist_manager),
+    ] {
+        execute_set_role(&mut test_environment, role, holder.pubkey(), &authority).unwrap();
+    }
+
+    // The authority no longer holds the roles it gave out, and each holder only has their own
+    let result = execute_set_paused(&mut test_environment, true, &authority);
+    assert_escrow_error(result, ErrorCode::InvalidPauser);
+    let result = execute_set_paused(&mut test_environment, true, &fee_manager);
+    assert_escrow_error(result, ErrorCode::InvalidPauser);
+    execute_set_paused(&mut test_environment, true, &pauser).unwrap();
+    execute_set_paused(&mut test_environment, false, &pauser).unwrap();
+
+    let result = execute_set_fee_tiers(&mut test_environment, Vec::new(), token_mint_b, &authority);
+    assert_escrow_error(result, ErrorCode::InvalidFeeManager);
+    let result = execute_set_fee_tiers(&mut test_environment, Vec::new(), token_mint_b, &pauser);
+    assert_escrow_error(result, ErrorCode::InvalidFeeManager);
+    execute_set_fee_tiers(&mut test_environment, Vec::new(), token_mint_b, &fee_manager).unwrap();
+
+    let result = execute_set_mint_allowlist_enabled(&mut test_environment, false, &authority);
+    assert_escrow_error(result, ErrorCode::InvalidAllowlistManager);
+    let result = execute_set_mint_allowlist_enabled(&mut test_environment, false, &fee_manager);
+    assert_escrow_error(result, ErrorCode::InvalidAllowlistManager);
+    execute_set_mint_allowlist_enabled(&mut test_environment, false, &allowlist_manager).unwrap();
+
+    // The authority keeps the settings no role covers
+    let result = execute_set_limits(&mut test_environment, 0, u64::MAX, &pauser);
+    assert_escrow_error(result, ErrorCode::InvalidAuthority);
+    execute_set_limits(&mut test_environment, 0, u64::MAX, &authority).unwrap();
+}
+
+#[test]
+fn test_maker_token_accounts_must_still_belong_to_the_maker() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let alice_token_account_b = test_environment.alice_token_account_b;
+    let bob_token_account_a = test_environment.bob_token_account_a;
+    let bob_token_account_b = test_environment.bob_token_account_b;
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Alice's token b account keeps its address, but Bob owns it now, so paying into it would pay Bob
+    set_token_account_owner(&mut test_environment, &alice_token_account_b, &alice, &bob.pubkey()).unwrap();
+    let result = execute_take_offer(
+        &mut test_environment,
+        &bob,
+        &alice,
+        bob_token_account_a,
+        bob_token_account_b,
+        alice_token_account_b,
+        offer_account,
+        vault,
+    );
+    assert_escrow_error(result, ErrorCode::InvalidTokenAccountOwner);
+
+    // The same goes for the token a account refunds are sent to
+    set_token_account_owner(&mut test_environment, &alice_token_account_a, &alice, &bob.pubkey()).unwrap();
+    let result = execute_refund_offer(
+        &mut test_environment,
+        &alice,
+        alice_token_account_a,
+        offer_account,
+        vault,
+    );
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintTokenOwner);
+
+    assert_token_balance(
+        &test_environment.litesvm,
+        &vault,
+        3 * TOKEN_A,
+        "The vault should still hold the offered tokens",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &bob_token_account_b,
+        5 * TOKEN_B,
+        "Bob should still have all his token b",
+    );
+}
+
+#[test]
+fn test_look_alike_vault_cannot_stand_in_for_the_offers_vault() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // Same mint and same owner as the vault, but not the vault authority's associated token account
+    let look_alike_vault = create_token_account_at_new_address(
+        &mut test_environment,
+        &get_vault_authority_address(&offer_account),
+        &token_mint_a,
+        &bob,
+    );
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &token_mint_a,
+        &look_alike_vault,
+        3 * TOKEN_A,
+        &test_environment._mint_authority,
+    )
+    .unwrap();
+
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        build_take_offer_accounts_for_bob(&test_environment, offer_account, look_alike_vault),
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintAssociated);
+
+    let refund_instruction = build_refund_offer_instruction(RefundOfferAccounts {
+        token_program: spl_token::ID,
+        system_program: anchor_lang::system_program::ID,
+        maker_authority: None,
+        maker: alice.pubkey(),
+        rent_payer: None,
+        token_mint_a,
+        maker_token_account_a: alice_token_account_a,
+        offer_account,
+        vault: look_alike_vault,
+        allowlist: None,
+    });
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![refund_instruction],
+        &[&alice],
+        &alice.pubkey(),
+    );
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintAssociated);
+
+    // Neither account was touched, and the real vault can still be taken
+    assert_token_balance(
+        &test_environment.litesvm,
+        &look_alike_vault,
+        3 * TOKEN_A,
+        "The look-alike vault should still hold its tokens",
+    );
+    assert_token_balance(
+        &test_environment.litesvm,
+        &vault,
+        3 * TOKEN_A,
+        "The offer's vault should still hold the offered tokens",
+    );
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        build_take_offer_accounts_for_bob(&test_environment, offer_account, vault),
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+    check_account_is_closed(
+        &test_environment.litesvm,
+        &vault,
+        "The vault should be closed once the offer is taken"
+    );
+}
+
+#[test]
+fn test_take_offer_with_token_accounts_uses_the_takers_chosen_accounts() {
+    let mut test_environment = setup_escrow_test();
+    let alice = test_environment.alice.insecure_clone();
+    let bob = test_environment.bob.insecure_clone();
+    let alice_token_account_a = test_environment.alice_token_account_a;
+    let token_mint_a = test_environment.token_mint_a.pubkey();
+    let token_mint_b = test_environment.token_mint_b.pubkey();
+
+    // Bob keeps these tokens outside his associated token accounts
+    let bob_other_token_account_a =
+        create_token_account_at_new_address(&mut test_environment, &bob.pubkey(), &token_mint_a, &bob);
+    let bob_other_token_account_b =
+        create_token_account_at_new_address(&mut test_environment, &bob.pubkey(), &token_mint_b, &bob);
+    mint_tokens_to_account(
+        &mut test_environment.litesvm,
+        &token_mint_b,
+        &bob_other_token_account_b,
+        2 * TOKEN_B,
+        &test_environment._mint_authority,
+    )
+    .unwrap();
+
+    let (offer_account, vault) = execute_make_offer(
+        &mut test_environment,
+        generate_offer_id(),
+        &alice,
+        alice_token_account_a,
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+    )
+    .unwrap();
+
+    // take_offer only accepts Bob's associated token accounts
+    let take_offer_instruction = build_take_offer_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        TakeOfferAccounts {
+            taker_token_account_a: bob_other_token_account_a,
+            taker_token_account_b: bob_other_token_account_b,
+            ..build_take_offer_accounts_for_bob(&test_environment, offer_account, vault)
+        },
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert!(result.is_err(), "take_offer should only accept the taker's associated token accounts");
+
+    // Bob can't have the tokens sent to someone else's account
+    let take_offer_instruction = build_take_offer_with_token_accounts_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        TakeOfferAccounts {
+            taker_token_account_a: alice_token_account_a,
+            taker_token_account_b: bob_other_token_account_b,
+            ..build_take_offer_accounts_for_bob(&test_environment, offer_account, vault)
+        },
+    );
+    let result = send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    );
+    assert_anchor_error(result, anchor_lang::error::ErrorCode::ConstraintTokenOwner);
+
+    let take_offer_instruction = build_take_offer_with_token_accounts_instruction(
+        3 * TOKEN_A,
+        2 * TOKEN_B,
+        TakeOfferAccounts {
+            taker_token_account_a: bob_other_token_account_a,
+            taker_token_account_b: bob_other_token_account_b,
+            ..build_take_offer_accounts_for_bob(&test_environment, offer_account, vault)
+        },
+    );
+    send_transaction_from_instructions(
+        &mut test_environment.litesvm,
+        vec![take_offer_instruction],
+        &[&bob],
+        &bob.pubkey(),
+    )
+    .unwrap();
+
+    assert_eq!(get_token_balance(&test_environment, &bob_other_token_account_a), 3 * TOKEN_A);
+    assert_eq!(get_token_balance(&test_environment, &bob_other_token_account_b), 0);
+    // Bob's associated token accounts weren't touched
+    assert_eq!(get_token_balance(&test_environment, &test_environment.bob_token_account_a), 0);
+    assert_eq!(
+        get_token_balance(&test_environment, &test_environment.alice_token_account_b),
+        2 * TOKEN_B
+    );
+    check_account_is_closed(&test_environment.litesvm, &offer_account, "Offer account should be closed");
+    check_account_is_closed(&test_environment.litesvm, &vault, "Vault should be closed");
+}
@@ -41,12 +41,15 @@ export async function createTestOffer(params: {
     offerId = getRandomBigInt(),
   } = params;
 
-  const offerPDAAndBump = await connection.getPDAAndBump(programClient.ESCROW_PROGRAM_ADDRESS, ["offer", offerId]);
+  const offerPDAAndBump = await connection.getPDAAndBump(programClient.ESCROW_PROGRAM_ADDRESS, ["offer", maker.address, offerId]);
   const offer = offerPDAAndBump.pda;
-  const vault = await connection.getTokenAccountAddress(offer, tokenMintA, true);
+  const vaultAuthorityPDAAndBump = await connection.getPDAAndBump(programClient.ESCROW_PROGRAM_ADDRESS, ["vault_authority", offer]);
+  const vault = await connection.getTokenAccountAddress(vaultAuthorityPDAAndBump.pda, tokenMintA, true);
 
   const makeOfferInstruction = await programClient.getMakeOfferInstructionAsync({
-    maker,
+    makerAuthority: maker,
+    maker: maker.address,
+    payer: maker,
     tokenMintA,
     tokenMintB,
     makerTokenAccountA,
@@ -55,6 +58,16 @@ export async function createTestOffer(params: {
     id: offerId,
     tokenAOfferedAmount,
     tokenBWantedAmount,
+    dutchAuction: null,
+    englishAuction: null,
+    additionalTokenAOfferedAmounts: [],
+    memo: null,
+    minFillAmount: 0n,
+    hashlock: null,
+    vestingDuration: null,
+    arbiter: null,
+    keepHistory: false,
+    tags: [0, 0, 0, 0, 0, 0, 0, 0],
     tokenProgram: TOKEN_EXTENSIONS_PROGRAM,
   });
 
@@ -46,6 +46,18 @@ describe("Escrow", () => {
     // 'user' will be the account we use to create the token mints
     [user, alice, bob] = await connection.createWallets(3, { airdropAmount: ONE_SOL });
 
+    // Set up the protocol config. There's no protocol fee, so the balances below are exact
+    const initializeConfigInstruction = await programClient.getInitializeConfigInstructionAsync({
+      admin: user,
+      feeBps: 0,
+      feeAuthority: user.address,
+      referralShareBps: 0,
+    });
+    await connection.sendTransactionFromInstructions({
+      feePayer: user,
+      instructions: [initializeConfigInstruction],
+    });
+
     // Create two token mints - the factories that create token A, and token B
     tokenMintA = await connection.createTokenMint({
       mintAuthority: user,
@@ -293,6 +305,7 @@ describe("Escrow", () => {
       const takeOfferInstruction = await programClient.getTakeOfferInstructionAsync({
         taker: bob,
         maker: alice.address,
+        rentPayer: alice.address,
         tokenMintA,
         tokenMintB,
         takerTokenAccountA: bobTokenAccountA,
@@ -300,6 +313,10 @@ describe("Escrow", () => {
         offer: testOffer,
         vault: testVault,
         tokenProgram: TOKEN_EXTENSIONS_PROGRAM,
+        expectedTokenAAmount: tokenAOfferedAmount,
+        expectedTokenBAmount: tokenBWantedAmount,
+        preimage: null,
+        memo: null,
       });
 
       await connection.sendTransactionFromInstructions({
@@ -343,6 +360,7 @@ describe("Escrow", () => {
       const takeOfferInstruction = await programClient.getTakeOfferInstructionAsync({
         taker: bob,
         maker: alice.address,
+        rentPayer: alice.address,
         tokenMintA,
         tokenMintB,
         takerTokenAccountA: bobTokenAccountA,
@@ -350,6 +368,10 @@ describe("Escrow", () => {
         offer,
         vault,
         tokenProgram: TOKEN_EXTENSIONS_PROGRAM,
+        expectedTokenAAmount: tokenAOfferedAmount,
+        expectedTokenBAmount: largeTokenBAmount,
+        preimage: null,
+        memo: null,
       });
 
       try {
@@ -394,7 +416,9 @@ describe("Escrow", () => {
       });
 
       const refundOfferInstruction = await programClient.getRefundOfferInstructionAsync({
-        maker: alice,
+        makerAuthority: alice,
+        maker: alice.address,
+        rentPayer: alice.address,
         tokenMintA,
         makerTokenAccountA: aliceTokenAccountA,
         offer: testOffer,
@@ -438,7 +462,9 @@ describe("Escrow", () => {
       });
 
       const refundOfferInstruction = await programClient.getRefundOfferInstructionAsync({
-        maker: bob,
+        makerAuthority: bob,
+        maker: bob.address,
+        rentPayer: bob.address,
         tokenMintA,
         makerTokenAccountA: bobTokenAccountA,
         offer,